@@ -0,0 +1,242 @@
+//! Measures an HDR image with [AutoExposure] without a window, the way the eye adapts when
+//! stepping from a dim room into sunlight and back.
+//!
+//! The image is cleared to the scene's luminance every frame instead of being rendered, then the
+//! histogram and reduction passes run on it and the adapted luminance is read back once the
+//! frame's fence signals. The raw Vulkan objects are handed to a [DeviceScope], which destroys
+//! them once the device is idle. The shaders are compiled from `shaders/` at runtime, so this needs the
+//! `shader-compiler` feature:
+//!
+//! ```sh
+//! cargo run --example auto_exposure --features shader-compiler
+//! ```
+
+use std::error::Error;
+
+use ash::{
+    vk::{
+        self, make_api_version, AccessFlags, ClearColorValue, CommandBufferAllocateInfo,
+        CommandBufferBeginInfo, CommandBufferLevel, CommandBufferUsageFlags, DependencyFlags,
+        Extent2D, Extent3D, FenceCreateInfo, Format, ImageAspectFlags, ImageCreateInfo,
+        ImageLayout, ImageMemoryBarrier, ImageSubresourceRange, ImageTiling, ImageType,
+        ImageUsageFlags, ImageViewCreateInfo, ImageViewType, MemoryAllocateInfo,
+        MemoryPropertyFlags, PipelineStageFlags, SampleCountFlags, SharingMode, SubmitInfo,
+    },
+    Entry,
+};
+use learnvulkan::{
+    api2::host_allocation_callbacks,
+    renderer::{
+        auto_exposure::AutoExposure,
+        command_pool::CommandPool,
+        instance::Instance,
+        logical_device::LogicalDevice,
+        physical_device::PhysicalDevice,
+        scope::DeviceScope,
+        shader_cache::ShaderCache,
+        shader_compiler::compile_file,
+        shader_include::{IncludeResolver, ShaderFs},
+    },
+};
+
+const EXTENT: Extent2D = Extent2D {
+    width: 256,
+    height: 256,
+};
+
+/// The format [AutoExposure] reads.
+const HDR_FORMAT: Format = Format::R16G16B16A16_SFLOAT;
+
+/// Seconds per simulated frame.
+const FRAME_TIME: f32 = 1.0 / 60.0;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let entry = unsafe { Entry::load()? };
+    let instance = Instance::new(
+        entry,
+        Vec::new(),
+        "Auto Exposure",
+        learnvulkan::cargo_version!().to_vulkan(),
+        "No Engine",
+        make_api_version(0, 1, 0, 0),
+    )?;
+
+    let physical_device = PhysicalDevice::compute_only(instance)?;
+    let logical_device = LogicalDevice::compute_only(physical_device.clone())?;
+    let device = logical_device.device();
+    let command_pool = CommandPool::new(logical_device.clone(), &physical_device)?;
+    let shader_cache = ShaderCache::new(logical_device.clone());
+    let scope = DeviceScope::new(logical_device.clone());
+
+    let mut resolver = IncludeResolver::new(ShaderFs::with_root("shaders"));
+    let histogram_shader = compile_file(&mut resolver, "luminance_histogram.comp")?;
+    let average_shader = compile_file(&mut resolver, "average_luminance.comp")?;
+
+    let image = scope.adopt(unsafe {
+        device.create_image(
+            &ImageCreateInfo::default()
+                .image_type(ImageType::TYPE_2D)
+                .format(HDR_FORMAT)
+                .extent(Extent3D {
+                    width: EXTENT.width,
+                    height: EXTENT.height,
+                    depth: 1,
+                })
+                .mip_levels(1)
+                .array_layers(1)
+                .samples(SampleCountFlags::TYPE_1)
+                .tiling(ImageTiling::OPTIMAL)
+                .usage(ImageUsageFlags::STORAGE | ImageUsageFlags::TRANSFER_DST)
+                .sharing_mode(SharingMode::EXCLUSIVE)
+                .initial_layout(ImageLayout::UNDEFINED),
+            host_allocation_callbacks(),
+        )?
+    });
+
+    let requirements = unsafe { device.get_image_memory_requirements(image) };
+    let memory_type_index = physical_device
+        .find_memory_type(
+            requirements.memory_type_bits,
+            MemoryPropertyFlags::DEVICE_LOCAL,
+        )
+        .ok_or("no device-local memory for the HDR image")?;
+
+    let memory = scope.adopt(unsafe {
+        device.allocate_memory(
+            &MemoryAllocateInfo::default()
+                .allocation_size(requirements.size)
+                .memory_type_index(memory_type_index),
+            host_allocation_callbacks(),
+        )?
+    });
+
+    let range = ImageSubresourceRange::default()
+        .aspect_mask(ImageAspectFlags::COLOR)
+        .level_count(1)
+        .layer_count(1);
+
+    unsafe { device.bind_image_memory(image, memory, 0)? };
+
+    let view = scope.adopt(unsafe {
+        device.create_image_view(
+            &ImageViewCreateInfo::default()
+                .image(image)
+                .view_type(ImageViewType::TYPE_2D)
+                .format(HDR_FORMAT)
+                .subresource_range(range),
+            host_allocation_callbacks(),
+        )?
+    });
+
+    let fence = scope.adopt(unsafe {
+        device.create_fence(&FenceCreateInfo::default(), host_allocation_callbacks())?
+    });
+    let command_buffer = unsafe {
+        device.allocate_command_buffers(
+            &CommandBufferAllocateInfo::default()
+                .command_pool(*command_pool.command_pool())
+                .level(CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1),
+        )?[0]
+    };
+
+    let auto_exposure = AutoExposure::new(
+        logical_device.clone(),
+        &shader_cache,
+        view,
+        &histogram_shader,
+        &average_shader,
+    )?;
+    // Its descriptor set points at the view, so it goes first.
+    scope.keep(auto_exposure.clone());
+
+    // Two seconds indoors, two in the sun, then back inside.
+    let scene = [(0.05, 120), (20.0, 120), (0.05, 120)];
+    let mut layout = ImageLayout::UNDEFINED;
+    let mut frame = 0;
+
+    for (luminance, frames) in scene {
+        println!("scene luminance {}", luminance);
+
+        for _ in 0..frames {
+            let barrier = ImageMemoryBarrier::default()
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(range);
+
+            unsafe {
+                device.begin_command_buffer(
+                    command_buffer,
+                    &CommandBufferBeginInfo::default()
+                        .flags(CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+                )?;
+
+                // Last frame's histogram pass read the image, the clear has to wait for it.
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    PipelineStageFlags::COMPUTE_SHADER,
+                    PipelineStageFlags::TRANSFER,
+                    DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[barrier
+                        .old_layout(layout)
+                        .new_layout(ImageLayout::GENERAL)
+                        .dst_access_mask(AccessFlags::TRANSFER_WRITE)],
+                );
+                device.cmd_clear_color_image(
+                    command_buffer,
+                    image,
+                    ImageLayout::GENERAL,
+                    &ClearColorValue {
+                        float32: [luminance, luminance, luminance, 1.0],
+                    },
+                    &[range],
+                );
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    PipelineStageFlags::TRANSFER,
+                    PipelineStageFlags::COMPUTE_SHADER,
+                    DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[barrier
+                        .old_layout(ImageLayout::GENERAL)
+                        .new_layout(ImageLayout::GENERAL)
+                        .src_access_mask(AccessFlags::TRANSFER_WRITE)
+                        .dst_access_mask(AccessFlags::SHADER_READ)],
+                );
+            }
+
+            auto_exposure.record(command_buffer, EXTENT, FRAME_TIME);
+
+            unsafe {
+                device.end_command_buffer(command_buffer)?;
+                device.queue_submit(
+                    *logical_device.queue(),
+                    &[SubmitInfo::default().command_buffers(&[command_buffer])],
+                    fence,
+                )?;
+                device.wait_for_fences(&[fence], true, u64::MAX)?;
+                device.reset_fences(&[fence])?;
+            }
+
+            layout = ImageLayout::GENERAL;
+            frame += 1;
+
+            if frame % 20 == 0 {
+                println!(
+                    "  {:.2}s: adapted to luminance {:.4}",
+                    frame as f32 * FRAME_TIME,
+                    auto_exposure.average_luminance()
+                );
+            }
+        }
+    }
+
+    drop(auto_exposure);
+    scope.end()?;
+
+    Ok(())
+}
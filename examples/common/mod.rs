@@ -0,0 +1,429 @@
+//! What the windowed examples share: the chain from the window to the framebuffers, a frame loop
+//! recording into raw command buffers, and the image-based lighting and plain textures of the PBR
+//! ones.
+//!
+//! Cargo only builds the files at the top of `examples/`, each example includes this with
+//! `mod common;` and uses what it needs of it.
+
+#![allow(dead_code)]
+
+use std::{error::Error, f32::consts::PI, fs, path::Path};
+
+use ash::{
+    vk::{
+        self, make_api_version, ClearColorValue, ClearValue, CommandBufferAllocateInfo,
+        CommandBufferBeginInfo, CommandBufferLevel, CommandBufferResetFlags,
+        CommandBufferUsageFlags, Extent2D, Format, ImageLayout, PipelineStageFlags, Rect2D,
+        RenderPassBeginInfo, SubmitInfo, SubpassContents,
+    },
+    Entry,
+};
+use learnvulkan::{
+    api2::Color,
+    renderer::{
+        command_pool::CommandPool,
+        framebuffers::Framebuffers,
+        ibl::{EquirectangularImage, IblSettings, IblShaders, IblTextures},
+        image2d::Image2D,
+        image_desc::ImageDesc,
+        image_views::ImageViews,
+        instance::Instance,
+        logical_device::LogicalDevice,
+        pbr::PbrTextures,
+        physical_device::PhysicalDevice,
+        render_pass::RenderPass,
+        sampler::{SamplerBuilder, SamplerCache},
+        shader_cache::ShaderCache,
+        shader_compiler::{compile_file, ShaderCompileError},
+        shader_include::{IncludeResolver, ShaderFs},
+        staging_ring::StagingRing,
+        staging_uploader::StagingUploader,
+        surface::Surface,
+        swapchain::Swapchain,
+        sync_objects::SyncObjects,
+        window::Window,
+        DEFAULT_FRAMES_IN_FLIGHT,
+    },
+};
+
+/// A window presenting through a swapchain, with a render pass drawing straight to its images.
+pub struct Windowed {
+    pub window: Window,
+    pub logical_device: LogicalDevice,
+    pub command_pool: CommandPool,
+    pub shader_cache: ShaderCache,
+    pub uploader: StagingUploader,
+    pub swapchain: Swapchain,
+    pub render_pass: RenderPass,
+    framebuffers: Framebuffers,
+    sync_objects: SyncObjects,
+    command_buffers: Vec<vk::CommandBuffer>,
+    /// Resolves the includes of the shaders compiled from `shaders/`.
+    resolver: IncludeResolver,
+    current_frame: usize,
+}
+
+impl Windowed {
+    /// Opens an 800x600 window titled `title` and creates everything down to the framebuffers.
+    pub fn new(title: &str) -> Result<Self, Box<dyn Error>> {
+        let window = Window::new(title, glfw::WindowMode::Windowed, 600, 800)?;
+        let extensions = window
+            .get_required_instance_extensions()
+            .ok_or("GLFW found no Vulkan loader for window surfaces")?;
+
+        let entry = unsafe { Entry::load()? };
+        let instance = Instance::new(
+            entry,
+            extensions,
+            title,
+            learnvulkan::cargo_version!().to_vulkan(),
+            "No Engine",
+            make_api_version(0, 1, 0, 0),
+        )?;
+
+        let surface = Surface::new(instance.clone(), window.clone())?;
+        let physical_device = PhysicalDevice::new(instance, &surface)?;
+        let logical_device = LogicalDevice::new(physical_device.clone())?;
+        let command_pool = CommandPool::new(logical_device.clone(), &physical_device)?;
+        let uploader = StagingUploader::new(command_pool.clone())?;
+
+        let swapchain = Swapchain::new(physical_device, logical_device.clone(), surface, &window)?;
+        let render_pass = RenderPass::new(swapchain.clone())?;
+        let image_views = ImageViews::new(&swapchain, logical_device.clone())?;
+        let framebuffers = Framebuffers::new(render_pass.clone(), image_views)?;
+
+        let sync_objects = SyncObjects::new(logical_device.clone(), DEFAULT_FRAMES_IN_FLIGHT)?;
+        let command_buffers = unsafe {
+            logical_device.device().allocate_command_buffers(
+                &CommandBufferAllocateInfo::default()
+                    .command_pool(*command_pool.command_pool())
+                    .level(CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(DEFAULT_FRAMES_IN_FLIGHT as u32),
+            )?
+        };
+
+        Ok(Self {
+            window,
+            shader_cache: ShaderCache::new(logical_device.clone()),
+            logical_device,
+            command_pool,
+            uploader,
+            swapchain,
+            render_pass,
+            framebuffers,
+            sync_objects,
+            command_buffers,
+            resolver: IncludeResolver::new(ShaderFs::with_root("shaders")),
+            current_frame: 0,
+        })
+    }
+
+    /// Compiles the shader at `path` in `shaders/`, which needs the `shader-compiler` feature.
+    pub fn compile(&mut self, path: &str) -> Result<Vec<u32>, ShaderCompileError> {
+        compile_file(&mut self.resolver, path)
+    }
+
+    /// Compiles the shader at `path` with `define` defined, which is how shaders like
+    /// `pbr.frag` pick their variants.
+    pub fn compile_with_define(
+        &mut self,
+        path: &str,
+        define: &str,
+    ) -> Result<Vec<u32>, Box<dyn Error>> {
+        let source = fs::read_to_string(Path::new("shaders").join(path))?;
+        // Defines have to come after `#version`, which is the first line.
+        let (version, rest) = source.split_once('\n').unwrap_or((&source, ""));
+        // Registered next to the original so its includes resolve the same.
+        let variant = format!("{}.{}", define.to_lowercase(), path);
+
+        self.resolver.fs.insert(
+            &variant,
+            format!("{}\n#define {}\n{}", version, define, rest),
+        );
+
+        Ok(self.compile(&variant)?)
+    }
+
+    pub fn extent(&self) -> Extent2D {
+        self.swapchain.extent()
+    }
+
+    /// Width over height of the swapchain images.
+    pub fn aspect_ratio(&self) -> f32 {
+        let extent = self.extent();
+        extent.width as f32 / extent.height as f32
+    }
+
+    /// Polls the window's events, closing it on Escape, and returns them.
+    pub fn poll_events(&self) -> Vec<glfw::WindowEvent> {
+        self.window.poll_events();
+
+        let events = self.window.flush_events();
+
+        for event in &events {
+            if let glfw::WindowEvent::Key(glfw::Key::Escape, _, glfw::Action::Press, _) = event {
+                self.window.set_should_close(true);
+            }
+        }
+
+        events
+    }
+
+    /// Waits for the frame's previous submission, acquires an image and records the frame with
+    /// `record`, then submits and presents it.
+    ///
+    /// The window can't be resized, so the swapchain is never recreated: frames are skipped
+    /// while it's out of date, e.g. when minimized.
+    pub fn draw_frame(
+        &mut self,
+        record: impl FnOnce(&Frame) -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let index = self.current_frame;
+        let device = self.logical_device.device();
+
+        self.sync_objects.wait_in_flight_fence(index)?;
+
+        let image_available = *self.sync_objects.image_available_semaphore(index);
+        let image_index =
+            match self
+                .swapchain
+                .acquire_next_image(u64::MAX, Some(image_available), None)
+            {
+                Ok((image_index, _)) => image_index,
+                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => return Ok(()),
+                Err(e) => return Err(e.into()),
+            };
+
+        self.sync_objects.reset_in_flight_fence(index)?;
+
+        let command_buffer = self.command_buffers[index];
+        let fence = *self.sync_objects.in_flight_fence(index);
+
+        unsafe {
+            device.reset_command_buffer(command_buffer, CommandBufferResetFlags::empty())?;
+            device.begin_command_buffer(
+                command_buffer,
+                &CommandBufferBeginInfo::default().flags(CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+            )?;
+        }
+
+        record(&Frame {
+            index,
+            command_buffer,
+            fence,
+            framebuffer: self.framebuffers.framebuffer(image_index, 0),
+            windowed: self,
+        })?;
+
+        let render_finished = *self.sync_objects.render_finished_semaphore(index);
+
+        unsafe {
+            device.end_command_buffer(command_buffer)?;
+            device.queue_submit(
+                *self.logical_device.queue(),
+                &[SubmitInfo::default()
+                    .wait_semaphores(&[image_available])
+                    .wait_dst_stage_mask(&[PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT])
+                    .command_buffers(&[command_buffer])
+                    .signal_semaphores(&[render_finished])],
+                fence,
+            )?;
+        }
+
+        // The render pass leaves the image ready to present.
+        self.swapchain
+            .image(image_index)
+            .submitted(fence, ImageLayout::PRESENT_SRC_KHR);
+
+        match self
+            .swapchain
+            .queue_present(&[render_finished], &[image_index])
+        {
+            Ok(_) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        self.current_frame = (index + 1) % DEFAULT_FRAMES_IN_FLIGHT;
+
+        Ok(())
+    }
+
+    /// Waits for every frame to finish, before the example drops what they use.
+    pub fn wait_idle(&self) -> Result<(), Box<dyn Error>> {
+        Ok(self.logical_device.wait_idle()?)
+    }
+}
+
+/// The frame being recorded by [Windowed::draw_frame].
+pub struct Frame<'a> {
+    /// Which of the frames in flight this is, for per-frame buffers and descriptor sets.
+    pub index: usize,
+    pub command_buffer: vk::CommandBuffer,
+    /// Signalled once the frame's submission finished.
+    pub fence: vk::Fence,
+    framebuffer: vk::Framebuffer,
+    windowed: &'a Windowed,
+}
+
+impl Frame<'_> {
+    /// Begins the render pass on the acquired image, cleared to `clear_color`, with a viewport
+    /// and scissor covering it.
+    pub fn begin_render_pass(&self, clear_color: Color) {
+        let windowed = self.windowed;
+        let device = windowed.logical_device.device();
+        let extent = windowed.extent();
+
+        let clear_values = [ClearValue {
+            color: ClearColorValue {
+                float32: windowed.swapchain.encode_color(clear_color),
+            },
+        }];
+
+        unsafe {
+            device.cmd_begin_render_pass(
+                self.command_buffer,
+                &RenderPassBeginInfo::default()
+                    .render_pass(*windowed.render_pass.render_pass())
+                    .framebuffer(self.framebuffer)
+                    .render_area(Rect2D::default().extent(extent))
+                    .clear_values(&clear_values),
+                SubpassContents::INLINE,
+            );
+            device.cmd_set_viewport(
+                self.command_buffer,
+                0,
+                &[windowed.logical_device.clip_space_y().viewport(extent)],
+            );
+            device.cmd_set_scissor(self.command_buffer, 0, &[Rect2D::default().extent(extent)]);
+        }
+    }
+
+    pub fn end_render_pass(&self) {
+        unsafe {
+            self.windowed
+                .logical_device
+                .device()
+                .cmd_end_render_pass(self.command_buffer)
+        };
+    }
+}
+
+/// The image-based lighting of a clear sky with a bright sun, computed on the GPU at startup.
+pub fn sky_environment(windowed: &mut Windowed) -> Result<IblTextures, Box<dyn Error>> {
+    let shaders = [
+        "equirect_to_cube.comp",
+        "irradiance.comp",
+        "prefilter.comp",
+        "brdf_lut.comp",
+    ]
+    .map(|path| windowed.compile(path))
+    .into_iter()
+    .collect::<Result<Vec<_>, _>>()?;
+
+    let (width, height) = (1024, 512);
+    let panorama = sky_panorama(width, height);
+    // The panorama is staged as half floats.
+    let mut staging = StagingRing::new(
+        windowed.logical_device.clone(),
+        panorama.len() as vk::DeviceSize * 2,
+    )?;
+
+    let environment = IblTextures::generate(
+        windowed.logical_device.clone(),
+        &windowed.command_pool,
+        &windowed.shader_cache,
+        &SamplerCache::new(windowed.logical_device.clone()),
+        &mut staging,
+        EquirectangularImage {
+            width,
+            height,
+            pixels: &panorama,
+        },
+        IblShaders {
+            equirect_to_cube: &shaders[0],
+            irradiance: &shaders[1],
+            prefilter: &shaders[2],
+            brdf_lut: &shaders[3],
+        },
+        IblSettings::default(),
+    )?;
+
+    Ok(environment)
+}
+
+/// A clear sky over dark ground with a bright sun, as RGBA rows from the top.
+fn sky_panorama(width: u32, height: u32) -> Vec<f32> {
+    let sun = [0.3f32, 0.6, 0.74];
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+
+    for y in 0..height {
+        let elevation = (0.5 - (y as f32 + 0.5) / height as f32) * PI;
+
+        for x in 0..width {
+            let azimuth = ((x as f32 + 0.5) / width as f32) * 2.0 * PI;
+            let direction = [
+                elevation.cos() * azimuth.cos(),
+                elevation.sin(),
+                elevation.cos() * azimuth.sin(),
+            ];
+
+            let color = if elevation < 0.0 {
+                [0.15, 0.13, 0.1]
+            } else {
+                let t = elevation.sin();
+                let sky = [0.9 - 0.6 * t, 0.95 - 0.45 * t, 1.0 - 0.1 * t];
+                let alignment: f32 = direction.iter().zip(sun).map(|(a, b)| a * b).sum();
+
+                if alignment > 0.9995 {
+                    [50.0, 45.0, 40.0]
+                } else {
+                    sky
+                }
+            };
+
+            pixels.extend_from_slice(&[color[0], color[1], color[2], 1.0]);
+        }
+    }
+
+    pixels
+}
+
+/// 1x1 textures leaving a material to its [MaterialFactors]: white albedo, occlusion, roughness
+/// and metalness, and a normal map that doesn't bend the normals.
+///
+/// [MaterialFactors]: learnvulkan::renderer::pbr::MaterialFactors
+pub struct FlatTextures {
+    pub albedo: Image2D,
+    pub normal: Image2D,
+    /// Both the metallic-roughness and the occlusion texture.
+    pub white: Image2D,
+}
+
+impl FlatTextures {
+    pub fn new(uploader: &StagingUploader) -> Result<Self, Box<dyn Error>> {
+        let texel = |format, data: [u8; 4]| {
+            Image2D::new(
+                uploader,
+                ImageDesc::new(1, 1),
+                format,
+                &data,
+                SamplerBuilder::default(),
+            )
+        };
+
+        Ok(Self {
+            albedo: texel(Format::R8G8B8A8_SRGB, [255; 4])?,
+            normal: texel(Format::R8G8B8A8_UNORM, [128, 128, 255, 255])?,
+            white: texel(Format::R8G8B8A8_UNORM, [255; 4])?,
+        })
+    }
+
+    pub fn pbr_textures(&self) -> PbrTextures {
+        PbrTextures {
+            albedo: self.albedo.descriptor(),
+            normal: self.normal.descriptor(),
+            metallic_roughness: self.white.descriptor(),
+            occlusion: self.white.descriptor(),
+        }
+    }
+}
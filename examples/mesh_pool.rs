@@ -0,0 +1,153 @@
+//! Packs a few hundred small polygons into a [MeshBufferPool] and draws them with the tutorial's
+//! [GraphicsPipeline], each its own mesh.
+//!
+//! The polygons are uploaded through a [StagingRing] in the first frame's command buffer, before
+//! its render pass. Drawing them in the order they were added only binds each block's buffers
+//! once, which the printed stats show.
+//!
+//! ```sh
+//! cargo run --example mesh_pool
+//! ```
+
+mod common;
+
+use std::{error::Error, f32::consts::PI, mem};
+
+use ash::vk::PipelineBindPoint;
+use learnvulkan::{
+    api2::Color,
+    renderer::{
+        graphics_pipeline::GraphicsPipeline,
+        mesh_pool::{MeshBufferPool, PooledMesh},
+        staging_ring::StagingRing,
+        vertex::Vertex,
+    },
+    types::slice_bytes,
+};
+
+use common::Windowed;
+
+/// Polygons along each row and column of the grid.
+const COLUMNS: usize = 20;
+const ROWS: usize = 15;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut windowed = Windowed::new("Mesh Pool")?;
+    let logical_device = windowed.logical_device.clone();
+    let device = logical_device.device();
+
+    let pipeline = GraphicsPipeline::new(windowed.render_pass.clone(), &windowed.shader_cache)?;
+    // Small blocks, so the polygons spread over a few of them.
+    let mut pool = MeshBufferPool::new(
+        logical_device.clone(),
+        mem::size_of::<Vertex>() as u32,
+        1024,
+        3072,
+    )?;
+    let mut staging = StagingRing::new(logical_device.clone(), 256 * 1024)?;
+    let mut meshes: Vec<PooledMesh> = Vec::new();
+
+    while !windowed.window.should_close() {
+        windowed.poll_events();
+        staging.retire()?;
+
+        let before = pool.stats();
+
+        windowed.draw_frame(|frame| {
+            if meshes.is_empty() {
+                for i in 0..COLUMNS * ROWS {
+                    let (vertices, indices) = polygon(i);
+
+                    meshes.push(pool.add(
+                        &mut staging,
+                        frame.command_buffer,
+                        slice_bytes(&vertices),
+                        &indices,
+                    )?);
+                }
+
+                // The staging space is free again once this frame's submission finished.
+                staging.submit(frame.fence);
+            }
+
+            frame.begin_render_pass(Color::BLACK);
+
+            unsafe {
+                device.cmd_bind_pipeline(
+                    frame.command_buffer,
+                    PipelineBindPoint::GRAPHICS,
+                    pipeline.pipeline()[0],
+                );
+                // The tutorial's positions are given with Y pointing down, as its own renderer
+                // draws them.
+                device.cmd_set_viewport(frame.command_buffer, 0, pipeline.viewports());
+            }
+
+            let mut bound_block = None;
+
+            for mesh in &meshes {
+                pool.draw(frame.command_buffer, mesh, 1, &mut bound_block);
+            }
+
+            frame.end_render_pass();
+
+            Ok(())
+        })?;
+
+        // The frame that added the meshes.
+        if before.meshes == 0 {
+            let stats = pool.stats();
+
+            println!(
+                "{} meshes with {} vertices and {} indices in {} blocks, drawn with {} binds",
+                stats.meshes,
+                stats.vertices,
+                stats.indices,
+                stats.blocks,
+                stats.binds - before.binds,
+            );
+        }
+    }
+
+    windowed.wait_idle()?;
+
+    Ok(())
+}
+
+/// The `index`th polygon of the grid, a regular polygon with 3 to 10 sides in a color of its own.
+fn polygon(index: usize) -> (Vec<Vertex>, Vec<u32>) {
+    let (column, row) = (index % COLUMNS, index / COLUMNS);
+    let center = [
+        (column as f32 + 0.5) / COLUMNS as f32 * 2.0 - 1.0,
+        (row as f32 + 0.5) / ROWS as f32 * 2.0 - 1.0,
+    ];
+    let radius = 0.8 / COLUMNS as f32;
+    let sides = 3 + (index * 7) % 8;
+    let color = Color::hsv(
+        index as f32 * 360.0 / (COLUMNS * ROWS) as f32,
+        0.7,
+        1.0,
+        1.0,
+    );
+
+    let vertices = (0..sides)
+        .map(|side| {
+            let angle = side as f32 / sides as f32 * 2.0 * PI;
+
+            Vertex {
+                position: [
+                    center[0] + angle.cos() * radius,
+                    center[1] + angle.sin() * radius,
+                ],
+                color: [color.r, color.g, color.b],
+            }
+        })
+        .collect();
+
+    // A fan around the first corner, clockwise on screen like the tutorial's triangle.
+    let indices = (1..sides as u32 - 1)
+        .flat_map(|corner| [0, corner, corner + 1])
+        .collect();
+
+    (vertices, indices)
+}
@@ -0,0 +1,370 @@
+//! Draws a grid of spheres with [PbrPipeline], metalness growing to the right and roughness
+//! upwards, lit by the image-based lighting of a sky and a few lights while the camera circles
+//! them.
+//!
+//! Passing the path of an OBJ file draws it in place of the spheres, scaled to the same size,
+//! which needs the `tobj` feature as well. `--albedo` takes a PNG or JPEG to color them with in
+//! place of the flat red, which needs the `image-loading` feature. `--many-lights` adds a few
+//! hundred small lights circling the grid, which [LightClusters] sorts into clusters so every
+//! fragment only shades the ones near it.
+//!
+//! Clicking a sphere prints its material, found by drawing the grid's object IDs into a
+//! [PickingPass] every frame and reading back the pixel under the cursor.
+//!
+//! There's no tonemapping pass, so the lit colors are written as they are and the highlights
+//! clip. The shaders are compiled from `shaders/` at runtime, so this needs the
+//! `shader-compiler` feature:
+//!
+//! ```sh
+//! cargo run --example pbr --features shader-compiler
+//! cargo run --example pbr --features shader-compiler,tobj -- model.obj
+//! cargo run --example pbr --features shader-compiler -- --many-lights
+//! cargo run --example pbr --features full -- model.obj --albedo albedo.png
+//! ```
+
+mod common;
+
+use std::{env, error::Error, f32::consts::PI, mem, time::Instant};
+
+use learnvulkan::{
+    api2::{Camera, Color},
+    renderer::{
+        image2d::{ColorSpace, Image2D},
+        lights::{ClusterView, Light, LightBuffers, LightClusters, LightList},
+        mesh::Mesh,
+        model::Model,
+        pbr::{MaterialFactors, PbrFrameUniforms, PbrPipeline, PbrVertex},
+        picking::{ObjectId, PickingPass},
+        sampler::SamplerBuilder,
+    },
+};
+use nalgebra_glm as glm;
+
+use common::{sky_environment, FlatTextures, Windowed};
+
+/// Spheres along each side of the grid.
+const GRID: usize = 5;
+
+/// Lights circling the grid with `--many-lights`.
+const CIRCLING_LIGHTS: usize = 256;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut model_path = None;
+    let mut albedo_path = None;
+    let mut many_lights = false;
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == "--albedo" {
+            albedo_path = Some(args.next().ok_or("--albedo needs the path of an image")?);
+        } else if arg == "--many-lights" {
+            many_lights = true;
+        } else {
+            model_path = Some(arg);
+        }
+    }
+
+    let mut windowed = Windowed::new("PBR")?;
+
+    let vertex_shader = windowed.compile("pbr.vert")?;
+    let fragment_shader = if many_lights {
+        windowed.compile_with_define("pbr.frag", "CLUSTERED_LIGHTS")?
+    } else {
+        windowed.compile("pbr.frag")?
+    };
+    let pipeline = PbrPipeline::new(
+        windowed.render_pass.clone(),
+        &windowed.shader_cache,
+        &vertex_shader,
+        &fragment_shader,
+        (GRID * GRID) as u32,
+    )?;
+
+    let environment = sky_environment(&mut windowed)?;
+    pipeline.set_environment(&environment);
+
+    let mut lights = LightList::new();
+    lights.push(Light::Directional {
+        direction: [-0.3, -0.6, -0.74],
+        color: [1.0, 0.95, 0.85],
+        intensity: 3.0,
+    });
+    lights.push(Light::Point {
+        position: [-3.0, 2.0, 3.0],
+        range: 8.0,
+        color: [1.0, 0.4, 0.2],
+        intensity: 20.0,
+    });
+    lights.push(Light::Point {
+        position: [3.0, -2.0, 3.0],
+        range: 8.0,
+        color: [0.2, 0.5, 1.0],
+        intensity: 20.0,
+    });
+
+    let fixed_lights = lights.len();
+    let circling_lights = if many_lights { CIRCLING_LIGHTS } else { 0 };
+    let light_buffers = LightBuffers::new(
+        windowed.logical_device.clone(),
+        (fixed_lights + circling_lights) as u32,
+    )?;
+    pipeline.set_lights(&light_buffers);
+
+    let light_clusters = if many_lights {
+        let cull_shader = windowed.compile("light_cull.comp")?;
+        let light_clusters = LightClusters::new(
+            windowed.logical_device.clone(),
+            &windowed.shader_cache,
+            &light_buffers,
+            &cull_shader,
+        )?;
+        pipeline.set_light_clusters(&light_clusters);
+
+        Some(light_clusters)
+    } else {
+        None
+    };
+
+    let flat_textures = FlatTextures::new(&windowed.uploader)?;
+    let mut textures = flat_textures.pbr_textures();
+    let mut base_color = [0.9, 0.2, 0.15, 1.0];
+    let albedo = albedo_path
+        .map(|path| {
+            Image2D::from_file(
+                &windowed.uploader,
+                path,
+                ColorSpace::Srgb,
+                SamplerBuilder::default(),
+            )
+        })
+        .transpose()?;
+
+    if let Some(albedo) = &albedo {
+        textures.albedo = albedo.descriptor();
+        base_color = [1.0; 4];
+    }
+
+    let model = match model_path {
+        Some(path) => Model::from_file(&windowed.uploader, path)?,
+        None => Model::new(&windowed.uploader, &uv_sphere(1.0, 32, 16))?,
+    };
+    let mesh = model.pbr_mesh();
+    // Centered and scaled to the size of the spheres.
+    let bounds = model.bounds().sphere;
+    let scale = 0.4 / bounds.radius;
+    let fit = glm::scaling(&glm::vec3(scale, scale, scale))
+        * glm::translation(&-glm::Vec3::from(bounds.center));
+
+    let instances = (0..GRID * GRID)
+        .map(|i| {
+            let (column, row) = ((i % GRID) as f32, (i / GRID) as f32);
+            let spacing = 1.0;
+            let offset = (GRID - 1) as f32 * spacing / 2.0;
+
+            let material = pipeline.create_material(
+                &textures,
+                MaterialFactors {
+                    base_color,
+                    metallic: column / (GRID - 1) as f32,
+                    // Fully smooth spheres reflect the sun as a single pixel.
+                    roughness: (row / (GRID - 1) as f32).max(0.05),
+                    ..Default::default()
+                },
+            )?;
+            let placement = glm::translation(&glm::vec3(
+                column * spacing - offset,
+                row * spacing - offset,
+                0.0,
+            ));
+
+            Ok((material, placement * fit))
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+    let picking_vertex_shader = windowed.compile("picking.vert")?;
+    let picking_fragment_shader = windowed.compile("picking.frag")?;
+    let mut picking = PickingPass::new(
+        windowed.logical_device.clone(),
+        &windowed.shader_cache,
+        windowed.extent(),
+        &picking_vertex_shader,
+        &picking_fragment_shader,
+        mem::size_of::<PbrVertex>() as u32,
+    )?;
+    let mut cursor = None;
+    let mut hovered: Option<ObjectId> = None;
+
+    let start = Instant::now();
+
+    while !windowed.window.should_close() {
+        for event in windowed.poll_events() {
+            match event {
+                glfw::WindowEvent::CursorPos(x, y) => cursor = Some((x as u32, y as u32)),
+                glfw::WindowEvent::CursorEnter(false) => {
+                    cursor = None;
+                    picking.clear_cursor();
+                }
+                glfw::WindowEvent::MouseButton(
+                    glfw::MouseButton::Button1,
+                    glfw::Action::Press,
+                    _,
+                ) => match hovered {
+                    Some(object) => {
+                        let (material, _) = &instances[object.get() as usize - 1];
+                        println!(
+                            "metallic {:.2}, roughness {:.2}",
+                            material.factors.metallic, material.factors.roughness
+                        );
+                    }
+                    None => println!("nothing there"),
+                },
+                _ => {}
+            }
+        }
+
+        let time = start.elapsed().as_secs_f32();
+        let angle = time * 0.3;
+
+        lights.lights_mut().truncate(fixed_lights);
+
+        for i in 0..circling_lights {
+            let t = i as f32 / circling_lights as f32;
+            // Every light on its own orbit, the inner ones faster.
+            let orbit = 1.0 + 3.0 * (t * 37.0).fract();
+            let phase = t * 2.0 * PI * 11.0 + time / orbit;
+
+            lights.push(Light::Point {
+                position: [
+                    phase.cos() * orbit,
+                    (t * 2.0 - 1.0) * 2.5,
+                    phase.sin() * orbit,
+                ],
+                range: 1.0,
+                color: {
+                    let color = Color::hsv(t * 360.0, 0.8, 1.0, 1.0);
+                    [color.r, color.g, color.b]
+                },
+                intensity: 2.0,
+            });
+        }
+
+        let camera = Camera::new(
+            glm::vec3(angle.sin() * 8.0, 1.0, angle.cos() * 8.0),
+            glm::Vec3::zeros(),
+        );
+        let clip_space_y = windowed.logical_device.clip_space_y();
+        let aspect_ratio = windowed.aspect_ratio();
+        let extent = windowed.extent();
+
+        windowed.draw_frame(|frame| {
+            // The frame's fence has signaled, so its readback is in.
+            picking.resolve(frame.index);
+            hovered = cursor.and_then(|(x, y)| picking.pick(x, y));
+
+            let view_projection = camera.view_projection_for(clip_space_y, aspect_ratio);
+
+            picking.begin(frame.command_buffer);
+
+            for (i, (_, transform)) in instances.iter().enumerate() {
+                // IDs start at 1, 0 is the background.
+                let object = ObjectId::new(i as u32 + 1).unwrap();
+                picking.draw(
+                    frame.command_buffer,
+                    object,
+                    (view_projection * transform).into(),
+                    &mesh,
+                );
+            }
+
+            picking.end(frame.command_buffer, frame.index);
+
+            let mut uniforms = PbrFrameUniforms::default();
+            uniforms.view_projection = view_projection.into();
+            uniforms.view = camera.view().into();
+            uniforms.camera_position = camera.position.push(1.0).into();
+            uniforms.cluster_params = [
+                camera.near,
+                camera.far,
+                extent.width as f32,
+                extent.height as f32,
+            ];
+
+            pipeline.update_frame(frame.index, &uniforms);
+            light_buffers.upload(frame.index, &lights);
+
+            if let Some(light_clusters) = &light_clusters {
+                light_clusters.record(
+                    frame.command_buffer,
+                    frame.index,
+                    &ClusterView {
+                        view: uniforms.view,
+                        fov_y: camera.fovy,
+                        aspect_ratio,
+                        near: camera.near,
+                        far: camera.far,
+                    },
+                );
+            }
+
+            frame.begin_render_pass(Color::BLACK);
+            pipeline.bind(frame.command_buffer, frame.index);
+
+            for (material, transform) in &instances {
+                pipeline.draw(frame.command_buffer, material, (*transform).into(), &mesh);
+            }
+
+            frame.end_render_pass();
+
+            Ok(())
+        })?;
+    }
+
+    windowed.wait_idle()?;
+
+    Ok(())
+}
+
+/// A sphere of `radius` around the origin, split into `segments` around the Y axis and `rings`
+/// from pole to pole.
+fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Mesh {
+    let mut vertices = Vec::new();
+
+    for ring in 0..=rings {
+        let v = ring as f32 / rings as f32;
+        let polar = v * PI;
+
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let azimuth = u * 2.0 * PI;
+            let normal = [
+                polar.sin() * azimuth.cos(),
+                polar.cos(),
+                -polar.sin() * azimuth.sin(),
+            ];
+
+            vertices.push(PbrVertex {
+                position: normal.map(|n| n * radius),
+                normal,
+                tangent: [0.0; 4],
+                uv: [u, v],
+            });
+        }
+    }
+
+    let mut indices = Vec::new();
+    let row = segments + 1;
+
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let top = ring * row + segment;
+            let bottom = top + row;
+
+            // Counter-clockwise seen from outside.
+            indices.extend_from_slice(&[top, bottom, top + 1, top + 1, bottom, bottom + 1]);
+        }
+    }
+
+    let mut mesh = Mesh::new(vertices, indices);
+    mesh.compute_tangents();
+    mesh
+}
@@ -0,0 +1,213 @@
+//! Bends a tube back and forth with a three-joint [Skeleton], deformed on the GPU by the skinned
+//! [PbrPipeline].
+//!
+//! The animation is sampled into a pose every frame, resolved into the joint matrices of a
+//! palette and uploaded to [JointPalettes], which `shaders/pbr_skinned.vert` blends between
+//! with the weights of every vertex. The shaders are compiled from `shaders/` at runtime, so
+//! this needs the `shader-compiler` feature:
+//!
+//! ```sh
+//! cargo run --example skinning --features shader-compiler
+//! ```
+
+mod common;
+
+use std::{error::Error, f32::consts::PI, time::Instant};
+
+use ash::vk::BufferUsageFlags;
+use learnvulkan::{
+    api2::{Camera, Color, Transform},
+    renderer::{
+        buffer::IndexBuffer,
+        lights::{Light, LightBuffers, LightList},
+        pbr::{MaterialFactors, PbrFrameUniforms, PbrMesh, PbrPipeline},
+        skinning::{
+            AnimationClip, Channel, ChannelValues, Interpolation, Joint, JointPalettes, Skeleton,
+            SkinnedVertex,
+        },
+    },
+};
+use nalgebra_glm as glm;
+
+use common::{sky_environment, FlatTextures, Windowed};
+
+/// Joints stacked along the tube, one unit apart.
+const JOINTS: usize = 3;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut windowed = Windowed::new("Skinning")?;
+
+    let vertex_shader = windowed.compile("pbr_skinned.vert")?;
+    let fragment_shader = windowed.compile("pbr.frag")?;
+    let pipeline = PbrPipeline::new_skinned(
+        windowed.render_pass.clone(),
+        &windowed.shader_cache,
+        &vertex_shader,
+        &fragment_shader,
+        1,
+    )?;
+
+    let environment = sky_environment(&mut windowed)?;
+    pipeline.set_environment(&environment);
+
+    let mut lights = LightList::new();
+    lights.push(Light::Directional {
+        direction: [-0.3, -0.6, -0.74],
+        color: [1.0, 0.95, 0.85],
+        intensity: 3.0,
+    });
+
+    let light_buffers = LightBuffers::new(windowed.logical_device.clone(), lights.len() as u32)?;
+    pipeline.set_lights(&light_buffers);
+
+    let joint_palettes = JointPalettes::new(windowed.logical_device.clone(), JOINTS as u32)?;
+    pipeline.set_joint_palettes(&joint_palettes);
+
+    let textures = FlatTextures::new(&windowed.uploader)?;
+    let material = pipeline.create_material(
+        &textures.pbr_textures(),
+        MaterialFactors {
+            base_color: [0.2, 0.6, 0.3, 1.0],
+            metallic: 0.0,
+            roughness: 0.4,
+            ..Default::default()
+        },
+    )?;
+
+    let (vertices, indices) = tube(0.3, JOINTS as f32, 32, 48);
+    let vertex_buffer = windowed
+        .uploader
+        .create_buffer(&vertices, BufferUsageFlags::VERTEX_BUFFER)?;
+    let index_buffer = IndexBuffer::new(&windowed.uploader, &indices)?;
+    let mesh = PbrMesh {
+        vertex_buffer: *vertex_buffer.buffer(),
+        index_buffer: *index_buffer.buffer().buffer(),
+        index_type: index_buffer.index_type(),
+        index_count: index_buffer.count(),
+    };
+
+    let skeleton = Skeleton::new(
+        (0..JOINTS)
+            .map(|i| Joint {
+                name: format!("segment{}", i),
+                parent: i.checked_sub(1),
+                // Each joint sits one unit above its parent, the root at the bottom of the tube.
+                inverse_bind: glm::translation(&glm::vec3(0.0, -(i as f32), 0.0)),
+                rest: Transform::from_translation(glm::vec3(
+                    0.0,
+                    if i == 0 { 0.0 } else { 1.0 },
+                    0.0,
+                )),
+            })
+            .collect(),
+    )?;
+    // Every joint above the root swings around Z, adding up along the chain.
+    let clip = AnimationClip::new(
+        "bend",
+        (1..JOINTS)
+            .map(|joint| Channel {
+                joint,
+                interpolation: Interpolation::Linear,
+                times: vec![0.0, 1.0, 2.0],
+                values: ChannelValues::Rotation(
+                    [-0.6, 0.6, -0.6]
+                        .map(|angle| glm::quat_angle_axis(angle, &glm::Vec3::z()))
+                        .to_vec(),
+                ),
+            })
+            .collect(),
+    );
+
+    let mut pose = skeleton.rest_pose();
+    let mut palette = Vec::new();
+
+    let camera = Camera::new(glm::vec3(0.0, 1.5, 6.0), glm::vec3(0.0, 1.5, 0.0));
+    let start = Instant::now();
+
+    while !windowed.window.should_close() {
+        windowed.poll_events();
+
+        clip.sample_looped(start.elapsed().as_secs_f32(), &mut pose);
+        skeleton.compute_palette(&pose, &mut palette);
+
+        let clip_space_y = windowed.logical_device.clip_space_y();
+        let aspect_ratio = windowed.aspect_ratio();
+
+        windowed.draw_frame(|frame| {
+            let mut uniforms = PbrFrameUniforms::default();
+            uniforms.view_projection = camera
+                .view_projection_for(clip_space_y, aspect_ratio)
+                .into();
+            uniforms.view = camera.view().into();
+            uniforms.camera_position = camera.position.push(1.0).into();
+
+            pipeline.update_frame(frame.index, &uniforms);
+            light_buffers.upload(frame.index, &lights);
+            joint_palettes.upload(frame.index, 0, &palette);
+
+            frame.begin_render_pass(Color::BLACK);
+            pipeline.bind(frame.command_buffer, frame.index);
+            pipeline.draw_skinned(
+                frame.command_buffer,
+                &material,
+                glm::Mat4::identity().into(),
+                &mesh,
+                0,
+            );
+            frame.end_render_pass();
+
+            Ok(())
+        })?;
+    }
+
+    windowed.wait_idle()?;
+
+    Ok(())
+}
+
+/// An open tube of `radius` from the origin up to `height`, split into `segments` around the Y
+/// axis and `rings` along it, each ring weighted between the joints closest to it.
+fn tube(radius: f32, height: f32, segments: u32, rings: u32) -> (Vec<SkinnedVertex>, Vec<u32>) {
+    let mut vertices = Vec::new();
+
+    for ring in 0..=rings {
+        let v = ring as f32 / rings as f32;
+        let y = v * height;
+
+        // Fully bound to a joint in the middle of its segment, blending into the next one
+        // between the middles.
+        let blend = (y - 0.5).clamp(0.0, (JOINTS - 1) as f32);
+        let joint = (blend as u16).min(JOINTS as u16 - 2);
+        let weight = blend - joint as f32;
+
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let azimuth = u * 2.0 * PI;
+            let (sin, cos) = azimuth.sin_cos();
+
+            vertices.push(SkinnedVertex {
+                position: [cos * radius, y, -sin * radius],
+                normal: [cos, 0.0, -sin],
+                tangent: [-sin, 0.0, -cos, 1.0],
+                uv: [u, v],
+                joints: [joint, joint + 1, 0, 0],
+                weights: [1.0 - weight, weight, 0.0, 0.0],
+            });
+        }
+    }
+
+    let mut indices = Vec::new();
+    let row = segments + 1;
+
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let bottom = ring * row + segment;
+            let top = bottom + row;
+
+            // Counter-clockwise seen from outside.
+            indices.extend_from_slice(&[top, bottom, top + 1, top + 1, bottom, bottom + 1]);
+        }
+    }
+
+    (vertices, indices)
+}
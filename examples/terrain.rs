@@ -0,0 +1,151 @@
+//! Flies a camera around rolling hills drawn by [Terrain], printing how many chunks the quadtree
+//! picked once a second.
+//!
+//! The chunks close to the camera are split finer and those behind it are culled, so the count
+//! changes as the camera circles. The swapchain's render pass has no depth attachment, so the
+//! camera looks down steeply enough that no hill hides another. The shaders are compiled from
+//! `shaders/` at runtime, so this needs the `shader-compiler` feature.
+//!
+//! With the `overlay` feature, an [Overlay] in the top-left corner graphs the frame times, the
+//! memory in use and the chunks drawn out of the most the quadtree can split into.
+//!
+//! ```sh
+//! cargo run --example terrain --features shader-compiler
+//! cargo run --example terrain --features shader-compiler,overlay
+//! ```
+
+mod common;
+
+use std::{
+    error::Error,
+    time::{Duration, Instant},
+};
+
+use learnvulkan::{
+    api2::{Camera, Color, FrameClock},
+    renderer::{
+        overlay::{CounterBars, FrameTimeGraph, MemoryUsageBars, Overlay, OverlayBatch},
+        terrain::{Heightmap, Terrain, TerrainSettings},
+    },
+};
+use nalgebra_glm as glm;
+
+use common::Windowed;
+
+/// Samples along each side of the heightmap.
+const HEIGHTMAP_SIZE: u32 = 257;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut windowed = Windowed::new("Terrain")?;
+
+    let vertex_shader = windowed.compile("terrain.vert")?;
+    let fragment_shader = windowed.compile("terrain.frag")?;
+
+    // A few octaves of waves, between 0 and 1.
+    let heightmap = Heightmap::from_fn(HEIGHTMAP_SIZE, HEIGHTMAP_SIZE, |x, y| {
+        let (x, y) = (x as f32 / 16.0, y as f32 / 16.0);
+        let waves = (x.sin() * y.cos()) * 0.5
+            + (x * 2.3 + y * 1.7).sin() * 0.25
+            + (x * 5.1 - y * 4.3).cos() * 0.125;
+
+        waves / 1.75 + 0.5
+    });
+    let settings = TerrainSettings {
+        size: 512.0,
+        height_scale: 48.0,
+        ..Default::default()
+    };
+    let terrain = Terrain::new(
+        windowed.render_pass.clone(),
+        &windowed.shader_cache,
+        &vertex_shader,
+        &fragment_shader,
+        &heightmap,
+        settings,
+    )?;
+
+    // Without the feature there's nothing to draw it with.
+    let overlay = if cfg!(feature = "overlay") {
+        let vertex_shader = windowed.compile("overlay.vert")?;
+        let fragment_shader = windowed.compile("overlay.frag")?;
+
+        Some(Overlay::new(
+            windowed.render_pass.clone(),
+            &windowed.shader_cache,
+            &vertex_shader,
+            &fragment_shader,
+            1024,
+        )?)
+    } else {
+        None
+    };
+    let mut clock = FrameClock::new(120);
+    let mut batch = OverlayBatch::new();
+    let max_chunks = 4u64.pow(settings.max_depth);
+
+    let sun_direction = [0.4, 0.8, 0.3];
+    let mut chunks = Vec::new();
+    let start = Instant::now();
+    let mut last_report = start;
+
+    while !windowed.window.should_close() {
+        windowed.poll_events();
+
+        let angle = start.elapsed().as_secs_f32() * 0.1;
+        let mut camera = Camera::new(
+            glm::vec3(angle.sin() * 160.0, 220.0, angle.cos() * 160.0),
+            glm::vec3(angle.sin() * 40.0, 0.0, angle.cos() * 40.0),
+        );
+        camera.near = 1.0;
+        camera.far = 2000.0;
+
+        let view_projection = camera
+            .view_projection_for(
+                windowed.logical_device.clip_space_y(),
+                windowed.aspect_ratio(),
+            )
+            .into();
+
+        terrain.select(camera.position.into(), &view_projection, &mut chunks);
+
+        clock.tick();
+        batch.clear();
+
+        if overlay.is_some() {
+            let mut column = OverlayBatch::column(8.0, 8.0, 240.0);
+
+            FrameTimeGraph::from_clock(&clock).draw(&mut batch, column.next(60.0));
+            MemoryUsageBars::from_device(&windowed.logical_device)
+                .draw(&mut batch, column.next(24.0));
+            CounterBars::new([(chunks.len() as u64, max_chunks)])
+                .draw(&mut batch, column.next(12.0));
+        }
+
+        windowed.draw_frame(|frame| {
+            frame.begin_render_pass(Color::linear(0.5, 0.7, 1.0, 1.0));
+            terrain.record(
+                frame.command_buffer,
+                view_projection,
+                sun_direction,
+                &chunks,
+            );
+
+            if let Some(overlay) = &overlay {
+                overlay.record(frame.command_buffer, frame.index, &batch);
+            }
+
+            frame.end_render_pass();
+
+            Ok(())
+        })?;
+
+        if last_report.elapsed() >= Duration::from_secs(1) {
+            println!("{} chunks drawn", terrain.chunks_drawn());
+            last_report = Instant::now();
+        }
+    }
+
+    windowed.wait_idle()?;
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+//! Opens a window where it was closed the last time, through the api2 wrappers.
+//!
+//! The placement is saved to `window-state.txt` in the working directory on exit, and restored
+//! on the next run unless the monitor it was on is gone.
+
+use std::{error::Error, rc::Rc};
+
+use learnvulkan::{
+    instance::InstanceBuilder,
+    window::{GlfwEntry, WindowState},
+};
+
+const STATE_PATH: &str = "window-state.txt";
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut entry = GlfwEntry::new()?;
+    let extensions = entry
+        .required_extensions()
+        .ok_or("GLFW found no Vulkan loader for window surfaces")?;
+
+    let instance = Rc::new(
+        InstanceBuilder::default()
+            .application_name("window_state")
+            .extensions(extensions)
+            .build()?,
+    );
+
+    let state = match WindowState::load(STATE_PATH) {
+        Ok(state) => state,
+        Err(e) => {
+            println!("starting from the default placement: {}", e);
+
+            WindowState {
+                position: (100, 100),
+                size: (800, 600),
+                maximized: false,
+                monitor: None,
+            }
+        }
+    };
+
+    let mut window = entry.create_window_with_state(instance, "Window state", &state)?;
+    window.window.set_key_polling(true);
+
+    while !window.window.should_close() {
+        entry.glfw.wait_events();
+
+        for (_, event) in glfw::flush_messages(&window.events) {
+            if let glfw::WindowEvent::Key(glfw::Key::Escape, _, glfw::Action::Press, _) = event {
+                window.window.set_should_close(true);
+            }
+        }
+    }
+
+    let state = window.state(&entry.monitors());
+    state.save(STATE_PATH)?;
+    println!("saved {:?} to {}", state, STATE_PATH);
+
+    Ok(())
+}
@@ -0,0 +1,108 @@
+#ifndef LEARNVULKAN_H
+#define LEARNVULKAN_H
+
+/* Generated with cbindgen, do not edit. */
+
+#include <stdint.h>
+
+// The windowing system a LvNativeWindow comes from.
+typedef enum LvWindowKind {
+  LvWindowKind_Xlib = 0,
+  LvWindowKind_Wayland = 1,
+  LvWindowKind_Win32 = 2,
+} LvWindowKind;
+
+// The outcome of every `lv_*` call.
+typedef enum LvResult {
+  LvResult_Success = 0,
+  // A required pointer was null.
+  LvResult_InvalidArgument = 1,
+  // A Vulkan call failed, the reason is printed to stderr.
+  LvResult_Failed = 2,
+  // The renderer panicked, it must not be used again except to destroy it.
+  LvResult_Panicked = 3,
+} LvResult;
+
+// An opaque renderer handle.
+typedef struct LvRenderer LvRenderer;
+
+// A window created by the host.
+typedef struct LvNativeWindow {
+  LvWindowKind kind;
+  // `Display*` for Xlib, `wl_display*` for Wayland and `HINSTANCE` for Win32.
+  void *display;
+  // `Window` for Xlib, `wl_surface*` for Wayland and `HWND` for Win32.
+  void *window;
+  // Framebuffer width in pixels.
+  uint32_t width;
+  // Framebuffer height in pixels.
+  uint32_t height;
+} LvNativeWindow;
+
+// How a present mode did in lv_renderer_benchmark_present_modes.
+typedef struct LvPresentModeResult {
+  // The `VkPresentModeKHR` value.
+  int32_t present_mode;
+  uint32_t frames;
+  double fps;
+  double mean_frame_time_ms;
+  // How much frame times jitter, in ms².
+  double frame_time_variance_ms2;
+  double estimated_latency_ms;
+} LvPresentModeResult;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates a renderer presenting to `window` and stores it in `renderer`.
+//
+// # Safety
+//
+// `window` must point to a valid LvNativeWindow whose handles outlive the renderer, and
+// `renderer` must be valid for writes.
+LvResult lv_renderer_create(const LvNativeWindow *window, LvRenderer **renderer);
+
+// Tells the renderer the window's framebuffer is now `width` by `height` pixels. A zero size
+// pauses rendering until the next resize.
+//
+// # Safety
+//
+// `renderer` must come from lv_renderer_create and not be destroyed yet.
+LvResult lv_renderer_resize(LvRenderer *renderer, uint32_t width, uint32_t height);
+
+// Renders and presents one frame. Does nothing while the window has no area, e.g. when it's
+// minimized.
+//
+// # Safety
+//
+// `renderer` must come from lv_renderer_create and not be destroyed yet.
+LvResult lv_renderer_draw_frame(LvRenderer *renderer);
+
+// Renders with every supported present mode for `seconds_per_mode` each, blocking the caller,
+// and writes up to `capacity` results to `results`. `count` receives the number of modes
+// measured, which may exceed `capacity`.
+//
+// # Safety
+//
+// `renderer` must come from lv_renderer_create and not be destroyed yet, `results` must be
+// valid for `capacity` writes, or null if `capacity` is zero, and `count` must be valid for
+// writes.
+LvResult lv_renderer_benchmark_present_modes(LvRenderer *renderer,
+                                             double seconds_per_mode,
+                                             LvPresentModeResult *results,
+                                             uintptr_t capacity,
+                                             uintptr_t *count);
+
+// Waits for the GPU and destroys the renderer. Passing null does nothing.
+//
+// # Safety
+//
+// `renderer` must be null or come from lv_renderer_create and not be destroyed yet.
+void lv_renderer_destroy(LvRenderer *renderer);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LEARNVULKAN_H */
@@ -0,0 +1,13 @@
+// Layout of the buffer the auto-exposure pass writes, shared with the passes reading it.
+
+#define HISTOGRAM_BINS 256
+
+#ifndef EXPOSURE_DATA_QUALIFIER
+#define EXPOSURE_DATA_QUALIFIER
+#endif
+
+layout(set = EXPOSURE_SET, binding = EXPOSURE_BINDING) EXPOSURE_DATA_QUALIFIER buffer ExposureData {
+    uint histogram[HISTOGRAM_BINS];
+    float averageLuminance;
+    float exposure;
+} exposureData;
@@ -0,0 +1,10 @@
+// Push constants of the auto-exposure compute shaders, mirrors `ExposurePushConstants`.
+
+layout(push_constant) uniform ExposureParams {
+    float minLogLuminance;
+    float inverseLogLuminanceRange;
+    float logLuminanceRange;
+    float adaptation;
+    uint pixelCount;
+    float key;
+} params;
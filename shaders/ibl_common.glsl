@@ -0,0 +1,63 @@
+// Helpers shared by the image-based lighting precompute shaders.
+
+#define PI 3.14159265359
+
+// Push constants of the IBL shaders, mirrors `IblPushConstants`.
+layout(push_constant) uniform IblParams {
+    float roughness;
+    float environmentSize;
+    uint sampleCount;
+} params;
+
+// The direction through the center of `pixel` on cube face `face` of a `size` sized face, with
+// the faces in Vulkan's +X, -X, +Y, -Y, +Z, -Z order.
+vec3 cubeDirection(uvec2 pixel, uint face, uint size) {
+    vec2 uv = (vec2(pixel) + 0.5) / float(size) * 2.0 - 1.0;
+
+    vec3 direction;
+    switch (face) {
+        case 0: direction = vec3(1.0, -uv.y, -uv.x); break;
+        case 1: direction = vec3(-1.0, -uv.y, uv.x); break;
+        case 2: direction = vec3(uv.x, 1.0, uv.y); break;
+        case 3: direction = vec3(uv.x, -1.0, -uv.y); break;
+        case 4: direction = vec3(uv.x, -uv.y, 1.0); break;
+        default: direction = vec3(-uv.x, -uv.y, -1.0); break;
+    }
+
+    return normalize(direction);
+}
+
+// The i-th of n points of the Hammersley sequence, well spread over the unit square.
+vec2 hammersley(uint i, uint n) {
+    uint bits = i;
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+
+    return vec2(float(i) / float(n), float(bits) * 2.3283064365386963e-10);
+}
+
+// A half vector around `normal` distributed like the GGX normal distribution for `roughness`.
+vec3 importanceSampleGgx(vec2 xi, vec3 normal, float roughness) {
+    float a = roughness * roughness;
+
+    float phi = 2.0 * PI * xi.x;
+    float cosTheta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sinTheta = sqrt(1.0 - cosTheta * cosTheta);
+
+    vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, normal));
+    vec3 bitangent = cross(normal, tangent);
+
+    return normalize(tangent * (cos(phi) * sinTheta) + bitangent * (sin(phi) * sinTheta) + normal * cosTheta);
+}
+
+float distributionGgx(float nDotH, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denominator = nDotH * nDotH * (a2 - 1.0) + 1.0;
+
+    return a2 / (PI * denominator * denominator);
+}
@@ -0,0 +1,77 @@
+// The light list uploaded by `LightBuffers` and the clusters `light_cull.comp` sorts it into.
+// Define LIGHTS_SET, LIGHTS_BINDING and CLUSTERS_BINDING before including.
+
+#define LIGHT_DIRECTIONAL 0
+#define LIGHT_POINT 1
+#define LIGHT_SPOT 2
+
+// The froxel grid, matches `CLUSTER_GRID` and `MAX_LIGHTS_PER_CLUSTER`.
+#define CLUSTER_X 16
+#define CLUSTER_Y 9
+#define CLUSTER_Z 24
+#define CLUSTER_COUNT (CLUSTER_X * CLUSTER_Y * CLUSTER_Z)
+#define MAX_LIGHTS_PER_CLUSTER 32
+
+#ifndef CLUSTERS_QUALIFIER
+#define CLUSTERS_QUALIFIER readonly
+#endif
+
+// Mirrors `GpuLight`.
+struct Light {
+    // xyz is the world position, w the distance at which the light fades out.
+    vec4 positionRange;
+    // xyz is the direction the light travels in, w the LIGHT_* kind.
+    vec4 directionKind;
+    // rgb is the linear color, w the intensity.
+    vec4 colorIntensity;
+    // x is the cosine of the outer cone angle, y one over the cosine difference to the inner one.
+    vec4 spotCosines;
+};
+
+layout(std430, set = LIGHTS_SET, binding = LIGHTS_BINDING) readonly buffer Lights {
+    uint lightCount;
+    Light lights[];
+} lightList;
+
+layout(std430, set = LIGHTS_SET, binding = CLUSTERS_BINDING) CLUSTERS_QUALIFIER buffer LightClusters {
+    uint lightCounts[CLUSTER_COUNT];
+    uint lightIndices[CLUSTER_COUNT * MAX_LIGHTS_PER_CLUSTER];
+} clusters;
+
+// The depth slice of a view-space distance, sliced exponentially between near and far.
+uint clusterSlice(float viewDepth, float near, float far) {
+    float slice = log(viewDepth / near) / log(far / near) * float(CLUSTER_Z);
+    return uint(clamp(slice, 0.0, float(CLUSTER_Z - 1)));
+}
+
+uint clusterIndex(uvec3 cluster) {
+    return cluster.x + cluster.y * CLUSTER_X + cluster.z * CLUSTER_X * CLUSTER_Y;
+}
+
+// The radiance `light` sends towards `worldPosition` and the direction to it in `toLight`.
+vec3 lightRadiance(Light light, vec3 worldPosition, out vec3 toLight) {
+    vec3 radiance = light.colorIntensity.rgb * light.colorIntensity.w;
+    uint kind = uint(light.directionKind.w);
+
+    if (kind == LIGHT_DIRECTIONAL) {
+        toLight = normalize(-light.directionKind.xyz);
+        return radiance;
+    }
+
+    vec3 offset = light.positionRange.xyz - worldPosition;
+    float distanceSquared = max(dot(offset, offset), 0.0001);
+    toLight = offset * inversesqrt(distanceSquared);
+
+    // Inverse-square falloff, windowed to reach zero at the range.
+    float range = light.positionRange.w;
+    float window = clamp(1.0 - pow(distanceSquared / (range * range), 2.0), 0.0, 1.0);
+    radiance *= window * window / distanceSquared;
+
+    if (kind == LIGHT_SPOT) {
+        float cosAngle = dot(-toLight, normalize(light.directionKind.xyz));
+        float cone = clamp((cosAngle - light.spotCosines.x) * light.spotCosines.y, 0.0, 1.0);
+        radiance *= cone * cone;
+    }
+
+    return radiance;
+}
@@ -0,0 +1,16 @@
+#version 450
+
+layout(push_constant) uniform Screen {
+    // Maps pixels to clip space: xy scale, zw offset.
+    vec4 pixelToClip;
+} screen;
+
+layout(location = 0) in vec2 inPosition;
+layout(location = 1) in vec4 inColor;
+
+layout(location = 0) out vec4 fragColor;
+
+void main() {
+    gl_Position = vec4(inPosition * screen.pixelToClip.xy + screen.pixelToClip.zw, 0.0, 1.0);
+    fragColor = inColor;
+}
@@ -0,0 +1,94 @@
+#version 450
+
+// Forward PBR shading of the metallic-roughness model with the light list and image-based ambient
+// light. Writes linear HDR radiance for a later tonemap pass.
+//
+// Compiled with CLUSTERED_LIGHTS defined, only the lights `light_cull.comp` put in the fragment's
+// cluster are shaded instead of every light.
+
+#include "pbr_frame.glsl"
+#include "pbr_brdf.glsl"
+
+#define LIGHTS_SET 0
+#define LIGHTS_BINDING 4
+#define CLUSTERS_BINDING 5
+#include "lights.glsl"
+
+layout(set = 0, binding = 1) uniform samplerCube irradianceMap;
+layout(set = 0, binding = 2) uniform samplerCube prefilteredMap;
+layout(set = 0, binding = 3) uniform sampler2D brdfLut;
+
+layout(set = 1, binding = 0) uniform sampler2D albedoMap;
+layout(set = 1, binding = 1) uniform sampler2D normalMap;
+// Roughness in green and metallic in blue, like glTF.
+layout(set = 1, binding = 2) uniform sampler2D metallicRoughnessMap;
+layout(set = 1, binding = 3) uniform sampler2D occlusionMap;
+
+layout(location = 0) in vec3 worldPosition;
+layout(location = 1) in vec3 worldNormal;
+layout(location = 2) in vec4 worldTangent;
+layout(location = 3) in vec2 uv;
+
+layout(location = 0) out vec4 outColor;
+
+vec3 surfaceNormal() {
+    vec3 normal = normalize(worldNormal);
+    vec3 tangent = normalize(worldTangent.xyz - dot(worldTangent.xyz, normal) * normal);
+    vec3 bitangent = cross(normal, tangent) * worldTangent.w;
+
+    vec3 tangentNormal = texture(normalMap, uv).xyz * 2.0 - 1.0;
+    tangentNormal.xy *= draw.normalScale;
+
+    return normalize(mat3(tangent, bitangent, normal) * tangentNormal);
+}
+
+void main() {
+    vec4 baseColor = texture(albedoMap, uv) * draw.baseColorFactor;
+    vec4 metallicRoughness = texture(metallicRoughnessMap, uv);
+    float metallic = metallicRoughness.b * draw.metallicFactor;
+    float roughness = clamp(metallicRoughness.g * draw.roughnessFactor, 0.04, 1.0);
+    float occlusion = mix(1.0, texture(occlusionMap, uv).r, draw.occlusionStrength);
+
+    vec3 albedo = baseColor.rgb;
+    vec3 normal = surfaceNormal();
+    vec3 view = normalize(frame.cameraPosition.xyz - worldPosition);
+    vec3 f0 = mix(vec3(0.04), albedo, metallic);
+
+    vec3 color = vec3(0.0);
+
+#ifdef CLUSTERED_LIGHTS
+    float viewDepth = -(frame.view * vec4(worldPosition, 1.0)).z;
+    uvec2 tile = uvec2(gl_FragCoord.xy / frame.clusterParams.zw * vec2(CLUSTER_X, CLUSTER_Y));
+    uvec3 cluster = uvec3(min(tile, uvec2(CLUSTER_X - 1, CLUSTER_Y - 1)),
+        clusterSlice(viewDepth, frame.clusterParams.x, frame.clusterParams.y));
+    uint clusterStart = clusterIndex(cluster) * MAX_LIGHTS_PER_CLUSTER;
+    uint lightCount = clusters.lightCounts[clusterIndex(cluster)];
+#else
+    uint lightCount = lightList.lightCount;
+#endif
+
+    for (uint i = 0; i < lightCount; i++) {
+#ifdef CLUSTERED_LIGHTS
+        Light light = lightList.lights[clusters.lightIndices[clusterStart + i]];
+#else
+        Light light = lightList.lights[i];
+#endif
+
+        vec3 toLight;
+        vec3 radiance = lightRadiance(light, worldPosition, toLight);
+        color += shadeLight(normal, view, toLight, radiance, albedo, metallic, roughness, f0);
+    }
+
+    float nDotV = max(dot(normal, view), 0.0001);
+    vec3 fresnel = fresnelSchlickRoughness(nDotV, f0, roughness);
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * texture(irradianceMap, normal).rgb * albedo;
+
+    float lod = roughness * (frame.prefilteredMipLevels - 1.0);
+    vec3 prefiltered = textureLod(prefilteredMap, reflect(-view, normal), lod).rgb;
+    vec2 brdf = texture(brdfLut, vec2(nDotV, roughness)).rg;
+    vec3 specular = prefiltered * (fresnel * brdf.x + brdf.y);
+
+    color += (diffuse + specular) * occlusion;
+
+    outColor = vec4(color, baseColor.a);
+}
@@ -0,0 +1,44 @@
+// The Cook-Torrance specular BRDF with a GGX distribution, Smith-Schlick geometry term and
+// Schlick Fresnel.
+
+#define PI 3.14159265359
+
+float distributionGgx(float nDotH, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denominator = nDotH * nDotH * (a2 - 1.0) + 1.0;
+
+    return a2 / (PI * denominator * denominator);
+}
+
+float geometrySmith(float nDotV, float nDotL, float roughness) {
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+
+    return nDotV / (nDotV * (1.0 - k) + k) * nDotL / (nDotL * (1.0 - k) + k);
+}
+
+vec3 fresnelSchlick(float cosTheta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+// Fresnel for ambient light, which arrives from every direction, so rough surfaces reflect less
+// of it at grazing angles.
+vec3 fresnelSchlickRoughness(float cosTheta, vec3 f0, float roughness) {
+    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+// The light reflected towards `view` from light arriving along `light` with `radiance`.
+vec3 shadeLight(vec3 normal, vec3 view, vec3 light, vec3 radiance, vec3 albedo, float metallic, float roughness, vec3 f0) {
+    vec3 halfway = normalize(view + light);
+    float nDotV = max(dot(normal, view), 0.0001);
+    float nDotL = max(dot(normal, light), 0.0);
+
+    vec3 fresnel = fresnelSchlick(max(dot(halfway, view), 0.0), f0);
+    float distribution = distributionGgx(max(dot(normal, halfway), 0.0), roughness);
+    float geometry = geometrySmith(nDotV, nDotL, roughness);
+
+    vec3 specular = distribution * geometry * fresnel / (4.0 * nDotV * nDotL + 0.0001);
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * albedo / PI;
+
+    return (diffuse + specular) * radiance * nDotL;
+}
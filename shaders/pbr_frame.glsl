@@ -0,0 +1,23 @@
+// Per-frame data of the PBR shaders, mirrors `PbrFrameUniforms`.
+
+layout(set = 0, binding = 0) uniform PbrFrame {
+    mat4 viewProjection;
+    mat4 view;
+    vec4 cameraPosition;
+    // x and y are the near and far planes the light clusters span, z and w the viewport size.
+    vec4 clusterParams;
+    float prefilteredMipLevels;
+} frame;
+
+// Per-draw data, mirrors `PbrPushConstants`.
+layout(push_constant) uniform PbrDraw {
+    // Assumed to scale uniformly, so it also transforms normals.
+    mat4 model;
+    vec4 baseColorFactor;
+    float metallicFactor;
+    float roughnessFactor;
+    float occlusionStrength;
+    float normalScale;
+    // The first matrix of the skeleton in the joint palette, unused by static meshes.
+    uint firstJoint;
+} draw;
@@ -0,0 +1,30 @@
+#version 450
+
+#include "pbr_frame.glsl"
+#include "skinning.glsl"
+
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inNormal;
+layout(location = 2) in vec4 inTangent;
+layout(location = 3) in vec2 inUv;
+layout(location = 4) in uvec4 inJoints;
+layout(location = 5) in vec4 inWeights;
+
+layout(location = 0) out vec3 worldPosition;
+layout(location = 1) out vec3 worldNormal;
+layout(location = 2) out vec4 worldTangent;
+layout(location = 3) out vec2 uv;
+
+void main() {
+    // Joints are assumed to scale uniformly, like the model matrix.
+    mat4 skinnedModel = draw.model * skinMatrix(inJoints, inWeights);
+    vec4 position = skinnedModel * vec4(inPosition, 1.0);
+    mat3 normalMatrix = mat3(skinnedModel);
+
+    worldPosition = position.xyz;
+    worldNormal = normalMatrix * inNormal;
+    worldTangent = vec4(normalMatrix * inTangent.xyz, inTangent.w);
+    uv = inUv;
+
+    gl_Position = frame.viewProjection * position;
+}
@@ -0,0 +1,11 @@
+#version 450
+
+// Writes the ID of the object covering each pixel into the R32_UINT picking attachment.
+
+#include "picking_draw.glsl"
+
+layout(location = 0) out uint outObjectId;
+
+void main() {
+    outObjectId = draw.objectId;
+}
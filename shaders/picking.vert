@@ -0,0 +1,11 @@
+#version 450
+
+// Only the position is read, which has to come first in the vertex.
+
+#include "picking_draw.glsl"
+
+layout(location = 0) in vec3 inPosition;
+
+void main() {
+    gl_Position = draw.modelViewProjection * vec4(inPosition, 1.0);
+}
@@ -0,0 +1,7 @@
+// Push constants of the picking shaders, mirrors `PickingPushConstants`.
+
+layout(push_constant) uniform PickingDraw {
+    mat4 modelViewProjection;
+    // 0 is left for the background.
+    uint objectId;
+} draw;
@@ -0,0 +1,21 @@
+// The interface of procedural texture generators, see `ProceduralTextureGenerator`.
+//
+// Define TEXTURE_FORMAT before including to write something other than rgba8, matching the
+// storage format of the texture.
+
+#ifndef TEXTURE_FORMAT
+#define TEXTURE_FORMAT rgba8
+#endif
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0, TEXTURE_FORMAT) uniform writeonly image2D outputTexture;
+
+// The texel this invocation writes and its center in 0..1, or false past the texture's edges.
+bool currentTexel(out ivec2 texel, out vec2 uv) {
+    ivec2 size = imageSize(outputTexture);
+    texel = ivec2(gl_GlobalInvocationID.xy);
+    uv = (vec2(texel) + 0.5) / vec2(size);
+
+    return all(lessThan(texel, size));
+}
@@ -0,0 +1,13 @@
+// The joint matrices of every skinned draw of the frame, mirrors `JointPalettes`.
+
+layout(std430, set = 0, binding = 6) readonly buffer JointPalette {
+    mat4 joints[];
+} palette;
+
+// Blends the matrices of up to four joints, offset by the draw's first joint.
+mat4 skinMatrix(uvec4 joints, vec4 weights) {
+    return weights.x * palette.joints[draw.firstJoint + joints.x]
+        + weights.y * palette.joints[draw.firstJoint + joints.y]
+        + weights.z * palette.joints[draw.firstJoint + joints.z]
+        + weights.w * palette.joints[draw.firstJoint + joints.w];
+}
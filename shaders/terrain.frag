@@ -0,0 +1,24 @@
+#version 450
+
+#include "terrain_common.glsl"
+
+layout(location = 0) in vec3 worldNormal;
+layout(location = 1) in float height;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    vec3 normal = normalize(worldNormal);
+
+    // Grass in the valleys, rock on steep slopes and up high, snow on the peaks.
+    vec3 grass = vec3(0.13, 0.28, 0.07);
+    vec3 rock = vec3(0.30, 0.27, 0.24);
+    vec3 snow = vec3(0.90, 0.92, 0.95);
+
+    float steepness = 1.0 - normal.y;
+    vec3 albedo = mix(grass, rock, smoothstep(0.2, 0.4, max(steepness, height - 0.3)));
+    albedo = mix(albedo, snow, smoothstep(0.75, 0.85, height) * smoothstep(0.5, 0.2, steepness));
+
+    float diffuse = max(dot(normal, normalize(draw.sunDirection.xyz)), 0.0);
+    outColor = vec4(albedo * (0.15 + 0.85 * diffuse), 1.0);
+}
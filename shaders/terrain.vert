@@ -0,0 +1,30 @@
+#version 450
+
+#include "terrain_common.glsl"
+
+// xy is the position in the chunk from 0 to 1, z is 1 on the skirts.
+layout(location = 0) in vec3 inPatch;
+
+layout(location = 0) out vec3 worldNormal;
+layout(location = 1) out float height;
+
+void main() {
+    float size = draw.terrain.x;
+    float heightScale = draw.terrain.y;
+
+    vec2 xz = draw.chunk.xy + inPatch.xy * draw.chunk.z;
+    vec2 uv = (xz + 0.5 * size) / size;
+    vec2 texel = 1.0 / (draw.terrain.zw - 1.0);
+
+    height = terrainHeight(uv);
+
+    // Central differences of the heightmap, scaled to world units.
+    float dx = (terrainHeight(uv + vec2(texel.x, 0.0)) - terrainHeight(uv - vec2(texel.x, 0.0)))
+        * heightScale / (2.0 * texel.x * size);
+    float dz = (terrainHeight(uv + vec2(0.0, texel.y)) - terrainHeight(uv - vec2(0.0, texel.y)))
+        * heightScale / (2.0 * texel.y * size);
+    worldNormal = normalize(vec3(-dx, 1.0, -dz));
+
+    vec3 position = vec3(xz.x, height * heightScale - inPatch.z * draw.chunk.w, xz.y);
+    gl_Position = draw.viewProjection * vec4(position, 1.0);
+}
@@ -0,0 +1,33 @@
+// Per-chunk data of the terrain shaders, mirrors `TerrainPushConstants`.
+layout(push_constant) uniform TerrainDraw {
+    mat4 viewProjection;
+    // xy is the chunk's corner on the XZ plane, z its size and w how far the skirts reach down.
+    vec4 chunk;
+    // x is the terrain's size, y its height scale, zw the heightmap's width and height.
+    vec4 terrain;
+    // xyz points towards the sun.
+    vec4 sunDirection;
+} draw;
+
+// The heightmap's samples row by row, mirrors `Heightmap`.
+layout(std430, set = 0, binding = 0) readonly buffer Heights {
+    float samples[];
+} heights;
+
+float texelHeight(ivec2 texel) {
+    ivec2 size = ivec2(draw.terrain.zw);
+    texel = clamp(texel, ivec2(0), size - 1);
+    return heights.samples[texel.y * size.x + texel.x];
+}
+
+// Bilinearly filters the heightmap at `uv`, from 0 to 1 across the terrain.
+float terrainHeight(vec2 uv) {
+    vec2 position = clamp(uv, 0.0, 1.0) * (draw.terrain.zw - 1.0);
+    ivec2 texel = ivec2(floor(position));
+    vec2 f = position - vec2(texel);
+
+    return mix(
+        mix(texelHeight(texel), texelHeight(texel + ivec2(1, 0)), f.x),
+        mix(texelHeight(texel + ivec2(0, 1)), texelHeight(texel + ivec2(1, 1)), f.x),
+        f.y);
+}
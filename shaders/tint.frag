@@ -0,0 +1,11 @@
+#version 450
+
+// Fills the object with its tint.
+
+#include "tint_draw.glsl"
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    outColor = draw.tint;
+}
@@ -0,0 +1,14 @@
+#version 450
+
+// Reads the position and normal of a `PbrVertex`, the outline draw pushes the surface out along
+// the normal and only keeps the back faces, leaving a rim around the object.
+
+#include "tint_draw.glsl"
+
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inNormal;
+
+void main() {
+    vec3 position = inPosition + normalize(inNormal) * draw.outlineWidth;
+    gl_Position = draw.modelViewProjection * vec4(position, 1.0);
+}
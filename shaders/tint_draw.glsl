@@ -0,0 +1,10 @@
+// Push constants of the tint shaders, mirrors `TintPushConstants`.
+
+layout(push_constant) uniform TintDraw {
+    mat4 modelViewProjection;
+    // Linear RGBA.
+    vec4 tint;
+    // How far vertices are pushed out along their normal, in object space. Only the outline
+    // draw sets it.
+    float outlineWidth;
+} draw;
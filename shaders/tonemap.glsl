@@ -0,0 +1,14 @@
+// Tonemapping for the pass resolving the HDR target, using the exposure written by the
+// auto-exposure pass. Define EXPOSURE_SET and EXPOSURE_BINDING before including.
+
+#define EXPOSURE_DATA_QUALIFIER readonly
+#include "exposure_data.glsl"
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve.
+vec3 acesFilmic(vec3 color) {
+    return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+}
+
+vec3 tonemap(vec3 hdr) {
+    return acesFilmic(hdr * exposureData.exposure);
+}
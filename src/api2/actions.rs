@@ -0,0 +1,525 @@
+//! Named actions on top of raw input, so code asks whether "move_forward" is held instead of
+//! checking for a key, and players can rebind them.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    error, fmt, fs, io,
+    path::Path,
+    str::FromStr,
+};
+
+use glfw::{Action, Key, MouseButton, WindowEvent};
+
+/// An axis of the scroll wheel or the cursor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum InputAxis {
+    X,
+    Y,
+}
+
+/// An input an action can be bound to.
+///
+/// Bindings are written as text, e.g. `key W`, `mouse Button1`, `keys S W`, `scroll y` or
+/// `cursor x`, keys and buttons named like the variants of [Key] and [MouseButton].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "String", try_from = "String")
+)]
+pub enum Binding {
+    Key(Key),
+    MouseButton(MouseButton),
+    /// -1 while `negative` is held, 1 while `positive` is, and 0 for both or neither.
+    KeyAxis {
+        negative: Key,
+        positive: Key,
+    },
+    /// How far the wheel scrolled this frame.
+    Scroll(InputAxis),
+    /// How far the cursor moved this frame, in screen coordinates.
+    Cursor(InputAxis),
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "key {:?}", key),
+            Self::MouseButton(button) => write!(f, "mouse {:?}", button),
+            Self::KeyAxis { negative, positive } => write!(f, "keys {:?} {:?}", negative, positive),
+            Self::Scroll(axis) => write!(f, "scroll {}", axis_name(*axis)),
+            Self::Cursor(axis) => write!(f, "cursor {}", axis_name(*axis)),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = BindingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+
+        match words.as_slice() {
+            ["key", key] => Ok(Self::Key(parse_key(key)?)),
+            ["mouse", button] => Ok(Self::MouseButton(parse_mouse_button(button)?)),
+            ["keys", negative, positive] => Ok(Self::KeyAxis {
+                negative: parse_key(negative)?,
+                positive: parse_key(positive)?,
+            }),
+            ["scroll", axis] => Ok(Self::Scroll(parse_axis(axis)?)),
+            ["cursor", axis] => Ok(Self::Cursor(parse_axis(axis)?)),
+            _ => Err(BindingError::Malformed),
+        }
+    }
+}
+
+impl From<Binding> for String {
+    fn from(value: Binding) -> Self {
+        value.to_string()
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = BindingError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// The bindings of every action, saved per player or per device.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BindingProfile {
+    /// The bindings of each action by name. An action is active when any of its bindings is.
+    pub bindings: BTreeMap<String, Vec<Binding>>,
+}
+
+impl BindingProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a binding to `action`, keeping its others.
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let bindings = self.bindings.entry(action.to_owned()).or_default();
+
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Replaces every binding of `action` with `binding`.
+    pub fn rebind(&mut self, action: &str, binding: Binding) {
+        self.bindings.insert(action.to_owned(), vec![binding]);
+    }
+
+    /// Removes every binding of `action`.
+    pub fn unbind(&mut self, action: &str) {
+        self.bindings.remove(action);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.bindings.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// The actions `binding` is bound to, to warn about conflicts when rebinding.
+    pub fn actions_bound_to(&self, binding: Binding) -> impl Iterator<Item = &str> {
+        self.bindings
+            .iter()
+            .filter(move |(_, bindings)| bindings.contains(&binding))
+            .map(|(action, _)| action.as_str())
+    }
+
+    /// Writes the profile to a file, one binding per line.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), BindingError> {
+        fs::write(path, self.to_string()).map_err(BindingError::from)
+    }
+
+    /// Reads a profile from a file written by [`BindingProfile::save`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, BindingError> {
+        fs::read_to_string(path)
+            .map_err(BindingError::from)?
+            .parse()
+    }
+}
+
+impl fmt::Display for BindingProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (action, bindings) in &self.bindings {
+            for binding in bindings {
+                writeln!(f, "{} {}", action, binding)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for BindingProfile {
+    type Err = BindingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut profile = Self::new();
+
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let (action, binding) = line.trim().split_once(' ').ok_or(BindingError::Malformed)?;
+
+            profile.bind(action, binding.parse()?);
+        }
+
+        Ok(profile)
+    }
+}
+
+/// The state of every action of a [BindingProfile], updated from window events.
+///
+/// Call [ActionMap::begin_frame] before handing it the frame's events with
+/// [ActionMap::handle_glfw_event], then query the actions.
+#[derive(Debug, Clone)]
+pub struct ActionMap {
+    profile: BindingProfile,
+    keys: HashSet<Key>,
+    buttons: HashSet<MouseButton>,
+    scroll: (f64, f64),
+    cursor: Option<(f64, f64)>,
+    cursor_delta: (f64, f64),
+    /// The actions that were down at the start of the frame.
+    previous: HashSet<String>,
+    /// The action the next key or button press gets bound to.
+    capturing: Option<String>,
+}
+
+impl ActionMap {
+    pub fn new(profile: BindingProfile) -> Self {
+        Self {
+            profile,
+            keys: HashSet::new(),
+            buttons: HashSet::new(),
+            scroll: (0.0, 0.0),
+            cursor: None,
+            cursor_delta: (0.0, 0.0),
+            previous: HashSet::new(),
+            capturing: None,
+        }
+    }
+
+    pub fn profile(&self) -> &BindingProfile {
+        &self.profile
+    }
+
+    /// The profile, to change bindings at runtime. Changes apply right away.
+    pub fn profile_mut(&mut self) -> &mut BindingProfile {
+        &mut self.profile
+    }
+
+    pub fn set_profile(&mut self, profile: BindingProfile) {
+        self.profile = profile;
+    }
+
+    /// Binds `action` to the next key or mouse button pressed, replacing its bindings. That
+    /// press doesn't trigger any action.
+    pub fn capture_next(&mut self, action: &str) {
+        self.capturing = Some(action.to_owned());
+    }
+
+    /// The action waiting for [ActionMap::capture_next] to bind it.
+    pub fn capturing(&self) -> Option<&str> {
+        self.capturing.as_deref()
+    }
+
+    pub fn cancel_capture(&mut self) {
+        self.capturing = None;
+    }
+
+    /// Starts a new frame, resetting what only lasts a frame: presses, releases and the scroll
+    /// and cursor movement.
+    pub fn begin_frame(&mut self) {
+        self.previous = self
+            .profile
+            .bindings
+            .keys()
+            .filter(|action| self.is_down(action))
+            .cloned()
+            .collect();
+
+        self.scroll = (0.0, 0.0);
+        self.cursor_delta = (0.0, 0.0);
+    }
+
+    pub fn handle_glfw_event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::Key(key, _, action, _) => match action {
+                Action::Press if self.capturing.is_some() => {
+                    if let Some(name) = self.capturing.take() {
+                        self.profile.rebind(&name, Binding::Key(key));
+                    }
+                }
+                Action::Press | Action::Repeat => {
+                    self.keys.insert(key);
+                }
+                Action::Release => {
+                    self.keys.remove(&key);
+                }
+            },
+            WindowEvent::MouseButton(button, action, _) => match action {
+                Action::Press if self.capturing.is_some() => {
+                    if let Some(name) = self.capturing.take() {
+                        self.profile.rebind(&name, Binding::MouseButton(button));
+                    }
+                }
+                Action::Press | Action::Repeat => {
+                    self.buttons.insert(button);
+                }
+                Action::Release => {
+                    self.buttons.remove(&button);
+                }
+            },
+            WindowEvent::Scroll(x, y) => {
+                self.scroll.0 += x;
+                self.scroll.1 += y;
+            }
+            WindowEvent::CursorPos(x, y) => {
+                if let Some((last_x, last_y)) = self.cursor {
+                    self.cursor_delta.0 += x - last_x;
+                    self.cursor_delta.1 += y - last_y;
+                }
+
+                self.cursor = Some((x, y));
+            }
+            // Keys released while unfocused never send their release.
+            WindowEvent::Focus(false) => {
+                self.keys.clear();
+                self.buttons.clear();
+            }
+            _ => {}
+        }
+    }
+
+    /// The strongest value of the action's bindings: 1 for held keys and buttons, -1 to 1 for
+    /// key axes, and the scroll or cursor movement of this frame.
+    pub fn value(&self, action: &str) -> f32 {
+        self.profile
+            .bindings(action)
+            .iter()
+            .map(|binding| self.binding_value(*binding))
+            .fold(0.0, |strongest, value| {
+                if value.abs() > strongest.abs() {
+                    value
+                } else {
+                    strongest
+                }
+            })
+    }
+
+    /// Whether any of the action's bindings is active.
+    pub fn is_down(&self, action: &str) -> bool {
+        self.value(action) != 0.0
+    }
+
+    /// Whether the action became active this frame.
+    pub fn was_pressed(&self, action: &str) -> bool {
+        self.is_down(action) && !self.previous.contains(action)
+    }
+
+    /// Whether the action stopped being active this frame.
+    pub fn was_released(&self, action: &str) -> bool {
+        !self.is_down(action) && self.previous.contains(action)
+    }
+
+    fn binding_value(&self, binding: Binding) -> f32 {
+        let held = |key| self.keys.contains(&key) as i32 as f32;
+
+        match binding {
+            Binding::Key(key) => held(key),
+            Binding::MouseButton(button) => self.buttons.contains(&button) as i32 as f32,
+            Binding::KeyAxis { negative, positive } => held(positive) - held(negative),
+            Binding::Scroll(InputAxis::X) => self.scroll.0 as f32,
+            Binding::Scroll(InputAxis::Y) => self.scroll.1 as f32,
+            Binding::Cursor(InputAxis::X) => self.cursor_delta.0 as f32,
+            Binding::Cursor(InputAxis::Y) => self.cursor_delta.1 as f32,
+        }
+    }
+}
+
+fn axis_name(axis: InputAxis) -> &'static str {
+    match axis {
+        InputAxis::X => "x",
+        InputAxis::Y => "y",
+    }
+}
+
+fn parse_axis(name: &str) -> Result<InputAxis, BindingError> {
+    match name {
+        "x" => Ok(InputAxis::X),
+        "y" => Ok(InputAxis::Y),
+        _ => Err(BindingError::UnknownInput(name.to_owned())),
+    }
+}
+
+fn parse_key(name: &str) -> Result<Key, BindingError> {
+    KEYS.iter()
+        .copied()
+        .find(|key| format!("{:?}", key) == name)
+        .ok_or_else(|| BindingError::UnknownInput(name.to_owned()))
+}
+
+fn parse_mouse_button(name: &str) -> Result<MouseButton, BindingError> {
+    name.strip_prefix("Button")
+        .and_then(|number| number.parse::<i32>().ok())
+        .and_then(|number| MouseButton::from_i32(number - 1))
+        .ok_or_else(|| BindingError::UnknownInput(name.to_owned()))
+}
+
+// GLFW has no conversion from names or numbers to keys, so bindings are parsed against this.
+const KEYS: [Key; 120] = [
+    Key::Space,
+    Key::Apostrophe,
+    Key::Comma,
+    Key::Minus,
+    Key::Period,
+    Key::Slash,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::Semicolon,
+    Key::Equal,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::LeftBracket,
+    Key::Backslash,
+    Key::RightBracket,
+    Key::GraveAccent,
+    Key::World1,
+    Key::World2,
+    Key::Escape,
+    Key::Enter,
+    Key::Tab,
+    Key::Backspace,
+    Key::Insert,
+    Key::Delete,
+    Key::Right,
+    Key::Left,
+    Key::Down,
+    Key::Up,
+    Key::PageUp,
+    Key::PageDown,
+    Key::Home,
+    Key::End,
+    Key::CapsLock,
+    Key::ScrollLock,
+    Key::NumLock,
+    Key::PrintScreen,
+    Key::Pause,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::F13,
+    Key::F14,
+    Key::F15,
+    Key::F16,
+    Key::F17,
+    Key::F18,
+    Key::F19,
+    Key::F20,
+    Key::F21,
+    Key::F22,
+    Key::F23,
+    Key::F24,
+    Key::F25,
+    Key::Kp0,
+    Key::Kp1,
+    Key::Kp2,
+    Key::Kp3,
+    Key::Kp4,
+    Key::Kp5,
+    Key::Kp6,
+    Key::Kp7,
+    Key::Kp8,
+    Key::Kp9,
+    Key::KpDecimal,
+    Key::KpDivide,
+    Key::KpMultiply,
+    Key::KpSubtract,
+    Key::KpAdd,
+    Key::KpEnter,
+    Key::KpEqual,
+    Key::LeftShift,
+    Key::LeftControl,
+    Key::LeftAlt,
+    Key::LeftSuper,
+    Key::RightShift,
+    Key::RightControl,
+    Key::RightAlt,
+    Key::RightSuper,
+    Key::Menu,
+];
+
+/// Represents an error that occurred while parsing, saving or loading bindings.
+#[derive(Debug)]
+pub enum BindingError {
+    /// An I/O error occurred.
+    Io(io::Error),
+    /// A line was not in the expected format.
+    Malformed,
+    /// A key, mouse button or axis name isn't known.
+    UnknownInput(String),
+}
+
+impl From<io::Error> for BindingError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            Self::Malformed => write!(f, "malformed binding"),
+            Self::UnknownInput(name) => write!(f, "unknown input {}", name),
+        }
+    }
+}
+
+impl error::Error for BindingError {}
@@ -0,0 +1,396 @@
+//! Keyframe animation for values uploaded to the GPU every frame.
+
+use nalgebra_glm as glm;
+
+/// Values that can be interpolated between two keyframes.
+pub trait Interpolate: Clone {
+    /// Interpolates between `self` (at `t = 0`) and `other` (at `t = 1`).
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for glm::Vec2 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        glm::lerp(self, other, t)
+    }
+}
+
+impl Interpolate for glm::Vec3 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        glm::lerp(self, other, t)
+    }
+}
+
+impl Interpolate for glm::Vec4 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        glm::lerp(self, other, t)
+    }
+}
+
+impl Interpolate for glm::Quat {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        glm::quat_slerp(self, other, t)
+    }
+}
+
+/// Easing curves remapping the interpolation factor between two keyframes.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Easing {
+    /// Holds the start value until the next keyframe.
+    Step,
+    /// Constant speed.
+    #[default]
+    Linear,
+    /// Quadratic, starts slow.
+    EaseInQuad,
+    /// Quadratic, ends slow.
+    EaseOutQuad,
+    /// Quadratic, starts and ends slow.
+    EaseInOutQuad,
+    /// Cubic, starts slow.
+    EaseInCubic,
+    /// Cubic, ends slow.
+    EaseOutCubic,
+    /// Cubic, starts and ends slow.
+    EaseInOutCubic,
+}
+
+impl Easing {
+    /// Remaps `t` in `[0, 1]` through the curve.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Self::Step => 0.0,
+            Self::Linear => t,
+            Self::EaseInQuad => t * t,
+            Self::EaseOutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+            Self::EaseInOutQuad => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Self::EaseInCubic => t * t * t,
+            Self::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Self::EaseInOutCubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+/// A value at a point in time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe<T> {
+    /// Time of the keyframe in seconds.
+    pub time: f32,
+    /// Value at that time.
+    pub value: T,
+    /// Easing used from this keyframe to the next one.
+    pub easing: Easing,
+}
+
+/// A sequence of keyframes sorted by time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track<T> {
+    /// The keyframes, sorted by time.
+    pub keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T> Default for Track<T> {
+    fn default() -> Self {
+        Self {
+            keyframes: Vec::new(),
+        }
+    }
+}
+
+impl<T: Interpolate> Track<T> {
+    /// Creates an empty track.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a keyframe, keeping the keyframes sorted by time.
+    pub fn keyframe(mut self, time: f32, value: T, easing: Easing) -> Self {
+        let index = self.keyframes.partition_point(|k| k.time <= time);
+
+        self.keyframes.insert(
+            index,
+            Keyframe {
+                time,
+                value,
+                easing,
+            },
+        );
+
+        self
+    }
+
+    /// Time of the last keyframe.
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    /// Samples the track, clamping to the first and last keyframes outside of their range.
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let first = self.keyframes.first()?;
+
+        if time <= first.time {
+            return Some(first.value.clone());
+        }
+
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+
+        let Some(to) = self.keyframes.get(next) else {
+            return self.keyframes.last().map(|k| k.value.clone());
+        };
+
+        let from = &self.keyframes[next - 1];
+        let span = to.time - from.time;
+        let t = if span > 0.0 {
+            (time - from.time) / span
+        } else {
+            1.0
+        };
+
+        Some(from.value.interpolate(&to.value, from.easing.apply(t)))
+    }
+}
+
+/// What a player does when it reaches the end of the animation.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LoopMode {
+    /// Stops at the end.
+    #[default]
+    Once,
+    /// Starts again from the beginning.
+    Loop,
+    /// Plays backwards, then forwards again.
+    PingPong,
+}
+
+/// Advances a playhead over time and samples tracks at it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationPlayer {
+    /// Current playhead in seconds.
+    pub time: f32,
+    /// Length of the animation in seconds.
+    pub duration: f32,
+    /// Playback speed multiplier.
+    pub speed: f32,
+    /// Behavior at the end of the animation.
+    pub loop_mode: LoopMode,
+    /// Whether [AnimationPlayer::advance] moves the playhead.
+    pub playing: bool,
+    /// Whether the playhead is moving backwards, used by [LoopMode::PingPong].
+    pub reversed: bool,
+}
+
+impl AnimationPlayer {
+    /// Creates a playing player for an animation of the given length.
+    pub fn new(duration: f32, loop_mode: LoopMode) -> Self {
+        Self {
+            time: 0.0,
+            duration,
+            speed: 1.0,
+            loop_mode,
+            playing: true,
+            reversed: false,
+        }
+    }
+
+    /// Moves the playhead by `delta_seconds`, usually [super::FrameClock::delta_seconds].
+    pub fn advance(&mut self, delta_seconds: f32) {
+        if !self.playing || self.duration <= 0.0 {
+            return;
+        }
+
+        let step = delta_seconds * self.speed;
+        self.time += if self.reversed { -step } else { step };
+
+        match self.loop_mode {
+            LoopMode::Once => {
+                if self.time >= self.duration {
+                    self.time = self.duration;
+                    self.playing = false;
+                }
+            }
+            LoopMode::Loop => self.time = self.time.rem_euclid(self.duration),
+            LoopMode::PingPong => {
+                if self.time >= self.duration {
+                    self.time = 2.0 * self.duration - self.time;
+                    self.reversed = true;
+                } else if self.time <= 0.0 {
+                    self.time = -self.time;
+                    self.reversed = false;
+                }
+
+                self.time = self.time.clamp(0.0, self.duration);
+            }
+        }
+    }
+
+    /// Samples a track at the playhead.
+    pub fn sample<T: Interpolate>(&self, track: &Track<T>) -> Option<T> {
+        track.sample(self.time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [Easing; 8] = [
+        Easing::Step,
+        Easing::Linear,
+        Easing::EaseInQuad,
+        Easing::EaseOutQuad,
+        Easing::EaseInOutQuad,
+        Easing::EaseInCubic,
+        Easing::EaseOutCubic,
+        Easing::EaseInOutCubic,
+    ];
+
+    #[test]
+    fn easing_keeps_the_endpoints() {
+        for easing in CURVES {
+            assert_eq!(easing.apply(0.0), 0.0, "{:?}", easing);
+
+            if easing != Easing::Step {
+                assert_eq!(easing.apply(1.0), 1.0, "{:?}", easing);
+            }
+        }
+    }
+
+    #[test]
+    fn easing_clamps_outside_the_unit_range() {
+        for easing in CURVES {
+            assert_eq!(easing.apply(-1.0), easing.apply(0.0), "{:?}", easing);
+            assert_eq!(easing.apply(f32::MAX), easing.apply(1.0), "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn easing_curves() {
+        assert_eq!(Easing::Step.apply(0.99), 0.0);
+        assert_eq!(Easing::Linear.apply(0.3), 0.3);
+        assert_eq!(Easing::EaseInQuad.apply(0.5), 0.25);
+        assert_eq!(Easing::EaseOutQuad.apply(0.5), 0.75);
+        assert_eq!(Easing::EaseInOutQuad.apply(0.5), 0.5);
+        assert_eq!(Easing::EaseInCubic.apply(0.5), 0.125);
+        assert_eq!(Easing::EaseOutCubic.apply(0.5), 0.875);
+        assert_eq!(Easing::EaseInOutCubic.apply(0.5), 0.5);
+        assert_eq!(Easing::EaseInOutCubic.apply(0.25), 0.0625);
+    }
+
+    #[test]
+    fn track_keeps_keyframes_sorted() {
+        let track = Track::new()
+            .keyframe(2.0, 20.0, Easing::Linear)
+            .keyframe(0.0, 0.0, Easing::Linear)
+            .keyframe(1.0, 10.0, Easing::Linear);
+
+        let times: Vec<_> = track.keyframes.iter().map(|k| k.time).collect();
+        assert_eq!(times, [0.0, 1.0, 2.0]);
+        assert_eq!(track.duration(), 2.0);
+    }
+
+    #[test]
+    fn track_samples_and_clamps() {
+        assert_eq!(Track::<f32>::new().sample(1.0), None);
+        assert_eq!(Track::<f32>::new().duration(), 0.0);
+
+        let single = Track::new().keyframe(1.0, 5.0, Easing::Linear);
+        assert_eq!(single.sample(0.0), Some(5.0));
+        assert_eq!(single.sample(3.0), Some(5.0));
+
+        let track = Track::new()
+            .keyframe(0.0, 0.0, Easing::Linear)
+            .keyframe(4.0, 8.0, Easing::Step)
+            .keyframe(6.0, 0.0, Easing::Linear);
+
+        assert_eq!(track.sample(-1.0), Some(0.0));
+        assert_eq!(track.sample(1.0), Some(2.0));
+        assert_eq!(track.sample(4.0), Some(8.0));
+        assert_eq!(track.sample(5.0), Some(8.0));
+        assert_eq!(track.sample(f32::MAX), Some(0.0));
+    }
+
+    #[test]
+    fn keyframes_at_the_same_time_jump() {
+        let track = Track::new()
+            .keyframe(0.0, 0.0, Easing::Linear)
+            .keyframe(1.0, 1.0, Easing::Linear)
+            .keyframe(1.0, 3.0, Easing::Linear);
+
+        assert_eq!(track.sample(0.5), Some(0.5));
+        assert_eq!(track.sample(1.0), Some(3.0));
+    }
+
+    #[test]
+    fn player_once_stops_at_the_end() {
+        let mut player = AnimationPlayer::new(2.0, LoopMode::Once);
+
+        player.advance(1.5);
+        assert_eq!(player.time, 1.5);
+        assert!(player.playing);
+
+        player.advance(1.0);
+        assert_eq!(player.time, 2.0);
+        assert!(!player.playing);
+
+        player.advance(1.0);
+        assert_eq!(player.time, 2.0);
+    }
+
+    #[test]
+    fn player_loops_and_ping_pongs() {
+        let mut looping = AnimationPlayer::new(2.0, LoopMode::Loop);
+        looping.advance(5.0);
+        assert_eq!(looping.time, 1.0);
+
+        let mut ping_pong = AnimationPlayer::new(2.0, LoopMode::PingPong);
+        ping_pong.advance(3.0);
+        assert_eq!(ping_pong.time, 1.0);
+        assert!(ping_pong.reversed);
+
+        ping_pong.advance(1.5);
+        assert_eq!(ping_pong.time, 0.5);
+        assert!(!ping_pong.reversed);
+    }
+
+    #[test]
+    fn player_without_duration_stays_put() {
+        let mut player = AnimationPlayer::new(0.0, LoopMode::Loop);
+        player.advance(1.0);
+        assert_eq!(player.time, 0.0);
+
+        let mut player = AnimationPlayer::new(1.0, LoopMode::Loop);
+        player.speed = 0.0;
+        player.advance(1.0);
+        assert_eq!(player.time, 0.0);
+    }
+
+    #[test]
+    fn player_samples_vectors() {
+        let track = Track::new()
+            .keyframe(0.0, glm::vec3(0.0, 0.0, 0.0), Easing::Linear)
+            .keyframe(1.0, glm::vec3(2.0, 4.0, 8.0), Easing::Linear);
+        let mut player = AnimationPlayer::new(track.duration(), LoopMode::Once);
+
+        player.advance(0.5);
+        assert_eq!(player.sample(&track), Some(glm::vec3(1.0, 2.0, 4.0)));
+    }
+}
@@ -0,0 +1,524 @@
+//! Texture atlases packing many small images, like sprites or glyphs, into one texture so they
+//! can be drawn without rebinding.
+
+use std::{error, fmt};
+
+use ash::vk;
+
+/// Identifies a region of an [Atlas].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RegionId(u32);
+
+/// Normalized texture coordinates of a region, `(u0, v0)` being the top-left corner.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct UvRect {
+    pub u0: f32,
+    pub v0: f32,
+    pub u1: f32,
+    pub v1: f32,
+}
+
+/// A packed image inside the atlas.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AtlasRegion {
+    /// The pixels of the region, without padding.
+    pub rect: vk::Rect2D,
+    /// The texture coordinates of `rect`.
+    pub uv: UvRect,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct SkylineSegment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+/// A CPU copy of an atlas texture with a skyline rectangle packer.
+///
+/// Inserting or rewriting regions marks them dirty, and [Atlas::stage_dirty] copies only those
+/// into a staging buffer along with the copy regions to record into the backing texture.
+/// Regions are never freed one by one, the atlas is rebuilt with [Atlas::clear] instead.
+#[derive(Debug, Clone)]
+pub struct Atlas {
+    extent: vk::Extent2D,
+    bytes_per_pixel: usize,
+    padding: u32,
+    pixels: Vec<u8>,
+    skyline: Vec<SkylineSegment>,
+    regions: Vec<AtlasRegion>,
+    dirty: Vec<vk::Rect2D>,
+}
+
+impl Atlas {
+    /// Creates an empty atlas of `extent` pixels of `bytes_per_pixel` bytes each, e.g. 1 for an
+    /// `R8_UNORM` glyph atlas or 4 for an `R8G8B8A8_SRGB` sprite atlas.
+    pub fn new(extent: vk::Extent2D, bytes_per_pixel: usize) -> Self {
+        Self {
+            extent,
+            bytes_per_pixel,
+            padding: 1,
+            pixels: vec![0; extent.width as usize * extent.height as usize * bytes_per_pixel],
+            skyline: vec![SkylineSegment {
+                x: 0,
+                y: 0,
+                width: extent.width,
+            }],
+            regions: Vec::new(),
+            // The first upload also clears the padding, the texture starts out undefined.
+            dirty: vec![vk::Rect2D {
+                offset: vk::Offset2D::default(),
+                extent,
+            }],
+        }
+    }
+
+    /// Sets the empty pixels kept around regions inserted afterwards, so linear filtering doesn't
+    /// bleed neighbors in. Defaults to 1.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    pub fn region(&self, id: RegionId) -> Option<&AtlasRegion> {
+        self.regions.get(id.0 as usize)
+    }
+
+    /// Packs a `width` by `height` image with the given tightly packed pixels.
+    pub fn insert(
+        &mut self,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<RegionId, AtlasError> {
+        self.check_size(width, height, pixels)?;
+
+        let padding = self.padding.saturating_mul(2);
+        let (padded_width, padded_height) = (
+            width.saturating_add(padding),
+            height.saturating_add(padding),
+        );
+
+        if padded_width > self.extent.width || padded_height > self.extent.height {
+            return Err(AtlasError::TooLarge);
+        }
+
+        let (index, x, y) = self
+            .find_position(padded_width, padded_height)
+            .ok_or(AtlasError::Full)?;
+
+        self.place(index, x, y + padded_height, padded_width);
+
+        let rect = vk::Rect2D {
+            offset: vk::Offset2D {
+                x: (x + self.padding) as i32,
+                y: (y + self.padding) as i32,
+            },
+            extent: vk::Extent2D { width, height },
+        };
+
+        let id = RegionId(self.regions.len() as u32);
+        self.regions.push(AtlasRegion {
+            rect,
+            uv: self.uv_rect(&rect),
+        });
+        self.write_pixels(&rect, pixels);
+
+        Ok(id)
+    }
+
+    /// Replaces the pixels of a region, e.g. for an animated sprite.
+    pub fn write(&mut self, id: RegionId, pixels: &[u8]) -> Result<(), AtlasError> {
+        let rect = self.region(id).ok_or(AtlasError::UnknownRegion)?.rect;
+
+        self.check_size(rect.extent.width, rect.extent.height, pixels)?;
+        self.write_pixels(&rect, pixels);
+
+        Ok(())
+    }
+
+    /// Removes every region. Existing [RegionId]s become invalid.
+    pub fn clear(&mut self) {
+        self.pixels.fill(0);
+        self.skyline = vec![SkylineSegment {
+            x: 0,
+            y: 0,
+            width: self.extent.width,
+        }];
+        self.regions.clear();
+        self.dirty = vec![vk::Rect2D {
+            offset: vk::Offset2D::default(),
+            extent: self.extent,
+        }];
+    }
+
+    /// Whether there are changes that haven't been staged yet.
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    /// The number of bytes [Atlas::stage_dirty] needs.
+    pub fn dirty_size(&self) -> usize {
+        self.dirty
+            .iter()
+            .map(|rect| {
+                let size =
+                    rect.extent.width as usize * rect.extent.height as usize * self.bytes_per_pixel;
+                size.next_multiple_of(self.copy_alignment())
+            })
+            .sum()
+    }
+
+    /// Copies the dirty regions into `staging`, the mapped memory of a buffer starting at
+    /// `buffer_offset`, and returns the copies to record into the backing texture's color
+    /// aspect.
+    ///
+    /// `staging` must hold at least [Atlas::dirty_size] bytes and `buffer_offset` must be a
+    /// multiple of 4 and of the texel size.
+    pub fn stage_dirty(
+        &mut self,
+        staging: &mut [u8],
+        buffer_offset: u64,
+    ) -> Vec<vk::BufferImageCopy> {
+        debug_assert!(staging.len() >= self.dirty_size());
+
+        let row_pitch = self.extent.width as usize * self.bytes_per_pixel;
+        let alignment = self.copy_alignment();
+        let mut written = 0usize;
+        let mut copies = Vec::with_capacity(self.dirty.len());
+
+        for rect in self.dirty.drain(..) {
+            let row_size = rect.extent.width as usize * self.bytes_per_pixel;
+            let start = written.next_multiple_of(alignment);
+            written = start;
+
+            for row in 0..rect.extent.height as usize {
+                let source = (rect.offset.y as usize + row) * row_pitch
+                    + rect.offset.x as usize * self.bytes_per_pixel;
+
+                staging[written..written + row_size]
+                    .copy_from_slice(&self.pixels[source..source + row_size]);
+                written += row_size;
+            }
+
+            copies.push(
+                vk::BufferImageCopy::default()
+                    .buffer_offset(buffer_offset + start as u64)
+                    .image_subresource(
+                        vk::ImageSubresourceLayers::default()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .layer_count(1),
+                    )
+                    .image_offset(vk::Offset3D {
+                        x: rect.offset.x,
+                        y: rect.offset.y,
+                        z: 0,
+                    })
+                    .image_extent(vk::Extent3D {
+                        width: rect.extent.width,
+                        height: rect.extent.height,
+                        depth: 1,
+                    }),
+            );
+        }
+
+        copies
+    }
+
+    /// Buffer offsets of image copies must be multiples of both the texel size and 4.
+    fn copy_alignment(&self) -> usize {
+        match self.bytes_per_pixel {
+            size if size % 4 == 0 => size,
+            size if size % 2 == 0 => size * 2,
+            size => size * 4,
+        }
+    }
+
+    fn check_size(&self, width: u32, height: u32, pixels: &[u8]) -> Result<(), AtlasError> {
+        let expected = width as usize * height as usize * self.bytes_per_pixel;
+
+        if pixels.len() != expected {
+            return Err(AtlasError::SizeMismatch {
+                expected,
+                found: pixels.len(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Finds the skyline segment to place a rectangle at, preferring the lowest and then the
+    /// leftmost position.
+    fn find_position(&self, width: u32, height: u32) -> Option<(usize, u32, u32)> {
+        let mut best: Option<(usize, u32, u32)> = None;
+
+        for (index, segment) in self.skyline.iter().enumerate() {
+            if segment.x + width > self.extent.width {
+                break;
+            }
+
+            let mut y = 0;
+            let mut remaining = width as i64;
+
+            for spanned in &self.skyline[index..] {
+                if remaining <= 0 {
+                    break;
+                }
+
+                y = y.max(spanned.y);
+                remaining -= spanned.width as i64;
+            }
+
+            if y + height > self.extent.height {
+                continue;
+            }
+
+            if best.map_or(true, |(_, _, best_y)| y < best_y) {
+                best = Some((index, segment.x, y));
+            }
+        }
+
+        best
+    }
+
+    /// Raises the skyline to `top` over `[x, x + width)` starting at segment `index`.
+    fn place(&mut self, index: usize, x: u32, top: u32, width: u32) {
+        self.skyline
+            .insert(index, SkylineSegment { x, y: top, width });
+
+        let right = x + width;
+        let next = index + 1;
+
+        while next < self.skyline.len() && self.skyline[next].x < right {
+            let segment = &mut self.skyline[next];
+            let segment_right = segment.x + segment.width;
+
+            if segment_right <= right {
+                self.skyline.remove(next);
+            } else {
+                segment.width = segment_right - right;
+                segment.x = right;
+                break;
+            }
+        }
+
+        self.skyline.dedup_by(|right, left| {
+            if left.y == right.y {
+                left.width += right.width;
+                true
+            } else {
+                false
+            }
+        });
+    }
+
+    fn uv_rect(&self, rect: &vk::Rect2D) -> UvRect {
+        let (width, height) = (self.extent.width as f32, self.extent.height as f32);
+
+        UvRect {
+            u0: rect.offset.x as f32 / width,
+            v0: rect.offset.y as f32 / height,
+            u1: (rect.offset.x as u32 + rect.extent.width) as f32 / width,
+            v1: (rect.offset.y as u32 + rect.extent.height) as f32 / height,
+        }
+    }
+
+    fn write_pixels(&mut self, rect: &vk::Rect2D, pixels: &[u8]) {
+        let row_pitch = self.extent.width as usize * self.bytes_per_pixel;
+        let row_size = rect.extent.width as usize * self.bytes_per_pixel;
+
+        if row_size == 0 {
+            return;
+        }
+
+        for (row, source) in pixels.chunks_exact(row_size).enumerate() {
+            let start = (rect.offset.y as usize + row) * row_pitch
+                + rect.offset.x as usize * self.bytes_per_pixel;
+
+            self.pixels[start..start + row_size].copy_from_slice(source);
+        }
+
+        self.dirty.push(*rect);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AtlasError {
+    /// The image doesn't fit even into an empty atlas.
+    TooLarge,
+    /// There is no room left for the image.
+    Full,
+    UnknownRegion,
+    SizeMismatch {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooLarge => write!(f, "image is larger than the atlas"),
+            Self::Full => write!(f, "atlas has no room left for the image"),
+            Self::UnknownRegion => write!(f, "region does not belong to this atlas"),
+            Self::SizeMismatch { expected, found } => {
+                write!(f, "expected {} bytes of pixels but got {}", expected, found)
+            }
+        }
+    }
+}
+
+impl error::Error for AtlasError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extent(width: u32, height: u32) -> vk::Extent2D {
+        vk::Extent2D { width, height }
+    }
+
+    fn offset(rect: &vk::Rect2D) -> (i32, i32) {
+        (rect.offset.x, rect.offset.y)
+    }
+
+    #[test]
+    fn regions_fill_the_atlas_without_padding() {
+        let mut atlas = Atlas::new(extent(4, 4), 1).with_padding(0);
+        let ids: Vec<_> = (0..4)
+            .map(|_| atlas.insert(2, 2, &[1; 4]).unwrap())
+            .collect();
+
+        let offsets: Vec<_> = ids
+            .iter()
+            .map(|&id| offset(&atlas.region(id).unwrap().rect))
+            .collect();
+        assert_eq!(offsets, [(0, 0), (2, 0), (0, 2), (2, 2)]);
+        assert_eq!(atlas.insert(1, 1, &[1]), Err(AtlasError::Full));
+    }
+
+    #[test]
+    fn padding_separates_regions() {
+        let mut atlas = Atlas::new(extent(7, 3), 1);
+        let a = atlas.insert(1, 1, &[1]).unwrap();
+        let b = atlas.insert(1, 1, &[2]).unwrap();
+
+        assert_eq!(offset(&atlas.region(a).unwrap().rect), (1, 1));
+        assert_eq!(offset(&atlas.region(b).unwrap().rect), (4, 1));
+        assert_eq!(atlas.insert(2, 1, &[3; 2]), Err(AtlasError::Full));
+    }
+
+    #[test]
+    fn images_larger_than_the_atlas_are_rejected() {
+        let mut atlas = Atlas::new(extent(3, 3), 1);
+
+        assert_eq!(atlas.insert(2, 1, &[0; 2]), Err(AtlasError::TooLarge));
+        assert_eq!(atlas.insert(u32::MAX, 0, &[]), Err(AtlasError::TooLarge));
+        assert_eq!(
+            Atlas::new(extent(3, 3), 1)
+                .with_padding(u32::MAX)
+                .insert(1, 1, &[0]),
+            Err(AtlasError::TooLarge)
+        );
+        assert!(atlas.insert(1, 1, &[0]).is_ok());
+    }
+
+    #[test]
+    fn empty_atlas_rejects_everything() {
+        let mut atlas = Atlas::new(extent(0, 0), 4).with_padding(0);
+
+        assert_eq!(atlas.insert(1, 1, &[0; 4]), Err(AtlasError::TooLarge));
+        assert_eq!(atlas.dirty_size(), 0);
+    }
+
+    #[test]
+    fn pixel_data_has_to_match_the_size() {
+        let mut atlas = Atlas::new(extent(8, 8), 4);
+
+        assert_eq!(
+            atlas.insert(2, 2, &[0; 15]),
+            Err(AtlasError::SizeMismatch {
+                expected: 16,
+                found: 15
+            })
+        );
+
+        let id = atlas.insert(2, 2, &[0; 16]).unwrap();
+        assert!(atlas.write(id, &[0; 4]).is_err());
+        assert_eq!(
+            atlas.write(RegionId(7), &[0; 16]),
+            Err(AtlasError::UnknownRegion)
+        );
+    }
+
+    #[test]
+    fn uvs_cover_the_region() {
+        let mut atlas = Atlas::new(extent(10, 5), 1).with_padding(0);
+        let id = atlas.insert(5, 5, &[0; 25]).unwrap();
+
+        assert_eq!(
+            atlas.region(id).unwrap().uv,
+            UvRect {
+                u0: 0.0,
+                v0: 0.0,
+                u1: 0.5,
+                v1: 1.0
+            }
+        );
+    }
+
+    #[test]
+    fn staging_copies_only_dirty_regions() {
+        let mut atlas = Atlas::new(extent(5, 3), 1).with_padding(0);
+        let mut staging = vec![0; atlas.dirty_size()];
+
+        // The first upload covers the whole atlas.
+        let copies = atlas.stage_dirty(&mut staging, 0);
+        assert_eq!(copies.len(), 1);
+        assert!(!atlas.is_dirty());
+
+        let id = atlas.insert(3, 2, &[1, 2, 3, 4, 5, 6]).unwrap();
+        atlas.write(id, &[6, 5, 4, 3, 2, 1]).unwrap();
+
+        // Rows of 3 bytes, each copy starting on a multiple of 4.
+        assert_eq!(atlas.dirty_size(), 16);
+
+        let mut staging = vec![0; atlas.dirty_size()];
+        let copies = atlas.stage_dirty(&mut staging, 256);
+
+        assert_eq!(copies.len(), 2);
+        assert_eq!(copies[0].buffer_offset, 256);
+        assert_eq!(copies[1].buffer_offset, 264);
+        assert_eq!(copies[1].image_extent.width, 3);
+        assert_eq!(&staging[8..14], &[6, 5, 4, 3, 2, 1]);
+        assert_eq!(atlas.dirty_size(), 0);
+    }
+
+    #[test]
+    fn copy_alignment_respects_the_texel_size() {
+        for (bytes_per_pixel, alignment) in [(1, 4), (2, 4), (3, 12), (4, 4), (8, 8), (12, 12)] {
+            assert_eq!(
+                Atlas::new(extent(1, 1), bytes_per_pixel).copy_alignment(),
+                alignment
+            );
+        }
+    }
+
+    #[test]
+    fn clear_starts_over() {
+        let mut atlas = Atlas::new(extent(2, 2), 1).with_padding(0);
+        let id = atlas.insert(2, 2, &[9; 4]).unwrap();
+
+        atlas.clear();
+
+        assert_eq!(atlas.region(id), None);
+        assert!(atlas.is_dirty());
+
+        let id = atlas.insert(2, 2, &[1; 4]).unwrap();
+        assert_eq!(offset(&atlas.region(id).unwrap().rect), (0, 0));
+    }
+}
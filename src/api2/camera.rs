@@ -0,0 +1,237 @@
+//! Cameras producing projection matrices for Vulkan's clip space.
+
+use ash::vk;
+use nalgebra_glm as glm;
+
+use super::Ray;
+
+/// Which way clip-space Y points on screen.
+///
+/// Vulkan's clip space has Y pointing down, unlike OpenGL's and the one `glm` projections are
+/// written for. A viewport with a negative height, which needs `VK_KHR_maintenance1`, flips it
+/// back up. Viewports and projections have to agree on the orientation or the image ends up
+/// flipped twice, so both are derived from one value. Either way the image and the winding of
+/// its triangles in the framebuffer are the same.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ClipSpaceY {
+    /// Vulkan's own orientation, projections have to flip Y themselves.
+    #[default]
+    Down,
+    /// Flipped by the viewport, projections are used as written.
+    Up,
+}
+
+impl ClipSpaceY {
+    /// Turns a viewport covering an area into one producing this orientation.
+    pub fn apply(self, viewport: vk::Viewport) -> vk::Viewport {
+        match self {
+            Self::Down => viewport,
+            Self::Up => vk::Viewport {
+                y: viewport.y + viewport.height,
+                height: -viewport.height,
+                ..viewport
+            },
+        }
+    }
+
+    /// The orientation `viewport` produces, flipped when its height is negative.
+    pub fn of_viewport(viewport: vk::Viewport) -> Self {
+        if viewport.height < 0.0 {
+            Self::Up
+        } else {
+            Self::Down
+        }
+    }
+
+    /// A viewport covering all of `extent` with depth in `[0, 1]`.
+    pub fn viewport(self, extent: vk::Extent2D) -> vk::Viewport {
+        self.apply(
+            vk::Viewport::default()
+                .width(extent.width as f32)
+                .height(extent.height as f32)
+                .max_depth(1.0),
+        )
+    }
+
+    /// Adapts a projection with Y pointing up, like the ones from `glm`, to this orientation.
+    pub fn projection(self, y_up_projection: glm::Mat4) -> glm::Mat4 {
+        match self {
+            Self::Up => y_up_projection,
+            Self::Down => {
+                let mut projection = y_up_projection;
+                projection.row_mut(1).neg_mut();
+                projection
+            }
+        }
+    }
+
+    /// A right-handed perspective projection with depth in `[0, 1]`.
+    pub fn perspective(self, aspect: f32, fovy: f32, near: f32, far: f32) -> glm::Mat4 {
+        self.projection(glm::perspective_rh_zo(aspect, fovy, near, far))
+    }
+}
+
+/// A 2D camera working in pixel coordinates, with the origin at the top-left corner and Y pointing
+/// down.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera2D {
+    /// Size of the viewport in pixels.
+    pub viewport_size: glm::Vec2,
+    /// World position shown at the top-left corner of the viewport.
+    pub position: glm::Vec2,
+    /// Zoom factor, values above 1 magnify the world.
+    pub zoom: f32,
+}
+
+impl Camera2D {
+    /// Creates a camera showing the world 1:1 in a viewport of the given size.
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            viewport_size: glm::vec2(width, height),
+            position: glm::Vec2::zeros(),
+            zoom: 1.0,
+        }
+    }
+
+    /// Updates the viewport size, e.g. after the swapchain was resized.
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.viewport_size = glm::vec2(width, height);
+    }
+
+    /// Moves the camera by a distance in screen pixels.
+    pub fn pan(&mut self, screen_delta: glm::Vec2) {
+        self.position -= screen_delta / self.zoom;
+    }
+
+    /// Multiplies the zoom by `factor`, keeping the world point under `screen_point` in place.
+    pub fn zoom_at(&mut self, screen_point: glm::Vec2, factor: f32) {
+        let anchor = self.screen_to_world(screen_point);
+
+        self.zoom = (self.zoom * factor).max(f32::EPSILON);
+        self.position = anchor - screen_point / self.zoom;
+    }
+
+    /// Size of the visible world area.
+    pub fn visible_size(&self) -> glm::Vec2 {
+        self.viewport_size / self.zoom
+    }
+
+    /// The orthographic projection mapping the visible world area to Vulkan's clip space, with
+    /// depth in `[0, 1]` for z values in `[-1, 1]`.
+    pub fn projection(&self) -> glm::Mat4 {
+        self.projection_for(ClipSpaceY::Down)
+    }
+
+    /// The projection for viewports built with `clip_space_y`.
+    pub fn projection_for(&self, clip_space_y: ClipSpaceY) -> glm::Mat4 {
+        let size = self.visible_size();
+        let (top, bottom) = (self.position.y, self.position.y + size.y);
+
+        clip_space_y.projection(glm::ortho_rh_zo(
+            self.position.x,
+            self.position.x + size.x,
+            bottom,
+            top,
+            -1.0,
+            1.0,
+        ))
+    }
+
+    /// Converts a point in screen pixels into world coordinates.
+    pub fn screen_to_world(&self, screen_point: glm::Vec2) -> glm::Vec2 {
+        self.position + screen_point / self.zoom
+    }
+
+    /// Converts a point in world coordinates into screen pixels.
+    pub fn world_to_screen(&self, world_point: glm::Vec2) -> glm::Vec2 {
+        (world_point - self.position) * self.zoom
+    }
+}
+
+/// A perspective camera looking from `position` at `target`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub position: glm::Vec3,
+    pub target: glm::Vec3,
+    pub up: glm::Vec3,
+    /// Vertical field of view in radians.
+    pub fovy: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Camera {
+    /// Creates a camera with Y up, a 45° field of view and depth from 0.1 to 100.
+    pub fn new(position: glm::Vec3, target: glm::Vec3) -> Self {
+        Self {
+            position,
+            target,
+            up: glm::Vec3::y(),
+            fovy: std::f32::consts::FRAC_PI_4,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+
+    pub fn view(&self) -> glm::Mat4 {
+        glm::look_at_rh(&self.position, &self.target, &self.up)
+    }
+
+    /// The projection for viewports built with `clip_space_y`, see [ClipSpaceY::perspective].
+    pub fn projection_for(&self, clip_space_y: ClipSpaceY, aspect: f32) -> glm::Mat4 {
+        clip_space_y.perspective(aspect, self.fovy, self.near, self.far)
+    }
+
+    pub fn view_projection_for(&self, clip_space_y: ClipSpaceY, aspect: f32) -> glm::Mat4 {
+        self.projection_for(clip_space_y, aspect) * self.view()
+    }
+
+    /// The world-space ray through the pixel under `cursor`, in pixels from the top-left corner
+    /// of the window, starting on the near plane.
+    ///
+    /// `viewport` is the one the scene is drawn with. A negative height means it flips Y, see
+    /// [ClipSpaceY::Up], and the projection is picked to match.
+    pub fn screen_to_ray(&self, cursor: glm::Vec2, viewport: vk::Viewport) -> Ray {
+        let clip_space_y = ClipSpaceY::of_viewport(viewport);
+        let aspect = (viewport.width / viewport.height).abs();
+
+        // The inverse of the viewport transform, which flipped viewports undo on their own
+        // since their center stays put and their half height is negated.
+        let ndc = glm::vec2(
+            (cursor.x - viewport.x) / viewport.width * 2.0 - 1.0,
+            (cursor.y - (viewport.y + viewport.height * 0.5)) / (viewport.height * 0.5),
+        );
+
+        let inverse = self
+            .view_projection_for(clip_space_y, aspect)
+            .try_inverse()
+            .unwrap_or_else(glm::Mat4::identity);
+        let unproject = |depth: f32| {
+            let point = inverse * glm::vec4(ndc.x, ndc.y, depth, 1.0);
+            point.xyz() / point.w
+        };
+
+        let near = unproject(0.0);
+        Ray::new(near, unproject(1.0) - near)
+    }
+
+    /// The pixel `point` lands on in `viewport`, or `None` behind the camera.
+    pub fn world_to_screen(&self, point: glm::Vec3, viewport: vk::Viewport) -> Option<glm::Vec2> {
+        let clip_space_y = ClipSpaceY::of_viewport(viewport);
+        let aspect = (viewport.width / viewport.height).abs();
+
+        let clip = self.view_projection_for(clip_space_y, aspect)
+            * glm::vec4(point.x, point.y, point.z, 1.0);
+
+        if clip.w <= 0.0 {
+            return None;
+        }
+
+        let ndc = clip.xy() / clip.w;
+
+        Some(glm::vec2(
+            viewport.x + (ndc.x + 1.0) * 0.5 * viewport.width,
+            viewport.y + viewport.height * 0.5 + ndc.y * viewport.height * 0.5,
+        ))
+    }
+}
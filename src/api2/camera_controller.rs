@@ -0,0 +1,413 @@
+//! Controllers moving a [Camera] from mouse and keyboard input, so samples can be looked around.
+//!
+//! Input is translated from the window backend into [ControllerInput] first, from GLFW events or
+//! from the Win32 messages a host embedding the renderer receives, and controllers only see
+//! that. Events accumulate until [OrbitController::update] or [FlyController::update] applies
+//! them along with the frame's delta time.
+
+use nalgebra_glm as glm;
+
+use super::Camera;
+
+/// Pitch stays this far from straight up or down, where the view direction would line up with
+/// the camera's up vector.
+const PITCH_LIMIT: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+/// Win32 messages, so translating them needs no bindings to the Windows API.
+mod win32 {
+    pub const WM_KILLFOCUS: u32 = 0x0008;
+    pub const WM_KEYDOWN: u32 = 0x0100;
+    pub const WM_KEYUP: u32 = 0x0101;
+    pub const WM_MOUSEMOVE: u32 = 0x0200;
+    pub const WM_LBUTTONDOWN: u32 = 0x0201;
+    pub const WM_LBUTTONUP: u32 = 0x0202;
+    pub const WM_RBUTTONDOWN: u32 = 0x0204;
+    pub const WM_RBUTTONUP: u32 = 0x0205;
+    pub const WM_MBUTTONDOWN: u32 = 0x0207;
+    pub const WM_MBUTTONUP: u32 = 0x0208;
+    pub const WM_MOUSEWHEEL: u32 = 0x020A;
+
+    pub const VK_SHIFT: usize = 0x10;
+    pub const WHEEL_DELTA: f32 = 120.0;
+}
+
+/// Input a camera controller reacts to, independent of the window backend.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ControllerInput {
+    /// The cursor moved to a position in pixels from the top-left corner of the window.
+    CursorMoved(glm::Vec2),
+    Button {
+        button: ControllerButton,
+        pressed: bool,
+    },
+    /// The wheel scrolled, in notches, positive away from the user.
+    Scroll(f32),
+    Key {
+        key: ControllerKey,
+        pressed: bool,
+    },
+    /// The window lost focus, releasing every button and key since their releases won't be
+    /// reported.
+    FocusLost,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ControllerButton {
+    Left,
+    Right,
+    Middle,
+}
+
+/// The movement keys, WASD with Q and E for down and up, Shift to go faster.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ControllerKey {
+    Forward,
+    Backward,
+    Left,
+    Right,
+    Up,
+    Down,
+    Fast,
+}
+
+impl ControllerKey {
+    fn from_glfw_key(key: glfw::Key) -> Option<Self> {
+        Some(match key {
+            glfw::Key::W => Self::Forward,
+            glfw::Key::S => Self::Backward,
+            glfw::Key::A => Self::Left,
+            glfw::Key::D => Self::Right,
+            glfw::Key::E => Self::Up,
+            glfw::Key::Q => Self::Down,
+            glfw::Key::LeftShift | glfw::Key::RightShift => Self::Fast,
+            _ => return None,
+        })
+    }
+
+    /// The key of a Win32 virtual-key code, letters being their uppercase ASCII code.
+    fn from_virtual_key(virtual_key: usize) -> Option<Self> {
+        Some(match virtual_key {
+            0x57 => Self::Forward,
+            0x53 => Self::Backward,
+            0x41 => Self::Left,
+            0x44 => Self::Right,
+            0x45 => Self::Up,
+            0x51 => Self::Down,
+            win32::VK_SHIFT => Self::Fast,
+            _ => return None,
+        })
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+impl ControllerInput {
+    /// The input of a GLFW event, `None` for events controllers don't use. Key repeats are
+    /// dropped, keys count as held until released.
+    pub fn from_glfw_event(event: &glfw::WindowEvent) -> Option<Self> {
+        match *event {
+            glfw::WindowEvent::CursorPos(x, y) => {
+                Some(Self::CursorMoved(glm::vec2(x as f32, y as f32)))
+            }
+            glfw::WindowEvent::MouseButton(button, action, _) => Some(Self::Button {
+                button: match button {
+                    glfw::MouseButtonLeft => ControllerButton::Left,
+                    glfw::MouseButtonRight => ControllerButton::Right,
+                    glfw::MouseButtonMiddle => ControllerButton::Middle,
+                    _ => return None,
+                },
+                pressed: action == glfw::Action::Press,
+            }),
+            glfw::WindowEvent::Scroll(_, y) => Some(Self::Scroll(y as f32)),
+            glfw::WindowEvent::Key(key, _, action, _) if action != glfw::Action::Repeat => {
+                Some(Self::Key {
+                    key: ControllerKey::from_glfw_key(key)?,
+                    pressed: action == glfw::Action::Press,
+                })
+            }
+            glfw::WindowEvent::Focus(false) => Some(Self::FocusLost),
+            _ => None,
+        }
+    }
+
+    /// The input of a Win32 window message with its `WPARAM` and `LPARAM`, `None` for messages
+    /// controllers don't use. Auto-repeated key downs are dropped.
+    pub fn from_win32_message(message: u32, wparam: usize, lparam: isize) -> Option<Self> {
+        // Client coordinates are signed 16-bit values packed in the low 32 bits.
+        let low_word = |value: isize| value as u16 as i16;
+        let high_word = |value: isize| (value >> 16) as u16 as i16;
+
+        let button = |button, pressed| Some(Self::Button { button, pressed });
+
+        match message {
+            win32::WM_MOUSEMOVE => Some(Self::CursorMoved(glm::vec2(
+                low_word(lparam) as f32,
+                high_word(lparam) as f32,
+            ))),
+            win32::WM_LBUTTONDOWN => button(ControllerButton::Left, true),
+            win32::WM_LBUTTONUP => button(ControllerButton::Left, false),
+            win32::WM_RBUTTONDOWN => button(ControllerButton::Right, true),
+            win32::WM_RBUTTONUP => button(ControllerButton::Right, false),
+            win32::WM_MBUTTONDOWN => button(ControllerButton::Middle, true),
+            win32::WM_MBUTTONUP => button(ControllerButton::Middle, false),
+            win32::WM_MOUSEWHEEL => Some(Self::Scroll(
+                high_word(wparam as isize) as f32 / win32::WHEEL_DELTA,
+            )),
+            // Bit 30 of LPARAM is set when the key was already down.
+            win32::WM_KEYDOWN if lparam & (1 << 30) == 0 => Some(Self::Key {
+                key: ControllerKey::from_virtual_key(wparam)?,
+                pressed: true,
+            }),
+            win32::WM_KEYUP => Some(Self::Key {
+                key: ControllerKey::from_virtual_key(wparam)?,
+                pressed: false,
+            }),
+            win32::WM_KILLFOCUS => Some(Self::FocusLost),
+            _ => None,
+        }
+    }
+}
+
+/// The buttons and keys held and the cursor movement since the last update, shared by the
+/// controllers.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct InputState {
+    cursor: Option<glm::Vec2>,
+    cursor_delta: glm::Vec2,
+    scroll: f32,
+    buttons: [bool; 3],
+    keys: u8,
+}
+
+impl InputState {
+    fn handle(&mut self, input: &ControllerInput) {
+        match *input {
+            ControllerInput::CursorMoved(position) => {
+                if let Some(previous) = self.cursor {
+                    self.cursor_delta += position - previous;
+                }
+
+                self.cursor = Some(position);
+            }
+            ControllerInput::Button { button, pressed } => self.buttons[button as usize] = pressed,
+            ControllerInput::Scroll(notches) => self.scroll += notches,
+            ControllerInput::Key { key, pressed } if pressed => self.keys |= key.bit(),
+            ControllerInput::Key { key, .. } => self.keys &= !key.bit(),
+            ControllerInput::FocusLost => {
+                self.buttons = [false; 3];
+                self.keys = 0;
+            }
+        }
+    }
+
+    fn is_held(&self, button: ControllerButton) -> bool {
+        self.buttons[button as usize]
+    }
+
+    fn is_pressed(&self, key: ControllerKey) -> bool {
+        self.keys & key.bit() != 0
+    }
+
+    /// -1, 0 or 1 depending on which of the two keys is held.
+    fn axis(&self, negative: ControllerKey, positive: ControllerKey) -> f32 {
+        self.is_pressed(positive) as i32 as f32 - self.is_pressed(negative) as i32 as f32
+    }
+
+    /// Takes the cursor movement and scrolling since the last call.
+    fn take_deltas(&mut self) -> (glm::Vec2, f32) {
+        let deltas = (self.cursor_delta, self.scroll);
+        self.cursor_delta = glm::Vec2::zeros();
+        self.scroll = 0.0;
+        deltas
+    }
+}
+
+/// The direction yaw and pitch point to, yaw 0 looking down +Z.
+fn direction(yaw: f32, pitch: f32) -> glm::Vec3 {
+    glm::vec3(
+        pitch.cos() * yaw.sin(),
+        pitch.sin(),
+        pitch.cos() * yaw.cos(),
+    )
+}
+
+/// The yaw and pitch of a direction, the inverse of [direction].
+fn yaw_pitch(direction: &glm::Vec3) -> (f32, f32) {
+    let direction = direction
+        .try_normalize(f32::EPSILON)
+        .unwrap_or(glm::Vec3::z());
+
+    (
+        direction.x.atan2(direction.z),
+        direction.y.clamp(-1.0, 1.0).asin(),
+    )
+}
+
+/// Orbits around a target: dragging with the left button rotates, with the right or middle
+/// button pans and scrolling zooms. A and D orbit, W and S zoom, Q and E rotate up and down.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrbitController {
+    pub target: glm::Vec3,
+    pub distance: f32,
+    /// Around the Y axis, in radians.
+    pub yaw: f32,
+    /// Above the target's horizon, in radians.
+    pub pitch: f32,
+    /// Radians per pixel dragged.
+    pub rotate_speed: f32,
+    /// Radians per second with the keys.
+    pub key_rotate_speed: f32,
+    /// The fraction of the distance each scroll notch zooms by.
+    pub zoom_speed: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    input: InputState,
+}
+
+impl OrbitController {
+    /// Orbits around the camera's target at its current distance.
+    pub fn new(camera: &Camera) -> Self {
+        let offset = camera.position - camera.target;
+        let (yaw, pitch) = yaw_pitch(&offset);
+
+        Self {
+            target: camera.target,
+            distance: offset.norm(),
+            yaw,
+            pitch: pitch.clamp(-PITCH_LIMIT, PITCH_LIMIT),
+            rotate_speed: 0.005,
+            key_rotate_speed: std::f32::consts::FRAC_PI_2,
+            zoom_speed: 0.1,
+            min_distance: 0.1,
+            max_distance: 1000.0,
+            input: InputState::default(),
+        }
+    }
+
+    pub fn handle(&mut self, input: &ControllerInput) {
+        self.input.handle(input);
+    }
+
+    /// Shorthand for [ControllerInput::from_glfw_event] and [OrbitController::handle].
+    pub fn handle_glfw_event(&mut self, event: &glfw::WindowEvent) {
+        if let Some(input) = ControllerInput::from_glfw_event(event) {
+            self.handle(&input);
+        }
+    }
+
+    /// Applies the input since the last update over `delta_time` seconds and moves the camera.
+    pub fn update(&mut self, camera: &mut Camera, delta_time: f32) {
+        let (cursor_delta, scroll) = self.input.take_deltas();
+        let input = &self.input;
+
+        if input.is_held(ControllerButton::Left) {
+            self.yaw -= cursor_delta.x * self.rotate_speed;
+            self.pitch += cursor_delta.y * self.rotate_speed;
+        }
+
+        let key_rotation = self.key_rotate_speed * delta_time;
+        self.yaw += input.axis(ControllerKey::Left, ControllerKey::Right) * key_rotation;
+        self.pitch += input.axis(ControllerKey::Down, ControllerKey::Up) * key_rotation;
+        self.pitch = self.pitch.clamp(-PITCH_LIMIT, PITCH_LIMIT);
+
+        let zoom = scroll
+            + input.axis(ControllerKey::Backward, ControllerKey::Forward) * 10.0 * delta_time;
+        self.distance = (self.distance * (1.0 - self.zoom_speed).powf(zoom))
+            .clamp(self.min_distance, self.max_distance);
+
+        let offset = direction(self.yaw, self.pitch) * self.distance;
+
+        if input.is_held(ControllerButton::Right) || input.is_held(ControllerButton::Middle) {
+            // The target follows the cursor, a pixel covering more ground the further away.
+            let forward = -offset.normalize();
+            let right = forward.cross(&camera.up).normalize();
+            let up = right.cross(&forward);
+            let scale = self.distance * self.rotate_speed * 0.5;
+
+            self.target += (-right * cursor_delta.x + up * cursor_delta.y) * scale;
+        }
+
+        camera.target = self.target;
+        camera.position = self.target + offset;
+    }
+}
+
+/// Flies freely: WASD moves, Q and E go down and up, Shift goes faster, and the mouse looks
+/// around while the right button is held.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlyController {
+    pub position: glm::Vec3,
+    /// Around the Y axis, in radians.
+    pub yaw: f32,
+    /// Above the horizon, in radians.
+    pub pitch: f32,
+    /// Units per second.
+    pub speed: f32,
+    /// How much faster Shift moves.
+    pub fast_multiplier: f32,
+    /// Radians per pixel the cursor moves.
+    pub look_speed: f32,
+    input: InputState,
+}
+
+impl FlyController {
+    /// Flies from the camera's position, looking at its target.
+    pub fn new(camera: &Camera) -> Self {
+        let (yaw, pitch) = yaw_pitch(&(camera.target - camera.position));
+
+        Self {
+            position: camera.position,
+            yaw,
+            pitch: pitch.clamp(-PITCH_LIMIT, PITCH_LIMIT),
+            speed: 5.0,
+            fast_multiplier: 4.0,
+            look_speed: 0.003,
+            input: InputState::default(),
+        }
+    }
+
+    pub fn handle(&mut self, input: &ControllerInput) {
+        self.input.handle(input);
+    }
+
+    /// Shorthand for [ControllerInput::from_glfw_event] and [FlyController::handle].
+    pub fn handle_glfw_event(&mut self, event: &glfw::WindowEvent) {
+        if let Some(input) = ControllerInput::from_glfw_event(event) {
+            self.handle(&input);
+        }
+    }
+
+    /// Applies the input since the last update over `delta_time` seconds and moves the camera.
+    pub fn update(&mut self, camera: &mut Camera, delta_time: f32) {
+        let (cursor_delta, _) = self.input.take_deltas();
+        let input = &self.input;
+
+        if input.is_held(ControllerButton::Right) {
+            self.yaw -= cursor_delta.x * self.look_speed;
+            self.pitch =
+                (self.pitch - cursor_delta.y * self.look_speed).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+        }
+
+        let forward = direction(self.yaw, self.pitch);
+        let right = forward.cross(&camera.up).normalize();
+
+        let movement = forward * input.axis(ControllerKey::Backward, ControllerKey::Forward)
+            + right * input.axis(ControllerKey::Left, ControllerKey::Right)
+            + camera.up * input.axis(ControllerKey::Down, ControllerKey::Up);
+
+        if let Some(movement) = movement.try_normalize(f32::EPSILON) {
+            let speed = if input.is_pressed(ControllerKey::Fast) {
+                self.speed * self.fast_multiplier
+            } else {
+                self.speed
+            };
+
+            self.position += movement * speed * delta_time;
+        }
+
+        camera.position = self.position;
+        camera.target = self.position + forward;
+    }
+}
@@ -0,0 +1,172 @@
+//! Conversion of [Color]s into the values a surface format expects.
+
+use ash::vk;
+
+use super::{is_srgb_format, linear_to_srgb, srgb_to_linear};
+
+pub use crate::types::Color;
+
+// These need `std` float math, so they live here rather than next to the type.
+impl Color {
+    /// Creates a color from sRGB-encoded bytes, as found in color pickers and CSS.
+    pub fn srgb_u8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self {
+            r: srgb_to_linear(r as f32 / 255.0),
+            g: srgb_to_linear(g as f32 / 255.0),
+            b: srgb_to_linear(b as f32 / 255.0),
+            a: a as f32 / 255.0,
+        }
+    }
+
+    /// Creates a color from sRGB-encoded hue (degrees), saturation and value in `[0, 1]`.
+    pub fn hsv(hue: f32, saturation: f32, value: f32, alpha: f32) -> Self {
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let m = value - chroma;
+
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+
+        Self {
+            r: srgb_to_linear(r + m),
+            g: srgb_to_linear(g + m),
+            b: srgb_to_linear(b + m),
+            a: alpha,
+        }
+    }
+
+    /// The sRGB-encoded components as an array.
+    pub fn to_srgb_array(&self) -> [f32; 4] {
+        [
+            linear_to_srgb(self.r),
+            linear_to_srgb(self.g),
+            linear_to_srgb(self.b),
+            self.a,
+        ]
+    }
+
+    /// The components to write into an image of the given surface format so the color is displayed
+    /// correctly.
+    ///
+    /// sRGB formats encode on write and linear color spaces expect linear values, so only UNORM
+    /// formats presented in the sRGB non-linear color space need the encoding done here.
+    pub fn for_surface_format(&self, format: vk::SurfaceFormatKHR) -> [f32; 4] {
+        if !is_srgb_format(format.format) && format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
+        {
+            self.to_srgb_array()
+        } else {
+            self.to_linear_array()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: [f32; 4], b: [f32; 4]) {
+        assert!(
+            a.iter().zip(&b).all(|(a, b)| (a - b).abs() < 1e-4),
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn srgb_bytes_round_trip() {
+        for value in 0..=u8::MAX {
+            let color = Color::srgb_u8(value, value, value, value);
+            let encoded = color.to_srgb_array().map(|c| (c * 255.0).round() as u8);
+
+            assert_eq!(encoded, [value; 4]);
+        }
+    }
+
+    #[test]
+    fn srgb_bytes_decode_to_linear() {
+        assert_eq!(Color::srgb_u8(0, 0, 0, 255), Color::BLACK);
+        assert_eq!(Color::srgb_u8(255, 255, 255, 255), Color::WHITE);
+        assert_eq!(Color::srgb_u8(0, 0, 0, 0), Color::TRANSPARENT);
+
+        // Mid grey is much darker in linear space, while alpha stays linear.
+        let grey = Color::srgb_u8(128, 128, 128, 128);
+        assert!((grey.r - 0.2158).abs() < 1e-3);
+        assert_eq!(grey.a, 128.0 / 255.0);
+
+        // Small values are on the linear segment of the curve.
+        assert_eq!(Color::srgb_u8(1, 1, 1, 1).r, 1.0 / 255.0 / 12.92);
+    }
+
+    #[test]
+    fn hsv_primaries() {
+        assert_close(
+            Color::hsv(0.0, 1.0, 1.0, 1.0).to_linear_array(),
+            [1.0, 0.0, 0.0, 1.0],
+        );
+        assert_close(
+            Color::hsv(120.0, 1.0, 1.0, 1.0).to_linear_array(),
+            [0.0, 1.0, 0.0, 1.0],
+        );
+        assert_close(
+            Color::hsv(240.0, 1.0, 1.0, 0.5).to_linear_array(),
+            [0.0, 0.0, 1.0, 0.5],
+        );
+        assert_close(
+            Color::hsv(60.0, 1.0, 1.0, 1.0).to_linear_array(),
+            [1.0, 1.0, 0.0, 1.0],
+        );
+    }
+
+    #[test]
+    fn hsv_wraps_the_hue() {
+        let red = Color::hsv(0.0, 1.0, 1.0, 1.0).to_linear_array();
+
+        assert_close(Color::hsv(360.0, 1.0, 1.0, 1.0).to_linear_array(), red);
+        assert_close(Color::hsv(-360.0, 1.0, 1.0, 1.0).to_linear_array(), red);
+        assert_close(
+            Color::hsv(300.0, 1.0, 1.0, 1.0).to_linear_array(),
+            Color::hsv(-60.0, 1.0, 1.0, 1.0).to_linear_array(),
+        );
+    }
+
+    #[test]
+    fn hsv_without_saturation_is_grey() {
+        let grey = Color::hsv(200.0, 0.0, 0.5, 1.0).to_srgb_array();
+
+        assert_close(grey, [0.5, 0.5, 0.5, 1.0]);
+        assert_eq!(Color::hsv(123.0, 1.0, 0.0, 1.0), Color::BLACK);
+    }
+
+    #[test]
+    fn surface_formats_get_the_encoding_they_expect() {
+        let grey = Color::linear(0.5, 0.5, 0.5, 0.5);
+        let format = |format| vk::SurfaceFormatKHR {
+            format,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        };
+
+        assert_eq!(
+            grey.for_surface_format(format(vk::Format::B8G8R8A8_SRGB)),
+            grey.to_linear_array()
+        );
+        assert_eq!(
+            grey.for_surface_format(format(vk::Format::B8G8R8A8_UNORM)),
+            grey.to_srgb_array()
+        );
+        assert_eq!(
+            grey.for_surface_format(vk::SurfaceFormatKHR {
+                format: vk::Format::R16G16B16A16_SFLOAT,
+                color_space: vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+            }),
+            grey.to_linear_array()
+        );
+    }
+}
@@ -1,6 +1,10 @@
-use std::{error::Error, fmt};
+use std::{error::Error, ffi::CStr, fmt};
 
-use super::{Extensions, Instance, PropertiesConversionError, SwapchainSupportDetails};
+use super::{
+    host_allocation_callbacks, supports_protected_memory, ErrorCtx, Extensions, Instance, Poison,
+    Profile, ProfileMismatch, PropertiesConversionError, ProtectedFlags, Queue, QueueConfig,
+    QueueConfigError, QueueError, QueueRole, SwapchainSupportDetails,
+};
 use ash::{khr::surface, prelude::*, vk};
 
 /// Represents a Vulkan physical and logical device.
@@ -17,17 +21,47 @@ pub struct Device<T: AsRef<Instance>> {
     pub swapchain_support: SwapchainSupportDetails,
     /// The Vulkan logical device.
     pub logical: ash::Device,
-    /// The Vulkan queue.
-    pub queue: vk::Queue,
+    /// The first queue of the graphics family.
+    pub graphics_queue: vk::Queue,
+    /// The first queue of the present family, the same as the graphics queue when both families
+    /// are the same.
+    pub present_queue: vk::Queue,
+    /// The queues created from the [QueueConfig], by role.
+    queues: Vec<(QueueRole, Queue)>,
+    /// The protected flags to create objects with.
+    protected: ProtectedFlags,
+    /// The device extensions the device was created with.
+    enabled_extensions: Extensions,
+    /// The core features the device was created with.
+    enabled_features: vk::PhysicalDeviceFeatures,
+    poison: Poison,
 }
 
 impl<T: AsRef<Instance>> Device<T> {
-    /// Creates a new Vulkan device.
+    /// Creates a new Vulkan device without any optional features, see [super::DeviceBuilder] to
+    /// request some.
     pub fn new(
         instance: T,
         extensions: &Extensions,
         surface_instance: &surface::Instance,
         surface: vk::SurfaceKHR,
+    ) -> Result<Self, DeviceError> {
+        Self::with_queues(
+            instance,
+            extensions,
+            surface_instance,
+            surface,
+            &QueueConfig::default(),
+        )
+    }
+
+    /// Creates a new Vulkan device with the queues described by the config.
+    pub fn with_queues(
+        instance: T,
+        extensions: &Extensions,
+        surface_instance: &surface::Instance,
+        surface: vk::SurfaceKHR,
+        queue_config: &QueueConfig,
     ) -> Result<Self, DeviceError> {
         let devices = unsafe {
             instance
@@ -40,69 +74,208 @@ impl<T: AsRef<Instance>> Device<T> {
             return Err(DeviceError::NoDevices);
         }
 
-        let mut detected = None;
-
-        for physical_device in devices {
-            if let Ok(v) = QueueFamilyIndices::find_queue_families(
-                instance.as_ref(),
-                physical_device,
-                surface_instance,
-                surface,
-            ) {
-                if v.is_complete()
-                    && check_device_extension_support(
-                        instance.as_ref(),
-                        physical_device,
-                        &Extensions::default(),
-                    )
-                    .map_err(DeviceError::from)?
-                {
-                    let local_swapchain_support = SwapchainSupportDetails::query_support(
-                        surface_instance,
-                        surface,
-                        physical_device,
-                    )?;
-
-                    if !local_swapchain_support.formats.is_empty()
-                        && !local_swapchain_support.present_modes.is_empty()
-                    {
-                        detected = Some((
-                            physical_device,
-                            v.graphics_family.unwrap() as u32,
-                            v.present_family.unwrap() as u32,
-                            local_swapchain_support,
-                        ));
-
-                        break;
-                    }
-                }
+        for physical in devices {
+            if let Some(suitable) =
+                probe_physical_device(instance.as_ref(), physical, surface_instance, surface)?
+            {
+                return Self::create(instance, physical, suitable, extensions, queue_config);
+            }
+        }
+
+        Err(DeviceError::NoSuitableDevices)
+    }
+
+    /// Creates a new Vulkan device on the first suitable physical device meeting the profile.
+    ///
+    /// The profile's extensions and features are enabled on top of the given extensions.
+    pub fn with_profile(
+        instance: T,
+        extensions: &Extensions,
+        surface_instance: &surface::Instance,
+        surface: vk::SurfaceKHR,
+        profile: &Profile,
+    ) -> Result<Self, DeviceError> {
+        let devices = unsafe {
+            instance
+                .as_ref()
+                .enumerate_physical_devices()
+                .map_err(DeviceError::from)?
+        };
+
+        if devices.is_empty() {
+            return Err(DeviceError::NoDevices);
+        }
+
+        let mut extensions = extensions.clone();
+
+        for extension in profile.extensions {
+            if !extensions.iter().any(|e| e.as_c_str() == *extension) {
+                extensions.push((*extension).to_owned());
+            }
+        }
+
+        for physical in devices {
+            if profile.check(instance.as_ref(), physical).is_err() {
+                continue;
+            }
+
+            if let Some(suitable) =
+                probe_physical_device(instance.as_ref(), physical, surface_instance, surface)?
+            {
+                return Self::create_with_features(
+                    instance,
+                    physical,
+                    suitable,
+                    &extensions,
+                    &QueueConfig::default(),
+                    profile.enabled_features(),
+                );
+            }
+        }
+
+        Err(DeviceError::NoSuitableDevices)
+    }
+
+    /// Creates a logical device on every suitable physical device, e.g. one per GPU.
+    ///
+    /// Each device gets its own queues and memory; use [copy_memory_via_host] to move data
+    /// between them.
+    pub fn new_all(
+        instance: T,
+        extensions: &Extensions,
+        surface_instance: &surface::Instance,
+        surface: vk::SurfaceKHR,
+    ) -> Result<Vec<Self>, DeviceError>
+    where
+        T: Clone,
+    {
+        let devices = unsafe {
+            instance
+                .as_ref()
+                .enumerate_physical_devices()
+                .map_err(DeviceError::from)?
+        };
+
+        if devices.is_empty() {
+            return Err(DeviceError::NoDevices);
+        }
+
+        let mut created = Vec::new();
+
+        for physical in devices {
+            if let Some(suitable) =
+                probe_physical_device(instance.as_ref(), physical, surface_instance, surface)?
+            {
+                created.push(Self::create(
+                    instance.clone(),
+                    physical,
+                    suitable,
+                    extensions,
+                    &QueueConfig::default(),
+                )?);
             }
         }
 
-        let Some((physical, graphics_family, present_family, swapchain_support)) = detected else {
+        if created.is_empty() {
             return Err(DeviceError::NoSuitableDevices);
+        }
+
+        Ok(created)
+    }
+
+    fn create(
+        instance: T,
+        physical: vk::PhysicalDevice,
+        suitable: (u32, u32, SwapchainSupportDetails),
+        extensions: &Extensions,
+        queue_config: &QueueConfig,
+    ) -> Result<Self, DeviceError> {
+        Self::create_with_features(
+            instance,
+            physical,
+            suitable,
+            extensions,
+            queue_config,
+            vk::PhysicalDeviceFeatures::default(),
+        )
+    }
+
+    pub(super) fn create_with_features(
+        instance: T,
+        physical: vk::PhysicalDevice,
+        (graphics_family, present_family, swapchain_support): (u32, u32, SwapchainSupportDetails),
+        extensions: &Extensions,
+        queue_config: &QueueConfig,
+        device_features: vk::PhysicalDeviceFeatures,
+    ) -> Result<Self, DeviceError> {
+        let families = unsafe {
+            instance
+                .as_ref()
+                .get_physical_device_queue_family_properties(physical)
         };
 
-        let queue_priority = [1.0];
-        let queue_family_indices = [graphics_family, present_family];
-        let queue_create_infos = create_queue_create_infos(&queue_family_indices, &queue_priority);
-        let device_features = vk::PhysicalDeviceFeatures::default();
+        let queue_plan = queue_config.plan(&families, graphics_family, present_family)?;
+        let queue_create_infos = queue_plan.create_infos();
 
         let extensions_ptr = extensions.as_vec_ptr();
 
-        let create_info = vk::DeviceCreateInfo::default()
+        let mut create_info = vk::DeviceCreateInfo::default()
             .queue_create_infos(&queue_create_infos)
             .enabled_features(&device_features)
             .enabled_extension_names(&extensions_ptr);
 
+        let mut protected_features =
+            vk::PhysicalDeviceProtectedMemoryFeatures::default().protected_memory(true);
+
+        if queue_plan.protected.enabled {
+            if !supports_protected_memory(instance.as_ref(), physical) {
+                return Err(DeviceError::ProtectedMemoryUnsupported);
+            }
+
+            create_info = create_info.push_next(&mut protected_features);
+        }
+
         let logical = unsafe {
             instance
                 .as_ref()
-                .create_device(physical, &create_info, None)
-                .map_err(DeviceError::from)
-        }?;
-
-        let queue = unsafe { logical.get_device_queue(graphics_family, 0) };
+                .create_device(physical, &create_info, host_allocation_callbacks())
+        }
+        .map_err(|e| {
+            DeviceError::Context(ErrorCtx::new("creating logical device", e).details(format!(
+                "graphics_family={}, present_family={}, extensions={:?}",
+                graphics_family,
+                present_family,
+                extensions.as_vec_str()
+            )))
+        })?;
+
+        let graphics_queue = unsafe { logical.get_device_queue(graphics_family, 0) };
+        let present_queue = unsafe { logical.get_device_queue(present_family, 0) };
+
+        let poison = Poison::default();
+
+        let queues = queue_plan
+            .assignments
+            .iter()
+            .map(|&(role, family, index)| {
+                // Protected queues can only be retrieved through vkGetDeviceQueue2.
+                let queue = if queue_plan.protected.enabled {
+                    let queue_info = vk::DeviceQueueInfo2::default()
+                        .flags(queue_plan.protected.queue())
+                        .queue_family_index(family)
+                        .queue_index(index);
+
+                    unsafe { logical.get_device_queue2(&queue_info) }
+                } else {
+                    unsafe { logical.get_device_queue(family, index) }
+                };
+
+                (
+                    role,
+                    Queue::new(logical.clone(), queue, family, poison.clone()),
+                )
+            })
+            .collect();
 
         Ok(Self {
             instance,
@@ -111,9 +284,330 @@ impl<T: AsRef<Instance>> Device<T> {
             present_family,
             swapchain_support,
             logical,
-            queue,
+            graphics_queue,
+            present_queue,
+            queues,
+            protected: queue_plan.protected,
+            enabled_extensions: extensions.clone(),
+            enabled_features: device_features,
+            poison,
         })
     }
+
+    /// Returns the graphics queue.
+    pub fn graphics_queue(&self) -> Queue {
+        Queue::new(
+            self.logical.clone(),
+            self.graphics_queue,
+            self.graphics_family,
+            self.poison.clone(),
+        )
+    }
+
+    /// Returns the present queue.
+    pub fn present_queue(&self) -> Queue {
+        Queue::new(
+            self.logical.clone(),
+            self.present_queue,
+            self.present_family,
+            self.poison.clone(),
+        )
+    }
+
+    /// Returns the queue created for the role, if it was requested.
+    pub fn queue(&self, role: QueueRole) -> Option<&Queue> {
+        self.queues
+            .iter()
+            .find(|(r, _)| *r == role)
+            .map(|(_, queue)| queue)
+    }
+
+    /// The flags objects need to hold protected content on this device, empty when protected
+    /// content isn't enabled.
+    pub fn protected_flags(&self) -> ProtectedFlags {
+        self.protected
+    }
+
+    /// The device extensions the device was created with.
+    pub fn enabled_extensions(&self) -> &Extensions {
+        &self.enabled_extensions
+    }
+
+    /// Whether the device was created with the extension, e.g. an optional one from
+    /// [super::DeviceBuilder].
+    pub fn is_extension_enabled(&self, extension: &CStr) -> bool {
+        self.enabled_extensions
+            .iter()
+            .any(|e| e.as_c_str() == extension)
+    }
+
+    /// The core features the device was created with.
+    pub fn enabled_features(&self) -> &vk::PhysicalDeviceFeatures {
+        &self.enabled_features
+    }
+
+    /// Waits for all queues of the device to become idle.
+    pub fn wait_idle(&self) -> Result<(), QueueError> {
+        self.poison.check()?;
+        self.poison
+            .record(unsafe { self.logical.device_wait_idle() })
+    }
+
+    /// Records one-off work with `record`, e.g. an upload or a readback, submits it to the
+    /// graphics queue and waits for it to finish, returning what `record` returned.
+    ///
+    /// The command buffer comes from a transient pool created for the call and destroyed after
+    /// it, so this is meant for setup and tools rather than every frame. `record` gets the
+    /// command buffer already begun and must not end it.
+    pub fn immediate_submit<R>(
+        &self,
+        record: impl FnOnce(&ash::Device, vk::CommandBuffer) -> R,
+    ) -> Result<R, QueueError> {
+        self.poison.check()?;
+
+        let pool = self.poison.record(unsafe {
+            self.logical.create_command_pool(
+                &vk::CommandPoolCreateInfo::default()
+                    .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+                    .queue_family_index(self.graphics_family),
+                host_allocation_callbacks(),
+            )
+        })?;
+
+        let fence = match self.poison.record(unsafe {
+            self.logical
+                .create_fence(&vk::FenceCreateInfo::default(), host_allocation_callbacks())
+        }) {
+            Ok(fence) => fence,
+            Err(e) => {
+                unsafe {
+                    self.logical
+                        .destroy_command_pool(pool, host_allocation_callbacks())
+                };
+                return Err(e);
+            }
+        };
+
+        let result = self.record_and_wait(pool, fence, record);
+
+        // A failed wait leaves the command buffer possibly pending, waiting for the whole device
+        // is the only way to free it then.
+        if result.is_err() {
+            let _ = unsafe { self.logical.device_wait_idle() };
+        }
+
+        unsafe {
+            self.logical
+                .destroy_fence(fence, host_allocation_callbacks());
+            // Frees the command buffer too.
+            self.logical
+                .destroy_command_pool(pool, host_allocation_callbacks());
+        }
+
+        result
+    }
+
+    fn record_and_wait<R>(
+        &self,
+        pool: vk::CommandPool,
+        fence: vk::Fence,
+        record: impl FnOnce(&ash::Device, vk::CommandBuffer) -> R,
+    ) -> Result<R, QueueError> {
+        let command_buffers = self.poison.record(unsafe {
+            self.logical.allocate_command_buffers(
+                &vk::CommandBufferAllocateInfo::default()
+                    .command_pool(pool)
+                    .level(vk::CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(1),
+            )
+        })?;
+
+        self.poison.record(unsafe {
+            self.logical.begin_command_buffer(
+                command_buffers[0],
+                &vk::CommandBufferBeginInfo::default()
+                    .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+            )
+        })?;
+
+        let value = record(&self.logical, command_buffers[0]);
+
+        self.poison
+            .record(unsafe { self.logical.end_command_buffer(command_buffers[0]) })?;
+
+        self.graphics_queue().submit(
+            &[vk::SubmitInfo::default().command_buffers(&command_buffers)],
+            fence,
+        )?;
+
+        self.poison
+            .record(unsafe { self.logical.wait_for_fences(&[fence], true, u64::MAX) })?;
+
+        Ok(value)
+    }
+
+    /// The error that made the device unusable, if any.
+    pub fn poisoned(&self) -> Option<vk::Result> {
+        self.poison.get()
+    }
+
+    /// The poison flag shared with the queues of this device.
+    pub fn poison(&self) -> &Poison {
+        &self.poison
+    }
+
+    /// Returns the first candidate format supporting the requested features with the given tiling.
+    pub fn find_supported_format(
+        &self,
+        candidates: &[vk::Format],
+        tiling: vk::ImageTiling,
+        features: vk::FormatFeatureFlags,
+    ) -> Option<vk::Format> {
+        candidates.iter().copied().find(|&format| {
+            let properties = self.format_properties(format);
+
+            match tiling {
+                vk::ImageTiling::LINEAR => properties.linear_tiling_features.contains(features),
+                vk::ImageTiling::OPTIMAL => properties.optimal_tiling_features.contains(features),
+                _ => false,
+            }
+        })
+    }
+
+    /// Returns the best supported depth format for an optimally tiled depth attachment.
+    pub fn find_depth_format(&self) -> Option<vk::Format> {
+        self.find_supported_format(
+            &[
+                vk::Format::D32_SFLOAT,
+                vk::Format::D32_SFLOAT_S8_UINT,
+                vk::Format::D24_UNORM_S8_UINT,
+            ],
+            vk::ImageTiling::OPTIMAL,
+            vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+        )
+    }
+
+    /// Whether an optimally tiled image of this format can be used as blit source and destination
+    /// with linear filtering, as needed to generate mipmaps.
+    pub fn supports_blit(&self, format: vk::Format) -> bool {
+        self.format_properties(format)
+            .optimal_tiling_features
+            .contains(
+                vk::FormatFeatureFlags::BLIT_SRC
+                    | vk::FormatFeatureFlags::BLIT_DST
+                    | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR,
+            )
+    }
+
+    /// Queries the format properties of the physical device.
+    pub fn format_properties(&self, format: vk::Format) -> vk::FormatProperties {
+        unsafe {
+            self.instance
+                .as_ref()
+                .get_physical_device_format_properties(self.physical, format)
+        }
+    }
+}
+
+/// Checks whether the physical device can be used, returning its graphics and present queue
+/// families and swapchain support.
+pub(super) fn probe_physical_device(
+    instance: &ash::Instance,
+    physical: vk::PhysicalDevice,
+    surface_instance: &surface::Instance,
+    surface: vk::SurfaceKHR,
+) -> Result<Option<(u32, u32, SwapchainSupportDetails)>, DeviceError> {
+    let Ok(QueueFamilyIndices {
+        graphics_family: Some(graphics_family),
+        present_family: Some(present_family),
+    }) = QueueFamilyIndices::find_queue_families(instance, physical, surface_instance, surface)
+    else {
+        return Ok(None);
+    };
+
+    if !check_device_extension_support(instance, physical, &Extensions::default())? {
+        return Ok(None);
+    }
+
+    let swapchain_support =
+        SwapchainSupportDetails::query_support(surface_instance, surface, physical)?;
+
+    if swapchain_support.formats.is_empty() || swapchain_support.present_modes.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some((
+        graphics_family as u32,
+        present_family as u32,
+        swapchain_support,
+    )))
+}
+
+/// Copies bytes between memory allocated on two different devices by mapping both.
+///
+/// Both allocations must be `HOST_VISIBLE`. The ranges are invalidated and flushed, so for
+/// non-coherent memory the offsets and size must be multiples of `nonCoherentAtomSize`. The source
+/// device must have finished writing the source range.
+pub fn copy_memory_via_host<A: AsRef<Instance>, B: AsRef<Instance>>(
+    src: &Device<A>,
+    src_memory: vk::DeviceMemory,
+    src_offset: vk::DeviceSize,
+    dst: &Device<B>,
+    dst_memory: vk::DeviceMemory,
+    dst_offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+) -> VkResult<()> {
+    unsafe {
+        let src_ptr =
+            src.logical
+                .map_memory(src_memory, src_offset, size, vk::MemoryMapFlags::empty())?;
+
+        let src_range = vk::MappedMemoryRange::default()
+            .memory(src_memory)
+            .offset(src_offset)
+            .size(size);
+
+        if let Err(e) = src.logical.invalidate_mapped_memory_ranges(&[src_range]) {
+            src.logical.unmap_memory(src_memory);
+            return Err(e);
+        }
+
+        let dst_ptr =
+            match dst
+                .logical
+                .map_memory(dst_memory, dst_offset, size, vk::MemoryMapFlags::empty())
+            {
+                Ok(ptr) => ptr,
+                Err(e) => {
+                    src.logical.unmap_memory(src_memory);
+                    return Err(e);
+                }
+            };
+
+        std::ptr::copy_nonoverlapping(src_ptr as *const u8, dst_ptr as *mut u8, size as usize);
+
+        let dst_range = vk::MappedMemoryRange::default()
+            .memory(dst_memory)
+            .offset(dst_offset)
+            .size(size);
+
+        let result = dst.logical.flush_mapped_memory_ranges(&[dst_range]);
+
+        dst.logical.unmap_memory(dst_memory);
+        src.logical.unmap_memory(src_memory);
+
+        result
+    }
+}
+
+/// Whether the format has a stencil component.
+pub fn has_stencil_component(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::D32_SFLOAT_S8_UINT
+            | vk::Format::D24_UNORM_S8_UINT
+            | vk::Format::D16_UNORM_S8_UINT
+    )
 }
 
 /// Represents an error that occurred while creating a device.
@@ -125,8 +619,23 @@ pub enum DeviceError {
     NoSuitableDevices,
     /// An error occurred while converting extension properties.
     PropertiesConversion(PropertiesConversionError),
+    /// Protected queues were requested but the device lacks the `protectedMemory` feature.
+    ProtectedMemoryUnsupported,
+    /// The queue configuration can't be satisfied by the device.
+    QueueConfig(QueueConfigError),
+    /// No device supports the features and extensions requested through a
+    /// [super::DeviceBuilder], with what each candidate lacks.
+    MissingRequirements(Vec<UnsupportedDevice>),
     /// A Vulkan error occurred.
     VulkanError(vk::Result),
+    /// A Vulkan error occurred during a labelled operation.
+    Context(ErrorCtx),
+}
+
+impl From<QueueConfigError> for DeviceError {
+    fn from(error: QueueConfigError) -> Self {
+        DeviceError::QueueConfig(error)
+    }
 }
 
 impl From<PropertiesConversionError> for DeviceError {
@@ -146,14 +655,56 @@ impl fmt::Display for DeviceError {
         match self {
             Self::NoDevices => write!(f, "no devices found"),
             Self::NoSuitableDevices => write!(f, "no suitable devices found"),
+            Self::ProtectedMemoryUnsupported => {
+                write!(f, "device does not support protected memory")
+            }
             Self::VulkanError(e) => e.fmt(f),
+            Self::Context(e) => e.fmt(f),
             Self::PropertiesConversion(e) => e.fmt(f),
+            Self::QueueConfig(e) => e.fmt(f),
+            Self::MissingRequirements(devices) => {
+                write!(
+                    f,
+                    "no device supports the requested features and extensions"
+                )?;
+
+                for device in devices {
+                    write!(f, "; {}", device)?;
+                }
+
+                Ok(())
+            }
         }
     }
 }
 
 impl Error for DeviceError {}
 
+/// A device rejected by a [super::DeviceBuilder] and what it's missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedDevice {
+    /// The name of the physical device.
+    pub name: String,
+    /// The requested features and required extensions it lacks.
+    pub missing: Vec<ProfileMismatch>,
+}
+
+impl fmt::Display for UnsupportedDevice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.name)?;
+
+        for (i, mismatch) in self.missing.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{}", mismatch)?;
+        }
+
+        Ok(())
+    }
+}
+
 /// Checks if a device supports the required extensions.
 pub fn check_device_extension_support(
     instance: &ash::Instance,
@@ -233,7 +784,7 @@ pub fn create_queue_create_infos<'a>(
         if !processed_indices.contains(&index) {
             let queue_create_info = vk::DeviceQueueCreateInfo::default()
                 .queue_family_index(index)
-                .queue_priorities(&queue_priority);
+                .queue_priorities(queue_priority);
 
             queue_create_infos.push(queue_create_info);
             processed_indices.push(index);
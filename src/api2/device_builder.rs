@@ -0,0 +1,201 @@
+//! Builder for creating a [Device] with requested features and extensions.
+
+use std::ffi::CStr;
+
+use ash::{khr::surface, vk};
+
+use super::{
+    device::probe_physical_device, Device, DeviceError, Extensions, Instance, ProfileFeature,
+    ProfileMismatch, QueueConfig, UnsupportedDevice,
+};
+
+/// Builder for creating a [Device] on the first suitable physical device that supports every
+/// requested feature and required extension.
+#[derive(Clone)]
+pub struct DeviceBuilder {
+    /// The core features to enable, all of them required.
+    pub features: Vec<ProfileFeature>,
+    /// The extensions the device must support, the swapchain extension by default.
+    pub required_extensions: Extensions,
+    /// The extensions to enable when supported, see [Device::is_extension_enabled].
+    pub optional_extensions: Extensions,
+    /// The queues to create.
+    pub queue_config: QueueConfig,
+}
+
+impl Default for DeviceBuilder {
+    fn default() -> Self {
+        Self {
+            features: Vec::new(),
+            required_extensions: Extensions::from([vk::KHR_SWAPCHAIN_NAME]),
+            optional_extensions: Extensions::new(),
+            queue_config: QueueConfig::default(),
+        }
+    }
+}
+
+impl DeviceBuilder {
+    /// Request a core feature.
+    pub fn feature(mut self, feature: ProfileFeature) -> Self {
+        if !self.features.iter().any(|f| f.name == feature.name) {
+            self.features.push(feature);
+        }
+
+        self
+    }
+
+    /// Request anisotropic filtering.
+    pub fn sampler_anisotropy(self) -> Self {
+        self.feature(ProfileFeature::SAMPLER_ANISOTROPY)
+    }
+
+    /// Request line and point polygon modes.
+    pub fn fill_mode_non_solid(self) -> Self {
+        self.feature(ProfileFeature::FILL_MODE_NON_SOLID)
+    }
+
+    /// Request line widths other than 1.0.
+    pub fn wide_lines(self) -> Self {
+        self.feature(ProfileFeature::WIDE_LINES)
+    }
+
+    /// Require a device extension.
+    pub fn required_extension(mut self, extension: &CStr) -> Self {
+        if !self
+            .required_extensions
+            .iter()
+            .any(|e| e.as_c_str() == extension)
+        {
+            self.required_extensions.push(extension.to_owned());
+        }
+
+        self
+    }
+
+    /// Enable a device extension if the device supports it.
+    pub fn optional_extension(mut self, extension: &CStr) -> Self {
+        if !self
+            .optional_extensions
+            .iter()
+            .any(|e| e.as_c_str() == extension)
+        {
+            self.optional_extensions.push(extension.to_owned());
+        }
+
+        self
+    }
+
+    /// Set the queues to create.
+    pub fn queue_config(mut self, queue_config: QueueConfig) -> Self {
+        self.queue_config = queue_config;
+        self
+    }
+
+    /// Build the [Device].
+    ///
+    /// Fails with [DeviceError::MissingRequirements] listing what each presentable device
+    /// lacks when none of them supports everything requested.
+    pub fn build<T: AsRef<Instance>>(
+        &self,
+        instance: T,
+        surface_instance: &surface::Instance,
+        surface: vk::SurfaceKHR,
+    ) -> Result<Device<T>, DeviceError> {
+        let devices = unsafe {
+            instance
+                .as_ref()
+                .enumerate_physical_devices()
+                .map_err(DeviceError::from)?
+        };
+
+        if devices.is_empty() {
+            return Err(DeviceError::NoDevices);
+        }
+
+        let mut unsupported = Vec::new();
+
+        for physical in devices {
+            let Some(suitable) =
+                probe_physical_device(instance.as_ref(), physical, surface_instance, surface)?
+            else {
+                continue;
+            };
+
+            let available = Extensions::try_from(unsafe {
+                instance
+                    .as_ref()
+                    .enumerate_device_extension_properties(physical)
+                    .map_err(DeviceError::from)?
+            })?;
+
+            let missing = self.missing(instance.as_ref(), physical, &available);
+
+            if !missing.is_empty() {
+                let properties =
+                    unsafe { instance.as_ref().get_physical_device_properties(physical) };
+
+                unsupported.push(UnsupportedDevice {
+                    name: properties
+                        .device_name_as_c_str()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    missing,
+                });
+
+                continue;
+            }
+
+            let mut extensions = self.required_extensions.clone();
+
+            for extension in self.optional_extensions.iter() {
+                if available.contains(extension) && !extensions.contains(extension) {
+                    extensions.push(extension.clone());
+                }
+            }
+
+            let mut features = vk::PhysicalDeviceFeatures::default();
+
+            for feature in &self.features {
+                (feature.enable)(&mut features);
+            }
+
+            return Device::create_with_features(
+                instance,
+                physical,
+                suitable,
+                &extensions,
+                &self.queue_config,
+                features,
+            );
+        }
+
+        if unsupported.is_empty() {
+            Err(DeviceError::NoSuitableDevices)
+        } else {
+            Err(DeviceError::MissingRequirements(unsupported))
+        }
+    }
+
+    /// The requested features and required extensions the physical device lacks.
+    fn missing(
+        &self,
+        instance: &ash::Instance,
+        physical: vk::PhysicalDevice,
+        available: &Extensions,
+    ) -> Vec<ProfileMismatch> {
+        let supported = unsafe { instance.get_physical_device_features(physical) };
+
+        self.features
+            .iter()
+            .filter(|feature| !feature.is_set(&supported))
+            .map(|feature| ProfileMismatch::Feature(feature.name))
+            .chain(
+                self.required_extensions
+                    .iter()
+                    .filter(|e| !available.contains(e))
+                    .cloned()
+                    .map(ProfileMismatch::Extension),
+            )
+            .collect()
+    }
+}
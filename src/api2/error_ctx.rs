@@ -0,0 +1,79 @@
+//! Attaching what was being attempted to an error.
+
+use std::{error, fmt};
+
+use ash::vk;
+
+/// An error together with the operation that failed and the parameters it was attempted with.
+///
+/// Displays as `creating swapchain (image_count=3, format=B8G8R8A8_SRGB): ERROR_...`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorCtx<E = vk::Result> {
+    /// What was being attempted, e.g. "creating swapchain".
+    pub operation: &'static str,
+    /// The parameters the operation was attempted with, may be empty.
+    pub details: String,
+    /// The underlying error.
+    pub source: E,
+}
+
+impl<E> ErrorCtx<E> {
+    /// Wraps an error with the operation that failed.
+    pub fn new(operation: &'static str, source: E) -> Self {
+        Self {
+            operation,
+            details: String::new(),
+            source,
+        }
+    }
+
+    /// Sets the parameters the operation was attempted with.
+    pub fn details(mut self, details: impl Into<String>) -> Self {
+        self.details = details.into();
+        self
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for ErrorCtx<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.details.is_empty() {
+            write!(f, "{}: {}", self.operation, self.source)
+        } else {
+            write!(f, "{} ({}): {}", self.operation, self.details, self.source)
+        }
+    }
+}
+
+impl<E: error::Error + 'static> error::Error for ErrorCtx<E> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Adds [ErrorCtx] to the error of a [Result].
+pub trait ResultExt<T, E> {
+    /// Labels the error with the operation that failed.
+    fn context(self, operation: &'static str) -> Result<T, ErrorCtx<E>>;
+
+    /// Labels the error with the operation that failed and its parameters, only formatting them
+    /// on failure.
+    fn with_context<F: FnOnce() -> String>(
+        self,
+        operation: &'static str,
+        details: F,
+    ) -> Result<T, ErrorCtx<E>>;
+}
+
+impl<T, E> ResultExt<T, E> for Result<T, E> {
+    fn context(self, operation: &'static str) -> Result<T, ErrorCtx<E>> {
+        self.map_err(|source| ErrorCtx::new(operation, source))
+    }
+
+    fn with_context<F: FnOnce() -> String>(
+        self,
+        operation: &'static str,
+        details: F,
+    ) -> Result<T, ErrorCtx<E>> {
+        self.map_err(|source| ErrorCtx::new(operation, source).details(details()))
+    }
+}
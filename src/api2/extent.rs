@@ -0,0 +1,257 @@
+//! Math helpers for [vk::Extent2D], [vk::Offset2D] and viewports.
+
+use ash::vk;
+
+use crate::types::{Extent, Offset};
+
+pub use crate::types::{align_up, div_round_up, round_to_multiple};
+
+/// Extra operations on [vk::Extent2D].
+pub trait ExtentExt: Sized {
+    /// Scales both dimensions by `factor`, rounding to the nearest pixel and never going below 1.
+    fn scale(self, factor: f32) -> Self;
+    /// Component-wise minimum.
+    fn min(self, other: Self) -> Self;
+    /// Component-wise maximum.
+    fn max(self, other: Self) -> Self;
+    /// Clamps both dimensions into `[min, max]`.
+    fn clamp(self, min: Self, max: Self) -> Self;
+    /// Number of pixels covered by the extent.
+    fn area(self) -> u64;
+    /// Width divided by height, 0 for zero-height extents.
+    fn aspect_ratio(self) -> f32;
+    /// Whether any of the dimensions is zero.
+    fn is_empty(&self) -> bool;
+}
+
+impl ExtentExt for vk::Extent2D {
+    fn scale(self, factor: f32) -> Self {
+        vk::Extent2D {
+            width: ((self.width as f32 * factor).round() as u32).max(1),
+            height: ((self.height as f32 * factor).round() as u32).max(1),
+        }
+    }
+
+    fn min(self, other: Self) -> Self {
+        Extent::from(self).min(other.into()).into()
+    }
+
+    fn max(self, other: Self) -> Self {
+        Extent::from(self).max(other.into()).into()
+    }
+
+    fn clamp(self, min: Self, max: Self) -> Self {
+        ExtentExt::min(ExtentExt::max(self, min), max)
+    }
+
+    fn area(self) -> u64 {
+        Extent::from(self).area()
+    }
+
+    fn aspect_ratio(self) -> f32 {
+        Extent::from(self).aspect_ratio()
+    }
+
+    fn is_empty(&self) -> bool {
+        Extent::from(*self).is_empty()
+    }
+}
+
+impl From<vk::Extent2D> for Extent {
+    fn from(value: vk::Extent2D) -> Self {
+        Self::new(value.width, value.height)
+    }
+}
+
+impl From<Extent> for vk::Extent2D {
+    fn from(value: Extent) -> Self {
+        vk::Extent2D {
+            width: value.width,
+            height: value.height,
+        }
+    }
+}
+
+/// Extra operations on [vk::Offset2D].
+pub trait OffsetExt: Sized {
+    /// Component-wise addition.
+    fn add(self, other: Self) -> Self;
+    /// Component-wise subtraction.
+    fn sub(self, other: Self) -> Self;
+}
+
+impl OffsetExt for vk::Offset2D {
+    fn add(self, other: Self) -> Self {
+        (Offset::from(self) + Offset::from(other)).into()
+    }
+
+    fn sub(self, other: Self) -> Self {
+        (Offset::from(self) - Offset::from(other)).into()
+    }
+}
+
+impl From<vk::Offset2D> for Offset {
+    fn from(value: vk::Offset2D) -> Self {
+        Self::new(value.x, value.y)
+    }
+}
+
+impl From<Offset> for vk::Offset2D {
+    fn from(value: Offset) -> Self {
+        vk::Offset2D {
+            x: value.x,
+            y: value.y,
+        }
+    }
+}
+
+/// Computes the largest viewport with the `target_aspect` ratio centered inside `extent`, adding
+/// bars on the sides (pillarbox) or top and bottom (letterbox) as needed.
+pub fn letterbox_viewport(extent: vk::Extent2D, target_aspect: f32) -> vk::Viewport {
+    let (width, height) = (extent.width as f32, extent.height as f32);
+
+    let (viewport_width, viewport_height) = if target_aspect <= 0.0 || extent.is_empty() {
+        (width, height)
+    } else if width / height > target_aspect {
+        (height * target_aspect, height)
+    } else {
+        (width, width / target_aspect)
+    };
+
+    vk::Viewport {
+        x: ((width - viewport_width) / 2.0).floor(),
+        y: ((height - viewport_height) / 2.0).floor(),
+        width: viewport_width.floor(),
+        height: viewport_height.floor(),
+        min_depth: 0.0,
+        max_depth: 1.0,
+    }
+}
+
+/// Returns the scissor rect covering the same pixels as the viewport.
+pub fn viewport_scissor(viewport: &vk::Viewport) -> vk::Rect2D {
+    vk::Rect2D {
+        offset: vk::Offset2D {
+            x: viewport.x as i32,
+            y: viewport.y as i32,
+        },
+        extent: vk::Extent2D {
+            width: viewport.width as u32,
+            height: viewport.height as u32,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extent(width: u32, height: u32) -> vk::Extent2D {
+        vk::Extent2D { width, height }
+    }
+
+    #[test]
+    fn scale_rounds_and_never_reaches_zero() {
+        assert_eq!(extent(1920, 1080).scale(0.5), extent(960, 540));
+        assert_eq!(extent(333, 7).scale(1.5), extent(500, 11));
+        assert_eq!(extent(1, 1).scale(0.1), extent(1, 1));
+        assert_eq!(extent(0, 0).scale(2.0), extent(1, 1));
+        assert_eq!(
+            extent(u32::MAX, u32::MAX).scale(1.0),
+            extent(u32::MAX, u32::MAX)
+        );
+    }
+
+    #[test]
+    fn clamp_is_component_wise() {
+        let min = extent(1, 1);
+        let max = extent(4096, 2048);
+
+        assert_eq!(extent(800, 600).clamp(min, max), extent(800, 600));
+        assert_eq!(extent(0, 0).clamp(min, max), min);
+        assert_eq!(extent(u32::MAX, 3).clamp(min, max), extent(4096, 3));
+        assert_eq!(extent(5000, u32::MAX).clamp(min, max), max);
+    }
+
+    #[test]
+    fn area_and_aspect_ratio() {
+        assert_eq!(extent(1920, 1080).area(), 2_073_600);
+        assert_eq!(
+            extent(u32::MAX, u32::MAX).area(),
+            u32::MAX as u64 * u32::MAX as u64
+        );
+        assert_eq!(extent(0, 1080).area(), 0);
+        assert_eq!(extent(1920, 0).aspect_ratio(), 0.0);
+        assert_eq!(extent(1, 1).aspect_ratio(), 1.0);
+        assert!(extent(0, 1).is_empty());
+        assert!(extent(1, 0).is_empty());
+        assert!(!extent(1, 1).is_empty());
+    }
+
+    #[test]
+    fn offsets_add_and_subtract() {
+        let a = vk::Offset2D { x: 10, y: -5 };
+        let b = vk::Offset2D { x: 3, y: 7 };
+
+        assert_eq!(a.add(b), vk::Offset2D { x: 13, y: 2 });
+        assert_eq!(a.sub(b), vk::Offset2D { x: 7, y: -12 });
+    }
+
+    #[test]
+    fn integer_rounding() {
+        assert_eq!(div_round_up(0, 4), 0);
+        assert_eq!(div_round_up(1, 4), 1);
+        assert_eq!(div_round_up(9, 4), 3);
+        assert_eq!(div_round_up(u32::MAX, 1), u32::MAX);
+        assert_eq!(align_up(0, 256), 0);
+        assert_eq!(align_up(1, 256), 256);
+        assert_eq!(align_up(300, 256), 512);
+        assert_eq!(align_up(512, 256), 512);
+        assert_eq!(round_to_multiple(7, 0), 7);
+        assert_eq!(round_to_multiple(7, 3), 6);
+        assert_eq!(round_to_multiple(8, 3), 9);
+    }
+
+    #[test]
+    fn letterbox_adds_bars_on_the_longer_side() {
+        // A 4:3 image in a 16:9 window gets pillarboxed.
+        let viewport = letterbox_viewport(extent(1920, 1080), 4.0 / 3.0);
+        assert_eq!((viewport.x, viewport.y), (240.0, 0.0));
+        assert_eq!((viewport.width, viewport.height), (1440.0, 1080.0));
+
+        // A 16:9 image in a 4:3 window gets letterboxed.
+        let viewport = letterbox_viewport(extent(1024, 768), 16.0 / 9.0);
+        assert_eq!((viewport.x, viewport.y), (0.0, 96.0));
+        assert_eq!((viewport.width, viewport.height), (1024.0, 576.0));
+
+        // Odd sizes floor towards the top-left.
+        let viewport = letterbox_viewport(extent(101, 100), 1.0);
+        assert_eq!((viewport.x, viewport.y), (0.0, 0.0));
+        assert_eq!((viewport.width, viewport.height), (100.0, 100.0));
+    }
+
+    #[test]
+    fn letterbox_falls_back_to_the_full_extent() {
+        for (size, aspect) in [
+            (extent(0, 0), 1.0),
+            (extent(640, 0), 1.0),
+            (extent(640, 480), 0.0),
+        ] {
+            let viewport = letterbox_viewport(size, aspect);
+            assert_eq!((viewport.x, viewport.y), (0.0, 0.0));
+            assert_eq!(
+                (viewport.width, viewport.height),
+                (size.width as f32, size.height as f32)
+            );
+        }
+    }
+
+    #[test]
+    fn scissor_matches_viewport() {
+        let viewport = letterbox_viewport(extent(1920, 1080), 1.0);
+        let scissor = viewport_scissor(&viewport);
+
+        assert_eq!(scissor.offset, vk::Offset2D { x: 420, y: 0 });
+        assert_eq!(scissor.extent, extent(1080, 1080));
+    }
+}
@@ -0,0 +1,457 @@
+//! Allocation callbacks for the host memory the driver allocates for its objects, to measure it
+//! or to test custom allocators.
+//!
+//! Vulkan requires an object to be destroyed with callbacks compatible with the ones it was
+//! created with, so there's one allocator for the whole process. It has to be installed with
+//! [install_host_allocator] before the first Vulkan object is created, every create and destroy
+//! call passes [host_allocation_callbacks].
+
+use std::{
+    alloc::{self, Layout},
+    error,
+    ffi::c_void,
+    fmt, mem, ptr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+};
+
+use ash::vk;
+
+/// Serves the host allocations of the driver.
+///
+/// The driver may call it from any thread and expects the usual `malloc` semantics, see
+/// `VkAllocationCallbacks`.
+pub trait HostAllocator: Send + Sync + 'static {
+    /// Returns at least `size` bytes aligned to `alignment`, or null when out of memory.
+    fn allocate(
+        &self,
+        size: usize,
+        alignment: usize,
+        scope: vk::SystemAllocationScope,
+    ) -> *mut c_void;
+
+    /// Resizes an allocation of this allocator, keeping its contents. A null `original`
+    /// allocates and a `size` of 0 frees, returning null.
+    ///
+    /// # Safety
+    ///
+    /// `original` is null or was returned by [HostAllocator::allocate] or
+    /// [HostAllocator::reallocate] of this allocator and wasn't freed yet. It's invalid
+    /// afterwards unless null is returned for a non-zero `size`.
+    unsafe fn reallocate(
+        &self,
+        original: *mut c_void,
+        size: usize,
+        alignment: usize,
+        scope: vk::SystemAllocationScope,
+    ) -> *mut c_void;
+
+    /// Frees an allocation of this allocator, ignoring null.
+    ///
+    /// # Safety
+    ///
+    /// `memory` is null or was returned by [HostAllocator::allocate] or
+    /// [HostAllocator::reallocate] of this allocator and wasn't freed yet.
+    unsafe fn free(&self, memory: *mut c_void);
+
+    /// Told about memory the driver allocated by other means, e.g. for executable code.
+    fn internal_allocation(
+        &self,
+        _size: usize,
+        _kind: vk::InternalAllocationType,
+        _scope: vk::SystemAllocationScope,
+    ) {
+    }
+
+    fn internal_free(
+        &self,
+        _size: usize,
+        _kind: vk::InternalAllocationType,
+        _scope: vk::SystemAllocationScope,
+    ) {
+    }
+}
+
+/// Counters of the host allocations made through a [CountingAllocator].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct HostAllocationStats {
+    pub allocations: u64,
+    pub reallocations: u64,
+    pub frees: u64,
+    /// Allocation calls that returned null.
+    pub failures: u64,
+    pub live_bytes: u64,
+    pub peak_bytes: u64,
+    /// Memory the driver reported allocating by itself.
+    pub internal_bytes: u64,
+}
+
+impl HostAllocationStats {
+    /// Allocations that weren't freed yet.
+    pub fn live_allocations(&self) -> u64 {
+        self.allocations.saturating_sub(self.frees)
+    }
+}
+
+/// Allocates from the global Rust allocator while counting every call, the default
+/// [HostAllocator].
+#[derive(Debug, Default)]
+pub struct CountingAllocator {
+    allocations: AtomicU64,
+    reallocations: AtomicU64,
+    frees: AtomicU64,
+    failures: AtomicU64,
+    live_bytes: AtomicU64,
+    peak_bytes: AtomicU64,
+    internal_bytes: AtomicU64,
+}
+
+impl CountingAllocator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stats(&self) -> HostAllocationStats {
+        HostAllocationStats {
+            allocations: self.allocations.load(Ordering::Relaxed),
+            reallocations: self.reallocations.load(Ordering::Relaxed),
+            frees: self.frees.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            live_bytes: self.live_bytes.load(Ordering::Relaxed),
+            peak_bytes: self.peak_bytes.load(Ordering::Relaxed),
+            internal_bytes: self.internal_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    fn allocate_counted(&self, size: usize, alignment: usize) -> *mut c_void {
+        let Some(layout) = header_layout(size, alignment) else {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+            return ptr::null_mut();
+        };
+
+        // SAFETY: the layout is never zero-sized, it always has room for the header.
+        let base = unsafe { alloc::alloc(layout) };
+
+        if base.is_null() {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+            return ptr::null_mut();
+        }
+
+        let header = layout.align();
+
+        // SAFETY: the allocation starts with `header` bytes, the last two words of which hold
+        // the size and alignment. `header` is a multiple of the word size.
+        unsafe {
+            let memory = base.add(header);
+            memory.cast::<usize>().sub(1).write(size);
+            memory.cast::<usize>().sub(2).write(alignment);
+
+            let live = self.live_bytes.fetch_add(size as u64, Ordering::Relaxed) + size as u64;
+            self.peak_bytes.fetch_max(live, Ordering::Relaxed);
+
+            memory.cast()
+        }
+    }
+
+    /// Frees memory from [CountingAllocator::allocate_counted] and returns its size.
+    ///
+    /// # Safety
+    ///
+    /// `memory` has to come from `allocate_counted` and not be freed yet.
+    unsafe fn free_counted(&self, memory: *mut c_void) -> usize {
+        let memory = memory.cast::<u8>();
+        let size = memory.cast::<usize>().sub(1).read();
+        let alignment = memory.cast::<usize>().sub(2).read();
+        let layout = header_layout(size, alignment).unwrap();
+
+        alloc::dealloc(memory.sub(layout.align()), layout);
+        self.live_bytes.fetch_sub(size as u64, Ordering::Relaxed);
+
+        size
+    }
+}
+
+impl HostAllocator for CountingAllocator {
+    fn allocate(
+        &self,
+        size: usize,
+        alignment: usize,
+        _scope: vk::SystemAllocationScope,
+    ) -> *mut c_void {
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        self.allocate_counted(size, alignment)
+    }
+
+    unsafe fn reallocate(
+        &self,
+        original: *mut c_void,
+        size: usize,
+        alignment: usize,
+        scope: vk::SystemAllocationScope,
+    ) -> *mut c_void {
+        if original.is_null() {
+            return self.allocate(size, alignment, scope);
+        }
+
+        if size == 0 {
+            self.free(original);
+            return ptr::null_mut();
+        }
+
+        self.reallocations.fetch_add(1, Ordering::Relaxed);
+
+        let memory = self.allocate_counted(size, alignment);
+
+        if !memory.is_null() {
+            // SAFETY: `original` came from this allocator and holds its size before it.
+            let original_size = original.cast::<usize>().sub(1).read();
+            ptr::copy_nonoverlapping(
+                original.cast::<u8>(),
+                memory.cast::<u8>(),
+                original_size.min(size),
+            );
+            self.free_counted(original);
+        }
+
+        memory
+    }
+
+    unsafe fn free(&self, memory: *mut c_void) {
+        if memory.is_null() {
+            return;
+        }
+
+        self.frees.fetch_add(1, Ordering::Relaxed);
+        self.free_counted(memory);
+    }
+
+    fn internal_allocation(
+        &self,
+        size: usize,
+        _kind: vk::InternalAllocationType,
+        _scope: vk::SystemAllocationScope,
+    ) {
+        self.internal_bytes
+            .fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    fn internal_free(
+        &self,
+        size: usize,
+        _kind: vk::InternalAllocationType,
+        _scope: vk::SystemAllocationScope,
+    ) {
+        self.internal_bytes
+            .fetch_sub(size as u64, Ordering::Relaxed);
+    }
+}
+
+/// A layout with a header of at least two words in front of `size` bytes, keeping the memory
+/// after the header aligned to `alignment`.
+fn header_layout(size: usize, alignment: usize) -> Option<Layout> {
+    let align = alignment
+        .max(2 * mem::size_of::<usize>())
+        .next_power_of_two();
+    Layout::from_size_align(size.checked_add(align)?, align).ok()
+}
+
+struct Installed {
+    callbacks: vk::AllocationCallbacks<'static>,
+    allocator: &'static dyn HostAllocator,
+}
+
+// SAFETY: the callbacks only point at the allocator, which is `Send + Sync` and never freed.
+unsafe impl Send for Installed {}
+unsafe impl Sync for Installed {}
+
+static INSTALLED: OnceLock<Option<Installed>> = OnceLock::new();
+
+/// Makes every following Vulkan create and destroy call allocate through `allocator`, which
+/// lives as long as the process, e.g. `Box::leak(Box::new(CountingAllocator::new()))`.
+///
+/// Fails once any object was created, with or without an allocator, since objects have to be
+/// destroyed with the callbacks they were created with.
+pub fn install_host_allocator(
+    allocator: &'static dyn HostAllocator,
+) -> Result<(), HostAllocatorError> {
+    // The user data has to be a thin pointer, so the trait object is boxed.
+    let user_data: &'static &'static dyn HostAllocator = Box::leak(Box::new(allocator));
+
+    let callbacks = vk::AllocationCallbacks::default()
+        .user_data(
+            (user_data as *const &'static dyn HostAllocator)
+                .cast_mut()
+                .cast(),
+        )
+        .pfn_allocation(Some(allocation))
+        .pfn_reallocation(Some(reallocation))
+        .pfn_free(Some(free))
+        .pfn_internal_allocation(Some(internal_allocation))
+        .pfn_internal_free(Some(internal_free));
+
+    INSTALLED
+        .set(Some(Installed {
+            callbacks,
+            allocator,
+        }))
+        .map_err(|_| HostAllocatorError::TooLate)
+}
+
+/// The callbacks to pass to every create and destroy call, `None` without an allocator.
+pub fn host_allocation_callbacks() -> Option<&'static vk::AllocationCallbacks<'static>> {
+    INSTALLED
+        .get_or_init(|| None)
+        .as_ref()
+        .map(|installed| &installed.callbacks)
+}
+
+/// The installed allocator.
+pub fn host_allocator() -> Option<&'static dyn HostAllocator> {
+    INSTALLED
+        .get()
+        .and_then(Option::as_ref)
+        .map(|installed| installed.allocator)
+}
+
+/// # Safety
+///
+/// `user_data` is the one set by [install_host_allocator]. The driver only passes memory back
+/// to the allocator that returned it, as [HostAllocator::reallocate] and [HostAllocator::free]
+/// require.
+unsafe fn allocator<'a>(user_data: *mut c_void) -> &'a dyn HostAllocator {
+    *user_data.cast::<&'static dyn HostAllocator>()
+}
+
+unsafe extern "system" fn allocation(
+    user_data: *mut c_void,
+    size: usize,
+    alignment: usize,
+    scope: vk::SystemAllocationScope,
+) -> *mut c_void {
+    allocator(user_data).allocate(size, alignment, scope)
+}
+
+unsafe extern "system" fn reallocation(
+    user_data: *mut c_void,
+    original: *mut c_void,
+    size: usize,
+    alignment: usize,
+    scope: vk::SystemAllocationScope,
+) -> *mut c_void {
+    allocator(user_data).reallocate(original, size, alignment, scope)
+}
+
+unsafe extern "system" fn free(user_data: *mut c_void, memory: *mut c_void) {
+    allocator(user_data).free(memory)
+}
+
+unsafe extern "system" fn internal_allocation(
+    user_data: *mut c_void,
+    size: usize,
+    kind: vk::InternalAllocationType,
+    scope: vk::SystemAllocationScope,
+) {
+    allocator(user_data).internal_allocation(size, kind, scope)
+}
+
+unsafe extern "system" fn internal_free(
+    user_data: *mut c_void,
+    size: usize,
+    kind: vk::InternalAllocationType,
+    scope: vk::SystemAllocationScope,
+) {
+    allocator(user_data).internal_free(size, kind, scope)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum HostAllocatorError {
+    /// A Vulkan object was already created without the allocator.
+    TooLate,
+}
+
+impl fmt::Display for HostAllocatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooLate => write!(
+                f,
+                "the host allocator has to be installed before any Vulkan object is created"
+            ),
+        }
+    }
+}
+
+impl error::Error for HostAllocatorError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCOPE: vk::SystemAllocationScope = vk::SystemAllocationScope::OBJECT;
+
+    #[test]
+    fn counts_allocations_and_live_bytes() {
+        let allocator = CountingAllocator::new();
+
+        let a = allocator.allocate(24, 8, SCOPE);
+        let b = allocator.allocate(100, 64, SCOPE);
+        assert!(!a.is_null() && !b.is_null());
+        assert_eq!(b as usize % 64, 0);
+
+        // SAFETY: both came from this allocator.
+        unsafe {
+            allocator.free(a);
+            allocator.free(ptr::null_mut());
+        }
+
+        let stats = allocator.stats();
+        assert_eq!(stats.allocations, 2);
+        assert_eq!(stats.frees, 1);
+        assert_eq!(stats.live_allocations(), 1);
+        assert_eq!(stats.live_bytes, 100);
+        assert_eq!(stats.peak_bytes, 124);
+
+        // SAFETY: `b` came from this allocator.
+        unsafe { allocator.free(b) };
+        assert_eq!(allocator.stats().live_bytes, 0);
+    }
+
+    #[test]
+    fn reallocate_keeps_contents() {
+        let allocator = CountingAllocator::new();
+
+        // SAFETY: every pointer comes from this allocator and is used within its size.
+        unsafe {
+            let memory = allocator
+                .reallocate(ptr::null_mut(), 4, 4, SCOPE)
+                .cast::<u8>();
+            memory.copy_from_nonoverlapping([1, 2, 3, 4].as_ptr(), 4);
+
+            let grown = allocator
+                .reallocate(memory.cast(), 4096, 16, SCOPE)
+                .cast::<u8>();
+            assert_eq!(std::slice::from_raw_parts(grown, 4), [1, 2, 3, 4]);
+
+            let shrunk = allocator.reallocate(grown.cast(), 1, 1, SCOPE).cast::<u8>();
+            assert_eq!(shrunk.read(), 1);
+
+            assert!(allocator.reallocate(shrunk.cast(), 0, 1, SCOPE).is_null());
+        }
+
+        let stats = allocator.stats();
+        assert_eq!(stats.allocations, 1);
+        assert_eq!(stats.reallocations, 2);
+        assert_eq!(stats.frees, 1);
+        assert_eq!(stats.live_bytes, 0);
+        assert_eq!(stats.peak_bytes, 4100);
+    }
+
+    #[test]
+    fn impossible_layouts_fail() {
+        let allocator = CountingAllocator::new();
+
+        assert!(allocator.allocate(usize::MAX, 8, SCOPE).is_null());
+        assert_eq!(allocator.stats().failures, 1);
+    }
+}
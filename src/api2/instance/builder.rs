@@ -1,19 +1,19 @@
 //! Builder for creating a new [Instance].
 
-use ash::vk::{self, make_api_version};
+use ash::vk;
 
-use super::{print_warnings, Extensions, Instance, InstanceBuilderError};
+use super::{super::Version, print_warnings, Extensions, Instance, InstanceBuilderError};
 
 /// Builder for creating a new [Instance].
 #[derive(Clone, Default)]
 pub struct InstanceBuilder {
     /// The name of the application.
     pub application_name: Option<String>,
-    /// The version of the application.
+    /// The version of the application, this crate's version if `None`.
     pub application_version: Option<u32>,
     /// The name of the engine.
     pub engine_name: Option<String>,
-    /// The version of the engine.
+    /// The version of the engine, this crate's version if `None`.
     pub engine_version: Option<u32>,
     /// The extensions to enable.
     pub extensions: Option<Extensions>,
@@ -119,7 +119,7 @@ impl InstanceBuilder {
         let application_version = self
             .application_version
             .take()
-            .unwrap_or(make_api_version(0, 0, 0, 0));
+            .unwrap_or_else(|| Version::of_crate().to_vulkan());
         let engine_name = self
             .engine_name
             .take()
@@ -127,7 +127,7 @@ impl InstanceBuilder {
         let engine_version = self
             .engine_version
             .take()
-            .unwrap_or(make_api_version(0, 0, 0, 0));
+            .unwrap_or_else(|| Version::of_crate().to_vulkan());
         let extensions = self.extensions.take().unwrap_or_default();
         let layers = self.layers.take().unwrap_or_default();
         let entry = match self.entry.take() {
@@ -1,9 +1,20 @@
 //! Controls the lifecycle of the debug layer.
 
-use std::ffi::c_void;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    ffi::{c_void, CStr},
+    fmt, fs, io,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
 
 use ash::{ext::debug_utils, vk};
 
+use super::super::host_allocation_callbacks;
+
 /// Controls the lifecycle of the debug layer.
 pub struct DebugLayer {
     pub instance: debug_utils::Instance,
@@ -18,7 +29,9 @@ impl DebugLayer {
     ) -> Result<Self, vk::Result> {
         let create_info = create_debug_messenger(callback);
 
-        let messenger = unsafe { instance.create_debug_utils_messenger(&create_info, None)? };
+        let messenger = unsafe {
+            instance.create_debug_utils_messenger(&create_info, host_allocation_callbacks())?
+        };
 
         Ok(Self {
             instance,
@@ -31,7 +44,7 @@ impl Drop for DebugLayer {
     fn drop(&mut self) {
         unsafe {
             self.instance
-                .destroy_debug_utils_messenger(self.messenger, None);
+                .destroy_debug_utils_messenger(self.messenger, host_allocation_callbacks());
         }
     }
 }
@@ -54,7 +67,23 @@ pub fn create_debug_messenger<'a>(
         .pfn_user_callback(callback)
 }
 
-/// Print all messages with a severity of warning or higher.
+static STRICT: AtomicBool = AtomicBool::new(false);
+static SINK: Mutex<Vec<ValidationMessage>> = Mutex::new(Vec::new());
+static RECENT: Mutex<VecDeque<ValidationMessage>> = Mutex::new(VecDeque::new());
+/// The suppressed message IDs, with how often each was suppressed since the last
+/// [take_validation_report].
+static SUPPRESSED: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+
+/// How many messages [recent_validation_messages] keeps.
+pub const RECENT_VALIDATION_MESSAGES: usize = 64;
+
+/// Print all messages with a severity of warning or higher, keeping the last ones for
+/// [recent_validation_messages] and collecting them for [validation_report] in strict mode.
+/// Messages whose ID was suppressed with [suppress_validation_message] are only counted.
+///
+/// # Safety
+///
+/// Only meant to be called by the validation layers, `callback_data` has to be valid.
 pub unsafe extern "system" fn print_warnings(
     severity: vk::DebugUtilsMessageSeverityFlagsEXT,
     _: vk::DebugUtilsMessageTypeFlagsEXT,
@@ -62,16 +91,231 @@ pub unsafe extern "system" fn print_warnings(
     _: *mut c_void,
 ) -> vk::Bool32 {
     if severity >= vk::DebugUtilsMessageSeverityFlagsEXT::WARNING {
-        println!(
-            "validation layer: {}",
-            callback_data
-                .read()
-                .message_as_c_str()
-                .unwrap()
-                .to_str()
-                .unwrap()
-        );
+        // Panicking here would unwind across the FFI boundary, so bad messages are printed lossily.
+        let data = callback_data.as_ref();
+        let id = data
+            .and_then(|data| data.message_id_name_as_c_str())
+            .map(CStr::to_string_lossy);
+
+        if let Some(id) = &id {
+            let mut suppressed = SUPPRESSED.lock().unwrap_or_else(|e| e.into_inner());
+
+            if let Some(count) = suppressed.get_mut(id.as_ref()) {
+                *count += 1;
+                return vk::TRUE;
+            }
+        }
+
+        let message = data
+            .and_then(|data| data.message_as_c_str())
+            .map(CStr::to_string_lossy)
+            .unwrap_or_else(|| "<invalid message>".into());
+
+        println!("validation layer: {}", message);
+
+        let message = ValidationMessage {
+            severity: if severity >= vk::DebugUtilsMessageSeverityFlagsEXT::ERROR {
+                ValidationSeverity::Error
+            } else {
+                ValidationSeverity::Warning
+            },
+            id: id.map(|id| id.into_owned()),
+            message: message.into_owned(),
+        };
+
+        {
+            let mut recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+
+            if recent.len() == RECENT_VALIDATION_MESSAGES {
+                recent.pop_front();
+            }
+
+            recent.push_back(message.clone());
+        }
+
+        if is_strict_validation() {
+            // A poisoned sink still holds every message recorded before the panic.
+            SINK.lock().unwrap_or_else(|e| e.into_inner()).push(message);
+        }
     }
 
     vk::TRUE
 }
+
+/// Starts collecting every warning and error of the validation layers, for tests to turn them
+/// into failures with [validation_report] instead of console noise.
+pub fn enable_strict_validation() {
+    STRICT.store(true, Ordering::Relaxed);
+}
+
+pub fn is_strict_validation() -> bool {
+    STRICT.load(Ordering::Relaxed)
+}
+
+/// The messages collected since strict validation was enabled or the last
+/// [take_validation_report].
+pub fn validation_report() -> ValidationReport {
+    ValidationReport {
+        messages: SINK.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+        suppressed: suppressed_counts(false),
+    }
+}
+
+/// Like [validation_report], clearing the collected messages and suppression counters, e.g.
+/// between tests.
+pub fn take_validation_report() -> ValidationReport {
+    ValidationReport {
+        messages: std::mem::take(&mut *SINK.lock().unwrap_or_else(|e| e.into_inner())),
+        suppressed: suppressed_counts(true),
+    }
+}
+
+fn suppressed_counts(reset: bool) -> BTreeMap<String, u64> {
+    let mut suppressed = SUPPRESSED.lock().unwrap_or_else(|e| e.into_inner());
+
+    let counts = suppressed
+        .iter()
+        .filter(|(_, &count)| count > 0)
+        .map(|(id, &count)| (id.clone(), count))
+        .collect();
+
+    if reset {
+        suppressed.values_mut().for_each(|count| *count = 0);
+    }
+
+    counts
+}
+
+/// Stops printing and collecting the messages with the ID `id`, e.g.
+/// `VUID-vkCmdDraw-None-02859`, counting them in [ValidationReport::suppressed] instead. Meant
+/// for known noise, like messages of a layer bug, not for hiding real problems.
+pub fn suppress_validation_message(id: impl Into<String>) {
+    SUPPRESSED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(id.into())
+        .or_insert(0);
+}
+
+/// Reports the messages with the ID `id` again, returning whether they were suppressed.
+pub fn unsuppress_validation_message(id: &str) -> bool {
+    SUPPRESSED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(id)
+        .is_some()
+}
+
+/// The IDs suppressed with [suppress_validation_message].
+pub fn suppressed_validation_messages() -> Vec<String> {
+    SUPPRESSED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .keys()
+        .cloned()
+        .collect()
+}
+
+/// Suppresses every message ID listed in a file, one per line. Blank lines and lines starting
+/// with `#` are skipped. Returns how many IDs were listed.
+pub fn load_validation_suppressions<P: AsRef<Path>>(path: P) -> io::Result<usize> {
+    let list = fs::read_to_string(path)?;
+    let ids: Vec<_> = list
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    for id in &ids {
+        suppress_validation_message(*id);
+    }
+
+    Ok(ids.len())
+}
+
+/// The last [RECENT_VALIDATION_MESSAGES] warnings and errors, oldest first, whether strict
+/// validation is enabled or not, e.g. for a crash report.
+pub fn recent_validation_messages() -> Vec<ValidationMessage> {
+    RECENT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .cloned()
+        .collect()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValidationSeverity {
+    Warning,
+    Error,
+}
+
+/// A message of the validation layers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ValidationMessage {
+    pub severity: ValidationSeverity,
+    /// The name of the validation check, e.g. `VUID-vkCmdDraw-None-02859`.
+    pub id: Option<String>,
+    pub message: String,
+}
+
+/// The validation messages collected in strict mode.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct ValidationReport {
+    pub messages: Vec<ValidationMessage>,
+    /// How often each suppressed message ID occurred, in every mode, leaving out IDs that
+    /// didn't.
+    pub suppressed: BTreeMap<String, u64>,
+}
+
+impl ValidationReport {
+    /// Whether validation found nothing, what tests assert. Suppressed messages don't count.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// How many messages were suppressed in total.
+    pub fn suppressed_count(&self) -> u64 {
+        self.suppressed.values().sum()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationMessage> {
+        self.messages
+            .iter()
+            .filter(|message| message.severity == ValidationSeverity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationMessage> {
+        self.messages
+            .iter()
+            .filter(|message| message.severity == ValidationSeverity::Warning)
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} validation errors, {} warnings, {} suppressed",
+            self.errors().count(),
+            self.warnings().count(),
+            self.suppressed_count()
+        )?;
+
+        for message in &self.messages {
+            write!(
+                f,
+                "\n{:?} [{}]: {}",
+                message.severity,
+                message.id.as_deref().unwrap_or("unknown"),
+                message.message
+            )?;
+        }
+
+        for (id, count) in &self.suppressed {
+            write!(f, "\nsuppressed [{}]: {} times", id, count)?;
+        }
+
+        Ok(())
+    }
+}
@@ -2,7 +2,7 @@
 
 use std::{borrow::Borrow, ffi::CString, ops::Deref};
 
-use super::Extensions;
+use super::{host_allocation_callbacks, Extensions};
 use ash::{ext::debug_utils, vk};
 
 mod builder;
@@ -27,6 +27,7 @@ impl Instance {
     /// Create a new Vulkan instance with the given parameters.
     ///
     /// You can use the `InstanceBuilder` to create a new instance that's easier to configure and has default values.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         entry: ash::Entry,
         application_name: &str,
@@ -91,7 +92,7 @@ impl Instance {
             create_info = create_info.flags(vk::InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR);
         }
 
-        let instance = unsafe { entry.create_instance(&create_info, None)? };
+        let instance = unsafe { entry.create_instance(&create_info, host_allocation_callbacks())? };
 
         let debug_layer = if enable_debug_layer {
             Some(DebugLayer::new(
@@ -133,7 +134,7 @@ impl Drop for Instance {
         }
 
         unsafe {
-            self.instance.destroy_instance(None);
+            self.instance.destroy_instance(host_allocation_callbacks());
         }
     }
 }
@@ -161,5 +162,5 @@ impl Borrow<ash::Instance> for Instance {
 /// Get the validation layers to enable.
 #[inline]
 pub fn get_validation_layers() -> [CString; 1] {
-    [CString::new("VK_LAYER_KHRONOS_validation").unwrap()]
+    [c"VK_LAYER_KHRONOS_validation".to_owned()]
 }
@@ -0,0 +1,268 @@
+//! Renderer lifecycle states driven by window events.
+
+use std::time::Duration;
+
+use super::{RenderEvent, SwapchainExtent};
+
+/// The state the renderer is in.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LifecycleState {
+    /// Frames are being rendered normally.
+    #[default]
+    Active,
+    /// The window is minimized or has a zero-sized framebuffer, no images should be acquired.
+    Minimized,
+    /// The surface was lost and must be recreated before rendering again.
+    SurfaceLost,
+    /// Rendering was explicitly suspended by the application.
+    Suspended,
+}
+
+/// Tracks the [LifecycleState] and whether the surface must be revalidated before the next frame.
+///
+/// Every cause of a pause is tracked on its own, so ending one doesn't end another: a surface lost
+/// while suspended stays lost after [Lifecycle::resume]. The state is the most severe cause, in
+/// the order [LifecycleState::SurfaceLost], [LifecycleState::Suspended],
+/// [LifecycleState::Minimized].
+#[derive(Debug, Clone)]
+pub struct Lifecycle {
+    /// How long to sleep per loop iteration while not [LifecycleState::Active].
+    pub throttle: Duration,
+    /// Whether the surface capabilities must be queried again before rendering.
+    pub needs_revalidation: bool,
+    state: LifecycleState,
+    /// Whether the window is iconified, tracked apart from the framebuffer size.
+    iconified: bool,
+    /// Whether the framebuffer has a zero-sized dimension.
+    zero_sized: bool,
+    /// Whether the surface had no area for a swapchain, see [Lifecycle::set_extent].
+    no_extent: bool,
+    surface_lost: bool,
+    suspended: bool,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self {
+            throttle: Duration::from_millis(16),
+            needs_revalidation: false,
+            state: LifecycleState::Active,
+            iconified: false,
+            zero_sized: false,
+            no_extent: false,
+            surface_lost: false,
+            suspended: false,
+        }
+    }
+}
+
+impl Lifecycle {
+    /// The current state.
+    pub fn state(&self) -> LifecycleState {
+        self.state
+    }
+
+    /// Updates the state from a GLFW window event, returns the new state.
+    pub fn handle_glfw_event(&mut self, event: &glfw::WindowEvent) -> LifecycleState {
+        match *event {
+            glfw::WindowEvent::Iconify(iconified) => self.set_iconified(iconified),
+            glfw::WindowEvent::FramebufferSize(width, height) => {
+                self.framebuffer_resized(width.max(0) as u32, height.max(0) as u32);
+            }
+            _ => {}
+        }
+
+        self.state
+    }
+
+    /// Updates the state from an event forwarded to a [super::RenderThread], returns the new state.
+    pub fn handle_render_event(&mut self, event: &RenderEvent) -> LifecycleState {
+        match *event {
+            RenderEvent::Resized { width, height } => self.framebuffer_resized(width, height),
+            RenderEvent::Minimized(minimized) => self.set_iconified(minimized),
+            RenderEvent::Close => {}
+        }
+
+        self.state
+    }
+
+    /// Notes the window being iconified or restored, for windows not driven by GLFW events.
+    pub fn set_iconified(&mut self, iconified: bool) {
+        self.iconified = iconified;
+        self.no_extent = false;
+        self.update_state();
+    }
+
+    /// Notes a new framebuffer size, for windows not driven by GLFW events. A zero-sized
+    /// dimension counts as minimized.
+    pub fn framebuffer_resized(&mut self, width: u32, height: u32) {
+        self.zero_sized = width == 0 || height == 0;
+        self.no_extent = false;
+        self.needs_revalidation = true;
+        self.update_state();
+    }
+
+    /// Notes the extent chosen for the swapchain. [SwapchainExtent::NeedsWait] counts as
+    /// minimized until the next iconify or resize, since some platforms report a surface with no
+    /// area while the framebuffer size still has one.
+    pub fn set_extent(&mut self, extent: SwapchainExtent) {
+        self.no_extent = extent == SwapchainExtent::NeedsWait;
+        self.update_state();
+    }
+
+    /// Marks the surface as lost, e.g. after `VK_ERROR_SURFACE_LOST_KHR`.
+    pub fn surface_lost(&mut self) {
+        self.surface_lost = true;
+        self.needs_revalidation = true;
+        self.update_state();
+    }
+
+    /// Marks the surface as recreated after [LifecycleState::SurfaceLost].
+    pub fn surface_recreated(&mut self) {
+        self.surface_lost = false;
+        self.update_state();
+    }
+
+    /// Suspends rendering until [Lifecycle::resume] is called.
+    pub fn suspend(&mut self) {
+        self.suspended = true;
+        self.update_state();
+    }
+
+    /// Resumes rendering after [Lifecycle::suspend], the surface is revalidated first. Other
+    /// causes of a pause, like a lost surface, still hold.
+    pub fn resume(&mut self) {
+        if self.suspended {
+            self.suspended = false;
+            self.needs_revalidation = true;
+            self.update_state();
+        }
+    }
+
+    /// Whether [Lifecycle::suspend] was called without [Lifecycle::resume], even while a more
+    /// severe state shows.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// Whether a frame should be acquired and rendered in this loop iteration.
+    pub fn should_render(&self) -> bool {
+        self.state == LifecycleState::Active
+    }
+
+    /// Returns `true` once after the surface needs to be revalidated, clearing the flag.
+    ///
+    /// Callers should query the [super::SwapchainSupportDetails] again and recreate the swapchain
+    /// if the capabilities changed.
+    pub fn take_revalidation(&mut self) -> bool {
+        std::mem::take(&mut self.needs_revalidation)
+    }
+
+    /// Sleeps for [Lifecycle::throttle] if rendering is paused.
+    pub fn throttle(&self) {
+        if !self.should_render() {
+            std::thread::sleep(self.throttle);
+        }
+    }
+
+    /// Picks the most severe cause of a pause, revalidating the surface when rendering resumes.
+    fn update_state(&mut self) {
+        let state = if self.surface_lost {
+            LifecycleState::SurfaceLost
+        } else if self.suspended {
+            LifecycleState::Suspended
+        } else if self.iconified || self.zero_sized || self.no_extent {
+            LifecycleState::Minimized
+        } else {
+            LifecycleState::Active
+        };
+
+        if state == LifecycleState::Active && self.state != LifecycleState::Active {
+            self.needs_revalidation = true;
+        }
+
+        self.state = state;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    use super::*;
+
+    #[test]
+    fn surface_lost_survives_suspend_and_resume() {
+        let mut lifecycle = Lifecycle::default();
+
+        lifecycle.surface_lost();
+        lifecycle.suspend();
+        assert_eq!(lifecycle.state(), LifecycleState::SurfaceLost);
+
+        lifecycle.resume();
+        assert_eq!(lifecycle.state(), LifecycleState::SurfaceLost);
+        assert!(!lifecycle.should_render());
+
+        lifecycle.surface_recreated();
+        assert_eq!(lifecycle.state(), LifecycleState::Active);
+    }
+
+    #[test]
+    fn suspend_outlasts_a_recreated_surface() {
+        let mut lifecycle = Lifecycle::default();
+
+        lifecycle.suspend();
+        lifecycle.surface_lost();
+        lifecycle.surface_recreated();
+        assert_eq!(lifecycle.state(), LifecycleState::Suspended);
+
+        lifecycle.resume();
+        assert_eq!(lifecycle.state(), LifecycleState::Active);
+    }
+
+    #[test]
+    fn resume_while_minimized_stays_minimized() {
+        let mut lifecycle = Lifecycle::default();
+
+        lifecycle.handle_glfw_event(&glfw::WindowEvent::Iconify(true));
+        lifecycle.suspend();
+        lifecycle.resume();
+        assert_eq!(lifecycle.state(), LifecycleState::Minimized);
+
+        lifecycle.take_revalidation();
+        lifecycle.handle_glfw_event(&glfw::WindowEvent::Iconify(false));
+        assert_eq!(lifecycle.state(), LifecycleState::Active);
+        assert!(lifecycle.take_revalidation());
+    }
+
+    #[test]
+    fn zero_sized_framebuffer_minimizes() {
+        let mut lifecycle = Lifecycle::default();
+
+        lifecycle.handle_glfw_event(&glfw::WindowEvent::FramebufferSize(0, 0));
+        assert_eq!(lifecycle.state(), LifecycleState::Minimized);
+
+        lifecycle.handle_glfw_event(&glfw::WindowEvent::FramebufferSize(800, 0));
+        assert_eq!(lifecycle.state(), LifecycleState::Minimized);
+
+        lifecycle.handle_glfw_event(&glfw::WindowEvent::FramebufferSize(1, 1));
+        assert_eq!(lifecycle.state(), LifecycleState::Active);
+    }
+
+    #[test]
+    fn unusable_extent_minimizes_until_the_next_resize() {
+        let mut lifecycle = Lifecycle::default();
+
+        lifecycle.set_extent(SwapchainExtent::NeedsWait);
+        assert_eq!(lifecycle.state(), LifecycleState::Minimized);
+
+        lifecycle.framebuffer_resized(640, 480);
+        assert_eq!(lifecycle.state(), LifecycleState::Active);
+
+        lifecycle.set_extent(SwapchainExtent::Extent(vk::Extent2D {
+            width: 640,
+            height: 480,
+        }));
+        assert_eq!(lifecycle.state(), LifecycleState::Active);
+    }
+}
@@ -1,11 +1,57 @@
+pub use actions::*;
+pub use animation::*;
+pub use atlas::*;
+pub use camera::*;
+pub use camera_controller::*;
+pub use color::*;
 pub use device::*;
+pub use device_builder::*;
+pub use error_ctx::*;
 pub use extensions::*;
+pub use extent::*;
+pub use host_allocator::*;
 pub use instance::*;
+pub use lifecycle::*;
+pub use profile::*;
+pub use protected::*;
+pub use queue::*;
+pub use queue_config::*;
+pub use ray::*;
+pub use redraw::*;
+pub use render_thread::*;
+pub use requirements::*;
 pub use swapchain::*;
+pub use text_input::*;
+pub use time::*;
+pub use transform::*;
+pub use version::*;
 pub use window::*;
 
+mod actions;
+mod animation;
+mod atlas;
+mod camera;
+mod camera_controller;
+mod color;
 mod device;
+mod device_builder;
+mod error_ctx;
 mod extensions;
+mod extent;
+mod host_allocator;
 mod instance;
+mod lifecycle;
+mod profile;
+mod protected;
+mod queue;
+mod queue_config;
+mod ray;
+mod redraw;
+mod render_thread;
+mod requirements;
 mod swapchain;
+mod text_input;
+mod time;
+mod transform;
+mod version;
 mod window;
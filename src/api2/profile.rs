@@ -0,0 +1,271 @@
+//! Capability baselines a device must meet, in the spirit of the Vulkan profiles.
+
+use std::{
+    ffi::{CStr, CString},
+    fmt,
+};
+
+use ash::vk;
+
+use super::Extensions;
+
+/// A required boolean feature, checked on [vk::PhysicalDeviceFeatures].
+#[derive(Debug, Copy, Clone)]
+pub struct ProfileFeature {
+    /// The name of the feature as in the specification.
+    pub name: &'static str,
+    /// Reads the feature from the supported features.
+    pub get: fn(&vk::PhysicalDeviceFeatures) -> vk::Bool32,
+    /// Sets the feature in the features to enable.
+    pub enable: fn(&mut vk::PhysicalDeviceFeatures),
+}
+
+/// A required limit, checked on [vk::PhysicalDeviceLimits].
+#[derive(Debug, Copy, Clone)]
+pub struct ProfileLimit {
+    /// The name of the limit as in the specification.
+    pub name: &'static str,
+    /// The value the device must reach.
+    pub required: u64,
+    /// Reads the limit from the device limits.
+    pub get: fn(&vk::PhysicalDeviceLimits) -> u64,
+}
+
+/// A predefined capability baseline: API version, features, extensions and limits.
+///
+/// Targeting a profile instead of hand-listing requirements keeps an application inside a known
+/// feature envelope. Only the Vulkan 1.0 feature and limit structures are checked.
+#[derive(Debug, Clone)]
+pub struct Profile {
+    /// The name of the profile.
+    pub name: &'static str,
+    /// The minimum device API version.
+    pub api_version: u32,
+    /// The required features.
+    pub features: &'static [ProfileFeature],
+    /// The required device extensions.
+    pub extensions: &'static [&'static CStr],
+    /// The required minimum limits.
+    pub limits: &'static [ProfileLimit],
+}
+
+macro_rules! feature {
+    ($field:ident) => {
+        ProfileFeature {
+            name: stringify!($field),
+            get: |features| features.$field,
+            enable: |features| features.$field = vk::TRUE,
+        }
+    };
+}
+
+macro_rules! limit {
+    ($field:ident, $required:expr) => {
+        ProfileLimit {
+            name: stringify!($field),
+            required: $required,
+            get: |limits| limits.$field as u64,
+        }
+    };
+}
+
+impl ProfileFeature {
+    /// Anisotropic filtering in samplers.
+    pub const SAMPLER_ANISOTROPY: ProfileFeature = feature!(sampler_anisotropy);
+    /// Line and point polygon modes, e.g. for wireframes.
+    pub const FILL_MODE_NON_SOLID: ProfileFeature = feature!(fill_mode_non_solid);
+    /// Line widths other than 1.0.
+    pub const WIDE_LINES: ProfileFeature = feature!(wide_lines);
+
+    /// Whether the feature is set in the features.
+    pub fn is_set(&self, features: &vk::PhysicalDeviceFeatures) -> bool {
+        (self.get)(features) == vk::TRUE
+    }
+}
+
+impl Profile {
+    /// A subset of the Vulkan Roadmap 2022 milestone for desktop and high-end mobile GPUs.
+    pub const ROADMAP_2022: Profile = Profile {
+        name: "Roadmap 2022",
+        api_version: vk::API_VERSION_1_3,
+        features: &[
+            feature!(full_draw_index_uint32),
+            feature!(image_cube_array),
+            feature!(independent_blend),
+            feature!(sample_rate_shading),
+            feature!(draw_indirect_first_instance),
+            feature!(depth_clamp),
+            feature!(depth_bias_clamp),
+            feature!(sampler_anisotropy),
+            feature!(occlusion_query_precise),
+            feature!(fragment_stores_and_atomics),
+            feature!(shader_storage_image_extended_formats),
+            feature!(shader_uniform_buffer_array_dynamic_indexing),
+            feature!(shader_sampled_image_array_dynamic_indexing),
+            feature!(shader_storage_buffer_array_dynamic_indexing),
+            feature!(shader_storage_image_array_dynamic_indexing),
+            feature!(shader_image_gather_extended),
+            feature!(shader_int16),
+        ],
+        extensions: &[],
+        limits: &[
+            limit!(max_image_dimension2_d, 8192),
+            limit!(max_image_array_layers, 2048),
+            limit!(max_uniform_buffer_range, 65536),
+            limit!(max_per_stage_descriptor_samplers, 64),
+            limit!(max_per_stage_descriptor_uniform_buffers, 15),
+            limit!(max_per_stage_descriptor_storage_buffers, 30),
+            limit!(max_per_stage_descriptor_sampled_images, 200),
+            limit!(max_per_stage_descriptor_storage_images, 16),
+            limit!(max_per_stage_resources, 200),
+            limit!(max_color_attachments, 7),
+            limit!(max_compute_work_group_invocations, 256),
+        ],
+    };
+
+    /// A conservative baseline most mobile GPUs shipped in recent years meet.
+    pub const MOBILE_BASELINE: Profile = Profile {
+        name: "Mobile baseline",
+        api_version: vk::API_VERSION_1_1,
+        features: &[
+            feature!(full_draw_index_uint32),
+            feature!(image_cube_array),
+            feature!(independent_blend),
+            feature!(sample_rate_shading),
+            feature!(texture_compression_etc2),
+            feature!(fragment_stores_and_atomics),
+            feature!(shader_uniform_buffer_array_dynamic_indexing),
+            feature!(shader_sampled_image_array_dynamic_indexing),
+        ],
+        extensions: &[vk::KHR_SWAPCHAIN_NAME],
+        limits: &[
+            limit!(max_image_dimension2_d, 4096),
+            limit!(max_image_array_layers, 256),
+            limit!(max_uniform_buffer_range, 16384),
+            limit!(max_per_stage_descriptor_samplers, 16),
+            limit!(max_per_stage_descriptor_uniform_buffers, 12),
+            limit!(max_per_stage_descriptor_storage_buffers, 4),
+            limit!(max_per_stage_descriptor_sampled_images, 16),
+            limit!(max_per_stage_descriptor_storage_images, 4),
+            limit!(max_color_attachments, 4),
+            limit!(max_push_constants_size, 128),
+            limit!(max_compute_work_group_invocations, 128),
+        ],
+    };
+
+    /// Checks the physical device against the profile, returning every mismatch found.
+    pub fn check(
+        &self,
+        instance: &ash::Instance,
+        physical: vk::PhysicalDevice,
+    ) -> Result<(), Vec<ProfileMismatch>> {
+        let properties = unsafe { instance.get_physical_device_properties(physical) };
+        let features = unsafe { instance.get_physical_device_features(physical) };
+        let available_extensions =
+            unsafe { instance.enumerate_device_extension_properties(physical) }
+                .ok()
+                .and_then(|e| Extensions::try_from(e).ok())
+                .unwrap_or_default();
+
+        let mut mismatches = Vec::new();
+
+        if properties.api_version < self.api_version {
+            mismatches.push(ProfileMismatch::ApiVersion {
+                required: self.api_version,
+                found: properties.api_version,
+            });
+        }
+
+        mismatches.extend(
+            self.features
+                .iter()
+                .filter(|feature| (feature.get)(&features) != vk::TRUE)
+                .map(|feature| ProfileMismatch::Feature(feature.name)),
+        );
+
+        mismatches.extend(
+            self.extensions
+                .iter()
+                .filter(|extension| {
+                    !available_extensions
+                        .iter()
+                        .any(|e| e.as_c_str() == **extension)
+                })
+                .map(|extension| ProfileMismatch::Extension(CString::from(*extension))),
+        );
+
+        mismatches.extend(self.limits.iter().filter_map(|limit| {
+            let found = (limit.get)(&properties.limits);
+
+            (found < limit.required).then_some(ProfileMismatch::Limit {
+                name: limit.name,
+                required: limit.required,
+                found,
+            })
+        }));
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(mismatches)
+        }
+    }
+
+    /// The device features to enable so everything the profile guarantees can be used.
+    pub fn enabled_features(&self) -> vk::PhysicalDeviceFeatures {
+        let mut features = vk::PhysicalDeviceFeatures::default();
+
+        for feature in self.features {
+            (feature.enable)(&mut features);
+        }
+
+        features
+    }
+}
+
+/// A requirement of a [Profile] that a device does not meet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProfileMismatch {
+    /// The device API version is too old.
+    ApiVersion {
+        /// The version the profile needs.
+        required: u32,
+        /// The version of the device.
+        found: u32,
+    },
+    /// A required feature isn't supported.
+    Feature(&'static str),
+    /// A required extension isn't available.
+    Extension(CString),
+    /// A limit is below the profile's value.
+    Limit {
+        /// The name of the limit.
+        name: &'static str,
+        /// The value the profile needs.
+        required: u64,
+        /// The value of the device.
+        found: u64,
+    },
+}
+
+impl fmt::Display for ProfileMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ApiVersion { required, found } => write!(
+                f,
+                "API version {}.{} is below {}.{}",
+                vk::api_version_major(*found),
+                vk::api_version_minor(*found),
+                vk::api_version_major(*required),
+                vk::api_version_minor(*required)
+            ),
+            Self::Feature(name) => write!(f, "feature {} is not supported", name),
+            Self::Extension(name) => write!(f, "extension {:?} is not available", name),
+            Self::Limit {
+                name,
+                required,
+                found,
+            } => write!(f, "limit {} is {}, below {}", name, found, required),
+        }
+    }
+}
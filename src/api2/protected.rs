@@ -0,0 +1,99 @@
+//! Protected memory, for experimenting with protected (DRM-style) rendering paths.
+//!
+//! Protected content needs a Vulkan 1.1 instance, the `protectedMemory` feature, queues created
+//! with the protected flag, and every image, buffer, memory allocation, command pool and swapchain
+//! touching the content created with its protected flag.
+
+use ash::{khr::get_surface_capabilities2, prelude::*, vk};
+
+/// Whether the physical device supports the `protectedMemory` feature.
+///
+/// The instance must have been created with Vulkan 1.1 or newer.
+pub fn supports_protected_memory(instance: &ash::Instance, physical: vk::PhysicalDevice) -> bool {
+    let mut protected_features = vk::PhysicalDeviceProtectedMemoryFeatures::default();
+    let mut features = vk::PhysicalDeviceFeatures2::default().push_next(&mut protected_features);
+
+    unsafe { instance.get_physical_device_features2(physical, &mut features) };
+
+    protected_features.protected_memory == vk::TRUE
+}
+
+/// Whether the surface can present from a protected swapchain.
+///
+/// Needs the `VK_KHR_get_surface_capabilities2` and `VK_KHR_surface_protected_capabilities`
+/// instance extensions.
+pub fn supports_protected_swapchain(
+    entry: &ash::Entry,
+    instance: &ash::Instance,
+    physical: vk::PhysicalDevice,
+    surface: vk::SurfaceKHR,
+) -> VkResult<bool> {
+    let capabilities2 = get_surface_capabilities2::Instance::new(entry, instance);
+
+    let surface_info = vk::PhysicalDeviceSurfaceInfo2KHR::default().surface(surface);
+    let mut protected_capabilities = vk::SurfaceProtectedCapabilitiesKHR::default();
+    let mut capabilities =
+        vk::SurfaceCapabilities2KHR::default().push_next(&mut protected_capabilities);
+
+    unsafe {
+        capabilities2.get_physical_device_surface_capabilities2(
+            physical,
+            &surface_info,
+            &mut capabilities,
+        )?;
+    }
+
+    Ok(protected_capabilities.supports_protected == vk::TRUE)
+}
+
+/// The creation flags that make objects hold protected content.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ProtectedFlags {
+    /// Whether protected content is enabled, every flag is empty otherwise.
+    pub enabled: bool,
+}
+
+impl ProtectedFlags {
+    /// Flags for swapchains presenting protected images.
+    pub fn swapchain(&self) -> vk::SwapchainCreateFlagsKHR {
+        self.pick(vk::SwapchainCreateFlagsKHR::PROTECTED)
+    }
+
+    /// Flags for protected images.
+    pub fn image(&self) -> vk::ImageCreateFlags {
+        self.pick(vk::ImageCreateFlags::PROTECTED)
+    }
+
+    /// Flags for protected buffers.
+    pub fn buffer(&self) -> vk::BufferCreateFlags {
+        self.pick(vk::BufferCreateFlags::PROTECTED)
+    }
+
+    /// Memory properties required to back protected images and buffers.
+    pub fn memory(&self) -> vk::MemoryPropertyFlags {
+        self.pick(vk::MemoryPropertyFlags::PROTECTED)
+    }
+
+    /// Flags for command pools recording protected command buffers.
+    pub fn command_pool(&self) -> vk::CommandPoolCreateFlags {
+        self.pick(vk::CommandPoolCreateFlags::PROTECTED)
+    }
+
+    /// Flags for queues that can run protected submissions.
+    pub fn queue(&self) -> vk::DeviceQueueCreateFlags {
+        self.pick(vk::DeviceQueueCreateFlags::PROTECTED)
+    }
+
+    /// Chains this into a submit so it runs as a protected submission.
+    pub fn submit_info(&self) -> vk::ProtectedSubmitInfo<'static> {
+        vk::ProtectedSubmitInfo::default().protected_submit(self.enabled)
+    }
+
+    fn pick<F: Default>(&self, flag: F) -> F {
+        if self.enabled {
+            flag
+        } else {
+            F::default()
+        }
+    }
+}
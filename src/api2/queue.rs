@@ -0,0 +1,131 @@
+use std::{
+    error::Error,
+    fmt,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc,
+    },
+};
+
+use ash::vk;
+
+/// Shared flag recording the first fatal error a device returned.
+///
+/// Once a device is lost every further call on it is undefined behavior, so the device and all of
+/// its queues refuse to run anything after that.
+#[derive(Debug, Default, Clone)]
+pub struct Poison(Arc<AtomicI32>);
+
+impl Poison {
+    /// The error that poisoned the device, if any.
+    pub fn get(&self) -> Option<vk::Result> {
+        match self.0.load(Ordering::Acquire) {
+            0 => None,
+            raw => Some(vk::Result::from_raw(raw)),
+        }
+    }
+
+    /// Whether a fatal error has been recorded.
+    pub fn is_poisoned(&self) -> bool {
+        self.get().is_some()
+    }
+
+    /// Returns an error if the device is poisoned.
+    pub fn check(&self) -> Result<(), QueueError> {
+        match self.get() {
+            Some(cause) => Err(QueueError::Poisoned(cause)),
+            None => Ok(()),
+        }
+    }
+
+    /// Records the result of a call, poisoning on fatal errors, and converts it into a
+    /// [`QueueError`].
+    pub fn record<T>(&self, result: Result<T, vk::Result>) -> Result<T, QueueError> {
+        result.map_err(|error| {
+            if is_fatal(error) {
+                // Keep the first cause, it is the interesting one.
+                let _ =
+                    self.0
+                        .compare_exchange(0, error.as_raw(), Ordering::AcqRel, Ordering::Acquire);
+            }
+
+            QueueError::Vulkan(error)
+        })
+    }
+}
+
+/// Whether an error leaves the device in a state where it cannot be used anymore.
+pub fn is_fatal(error: vk::Result) -> bool {
+    matches!(
+        error,
+        vk::Result::ERROR_DEVICE_LOST
+            | vk::Result::ERROR_OUT_OF_HOST_MEMORY
+            | vk::Result::ERROR_OUT_OF_DEVICE_MEMORY
+    )
+}
+
+/// Represents a Vulkan queue belonging to a [`Device`](super::Device).
+#[derive(Clone)]
+pub struct Queue {
+    /// The Vulkan queue.
+    pub queue: vk::Queue,
+    /// The queue family index.
+    pub family: u32,
+    logical: ash::Device,
+    poison: Poison,
+}
+
+impl Queue {
+    /// Wraps a queue retrieved from the logical device.
+    pub fn new(logical: ash::Device, queue: vk::Queue, family: u32, poison: Poison) -> Self {
+        Self {
+            queue,
+            family,
+            logical,
+            poison,
+        }
+    }
+
+    /// Waits for all work submitted to this queue to finish.
+    pub fn wait_idle(&self) -> Result<(), QueueError> {
+        self.poison.check()?;
+        self.poison
+            .record(unsafe { self.logical.queue_wait_idle(self.queue) })
+    }
+
+    /// Submits work to this queue.
+    pub fn submit(&self, submits: &[vk::SubmitInfo], fence: vk::Fence) -> Result<(), QueueError> {
+        self.poison.check()?;
+        self.poison
+            .record(unsafe { self.logical.queue_submit(self.queue, submits, fence) })
+    }
+
+    /// The poison flag shared with the device.
+    pub fn poison(&self) -> &Poison {
+        &self.poison
+    }
+}
+
+/// Represents an error returned by a device or queue operation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum QueueError {
+    /// The device was poisoned by an earlier fatal error and can no longer be used.
+    Poisoned(vk::Result),
+    /// A Vulkan error occurred.
+    Vulkan(vk::Result),
+}
+
+impl fmt::Display for QueueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Poisoned(cause) => write!(
+                f,
+                "device is unusable after an earlier fatal error ({})",
+                cause
+            ),
+            Self::Vulkan(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for QueueError {}
@@ -0,0 +1,233 @@
+//! Configuration of how many queues to create per family and at which priorities.
+
+use std::{error, fmt};
+
+use ash::vk;
+
+use super::ProtectedFlags;
+
+/// What a queue is used for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum QueueRole {
+    /// Rendering, on a family supporting graphics.
+    Graphics,
+    /// Compute work overlapping rendering, preferring a family without graphics.
+    AsyncCompute,
+    /// Uploads and downloads, preferring a transfer-only family.
+    Transfer,
+    /// Low-priority work such as streaming, on the graphics family.
+    Background,
+}
+
+/// A queue to create.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct QueueRequest {
+    /// The role the queue is looked up by.
+    pub role: QueueRole,
+    /// The priority, between 0.0 and 1.0.
+    pub priority: f32,
+}
+
+/// The queues to create on a device, each one dedicated to its role.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueConfig {
+    /// The requested queues, at most one per role.
+    pub requests: Vec<QueueRequest>,
+    /// Whether every queue is created protected, see [ProtectedFlags].
+    pub protected: bool,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            requests: vec![QueueRequest {
+                role: QueueRole::Graphics,
+                priority: 1.0,
+            }],
+            protected: false,
+        }
+    }
+}
+
+impl QueueConfig {
+    /// Requests a queue for the role, replacing any earlier request for it.
+    pub fn queue(mut self, role: QueueRole, priority: f32) -> Self {
+        self.requests.retain(|request| request.role != role);
+        self.requests.push(QueueRequest { role, priority });
+        self
+    }
+
+    /// Creates every queue with the protected flag, enabling protected content on the device.
+    pub fn protected(mut self, protected: bool) -> Self {
+        self.protected = protected;
+        self
+    }
+
+    /// Assigns every requested queue to a family and index, validating the priorities and the
+    /// number of queues each family has.
+    pub fn plan(
+        &self,
+        families: &[vk::QueueFamilyProperties],
+        graphics_family: u32,
+        present_family: u32,
+    ) -> Result<QueuePlan, QueueConfigError> {
+        let mut plan = QueuePlan {
+            protected: ProtectedFlags {
+                enabled: self.protected,
+            },
+            ..Default::default()
+        };
+
+        for request in &self.requests {
+            if !(0.0..=1.0).contains(&request.priority) {
+                return Err(QueueConfigError::InvalidPriority(request.priority));
+            }
+
+            let family = match request.role {
+                QueueRole::Graphics | QueueRole::Background => graphics_family,
+                QueueRole::AsyncCompute => {
+                    find_family(families, vk::QueueFlags::COMPUTE, vk::QueueFlags::GRAPHICS)
+                        .ok_or(QueueConfigError::NoFamily(request.role))?
+                }
+                QueueRole::Transfer => find_family(
+                    families,
+                    vk::QueueFlags::TRANSFER,
+                    vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE,
+                )
+                .ok_or(QueueConfigError::NoFamily(request.role))?,
+            };
+
+            let index = plan.push(family, request.priority);
+            plan.assignments.push((request.role, family, index));
+        }
+
+        // Presenting needs a queue on the present family even when nothing was requested there.
+        if !plan
+            .families
+            .iter()
+            .any(|(family, _)| *family == present_family)
+        {
+            plan.push(present_family, 1.0);
+        }
+
+        for (family, priorities) in &plan.families {
+            let available = families[*family as usize].queue_count;
+
+            if priorities.len() as u32 > available {
+                return Err(QueueConfigError::QueueCountExceeded {
+                    family: *family,
+                    requested: priorities.len() as u32,
+                    available,
+                });
+            }
+
+            if self.protected
+                && !families[*family as usize]
+                    .queue_flags
+                    .contains(vk::QueueFlags::PROTECTED)
+            {
+                return Err(QueueConfigError::ProtectedUnsupported(*family));
+            }
+        }
+
+        Ok(plan)
+    }
+}
+
+/// Returns the family having the `required` flags and as few of the `avoid` flags as possible.
+fn find_family(
+    families: &[vk::QueueFamilyProperties],
+    required: vk::QueueFlags,
+    avoid: vk::QueueFlags,
+) -> Option<u32> {
+    families
+        .iter()
+        .enumerate()
+        .filter(|(_, family)| family.queue_count > 0 && family.queue_flags.contains(required))
+        .min_by_key(|(_, family)| (family.queue_flags & avoid).as_raw().count_ones())
+        .map(|(i, _)| i as u32)
+}
+
+/// The queues to create per family and which role each one serves.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct QueuePlan {
+    /// Each family with the priorities of its queues.
+    pub families: Vec<(u32, Vec<f32>)>,
+    /// Each role with the family and index of its queue.
+    pub assignments: Vec<(QueueRole, u32, u32)>,
+    /// The protected flags the queues are created with.
+    pub protected: ProtectedFlags,
+}
+
+impl QueuePlan {
+    fn push(&mut self, family: u32, priority: f32) -> u32 {
+        match self.families.iter_mut().find(|(f, _)| *f == family) {
+            Some((_, priorities)) => {
+                priorities.push(priority);
+                priorities.len() as u32 - 1
+            }
+            None => {
+                self.families.push((family, vec![priority]));
+                0
+            }
+        }
+    }
+
+    /// Returns the create infos to pass to the device, borrowing the priorities.
+    pub fn create_infos(&self) -> Vec<vk::DeviceQueueCreateInfo<'_>> {
+        self.families
+            .iter()
+            .map(|(family, priorities)| {
+                vk::DeviceQueueCreateInfo::default()
+                    .flags(self.protected.queue())
+                    .queue_family_index(*family)
+                    .queue_priorities(priorities)
+            })
+            .collect()
+    }
+}
+
+/// Represents an error in a [QueueConfig].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum QueueConfigError {
+    /// A priority was outside of `0.0..=1.0`.
+    InvalidPriority(f32),
+    /// No family supports the role.
+    NoFamily(QueueRole),
+    /// More queues were requested on a family than it has.
+    QueueCountExceeded {
+        /// The family index.
+        family: u32,
+        /// How many queues were requested.
+        requested: u32,
+        /// How many queues the family has.
+        available: u32,
+    },
+    /// Protected queues were requested on a family without protected support.
+    ProtectedUnsupported(u32),
+}
+
+impl fmt::Display for QueueConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidPriority(priority) => {
+                write!(f, "queue priority {} is outside of 0.0..=1.0", priority)
+            }
+            Self::NoFamily(role) => write!(f, "no queue family supports {:?}", role),
+            Self::QueueCountExceeded {
+                family,
+                requested,
+                available,
+            } => write!(
+                f,
+                "{} queues requested on family {}, which only has {}",
+                requested, family, available
+            ),
+            Self::ProtectedUnsupported(family) => {
+                write!(f, "queue family {} can't create protected queues", family)
+            }
+        }
+    }
+}
+
+impl error::Error for QueueConfigError {}
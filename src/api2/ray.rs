@@ -0,0 +1,79 @@
+//! Rays and their intersections with simple shapes, for picking and placing things under the
+//! cursor.
+
+use nalgebra_glm as glm;
+
+/// A half-line starting at `origin`, see [super::Camera::screen_to_ray].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: glm::Vec3,
+    /// Normalized, so distances along the ray are in world units.
+    pub direction: glm::Vec3,
+}
+
+impl Ray {
+    /// Creates a ray, normalizing `direction`.
+    pub fn new(origin: glm::Vec3, direction: glm::Vec3) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    /// The point at distance `t` along the ray.
+    pub fn at(&self, t: f32) -> glm::Vec3 {
+        self.origin + self.direction * t
+    }
+
+    /// The distance at which the ray enters the box from `min` to `max`, 0 if it starts inside,
+    /// using the slab method.
+    pub fn intersect_aabb(&self, min: glm::Vec3, max: glm::Vec3) -> Option<f32> {
+        let mut near = 0.0f32;
+        let mut far = f32::INFINITY;
+
+        for i in 0..3 {
+            let inverse = 1.0 / self.direction[i];
+            let t0 = (min[i] - self.origin[i]) * inverse;
+            let t1 = (max[i] - self.origin[i]) * inverse;
+
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+        }
+
+        (near <= far).then_some(near)
+    }
+
+    /// The distance at which the ray hits the plane of points `p` with
+    /// `normal.dot(p) + distance == 0`, the form of the frustum planes. `None` if the ray is
+    /// parallel to the plane or points away from it.
+    pub fn intersect_plane(&self, normal: glm::Vec3, distance: f32) -> Option<f32> {
+        let denominator = normal.dot(&self.direction);
+
+        if denominator.abs() <= f32::EPSILON {
+            return None;
+        }
+
+        let t = -(normal.dot(&self.origin) + distance) / denominator;
+        (t >= 0.0).then_some(t)
+    }
+
+    /// The distance at which the ray enters the sphere, 0 if it starts inside.
+    pub fn intersect_sphere(&self, center: glm::Vec3, radius: f32) -> Option<f32> {
+        let offset = self.origin - center;
+        let b = offset.dot(&self.direction);
+        let c = offset.norm_squared() - radius * radius;
+
+        if c <= 0.0 {
+            return Some(0.0);
+        }
+
+        let discriminant = b * b - c;
+
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let t = -b - discriminant.sqrt();
+        (t >= 0.0).then_some(t)
+    }
+}
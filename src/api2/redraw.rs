@@ -0,0 +1,82 @@
+//! Deciding when the game loop should render a frame.
+
+/// When frames are rendered.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RedrawPolicy {
+    /// Render every loop iteration, as games do.
+    #[default]
+    Continuous,
+    /// Render only after input, a resize or an explicit [RedrawScheduler::request_redraw], as
+    /// editors do. The loop should block on window events in between to keep the CPU idle.
+    OnDemand,
+}
+
+/// Tracks whether a redraw is pending under a [RedrawPolicy].
+#[derive(Debug, Clone)]
+pub struct RedrawScheduler {
+    /// The current policy.
+    pub policy: RedrawPolicy,
+    /// Whether a frame must be rendered on the next iteration.
+    pending: bool,
+}
+
+impl Default for RedrawScheduler {
+    fn default() -> Self {
+        Self::new(RedrawPolicy::default())
+    }
+}
+
+impl RedrawScheduler {
+    /// Creates a scheduler, with the first frame already pending so the window isn't blank.
+    pub fn new(policy: RedrawPolicy) -> Self {
+        Self {
+            policy,
+            pending: true,
+        }
+    }
+
+    /// Changes the policy, scheduling a redraw so the switch takes effect right away.
+    pub fn set_policy(&mut self, policy: RedrawPolicy) {
+        self.policy = policy;
+        self.pending = true;
+    }
+
+    /// Schedules a frame, e.g. after the application changed what is displayed.
+    pub fn request_redraw(&mut self) {
+        self.pending = true;
+    }
+
+    /// Schedules a frame if the event changes what is displayed or may trigger a change.
+    pub fn handle_glfw_event(&mut self, event: &glfw::WindowEvent) {
+        if matches!(
+            event,
+            glfw::WindowEvent::Key(..)
+                | glfw::WindowEvent::Char(..)
+                | glfw::WindowEvent::MouseButton(..)
+                | glfw::WindowEvent::CursorPos(..)
+                | glfw::WindowEvent::Scroll(..)
+                | glfw::WindowEvent::Size(..)
+                | glfw::WindowEvent::FramebufferSize(..)
+                | glfw::WindowEvent::Refresh
+                | glfw::WindowEvent::Focus(..)
+                | glfw::WindowEvent::Iconify(..)
+                | glfw::WindowEvent::Maximize(..)
+                | glfw::WindowEvent::ContentScale(..)
+        ) {
+            self.pending = true;
+        }
+    }
+
+    /// Whether the loop should block waiting for events instead of polling.
+    pub fn should_wait(&self) -> bool {
+        self.policy == RedrawPolicy::OnDemand && !self.pending
+    }
+
+    /// Whether a frame should be rendered now, consuming the pending redraw.
+    pub fn take_redraw(&mut self) -> bool {
+        match self.policy {
+            RedrawPolicy::Continuous => true,
+            RedrawPolicy::OnDemand => std::mem::take(&mut self.pending),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Runs rendering on a dedicated thread, leaving the window's message pump on the main thread.
+
+use std::{
+    io,
+    sync::mpsc::{self, Receiver, Sender, TryIter},
+    thread::{self, JoinHandle},
+};
+
+/// Events sent from the window thread to the render thread.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RenderEvent {
+    /// The framebuffer was resized to the given size in pixels.
+    Resized { width: u32, height: u32 },
+    /// The window was minimized (`true`) or restored (`false`).
+    Minimized(bool),
+    /// The window is closing, the render thread should return.
+    Close,
+}
+
+/// Feedback sent from the render thread back to the window thread.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PresentFeedback {
+    /// A frame was presented.
+    Presented { frame: u64 },
+    /// Rendering failed and the render thread stopped.
+    Failed(String),
+}
+
+/// The render thread's side of the channels.
+pub struct RenderContext {
+    /// Events coming from the window thread.
+    pub events: Receiver<RenderEvent>,
+    /// Feedback going to the window thread.
+    pub feedback: Sender<PresentFeedback>,
+}
+
+impl RenderContext {
+    /// Returns the events received since the last call without blocking.
+    pub fn poll_events(&self) -> TryIter<'_, RenderEvent> {
+        self.events.try_iter()
+    }
+
+    /// Blocks until the next event, returns [None] if the window thread is gone.
+    pub fn wait_event(&self) -> Option<RenderEvent> {
+        self.events.recv().ok()
+    }
+
+    /// Reports a presented frame, returns `false` if the window thread is gone.
+    pub fn presented(&self, frame: u64) -> bool {
+        self.feedback
+            .send(PresentFeedback::Presented { frame })
+            .is_ok()
+    }
+
+    /// Reports a rendering failure, returns `false` if the window thread is gone.
+    pub fn failed(&self, message: impl Into<String>) -> bool {
+        self.feedback
+            .send(PresentFeedback::Failed(message.into()))
+            .is_ok()
+    }
+}
+
+/// Handle to a render thread owned by the window thread.
+///
+/// Dropping the handle sends [RenderEvent::Close] and joins the thread.
+pub struct RenderThread {
+    /// Events going to the render thread.
+    pub events: Sender<RenderEvent>,
+    /// Feedback coming from the render thread.
+    pub feedback: Receiver<PresentFeedback>,
+    /// The render thread, [None] once joined.
+    pub handle: Option<JoinHandle<()>>,
+}
+
+impl RenderThread {
+    /// Spawns a new render thread running `render` until it returns.
+    pub fn spawn<F>(render: F) -> io::Result<Self>
+    where
+        F: FnOnce(RenderContext) + Send + 'static,
+    {
+        let (event_sender, event_receiver) = mpsc::channel();
+        let (feedback_sender, feedback_receiver) = mpsc::channel();
+
+        let handle = thread::Builder::new()
+            .name("render".to_owned())
+            .spawn(move || {
+                render(RenderContext {
+                    events: event_receiver,
+                    feedback: feedback_sender,
+                })
+            })?;
+
+        Ok(Self {
+            events: event_sender,
+            feedback: feedback_receiver,
+            handle: Some(handle),
+        })
+    }
+
+    /// Sends an event to the render thread, returns `false` if it already stopped.
+    pub fn send(&self, event: RenderEvent) -> bool {
+        self.events.send(event).is_ok()
+    }
+
+    /// Returns the feedback received since the last call without blocking.
+    pub fn poll_feedback(&self) -> TryIter<'_, PresentFeedback> {
+        self.feedback.try_iter()
+    }
+
+    /// Asks the render thread to stop and waits for it.
+    pub fn join(mut self) -> thread::Result<()> {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> thread::Result<()> {
+        let _ = self.events.send(RenderEvent::Close);
+
+        match self.handle.take() {
+            Some(handle) => handle.join(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for RenderThread {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
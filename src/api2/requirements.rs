@@ -0,0 +1,285 @@
+//! Computing the extensions and layers a set of rendering features needs.
+
+use std::{
+    error,
+    ffi::{CStr, CString},
+    fmt,
+};
+
+use ash::vk;
+
+use super::{get_validation_layers, Extensions};
+
+/// A rendering feature that needs instance/device extensions or layers to work.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RenderFeature {
+    /// Validation layer and debug messenger.
+    DebugLayer,
+    /// Rendering without render pass objects.
+    DynamicRendering,
+    /// Hardware ray tracing pipelines.
+    RayTracing,
+    /// HDR swapchain color spaces and metadata.
+    Hdr,
+    /// Rendering without a window or swapchain.
+    Headless,
+}
+
+impl RenderFeature {
+    /// The lowest Vulkan version the feature can be used with, through extensions if needed.
+    pub fn min_api_version(&self) -> u32 {
+        match self {
+            Self::RayTracing => vk::API_VERSION_1_1,
+            _ => vk::API_VERSION_1_0,
+        }
+    }
+}
+
+/// The extensions and layers to enable, as computed by [`RequirementsResolver`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Requirements {
+    /// The instance extensions.
+    pub instance_extensions: Extensions,
+    /// The device extensions.
+    pub device_extensions: Extensions,
+    /// The instance layers.
+    pub layers: Extensions,
+}
+
+impl Requirements {
+    /// Returns the instance extensions and layers that aren't in the available lists.
+    pub fn missing_instance(
+        &self,
+        available_extensions: &Extensions,
+        available_layers: &Extensions,
+    ) -> Vec<CString> {
+        self.instance_extensions
+            .iter()
+            .filter(|e| !available_extensions.contains(e))
+            .chain(self.layers.iter().filter(|l| !available_layers.contains(l)))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the device extensions that aren't in the available list.
+    pub fn missing_device(&self, available_extensions: &Extensions) -> Vec<CString> {
+        self.device_extensions
+            .iter()
+            .filter(|e| !available_extensions.contains(e))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Resolves the complete set of extensions and layers for a set of requested features.
+///
+/// Everything is computed from static tables, so conflicts are reported before any Vulkan call.
+#[derive(Debug, Clone)]
+pub struct RequirementsResolver {
+    /// The requested features.
+    pub features: Vec<RenderFeature>,
+    /// The instance extensions the window backend needs.
+    pub window_extensions: Extensions,
+    /// The Vulkan version the instance will be created with.
+    pub api_version: u32,
+}
+
+impl Default for RequirementsResolver {
+    fn default() -> Self {
+        Self {
+            features: Vec::new(),
+            window_extensions: Extensions::new(),
+            api_version: vk::API_VERSION_1_0,
+        }
+    }
+}
+
+impl RequirementsResolver {
+    /// Requests a feature.
+    pub fn feature(mut self, feature: RenderFeature) -> Self {
+        if !self.features.contains(&feature) {
+            self.features.push(feature);
+        }
+
+        self
+    }
+
+    /// Sets the instance extensions the window backend needs.
+    pub fn window_extensions(mut self, extensions: Extensions) -> Self {
+        self.window_extensions = extensions;
+        self
+    }
+
+    /// Sets the Vulkan version the instance will be created with.
+    pub fn api_version(mut self, api_version: u32) -> Self {
+        self.api_version = api_version;
+        self
+    }
+
+    /// Computes the extensions and layers, or the first conflict found.
+    pub fn resolve(&self) -> Result<Requirements, RequirementsError> {
+        let headless = self.features.contains(&RenderFeature::Headless);
+
+        if headless && !self.window_extensions.is_empty() {
+            return Err(RequirementsError::HeadlessWithWindow);
+        }
+
+        if headless && self.features.contains(&RenderFeature::Hdr) {
+            return Err(RequirementsError::Conflict(
+                RenderFeature::Headless,
+                RenderFeature::Hdr,
+            ));
+        }
+
+        let mut requirements = Requirements::default();
+
+        if !headless {
+            push_unique(
+                &mut requirements.instance_extensions,
+                self.window_extensions.iter().map(CString::as_c_str),
+            );
+            push_unique(
+                &mut requirements.device_extensions,
+                [vk::KHR_SWAPCHAIN_NAME],
+            );
+        }
+
+        for &feature in &self.features {
+            if self.api_version < feature.min_api_version() {
+                return Err(RequirementsError::ApiVersionTooLow {
+                    feature,
+                    required: feature.min_api_version(),
+                });
+            }
+
+            self.add_feature(feature, &mut requirements);
+        }
+
+        Ok(requirements)
+    }
+
+    fn add_feature(&self, feature: RenderFeature, requirements: &mut Requirements) {
+        let below_1_1 = self.api_version < vk::API_VERSION_1_1;
+        let below_1_2 = self.api_version < vk::API_VERSION_1_2;
+        let below_1_3 = self.api_version < vk::API_VERSION_1_3;
+
+        match feature {
+            RenderFeature::DebugLayer => {
+                push_unique(
+                    &mut requirements.instance_extensions,
+                    [vk::EXT_DEBUG_UTILS_NAME],
+                );
+                push_unique(
+                    &mut requirements.layers,
+                    get_validation_layers().iter().map(CString::as_c_str),
+                );
+            }
+            RenderFeature::DynamicRendering if below_1_3 => {
+                if below_1_1 {
+                    push_unique(
+                        &mut requirements.instance_extensions,
+                        [vk::KHR_GET_PHYSICAL_DEVICE_PROPERTIES2_NAME],
+                    );
+                    push_unique(
+                        &mut requirements.device_extensions,
+                        [vk::KHR_MULTIVIEW_NAME, vk::KHR_MAINTENANCE2_NAME],
+                    );
+                }
+
+                if below_1_2 {
+                    push_unique(
+                        &mut requirements.device_extensions,
+                        [
+                            vk::KHR_CREATE_RENDERPASS2_NAME,
+                            vk::KHR_DEPTH_STENCIL_RESOLVE_NAME,
+                        ],
+                    );
+                }
+
+                push_unique(
+                    &mut requirements.device_extensions,
+                    [vk::KHR_DYNAMIC_RENDERING_NAME],
+                );
+            }
+            RenderFeature::DynamicRendering => {}
+            RenderFeature::RayTracing => {
+                if below_1_2 {
+                    push_unique(
+                        &mut requirements.device_extensions,
+                        [
+                            vk::KHR_BUFFER_DEVICE_ADDRESS_NAME,
+                            vk::EXT_DESCRIPTOR_INDEXING_NAME,
+                            vk::KHR_SHADER_FLOAT_CONTROLS_NAME,
+                            vk::KHR_SPIRV_1_4_NAME,
+                        ],
+                    );
+                }
+
+                push_unique(
+                    &mut requirements.device_extensions,
+                    [
+                        vk::KHR_DEFERRED_HOST_OPERATIONS_NAME,
+                        vk::KHR_ACCELERATION_STRUCTURE_NAME,
+                        vk::KHR_RAY_TRACING_PIPELINE_NAME,
+                    ],
+                );
+            }
+            RenderFeature::Hdr => {
+                push_unique(
+                    &mut requirements.instance_extensions,
+                    [vk::EXT_SWAPCHAIN_COLORSPACE_NAME],
+                );
+                push_unique(
+                    &mut requirements.device_extensions,
+                    [vk::EXT_HDR_METADATA_NAME],
+                );
+            }
+            RenderFeature::Headless => {}
+        }
+    }
+}
+
+/// Appends the names that aren't in the collection yet, keeping their order.
+fn push_unique<'a>(extensions: &mut Extensions, names: impl IntoIterator<Item = &'a CStr>) {
+    for name in names {
+        if !extensions.iter().any(|e| e.as_c_str() == name) {
+            extensions.push(name.to_owned());
+        }
+    }
+}
+
+/// Represents a conflict found while resolving requirements.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RequirementsError {
+    /// Two requested features can't be used together.
+    Conflict(RenderFeature, RenderFeature),
+    /// Headless rendering was requested along with window extensions.
+    HeadlessWithWindow,
+    /// The instance API version is too low for a feature.
+    ApiVersionTooLow {
+        /// The feature that needs a newer version.
+        feature: RenderFeature,
+        /// The lowest version the feature works with.
+        required: u32,
+    },
+}
+
+impl fmt::Display for RequirementsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Conflict(a, b) => write!(f, "{:?} can't be used with {:?}", a, b),
+            Self::HeadlessWithWindow => {
+                write!(f, "headless rendering can't use window extensions")
+            }
+            Self::ApiVersionTooLow { feature, required } => write!(
+                f,
+                "{:?} needs Vulkan {}.{} or newer",
+                feature,
+                vk::api_version_major(*required),
+                vk::api_version_minor(*required)
+            ),
+        }
+    }
+}
+
+impl error::Error for RequirementsError {}
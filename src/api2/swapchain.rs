@@ -1,4 +1,70 @@
-use ash::{khr::surface, prelude::*, vk};
+use std::marker::PhantomData;
+
+use ash::{
+    khr::{surface, swapchain},
+    prelude::*,
+    vk,
+};
+
+use super::{host_allocation_callbacks, Device, ErrorCtx, Instance, ResultExt};
+
+/// The `current_extent` width and height of surfaces whose size is decided by the swapchain
+/// created for them, e.g. on Wayland.
+pub const UNDEFINED_EXTENT: u32 = u32::MAX;
+
+/// The extent to create a swapchain with, see [choose_swapchain_extent].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SwapchainExtent {
+    Extent(vk::Extent2D),
+    /// The surface or the framebuffer has no area, usually because the window is minimized. No
+    /// swapchain can be created for it until it's resized.
+    NeedsWait,
+}
+
+impl SwapchainExtent {
+    /// The extent, `None` when the swapchain has to wait.
+    pub fn extent(self) -> Option<vk::Extent2D> {
+        match self {
+            Self::Extent(extent) => Some(extent),
+            Self::NeedsWait => None,
+        }
+    }
+}
+
+/// Chooses the extent of a swapchain for a framebuffer of `width` by `height` pixels.
+///
+/// The surface's `current_extent` is used when it reports one, the swapchain has to match it.
+/// When it's [UNDEFINED_EXTENT] the framebuffer size is clamped to the surface's limits instead.
+/// A zero-sized framebuffer or result means [SwapchainExtent::NeedsWait]: minimized windows report
+/// a current and maximum extent of 0x0 on some platforms, which clamping would otherwise turn into
+/// an invalid swapchain or a 1x1 one.
+pub fn choose_swapchain_extent(
+    capabilities: &vk::SurfaceCapabilitiesKHR,
+    width: u32,
+    height: u32,
+) -> SwapchainExtent {
+    if width == 0 || height == 0 {
+        return SwapchainExtent::NeedsWait;
+    }
+
+    let extent = if capabilities.current_extent.width != UNDEFINED_EXTENT {
+        capabilities.current_extent
+    } else {
+        let (min, max) = (capabilities.min_image_extent, capabilities.max_image_extent);
+
+        // Not clamp, which panics when a misbehaving surface reports a minimum over the maximum.
+        vk::Extent2D {
+            width: width.max(min.width).min(max.width),
+            height: height.max(min.height).min(max.height),
+        }
+    };
+
+    if extent.width == 0 || extent.height == 0 {
+        SwapchainExtent::NeedsWait
+    } else {
+        SwapchainExtent::Extent(extent)
+    }
+}
 
 /// Details about what the swapchain supports.
 #[derive(Clone, Default)]
@@ -35,16 +101,30 @@ impl SwapchainSupportDetails {
     }
 
     /// Choose the format of the swapchain.
+    ///
+    /// Prefers `B8G8R8A8_SRGB`, then any other sRGB format, and only falls back to the first
+    /// reported (usually UNORM) format when no sRGB format is available.
     pub fn choose_format(&self) -> &vk::SurfaceFormatKHR {
-        for format in &self.formats {
-            if format.format == vk::Format::B8G8R8A8_SRGB
-                && format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
-            {
-                return format;
-            }
-        }
+        self.formats
+            .iter()
+            .find(|format| {
+                format.format == vk::Format::B8G8R8A8_SRGB
+                    && format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
+            })
+            .or_else(|| {
+                self.formats.iter().find(|format| {
+                    is_srgb_format(format.format)
+                        && format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
+                })
+            })
+            .unwrap_or(&self.formats[0])
+    }
 
-        &self.formats[0]
+    /// Whether the surface supports any sRGB format.
+    pub fn has_srgb_format(&self) -> bool {
+        self.formats
+            .iter()
+            .any(|format| is_srgb_format(format.format))
     }
 
     /// Choose the present mode of the swapchain.
@@ -58,21 +138,328 @@ impl SwapchainSupportDetails {
         vk::PresentModeKHR::FIFO
     }
 
-    /// Choose the extent of the swapchain.
-    pub fn choose_extent(&self, width: u32, height: u32) -> vk::Extent2D {
-        let mut current_extent = vk::Extent2D { width, height };
+    /// Choose the extent of the swapchain, see [choose_swapchain_extent].
+    pub fn choose_extent(&self, width: u32, height: u32) -> SwapchainExtent {
+        choose_swapchain_extent(&self.capabilities, width, height)
+    }
+}
+
+/// A swapchain for a surface, owning its images' handles.
+///
+/// The support details are queried again on creation, since the ones stored in the [Device] are
+/// from when it was created and the surface may have been resized since. Drop it and create a
+/// new one when presenting reports it out of date or suboptimal.
+pub struct Swapchain<D: AsRef<Device<I>>, I: AsRef<Instance>> {
+    /// The device the swapchain was created on.
+    pub device: D,
+    /// The swapchain extension loader.
+    pub loader: swapchain::Device,
+    /// The Vulkan swapchain.
+    pub swapchain: vk::SwapchainKHR,
+    /// The images of the swapchain, which may be more than requested.
+    pub images: Vec<vk::Image>,
+    /// The format of the images.
+    pub format: vk::SurfaceFormatKHR,
+    /// The extent of the images.
+    pub extent: vk::Extent2D,
+    /// The present mode the swapchain was created with.
+    pub present_mode: vk::PresentModeKHR,
+    instance: PhantomData<I>,
+}
+
+impl<D: AsRef<Device<I>>, I: AsRef<Instance>> Swapchain<D, I> {
+    /// Creates a swapchain sized to the framebuffer, clamped to what the surface supports.
+    ///
+    /// Fails with `ERROR_OUT_OF_DATE_KHR` while the surface or framebuffer has no area, see
+    /// [SwapchainExtent::NeedsWait]. Wait for the window to be resized and try again.
+    pub fn new(
+        device: D,
+        surface_instance: &surface::Instance,
+        surface: vk::SurfaceKHR,
+        (width, height): (u32, u32),
+    ) -> Result<Self, ErrorCtx> {
+        let (loader, swapchain, format, extent, present_mode) = {
+            let device = device.as_ref();
+
+            let support =
+                SwapchainSupportDetails::query_support(surface_instance, surface, device.physical)
+                    .context("querying swapchain support")?;
+
+            let format = *support.choose_format();
+            let present_mode = support.choose_present_mode();
+            let SwapchainExtent::Extent(extent) = support.choose_extent(width, height) else {
+                return Err(
+                    ErrorCtx::new("creating swapchain", vk::Result::ERROR_OUT_OF_DATE_KHR).details(
+                        format!("the surface has no area, framebuffer {}x{}", width, height),
+                    ),
+                );
+            };
+
+            let mut image_count = support.capabilities.min_image_count + 1;
+
+            if support.capabilities.max_image_count > 0 {
+                image_count = image_count.min(support.capabilities.max_image_count);
+            }
+
+            let queue_family_indices = [device.graphics_family, device.present_family];
+
+            let mut create_info = vk::SwapchainCreateInfoKHR::default()
+                .surface(surface)
+                .min_image_count(image_count)
+                .image_format(format.format)
+                .image_color_space(format.color_space)
+                .image_extent(extent)
+                .image_array_layers(1)
+                .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+                .pre_transform(support.capabilities.current_transform)
+                .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
+                .present_mode(present_mode)
+                .clipped(true);
+
+            create_info = if device.graphics_family != device.present_family {
+                create_info
+                    .image_sharing_mode(vk::SharingMode::CONCURRENT)
+                    .queue_family_indices(&queue_family_indices)
+            } else {
+                create_info.image_sharing_mode(vk::SharingMode::EXCLUSIVE)
+            };
+
+            let loader = swapchain::Device::new(device.instance.as_ref(), &device.logical);
+
+            let swapchain =
+                unsafe { loader.create_swapchain(&create_info, host_allocation_callbacks()) }
+                    .with_context("creating swapchain", || {
+                        format!(
+                            "image_count={}, format={:?}, extent={}x{}, present_mode={:?}",
+                            image_count, format.format, extent.width, extent.height, present_mode
+                        )
+                    })?;
+
+            (loader, swapchain, format, extent, present_mode)
+        };
+
+        let images = match unsafe { loader.get_swapchain_images(swapchain) } {
+            Ok(images) => images,
+            Err(e) => {
+                unsafe { loader.destroy_swapchain(swapchain, host_allocation_callbacks()) };
+                return Err(ErrorCtx::new("getting swapchain images", e));
+            }
+        };
+
+        Ok(Self {
+            device,
+            loader,
+            swapchain,
+            images,
+            format,
+            extent,
+            present_mode,
+            instance: PhantomData,
+        })
+    }
+
+    /// Whether the images store sRGB-encoded values.
+    pub fn is_srgb(&self) -> bool {
+        is_srgb_format(self.format.format)
+    }
+
+    /// Acquires the next image to render to, signaling the semaphore and fence once it can be
+    /// written. Returns its index and whether the swapchain is suboptimal for the surface.
+    pub fn acquire_next_image(
+        &self,
+        timeout: u64,
+        semaphore: vk::Semaphore,
+        fence: vk::Fence,
+    ) -> VkResult<(u32, bool)> {
+        unsafe {
+            self.loader
+                .acquire_next_image(self.swapchain, timeout, semaphore, fence)
+        }
+    }
+
+    /// Presents the image on the device's present queue once the semaphores are signaled. Returns whether
+    /// the swapchain is suboptimal for the surface.
+    pub fn queue_present(
+        &self,
+        wait_semaphores: &[vk::Semaphore],
+        image_index: u32,
+    ) -> VkResult<bool> {
+        let swapchains = [self.swapchain];
+        let image_indices = [image_index];
+
+        let present_info = vk::PresentInfoKHR::default()
+            .wait_semaphores(wait_semaphores)
+            .swapchains(&swapchains)
+            .image_indices(&image_indices);
 
-        current_extent.width = nalgebra::clamp(
-            current_extent.width,
-            self.capabilities.min_image_extent.width,
-            self.capabilities.max_image_extent.width,
+        unsafe {
+            self.loader
+                .queue_present(self.device.as_ref().present_queue, &present_info)
+        }
+    }
+}
+
+impl<D: AsRef<Device<I>>, I: AsRef<Instance>> Drop for Swapchain<D, I> {
+    fn drop(&mut self) {
+        unsafe {
+            self.loader
+                .destroy_swapchain(self.swapchain, host_allocation_callbacks());
+        }
+    }
+}
+
+/// Whether the format stores sRGB-encoded values, meaning the hardware applies the gamma curve on
+/// write and the shaders should output linear colors.
+pub fn is_srgb_format(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::R8_SRGB
+            | vk::Format::R8G8_SRGB
+            | vk::Format::R8G8B8_SRGB
+            | vk::Format::B8G8R8_SRGB
+            | vk::Format::R8G8B8A8_SRGB
+            | vk::Format::B8G8R8A8_SRGB
+            | vk::Format::A8B8G8R8_SRGB_PACK32
+            | vk::Format::BC1_RGB_SRGB_BLOCK
+            | vk::Format::BC1_RGBA_SRGB_BLOCK
+            | vk::Format::BC2_SRGB_BLOCK
+            | vk::Format::BC3_SRGB_BLOCK
+            | vk::Format::BC7_SRGB_BLOCK
+            | vk::Format::ETC2_R8G8B8_SRGB_BLOCK
+            | vk::Format::ETC2_R8G8B8A1_SRGB_BLOCK
+            | vk::Format::ETC2_R8G8B8A8_SRGB_BLOCK
+    )
+}
+
+/// Applies the sRGB transfer function to a linear color component.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Removes the sRGB transfer function from an encoded color component.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.040_45 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extent(width: u32, height: u32) -> vk::Extent2D {
+        vk::Extent2D { width, height }
+    }
+
+    fn capabilities(
+        current: vk::Extent2D,
+        min: vk::Extent2D,
+        max: vk::Extent2D,
+    ) -> vk::SurfaceCapabilitiesKHR {
+        vk::SurfaceCapabilitiesKHR {
+            current_extent: current,
+            min_image_extent: min,
+            max_image_extent: max,
+            ..Default::default()
+        }
+    }
+
+    fn undefined(min: vk::Extent2D, max: vk::Extent2D) -> vk::SurfaceCapabilitiesKHR {
+        capabilities(extent(UNDEFINED_EXTENT, UNDEFINED_EXTENT), min, max)
+    }
+
+    #[test]
+    fn zero_sized_framebuffer_waits() {
+        let surface = undefined(extent(1, 1), extent(4096, 4096));
+
+        assert_eq!(
+            choose_swapchain_extent(&surface, 0, 0),
+            SwapchainExtent::NeedsWait
         );
-        current_extent.height = nalgebra::clamp(
-            current_extent.height,
-            self.capabilities.min_image_extent.height,
-            self.capabilities.max_image_extent.height,
+        assert_eq!(
+            choose_swapchain_extent(&surface, 800, 0),
+            SwapchainExtent::NeedsWait
         );
+        assert_eq!(
+            choose_swapchain_extent(&surface, 0, 600),
+            SwapchainExtent::NeedsWait
+        );
+    }
+
+    #[test]
+    fn zero_sized_surface_waits() {
+        // Minimized windows on Windows report a current and maximum extent of 0x0.
+        let minimized = capabilities(extent(0, 0), extent(0, 0), extent(0, 0));
+        assert_eq!(
+            choose_swapchain_extent(&minimized, 800, 600),
+            SwapchainExtent::NeedsWait
+        );
+
+        let clamped_to_nothing = undefined(extent(0, 0), extent(0, 0));
+        assert_eq!(
+            choose_swapchain_extent(&clamped_to_nothing, 800, 600),
+            SwapchainExtent::NeedsWait
+        );
+    }
+
+    #[test]
+    fn current_extent_wins_over_the_framebuffer() {
+        let surface = capabilities(extent(1280, 720), extent(1, 1), extent(4096, 4096));
+
+        assert_eq!(
+            choose_swapchain_extent(&surface, 800, 600),
+            SwapchainExtent::Extent(extent(1280, 720))
+        );
+        assert_eq!(
+            choose_swapchain_extent(&surface, u32::MAX, u32::MAX),
+            SwapchainExtent::Extent(extent(1280, 720))
+        );
+    }
 
-        current_extent
+    #[test]
+    fn undefined_extent_clamps_the_framebuffer() {
+        let surface = undefined(extent(16, 16), extent(4096, 2048));
+
+        assert_eq!(
+            choose_swapchain_extent(&surface, 800, 600),
+            SwapchainExtent::Extent(extent(800, 600))
+        );
+        assert_eq!(
+            choose_swapchain_extent(&surface, 1, 1),
+            SwapchainExtent::Extent(extent(16, 16))
+        );
+        assert_eq!(
+            choose_swapchain_extent(&surface, 333, 5000),
+            SwapchainExtent::Extent(extent(333, 2048))
+        );
+        assert_eq!(
+            choose_swapchain_extent(&surface, u32::MAX, u32::MAX),
+            SwapchainExtent::Extent(extent(4096, 2048))
+        );
+    }
+
+    #[test]
+    fn inverted_limits_do_not_panic() {
+        let surface = undefined(extent(1024, 1024), extent(512, 512));
+
+        assert_eq!(
+            choose_swapchain_extent(&surface, 800, 600),
+            SwapchainExtent::Extent(extent(512, 512))
+        );
+    }
+
+    #[test]
+    fn needs_wait_has_no_extent() {
+        assert_eq!(SwapchainExtent::NeedsWait.extent(), None);
+        assert_eq!(
+            SwapchainExtent::Extent(extent(1, 1)).extent(),
+            Some(extent(1, 1))
+        );
     }
 }
@@ -0,0 +1,332 @@
+//! Text entry, kept apart from raw key events: what was typed comes from character events, which
+//! already went through the keyboard layout, dead keys and the input method, while keys only
+//! move the caret and delete.
+//!
+//! GLFW reports text an input method committed as character events but not the composition in
+//! progress, which the input method draws itself on Win32 and most Linux desktops. Backends that
+//! report it, e.g. a host embedding the renderer, forward it with [TextInput::handle] and
+//! [TextEvent::Composition] so it's drawn inline.
+
+use glfw::{Action, Key, Modifiers, WindowEvent};
+
+/// An edit of a [TextInput], what window events translate to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TextEvent {
+    /// Text typed or committed by the input method, replacing the composition.
+    Commit(String),
+    /// The text the input method is composing, with the caret at byte `cursor` of it. Empty text
+    /// cancels the composition.
+    Composition {
+        text: String,
+        cursor: usize,
+    },
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    /// Enter was pressed, see [TextInput::take_submitted].
+    Submit,
+}
+
+impl TextEvent {
+    /// The edit a GLFW event makes, `None` for events unrelated to text entry.
+    ///
+    /// Only [WindowEvent::Char] is used for typed text, as windows polling every event get each
+    /// character twice, once with its modifiers.
+    pub fn from_glfw_event(event: &WindowEvent) -> Option<Self> {
+        match *event {
+            WindowEvent::Char(c) => Some(Self::Commit(c.to_string())),
+            WindowEvent::Key(key, _, Action::Press | Action::Repeat, modifiers) => {
+                Self::from_key(key, modifiers)
+            }
+            _ => None,
+        }
+    }
+
+    fn from_key(key: Key, modifiers: Modifiers) -> Option<Self> {
+        // Shortcuts like Ctrl+Left are the application's to handle.
+        if modifiers.intersects(Modifiers::Control | Modifiers::Alt | Modifiers::Super) {
+            return None;
+        }
+
+        Some(match key {
+            Key::Backspace => Self::Backspace,
+            Key::Delete => Self::Delete,
+            Key::Left => Self::Left,
+            Key::Right => Self::Right,
+            Key::Home => Self::Home,
+            Key::End => Self::End,
+            Key::Enter | Key::KpEnter => Self::Submit,
+            _ => return None,
+        })
+    }
+}
+
+/// The text being composed by an input method, not part of the text until committed.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Composition {
+    pub text: String,
+    /// The caret, a byte offset into `text`.
+    pub cursor: usize,
+}
+
+/// A single line of text being entered, with a caret and the input method's composition.
+///
+/// Only takes events while active, so the same events can drive an [ActionMap] otherwise:
+///
+/// ```ignore
+/// for event in window.flush_events() {
+///     if !text_input.handle_glfw_event(&event) {
+///         action_map.handle_glfw_event(&event);
+///     }
+/// }
+/// ```
+///
+/// [ActionMap]: super::ActionMap
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TextInput {
+    text: String,
+    /// The caret, a byte offset into `text` on a character boundary.
+    cursor: usize,
+    composition: Option<Composition>,
+    submitted: Option<String>,
+    active: bool,
+}
+
+impl TextInput {
+    /// An inactive, empty input.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An inactive input holding `text`, with the caret at its end.
+    pub fn with_text(text: impl Into<String>) -> Self {
+        let text = text.into();
+
+        Self {
+            cursor: text.len(),
+            text,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Starts or stops taking events, e.g. when the text field gains or loses focus. Stopping
+    /// cancels the composition.
+    pub fn set_active(&mut self, active: bool) {
+        self.active = active;
+
+        if !active {
+            self.composition = None;
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the text, moving the caret to its end and cancelling the composition.
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.cursor = self.text.len();
+        self.composition = None;
+    }
+
+    /// The caret, a byte offset into [TextInput::text].
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn composition(&self) -> Option<&Composition> {
+        self.composition.as_ref()
+    }
+
+    /// The text as it should be drawn, with the composition at the caret, and the caret as a byte
+    /// offset into it.
+    pub fn display(&self) -> (String, usize) {
+        let mut text = self.text.clone();
+
+        match &self.composition {
+            Some(composition) => {
+                text.insert_str(self.cursor, &composition.text);
+                (text, self.cursor + composition.cursor)
+            }
+            None => (text, self.cursor),
+        }
+    }
+
+    /// The text entered when Enter was last pressed, if it wasn't taken yet. The input is
+    /// cleared for the next entry.
+    pub fn take_submitted(&mut self) -> Option<String> {
+        self.submitted.take()
+    }
+
+    /// Applies a GLFW event, returning whether it was used for text entry and shouldn't trigger
+    /// anything else.
+    pub fn handle_glfw_event(&mut self, event: &WindowEvent) -> bool {
+        if !self.active {
+            return false;
+        }
+
+        match TextEvent::from_glfw_event(event) {
+            Some(event) => {
+                self.handle(event);
+                true
+            }
+            // Releases of the keys used for editing are used too, so they don't reach actions
+            // whose presses they never saw.
+            None => matches!(event, WindowEvent::Key(key, _, Action::Release, _)
+                if TextEvent::from_key(*key, Modifiers::empty()).is_some()),
+        }
+    }
+
+    /// Applies an edit, whether the input is active or not.
+    pub fn handle(&mut self, event: TextEvent) {
+        // While composing, the input method handles the editing keys itself.
+        if self.composition.is_some()
+            && !matches!(event, TextEvent::Commit(_) | TextEvent::Composition { .. })
+        {
+            return;
+        }
+
+        match event {
+            TextEvent::Commit(text) => {
+                self.composition = None;
+                self.text.insert_str(self.cursor, &text);
+                self.cursor += text.len();
+            }
+            TextEvent::Composition { text, cursor } => {
+                self.composition = (!text.is_empty()).then(|| Composition {
+                    cursor: floor_char_boundary(&text, cursor),
+                    text,
+                });
+            }
+            TextEvent::Backspace => {
+                if let Some(previous) = self.previous_boundary() {
+                    self.text.replace_range(previous..self.cursor, "");
+                    self.cursor = previous;
+                }
+            }
+            TextEvent::Delete => {
+                if let Some(next) = self.next_boundary() {
+                    self.text.replace_range(self.cursor..next, "");
+                }
+            }
+            TextEvent::Left => self.cursor = self.previous_boundary().unwrap_or(self.cursor),
+            TextEvent::Right => self.cursor = self.next_boundary().unwrap_or(self.cursor),
+            TextEvent::Home => self.cursor = 0,
+            TextEvent::End => self.cursor = self.text.len(),
+            TextEvent::Submit => {
+                self.submitted = Some(std::mem::take(&mut self.text));
+                self.cursor = 0;
+            }
+        }
+    }
+
+    fn previous_boundary(&self) -> Option<usize> {
+        self.text[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map(|(index, _)| index)
+    }
+
+    fn next_boundary(&self) -> Option<usize> {
+        self.text[self.cursor..]
+            .chars()
+            .next()
+            .map(|c| self.cursor + c.len_utf8())
+    }
+}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    (0..=index.min(text.len()))
+        .rev()
+        .find(|&index| text.is_char_boundary(index))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn composition(text: &str, cursor: usize) -> TextEvent {
+        TextEvent::Composition {
+            text: text.to_string(),
+            cursor,
+        }
+    }
+
+    #[test]
+    fn editing_keys_step_over_whole_characters() {
+        // 'é' is 2 bytes and '€' 3.
+        let mut input = TextInput::with_text("aé€b");
+        assert_eq!(input.cursor(), 7);
+
+        input.handle(TextEvent::Left);
+        assert_eq!(input.cursor(), 6);
+        input.handle(TextEvent::Left);
+        assert_eq!(input.cursor(), 3);
+        input.handle(TextEvent::Backspace);
+        assert_eq!((input.text(), input.cursor()), ("a€b", 1));
+        input.handle(TextEvent::Delete);
+        assert_eq!((input.text(), input.cursor()), ("ab", 1));
+
+        input.handle(TextEvent::Commit("€".to_string()));
+        input.handle(TextEvent::Home);
+        input.handle(TextEvent::Right);
+        input.handle(TextEvent::Right);
+        assert_eq!((input.text(), input.cursor()), ("a€b", 4));
+    }
+
+    #[test]
+    fn editing_keys_stop_at_the_ends() {
+        let mut input = TextInput::with_text("é");
+
+        input.handle(TextEvent::Right);
+        input.handle(TextEvent::Delete);
+        assert_eq!((input.text(), input.cursor()), ("é", 2));
+
+        input.handle(TextEvent::Home);
+        input.handle(TextEvent::Left);
+        input.handle(TextEvent::Backspace);
+        assert_eq!((input.text(), input.cursor()), ("é", 0));
+    }
+
+    #[test]
+    fn commit_replaces_the_composition() {
+        let mut input = TextInput::with_text("ab");
+        input.handle(TextEvent::Left);
+
+        input.handle(composition("にほ", 3));
+        assert_eq!(input.display(), ("aにほb".to_string(), 4));
+        // The input method handles the editing keys while composing.
+        input.handle(TextEvent::Backspace);
+        assert_eq!(input.text(), "ab");
+
+        input.handle(TextEvent::Commit("日本".to_string()));
+        assert_eq!(input.composition(), None);
+        assert_eq!((input.text(), input.cursor()), ("a日本b", 7));
+        assert_eq!(input.display(), ("a日本b".to_string(), 7));
+    }
+
+    #[test]
+    fn composition_cursor_is_clamped_to_a_character_boundary() {
+        let mut input = TextInput::new();
+
+        // Inside the second character.
+        input.handle(composition("日本", 4));
+        assert_eq!(input.composition().map(|c| c.cursor), Some(3));
+
+        // Past the end.
+        input.handle(composition("日本", 100));
+        assert_eq!(input.composition().map(|c| c.cursor), Some(6));
+
+        input.handle(composition("", 0));
+        assert_eq!(input.composition(), None);
+    }
+}
@@ -0,0 +1,97 @@
+//! Frame timing.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// A monotonic clock ticked once per frame.
+#[derive(Debug, Clone)]
+pub struct FrameClock {
+    /// When the clock was created.
+    pub start: Instant,
+    /// When the clock was last ticked.
+    pub last_tick: Instant,
+    /// Time between the last two ticks.
+    pub delta: Duration,
+    /// Number of ticks so far.
+    pub frame_index: u64,
+    /// Recent frame durations used to smooth the FPS, newest last.
+    pub samples: VecDeque<Duration>,
+    /// Maximum number of samples kept for smoothing.
+    pub window: usize,
+}
+
+impl Default for FrameClock {
+    fn default() -> Self {
+        Self::new(60)
+    }
+}
+
+impl FrameClock {
+    /// Creates a new clock smoothing the FPS over the last `window` frames.
+    pub fn new(window: usize) -> Self {
+        let now = Instant::now();
+
+        Self {
+            start: now,
+            last_tick: now,
+            delta: Duration::ZERO,
+            frame_index: 0,
+            samples: VecDeque::with_capacity(window.max(1)),
+            window: window.max(1),
+        }
+    }
+
+    /// Marks the start of a new frame, returning the time since the previous one.
+    pub fn tick(&mut self) -> Duration {
+        let now = Instant::now();
+
+        self.delta = now - self.last_tick;
+        self.last_tick = now;
+        self.frame_index += 1;
+
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(self.delta);
+
+        self.delta
+    }
+
+    /// Time between the last two ticks in seconds.
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Time since the clock was created.
+    pub fn total(&self) -> Duration {
+        self.last_tick - self.start
+    }
+
+    /// Time since the clock was created in seconds.
+    pub fn total_seconds(&self) -> f64 {
+        self.total().as_secs_f64()
+    }
+
+    /// Average frame time over the smoothing window.
+    pub fn average_frame_time(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+
+        self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+    }
+
+    /// Frames per second averaged over the smoothing window.
+    pub fn fps(&self) -> f32 {
+        let average = self.average_frame_time().as_secs_f32();
+
+        if average > 0.0 {
+            1.0 / average
+        } else {
+            0.0
+        }
+    }
+}
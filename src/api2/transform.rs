@@ -0,0 +1,250 @@
+//! Translation, rotation and scale transforms.
+
+use std::ops::Mul;
+
+use nalgebra_glm as glm;
+
+/// A transform made of a translation, a rotation and a non-uniform scale, applied in the order
+/// scale, rotation, translation.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    /// The translation.
+    pub translation: glm::Vec3,
+    /// The rotation as a unit quaternion.
+    pub rotation: glm::Quat,
+    /// The scale along each axis.
+    pub scale: glm::Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    /// The transform that doesn't change anything.
+    pub const IDENTITY: Self = Self {
+        translation: glm::Vec3::new(0.0, 0.0, 0.0),
+        rotation: glm::Quat::new(1.0, 0.0, 0.0, 0.0),
+        scale: glm::Vec3::new(1.0, 1.0, 1.0),
+    };
+
+    /// Creates a transform that only translates.
+    pub fn from_translation(translation: glm::Vec3) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Creates a transform that only rotates.
+    pub fn from_rotation(rotation: glm::Quat) -> Self {
+        Self {
+            rotation,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Creates a transform that only scales.
+    pub fn from_scale(scale: glm::Vec3) -> Self {
+        Self {
+            scale,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Rotates the transform by `angle` radians around `axis`.
+    pub fn rotate(&mut self, angle: f32, axis: &glm::Vec3) {
+        self.rotation = glm::quat_normalize(&(glm::quat_angle_axis(angle, axis) * self.rotation));
+    }
+
+    /// The model matrix of the transform.
+    pub fn to_matrix(&self) -> glm::Mat4 {
+        glm::translation(&self.translation)
+            * glm::quat_to_mat4(&self.rotation)
+            * glm::scaling(&self.scale)
+    }
+
+    /// The matrix transforming normals, the inverse transpose of the upper 3x3 of the model matrix.
+    pub fn normal_matrix(&self) -> glm::Mat3 {
+        let model = glm::mat4_to_mat3(&self.to_matrix());
+        glm::transpose(&glm::inverse(&model))
+    }
+
+    /// Transforms a point.
+    pub fn transform_point(&self, point: &glm::Vec3) -> glm::Vec3 {
+        self.translation + glm::quat_rotate_vec3(&self.rotation, &self.scale.component_mul(point))
+    }
+
+    /// Transforms a direction, ignoring the translation.
+    pub fn transform_vector(&self, vector: &glm::Vec3) -> glm::Vec3 {
+        glm::quat_rotate_vec3(&self.rotation, &self.scale.component_mul(vector))
+    }
+
+    /// Composes a child transform into this one, returning the child's transform in this
+    /// transform's parent space.
+    ///
+    /// Shear caused by rotated non-uniform scales can't be represented and is dropped.
+    pub fn compose(&self, child: &Self) -> Self {
+        Self {
+            translation: self.transform_point(&child.translation),
+            rotation: self.rotation * child.rotation,
+            scale: self.scale.component_mul(&child.scale),
+        }
+    }
+
+    /// The inverse transform, exact when the scale is uniform.
+    pub fn inverse(&self) -> Self {
+        let rotation = glm::quat_inverse(&self.rotation);
+        let scale = glm::vec3(1.0 / self.scale.x, 1.0 / self.scale.y, 1.0 / self.scale.z);
+        let translation =
+            -scale.component_mul(&glm::quat_rotate_vec3(&rotation, &self.translation));
+
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    /// Converts the transform into the layout expected by shaders.
+    pub fn to_gpu(&self) -> GpuTransform {
+        GpuTransform::new(&self.to_matrix(), &self.normal_matrix())
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.compose(&rhs)
+    }
+}
+
+pub use crate::types::GpuTransform;
+
+impl GpuTransform {
+    /// Creates the GPU representation from a model and normal matrix.
+    pub fn new(model: &glm::Mat4, normal: &glm::Mat3) -> Self {
+        Self::from_columns((*model).into(), (*normal).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::types::Pod;
+
+    fn assert_close(a: &glm::Vec3, b: &glm::Vec3) {
+        assert!((a - b).norm() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    fn sample() -> Transform {
+        Transform {
+            translation: glm::vec3(1.0, 2.0, 3.0),
+            rotation: glm::quat_angle_axis(FRAC_PI_2, &glm::Vec3::z()),
+            scale: glm::vec3(2.0, 2.0, 2.0),
+        }
+    }
+
+    #[test]
+    fn identity_changes_nothing() {
+        let point = glm::vec3(3.0, -4.0, 5.0);
+
+        assert_eq!(Transform::default(), Transform::IDENTITY);
+        assert_eq!(Transform::IDENTITY.to_matrix(), glm::Mat4::identity());
+        assert_eq!(Transform::IDENTITY.transform_point(&point), point);
+        assert_eq!(sample() * Transform::IDENTITY, sample());
+        assert_eq!(Transform::IDENTITY * sample(), sample());
+    }
+
+    #[test]
+    fn points_are_scaled_then_rotated_then_translated() {
+        let point = glm::vec3(1.0, 0.0, 0.0);
+
+        // Scaled to (2, 0, 0), rotated to (0, 2, 0), then translated.
+        assert_close(&sample().transform_point(&point), &glm::vec3(1.0, 4.0, 3.0));
+        assert_close(
+            &sample().transform_vector(&point),
+            &glm::vec3(0.0, 2.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn matrix_agrees_with_transform_point() {
+        let transform = sample();
+        let point = glm::vec3(0.5, -3.0, 7.0);
+        let by_matrix = transform.to_matrix() * glm::vec4(point.x, point.y, point.z, 1.0);
+
+        assert_close(&by_matrix.xyz(), &transform.transform_point(&point));
+    }
+
+    #[test]
+    fn compose_applies_the_child_first() {
+        let parent = sample();
+        let child = Transform::from_translation(glm::vec3(1.0, 0.0, 0.0));
+        let point = glm::vec3(0.0, 1.0, 0.0);
+
+        assert_close(
+            &parent.compose(&child).transform_point(&point),
+            &parent.transform_point(&child.transform_point(&point)),
+        );
+        assert_close(
+            &(parent * child).transform_point(&point),
+            &parent.transform_point(&child.transform_point(&point)),
+        );
+    }
+
+    #[test]
+    fn inverse_undoes_uniform_scales() {
+        let transform = sample();
+        let point = glm::vec3(-2.0, 5.0, 1.0);
+
+        assert_close(
+            &transform
+                .inverse()
+                .transform_point(&transform.transform_point(&point)),
+            &point,
+        );
+        assert_close(
+            &(transform * transform.inverse()).transform_point(&point),
+            &point,
+        );
+    }
+
+    #[test]
+    fn rotate_accumulates() {
+        let mut transform = Transform::IDENTITY;
+
+        for _ in 0..4 {
+            transform.rotate(FRAC_PI_2, &glm::Vec3::z());
+        }
+
+        assert_close(
+            &transform.transform_vector(&glm::Vec3::x()),
+            &glm::Vec3::x(),
+        );
+        assert!((glm::quat_length(&transform.rotation) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn normal_matrix_undoes_non_uniform_scale() {
+        let transform = Transform::from_scale(glm::vec3(2.0, 1.0, 1.0));
+        let normal = transform.normal_matrix() * glm::vec3(1.0, 0.0, 0.0);
+
+        assert_close(&normal, &glm::vec3(0.5, 0.0, 0.0));
+    }
+
+    #[test]
+    fn gpu_layout_pads_the_normal_matrix() {
+        let gpu = Transform::from_translation(glm::vec3(1.0, 2.0, 3.0)).to_gpu();
+
+        assert_eq!(gpu.model[3], [1.0, 2.0, 3.0, 1.0]);
+        assert_eq!(gpu.normal[0], [1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(gpu.normal[2], [0.0, 0.0, 1.0, 0.0]);
+        assert_eq!(gpu.bytes_of().len(), 28 * 4);
+    }
+}
@@ -0,0 +1,259 @@
+//! Converting between semver strings, such as `CARGO_PKG_VERSION`, and the versions Vulkan packs
+//! into a `u32`.
+
+use std::{error, fmt, num::ParseIntError, str::FromStr};
+
+use ash::vk;
+
+/// A version as Vulkan packs it: a 3-bit variant, 7-bit major, 10-bit minor and 12-bit patch.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    /// Non-zero for variants of the Vulkan API, always zero for application versions.
+    pub variant: u32,
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            variant: 0,
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// The version of this crate, the default application and engine version of
+    /// [super::InstanceBuilder]. Applications depending on the crate use [crate::cargo_version].
+    pub fn of_crate() -> Self {
+        env!("CARGO_PKG_VERSION").parse().unwrap_or_default()
+    }
+
+    /// Unpacks a version made with `VK_MAKE_API_VERSION`, e.g. an API version.
+    pub const fn from_vulkan(version: u32) -> Self {
+        Self {
+            variant: vk::api_version_variant(version),
+            major: vk::api_version_major(version),
+            minor: vk::api_version_minor(version),
+            patch: vk::api_version_patch(version),
+        }
+    }
+
+    /// Packs the version, the components have to fit their bits, which [Version::from_str]
+    /// checks.
+    pub const fn to_vulkan(self) -> u32 {
+        vk::make_api_version(self.variant, self.major, self.minor, self.patch)
+    }
+
+    /// Unpacks a driver version, whose encoding depends on the vendor. Drivers of unknown vendors
+    /// are assumed to use the Vulkan packing.
+    pub const fn from_driver(vendor_id: u32, version: u32) -> Self {
+        match vendor_id {
+            // NVIDIA: 10-bit major, 8-bit minor, 8-bit secondary branch, 6-bit tertiary branch.
+            0x10de => Self::new(
+                (version >> 22) & 0x3ff,
+                (version >> 14) & 0xff,
+                (version >> 6) & 0xff,
+            ),
+            // Intel on Windows: 18-bit major and 14-bit minor. On Linux Mesa uses the Vulkan
+            // packing, and there's no way to tell them apart here.
+            0x8086 if cfg!(windows) => Self::new(version >> 14, version & 0x3fff, 0),
+            _ => Self::from_vulkan(version),
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+
+        if self.variant != 0 {
+            write!(f, " (variant {})", self.variant)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Version {
+    type Err = VersionError;
+
+    /// Parses `major.minor.patch`, ignoring semver pre-release and build metadata.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let core = s.split_once(['-', '+']).map_or(s, |(core, _)| core).trim();
+
+        let mut components = core.split('.');
+        let mut next = || -> Result<u32, VersionError> {
+            components
+                .next()
+                .ok_or_else(|| VersionError::Malformed(s.to_owned()))?
+                .parse()
+                .map_err(VersionError::from)
+        };
+
+        let version = Self::new(next()?, next()?, next()?);
+
+        if components.next().is_some() {
+            return Err(VersionError::Malformed(s.to_owned()));
+        }
+
+        if version.major > 0x7f || version.minor > 0x3ff || version.patch > 0xfff {
+            return Err(VersionError::OutOfRange(version));
+        }
+
+        Ok(version)
+    }
+}
+
+/// The version of the crate calling it, as a [Version].
+///
+/// Panics if `CARGO_PKG_VERSION` doesn't fit the Vulkan packing.
+#[macro_export]
+macro_rules! cargo_version {
+    () => {
+        env!("CARGO_PKG_VERSION")
+            .parse::<$crate::api2::Version>()
+            .expect("CARGO_PKG_VERSION is not a Vulkan compatible version")
+    };
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionError {
+    /// Not in the `major.minor.patch` form.
+    Malformed(String),
+    /// A component isn't a number.
+    InvalidNumber(ParseIntError),
+    /// A component doesn't fit its bits in the packed version.
+    OutOfRange(Version),
+}
+
+impl From<ParseIntError> for VersionError {
+    fn from(e: ParseIntError) -> Self {
+        Self::InvalidNumber(e)
+    }
+}
+
+impl fmt::Display for VersionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Malformed(version) => write!(f, "{:?} is not a major.minor.patch version", version),
+            Self::InvalidNumber(e) => write!(f, "invalid version component: {}", e),
+            Self::OutOfRange(version) => write!(
+                f,
+                "{} doesn't fit a Vulkan version (major up to 127, minor up to 1023, patch up to 4095)",
+                version
+            ),
+        }
+    }
+}
+
+impl error::Error for VersionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_semver() {
+        assert_eq!("1.2.3".parse(), Ok(Version::new(1, 2, 3)));
+        assert_eq!("0.0.0".parse(), Ok(Version::new(0, 0, 0)));
+        assert_eq!("0.1.0-alpha.1".parse(), Ok(Version::new(0, 1, 0)));
+        assert_eq!("3.0.7+build.5".parse(), Ok(Version::new(3, 0, 7)));
+        assert_eq!(
+            "10.20.30-rc.1+sha.abc".parse(),
+            Ok(Version::new(10, 20, 30))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_versions() {
+        for version in ["", "1", "1.2", "1.2.3.4", "1..3", "-1.2.3"] {
+            assert!(version.parse::<Version>().is_err(), "{:?}", version);
+        }
+
+        assert!(matches!(
+            "1.2".parse::<Version>(),
+            Err(VersionError::Malformed(_))
+        ));
+        assert!(matches!(
+            "1.two.3".parse::<Version>(),
+            Err(VersionError::InvalidNumber(_))
+        ));
+        assert!(matches!(
+            "4294967296.0.0".parse::<Version>(),
+            Err(VersionError::InvalidNumber(_))
+        ));
+    }
+
+    #[test]
+    fn components_have_to_fit_their_bits() {
+        assert_eq!("127.1023.4095".parse(), Ok(Version::new(127, 1023, 4095)));
+
+        for version in ["128.0.0", "0.1024.0", "0.0.4096", "4294967295.0.0"] {
+            assert!(
+                matches!(version.parse::<Version>(), Err(VersionError::OutOfRange(_))),
+                "{:?}",
+                version
+            );
+        }
+    }
+
+    #[test]
+    fn vulkan_packing_round_trips() {
+        for version in [
+            Version::new(0, 0, 0),
+            Version::new(1, 0, 0),
+            Version::new(1, 3, 281),
+            Version::new(127, 1023, 4095),
+        ] {
+            assert_eq!(Version::from_vulkan(version.to_vulkan()), version);
+        }
+
+        assert_eq!(
+            Version::from_vulkan(vk::API_VERSION_1_3),
+            Version::new(1, 3, 0)
+        );
+        assert_eq!(Version::new(1, 1, 0).to_vulkan(), vk::API_VERSION_1_1);
+        assert_eq!(Version::from_vulkan(u32::MAX).variant, 7);
+    }
+
+    #[test]
+    fn versions_order_by_component() {
+        assert!(Version::new(1, 2, 3) < Version::new(1, 3, 0));
+        assert!(Version::new(1, 10, 0) > Version::new(1, 9, 99));
+        assert!(Version::new(2, 0, 0) > Version::new(1, 1023, 4095));
+    }
+
+    #[test]
+    fn driver_versions_by_vendor() {
+        let nvidia = (535 << 22) | (98 << 14) | (3 << 6);
+        assert_eq!(
+            Version::from_driver(0x10de, nvidia),
+            Version::new(535, 98, 3)
+        );
+        assert_eq!(
+            Version::from_driver(0x1002, vk::make_api_version(0, 2, 0, 279)),
+            Version::new(2, 0, 279)
+        );
+        assert_eq!(Version::from_driver(0, 0), Version::default());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Version::new(1, 2, 3).to_string(), "1.2.3");
+        assert_eq!(
+            Version::from_vulkan(vk::make_api_version(1, 1, 0, 0)).to_string(),
+            "1.0.0 (variant 1)"
+        );
+    }
+
+    #[test]
+    fn crate_version_parses() {
+        assert_eq!(
+            Version::of_crate().to_string(),
+            env!("CARGO_PKG_VERSION").split(['-', '+']).next().unwrap()
+        );
+    }
+}
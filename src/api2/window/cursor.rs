@@ -0,0 +1,116 @@
+//! Backend-independent cursor appearance.
+
+use std::{error, fmt};
+
+/// A cursor shape every platform provides.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CursorIcon {
+    #[default]
+    Arrow,
+    /// The I-beam shown over text.
+    Text,
+    Crosshair,
+    /// The pointing hand shown over links and buttons.
+    Hand,
+    /// The arrow shown over vertical edges, resizing horizontally.
+    ResizeHorizontal,
+    /// The arrow shown over horizontal edges, resizing vertically.
+    ResizeVertical,
+}
+
+/// A custom cursor drawn from an image.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CursorImage {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+    hotspot: (u32, u32),
+}
+
+impl CursorImage {
+    /// Creates a cursor from non-premultiplied RGBA8 pixels, row by row from the top left. The
+    /// cursor points at `hotspot`, in pixels from the top left corner.
+    pub fn new(
+        width: u32,
+        height: u32,
+        rgba: Vec<u8>,
+        hotspot: (u32, u32),
+    ) -> Result<Self, CursorImageError> {
+        if width == 0 || height == 0 {
+            return Err(CursorImageError::Empty);
+        }
+
+        let expected = width as usize * height as usize * 4;
+
+        if rgba.len() != expected {
+            return Err(CursorImageError::Size {
+                expected,
+                found: rgba.len(),
+            });
+        }
+
+        if hotspot.0 >= width || hotspot.1 >= height {
+            return Err(CursorImageError::HotspotOutside {
+                hotspot,
+                size: (width, height),
+            });
+        }
+
+        Ok(Self {
+            width,
+            height,
+            rgba,
+            hotspot,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    pub fn hotspot(&self) -> (u32, u32) {
+        self.hotspot
+    }
+}
+
+/// Represents an error that occurred while creating a [CursorImage].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CursorImageError {
+    /// The image has no pixels.
+    Empty,
+    /// The pixels aren't exactly four bytes per pixel of the image.
+    Size { expected: usize, found: usize },
+    /// The hotspot isn't a pixel of the image.
+    HotspotOutside {
+        hotspot: (u32, u32),
+        size: (u32, u32),
+    },
+}
+
+impl fmt::Display for CursorImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "cursor images can't be empty"),
+            Self::Size { expected, found } => write!(
+                f,
+                "the cursor image takes {} bytes of pixels but {} were given",
+                expected, found
+            ),
+            Self::HotspotOutside { hotspot, size } => write!(
+                f,
+                "the cursor hotspot {:?} is outside of the {}x{} image",
+                hotspot, size.0, size.1
+            ),
+        }
+    }
+}
+
+impl error::Error for CursorImageError {}
@@ -3,9 +3,17 @@
 use std::{error, fmt, ptr::null};
 
 use ash::{khr::surface, prelude::*, vk};
-use glfw::{fail_on_errors, ClientApiHint, Glfw, GlfwReceiver, InitError, PWindow, WindowHint};
+use glfw::{
+    fail_on_errors, ClientApiHint, Cursor, Glfw, GlfwReceiver, InitError, Monitor, PWindow,
+    PixelImage, StandardCursor, VidMode, WindowHint,
+};
 
-use super::super::{Extensions, Instance};
+use super::{
+    super::{Extensions, Instance},
+    CursorIcon, CursorImage, MonitorInfo, VideoMode, WindowState,
+};
+
+use super::super::host_allocation_callbacks;
 
 /// Entry point for GLFW.
 pub struct GlfwEntry {
@@ -36,6 +44,40 @@ impl GlfwEntry {
             .and_then(|v| Extensions::try_from(v).ok())
     }
 
+    /// Whether a queue family of the physical device can present to GLFW windows.
+    ///
+    /// Unlike querying through a surface, this works before any window exists, so a
+    /// presentation-capable device can be picked up front.
+    pub fn get_physical_device_presentation_support<T: AsRef<Instance>>(
+        &self,
+        instance: T,
+        physical_device: vk::PhysicalDevice,
+        queue_family: u32,
+    ) -> bool {
+        self.glfw.get_physical_device_presentation_support_raw(
+            instance.as_ref().handle(),
+            physical_device,
+            queue_family,
+        )
+    }
+
+    /// Returns every connected monitor, the primary one first.
+    pub fn monitors(&mut self) -> Vec<MonitorInfo> {
+        self.glfw.with_connected_monitors(|_, monitors| {
+            monitors
+                .iter()
+                .enumerate()
+                .map(|(i, monitor)| monitor_info(monitor, i == 0))
+                .collect()
+        })
+    }
+
+    /// Returns the primary monitor, if any is connected.
+    pub fn primary_monitor(&mut self) -> Option<MonitorInfo> {
+        self.glfw
+            .with_primary_monitor(|_, monitor| monitor.map(|monitor| monitor_info(monitor, true)))
+    }
+
     /// Creates a new GLFW window.
     pub fn create_window<T: AsRef<Instance>>(
         &mut self,
@@ -52,6 +94,99 @@ impl GlfwEntry {
 
         GlfwWindow::new(instance, window, events).map_err(GlfwError::from)
     }
+
+    /// Creates a new GLFW window placed according to a saved [WindowState].
+    ///
+    /// The position is only restored when it is still on a connected monitor.
+    pub fn create_window_with_state<T: AsRef<Instance>>(
+        &mut self,
+        instance: T,
+        title: &str,
+        state: &WindowState,
+    ) -> Result<GlfwWindow<T>, GlfwError> {
+        let monitors = self.monitors();
+
+        let mut window = self.create_window(
+            instance,
+            title,
+            state.size.0,
+            state.size.1,
+            glfw::WindowMode::Windowed,
+        )?;
+
+        if state.is_visible_on(&monitors) {
+            window.set_position(state.position);
+        }
+
+        if state.maximized {
+            window.window.maximize();
+        }
+
+        Ok(window)
+    }
+}
+
+fn monitor_info(monitor: &Monitor, primary: bool) -> MonitorInfo {
+    MonitorInfo {
+        name: monitor.get_name(),
+        primary,
+        physical_size: monitor.get_physical_size(),
+        position: monitor.get_pos(),
+        work_area: monitor.get_workarea(),
+        content_scale: monitor.get_content_scale(),
+        current_mode: monitor.get_video_mode().map(VideoMode::from),
+        video_modes: monitor
+            .get_video_modes()
+            .into_iter()
+            .map(VideoMode::from)
+            .collect(),
+    }
+}
+
+impl From<VidMode> for VideoMode {
+    fn from(mode: VidMode) -> Self {
+        Self {
+            width: mode.width,
+            height: mode.height,
+            refresh_rate: mode.refresh_rate,
+            bit_depth: (mode.red_bits, mode.green_bits, mode.blue_bits),
+        }
+    }
+}
+
+impl From<CursorIcon> for Cursor {
+    fn from(value: CursorIcon) -> Self {
+        Cursor::standard(match value {
+            CursorIcon::Arrow => StandardCursor::Arrow,
+            CursorIcon::Text => StandardCursor::IBeam,
+            CursorIcon::Crosshair => StandardCursor::Crosshair,
+            CursorIcon::Hand => StandardCursor::Hand,
+            CursorIcon::ResizeHorizontal => StandardCursor::HResize,
+            CursorIcon::ResizeVertical => StandardCursor::VResize,
+        })
+    }
+}
+
+impl From<&CursorImage> for Cursor {
+    fn from(value: &CursorImage) -> Self {
+        // GLFW reads the pixels as bytes, the words only have to hold them in order.
+        let pixels = value
+            .rgba()
+            .chunks_exact(4)
+            .map(|pixel| u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]))
+            .collect();
+        let (x, y) = value.hotspot();
+
+        Cursor::create_from_pixels(
+            PixelImage {
+                width: value.width(),
+                height: value.height(),
+                pixels,
+            },
+            x,
+            y,
+        )
+    }
 }
 
 /// A GLFW window with a Vulkan surface.
@@ -81,7 +216,11 @@ impl<T: AsRef<Instance>> GlfwWindow<T> {
         let mut surface = vk::SurfaceKHR::null();
 
         window
-            .create_window_surface(instance.as_ref().instance.handle(), null(), &mut surface)
+            .create_window_surface(
+                instance.as_ref().instance.handle(),
+                host_allocation_callbacks().map_or(null(), |callbacks| callbacks),
+                &mut surface,
+            )
             .result()?;
 
         Ok(Self {
@@ -93,6 +232,67 @@ impl<T: AsRef<Instance>> GlfwWindow<T> {
         })
     }
 
+    /// Enables polling of the events used by [super::super::Lifecycle] (iconify and framebuffer size).
+    pub fn set_lifecycle_polling(&mut self, should_poll: bool) {
+        self.window.set_iconify_polling(should_poll);
+        self.window.set_framebuffer_size_polling(should_poll);
+    }
+
+    /// Enables polling of the events used by [super::super::TextInput] (characters and keys).
+    pub fn set_text_input_polling(&mut self, should_poll: bool) {
+        self.window.set_char_polling(should_poll);
+        self.window.set_key_polling(should_poll);
+    }
+
+    /// Shows a standard cursor shape over the window.
+    pub fn set_cursor(&mut self, icon: CursorIcon) {
+        self.window.set_cursor(Some(Cursor::from(icon)));
+    }
+
+    /// Shows a custom cursor over the window.
+    pub fn set_cursor_image(&mut self, image: &CursorImage) {
+        self.window.set_cursor(Some(Cursor::from(image)));
+    }
+
+    /// Returns the position of the window's content area.
+    pub fn position(&self) -> (i32, i32) {
+        self.window.get_pos()
+    }
+
+    /// Moves the window's content area.
+    pub fn set_position(&mut self, (x, y): (i32, i32)) {
+        self.window.set_pos(x, y);
+    }
+
+    /// Returns the size of the window's content area in screen coordinates.
+    pub fn size(&self) -> (u32, u32) {
+        let (width, height) = self.window.get_size();
+        (width as u32, height as u32)
+    }
+
+    /// Resizes the window's content area.
+    pub fn set_size(&mut self, (width, height): (u32, u32)) {
+        self.window.set_size(width as i32, height as i32);
+    }
+
+    /// Captures the current placement so it can be saved and restored later.
+    ///
+    /// When maximized, the restored size is not known to GLFW, so the maximized size is saved.
+    pub fn state(&self, monitors: &[MonitorInfo]) -> WindowState {
+        let mut state = WindowState {
+            position: self.position(),
+            size: self.size(),
+            maximized: self.window.is_maximized(),
+            monitor: None,
+        };
+
+        state.monitor = state
+            .find_monitor(monitors)
+            .and_then(|monitor| monitor.name.clone());
+
+        state
+    }
+
     /// Returns the framebuffer size of the window, converting to the type used in Vulkan.
     pub fn framebuffer_size(&self) -> (u32, u32) {
         let (width, height) = self.window.get_framebuffer_size();
@@ -103,7 +303,8 @@ impl<T: AsRef<Instance>> GlfwWindow<T> {
 impl<T: AsRef<Instance>> Drop for GlfwWindow<T> {
     fn drop(&mut self) {
         unsafe {
-            self.surface_instance.destroy_surface(self.surface, None);
+            self.surface_instance
+                .destroy_surface(self.surface, host_allocation_callbacks());
         }
     }
 }
@@ -1,5 +1,11 @@
 //! Module for window backends.
 
+pub use cursor::*;
 pub use glfw::*;
+pub use monitor::*;
+pub use state::*;
 
+mod cursor;
 mod glfw;
+mod monitor;
+mod state;
@@ -0,0 +1,55 @@
+//! Backend-independent monitor information.
+
+/// A video mode supported by a monitor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VideoMode {
+    /// The width in screen coordinates.
+    pub width: u32,
+    /// The height in screen coordinates.
+    pub height: u32,
+    /// The refresh rate in Hz.
+    pub refresh_rate: u32,
+    /// The bit depth of the red, green and blue channels.
+    pub bit_depth: (u32, u32, u32),
+}
+
+/// A connected monitor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    /// The human-readable name, if the backend knows it.
+    pub name: Option<String>,
+    /// Whether this is the primary monitor.
+    pub primary: bool,
+    /// The physical size in millimetres, which some platforms report as zero.
+    pub physical_size: (i32, i32),
+    /// The position of the monitor on the virtual desktop.
+    pub position: (i32, i32),
+    /// The area not covered by taskbars and docks: x, y, width and height.
+    pub work_area: (i32, i32, i32, i32),
+    /// The ratio between the current DPI and the platform's default DPI.
+    pub content_scale: (f32, f32),
+    /// The current video mode.
+    pub current_mode: Option<VideoMode>,
+    /// Every video mode the monitor supports, sorted from smallest to largest.
+    pub video_modes: Vec<VideoMode>,
+}
+
+impl MonitorInfo {
+    /// The largest supported video mode, preferring higher refresh rates on ties.
+    pub fn largest_mode(&self) -> Option<VideoMode> {
+        self.video_modes
+            .iter()
+            .copied()
+            .max_by_key(|mode| (mode.width as u64 * mode.height as u64, mode.refresh_rate))
+    }
+
+    /// The supported mode closest to the requested size and refresh rate.
+    pub fn closest_mode(&self, width: u32, height: u32, refresh_rate: u32) -> Option<VideoMode> {
+        self.video_modes.iter().copied().min_by_key(|mode| {
+            (
+                mode.width.abs_diff(width) as u64 + mode.height.abs_diff(height) as u64,
+                mode.refresh_rate.abs_diff(refresh_rate),
+            )
+        })
+    }
+}
@@ -0,0 +1,145 @@
+//! Saving and restoring where a window was placed.
+
+use std::{error, fmt, fs, io, num::ParseIntError, path::Path, str::FromStr};
+
+use super::MonitorInfo;
+
+/// The placement of a window, to be restored the next time the application starts.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowState {
+    /// The position of the window's content area on the virtual desktop.
+    pub position: (i32, i32),
+    /// The size of the window's content area in screen coordinates.
+    pub size: (u32, u32),
+    /// Whether the window was maximized.
+    pub maximized: bool,
+    /// The name of the monitor the window was on.
+    pub monitor: Option<String>,
+}
+
+impl WindowState {
+    /// Whether the saved position is still on one of the given monitors.
+    ///
+    /// Restoring a position from a monitor that was since disconnected would open the window
+    /// off-screen, so callers should only apply the position when this is true.
+    pub fn is_visible_on(&self, monitors: &[MonitorInfo]) -> bool {
+        let (x, y) = self.position;
+
+        monitors.iter().any(|monitor| {
+            let (mx, my, mw, mh) = monitor.work_area;
+            x >= mx && y >= my && x < mx + mw && y < my + mh
+        })
+    }
+
+    /// Returns the monitor the position is on, by name first and then by position.
+    pub fn find_monitor<'a>(&self, monitors: &'a [MonitorInfo]) -> Option<&'a MonitorInfo> {
+        monitors
+            .iter()
+            .find(|monitor| monitor.name.is_some() && monitor.name == self.monitor)
+            .or_else(|| {
+                let (x, y) = self.position;
+
+                monitors.iter().find(|monitor| {
+                    let (mx, my, mw, mh) = monitor.work_area;
+                    x >= mx && y >= my && x < mx + mw && y < my + mh
+                })
+            })
+    }
+
+    /// Writes the state to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), WindowStateError> {
+        fs::write(path, self.to_string()).map_err(WindowStateError::from)
+    }
+
+    /// Reads the state from a file written by [`WindowState::save`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, WindowStateError> {
+        fs::read_to_string(path)
+            .map_err(WindowStateError::from)?
+            .parse()
+    }
+}
+
+impl fmt::Display for WindowState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "position {} {}", self.position.0, self.position.1)?;
+        writeln!(f, "size {} {}", self.size.0, self.size.1)?;
+        writeln!(f, "maximized {}", self.maximized as u8)?;
+
+        if let Some(monitor) = &self.monitor {
+            writeln!(f, "monitor {}", monitor)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for WindowState {
+    type Err = WindowStateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut position = None;
+        let mut size = None;
+        let mut maximized = false;
+        let mut monitor = None;
+
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line
+                .trim()
+                .split_once(' ')
+                .ok_or(WindowStateError::Malformed)?;
+            let values: Vec<&str> = value.split_whitespace().collect();
+
+            match (key, values.as_slice()) {
+                ("position", [x, y]) => position = Some((x.parse()?, y.parse()?)),
+                ("size", [width, height]) => size = Some((width.parse()?, height.parse()?)),
+                ("maximized", [value]) => maximized = value.parse::<u8>()? != 0,
+                // Monitor names may contain spaces, so take the rest of the line.
+                ("monitor", _) => monitor = Some(value.trim().to_owned()),
+                _ => return Err(WindowStateError::Malformed),
+            }
+        }
+
+        Ok(Self {
+            position: position.ok_or(WindowStateError::Malformed)?,
+            size: size.ok_or(WindowStateError::Malformed)?,
+            maximized,
+            monitor,
+        })
+    }
+}
+
+/// Represents an error that occurred while saving or loading a [`WindowState`].
+#[derive(Debug)]
+pub enum WindowStateError {
+    /// An I/O error occurred.
+    Io(io::Error),
+    /// A line was not in the expected format or a required line was missing.
+    Malformed,
+    /// A number could not be parsed.
+    ParseInt(ParseIntError),
+}
+
+impl From<io::Error> for WindowStateError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ParseIntError> for WindowStateError {
+    fn from(error: ParseIntError) -> Self {
+        Self::ParseInt(error)
+    }
+}
+
+impl fmt::Display for WindowStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            Self::Malformed => write!(f, "malformed window state"),
+            Self::ParseInt(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for WindowStateError {}
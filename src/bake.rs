@@ -0,0 +1,313 @@
+//! The `--bake <pack>` mode: runs the compute jobs of the crate without a window and writes what
+//! they produce to an asset pack, so the renderer can load them instead of computing them at
+//! startup.
+//!
+//! Every baked texture is stored as `baked/<name>`, its mips and layers packed as
+//! [ImageDesc::mip_offset] lays them out, ready for [StagingUploader::upload_image]. Their
+//! formats and sizes are listed in `baked/manifest.txt`, one texture per line:
+//! `<name> <format> <width> <height> <mips> <layers>`. Assets already in the pack are kept.
+//!
+//! The jobs compile their shaders from `shaders/` at runtime, so baking needs the
+//! `shader-compiler` feature.
+
+use std::{error::Error, f32::consts::PI, fmt::Write, path::Path};
+
+use ash::{
+    vk::{self, make_api_version, Format, ImageLayout},
+    Entry,
+};
+
+use learnvulkan::{
+    renderer::{
+        assets::{AssetKind, Pack, PackWriter},
+        command_pool::CommandPool,
+        ibl::{EquirectangularImage, IblSettings, IblShaders, IblTextures, IBL_FORMAT},
+        image_desc::{ImageDesc, TexelBlock},
+        instance::Instance,
+        logical_device::LogicalDevice,
+        physical_device::PhysicalDevice,
+        procedural_texture::{ProceduralTextureDesc, ProceduralTextureGenerator},
+        sampler::SamplerCache,
+        shader_cache::ShaderCache,
+        shader_compiler::compile_file,
+        shader_include::{IncludeResolver, ShaderFs},
+        staging_ring::StagingRing,
+        staging_uploader::StagingUploader,
+    },
+    types::Pod,
+};
+
+/// The size of the procedural textures, baked with their full mip chain.
+const TEXTURE_SIZE: u32 = 512;
+
+/// The size of the sky panorama the IBL textures are computed from.
+const PANORAMA_SIZE: (u32, u32) = (1024, 512);
+
+/// The push constants of `shaders/noise.comp`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct NoiseParams {
+    low: [f32; 4],
+    high: [f32; 4],
+    frequency: f32,
+    octaves: u32,
+    persistence: f32,
+    seed: u32,
+}
+
+unsafe impl Pod for NoiseParams {}
+
+/// The push constants of `shaders/gradient.comp`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct GradientParams {
+    from: [f32; 4],
+    to: [f32; 4],
+    direction: [f32; 2],
+}
+
+unsafe impl Pod for GradientParams {}
+
+/// The push constants of `shaders/checkerboard.comp`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct CheckerboardParams {
+    color_a: [f32; 4],
+    color_b: [f32; 4],
+    squares: u32,
+}
+
+unsafe impl Pod for CheckerboardParams {}
+
+/// Bakes every job into the pack at `output`, creating it if needed.
+pub fn run(output: &Path) -> Result<(), Box<dyn Error>> {
+    let entry = unsafe { Entry::load()? };
+    let instance = Instance::new(
+        entry,
+        Vec::new(),
+        "Vulkan Tutorial Bake",
+        learnvulkan::cargo_version!().to_vulkan(),
+        "No Engine",
+        make_api_version(0, 1, 0, 0),
+    )?;
+
+    let physical_device = PhysicalDevice::compute_only(instance)?;
+    let logical_device = LogicalDevice::compute_only(physical_device.clone())?;
+
+    println!(
+        "baking on {}",
+        physical_device
+            .properties()
+            .device_name_as_c_str()
+            .unwrap_or_default()
+            .to_string_lossy()
+    );
+
+    let command_pool = CommandPool::new(logical_device.clone(), &physical_device)?;
+    let shader_cache = ShaderCache::new(logical_device.clone());
+    // Every texture baked with the same addressing shares a sampler.
+    let sampler_cache = SamplerCache::new(logical_device.clone());
+    let uploader = StagingUploader::new(command_pool.clone())?;
+    let mut resolver = IncludeResolver::new(ShaderFs::with_root("shaders"));
+
+    let mut baker = Baker {
+        uploader,
+        pack: PackWriter::new(),
+        manifest: String::new(),
+    };
+
+    // Keep what's already packed, only the baked assets are replaced.
+    if output.exists() {
+        let existing = Pack::open(output)?;
+
+        for entry in existing.entries() {
+            baker
+                .pack
+                .add(entry.name.clone(), entry.kind, &existing.read(&entry.name)?);
+        }
+    }
+
+    let generator = ProceduralTextureGenerator::new(
+        logical_device.clone(),
+        &command_pool,
+        shader_cache.clone(),
+        sampler_cache.clone(),
+    )?;
+    let desc = ProceduralTextureDesc::new(TEXTURE_SIZE, TEXTURE_SIZE, Format::R8G8B8A8_UNORM);
+
+    let textures = [
+        (
+            "noise",
+            "noise.comp",
+            NoiseParams {
+                low: [0.0, 0.0, 0.0, 1.0],
+                high: [1.0, 1.0, 1.0, 1.0],
+                frequency: 8.0,
+                octaves: 5,
+                persistence: 0.5,
+                seed: 0,
+            }
+            .bytes_of()
+            .to_vec(),
+        ),
+        (
+            "gradient",
+            "gradient.comp",
+            GradientParams {
+                from: [0.1, 0.2, 0.5, 1.0],
+                to: [0.9, 0.8, 0.6, 1.0],
+                direction: [0.0, 1.0],
+            }
+            .bytes_of()
+            .to_vec(),
+        ),
+        (
+            "checkerboard",
+            "checkerboard.comp",
+            CheckerboardParams {
+                color_a: [0.9, 0.9, 0.9, 1.0],
+                color_b: [0.1, 0.1, 0.1, 1.0],
+                squares: 8,
+            }
+            .bytes_of()
+            .to_vec(),
+        ),
+    ];
+
+    for (name, path, params) in textures {
+        let shader = compile_file(&mut resolver, path)?;
+        let texture = generator.generate(&shader, desc, &params)?;
+
+        baker.add(name, texture.image(), texture.image_desc(), desc.format)?;
+    }
+
+    let ibl_shaders = [
+        "equirect_to_cube.comp",
+        "irradiance.comp",
+        "prefilter.comp",
+        "brdf_lut.comp",
+    ]
+    .map(|path| compile_file(&mut resolver, path))
+    .into_iter()
+    .collect::<Result<Vec<_>, _>>()?;
+
+    let (width, height) = PANORAMA_SIZE;
+    let panorama = sky_panorama(width, height);
+    // The panorama is staged as half floats.
+    let mut staging =
+        StagingRing::new(logical_device.clone(), panorama.len() as vk::DeviceSize * 2)?;
+
+    let ibl = IblTextures::generate(
+        logical_device.clone(),
+        &command_pool,
+        &shader_cache,
+        &sampler_cache,
+        &mut staging,
+        EquirectangularImage {
+            width,
+            height,
+            pixels: &panorama,
+        },
+        IblShaders {
+            equirect_to_cube: &ibl_shaders[0],
+            irradiance: &ibl_shaders[1],
+            prefilter: &ibl_shaders[2],
+            brdf_lut: &ibl_shaders[3],
+        },
+        IblSettings::default(),
+    )?;
+
+    for (name, image, desc) in ibl.images() {
+        baker.add(&format!("ibl/{}", name), image, desc, IBL_FORMAT)?;
+    }
+
+    baker
+        .pack
+        .add("baked/manifest.txt", None, baker.manifest.as_bytes());
+    baker.pack.write_file(output)?;
+
+    println!(
+        "wrote {} assets to {}, sampled through {} samplers",
+        baker.pack.len(),
+        output.display(),
+        sampler_cache.len()
+    );
+
+    Ok(())
+}
+
+struct Baker {
+    uploader: StagingUploader,
+    pack: PackWriter,
+    manifest: String,
+}
+
+impl Baker {
+    /// Reads `image` back and adds it to the pack.
+    fn add(
+        &mut self,
+        name: &str,
+        image: vk::Image,
+        desc: ImageDesc,
+        format: Format,
+    ) -> Result<(), Box<dyn Error>> {
+        let block = TexelBlock::of(format).ok_or("no texel block size for the baked format")?;
+        let data = self.uploader.download_image(
+            image,
+            &desc,
+            block,
+            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        )?;
+
+        self.pack
+            .add(format!("baked/{}", name), Some(AssetKind::Texture), &data);
+
+        writeln!(
+            self.manifest,
+            "{} {:?} {} {} {} {}",
+            name, format, desc.width, desc.height, desc.mip_levels, desc.array_layers
+        )?;
+
+        println!("baked {} ({} bytes)", name, data.len());
+
+        Ok(())
+    }
+}
+
+/// A clear sky over dark ground with a bright sun, as RGBA rows from the top, since the crate
+/// decodes no HDR panoramas.
+fn sky_panorama(width: u32, height: u32) -> Vec<f32> {
+    let sun = [0.3f32, 0.6, 0.74];
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+
+    for y in 0..height {
+        let elevation = (0.5 - (y as f32 + 0.5) / height as f32) * PI;
+
+        for x in 0..width {
+            let azimuth = ((x as f32 + 0.5) / width as f32) * 2.0 * PI;
+            let direction = [
+                elevation.cos() * azimuth.cos(),
+                elevation.sin(),
+                elevation.cos() * azimuth.sin(),
+            ];
+
+            let color = if elevation < 0.0 {
+                [0.15, 0.13, 0.1]
+            } else {
+                let t = elevation.sin();
+                let sky = [0.9 - 0.6 * t, 0.95 - 0.45 * t, 1.0 - 0.1 * t];
+                let alignment: f32 = direction.iter().zip(sun).map(|(a, b)| a * b).sum();
+
+                if alignment > 0.9995 {
+                    [50.0, 45.0, 40.0]
+                } else {
+                    sky
+                }
+            };
+
+            pixels.extend_from_slice(&[color[0], color[1], color[2], 1.0]);
+        }
+    }
+
+    pixels
+}
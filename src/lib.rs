@@ -0,0 +1,93 @@
+//! The Vulkan wrappers of [api2] as a library, so other projects can depend on them instead of
+//! copying the files, and the [renderer] built on them.
+//!
+//! The modules below group the wrappers by topic and are the paths to depend on. [api2] keeps
+//! its flat layout for the [renderer], and may be reorganized with it.
+
+extern crate alloc;
+
+pub mod api2;
+pub mod renderer;
+pub mod types;
+
+/// Instances, validation layers and the extensions and profiles they're created with.
+pub mod instance {
+    pub use crate::api2::{
+        create_debug_messenger, enable_strict_validation, get_validation_layers,
+        is_strict_validation, load_validation_suppressions, print_warnings,
+        recent_validation_messages, suppress_validation_message, suppressed_validation_messages,
+        take_validation_report, unsuppress_validation_message, validation_report, DebugLayer,
+        Extensions, Instance, InstanceBuilder, InstanceBuilderError, InstanceError, Profile,
+        ProfileFeature, ProfileLimit, ProfileMismatch, PropertiesConversionError,
+        ValidationMessage, ValidationReport, ValidationSeverity, Version, VersionError,
+    };
+}
+
+/// Allocation callbacks for the driver's host memory.
+pub mod host_allocator {
+    pub use crate::api2::{
+        host_allocation_callbacks, host_allocator, install_host_allocator, CountingAllocator,
+        HostAllocationStats, HostAllocator, HostAllocatorError,
+    };
+}
+
+/// Logical devices, their queues and what they're required to support.
+pub mod device {
+    pub use crate::api2::{
+        check_device_extension_support, copy_memory_via_host, create_queue_create_infos,
+        has_stencil_component, is_fatal, supports_protected_memory, supports_protected_swapchain,
+        Device, DeviceBuilder, DeviceError, Poison, ProtectedFlags, Queue, QueueConfig,
+        QueueConfigError, QueueError, QueueFamilyIndices, QueuePlan, QueueRequest, QueueRole,
+        RenderFeature, Requirements, RequirementsError, RequirementsResolver, UnsupportedDevice,
+    };
+}
+
+/// GLFW windows, monitors and the window's lifecycle.
+pub mod window {
+    pub use crate::api2::{
+        CursorIcon, CursorImage, CursorImageError, GlfwEntry, GlfwError, GlfwWindow, Lifecycle,
+        LifecycleState, MonitorInfo, VideoMode, WindowState, WindowStateError,
+    };
+}
+
+/// Swapchains, their formats, extents and viewports.
+pub mod swapchain {
+    pub use crate::{
+        api2::{
+            align_up, choose_swapchain_extent, div_round_up, is_srgb_format, letterbox_viewport,
+            linear_to_srgb, round_to_multiple, srgb_to_linear, viewport_scissor, ExtentExt,
+            OffsetExt, Swapchain, SwapchainExtent, SwapchainSupportDetails, UNDEFINED_EXTENT,
+        },
+        types::{Extent, Offset},
+    };
+}
+
+/// Pacing the game loop and rendering from another thread.
+pub mod frame {
+    pub use crate::api2::{
+        FrameClock, PresentFeedback, RedrawPolicy, RedrawScheduler, RenderContext, RenderEvent,
+        RenderThread,
+    };
+}
+
+/// Named actions on top of raw input, and text entry.
+pub mod input {
+    pub use crate::api2::{
+        ActionMap, Binding, BindingError, BindingProfile, Composition, InputAxis, TextEvent,
+        TextInput,
+    };
+}
+
+/// Cameras and their controllers, transforms, colors, texture atlases and animation.
+pub mod scene {
+    pub use crate::api2::{
+        AnimationPlayer, Atlas, AtlasError, AtlasRegion, Camera, Camera2D, ClipSpaceY, Color,
+        ControllerButton, ControllerInput, ControllerKey, Easing, FlyController, GpuTransform,
+        Interpolate, Keyframe, LoopMode, OrbitController, Ray, RegionId, Track, Transform, UvRect,
+    };
+}
+
+/// Errors carrying what was being done when they happened.
+pub mod error {
+    pub use crate::api2::{ErrorCtx, ResultExt};
+}
@@ -1,280 +1,522 @@
-use std::rc::Rc;
+use std::{env, error::Error, path::Path, time::Duration};
 
 use ash::{
-    vk::{make_api_version, PipelineStageFlags, SubmitInfo},
+    vk::{self, SampleCountFlags},
     Entry,
 };
-use command_buffers::CommandBuffers;
-use command_pool::CommandPool;
-use debug_layer::DebugLayer;
-use framebuffers::Framebuffers;
-use graphics_pipeline::GraphicsPipeline;
-use image_views::ImageViews;
-use instance::Instance;
-use logical_device::LogicalDevice;
-use physical_device::PhysicalDevice;
-use render_pass::RenderPass;
-use surface::Surface;
-use swapchain::Swapchain;
-use sync_objects::SyncObjects;
-use utils::{check_validation_layer_support, print_available_extensions};
-use window::Window;
-
-const VALIDATION_LAYERS: [&str; 1] = ["VK_LAYER_KHRONOS_validation"];
-
-const ENABLE_VALIDATION_LAYERS: bool = cfg!(debug_assertions);
-
-const SHADER_VERT: &[u8; 1504] = include_bytes!("../shaders/vert.spv");
-const SHADER_FRAG: &[u8; 572] = include_bytes!("../shaders/frag.spv");
-const MAX_FRAMES_IN_FLIGHT: usize = 2;
-
-mod api2;
-mod command_buffers;
-mod command_pool;
-mod debug_layer;
-mod framebuffers;
-mod graphics_pipeline;
-mod image_views;
-mod instance;
-mod logical_device;
-mod physical_device;
-mod render_pass;
-mod shader_module;
-mod surface;
-mod swapchain;
-mod sync_objects;
-mod utils;
-mod window;
+#[cfg(feature = "hot-reload")]
+use learnvulkan::renderer::assets;
+use learnvulkan::{
+    api2,
+    renderer::{
+        command_trace::CommandTrace,
+        logical_device::Robustness,
+        renderer_config::{RendererConfig, RendererPreset},
+        swapchain::SwapchainSharing,
+        teardown_trace,
+        utils::print_available_extensions,
+        window::Window,
+        Renderer,
+    },
+};
+use startup::{StartupFailure, StartupReport};
+
+mod bake;
+mod startup;
+mod threaded;
 
 fn main() {
-    let mut app = HelloTriangleApplication::new();
-    app.run();
-}
+    // Counts the driver's host allocations, it has to be installed before any Vulkan call.
+    let host_allocator = env::var_os("LEARNVULKAN_COUNT_HOST_ALLOCATIONS").map(|_| {
+        let allocator: &'static api2::CountingAllocator =
+            Box::leak(Box::new(api2::CountingAllocator::new()));
+        api2::install_host_allocator(allocator).unwrap();
+        allocator
+    });
+
+    // Fails the run on any validation warning or error, for automated runs.
+    let strict_validation = env::var_os("LEARNVULKAN_STRICT_VALIDATION").is_some();
+
+    if strict_validation {
+        api2::enable_strict_validation();
+    }
 
-struct HelloTriangleApplication2 {
-    glfw_entry: api2::GlfwEntry,
-    window: api2::GlfwWindow<Rc<api2::Instance>>,
-    instance: Rc<api2::Instance>,
-    device: Rc<api2::Device<Rc<api2::Instance>>>,
-}
+    // Known-noisy validation messages, one ID per line.
+    if let Some(path) = env::var_os("LEARNVULKAN_SUPPRESS") {
+        match api2::load_validation_suppressions(&path) {
+            Ok(count) => println!("suppressing {} validation message IDs", count),
+            Err(e) => eprintln!("failed to load suppressions from {:?}: {}", path, e),
+        }
+    }
 
-impl HelloTriangleApplication2 {
-    pub fn new() -> Self {
-        let mut glfw_entry = api2::GlfwEntry::new().unwrap();
-
-        let instance_builder = api2::InstanceBuilder::default()
-            .application_name("Hello Triangle")
-            .engine_name("No Engine")
-            .enable_debug_layer(true)
-            .extensions(glfw_entry.required_extensions().unwrap());
-
-        println!("Available extensions:");
-        instance_builder
-            .available_extensions()
-            .unwrap()
-            .iter()
-            .for_each(|v| {
-                if let Ok(extension) = v.to_str() {
-                    println!("    {}", extension);
-                }
-            });
+    let args: Vec<String> = env::args().collect();
 
-        println!("Available layers:");
-        instance_builder
-            .available_layers()
-            .unwrap()
-            .iter()
-            .for_each(|v| {
-                if let Ok(layer) = v.to_str() {
-                    println!("    {}", layer);
-                }
-            });
+    if let Some(index) = args.iter().position(|arg| arg == "--bake") {
+        let Some(output) = args.get(index + 1) else {
+            eprintln!("usage: --bake <pack>");
+            std::process::exit(2);
+        };
 
-        let instance = Rc::new(instance_builder.build().unwrap());
-
-        let window = glfw_entry
-            .create_window(
-                instance.clone(),
-                "Hello Triangle",
-                800,
-                600,
-                glfw::WindowMode::Windowed,
-            )
-            .unwrap();
-
-        let device_extensions = api2::Extensions::default();
-        let device = Rc::new(
-            api2::Device::new(
-                instance.clone(),
-                &device_extensions,
-                &window.surface_instance,
-                window.surface,
-            )
-            .unwrap(),
-        );
+        if let Err(e) = bake::run(Path::new(output)) {
+            eprintln!("baking failed: {}", e);
+            std::process::exit(1);
+        }
 
-        Self {
-            glfw_entry,
-            window,
-            instance,
-            device,
+        return;
+    }
+
+    // Compares the present modes instead of running the tutorial.
+    let benchmark_present = match args.iter().position(|arg| arg == "--benchmark-present") {
+        Some(index) => match args.get(index + 1).and_then(|seconds| seconds.parse().ok()) {
+            Some(seconds) => Some(Duration::from_secs_f64(seconds)),
+            None => {
+                eprintln!("usage: --benchmark-present <seconds per mode>");
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+
+    // Where to start from, every other LEARNVULKAN_* variable still overrides it.
+    let mut config = match env::var("LEARNVULKAN_PRESET") {
+        Ok(name) => match name.parse::<RendererPreset>() {
+            Ok(preset) => {
+                println!("using the {} preset", preset);
+                preset.config()
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        },
+        Err(_) => RendererConfig::default(),
+    };
+
+    if env::var_os("LEARNVULKAN_ROBUST").is_some() {
+        config.robustness = Robustness {
+            robust_buffer_access: true,
+            null_descriptor: true,
+        };
+    }
+
+    if env::var_os("LEARNVULKAN_EXCLUSIVE_PRESENT").is_some() {
+        config.swapchain.sharing = SwapchainSharing::Exclusive;
+    }
+
+    if let Some(count) = env::var("LEARNVULKAN_MIN_IMAGES")
+        .ok()
+        .and_then(|count| count.parse().ok())
+    {
+        config.swapchain.min_image_count = Some(count);
+    }
+
+    // Clamped to what the device can do, so asking for 8x works everywhere.
+    if let Ok(samples) = env::var("LEARNVULKAN_MSAA") {
+        match samples.as_str() {
+            "2" => config.samples = SampleCountFlags::TYPE_2,
+            "4" => config.samples = SampleCountFlags::TYPE_4,
+            "8" => config.samples = SampleCountFlags::TYPE_8,
+            _ => {}
         }
     }
+
+    // Renders on its own thread, so dragging the window on Windows doesn't stall it.
+    if env::var_os("LEARNVULKAN_RENDER_THREAD").is_some() {
+        if let Err(e) = threaded::run(config) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    } else {
+        match HelloTriangleApplication::new(config) {
+            Ok(mut app) => match benchmark_present {
+                Some(duration) => app.benchmark_present(duration),
+                None => app.run(),
+            },
+            Err(report) => {
+                report.show();
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(allocator) = host_allocator {
+        let stats = allocator.stats();
+
+        println!(
+            "driver host allocations: {} allocations, {} reallocations, {} frees, peak {} bytes, {} bytes in {} allocations never freed",
+            stats.allocations,
+            stats.reallocations,
+            stats.frees,
+            stats.peak_bytes,
+            stats.live_bytes,
+            stats.live_allocations()
+        );
+    }
+
+    let report = api2::validation_report();
+
+    if strict_validation && !report.is_empty() {
+        eprintln!("{}", report);
+        std::process::exit(1);
+    }
+
+    for (id, count) in &report.suppressed {
+        println!("suppressed validation message {} {} times", id, count);
+    }
 }
 
+/// Called with every changed asset, see [HelloTriangleApplication::on_asset_reload].
+#[cfg(feature = "hot-reload")]
+type AssetCallback = Box<dyn FnMut(&assets::AssetEvent)>;
+
 struct HelloTriangleApplication {
     window: Window,
-    logical_device: LogicalDevice,
-    swapchain: Swapchain,
-    command_buffers: CommandBuffers,
-    sync_objects: SyncObjects,
-    current_frame: usize,
-
-    #[allow(dead_code)]
-    debug_layer: Option<DebugLayer>,
+    renderer: Renderer,
+    frame_clock: api2::FrameClock,
+    redraw: api2::RedrawScheduler,
+    actions: api2::ActionMap,
+    profiling: bool,
+    /// Frames drawn from this trace, in a loop, instead of the triangle.
+    replay: Option<CommandTrace>,
+
+    #[cfg(feature = "hot-reload")]
+    asset_watcher: Option<assets::Watcher>,
+    #[cfg(feature = "hot-reload")]
+    asset_callbacks: Vec<AssetCallback>,
 }
 
 impl HelloTriangleApplication {
-    pub fn new() -> Self {
-        let entry = unsafe { Entry::load().unwrap() };
-
-        if ENABLE_VALIDATION_LAYERS && !check_validation_layer_support(&entry).unwrap() {
-            panic!("validation layers requested, but not available!");
-        }
+    /// Sets up the renderer as `config` describes, or reports why this machine can't run it.
+    pub fn new(config: RendererConfig) -> Result<Self, StartupReport> {
+        let entry = unsafe { Entry::load()? };
 
         print_available_extensions(&entry);
 
         let window = Window::new("Vulkan", glfw::WindowMode::Windowed, 600, 800).unwrap();
-        let instance = Instance::new(
-            entry,
-            window.get_required_instance_extensions().unwrap(),
-            "Vulkan Tutorial",
-            make_api_version(0, 1, 0, 0),
-            "No Engine",
-            make_api_version(0, 1, 0, 0),
-        )
-        .unwrap();
-
-        let mut debug_layer = None;
-        if ENABLE_VALIDATION_LAYERS {
-            debug_layer = Some(DebugLayer::new(instance.clone()).unwrap());
-        }
 
-        let surface = Surface::new(instance.clone(), window.clone()).unwrap();
+        // GLFW looks for the loader on its own, so it can still miss it after Entry::load.
+        if window.get_required_instance_extensions().is_none() {
+            return Err(StartupReport::new(
+                StartupFailure::MissingLoader,
+                "GLFW found no Vulkan loader for window surfaces",
+            ));
+        }
 
-        let physical_device = PhysicalDevice::new(instance.clone(), &surface).unwrap();
+        let profiling = config.profiling;
+
+        let renderer = Renderer::builder()
+            .config(config)
+            .stereo(env::var_os("LEARNVULKAN_STEREO").is_some())
+            .build(&window)
+            .map_err(|e| {
+                StartupReport::from_error(e.as_ref()).unwrap_or_else(|| panic!("{}", e))
+            })?;
+
+        let properties = renderer.logical_device().physical_device().properties();
+        println!(
+            "using {} (Vulkan {}, driver {})",
+            properties
+                .device_name_as_c_str()
+                .unwrap_or_default()
+                .to_string_lossy(),
+            api2::Version::from_vulkan(properties.api_version),
+            api2::Version::from_driver(properties.vendor_id, properties.driver_version)
+        );
 
-        let logical_device = LogicalDevice::new(physical_device.clone()).unwrap();
+        if let Some(swapchain) = renderer.swapchain() {
+            println!(
+                "swapchain has {} images ({} requested), up to {} can be acquired at once",
+                swapchain.image_count(),
+                swapchain.requested_image_count(),
+                swapchain.max_acquired_images()
+            );
+        }
 
-        let swapchain = Swapchain::new(
-            physical_device.clone(),
-            logical_device.clone(),
-            surface.clone(),
-            &window,
-        )
-        .unwrap();
+        Ok(Self {
+            window,
+            renderer,
+            frame_clock: api2::FrameClock::default(),
+            redraw: api2::RedrawScheduler::default(),
+            actions: api2::ActionMap::new(default_bindings()),
+            profiling,
+            replay: None,
+            #[cfg(feature = "hot-reload")]
+            asset_watcher: None,
+            #[cfg(feature = "hot-reload")]
+            asset_callbacks: Vec::new(),
+        })
+    }
 
-        let image_views = ImageViews::new(&swapchain, logical_device.clone()).unwrap();
+    pub fn set_redraw_policy(&mut self, policy: api2::RedrawPolicy) {
+        self.redraw.set_policy(policy);
+    }
 
-        let render_pass = RenderPass::new(swapchain.clone()).unwrap();
+    /// Watches `path` for changed assets, see [HelloTriangleApplication::on_asset_reload].
+    #[cfg(feature = "hot-reload")]
+    pub fn watch_assets<P: AsRef<std::path::Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<(), assets::WatcherError> {
+        let watcher = match &mut self.asset_watcher {
+            Some(watcher) => watcher,
+            None => self
+                .asset_watcher
+                .insert(assets::Watcher::new(std::time::Duration::from_millis(100))?),
+        };
+
+        watcher.watch(path)
+    }
 
-        let graphics_pipeline = GraphicsPipeline::new(render_pass.clone()).unwrap();
+    /// Calls `callback` for every changed asset before the next frame is drawn.
+    #[cfg(feature = "hot-reload")]
+    pub fn on_asset_reload(&mut self, callback: impl FnMut(&assets::AssetEvent) + 'static) {
+        self.asset_callbacks.push(Box::new(callback));
+    }
 
-        let framebuffers = Framebuffers::new(render_pass.clone(), image_views.clone()).unwrap();
+    #[cfg(feature = "hot-reload")]
+    fn dispatch_asset_reloads(&mut self) {
+        let Some(watcher) = &mut self.asset_watcher else {
+            return;
+        };
 
-        let command_pool = CommandPool::new(logical_device.clone(), &physical_device).unwrap();
+        for event in watcher.poll() {
+            for callback in &mut self.asset_callbacks {
+                callback(&event);
+            }
+        }
+    }
 
-        let command_buffers = CommandBuffers::new(
-            command_pool.clone(),
-            framebuffers.clone(),
-            graphics_pipeline.clone(),
-        )
-        .unwrap();
+    pub fn draw_frame(&mut self) {
+        #[cfg(feature = "hot-reload")]
+        self.dispatch_asset_reloads();
+
+        let result = match &self.replay {
+            Some(trace) if !trace.frames.is_empty() => {
+                let frame = self.frame_clock.frame_index as usize % trace.frames.len();
+                self.renderer.replay_frame(&trace.frames[frame])
+            }
+            _ => self.renderer.draw_frame(),
+        };
+
+        if let Err(e) = result {
+            self.write_crash_dump(e.as_ref());
+            panic!("failed to draw a frame: {}", e);
+        }
+    }
 
-        let sync_objects = SyncObjects::new(logical_device.clone(), MAX_FRAMES_IN_FLIGHT).unwrap();
+    /// Renders with every present mode the surface supports for `duration` each, then prints how
+    /// they compare.
+    pub fn benchmark_present(&mut self, duration: Duration) {
+        let report = match self.renderer.benchmark_present_modes(duration) {
+            Ok(report) => report,
+            Err(e) => {
+                self.write_crash_dump(e.as_ref());
+                panic!("failed to benchmark the present modes: {}", e);
+            }
+        };
+
+        print!("{}", report);
+
+        for (label, result) in [
+            ("fastest", report.fastest()),
+            ("lowest latency", report.lowest_latency()),
+            ("steadiest", report.steadiest()),
+        ] {
+            if let Some(result) = result {
+                println!("{}: {:?}", label, result.present_mode);
+            }
+        }
+    }
 
-        Self {
-            current_frame: 0,
-            window,
-            logical_device,
-            swapchain,
-            command_buffers,
-            sync_objects,
-            debug_layer,
+    /// Writes a crash dump to `LEARNVULKAN_CRASH_DUMP`, or `crash-dump.txt`, when `error` is a
+    /// lost device and crash diagnostics are enabled, before giving up on it.
+    fn write_crash_dump(&self, error: &(dyn Error + 'static)) {
+        let (Some(&cause @ vk::Result::ERROR_DEVICE_LOST), Some(crash_diagnostics)) = (
+            error.downcast_ref::<vk::Result>(),
+            self.renderer.crash_diagnostics(),
+        ) else {
+            return;
+        };
+
+        let path = env::var_os("LEARNVULKAN_CRASH_DUMP").unwrap_or_else(|| "crash-dump.txt".into());
+
+        match crash_diagnostics.dump(cause).save(&path) {
+            Ok(()) => eprintln!("device lost, wrote a crash dump to {:?}", path),
+            Err(e) => eprintln!(
+                "device lost, failed to write a crash dump to {:?}: {}",
+                path, e
+            ),
         }
     }
 
-    pub fn draw_frame(&mut self) {
-        self.sync_objects
-            .wait_in_flight_fence(self.current_frame)
-            .unwrap();
-
-        self.sync_objects
-            .reset_in_flight_fence(self.current_frame)
-            .unwrap();
-
-        let (image_index, _) = self
-            .swapchain
-            .acquire_next_image(
-                u64::MAX,
-                Some(
-                    *self
-                        .sync_objects
-                        .image_available_semaphore(self.current_frame),
-                ),
-                None,
-            )
-            .unwrap();
-
-        self.command_buffers.reset().unwrap();
-
-        self.command_buffers
-            .record(0, image_index.try_into().unwrap(), 0, 0, 0)
-            .unwrap();
-
-        let wait_semaphores = [*self
-            .sync_objects
-            .image_available_semaphore(self.current_frame)];
-        let signal_semaphores = [*self
-            .sync_objects
-            .render_finished_semaphore(self.current_frame)];
-
-        let wait_stages = [PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
-
-        let submit_info = SubmitInfo::default()
-            .wait_semaphores(&wait_semaphores)
-            .wait_dst_stage_mask(&wait_stages)
-            .command_buffers(self.command_buffers.command_buffers())
-            .signal_semaphores(&signal_semaphores);
-
-        let submit_infos = [submit_info];
-
-        unsafe {
-            self.logical_device
-                .device()
-                .queue_submit(
-                    *self.logical_device.queue(),
-                    &submit_infos,
-                    *self.sync_objects.in_flight_fence(self.current_frame),
-                )
-                .unwrap();
+    pub fn run(&mut self) {
+        let trace_path = env::var_os("LEARNVULKAN_TRACE");
+
+        if trace_path.is_some() {
+            self.renderer.start_trace();
         }
 
-        let image_indices = [image_index.try_into().unwrap()];
+        if let Some(path) = env::var_os("LEARNVULKAN_REPLAY") {
+            match CommandTrace::load(&path) {
+                Ok(trace) => self.replay = Some(trace),
+                Err(e) => eprintln!("failed to load a command trace from {:?}: {}", path, e),
+            }
+        }
 
-        self.swapchain
-            .queue_present(&signal_semaphores, &image_indices)
-            .unwrap();
+        let submit_trace_path = env::var_os("LEARNVULKAN_SUBMIT_TRACE");
 
-        self.current_frame = (self.current_frame + 1) % MAX_FRAMES_IN_FLIGHT;
-    }
+        if submit_trace_path.is_some() {
+            self.renderer
+                .enable_submit_trace()
+                .unwrap_or_else(|e| panic!("{}", e));
+        }
+
+        if env::var_os("LEARNVULKAN_TEARDOWN_TRACE").is_some() {
+            teardown_trace::enable();
+        }
+
+        if env::var_os("LEARNVULKAN_WIREFRAME").is_some() && !self.renderer.set_wireframe(true) {
+            println!("wireframe requested, but fillModeNonSolid is not supported or the preset has no wireframe pipeline");
+        }
+
+        #[cfg(feature = "hot-reload")]
+        if let Some(path) = env::var_os("LEARNVULKAN_HOT_RELOAD") {
+            self.watch_assets(path).unwrap();
+            self.on_asset_reload(|event| {
+                println!("{:?} changed: {}", event.kind, event.path.display())
+            });
+        }
+
+        if let Some(count) = env::var("LEARNVULKAN_FRAMES_IN_FLIGHT")
+            .ok()
+            .and_then(|count| count.parse().ok())
+        {
+            self.renderer.set_frames_in_flight(count).unwrap();
+        }
+
+        if let Some(path) = env::var_os("LEARNVULKAN_BINDINGS") {
+            match api2::BindingProfile::load(&path) {
+                Ok(profile) => self.actions.set_profile(profile),
+                Err(e) => eprintln!("failed to load bindings from {:?}: {}", path, e),
+            }
+        }
+
+        if env::var_os("LEARNVULKAN_ON_DEMAND").is_some() {
+            self.set_redraw_policy(api2::RedrawPolicy::OnDemand);
+        }
 
-    pub fn run(&mut self) {
         while !self.window.should_close() {
-            self.window.poll_events();
+            if self.redraw.should_wait() || self.renderer.needs_wait() {
+                self.window.wait_events();
+            } else {
+                self.window.poll_events();
+            }
+
+            self.actions.begin_frame();
+
+            for event in self.window.flush_events() {
+                self.redraw.handle_glfw_event(&event);
+                self.actions.handle_glfw_event(&event);
+                self.renderer.handle_glfw_event(&event);
+            }
+
+            if self.actions.was_pressed("quit") {
+                self.window.set_should_close(true);
+            }
+
+            if self.actions.was_pressed("toggle_on_demand") {
+                self.set_redraw_policy(match self.redraw.policy {
+                    api2::RedrawPolicy::Continuous => api2::RedrawPolicy::OnDemand,
+                    api2::RedrawPolicy::OnDemand => api2::RedrawPolicy::Continuous,
+                });
+            }
+
+            if self.actions.was_pressed("toggle_pause") {
+                if self.renderer.lifecycle().is_suspended() {
+                    self.renderer.resume();
+                } else {
+                    self.renderer.suspend();
+                }
+            }
+
+            if !self.redraw.take_redraw() {
+                continue;
+            }
+
+            self.frame_clock.tick();
             self.draw_frame();
         }
 
-        self.logical_device.wait_idle().unwrap();
+        println!(
+            "rendered {} frames in {:.2}s ({:.1} FPS over the last frames)",
+            self.frame_clock.frame_index,
+            self.frame_clock.total_seconds(),
+            self.frame_clock.fps()
+        );
+
+        let acquire_stats = self.renderer.acquire_stats();
+
+        println!(
+            "{} frames in flight over {} swapchain images: {:.2}ms per frame waiting for the GPU, {} acquire timeouts, {} skipped frames, {} suboptimal acquires, {} swapchain recreations requested",
+            self.renderer.frames_in_flight(),
+            self.renderer
+                .swapchain()
+                .map_or(0, |swapchain| swapchain.image_count()),
+            self.renderer.fence_wait().as_secs_f64() * 1000.0
+                / self.frame_clock.frame_index.max(1) as f64,
+            acquire_stats.timeouts,
+            acquire_stats.skipped_frames,
+            acquire_stats.suboptimal,
+            acquire_stats.recreate_requests
+        );
+
+        self.renderer.wait_idle().unwrap();
+
+        if self.profiling {
+            let logical_device = self.renderer.logical_device();
+            let pipeline_stats = logical_device.pipeline_stats();
+            let pipelines = pipeline_stats.graphics;
+            let resources = logical_device.resource_stats();
+
+            println!(
+                "created {} graphics pipelines in {:.2}ms, cache hit rate {}",
+                pipelines.pipelines,
+                pipelines.total_time.as_secs_f64() * 1000.0,
+                pipelines
+                    .hit_rate()
+                    .map_or("unknown".to_owned(), |rate| format!("{:.0}%", rate * 100.0))
+            );
+
+            if pipeline_stats.library.pipelines > 0 {
+                println!(
+                    "linked them from {} pipeline libraries created in {:.2}ms",
+                    pipeline_stats.library.pipelines,
+                    pipeline_stats.library.total_time.as_secs_f64() * 1000.0
+                );
+            }
+            println!(
+                "{} buffers and {} images live, {} bytes",
+                resources.buffers.live,
+                resources.images.live,
+                resources.total_bytes()
+            );
+        }
+
+        if let (Some(path), Some(trace)) = (submit_trace_path, self.renderer.take_submit_trace()) {
+            trace.save(path).unwrap();
+        }
+
+        if let (Some(path), Some(trace)) = (trace_path, self.renderer.take_trace()) {
+            trace.save(path).unwrap();
+        }
     }
 }
+
+/// The bindings used unless `LEARNVULKAN_BINDINGS` points at a saved profile.
+fn default_bindings() -> api2::BindingProfile {
+    let mut profile = api2::BindingProfile::new();
+    profile.bind("quit", api2::Binding::Key(glfw::Key::Escape));
+    profile.bind("toggle_on_demand", api2::Binding::Key(glfw::Key::F2));
+    profile.bind("toggle_pause", api2::Binding::Key(glfw::Key::F3));
+    profile
+}
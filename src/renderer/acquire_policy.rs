@@ -0,0 +1,145 @@
+use std::{error, fmt, time::Duration};
+
+use ash::vk::{self, Semaphore};
+
+use crate::renderer::swapchain::Swapchain;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TimeoutBehavior {
+    SkipFrame,
+    Block,
+    Error,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AcquirePolicy {
+    pub timeout: Duration,
+    pub on_timeout: TimeoutBehavior,
+    pub suboptimal_limit: u32,
+}
+
+impl Default for AcquirePolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(100),
+            on_timeout: TimeoutBehavior::SkipFrame,
+            suboptimal_limit: 3,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AcquireOutcome {
+    Image { index: u32, needs_recreate: bool },
+    Skipped,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AcquireStats {
+    pub acquired: u64,
+    pub timeouts: u64,
+    pub skipped_frames: u64,
+    pub suboptimal: u64,
+    pub recreate_requests: u64,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FrameAcquirer {
+    policy: AcquirePolicy,
+    stats: AcquireStats,
+    consecutive_suboptimal: u32,
+}
+
+impl FrameAcquirer {
+    pub fn new(policy: AcquirePolicy) -> Self {
+        Self {
+            policy,
+            stats: AcquireStats::default(),
+            consecutive_suboptimal: 0,
+        }
+    }
+
+    pub fn policy(&self) -> &AcquirePolicy {
+        &self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: AcquirePolicy) {
+        self.policy = policy;
+    }
+
+    pub fn stats(&self) -> AcquireStats {
+        self.stats
+    }
+
+    pub fn acquire(
+        &mut self,
+        swapchain: &Swapchain,
+        semaphore: Semaphore,
+    ) -> Result<AcquireOutcome, AcquireError> {
+        let timeout = self.policy.timeout.as_nanos().min(u64::MAX as u128) as u64;
+
+        let result = match swapchain.acquire_next_image(timeout, Some(semaphore), None) {
+            Err(vk::Result::TIMEOUT) | Err(vk::Result::NOT_READY) => {
+                self.stats.timeouts += 1;
+
+                match self.policy.on_timeout {
+                    TimeoutBehavior::SkipFrame => {
+                        self.stats.skipped_frames += 1;
+                        return Ok(AcquireOutcome::Skipped);
+                    }
+                    TimeoutBehavior::Block => {
+                        swapchain.acquire_next_image(u64::MAX, Some(semaphore), None)
+                    }
+                    TimeoutBehavior::Error => return Err(AcquireError::Timeout),
+                }
+            }
+            result => result,
+        };
+
+        let (index, suboptimal) = result.map_err(AcquireError::from)?;
+
+        self.stats.acquired += 1;
+
+        if suboptimal {
+            self.stats.suboptimal += 1;
+            self.consecutive_suboptimal += 1;
+        } else {
+            self.consecutive_suboptimal = 0;
+        }
+
+        let needs_recreate = self.consecutive_suboptimal > self.policy.suboptimal_limit;
+
+        if needs_recreate {
+            self.stats.recreate_requests += 1;
+            self.consecutive_suboptimal = 0;
+        }
+
+        Ok(AcquireOutcome::Image {
+            index,
+            needs_recreate,
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AcquireError {
+    Vulkan(vk::Result),
+    Timeout,
+}
+
+impl From<vk::Result> for AcquireError {
+    fn from(value: vk::Result) -> Self {
+        Self::Vulkan(value)
+    }
+}
+
+impl fmt::Display for AcquireError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Vulkan(e) => e.fmt(f),
+            Self::Timeout => write!(f, "timed out acquiring the next swapchain image"),
+        }
+    }
+}
+
+impl error::Error for AcquireError {}
@@ -0,0 +1,19 @@
+/// What an asset file is loaded as.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AssetKind {
+    Shader,
+    Texture,
+    Mesh,
+}
+
+impl AssetKind {
+    /// The kind of files with `extension` by default, compared case-insensitively.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "vert" | "frag" | "comp" | "glsl" | "wgsl" | "spv" => Some(Self::Shader),
+            "png" | "jpg" | "jpeg" | "hdr" | "ktx2" => Some(Self::Texture),
+            "obj" | "gltf" | "glb" => Some(Self::Mesh),
+            _ => None,
+        }
+    }
+}
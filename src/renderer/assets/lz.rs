@@ -0,0 +1,231 @@
+//! A small LZ77 byte compressor for pack blobs, fast to decode rather than small.
+//!
+//! A block is a series of sequences, each a LEB128 literal count, the literals, then a LEB128
+//! match length minus [MIN_MATCH] and a little-endian `u16` distance back into the output. The
+//! last sequence stops after its literals.
+
+const MIN_MATCH: usize = 4;
+const MAX_DISTANCE: usize = u16::MAX as usize;
+const HASH_BITS: u32 = 14;
+
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() / 2 + 16);
+    // The position of the last 4 bytes with each hash, plus one so 0 means none.
+    let mut table = vec![0u32; 1 << HASH_BITS];
+    let mut literal_start = 0;
+    let mut position = 0;
+
+    while position + MIN_MATCH <= input.len() {
+        let key = u32::from_le_bytes([
+            input[position],
+            input[position + 1],
+            input[position + 2],
+            input[position + 3],
+        ]);
+        let hash = (key.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize;
+        let candidate = table[hash] as usize;
+        table[hash] = position as u32 + 1;
+
+        if candidate != 0 {
+            let candidate = candidate - 1;
+            let distance = position - candidate;
+
+            if distance <= MAX_DISTANCE
+                && input[candidate..candidate + MIN_MATCH] == input[position..position + MIN_MATCH]
+            {
+                let mut length = MIN_MATCH;
+
+                while position + length < input.len()
+                    && input[candidate + length] == input[position + length]
+                {
+                    length += 1;
+                }
+
+                write_varint(&mut output, (position - literal_start) as u64);
+                output.extend_from_slice(&input[literal_start..position]);
+                write_varint(&mut output, (length - MIN_MATCH) as u64);
+                output.extend_from_slice(&(distance as u16).to_le_bytes());
+
+                position += length;
+                literal_start = position;
+                continue;
+            }
+        }
+
+        position += 1;
+    }
+
+    write_varint(&mut output, (input.len() - literal_start) as u64);
+    output.extend_from_slice(&input[literal_start..]);
+
+    output
+}
+
+/// Decompresses `input` into `output`, which has to be exactly the size of the original data.
+/// Returns `None` if `input` is corrupt or doesn't fill `output`.
+pub fn decompress_into(input: &[u8], output: &mut [u8]) -> Option<()> {
+    let mut read = 0usize;
+    let mut written = 0usize;
+
+    loop {
+        let literals = read_varint(input, &mut read)? as usize;
+        let literal_end = read.checked_add(literals)?;
+        let output_end = written.checked_add(literals)?;
+
+        output
+            .get_mut(written..output_end)?
+            .copy_from_slice(input.get(read..literal_end)?);
+        read = literal_end;
+        written = output_end;
+
+        if read == input.len() {
+            return (written == output.len()).then_some(());
+        }
+
+        let length = (read_varint(input, &mut read)? as usize).checked_add(MIN_MATCH)?;
+        let distance = u16::from_le_bytes([*input.get(read)?, *input.get(read + 1)?]) as usize;
+        read += 2;
+
+        if distance == 0 || distance > written || written.checked_add(length)? > output.len() {
+            return None;
+        }
+
+        // Byte by byte, since a match may overlap the bytes it produces.
+        for i in written..written + length {
+            output[i] = output[i - distance];
+        }
+
+        written += length;
+    }
+}
+
+fn write_varint(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+
+    output.push(value as u8);
+}
+
+fn read_varint(input: &[u8], read: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+
+    for shift in (0..64).step_by(7) {
+        let byte = *input.get(*read)?;
+        *read += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(input: &[u8]) -> Vec<u8> {
+        let compressed = compress(input);
+        let mut output = vec![0; input.len()];
+
+        decompress_into(&compressed, &mut output).expect("decompressing");
+        assert_eq!(output, input);
+
+        compressed
+    }
+
+    /// Bytes from a small xorshift generator, which don't compress.
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491u32;
+
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty_and_tiny_inputs() {
+        assert_eq!(round_trip(&[]), [0]);
+        assert_eq!(round_trip(&[7]), [1, 7]);
+        round_trip(&[1, 2, 3]);
+        round_trip(&[9; MIN_MATCH]);
+    }
+
+    #[test]
+    fn repeated_bytes_compress() {
+        let input = vec![0xab; 1000];
+        let compressed = round_trip(&input);
+
+        assert!(compressed.len() < 16, "{} bytes", compressed.len());
+    }
+
+    #[test]
+    fn text_and_noise_round_trip() {
+        let text = "the quick brown fox jumps over the lazy dog, ".repeat(97);
+        assert!(round_trip(text.as_bytes()).len() < text.len() / 4);
+
+        let noise = noise(4099);
+        assert!(round_trip(&noise).len() >= noise.len());
+    }
+
+    #[test]
+    fn matches_as_far_back_as_a_distance_allows() {
+        let block = noise(MAX_DISTANCE - 1);
+        let mut input = block.clone();
+        input.push(0);
+        input.extend_from_slice(&block);
+
+        assert!(round_trip(&input).len() < block.len() + 100);
+
+        // Too far back for a match, so both copies are stored as literals.
+        let mut input = block.clone();
+        input.extend_from_slice(&[0; 2]);
+        input.extend_from_slice(&block);
+
+        assert!(round_trip(&input).len() > block.len() * 2);
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        for value in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value);
+
+            let mut read = 0;
+            assert_eq!(read_varint(&bytes, &mut read), Some(value));
+            assert_eq!(read, bytes.len());
+        }
+    }
+
+    #[test]
+    fn corrupt_input_is_rejected() {
+        let input = b"abcdabcdabcdabcd".repeat(4);
+        let compressed = compress(&input);
+
+        // Truncated, or decompressed into the wrong size.
+        assert!(decompress_into(
+            &compressed[..compressed.len() - 1],
+            &mut vec![0; input.len()]
+        )
+        .is_none());
+        assert!(decompress_into(&compressed, &mut vec![0; input.len() - 1]).is_none());
+        assert!(decompress_into(&compressed, &mut vec![0; input.len() + 1]).is_none());
+
+        // A match reaching before the start of the output.
+        assert!(decompress_into(&[0, 0, 1, 0, 0], &mut [0; 4]).is_none());
+        assert!(decompress_into(&[1, 5, 0, 0, 0, 0], &mut [0; 5]).is_none());
+
+        // A varint that never ends.
+        assert!(decompress_into(&[0xff; 11], &mut []).is_none());
+        assert!(decompress_into(&[], &mut []).is_none());
+    }
+}
@@ -0,0 +1,12 @@
+//! Runtime asset handling.
+
+pub use kind::*;
+pub use pack::*;
+#[cfg(feature = "hot-reload")]
+pub use watcher::*;
+
+mod kind;
+mod lz;
+mod pack;
+#[cfg(feature = "hot-reload")]
+mod watcher;
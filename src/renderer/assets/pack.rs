@@ -0,0 +1,573 @@
+//! Asset packs: many assets in one file, each compressed on its own so it can be read without
+//! touching the others.
+//!
+//! A pack starts with a fixed-size header, followed by the blobs and then the table of
+//! contents the header points at. Everything is little-endian.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    error, fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use ash::vk;
+
+use super::{lz, AssetKind};
+use crate::renderer::staging_ring::{StagingError, StagingRing};
+
+const MAGIC: [u8; 4] = *b"LVPK";
+const VERSION: u32 = 1;
+
+/// The magic, the version, the entry count and the offset of the table of contents.
+const HEADER_SIZE: u64 = 4 + 4 + 4 + 8;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Compression {
+    None,
+    Lz,
+}
+
+/// An asset in a pack.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PackEntry {
+    /// The path the asset was packed from, with `/` separators.
+    pub name: String,
+    pub kind: Option<AssetKind>,
+    pub compression: Compression,
+    offset: u64,
+    /// The size of the blob in the pack.
+    pub stored_size: u64,
+    /// The size of the asset once decompressed.
+    pub size: u64,
+    /// FNV-1a of the decompressed asset.
+    checksum: u32,
+}
+
+/// Collects assets and writes them out as a pack.
+#[derive(Debug, Default)]
+pub struct PackWriter {
+    entries: Vec<(PackEntry, Vec<u8>)>,
+}
+
+impl PackWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `data` as `name`, compressed unless that doesn't make it smaller. Replaces an
+    /// earlier asset with the same name.
+    pub fn add(&mut self, name: impl Into<String>, kind: Option<AssetKind>, data: &[u8]) {
+        let name = name.into();
+        let compressed = lz::compress(data);
+
+        let (compression, blob) = if compressed.len() < data.len() {
+            (Compression::Lz, compressed)
+        } else {
+            (Compression::None, data.to_vec())
+        };
+
+        let entry = PackEntry {
+            name,
+            kind,
+            compression,
+            offset: 0,
+            stored_size: blob.len() as u64,
+            size: data.len() as u64,
+            checksum: fnv1a(data),
+        };
+
+        self.entries
+            .retain(|(existing, _)| existing.name != entry.name);
+        self.entries.push((entry, blob));
+    }
+
+    /// Adds every file below `directory`, named by its path relative to it, with the kind its
+    /// extension maps to.
+    pub fn add_directory(&mut self, directory: impl AsRef<Path>) -> Result<(), PackError> {
+        let root = directory.as_ref();
+        let mut pending = vec![root.to_path_buf()];
+
+        while let Some(directory) = pending.pop() {
+            let mut children = fs::read_dir(&directory)?.collect::<Result<Vec<_>, _>>()?;
+            children.sort_by_key(|child| child.path());
+
+            for child in children {
+                let path = child.path();
+
+                if child.file_type()?.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+
+                let relative = path.strip_prefix(root).unwrap_or(&path);
+                let name = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let kind = path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .and_then(AssetKind::from_extension);
+
+                self.add(name, kind, &fs::read(&path)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn write_to<W: Write + Seek>(&self, mut writer: W) -> Result<(), PackError> {
+        // The header is written again once the table's offset is known.
+        writer.write_all(&[0; HEADER_SIZE as usize])?;
+
+        let mut offset = HEADER_SIZE;
+        let mut table = Vec::new();
+
+        for (entry, blob) in &self.entries {
+            writer.write_all(blob)?;
+
+            let name = entry.name.as_bytes();
+            table.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            table.extend_from_slice(name);
+            table.push(kind_to_byte(entry.kind));
+            table.push(match entry.compression {
+                Compression::None => 0,
+                Compression::Lz => 1,
+            });
+            table.extend_from_slice(&offset.to_le_bytes());
+            table.extend_from_slice(&entry.stored_size.to_le_bytes());
+            table.extend_from_slice(&entry.size.to_le_bytes());
+            table.extend_from_slice(&entry.checksum.to_le_bytes());
+
+            offset += blob.len() as u64;
+        }
+
+        writer.write_all(&table)?;
+        writer.seek(SeekFrom::Start(0))?;
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        writer.write_all(&offset.to_le_bytes())?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    pub fn write_file(&self, path: impl AsRef<Path>) -> Result<(), PackError> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+}
+
+/// Packs every file below `directory` into the pack at `output`, the tool side of [Pack].
+pub fn write_pack(
+    directory: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+) -> Result<usize, PackError> {
+    let mut writer = PackWriter::new();
+    writer.add_directory(directory)?;
+    writer.write_file(output)?;
+
+    Ok(writer.len())
+}
+
+/// A pack opened for reading. Only the table of contents is read up front, assets are read from
+/// the file when asked for.
+pub struct Pack {
+    file: RefCell<File>,
+    entries: Vec<PackEntry>,
+    index: HashMap<String, usize>,
+}
+
+impl Pack {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, PackError> {
+        let mut file = File::open(path)?;
+
+        let mut header = [0; HEADER_SIZE as usize];
+        file.read_exact(&mut header)?;
+
+        if header[0..4] != MAGIC {
+            return Err(PackError::BadMagic);
+        }
+
+        let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+
+        if version != VERSION {
+            return Err(PackError::UnsupportedVersion(version));
+        }
+
+        let entry_count = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        let table_offset = u64::from_le_bytes(header[12..20].try_into().unwrap());
+
+        let mut table = Vec::new();
+        file.seek(SeekFrom::Start(table_offset))?;
+        file.read_to_end(&mut table)?;
+
+        let mut reader = TableReader {
+            table: &table,
+            position: 0,
+        };
+        let entries = (0..entry_count)
+            .map(|_| reader.entry())
+            .collect::<Option<Vec<_>>>()
+            .ok_or(PackError::Corrupt("truncated table of contents"))?;
+
+        if entries.iter().any(|entry| {
+            entry
+                .offset
+                .checked_add(entry.stored_size)
+                .map_or(true, |end| end > table_offset)
+        }) {
+            return Err(PackError::Corrupt("blob overlaps the table of contents"));
+        }
+
+        let index = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.name.clone(), i))
+            .collect();
+
+        Ok(Self {
+            file: RefCell::new(file),
+            entries,
+            index,
+        })
+    }
+
+    pub fn entries(&self) -> &[PackEntry] {
+        &self.entries
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&PackEntry> {
+        self.index.get(name).map(|&i| &self.entries[i])
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.index.contains_key(name)
+    }
+
+    /// Reads and decompresses asset `name`.
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, PackError> {
+        let entry = self.find(name)?;
+        let mut data = vec![0; entry.size as usize];
+        self.read_entry_into(entry, &mut data)?;
+
+        Ok(data)
+    }
+
+    /// Decompresses asset `name` straight into `staging`, aligned to `alignment`, and returns its
+    /// offset in [StagingRing::buffer] and its size, ready to record a copy from.
+    pub fn stage(
+        &self,
+        name: &str,
+        staging: &mut StagingRing,
+        alignment: vk::DeviceSize,
+    ) -> Result<(vk::DeviceSize, vk::DeviceSize), PackError> {
+        let entry = self.find(name)?;
+        let allocation = staging.allocate(entry.size, alignment)?;
+        self.read_entry_into(entry, allocation.data)?;
+
+        Ok((allocation.offset, entry.size))
+    }
+
+    fn find(&self, name: &str) -> Result<&PackEntry, PackError> {
+        self.entry(name)
+            .ok_or_else(|| PackError::NotFound(name.to_owned()))
+    }
+
+    /// Decompresses `entry` into `output`, which has to be its size.
+    fn read_entry_into(&self, entry: &PackEntry, output: &mut [u8]) -> Result<(), PackError> {
+        let mut file = self.file.borrow_mut();
+        file.seek(SeekFrom::Start(entry.offset))?;
+
+        match entry.compression {
+            Compression::None => file.read_exact(output)?,
+            Compression::Lz => {
+                let mut blob = vec![0; entry.stored_size as usize];
+                file.read_exact(&mut blob)?;
+
+                lz::decompress_into(&blob, output)
+                    .ok_or(PackError::Corrupt("invalid compressed blob"))?;
+            }
+        }
+
+        if fnv1a(output) != entry.checksum {
+            return Err(PackError::ChecksumMismatch(entry.name.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+struct TableReader<'a> {
+    table: &'a [u8],
+    position: usize,
+}
+
+impl TableReader<'_> {
+    fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.table.get(self.position..self.position + N)?;
+        self.position += N;
+        bytes.try_into().ok()
+    }
+
+    fn entry(&mut self) -> Option<PackEntry> {
+        let name_len = u16::from_le_bytes(self.bytes()?) as usize;
+        let name = self.table.get(self.position..self.position + name_len)?;
+        let name = String::from_utf8(name.to_vec()).ok()?;
+        self.position += name_len;
+
+        let [kind] = self.bytes()?;
+        let [compression] = self.bytes()?;
+
+        Some(PackEntry {
+            name,
+            kind: kind_from_byte(kind)?,
+            compression: match compression {
+                0 => Compression::None,
+                1 => Compression::Lz,
+                _ => return None,
+            },
+            offset: u64::from_le_bytes(self.bytes()?),
+            stored_size: u64::from_le_bytes(self.bytes()?),
+            size: u64::from_le_bytes(self.bytes()?),
+            checksum: u32::from_le_bytes(self.bytes()?),
+        })
+    }
+}
+
+fn kind_to_byte(kind: Option<AssetKind>) -> u8 {
+    match kind {
+        None => 0,
+        Some(AssetKind::Shader) => 1,
+        Some(AssetKind::Texture) => 2,
+        Some(AssetKind::Mesh) => 3,
+    }
+}
+
+fn kind_from_byte(byte: u8) -> Option<Option<AssetKind>> {
+    match byte {
+        0 => Some(None),
+        1 => Some(Some(AssetKind::Shader)),
+        2 => Some(Some(AssetKind::Texture)),
+        3 => Some(Some(AssetKind::Mesh)),
+        _ => None,
+    }
+}
+
+fn fnv1a(data: &[u8]) -> u32 {
+    data.iter().fold(0x811c_9dc5, |hash, &byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+#[derive(Debug)]
+pub enum PackError {
+    Io(io::Error),
+    Staging(StagingError),
+    BadMagic,
+    UnsupportedVersion(u32),
+    Corrupt(&'static str),
+    ChecksumMismatch(String),
+    NotFound(String),
+}
+
+impl From<io::Error> for PackError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<StagingError> for PackError {
+    fn from(value: StagingError) -> Self {
+        Self::Staging(value)
+    }
+}
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            Self::Staging(e) => e.fmt(f),
+            Self::BadMagic => write!(f, "not an asset pack"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported asset pack version {}", version)
+            }
+            Self::Corrupt(reason) => write!(f, "corrupt asset pack: {}", reason),
+            Self::ChecksumMismatch(name) => write!(f, "asset {} failed its checksum", name),
+            Self::NotFound(name) => write!(f, "asset {} isn't in the pack", name),
+        }
+    }
+}
+
+impl error::Error for PackError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::PathBuf, process};
+
+    use super::*;
+
+    /// A file in the temporary directory, removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            Self(env::temp_dir().join(format!("learnvulkan-{}-{}.pack", process::id(), name)))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn write(name: &str, writer: &PackWriter) -> TempFile {
+        let file = TempFile::new(name);
+        writer.write_file(&file.0).unwrap();
+        file
+    }
+
+    #[test]
+    fn assets_round_trip() {
+        let text = b"void main() {}\n".repeat(40);
+        let binary: Vec<u8> = (0..=u8::MAX).collect();
+
+        let mut writer = PackWriter::new();
+        writer.add("shaders/a.frag", Some(AssetKind::Shader), &text);
+        writer.add("raw.bin", None, &binary);
+        writer.add("empty", Some(AssetKind::Mesh), &[]);
+        writer.add("one", Some(AssetKind::Texture), &[42]);
+
+        let file = write("round-trip", &writer);
+        let pack = Pack::open(&file.0).unwrap();
+
+        assert_eq!(pack.entries().len(), 4);
+        assert_eq!(pack.read("shaders/a.frag").unwrap(), text);
+        assert_eq!(pack.read("raw.bin").unwrap(), binary);
+        assert!(pack.read("empty").unwrap().is_empty());
+        assert_eq!(pack.read("one").unwrap(), [42]);
+
+        let entry = pack.entry("shaders/a.frag").unwrap();
+        assert_eq!(entry.kind, Some(AssetKind::Shader));
+        assert_eq!(entry.compression, Compression::Lz);
+        assert_eq!(entry.size, text.len() as u64);
+        assert!(entry.stored_size < entry.size);
+
+        // Data that doesn't compress is stored as is.
+        assert_eq!(
+            pack.entry("raw.bin").unwrap().compression,
+            Compression::None
+        );
+        assert_eq!(pack.entry("one").unwrap().stored_size, 1);
+    }
+
+    #[test]
+    fn empty_pack() {
+        let writer = PackWriter::new();
+        assert!(writer.is_empty());
+
+        let file = write("empty", &writer);
+        let pack = Pack::open(&file.0).unwrap();
+
+        assert!(pack.entries().is_empty());
+        assert!(!pack.contains("anything"));
+        assert!(matches!(pack.read("anything"), Err(PackError::NotFound(_))));
+    }
+
+    #[test]
+    fn adding_a_name_again_replaces_it() {
+        let mut writer = PackWriter::new();
+        writer.add("a", None, b"old");
+        writer.add("a", None, b"new");
+
+        assert_eq!(writer.len(), 1);
+
+        let file = write("replace", &writer);
+        assert_eq!(Pack::open(&file.0).unwrap().read("a").unwrap(), b"new");
+    }
+
+    #[test]
+    fn damaged_packs_are_rejected() {
+        let mut writer = PackWriter::new();
+        writer.add("a", None, &b"abcd".repeat(64));
+
+        let file = write("damaged", &writer);
+        let mut bytes = fs::read(&file.0).unwrap();
+
+        // A flipped bit in the blob fails the checksum or the decompression.
+        bytes[HEADER_SIZE as usize + 1] ^= 1;
+        fs::write(&file.0, &bytes).unwrap();
+        assert!(matches!(
+            Pack::open(&file.0).unwrap().read("a"),
+            Err(PackError::ChecksumMismatch(_) | PackError::Corrupt(_))
+        ));
+
+        bytes[4] = 2;
+        fs::write(&file.0, &bytes).unwrap();
+        assert!(matches!(
+            Pack::open(&file.0),
+            Err(PackError::UnsupportedVersion(2))
+        ));
+
+        bytes[0] = b'X';
+        fs::write(&file.0, &bytes).unwrap();
+        assert!(matches!(Pack::open(&file.0), Err(PackError::BadMagic)));
+
+        fs::write(&file.0, &bytes[..HEADER_SIZE as usize - 1]).unwrap();
+        assert!(matches!(Pack::open(&file.0), Err(PackError::Io(_))));
+    }
+
+    #[test]
+    fn truncated_table_is_rejected() {
+        let mut writer = PackWriter::new();
+        writer.add("a", None, b"data");
+
+        let file = write("truncated", &writer);
+        let bytes = fs::read(&file.0).unwrap();
+
+        fs::write(&file.0, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(matches!(Pack::open(&file.0), Err(PackError::Corrupt(_))));
+    }
+
+    #[test]
+    fn kinds_round_trip_through_bytes() {
+        for kind in [
+            None,
+            Some(AssetKind::Shader),
+            Some(AssetKind::Texture),
+            Some(AssetKind::Mesh),
+        ] {
+            assert_eq!(kind_from_byte(kind_to_byte(kind)), Some(kind));
+        }
+
+        assert_eq!(kind_from_byte(4), None);
+        assert_eq!(kind_from_byte(u8::MAX), None);
+    }
+
+    #[test]
+    fn fnv1a_matches_the_reference() {
+        assert_eq!(fnv1a(b""), 0x811c_9dc5);
+        assert_eq!(fnv1a(b"a"), 0xe40c_292c);
+        assert_eq!(fnv1a(b"foobar"), 0xbf9c_f968);
+    }
+}
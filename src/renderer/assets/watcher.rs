@@ -0,0 +1,153 @@
+//! Watches asset files on disk and reports changes once editors are done writing them.
+
+use std::{
+    collections::HashMap,
+    error, fmt,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
+
+use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+use super::AssetKind;
+
+/// A file that changed and is ready to be reloaded.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AssetEvent {
+    pub kind: AssetKind,
+    pub path: PathBuf,
+}
+
+/// Watches directories for changed assets, routing each file to an [AssetKind] by extension.
+///
+/// Events are collected in the background and handed out by [Watcher::poll], which the main
+/// loop calls at a point in the frame where GPU resources can be replaced, i.e. after waiting
+/// for the frame's fence.
+pub struct Watcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    debounce: Duration,
+    routes: HashMap<String, AssetKind>,
+    pending: HashMap<PathBuf, (AssetKind, Instant)>,
+}
+
+impl Watcher {
+    /// Creates a watcher that reports a file once it went `debounce` without changing, with the
+    /// default routes for shaders, textures and meshes.
+    pub fn new(debounce: Duration) -> Result<Self, WatcherError> {
+        let (sender, events) = mpsc::channel();
+        let watcher = RecommendedWatcher::new(sender, Config::default())?;
+
+        let mut routes = HashMap::new();
+
+        for extension in ["vert", "frag", "comp", "glsl", "wgsl", "spv"] {
+            routes.insert(extension.to_owned(), AssetKind::Shader);
+        }
+
+        for extension in ["png", "jpg", "jpeg", "hdr", "ktx2"] {
+            routes.insert(extension.to_owned(), AssetKind::Texture);
+        }
+
+        for extension in ["obj", "gltf", "glb"] {
+            routes.insert(extension.to_owned(), AssetKind::Mesh);
+        }
+
+        Ok(Self {
+            watcher,
+            events,
+            debounce,
+            routes,
+            pending: HashMap::new(),
+        })
+    }
+
+    /// Starts watching `path` and everything below it.
+    pub fn watch<P: AsRef<Path>>(&mut self, path: P) -> Result<(), WatcherError> {
+        self.watcher
+            .watch(path.as_ref(), RecursiveMode::Recursive)
+            .map_err(WatcherError::from)
+    }
+
+    /// Stops watching `path`.
+    pub fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> Result<(), WatcherError> {
+        self.watcher
+            .unwatch(path.as_ref())
+            .map_err(WatcherError::from)
+    }
+
+    /// Reloads files with `extension` as `kind`, replacing the default route if any.
+    pub fn route(&mut self, extension: &str, kind: AssetKind) {
+        self.routes.insert(extension.to_ascii_lowercase(), kind);
+    }
+
+    /// Ignores files with `extension`.
+    pub fn unroute(&mut self, extension: &str) {
+        self.routes.remove(&extension.to_ascii_lowercase());
+    }
+
+    /// The kind `path` is routed to, if any.
+    pub fn kind_of(&self, path: &Path) -> Option<AssetKind> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        self.routes.get(&extension).copied()
+    }
+
+    /// Returns the files that changed and then stayed untouched for the debounce time.
+    pub fn poll(&mut self) -> Vec<AssetEvent> {
+        let now = Instant::now();
+
+        while let Ok(event) = self.events.try_recv() {
+            let Ok(event) = event else {
+                continue;
+            };
+
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                continue;
+            }
+
+            for path in event.paths {
+                if let Some(kind) = self.kind_of(&path) {
+                    self.pending.insert(path, (kind, now));
+                }
+            }
+        }
+
+        let mut ready = Vec::new();
+
+        self.pending.retain(|path, (kind, changed)| {
+            if now.duration_since(*changed) < self.debounce {
+                return true;
+            }
+
+            ready.push(AssetEvent {
+                kind: *kind,
+                path: path.clone(),
+            });
+
+            false
+        });
+
+        ready
+    }
+}
+
+#[derive(Debug)]
+pub enum WatcherError {
+    Notify(notify::Error),
+}
+
+impl From<notify::Error> for WatcherError {
+    fn from(value: notify::Error) -> Self {
+        Self::Notify(value)
+    }
+}
+
+impl fmt::Display for WatcherError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Notify(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for WatcherError {}
@@ -0,0 +1,512 @@
+use std::{cell::Cell, mem, rc::Rc};
+
+use ash::vk::{
+    self, AccessFlags, BufferCreateInfo, BufferMemoryBarrier, BufferUsageFlags, CommandBuffer,
+    ComputePipelineCreateInfo, DependencyFlags, DescriptorBufferInfo, DescriptorImageInfo,
+    DescriptorPoolCreateInfo, DescriptorPoolSize, DescriptorSetAllocateInfo,
+    DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo, DescriptorType, Extent2D, Handle,
+    ImageLayout, MemoryAllocateInfo, MemoryBarrier, MemoryMapFlags, MemoryPropertyFlags,
+    PipelineBindPoint, PipelineCache, PipelineLayoutCreateInfo, PipelineShaderStageCreateInfo,
+    PipelineStageFlags, PushConstantRange, ShaderStageFlags, SharingMode, WriteDescriptorSet,
+    WHOLE_SIZE,
+};
+
+use crate::{
+    api2::{div_round_up, host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        logical_device::LogicalDevice, pipeline_stats, resource_stats::ResourceKind,
+        shader_cache::ShaderCache, shader_module::ShaderModule, teardown_trace,
+    },
+    types::Pod,
+};
+
+/// Number of bins in the luminance histogram, matches `HISTOGRAM_BINS` in the shaders.
+pub const HISTOGRAM_BINS: usize = 256;
+
+/// The workgroup size of `luminance_histogram.comp` along each axis.
+const HISTOGRAM_GROUP_SIZE: u32 = 16;
+
+/// How the auto-exposure pass measures the scene and reacts to changes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ExposureSettings {
+    /// Darkest log2 luminance the histogram resolves, anything darker is ignored.
+    pub min_log_luminance: f32,
+    /// Brightest log2 luminance the histogram resolves, anything brighter lands in the last bin.
+    pub max_log_luminance: f32,
+    /// How fast the eye adapts, higher is faster.
+    pub adaptation_rate: f32,
+    /// The middle grey the average luminance is mapped to.
+    pub key: f32,
+}
+
+impl Default for ExposureSettings {
+    fn default() -> Self {
+        Self {
+            min_log_luminance: -8.0,
+            max_log_luminance: 3.5,
+            adaptation_rate: 1.1,
+            key: 0.18,
+        }
+    }
+}
+
+impl ExposureSettings {
+    /// The fraction of the way from last frame's luminance to the measured one to move after
+    /// `delta_seconds`, independent of the frame rate.
+    pub fn adaptation(&self, delta_seconds: f32) -> f32 {
+        1.0 - (-delta_seconds * self.adaptation_rate).exp()
+    }
+
+    pub fn push_constants(&self, extent: Extent2D, delta_seconds: f32) -> ExposurePushConstants {
+        let log_luminance_range = self.max_log_luminance - self.min_log_luminance;
+
+        ExposurePushConstants {
+            min_log_luminance: self.min_log_luminance,
+            inverse_log_luminance_range: 1.0 / log_luminance_range,
+            log_luminance_range,
+            adaptation: self.adaptation(delta_seconds),
+            pixel_count: extent.width * extent.height,
+            key: self.key,
+        }
+    }
+}
+
+/// The push constants of both auto-exposure shaders, mirrors `exposure_params.glsl`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ExposurePushConstants {
+    pub min_log_luminance: f32,
+    pub inverse_log_luminance_range: f32,
+    pub log_luminance_range: f32,
+    pub adaptation: f32,
+    pub pixel_count: u32,
+    pub key: f32,
+}
+
+unsafe impl Pod for ExposurePushConstants {}
+
+/// The buffer written by the pass, mirrors `exposure_data.glsl`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct ExposureData {
+    histogram: [u32; HISTOGRAM_BINS],
+    average_luminance: f32,
+    exposure: f32,
+}
+
+/// Measures the luminance of an HDR image on the GPU and keeps an exposure adapted to it in a
+/// storage buffer, for the tonemap pass to read through `shaders/tonemap.glsl`.
+///
+/// The compute shaders are `shaders/luminance_histogram.comp` and
+/// `shaders/average_luminance.comp`, and they have to run on a queue that supports compute.
+#[derive(Clone)]
+pub struct AutoExposure(Rc<InnerAutoExposure>);
+
+impl AutoExposure {
+    /// Creates the pass reading `hdr_view`, an `R16G16B16A16_SFLOAT` storage image.
+    pub fn new(
+        logical_device: LogicalDevice,
+        shader_cache: &ShaderCache,
+        hdr_view: vk::ImageView,
+        histogram_shader: &[u32],
+        average_shader: &[u32],
+    ) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+
+        let (buffer, memory) = create_exposure_buffer(&logical_device)?;
+
+        let bindings = [
+            DescriptorSetLayoutBinding::default()
+                .binding(0)
+                .descriptor_type(DescriptorType::STORAGE_IMAGE)
+                .descriptor_count(1)
+                .stage_flags(ShaderStageFlags::COMPUTE),
+            DescriptorSetLayoutBinding::default()
+                .binding(1)
+                .descriptor_type(DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(ShaderStageFlags::COMPUTE),
+        ];
+
+        let descriptor_set_layout = unsafe {
+            device.create_descriptor_set_layout(
+                &DescriptorSetLayoutCreateInfo::default().bindings(&bindings),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating auto-exposure descriptor set layout")?;
+
+        let pool_sizes = [
+            DescriptorPoolSize::default()
+                .ty(DescriptorType::STORAGE_IMAGE)
+                .descriptor_count(1),
+            DescriptorPoolSize::default()
+                .ty(DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1),
+        ];
+
+        let descriptor_pool = unsafe {
+            device.create_descriptor_pool(
+                &DescriptorPoolCreateInfo::default()
+                    .max_sets(1)
+                    .pool_sizes(&pool_sizes),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating auto-exposure descriptor pool")?;
+
+        let set_layouts = [descriptor_set_layout];
+        let descriptor_set = unsafe {
+            device.allocate_descriptor_sets(
+                &DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(descriptor_pool)
+                    .set_layouts(&set_layouts),
+            )
+        }
+        .context("allocating auto-exposure descriptor set")?[0];
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::DescriptorSet, 1, 0);
+
+        let push_constant_ranges = [PushConstantRange::default()
+            .stage_flags(ShaderStageFlags::COMPUTE)
+            .size(mem::size_of::<ExposurePushConstants>() as u32)];
+
+        let pipeline_layout = unsafe {
+            device.create_pipeline_layout(
+                &PipelineLayoutCreateInfo::default()
+                    .set_layouts(&set_layouts)
+                    .push_constant_ranges(&push_constant_ranges),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating auto-exposure pipeline layout")?;
+
+        let shader_modules: [ShaderModule; 2] = [
+            shader_cache
+                .get_or_create(histogram_shader)
+                .context("creating luminance histogram shader module")?,
+            shader_cache
+                .get_or_create(average_shader)
+                .context("creating average luminance shader module")?,
+        ];
+
+        let create_infos = shader_modules.each_ref().map(|shader_module| {
+            ComputePipelineCreateInfo::default()
+                .stage(
+                    PipelineShaderStageCreateInfo::default()
+                        .stage(ShaderStageFlags::COMPUTE)
+                        .module(*shader_module.shader_module())
+                        .name(c"main"),
+                )
+                .layout(pipeline_layout)
+        });
+
+        let pipelines = unsafe {
+            pipeline_stats::create_compute_pipelines(
+                &logical_device,
+                PipelineCache::null(),
+                &create_infos,
+            )
+        }
+        .context("creating auto-exposure compute pipelines")?;
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::Pipeline, pipelines.len() as u64, 0);
+
+        let auto_exposure = Self(Rc::new(InnerAutoExposure {
+            buffer,
+            memory,
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_set,
+            pipeline_layout,
+            histogram_pipeline: pipelines[0],
+            average_pipeline: pipelines[1],
+            settings: Cell::new(ExposureSettings::default()),
+            logical_device,
+        }));
+
+        auto_exposure.set_hdr_view(hdr_view);
+
+        Ok(auto_exposure)
+    }
+
+    pub fn settings(&self) -> ExposureSettings {
+        self.0.settings.get()
+    }
+
+    pub fn set_settings(&self, settings: ExposureSettings) {
+        self.0.settings.set(settings);
+    }
+
+    /// Points the pass at a new HDR image, e.g. after a resize. The descriptor set must not be in
+    /// use by a pending command buffer.
+    pub fn set_hdr_view(&self, hdr_view: vk::ImageView) {
+        let image_info = [DescriptorImageInfo::default()
+            .image_view(hdr_view)
+            .image_layout(ImageLayout::GENERAL)];
+
+        let buffer_info = [self.exposure_buffer()];
+
+        let writes = [
+            WriteDescriptorSet::default()
+                .dst_set(self.0.descriptor_set)
+                .dst_binding(0)
+                .descriptor_type(DescriptorType::STORAGE_IMAGE)
+                .image_info(&image_info),
+            WriteDescriptorSet::default()
+                .dst_set(self.0.descriptor_set)
+                .dst_binding(1)
+                .descriptor_type(DescriptorType::STORAGE_BUFFER)
+                .buffer_info(&buffer_info),
+        ];
+
+        unsafe {
+            self.0
+                .logical_device
+                .device()
+                .update_descriptor_sets(&writes, &[]);
+        }
+    }
+
+    /// The buffer holding the exposure, to bind where `tonemap.glsl` expects it.
+    pub fn exposure_buffer(&self) -> DescriptorBufferInfo {
+        DescriptorBufferInfo::default()
+            .buffer(self.0.buffer)
+            .offset(0)
+            .range(WHOLE_SIZE)
+    }
+
+    /// The average luminance as of the last finished frame, read back from the host-visible
+    /// exposure buffer.
+    pub fn average_luminance(&self) -> f32 {
+        self.read_data().map_or(0.0, |data| data.average_luminance)
+    }
+
+    /// Records the histogram and reduction passes for an HDR image of size `extent`, which has to
+    /// be in the `GENERAL` layout when the commands execute.
+    ///
+    /// Waits for color attachment writes before reading the image and makes the exposure
+    /// visible to fragment shaders afterwards.
+    pub fn record(&self, command_buffer: CommandBuffer, extent: Extent2D, delta_seconds: f32) {
+        let device = self.0.logical_device.device();
+        let push_constants = self.settings().push_constants(extent, delta_seconds);
+
+        let buffer_barrier = |src_access, dst_access| {
+            [BufferMemoryBarrier::default()
+                .src_access_mask(src_access)
+                .dst_access_mask(dst_access)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .buffer(self.0.buffer)
+                .size(WHOLE_SIZE)]
+        };
+
+        unsafe {
+            // The HDR image was just rendered and last frame's tonemap pass may still be reading
+            // the exposure.
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | PipelineStageFlags::FRAGMENT_SHADER,
+                PipelineStageFlags::COMPUTE_SHADER,
+                DependencyFlags::empty(),
+                &[MemoryBarrier::default()
+                    .src_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE)
+                    .dst_access_mask(AccessFlags::SHADER_READ)],
+                &[],
+                &[],
+            );
+
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                PipelineBindPoint::COMPUTE,
+                self.0.pipeline_layout,
+                0,
+                &[self.0.descriptor_set],
+                &[],
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.0.pipeline_layout,
+                ShaderStageFlags::COMPUTE,
+                0,
+                push_constants.bytes_of(),
+            );
+
+            device.cmd_bind_pipeline(
+                command_buffer,
+                PipelineBindPoint::COMPUTE,
+                self.0.histogram_pipeline,
+            );
+            device.cmd_dispatch(
+                command_buffer,
+                div_round_up(extent.width, HISTOGRAM_GROUP_SIZE),
+                div_round_up(extent.height, HISTOGRAM_GROUP_SIZE),
+                1,
+            );
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::COMPUTE_SHADER,
+                PipelineStageFlags::COMPUTE_SHADER,
+                DependencyFlags::empty(),
+                &[],
+                &buffer_barrier(
+                    AccessFlags::SHADER_WRITE,
+                    AccessFlags::SHADER_READ | AccessFlags::SHADER_WRITE,
+                ),
+                &[],
+            );
+
+            device.cmd_bind_pipeline(
+                command_buffer,
+                PipelineBindPoint::COMPUTE,
+                self.0.average_pipeline,
+            );
+            device.cmd_dispatch(command_buffer, 1, 1, 1);
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::COMPUTE_SHADER,
+                PipelineStageFlags::FRAGMENT_SHADER | PipelineStageFlags::HOST,
+                DependencyFlags::empty(),
+                &[],
+                &buffer_barrier(
+                    AccessFlags::SHADER_WRITE,
+                    AccessFlags::SHADER_READ | AccessFlags::HOST_READ,
+                ),
+                &[],
+            );
+        }
+    }
+
+    fn read_data(&self) -> Option<ExposureData> {
+        let device = self.0.logical_device.device();
+
+        unsafe {
+            let data = device
+                .map_memory(self.0.memory, 0, WHOLE_SIZE, MemoryMapFlags::empty())
+                .ok()?;
+            let exposure_data = data.cast::<ExposureData>().read();
+            device.unmap_memory(self.0.memory);
+
+            Some(exposure_data)
+        }
+    }
+}
+
+/// Creates the exposure buffer in host-visible memory, starting from an empty histogram and a
+/// neutral exposure.
+fn create_exposure_buffer(
+    logical_device: &LogicalDevice,
+) -> Result<(vk::Buffer, vk::DeviceMemory), ErrorCtx> {
+    let device = logical_device.device();
+    let size = mem::size_of::<ExposureData>() as u64;
+
+    let buffer = unsafe {
+        device.create_buffer(
+            &BufferCreateInfo::default()
+                .size(size)
+                .usage(BufferUsageFlags::STORAGE_BUFFER)
+                .sharing_mode(SharingMode::EXCLUSIVE),
+            host_allocation_callbacks(),
+        )
+    }
+    .context("creating exposure buffer")?;
+
+    let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+
+    let memory_type_index = logical_device
+        .physical_device()
+        .find_memory_type(
+            requirements.memory_type_bits,
+            MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+        )
+        .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+        .with_context("finding exposure buffer memory", || {
+            format!("memory_type_bits={:#x}", requirements.memory_type_bits)
+        })?;
+
+    let memory = unsafe {
+        device.allocate_memory(
+            &MemoryAllocateInfo::default()
+                .allocation_size(requirements.size)
+                .memory_type_index(memory_type_index),
+            host_allocation_callbacks(),
+        )
+    }
+    .context("allocating exposure buffer memory")?;
+
+    unsafe {
+        device
+            .bind_buffer_memory(buffer, memory, 0)
+            .context("binding exposure buffer memory")?;
+
+        let data = device
+            .map_memory(memory, 0, WHOLE_SIZE, MemoryMapFlags::empty())
+            .context("mapping exposure buffer memory")?;
+        data.cast::<ExposureData>().write(ExposureData {
+            histogram: [0; HISTOGRAM_BINS],
+            average_luminance: 1.0,
+            exposure: 1.0,
+        });
+        device.unmap_memory(memory);
+    }
+
+    logical_device
+        .resource_tracker()
+        .track(ResourceKind::Buffer, 1, requirements.size);
+
+    Ok((buffer, memory))
+}
+
+struct InnerAutoExposure {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    pipeline_layout: vk::PipelineLayout,
+    histogram_pipeline: vk::Pipeline,
+    average_pipeline: vk::Pipeline,
+    settings: Cell<ExposureSettings>,
+
+    logical_device: LogicalDevice,
+}
+
+impl Drop for InnerAutoExposure {
+    fn drop(&mut self) {
+        teardown_trace::record(
+            "AutoExposure",
+            [
+                self.histogram_pipeline.as_raw(),
+                self.average_pipeline.as_raw(),
+                self.buffer.as_raw(),
+            ],
+        );
+
+        let device = self.logical_device.device();
+        let requirements = unsafe { device.get_buffer_memory_requirements(self.buffer) };
+
+        unsafe {
+            device.destroy_pipeline(self.histogram_pipeline, host_allocation_callbacks());
+            device.destroy_pipeline(self.average_pipeline, host_allocation_callbacks());
+            device.destroy_pipeline_layout(self.pipeline_layout, host_allocation_callbacks());
+            device.destroy_descriptor_pool(self.descriptor_pool, host_allocation_callbacks());
+            device.destroy_descriptor_set_layout(
+                self.descriptor_set_layout,
+                host_allocation_callbacks(),
+            );
+            device.destroy_buffer(self.buffer, host_allocation_callbacks());
+            device.free_memory(self.memory, host_allocation_callbacks());
+        }
+
+        let tracker = self.logical_device.resource_tracker();
+        tracker.untrack(ResourceKind::Pipeline, 2, 0);
+        tracker.untrack(ResourceKind::DescriptorSet, 1, 0);
+        tracker.untrack(ResourceKind::Buffer, 1, requirements.size);
+    }
+}
@@ -0,0 +1,777 @@
+//! Rendering many frames without a window or swapchain, e.g. thumbnails or the frames of a visual
+//! test matrix.
+//!
+//! A [BatchRenderer] owns a pool of offscreen targets, each with its own command buffer, fence
+//! and readback buffer. [BatchRenderer::render] records one frame per state into the next free
+//! target and only waits when the pool is exhausted, so as many frames as there are targets are
+//! in flight at once. Nothing is presented. Targets created with [BatchRenderer::with_samples]
+//! are multisampled and resolved at the end of the render pass, so the frames read back always
+//! have one sample per pixel.
+//!
+//! [BatchRenderer::capture_frame_async] renders a single frame without waiting for it at all, so
+//! a render loop can take captures and pick them up frames later from the returned
+//! [PendingCapture].
+
+use std::{cell::Cell, rc::Rc, slice};
+#[cfg(feature = "async")]
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use ash::{
+    prelude::VkResult,
+    vk::{
+        self, AccessFlags, AttachmentDescription, AttachmentLoadOp, AttachmentReference,
+        AttachmentStoreOp, BufferCreateInfo, BufferImageCopy, BufferMemoryBarrier,
+        BufferUsageFlags, ClearColorValue, ClearValue, CommandBuffer, CommandBufferAllocateInfo,
+        CommandBufferBeginInfo, CommandBufferLevel, CommandBufferUsageFlags,
+        CommandPoolCreateFlags, CommandPoolCreateInfo, DependencyFlags, Extent2D, Extent3D, Fence,
+        FenceCreateInfo, Format, FramebufferCreateInfo, Handle, ImageAspectFlags, ImageLayout,
+        ImageSubresourceLayers, ImageUsageFlags, MemoryAllocateInfo, MemoryMapFlags,
+        MemoryPropertyFlags, PipelineBindPoint, PipelineStageFlags, Rect2D, RenderPassBeginInfo,
+        RenderPassCreateInfo, SampleCountFlags, SharingMode, SubmitInfo, SubpassContents,
+        SubpassDependency, SubpassDescription, SUBPASS_EXTERNAL, WHOLE_SIZE,
+    },
+};
+
+use crate::{
+    api2::{host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        color_image::ColorImage,
+        logical_device::LogicalDevice,
+        resource_stats::ResourceKind,
+        teardown_trace,
+        testing::{CapturedFrame, PixelLayout},
+    },
+};
+
+/// What the record callback of [BatchRenderer::render] draws into.
+pub struct BatchFrame {
+    /// The command buffer, inside the render pass.
+    pub command_buffer: CommandBuffer,
+    /// The index of the state being rendered.
+    pub index: usize,
+    pub extent: Extent2D,
+}
+
+pub struct BatchRenderer {
+    render_pass: vk::RenderPass,
+    command_pool: vk::CommandPool,
+    targets: Vec<Rc<BatchTarget>>,
+    format: Format,
+    layout: PixelLayout,
+    extent: Extent2D,
+    samples: SampleCountFlags,
+    clear_color: [f32; 4],
+
+    logical_device: LogicalDevice,
+}
+
+impl BatchRenderer {
+    /// Creates `target_count` targets of `extent`, which is also how many frames are rendered
+    /// at once. The format has to be one of the 8-bit RGBA or BGRA formats.
+    pub fn new(
+        logical_device: LogicalDevice,
+        format: Format,
+        extent: Extent2D,
+        target_count: usize,
+    ) -> Result<Self, ErrorCtx> {
+        Self::with_samples(
+            logical_device,
+            format,
+            extent,
+            target_count,
+            SampleCountFlags::TYPE_1,
+        )
+    }
+
+    /// Creates the targets like [BatchRenderer::new], rendering with `samples` per pixel. The
+    /// frames are resolved before they're read back, so captures look the same whatever the
+    /// sample count.
+    pub fn with_samples(
+        logical_device: LogicalDevice,
+        format: Format,
+        extent: Extent2D,
+        target_count: usize,
+        samples: SampleCountFlags,
+    ) -> Result<Self, ErrorCtx> {
+        let layout = pixel_layout(format)
+            .ok_or(vk::Result::ERROR_FORMAT_NOT_SUPPORTED)
+            .with_context("creating batch renderer", || format!("format={:?}", format))?;
+
+        let supported_samples = logical_device
+            .physical_device()
+            .properties()
+            .limits
+            .framebuffer_color_sample_counts;
+
+        if !samples.as_raw().is_power_of_two() || !supported_samples.contains(samples) {
+            return Err(ErrorCtx::new(
+                "creating batch renderer",
+                vk::Result::ERROR_FORMAT_NOT_SUPPORTED,
+            )
+            .details(format!(
+                "samples={:?}, supported={:?}",
+                samples, supported_samples
+            )));
+        }
+
+        let device = logical_device.device();
+
+        let command_pool = unsafe {
+            device.create_command_pool(
+                &CommandPoolCreateInfo::default()
+                    .flags(CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+                    .queue_family_index(logical_device.physical_device().graphics_family_u32()),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating batch command pool")?;
+
+        let mut renderer = Self {
+            render_pass: vk::RenderPass::null(),
+            command_pool,
+            targets: Vec::with_capacity(target_count),
+            format,
+            layout,
+            extent,
+            samples,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            logical_device: logical_device.clone(),
+        };
+
+        renderer.render_pass = create_render_pass(&logical_device, format, samples)?;
+
+        let command_buffers = unsafe {
+            device.allocate_command_buffers(
+                &CommandBufferAllocateInfo::default()
+                    .command_pool(command_pool)
+                    .level(CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(target_count as u32),
+            )
+        }
+        .context("allocating batch command buffers")?;
+
+        for command_buffer in command_buffers {
+            renderer.targets.push(Rc::new(BatchTarget::new(
+                &logical_device,
+                renderer.render_pass,
+                format,
+                extent,
+                samples,
+                command_buffer,
+            )?));
+        }
+
+        Ok(renderer)
+    }
+
+    /// The render pass the frames are drawn in, to create pipelines against.
+    pub fn render_pass(&self) -> vk::RenderPass {
+        self.render_pass
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    pub fn extent(&self) -> Extent2D {
+        self.extent
+    }
+
+    /// The samples per pixel pipelines have to rasterize with.
+    pub fn samples(&self) -> SampleCountFlags {
+        self.samples
+    }
+
+    pub fn set_clear_color(&mut self, clear_color: [f32; 4]) {
+        self.clear_color = clear_color;
+    }
+
+    /// Renders one frame per state through `record`, which draws inside the render pass, and
+    /// returns the frames in the order of the states.
+    ///
+    /// Only uses the targets no [PendingCapture] holds, and fails when there's none.
+    pub fn render<S>(
+        &mut self,
+        states: &[S],
+        mut record: impl FnMut(&BatchFrame, &S),
+    ) -> Result<Vec<CapturedFrame>, ErrorCtx> {
+        let slots = self.free_slots();
+
+        if slots.is_empty() && !states.is_empty() {
+            return Err(no_free_target("rendering batch"));
+        }
+
+        // The index of the state each slot is rendering.
+        let mut pending: Vec<Option<usize>> = vec![None; slots.len()];
+        let mut frames: Vec<Option<CapturedFrame>> = vec![None; states.len()];
+
+        let result = states.iter().enumerate().try_for_each(|(index, state)| {
+            let i = index % slots.len();
+
+            self.collect(slots[i], &mut pending[i], &mut frames)?;
+            self.submit(slots[i], index, |frame| record(frame, state))?;
+            pending[i] = Some(index);
+
+            Ok(())
+        });
+
+        // Everything submitted has to finish before returning, even on failure.
+        let drained = slots
+            .iter()
+            .zip(&mut pending)
+            .try_for_each(|(&slot, pending)| self.collect(slot, pending, &mut frames));
+
+        result.and(drained)?;
+
+        Ok(frames.into_iter().flatten().collect())
+    }
+
+    /// Renders one frame through `record` without waiting for it, see [PendingCapture].
+    ///
+    /// The capture holds its target until it's dropped, leaving one less for
+    /// [BatchRenderer::render] and further captures. Fails when every target is held.
+    pub fn capture_frame_async(
+        &mut self,
+        record: impl FnOnce(&BatchFrame),
+    ) -> Result<PendingCapture, ErrorCtx> {
+        let free = self.free_slots();
+
+        // A dropped capture may leave its target still rendering, only wait for one if needed.
+        let slot = free
+            .iter()
+            .copied()
+            .find(|&slot| !self.targets[slot].in_flight.get())
+            .or(free.first().copied())
+            .ok_or_else(|| no_free_target("capturing frame"))?;
+
+        self.submit(slot, 0, record)?;
+
+        Ok(PendingCapture {
+            target: self.targets[slot].clone(),
+            extent: self.extent,
+            layout: self.layout,
+        })
+    }
+
+    /// The targets no [PendingCapture] holds.
+    fn free_slots(&self) -> Vec<usize> {
+        (0..self.targets.len())
+            .filter(|&slot| Rc::strong_count(&self.targets[slot]) == 1)
+            .collect()
+    }
+
+    fn submit(
+        &self,
+        slot: usize,
+        index: usize,
+        record: impl FnOnce(&BatchFrame),
+    ) -> Result<(), ErrorCtx> {
+        let device = self.logical_device.device();
+        let target = &self.targets[slot];
+        let command_buffer = target.command_buffer;
+
+        // The target's last frame has to be done before its command buffer is reused.
+        target
+            .wait()
+            .with_context("waiting for batch target", || format!("index={}", index))?;
+
+        let clear_values = [ClearValue {
+            color: ClearColorValue {
+                float32: self.clear_color,
+            },
+        }];
+
+        unsafe {
+            device
+                .begin_command_buffer(
+                    command_buffer,
+                    &CommandBufferBeginInfo::default()
+                        .flags(CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+                )
+                .context("beginning batch command buffer")?;
+
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &RenderPassBeginInfo::default()
+                    .render_pass(self.render_pass)
+                    .framebuffer(target.framebuffer)
+                    .render_area(Rect2D::default().extent(self.extent))
+                    .clear_values(&clear_values),
+                SubpassContents::INLINE,
+            );
+        }
+
+        record(&BatchFrame {
+            command_buffer,
+            index,
+            extent: self.extent,
+        });
+
+        unsafe {
+            device.cmd_end_render_pass(command_buffer);
+
+            device.cmd_copy_image_to_buffer(
+                command_buffer,
+                target.color.image(),
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                target.readback,
+                &[BufferImageCopy::default()
+                    .image_subresource(
+                        ImageSubresourceLayers::default()
+                            .aspect_mask(ImageAspectFlags::COLOR)
+                            .layer_count(1),
+                    )
+                    .image_extent(Extent3D {
+                        width: self.extent.width,
+                        height: self.extent.height,
+                        depth: 1,
+                    })],
+            );
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::HOST,
+                DependencyFlags::empty(),
+                &[],
+                &[BufferMemoryBarrier::default()
+                    .src_access_mask(AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(AccessFlags::HOST_READ)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .buffer(target.readback)
+                    .size(WHOLE_SIZE)],
+                &[],
+            );
+
+            device
+                .end_command_buffer(command_buffer)
+                .context("ending batch command buffer")?;
+
+            device
+                .reset_fences(&[target.fence])
+                .context("resetting batch fence")?;
+
+            device
+                .queue_submit(
+                    *self.logical_device.queue(),
+                    &[SubmitInfo::default().command_buffers(slice::from_ref(&command_buffer))],
+                    target.fence,
+                )
+                .with_context("submitting batch frame", || format!("index={}", index))?;
+        }
+
+        target.in_flight.set(true);
+
+        Ok(())
+    }
+
+    /// Waits for the slot's pending frame, if any, and reads it back into `frames`.
+    fn collect(
+        &self,
+        slot: usize,
+        pending: &mut Option<usize>,
+        frames: &mut [Option<CapturedFrame>],
+    ) -> Result<(), ErrorCtx> {
+        let Some(index) = pending.take() else {
+            return Ok(());
+        };
+
+        let target = &self.targets[slot];
+
+        target
+            .wait()
+            .with_context("waiting for batch frame", || format!("index={}", index))?;
+
+        frames[index] = Some(target.read(self.extent, self.layout));
+
+        Ok(())
+    }
+}
+
+/// A frame from [BatchRenderer::capture_frame_async] that may still be rendering.
+///
+/// Check on it once per frame of the render loop with [PendingCapture::poll_frame] or, with the
+/// `async` feature, await it. Nothing signals the waker when the fence does, so awaiting checks
+/// the fence again every time the executor polls it.
+pub struct PendingCapture {
+    target: Rc<BatchTarget>,
+    extent: Extent2D,
+    layout: PixelLayout,
+}
+
+impl PendingCapture {
+    /// Whether the frame finished rendering, without waiting.
+    pub fn is_ready(&self) -> Result<bool, ErrorCtx> {
+        self.target.is_done().context("polling batch capture")
+    }
+
+    /// Reads the frame back once it finished rendering, without waiting.
+    pub fn poll_frame(&self) -> Result<Option<CapturedFrame>, ErrorCtx> {
+        Ok(self
+            .is_ready()?
+            .then(|| self.target.read(self.extent, self.layout)))
+    }
+
+    /// Waits for the frame and reads it back.
+    pub fn wait(self) -> Result<CapturedFrame, ErrorCtx> {
+        self.target.wait().context("waiting for batch capture")?;
+
+        Ok(self.target.read(self.extent, self.layout))
+    }
+}
+
+#[cfg(feature = "async")]
+impl Future for PendingCapture {
+    type Output = Result<CapturedFrame, ErrorCtx>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        match self.poll_frame() {
+            Ok(Some(frame)) => Poll::Ready(Ok(frame)),
+            Ok(None) => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+impl Drop for BatchRenderer {
+    fn drop(&mut self) {
+        // Frames still in flight, after a failed render or from captures, have to finish before
+        // their command buffers are freed with the pool. Held targets outlive the renderer.
+        for target in &self.targets {
+            let _ = target.wait();
+        }
+
+        self.targets.clear();
+
+        teardown_trace::record(
+            "BatchRenderer",
+            [self.render_pass.as_raw(), self.command_pool.as_raw()],
+        );
+
+        let device = self.logical_device.device();
+
+        unsafe {
+            device.destroy_command_pool(self.command_pool, host_allocation_callbacks());
+
+            if self.render_pass != vk::RenderPass::null() {
+                device.destroy_render_pass(self.render_pass, host_allocation_callbacks());
+            }
+        }
+    }
+}
+
+/// The error for when every target is held by a [PendingCapture].
+fn no_free_target(operation: &'static str) -> ErrorCtx {
+    ErrorCtx::new(operation, vk::Result::NOT_READY)
+        .details("every target is held by a pending capture")
+}
+
+/// The byte order of the formats the frames can be read back from.
+fn pixel_layout(format: Format) -> Option<PixelLayout> {
+    match format {
+        Format::R8G8B8A8_UNORM | Format::R8G8B8A8_SRGB => Some(PixelLayout::Rgba8),
+        Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB => Some(PixelLayout::Bgra8),
+        _ => None,
+    }
+}
+
+/// Creates the render pass drawing into attachment 0, which is resolved into attachment 1 at the
+/// end when multisampled.
+fn create_render_pass(
+    logical_device: &LogicalDevice,
+    format: Format,
+    samples: SampleCountFlags,
+) -> Result<vk::RenderPass, ErrorCtx> {
+    let color_attachment = AttachmentDescription::default()
+        .format(format)
+        .samples(SampleCountFlags::TYPE_1)
+        .load_op(AttachmentLoadOp::CLEAR)
+        .store_op(AttachmentStoreOp::STORE)
+        .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+        .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+        .initial_layout(ImageLayout::UNDEFINED)
+        .final_layout(ImageLayout::TRANSFER_SRC_OPTIMAL);
+
+    let color_references = [AttachmentReference::default()
+        .attachment(0)
+        .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)];
+    let resolve_references = [AttachmentReference::default()
+        .attachment(1)
+        .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)];
+
+    let mut subpass = SubpassDescription::default()
+        .pipeline_bind_point(PipelineBindPoint::GRAPHICS)
+        .color_attachments(&color_references);
+
+    let attachments = if samples == SampleCountFlags::TYPE_1 {
+        vec![color_attachment]
+    } else {
+        subpass = subpass.resolve_attachments(&resolve_references);
+
+        // Only the resolved samples are read back, the multisampled ones are discarded.
+        vec![
+            color_attachment
+                .samples(samples)
+                .store_op(AttachmentStoreOp::DONT_CARE)
+                .final_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            color_attachment.load_op(AttachmentLoadOp::DONT_CARE),
+        ]
+    };
+
+    let subpasses = [subpass];
+
+    let dependencies = [
+        // The target's previous readback copy has to finish before clearing.
+        SubpassDependency::default()
+            .src_subpass(SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(PipelineStageFlags::TRANSFER)
+            .dst_stage_mask(PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .dst_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE),
+        SubpassDependency::default()
+            .src_subpass(0)
+            .dst_subpass(SUBPASS_EXTERNAL)
+            .src_stage_mask(PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_stage_mask(PipelineStageFlags::TRANSFER)
+            .dst_access_mask(AccessFlags::TRANSFER_READ),
+    ];
+
+    unsafe {
+        logical_device.device().create_render_pass(
+            &RenderPassCreateInfo::default()
+                .attachments(&attachments)
+                .subpasses(&subpasses)
+                .dependencies(&dependencies),
+            host_allocation_callbacks(),
+        )
+    }
+    .context("creating batch render pass")
+}
+
+/// An offscreen color image with its framebuffer, readback buffer and the command buffer and
+/// fence of the frame rendered into it. Multisampled targets render into a separate image that's
+/// resolved into the color image at the end of the render pass.
+struct BatchTarget {
+    color: ColorImage,
+    multisampled: Option<ColorImage>,
+    framebuffer: vk::Framebuffer,
+    readback: vk::Buffer,
+    readback_memory: vk::DeviceMemory,
+    mapped: *const u8,
+    command_buffer: CommandBuffer,
+    fence: Fence,
+    readback_size: vk::DeviceSize,
+    /// Whether a frame was submitted and its fence wasn't seen signaled yet.
+    in_flight: Cell<bool>,
+
+    logical_device: LogicalDevice,
+}
+
+impl BatchTarget {
+    fn new(
+        logical_device: &LogicalDevice,
+        render_pass: vk::RenderPass,
+        format: Format,
+        extent: Extent2D,
+        samples: SampleCountFlags,
+        command_buffer: CommandBuffer,
+    ) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+        let physical_device = logical_device.physical_device();
+        let tracker = logical_device.resource_tracker();
+
+        let color = ColorImage::new(
+            logical_device,
+            format,
+            extent,
+            SampleCountFlags::TYPE_1,
+            ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSFER_SRC,
+        )?;
+
+        let multisampled = (samples != SampleCountFlags::TYPE_1)
+            .then(|| {
+                ColorImage::new(
+                    logical_device,
+                    format,
+                    extent,
+                    samples,
+                    ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSIENT_ATTACHMENT,
+                )
+            })
+            .transpose()?;
+
+        // Every handle starts null so a failure halfway only destroys what was created.
+        let mut target = Self {
+            color,
+            multisampled,
+            framebuffer: vk::Framebuffer::null(),
+            readback: vk::Buffer::null(),
+            readback_memory: vk::DeviceMemory::null(),
+            mapped: std::ptr::null(),
+            command_buffer,
+            fence: Fence::null(),
+            readback_size: 0,
+            in_flight: Cell::new(false),
+            logical_device: logical_device.clone(),
+        };
+
+        // The multisampled image is drawn to and resolved into the color image.
+        let attachments: Vec<_> = target
+            .multisampled
+            .iter()
+            .chain([&target.color])
+            .map(ColorImage::view)
+            .collect();
+
+        target.framebuffer = unsafe {
+            device.create_framebuffer(
+                &FramebufferCreateInfo::default()
+                    .render_pass(render_pass)
+                    .attachments(&attachments)
+                    .width(extent.width)
+                    .height(extent.height)
+                    .layers(1),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating batch framebuffer")?;
+
+        tracker.track(ResourceKind::Framebuffer, 1, 0);
+
+        target.readback = unsafe {
+            device.create_buffer(
+                &BufferCreateInfo::default()
+                    .size(extent.width as u64 * extent.height as u64 * 4)
+                    .usage(BufferUsageFlags::TRANSFER_DST)
+                    .sharing_mode(SharingMode::EXCLUSIVE),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating batch readback buffer")?;
+
+        let requirements = unsafe { device.get_buffer_memory_requirements(target.readback) };
+
+        target.readback_memory = physical_device
+            .find_memory_type(
+                requirements.memory_type_bits,
+                MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+            )
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            .and_then(|memory_type_index| unsafe {
+                device.allocate_memory(
+                    &MemoryAllocateInfo::default()
+                        .allocation_size(requirements.size)
+                        .memory_type_index(memory_type_index),
+                    host_allocation_callbacks(),
+                )
+            })
+            .context("allocating batch readback memory")?;
+
+        target.readback_size = requirements.size;
+        tracker.track(ResourceKind::Buffer, 1, requirements.size);
+
+        target.mapped = unsafe {
+            device
+                .bind_buffer_memory(target.readback, target.readback_memory, 0)
+                .and_then(|_| {
+                    device.map_memory(
+                        target.readback_memory,
+                        0,
+                        WHOLE_SIZE,
+                        MemoryMapFlags::empty(),
+                    )
+                })
+        }
+        .context("mapping batch readback memory")?
+        .cast();
+
+        target.fence = unsafe {
+            device.create_fence(&FenceCreateInfo::default(), host_allocation_callbacks())
+        }
+        .context("creating batch fence")?;
+
+        Ok(target)
+    }
+
+    /// Waits for the target's frame, if it's still in flight.
+    fn wait(&self) -> VkResult<()> {
+        if self.in_flight.get() {
+            unsafe {
+                self.logical_device
+                    .device()
+                    .wait_for_fences(&[self.fence], true, u64::MAX)
+            }?;
+
+            self.in_flight.set(false);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the target's frame finished, without waiting.
+    fn is_done(&self) -> VkResult<bool> {
+        if self.in_flight.get()
+            && !unsafe { self.logical_device.device().get_fence_status(self.fence) }?
+        {
+            return Ok(false);
+        }
+
+        self.in_flight.set(false);
+
+        Ok(true)
+    }
+
+    /// Copies the finished frame out of the readback buffer.
+    fn read(&self, extent: Extent2D, layout: PixelLayout) -> CapturedFrame {
+        let size = extent.width as usize * extent.height as usize * 4;
+
+        // SAFETY: the fence guarantees the copy finished, and the memory is coherent.
+        let data = unsafe { slice::from_raw_parts(self.mapped, size) }.to_vec();
+
+        CapturedFrame::new(extent.width, extent.height, layout, data)
+            .expect("the readback buffer holds exactly one frame")
+    }
+}
+
+impl Drop for BatchTarget {
+    fn drop(&mut self) {
+        teardown_trace::record(
+            "BatchTarget",
+            [self.framebuffer.as_raw(), self.readback.as_raw()],
+        );
+
+        let device = self.logical_device.device();
+        let tracker = self.logical_device.resource_tracker();
+
+        // Destroying and freeing null handles does nothing, only the tracking needs checks.
+        unsafe {
+            device.destroy_fence(self.fence, host_allocation_callbacks());
+
+            if !self.mapped.is_null() {
+                device.unmap_memory(self.readback_memory);
+            }
+
+            device.destroy_buffer(self.readback, host_allocation_callbacks());
+            device.free_memory(self.readback_memory, host_allocation_callbacks());
+
+            device.destroy_framebuffer(self.framebuffer, host_allocation_callbacks());
+        }
+
+        if self.readback_size > 0 {
+            tracker.untrack(ResourceKind::Buffer, 1, self.readback_size);
+        }
+
+        if self.framebuffer != vk::Framebuffer::null() {
+            tracker.untrack(ResourceKind::Framebuffer, 1, 0);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use ash::vk::{BlendFactor, BlendOp, ColorComponentFlags, PipelineColorBlendAttachmentState};
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    #[default]
+    Opaque,
+    AlphaBlend,
+    Additive,
+    PremultipliedAlpha,
+    Custom {
+        src_color: BlendFactor,
+        dst_color: BlendFactor,
+        color_op: BlendOp,
+        src_alpha: BlendFactor,
+        dst_alpha: BlendFactor,
+        alpha_op: BlendOp,
+    },
+}
+
+impl BlendMode {
+    pub fn attachment_state(&self) -> PipelineColorBlendAttachmentState {
+        let state = PipelineColorBlendAttachmentState::default()
+            .color_write_mask(ColorComponentFlags::RGBA);
+
+        let (src_color, dst_color, color_op, src_alpha, dst_alpha, alpha_op) = match *self {
+            Self::Opaque => return state.blend_enable(false),
+            Self::AlphaBlend => (
+                BlendFactor::SRC_ALPHA,
+                BlendFactor::ONE_MINUS_SRC_ALPHA,
+                BlendOp::ADD,
+                BlendFactor::ONE,
+                BlendFactor::ONE_MINUS_SRC_ALPHA,
+                BlendOp::ADD,
+            ),
+            Self::Additive => (
+                BlendFactor::SRC_ALPHA,
+                BlendFactor::ONE,
+                BlendOp::ADD,
+                BlendFactor::ONE,
+                BlendFactor::ONE,
+                BlendOp::ADD,
+            ),
+            Self::PremultipliedAlpha => (
+                BlendFactor::ONE,
+                BlendFactor::ONE_MINUS_SRC_ALPHA,
+                BlendOp::ADD,
+                BlendFactor::ONE,
+                BlendFactor::ONE_MINUS_SRC_ALPHA,
+                BlendOp::ADD,
+            ),
+            Self::Custom {
+                src_color,
+                dst_color,
+                color_op,
+                src_alpha,
+                dst_alpha,
+                alpha_op,
+            } => (
+                src_color, dst_color, color_op, src_alpha, dst_alpha, alpha_op,
+            ),
+        };
+
+        state
+            .blend_enable(true)
+            .src_color_blend_factor(src_color)
+            .dst_color_blend_factor(dst_color)
+            .color_blend_op(color_op)
+            .src_alpha_blend_factor(src_alpha)
+            .dst_alpha_blend_factor(dst_alpha)
+            .alpha_blend_op(alpha_op)
+    }
+}
@@ -0,0 +1,282 @@
+use std::{mem, rc::Rc, slice};
+
+use ash::vk::{
+    self, BufferCreateInfo, BufferUsageFlags, DeviceSize, Handle, IndexType, MemoryAllocateInfo,
+    MemoryMapFlags, MemoryPropertyFlags, SharingMode,
+};
+
+use crate::{
+    api2::{host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        logical_device::LogicalDevice,
+        resource_stats::ResourceKind,
+        staging_uploader::{StagingUploader, UploadError},
+        teardown_trace,
+    },
+    types::{slice_bytes, Pod},
+};
+
+/// A buffer with its own memory allocation.
+#[derive(Clone)]
+pub struct Buffer(Rc<InnerBuffer>);
+
+impl Buffer {
+    /// Creates a buffer of `size` bytes in memory with the given properties. Vulkan has no empty
+    /// buffers, so `size` has to be at least 1.
+    pub fn new(
+        logical_device: LogicalDevice,
+        size: DeviceSize,
+        usage: BufferUsageFlags,
+        properties: MemoryPropertyFlags,
+    ) -> Result<Self, ErrorCtx> {
+        if size == 0 {
+            return Err(
+                ErrorCtx::new("creating buffer", vk::Result::ERROR_INITIALIZATION_FAILED)
+                    .details(format!("size=0, usage={:?}", usage)),
+            );
+        }
+
+        let device = logical_device.device();
+
+        let buffer = unsafe {
+            device.create_buffer(
+                &BufferCreateInfo::default()
+                    .size(size)
+                    .usage(usage)
+                    .sharing_mode(SharingMode::EXCLUSIVE),
+                host_allocation_callbacks(),
+            )
+        }
+        .with_context("creating buffer", || {
+            format!("size={}, usage={:?}", size, usage)
+        })?;
+
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::Buffer, 1, requirements.size);
+
+        // From here on dropping the inner buffer cleans up, even if allocating fails.
+        let mut inner = InnerBuffer {
+            buffer,
+            memory: vk::DeviceMemory::null(),
+            size,
+            allocation_size: requirements.size,
+            properties,
+            logical_device: logical_device.clone(),
+        };
+
+        let memory_type_index = logical_device
+            .physical_device()
+            .find_memory_type(requirements.memory_type_bits, properties)
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            .with_context("finding buffer memory", || {
+                format!(
+                    "memory_type_bits={:#x}, properties={:?}",
+                    requirements.memory_type_bits, properties
+                )
+            })?;
+
+        inner.memory = unsafe {
+            device.allocate_memory(
+                &MemoryAllocateInfo::default()
+                    .allocation_size(requirements.size)
+                    .memory_type_index(memory_type_index),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("allocating buffer memory")?;
+
+        unsafe { device.bind_buffer_memory(buffer, inner.memory, 0) }
+            .context("binding buffer memory")?;
+
+        Ok(Self(Rc::new(inner)))
+    }
+
+    /// Creates a host-visible buffer holding `data`.
+    pub fn with_data<T: Pod>(
+        logical_device: LogicalDevice,
+        data: &[T],
+        usage: BufferUsageFlags,
+    ) -> Result<Self, ErrorCtx> {
+        let buffer = Self::new(
+            logical_device,
+            mem::size_of_val(data) as DeviceSize,
+            usage,
+            MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+
+        buffer.write(0, data)?;
+
+        Ok(buffer)
+    }
+
+    pub fn buffer(&self) -> &vk::Buffer {
+        &self.0.buffer
+    }
+
+    /// The size the buffer was created with, which its allocation may exceed.
+    pub fn size(&self) -> DeviceSize {
+        self.0.size
+    }
+
+    /// Maps the buffer and copies `data` to `offset` bytes into it. The buffer has to be
+    /// host-visible and coherent.
+    pub fn write<T: Pod>(&self, offset: DeviceSize, data: &[T]) -> Result<(), ErrorCtx> {
+        let size = mem::size_of_val(data) as DeviceSize;
+
+        let in_bounds = offset
+            .checked_add(size)
+            .is_some_and(|end| end <= self.0.size);
+
+        if !self
+            .0
+            .properties
+            .contains(MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT)
+            || !in_bounds
+        {
+            return Err(
+                ErrorCtx::new("writing buffer", vk::Result::ERROR_MEMORY_MAP_FAILED).details(
+                    format!(
+                        "offset={}, size={}, buffer_size={}, properties={:?}",
+                        offset, size, self.0.size, self.0.properties
+                    ),
+                ),
+            );
+        }
+
+        if size == 0 {
+            return Ok(());
+        }
+
+        let device = self.0.logical_device.device();
+
+        unsafe {
+            let mapped = device
+                .map_memory(self.0.memory, offset, size, MemoryMapFlags::empty())
+                .context("mapping buffer memory")?;
+
+            // SAFETY: the mapping is `size` bytes long and doesn't alias `data`.
+            slice::from_raw_parts_mut(mapped.cast::<u8>(), size as usize)
+                .copy_from_slice(slice_bytes(data));
+
+            device.unmap_memory(self.0.memory);
+        }
+
+        Ok(())
+    }
+
+    /// Maps the buffer and copies `size` bytes from `offset` bytes into it. The buffer has to be
+    /// host-visible and coherent.
+    pub fn read(&self, offset: DeviceSize, size: DeviceSize) -> Result<Vec<u8>, ErrorCtx> {
+        let in_bounds = offset
+            .checked_add(size)
+            .is_some_and(|end| end <= self.0.size);
+
+        if !self
+            .0
+            .properties
+            .contains(MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT)
+            || !in_bounds
+        {
+            return Err(
+                ErrorCtx::new("reading buffer", vk::Result::ERROR_MEMORY_MAP_FAILED).details(
+                    format!(
+                        "offset={}, size={}, buffer_size={}, properties={:?}",
+                        offset, size, self.0.size, self.0.properties
+                    ),
+                ),
+            );
+        }
+
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+
+        let device = self.0.logical_device.device();
+
+        unsafe {
+            let mapped = device
+                .map_memory(self.0.memory, offset, size, MemoryMapFlags::empty())
+                .context("mapping buffer memory")?;
+
+            // SAFETY: the mapping is `size` bytes long.
+            let data = slice::from_raw_parts(mapped.cast::<u8>(), size as usize).to_vec();
+
+            device.unmap_memory(self.0.memory);
+
+            Ok(data)
+        }
+    }
+}
+
+struct InnerBuffer {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    size: DeviceSize,
+    allocation_size: DeviceSize,
+    properties: MemoryPropertyFlags,
+    logical_device: LogicalDevice,
+}
+
+impl Drop for InnerBuffer {
+    fn drop(&mut self) {
+        teardown_trace::record("Buffer", [self.buffer.as_raw()]);
+
+        unsafe {
+            let device = self.logical_device.device();
+            device.destroy_buffer(self.buffer, host_allocation_callbacks());
+            device.free_memory(self.memory, host_allocation_callbacks());
+        }
+
+        self.logical_device.resource_tracker().untrack(
+            ResourceKind::Buffer,
+            1,
+            self.allocation_size,
+        );
+    }
+}
+
+/// An index type [IndexBuffer] holds.
+pub trait Index: Pod {
+    const INDEX_TYPE: IndexType;
+}
+
+impl Index for u16 {
+    const INDEX_TYPE: IndexType = IndexType::UINT16;
+}
+
+impl Index for u32 {
+    const INDEX_TYPE: IndexType = IndexType::UINT32;
+}
+
+/// A device-local buffer of 16 or 32-bit indices.
+#[derive(Clone)]
+pub struct IndexBuffer {
+    buffer: Buffer,
+    index_type: IndexType,
+    count: u32,
+}
+
+impl IndexBuffer {
+    pub fn new<I: Index>(uploader: &StagingUploader, indices: &[I]) -> Result<Self, UploadError> {
+        Ok(Self {
+            buffer: uploader.create_buffer(indices, BufferUsageFlags::INDEX_BUFFER)?,
+            index_type: I::INDEX_TYPE,
+            count: indices.len() as u32,
+        })
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub fn index_type(&self) -> IndexType {
+        self.index_type
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
@@ -0,0 +1,290 @@
+//! A minimal C ABI for embedding the renderer into C and C++ hosts, e.g. to run it next to the
+//! original C++ tutorial.
+//!
+//! The host owns the native window and reports size changes, the renderer owns everything
+//! Vulkan. `include/learnvulkan.h` is generated from this module with
+//! `cbindgen --config cbindgen.toml --output include/learnvulkan.h`.
+
+use std::{
+    error::Error,
+    ffi::c_void,
+    panic::{self, AssertUnwindSafe},
+    slice,
+    time::Duration,
+};
+
+use ash::{khr, vk};
+
+use crate::{
+    api2::host_allocation_callbacks,
+    renderer::{
+        instance::Instance, present_benchmark::PresentModeResult, renderer_config::RendererConfig,
+        Renderer, DEFAULT_FRAMES_IN_FLIGHT,
+    },
+};
+
+/// The windowing system a [LvNativeWindow] comes from.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LvWindowKind {
+    Xlib = 0,
+    Wayland = 1,
+    Win32 = 2,
+}
+
+/// A window created by the host.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct LvNativeWindow {
+    pub kind: LvWindowKind,
+    /// `Display*` for Xlib, `wl_display*` for Wayland and `HINSTANCE` for Win32.
+    pub display: *mut c_void,
+    /// `Window` for Xlib, `wl_surface*` for Wayland and `HWND` for Win32.
+    pub window: *mut c_void,
+    /// Framebuffer width in pixels.
+    pub width: u32,
+    /// Framebuffer height in pixels.
+    pub height: u32,
+}
+
+/// The outcome of every `lv_*` call.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LvResult {
+    Success = 0,
+    /// A required pointer was null.
+    InvalidArgument = 1,
+    /// A Vulkan call failed, the reason is printed to stderr.
+    Failed = 2,
+    /// The renderer panicked, it must not be used again except to destroy it.
+    Panicked = 3,
+}
+
+/// An opaque renderer handle.
+pub struct LvRenderer {
+    renderer: Renderer,
+}
+
+impl LvRenderer {
+    fn new(window: &LvNativeWindow) -> Result<Self, Box<dyn Error>> {
+        let platform_extension = match window.kind {
+            LvWindowKind::Xlib => khr::xlib_surface::NAME,
+            LvWindowKind::Wayland => khr::wayland_surface::NAME,
+            LvWindowKind::Win32 => khr::win32_surface::NAME,
+        };
+
+        let renderer = Renderer::builder()
+            .config(RendererConfig {
+                frames_in_flight: DEFAULT_FRAMES_IN_FLIGHT,
+                ..RendererConfig::minimal()
+            })
+            .build_with_surface(
+                [khr::surface::NAME, platform_extension]
+                    .iter()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .collect(),
+                (window.width, window.height),
+                |instance| create_surface(instance, window),
+            )?;
+
+        Ok(Self { renderer })
+    }
+}
+
+fn create_surface(
+    instance: &Instance,
+    window: &LvNativeWindow,
+) -> Result<vk::SurfaceKHR, Box<dyn Error>> {
+    let entry = instance.entry();
+    let instance = instance.instance();
+
+    let surface = match window.kind {
+        LvWindowKind::Xlib => unsafe {
+            khr::xlib_surface::Instance::new(entry, instance).create_xlib_surface(
+                &vk::XlibSurfaceCreateInfoKHR::default()
+                    .dpy(window.display)
+                    .window(window.window as vk::Window),
+                host_allocation_callbacks(),
+            )
+        },
+        LvWindowKind::Wayland => unsafe {
+            khr::wayland_surface::Instance::new(entry, instance).create_wayland_surface(
+                &vk::WaylandSurfaceCreateInfoKHR::default()
+                    .display(window.display)
+                    .surface(window.window),
+                host_allocation_callbacks(),
+            )
+        },
+        LvWindowKind::Win32 => unsafe {
+            khr::win32_surface::Instance::new(entry, instance).create_win32_surface(
+                &vk::Win32SurfaceCreateInfoKHR::default()
+                    .hinstance(window.display as vk::HINSTANCE)
+                    .hwnd(window.window as vk::HWND),
+                host_allocation_callbacks(),
+            )
+        },
+    }?;
+
+    Ok(surface)
+}
+
+/// Runs `f`, reporting errors on stderr and keeping panics from unwinding into the host.
+fn guard(f: impl FnOnce() -> Result<(), Box<dyn Error>>) -> LvResult {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => LvResult::Success,
+        Ok(Err(e)) => {
+            eprintln!("learnvulkan: {}", e);
+            LvResult::Failed
+        }
+        Err(_) => LvResult::Panicked,
+    }
+}
+
+/// Creates a renderer presenting to `window` and stores it in `renderer`.
+///
+/// # Safety
+///
+/// `window` must point to a valid [LvNativeWindow] whose handles outlive the renderer, and
+/// `renderer` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn lv_renderer_create(
+    window: *const LvNativeWindow,
+    renderer: *mut *mut LvRenderer,
+) -> LvResult {
+    if window.is_null() || renderer.is_null() {
+        return LvResult::InvalidArgument;
+    }
+
+    let window = *window;
+
+    guard(|| {
+        *renderer = Box::into_raw(Box::new(LvRenderer::new(&window)?));
+        Ok(())
+    })
+}
+
+/// Tells the renderer the window's framebuffer is now `width` by `height` pixels. A zero size
+/// pauses rendering until the next resize.
+///
+/// # Safety
+///
+/// `renderer` must come from [lv_renderer_create] and not be destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn lv_renderer_resize(
+    renderer: *mut LvRenderer,
+    width: u32,
+    height: u32,
+) -> LvResult {
+    let Some(renderer) = renderer.as_mut() else {
+        return LvResult::InvalidArgument;
+    };
+
+    guard(|| {
+        renderer.renderer.resize(width, height);
+        Ok(())
+    })
+}
+
+/// Renders and presents one frame. Does nothing while the window has no area, e.g. when it's
+/// minimized.
+///
+/// # Safety
+///
+/// `renderer` must come from [lv_renderer_create] and not be destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn lv_renderer_draw_frame(renderer: *mut LvRenderer) -> LvResult {
+    let Some(renderer) = renderer.as_mut() else {
+        return LvResult::InvalidArgument;
+    };
+
+    guard(|| renderer.renderer.draw_frame())
+}
+
+/// How a present mode did in [lv_renderer_benchmark_present_modes].
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct LvPresentModeResult {
+    /// The `VkPresentModeKHR` value.
+    pub present_mode: i32,
+    pub frames: u32,
+    pub fps: f64,
+    pub mean_frame_time_ms: f64,
+    /// How much frame times jitter, in ms².
+    pub frame_time_variance_ms2: f64,
+    pub estimated_latency_ms: f64,
+}
+
+impl From<&PresentModeResult> for LvPresentModeResult {
+    fn from(result: &PresentModeResult) -> Self {
+        Self {
+            present_mode: result.present_mode.as_raw(),
+            frames: result.frames,
+            fps: result.fps,
+            mean_frame_time_ms: result.mean_frame_time.as_secs_f64() * 1000.0,
+            frame_time_variance_ms2: result.frame_time_variance,
+            estimated_latency_ms: result.estimated_latency.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+/// Renders with every supported present mode for `seconds_per_mode` each, blocking the caller,
+/// and writes up to `capacity` results to `results`. `count` receives the number of modes
+/// measured, which may exceed `capacity`.
+///
+/// # Safety
+///
+/// `renderer` must come from [lv_renderer_create] and not be destroyed yet, `results` must be
+/// valid for `capacity` writes, or null if `capacity` is zero, and `count` must be valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn lv_renderer_benchmark_present_modes(
+    renderer: *mut LvRenderer,
+    seconds_per_mode: f64,
+    results: *mut LvPresentModeResult,
+    capacity: usize,
+    count: *mut usize,
+) -> LvResult {
+    let Some(renderer) = renderer.as_mut() else {
+        return LvResult::InvalidArgument;
+    };
+
+    if count.is_null()
+        || (results.is_null() && capacity > 0)
+        || !seconds_per_mode.is_finite()
+        || seconds_per_mode < 0.0
+    {
+        return LvResult::InvalidArgument;
+    }
+
+    guard(|| {
+        let report = renderer
+            .renderer
+            .benchmark_present_modes(Duration::from_secs_f64(seconds_per_mode))?;
+
+        if capacity > 0 {
+            let results = slice::from_raw_parts_mut(results, capacity);
+
+            for (out, result) in results.iter_mut().zip(&report.results) {
+                *out = result.into();
+            }
+        }
+
+        *count = report.results.len();
+
+        Ok(())
+    })
+}
+
+/// Waits for the GPU and destroys the renderer. Passing null does nothing.
+///
+/// # Safety
+///
+/// `renderer` must be null or come from [lv_renderer_create] and not be destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn lv_renderer_destroy(renderer: *mut LvRenderer) {
+    if renderer.is_null() {
+        return;
+    }
+
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(renderer))));
+}
@@ -0,0 +1,164 @@
+use ash::vk::{
+    self, Extent2D, Extent3D, Format, Handle, ImageAspectFlags, ImageCreateInfo, ImageLayout,
+    ImageSubresourceRange, ImageTiling, ImageType, ImageUsageFlags, ImageViewCreateInfo,
+    ImageViewType, MemoryAllocateInfo, MemoryPropertyFlags, SampleCountFlags, SharingMode,
+};
+
+use crate::{
+    api2::{host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{logical_device::LogicalDevice, resource_stats::ResourceKind, teardown_trace},
+};
+
+/// A single-mip 2D color image with its memory and a view of all its layers, e.g. a render
+/// target.
+pub struct ColorImage {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+    size: vk::DeviceSize,
+
+    logical_device: LogicalDevice,
+}
+
+impl ColorImage {
+    pub fn new(
+        logical_device: &LogicalDevice,
+        format: Format,
+        extent: Extent2D,
+        samples: SampleCountFlags,
+        usage: ImageUsageFlags,
+    ) -> Result<Self, ErrorCtx> {
+        Self::layered(logical_device, format, extent, samples, usage, 1)
+    }
+
+    /// Creates an image of `layers` layers, viewed as a 2D array when there are more than one,
+    /// e.g. the target of a multiview render pass.
+    pub fn layered(
+        logical_device: &LogicalDevice,
+        format: Format,
+        extent: Extent2D,
+        samples: SampleCountFlags,
+        usage: ImageUsageFlags,
+        layers: u32,
+    ) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+        let tracker = logical_device.resource_tracker();
+
+        let mut color_image = Self {
+            image: vk::Image::null(),
+            memory: vk::DeviceMemory::null(),
+            view: vk::ImageView::null(),
+            size: 0,
+            logical_device: logical_device.clone(),
+        };
+
+        color_image.image = unsafe {
+            device.create_image(
+                &ImageCreateInfo::default()
+                    .image_type(ImageType::TYPE_2D)
+                    .format(format)
+                    .extent(Extent3D {
+                        width: extent.width,
+                        height: extent.height,
+                        depth: 1,
+                    })
+                    .mip_levels(1)
+                    .array_layers(layers)
+                    .samples(samples)
+                    .tiling(ImageTiling::OPTIMAL)
+                    .usage(usage)
+                    .sharing_mode(SharingMode::EXCLUSIVE)
+                    .initial_layout(ImageLayout::UNDEFINED),
+                host_allocation_callbacks(),
+            )
+        }
+        .with_context("creating color image", || {
+            format!(
+                "format={:?}, extent={:?}, samples={:?}, layers={}",
+                format, extent, samples, layers
+            )
+        })?;
+
+        let requirements = unsafe { device.get_image_memory_requirements(color_image.image) };
+
+        color_image.memory = logical_device
+            .physical_device()
+            .find_memory_type(
+                requirements.memory_type_bits,
+                MemoryPropertyFlags::DEVICE_LOCAL,
+            )
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            .and_then(|memory_type_index| unsafe {
+                device.allocate_memory(
+                    &MemoryAllocateInfo::default()
+                        .allocation_size(requirements.size)
+                        .memory_type_index(memory_type_index),
+                    host_allocation_callbacks(),
+                )
+            })
+            .context("allocating color image memory")?;
+
+        color_image.size = requirements.size;
+        tracker.track(ResourceKind::Image, 1, requirements.size);
+
+        let view_type = if layers > 1 {
+            ImageViewType::TYPE_2D_ARRAY
+        } else {
+            ImageViewType::TYPE_2D
+        };
+
+        color_image.view =
+            unsafe { device.bind_image_memory(color_image.image, color_image.memory, 0) }
+                .and_then(|_| unsafe {
+                    device.create_image_view(
+                        &ImageViewCreateInfo::default()
+                            .image(color_image.image)
+                            .view_type(view_type)
+                            .format(format)
+                            .subresource_range(
+                                ImageSubresourceRange::default()
+                                    .aspect_mask(ImageAspectFlags::COLOR)
+                                    .level_count(1)
+                                    .layer_count(layers),
+                            ),
+                        host_allocation_callbacks(),
+                    )
+                })
+                .context("creating color image view")?;
+
+        tracker.track(ResourceKind::ImageView, 1, 0);
+
+        Ok(color_image)
+    }
+
+    pub fn image(&self) -> vk::Image {
+        self.image
+    }
+
+    pub fn view(&self) -> vk::ImageView {
+        self.view
+    }
+}
+
+impl Drop for ColorImage {
+    fn drop(&mut self) {
+        teardown_trace::record("ColorImage", [self.image.as_raw()]);
+
+        let device = self.logical_device.device();
+        let tracker = self.logical_device.resource_tracker();
+
+        unsafe {
+            device.destroy_image_view(self.view, host_allocation_callbacks());
+            device.destroy_image(self.image, host_allocation_callbacks());
+            device.free_memory(self.memory, host_allocation_callbacks());
+        }
+
+        if self.view != vk::ImageView::null() {
+            tracker.untrack(ResourceKind::ImageView, 1, 0);
+        }
+
+        if self.size > 0 {
+            tracker.untrack(ResourceKind::Image, 1, self.size);
+        }
+    }
+}
@@ -0,0 +1,1274 @@
+use std::{
+    cell::{Cell, RefCell},
+    error, fmt, mem,
+    rc::Rc,
+};
+
+use ash::{
+    prelude::VkResult,
+    vk::{
+        self, AccessFlags, ClearAttachment, ClearColorValue, ClearRect, ClearValue, CommandBuffer,
+        CommandBufferAllocateInfo, CommandBufferBeginInfo, CommandBufferLevel, DependencyFlags,
+        Extent2D, Format, Image, ImageAspectFlags, ImageLayout, ImageMemoryBarrier,
+        ImageSubresourceRange, ImageUsageFlags, Offset2D, PipelineBindPoint, PipelineStageFlags,
+        Rect2D, RenderPassBeginInfo, ShaderStageFlags, SubpassContents, REMAINING_ARRAY_LAYERS,
+        REMAINING_MIP_LEVELS,
+    },
+    Device,
+};
+
+use crate::{
+    api2::Color,
+    renderer::{
+        buffer::{Buffer, IndexBuffer},
+        command_pool::CommandPool,
+        command_trace::{AttachmentClear, CommandTrace, RecordedCommand, TraceError},
+        crash_dump::CrashDiagnostics,
+        framebuffers::Framebuffers,
+        graphics_pipeline::GraphicsPipeline,
+        present_transfer,
+        submit_trace::SubmitTracer,
+        vertex::Vertex,
+        MAX_FRAMES_IN_FLIGHT,
+    },
+    types::Pod,
+};
+
+#[derive(Clone)]
+pub struct CommandBuffers(Rc<InnerCommandBuffers>);
+
+impl CommandBuffers {
+    /// Creates the command buffers drawing the [Vertex] list in `vertex_buffer`, indexed by
+    /// `index_buffer` when there's one.
+    pub fn new(
+        command_pool: CommandPool,
+        framebuffers: Framebuffers,
+        graphics_pipeline: GraphicsPipeline,
+        vertex_buffer: Buffer,
+        index_buffer: Option<IndexBuffer>,
+    ) -> VkResult<Self> {
+        let command_buffer_alloc_info = CommandBufferAllocateInfo::default()
+            .command_pool(*command_pool.command_pool())
+            .level(CommandBufferLevel::PRIMARY)
+            .command_buffer_count(MAX_FRAMES_IN_FLIGHT as u32);
+
+        let command_buffers = unsafe {
+            command_pool
+                .logical_device()
+                .device()
+                .allocate_command_buffers(&command_buffer_alloc_info)?
+        };
+
+        Ok(Self(Rc::new(InnerCommandBuffers {
+            command_buffers,
+            command_pool,
+            framebuffers,
+            graphics_pipeline,
+            vertex_buffer,
+            index_buffer,
+            trace: RefCell::new(None),
+            submit_tracer: RefCell::new(None),
+            crash_diagnostics: RefCell::new(None),
+            clear_color: Cell::new(Color::BLACK),
+            push_constants: RefCell::new(Vec::new()),
+            image_layouts: RefCell::new(vec![Vec::new(); MAX_FRAMES_IN_FLIGHT]),
+        })))
+    }
+
+    pub fn command_buffers(&self) -> &[CommandBuffer] {
+        &self.0.command_buffers
+    }
+
+    pub fn reset(&self) -> VkResult<()> {
+        self.reset_buffer(0)
+    }
+
+    pub fn reset_buffer(&self, command_buffer_index: usize) -> VkResult<()> {
+        let command_buffer = self.0.command_buffers[command_buffer_index];
+
+        let command_buffer_reset_flags = Default::default();
+
+        unsafe {
+            self.0
+                .command_pool
+                .logical_device()
+                .device()
+                .reset_command_buffer(command_buffer, command_buffer_reset_flags)
+        }
+    }
+
+    pub fn clear_color(&self) -> Color {
+        self.0.clear_color.get()
+    }
+
+    pub fn set_clear_color(&self, color: Color) {
+        self.0.clear_color.set(color);
+    }
+
+    /// Sets the push constants at `offset` for `stages`, pushed after binding the pipeline by
+    /// every following [CommandBuffers::record]. They have to fall into one of the
+    /// [GraphicsPipeline::push_constant_ranges], which is checked when recording.
+    pub fn push_constants<T: Pod>(&self, stages: ShaderStageFlags, offset: u32, value: &T) {
+        let command = RecordedCommand::PushConstants {
+            stages,
+            offset,
+            data: value.bytes_of().to_vec(),
+        };
+
+        let mut push_constants = self.0.push_constants.borrow_mut();
+
+        let existing = push_constants.iter_mut().find(|pushed| {
+            matches!(pushed, RecordedCommand::PushConstants { stages: s, offset: o, .. }
+                if *s == stages && *o == offset)
+        });
+
+        match existing {
+            Some(pushed) => *pushed = command,
+            None => push_constants.push(command),
+        }
+    }
+
+    /// Stops pushing the constants set with [CommandBuffers::push_constants].
+    pub fn clear_push_constants(&self) {
+        self.0.push_constants.borrow_mut().clear();
+    }
+
+    pub fn start_trace(&self) {
+        *self.0.trace.borrow_mut() = Some(CommandTrace::default());
+    }
+
+    pub fn take_trace(&self) -> Option<CommandTrace> {
+        self.0.trace.borrow_mut().take()
+    }
+
+    /// Keeps appending to `trace`, e.g. one taken from the command buffers these replace.
+    pub fn resume_trace(&self, trace: CommandTrace) {
+        *self.0.trace.borrow_mut() = Some(trace);
+    }
+
+    /// Makes every recorded command buffer write the tracer's timestamps, using the command
+    /// buffer index as the slot.
+    pub fn set_submit_tracer(&self, tracer: Option<SubmitTracer>) {
+        *self.0.submit_tracer.borrow_mut() = tracer;
+    }
+
+    /// Makes every recorded command buffer set checkpoints around its draws, using the command
+    /// buffer index as the slot.
+    pub fn set_crash_diagnostics(&self, diagnostics: Option<CrashDiagnostics>) {
+        *self.0.crash_diagnostics.borrow_mut() = diagnostics;
+    }
+
+    pub fn record(
+        &self,
+        command_buffer_index: usize,
+        image_index: usize,
+        pipeline_index: usize,
+        viewport_index: u32,
+        scissor_index: u32,
+    ) -> Result<(), CommandError> {
+        debug_assert!(
+            self.0
+                .framebuffers
+                .render_pass()
+                .swapchain()
+                .image(image_index as u32)
+                .is_acquired(),
+            "recording for swapchain image {} without acquiring it",
+            image_index
+        );
+
+        let mut commands = vec![
+            RecordedCommand::BeginRenderPass {
+                image_index,
+                extent: self.0.framebuffers.render_pass().swapchain().extent(),
+                // Both the multisampled target and the resolve attachment of MSAA passes.
+                clear_values: vec![
+                    AttachmentClear::ColorFloat(
+                        self.0
+                            .framebuffers
+                            .render_pass()
+                            .swapchain()
+                            .encode_color(self.0.clear_color.get()),
+                    );
+                    self.0.framebuffers.render_pass().attachment_formats().len()
+                ],
+            },
+            RecordedCommand::SetViewport {
+                first: viewport_index,
+                viewports: self.0.graphics_pipeline.viewports().to_vec(),
+            },
+            RecordedCommand::SetScissor {
+                first: scissor_index,
+                scissors: self.0.graphics_pipeline.scissors().to_vec(),
+            },
+            RecordedCommand::BindPipeline { pipeline_index },
+            RecordedCommand::BindVertexBuffer,
+        ];
+
+        commands.extend(self.0.push_constants.borrow().iter().cloned());
+
+        match &self.0.index_buffer {
+            Some(index_buffer) => commands.extend([
+                RecordedCommand::BindIndexBuffer,
+                RecordedCommand::DrawIndexed {
+                    index_count: index_buffer.count(),
+                    instance_count: 1,
+                    first_index: 0,
+                    vertex_offset: 0,
+                    first_instance: 0,
+                },
+            ]),
+            None => commands.push(RecordedCommand::Draw {
+                vertex_count: (self.0.vertex_buffer.size() / mem::size_of::<Vertex>() as u64)
+                    as u32,
+                instance_count: 1,
+                first_vertex: 0,
+                first_instance: 0,
+            }),
+        }
+
+        commands.push(RecordedCommand::EndRenderPass);
+
+        if self
+            .0
+            .framebuffers
+            .render_pass()
+            .swapchain()
+            .ownership_transfer()
+            .is_some()
+        {
+            commands.push(RecordedCommand::ReleaseToPresent { image_index });
+        }
+
+        self.record_commands(command_buffer_index, &commands)
+    }
+
+    /// Starts a list of commands for [CommandBuffers::record_commands], checked as they're added.
+    pub fn encoder(&self) -> CommandEncoder {
+        CommandEncoder {
+            command_buffers: self.clone(),
+            commands: Vec::new(),
+            state: ValidationState::default(),
+        }
+    }
+
+    /// The layout the commands last recorded into the command buffer at `command_buffer_index`
+    /// leave the swapchain image `image_index` in, `None` when they don't use it. Render passes
+    /// leave it in `PRESENT_SRC_KHR`, [RecordedCommand::ClearColorImage] in its layout.
+    pub fn image_layout(
+        &self,
+        command_buffer_index: usize,
+        image_index: usize,
+    ) -> Option<ImageLayout> {
+        self.0.image_layouts.borrow()[command_buffer_index]
+            .get(image_index)
+            .copied()
+            .flatten()
+    }
+
+    pub fn record_commands(
+        &self,
+        command_buffer_index: usize,
+        commands: &[RecordedCommand],
+    ) -> Result<(), CommandError> {
+        self.execute(command_buffer_index, commands)?;
+
+        if let Some(trace) = self.0.trace.borrow_mut().as_mut() {
+            trace.frames.push(commands.to_vec());
+        }
+
+        Ok(())
+    }
+
+    /// Records a frame of a [CommandTrace] for the swapchain image `image_index`, whichever image
+    /// the frame was traced with. Fails when the frame doesn't fit these command buffers, e.g.
+    /// it binds a pipeline variant or draws indices they don't have.
+    pub fn replay(
+        &self,
+        command_buffer_index: usize,
+        image_index: usize,
+        frame: &[RecordedCommand],
+    ) -> Result<(), TraceError> {
+        let mut commands = frame.to_vec();
+        commands
+            .iter_mut()
+            .for_each(|command| command.retarget(image_index));
+
+        self.execute(command_buffer_index, &commands)
+            .map_err(TraceError::from)
+    }
+
+    fn validate(&self, commands: &[RecordedCommand]) -> Result<(), CommandError> {
+        let mut state = ValidationState::default();
+
+        for command in commands {
+            self.check(&mut state, command)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks `command` can follow the commands `state` was updated with, and updates it.
+    fn check(
+        &self,
+        state: &mut ValidationState,
+        command: &RecordedCommand,
+    ) -> Result<(), CommandError> {
+        match command {
+            RecordedCommand::BeginRenderPass {
+                image_index,
+                extent,
+                clear_values,
+            } => {
+                self.check_image_index(*image_index)?;
+
+                let swapchain_extent = self.0.framebuffers.render_pass().swapchain().extent();
+
+                if extent.width > swapchain_extent.width || extent.height > swapchain_extent.height
+                {
+                    return Err(CommandError::RenderAreaOutOfBounds(*extent));
+                }
+
+                let formats = self.0.framebuffers.render_pass().attachment_formats();
+
+                if clear_values.len() != formats.len() {
+                    return Err(CommandError::ClearValueCount {
+                        expected: formats.len(),
+                        found: clear_values.len(),
+                    });
+                }
+
+                for (attachment, (clear_value, format)) in
+                    clear_values.iter().zip(formats).enumerate()
+                {
+                    if !clear_value_matches(clear_value, *format) {
+                        return Err(CommandError::ClearValueMismatch {
+                            attachment,
+                            format: *format,
+                        });
+                    }
+                }
+
+                state.render_area = Some(*extent);
+            }
+            RecordedCommand::EndRenderPass => {
+                if !state.scissors.is_empty() {
+                    return Err(CommandError::UnbalancedScissorStack);
+                }
+
+                state.render_area = None;
+            }
+            RecordedCommand::ClearAttachments {
+                attachment, rects, ..
+            } => {
+                let extent = state.render_area.ok_or(CommandError::OutsideRenderPass)?;
+
+                let available = self.0.framebuffers.render_pass().color_attachment_count();
+
+                if *attachment >= available {
+                    return Err(CommandError::AttachmentOutOfRange {
+                        index: *attachment,
+                        available,
+                    });
+                }
+
+                if rects.is_empty() {
+                    return Err(CommandError::EmptyClearRects);
+                }
+
+                if !rects.iter().all(|rect| rect_within(rect, extent)) {
+                    return Err(CommandError::ClearRectOutOfBounds);
+                }
+            }
+            RecordedCommand::PushScissor { rect } => {
+                let render_area = state.render_area.ok_or(CommandError::OutsideRenderPass)?;
+
+                if !rect_within(rect, self.0.framebuffers.render_pass().swapchain().extent()) {
+                    return Err(CommandError::ScissorOutOfBounds);
+                }
+
+                let top = state
+                    .scissors
+                    .last()
+                    .copied()
+                    .unwrap_or(Rect2D::default().extent(render_area));
+
+                state.scissors.push(intersect_rects(&top, rect));
+            }
+            RecordedCommand::PopScissor => {
+                if state.render_area.is_none() {
+                    return Err(CommandError::OutsideRenderPass);
+                }
+
+                if state.scissors.pop().is_none() {
+                    return Err(CommandError::UnbalancedScissorStack);
+                }
+            }
+            RecordedCommand::BindIndexBuffer => {
+                if self.0.index_buffer.is_none() {
+                    return Err(CommandError::NoIndexBuffer);
+                }
+
+                state.index_buffer_bound = true;
+            }
+            RecordedCommand::DrawIndexed {
+                index_count,
+                first_index,
+                ..
+            } => {
+                let index_buffer = match &self.0.index_buffer {
+                    Some(index_buffer) if state.index_buffer_bound => index_buffer,
+                    _ => return Err(CommandError::NoIndexBuffer),
+                };
+
+                if first_index
+                    .checked_add(*index_count)
+                    .map_or(true, |end| end > index_buffer.count())
+                {
+                    return Err(CommandError::IndexRangeOutOfBounds {
+                        first: *first_index,
+                        count: *index_count,
+                        available: index_buffer.count(),
+                    });
+                }
+            }
+            RecordedCommand::PushConstants {
+                stages,
+                offset,
+                data,
+            } => {
+                let size = data.len() as u32;
+
+                let in_range = !data.is_empty()
+                    && offset % 4 == 0
+                    && size % 4 == 0
+                    && self
+                        .0
+                        .graphics_pipeline
+                        .push_constant_ranges()
+                        .iter()
+                        .any(|range| {
+                            range.stage_flags.contains(*stages)
+                                && *offset >= range.offset
+                                && *offset as u64 + size as u64
+                                    <= range.offset as u64 + range.size as u64
+                        });
+
+                if !in_range {
+                    return Err(CommandError::PushConstantsOutOfRange {
+                        stages: *stages,
+                        offset: *offset,
+                        size,
+                    });
+                }
+            }
+            RecordedCommand::BindPipeline { pipeline_index } => {
+                let available = self.0.graphics_pipeline.pipeline().len();
+
+                if *pipeline_index >= available {
+                    return Err(CommandError::PipelineIndexOutOfRange {
+                        index: *pipeline_index,
+                        available,
+                    });
+                }
+            }
+            RecordedCommand::ReleaseToPresent { image_index } => {
+                if state.render_area.is_some() {
+                    return Err(CommandError::InsideRenderPass);
+                }
+
+                self.check_image_index(*image_index)?;
+            }
+            RecordedCommand::ClearColorImage {
+                image_index,
+                layout,
+                ..
+            } => {
+                if state.render_area.is_some() {
+                    return Err(CommandError::InsideRenderPass);
+                }
+
+                self.check_image_index(*image_index)?;
+
+                if ![
+                    ImageLayout::GENERAL,
+                    ImageLayout::TRANSFER_DST_OPTIMAL,
+                    ImageLayout::SHARED_PRESENT_KHR,
+                ]
+                .contains(layout)
+                {
+                    return Err(CommandError::InvalidClearLayout(*layout));
+                }
+
+                // Cleared by a render pass, which can't leave it in a transfer layout.
+                let transfer_dst = self
+                    .0
+                    .framebuffers
+                    .render_pass()
+                    .swapchain()
+                    .image_usage()
+                    .contains(ImageUsageFlags::TRANSFER_DST);
+
+                if !transfer_dst && *layout == ImageLayout::TRANSFER_DST_OPTIMAL {
+                    return Err(CommandError::InvalidClearLayout(*layout));
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Checks there's a swapchain image, and a framebuffer for it, at `index`.
+    fn check_image_index(&self, index: usize) -> Result<(), CommandError> {
+        let available = self
+            .0
+            .framebuffers
+            .framebuffers()
+            .len()
+            .min(self.0.framebuffers.render_pass().swapchain().images().len());
+
+        if index >= available {
+            return Err(CommandError::ImageIndexOutOfRange { index, available });
+        }
+
+        Ok(())
+    }
+
+    fn execute(
+        &self,
+        command_buffer_index: usize,
+        commands: &[RecordedCommand],
+    ) -> Result<(), CommandError> {
+        self.validate(commands)?;
+
+        let device = self.0.command_pool.logical_device().device();
+        let command_buffer = self.0.command_buffers[command_buffer_index];
+
+        let command_buffer_begin_info = CommandBufferBeginInfo::default();
+
+        // Each entry is the intersection of the pushed rect with every rect below it, the bottom
+        // being the full render area.
+        let mut scissor_stack: Vec<Rect2D> = Vec::new();
+
+        let swapchain = self.0.framebuffers.render_pass().swapchain();
+
+        // The layout the commands so far leave every swapchain image in, `None` for those they
+        // don't use, which are in the layout of the last submission.
+        let mut layouts: Vec<Option<ImageLayout>> = vec![None; swapchain.images().len()];
+        let current_layout = |layouts: &[Option<ImageLayout>], image_index: usize| {
+            layouts[image_index].unwrap_or_else(|| swapchain.images()[image_index].layout())
+        };
+
+        unsafe {
+            device
+                .begin_command_buffer(command_buffer, &command_buffer_begin_info)
+                .map_err(CommandError::from)?;
+        }
+
+        if let Some(tracer) = self.0.submit_tracer.borrow().as_ref() {
+            tracer.write_begin(command_buffer, command_buffer_index);
+        }
+
+        let crash_diagnostics = self.0.crash_diagnostics.borrow().clone();
+        let checkpoint = |name| {
+            if let Some(diagnostics) = &crash_diagnostics {
+                diagnostics.checkpoint(command_buffer, command_buffer_index, name);
+            }
+        };
+
+        checkpoint("begin");
+
+        for command in commands {
+            match command {
+                RecordedCommand::BeginRenderPass {
+                    image_index,
+                    extent,
+                    clear_values,
+                } => {
+                    let clear_values: Vec<_> =
+                        clear_values.iter().map(AttachmentClear::to_vk).collect();
+
+                    // The render pass only waits for earlier color attachment writes, not for
+                    // clears outside of it.
+                    let layout = current_layout(&layouts, *image_index);
+
+                    if layouts[*image_index].is_some() && layout != ImageLayout::PRESENT_SRC_KHR {
+                        unsafe {
+                            transition(
+                                device,
+                                command_buffer,
+                                swapchain.images()[*image_index].image(),
+                                layout,
+                                layout,
+                            );
+                        }
+                    }
+
+                    layouts[*image_index] = Some(ImageLayout::PRESENT_SRC_KHR);
+
+                    scissor_stack.clear();
+                    scissor_stack.push(Rect2D::default().extent(*extent));
+
+                    let render_pass_info = RenderPassBeginInfo::default()
+                        .render_pass(*self.0.framebuffers.render_pass().render_pass())
+                        .framebuffer(self.0.framebuffers.framebuffers()[*image_index])
+                        .render_area(
+                            Rect2D::default()
+                                .extent(*extent)
+                                .offset(Offset2D::default()),
+                        )
+                        .clear_values(&clear_values);
+
+                    unsafe {
+                        device.cmd_begin_render_pass(
+                            command_buffer,
+                            &render_pass_info,
+                            SubpassContents::INLINE,
+                        );
+                    }
+                }
+                RecordedCommand::SetViewport { first, viewports } => unsafe {
+                    device.cmd_set_viewport(command_buffer, *first, viewports);
+                },
+                RecordedCommand::SetScissor { first, scissors } => unsafe {
+                    device.cmd_set_scissor(command_buffer, *first, scissors);
+                },
+                RecordedCommand::BindPipeline { pipeline_index } => unsafe {
+                    device.cmd_bind_pipeline(
+                        command_buffer,
+                        PipelineBindPoint::GRAPHICS,
+                        self.0.graphics_pipeline.pipeline()[*pipeline_index],
+                    );
+                },
+                RecordedCommand::BindVertexBuffer => unsafe {
+                    device.cmd_bind_vertex_buffers(
+                        command_buffer,
+                        0,
+                        &[*self.0.vertex_buffer.buffer()],
+                        &[0],
+                    );
+                },
+                RecordedCommand::BindIndexBuffer => {
+                    // validate() guarantees there's an index buffer.
+                    let Some(index_buffer) = &self.0.index_buffer else {
+                        return Err(CommandError::NoIndexBuffer);
+                    };
+
+                    unsafe {
+                        device.cmd_bind_index_buffer(
+                            command_buffer,
+                            *index_buffer.buffer().buffer(),
+                            0,
+                            index_buffer.index_type(),
+                        );
+                    }
+                }
+                RecordedCommand::PushConstants {
+                    stages,
+                    offset,
+                    data,
+                } => unsafe {
+                    device.cmd_push_constants(
+                        command_buffer,
+                        self.0.graphics_pipeline.pipeline_layout(),
+                        *stages,
+                        *offset,
+                        data,
+                    );
+                },
+                RecordedCommand::DrawIndexed {
+                    index_count,
+                    instance_count,
+                    first_index,
+                    vertex_offset,
+                    first_instance,
+                } => unsafe {
+                    checkpoint("draw indexed");
+                    device.cmd_draw_indexed(
+                        command_buffer,
+                        *index_count,
+                        *instance_count,
+                        *first_index,
+                        *vertex_offset,
+                        *first_instance,
+                    );
+                },
+                RecordedCommand::Draw {
+                    vertex_count,
+                    instance_count,
+                    first_vertex,
+                    first_instance,
+                } => unsafe {
+                    checkpoint("draw");
+                    device.cmd_draw(
+                        command_buffer,
+                        *vertex_count,
+                        *instance_count,
+                        *first_vertex,
+                        *first_instance,
+                    );
+                },
+                RecordedCommand::EndRenderPass => unsafe {
+                    scissor_stack.clear();
+                    device.cmd_end_render_pass(command_buffer);
+                },
+                RecordedCommand::PushScissor { rect } => {
+                    // validate() guarantees a render pass, and so the render area, is on the stack.
+                    let Some(top) = scissor_stack.last() else {
+                        return Err(CommandError::OutsideRenderPass);
+                    };
+
+                    let clipped = intersect_rects(top, rect);
+                    scissor_stack.push(clipped);
+
+                    unsafe {
+                        device.cmd_set_scissor(command_buffer, 0, &[clipped]);
+                    }
+                }
+                RecordedCommand::PopScissor => {
+                    scissor_stack.pop();
+
+                    let Some(top) = scissor_stack.last() else {
+                        return Err(CommandError::UnbalancedScissorStack);
+                    };
+
+                    unsafe {
+                        device.cmd_set_scissor(command_buffer, 0, &[*top]);
+                    }
+                }
+                RecordedCommand::ClearAttachments {
+                    attachment,
+                    clear_color,
+                    rects,
+                } => {
+                    let attachments = [ClearAttachment {
+                        aspect_mask: ImageAspectFlags::COLOR,
+                        color_attachment: *attachment,
+                        clear_value: ClearValue {
+                            color: ClearColorValue {
+                                float32: *clear_color,
+                            },
+                        },
+                    }];
+
+                    // Multiview render passes clear every view with the framebuffer's one layer.
+                    let clear_rects: Vec<_> = rects
+                        .iter()
+                        .map(|rect| ClearRect {
+                            rect: *rect,
+                            base_array_layer: 0,
+                            layer_count: self.0.framebuffers.layers(),
+                        })
+                        .collect();
+
+                    unsafe {
+                        device.cmd_clear_attachments(command_buffer, &attachments, &clear_rects);
+                    }
+                }
+                RecordedCommand::ClearColorImage {
+                    image_index,
+                    layout,
+                    clear_color,
+                } => {
+                    let image = swapchain.images()[*image_index].image();
+                    let old_layout = current_layout(&layouts, *image_index);
+
+                    // Without TRANSFER_DST the image can only be written as an attachment, so an
+                    // empty pass of the render pass, which clears on load, clears it instead.
+                    if !swapchain
+                        .image_usage()
+                        .contains(ImageUsageFlags::TRANSFER_DST)
+                    {
+                        let clear_values =
+                            vec![
+                                ClearValue {
+                                    color: ClearColorValue {
+                                        float32: *clear_color,
+                                    },
+                                };
+                                self.0.framebuffers.render_pass().attachment_formats().len()
+                            ];
+
+                        let render_pass_info = RenderPassBeginInfo::default()
+                            .render_pass(*self.0.framebuffers.render_pass().render_pass())
+                            .framebuffer(self.0.framebuffers.framebuffers()[*image_index])
+                            .render_area(Rect2D::default().extent(swapchain.extent()))
+                            .clear_values(&clear_values);
+
+                        unsafe {
+                            if layouts[*image_index].is_some()
+                                && old_layout != ImageLayout::PRESENT_SRC_KHR
+                            {
+                                transition(device, command_buffer, image, old_layout, old_layout);
+                            }
+
+                            device.cmd_begin_render_pass(
+                                command_buffer,
+                                &render_pass_info,
+                                SubpassContents::INLINE,
+                            );
+                            device.cmd_end_render_pass(command_buffer);
+
+                            if *layout != ImageLayout::PRESENT_SRC_KHR {
+                                transition(
+                                    device,
+                                    command_buffer,
+                                    image,
+                                    ImageLayout::PRESENT_SRC_KHR,
+                                    *layout,
+                                );
+                            }
+                        }
+
+                        layouts[*image_index] = Some(*layout);
+
+                        continue;
+                    }
+
+                    let ranges = [ImageSubresourceRange {
+                        aspect_mask: ImageAspectFlags::COLOR,
+                        base_mip_level: 0,
+                        level_count: REMAINING_MIP_LEVELS,
+                        base_array_layer: 0,
+                        layer_count: REMAINING_ARRAY_LAYERS,
+                    }];
+
+                    unsafe {
+                        transition(device, command_buffer, image, old_layout, *layout);
+                        device.cmd_clear_color_image(
+                            command_buffer,
+                            image,
+                            *layout,
+                            &ClearColorValue {
+                                float32: *clear_color,
+                            },
+                            &ranges,
+                        );
+                    }
+
+                    layouts[*image_index] = Some(*layout);
+                }
+                RecordedCommand::ReleaseToPresent { image_index } => {
+                    // Replayed traces may come from a run that needed the transfer.
+                    let Some(transfer) = swapchain.ownership_transfer() else {
+                        continue;
+                    };
+
+                    unsafe {
+                        device.cmd_pipeline_barrier(
+                            command_buffer,
+                            PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                            PipelineStageFlags::BOTTOM_OF_PIPE,
+                            DependencyFlags::empty(),
+                            &[],
+                            &[],
+                            &[present_transfer::release_barrier(
+                                swapchain.images()[*image_index].image(),
+                                transfer,
+                            )],
+                        );
+                    }
+                }
+            }
+        }
+
+        checkpoint("end");
+
+        if let Some(tracer) = self.0.submit_tracer.borrow().as_ref() {
+            tracer.write_end(command_buffer, command_buffer_index);
+        }
+
+        unsafe { device.end_command_buffer(command_buffer) }.map_err(CommandError::from)?;
+
+        self.0.image_layouts.borrow_mut()[command_buffer_index] = layouts;
+
+        Ok(())
+    }
+}
+
+/// A list of [RecordedCommand]s for [CommandBuffers::record_commands], each one checked as it's
+/// added so a mistake fails where it's made instead of when recording.
+pub struct CommandEncoder {
+    command_buffers: CommandBuffers,
+    commands: Vec<RecordedCommand>,
+    state: ValidationState,
+}
+
+impl CommandEncoder {
+    /// Adds `command` when it can follow the commands added before it.
+    pub fn push(&mut self, command: RecordedCommand) -> Result<(), CommandError> {
+        self.command_buffers.check(&mut self.state, &command)?;
+        self.commands.push(command);
+
+        Ok(())
+    }
+
+    /// Clears every layer of the swapchain image `image_index` to `color`, outside of a render
+    /// pass. The image is left in `TRANSFER_DST_OPTIMAL`, or `GENERAL` when the swapchain images
+    /// can't be transfer destinations.
+    pub fn clear_color_image(
+        &mut self,
+        image_index: usize,
+        color: Color,
+    ) -> Result<(), CommandError> {
+        let swapchain = self
+            .command_buffers
+            .0
+            .framebuffers
+            .render_pass()
+            .swapchain();
+
+        let layout = if swapchain
+            .image_usage()
+            .contains(ImageUsageFlags::TRANSFER_DST)
+        {
+            ImageLayout::TRANSFER_DST_OPTIMAL
+        } else {
+            ImageLayout::GENERAL
+        };
+
+        self.push(RecordedCommand::ClearColorImage {
+            image_index,
+            layout,
+            clear_color: swapchain.encode_color(color),
+        })
+    }
+
+    /// Clears the color attachment `attachment` of the subpass to `color` in every one of
+    /// `rects`, inside a render pass.
+    pub fn clear_attachments(
+        &mut self,
+        attachment: u32,
+        color: Color,
+        rects: &[Rect2D],
+    ) -> Result<(), CommandError> {
+        let clear_color = self
+            .command_buffers
+            .0
+            .framebuffers
+            .render_pass()
+            .swapchain()
+            .encode_color(color);
+
+        self.push(RecordedCommand::ClearAttachments {
+            attachment,
+            clear_color,
+            rects: rects.to_vec(),
+        })
+    }
+
+    /// Limits the draws and clears that follow to `rect` within the scissor pushed before it,
+    /// until [CommandEncoder::pop_scissor]. Returns the scissor they're limited to, which is
+    /// empty when the rects don't overlap. Fails outside of a render pass or when `rect` isn't
+    /// within the framebuffer.
+    pub fn push_scissor(&mut self, rect: Rect2D) -> Result<Rect2D, CommandError> {
+        self.push(RecordedCommand::PushScissor { rect })?;
+
+        Ok(*self
+            .state
+            .scissors
+            .last()
+            .expect("pushed scissors are on the stack"))
+    }
+
+    /// Goes back to the scissor before the last [CommandEncoder::push_scissor]. Fails when every
+    /// pushed scissor was popped already.
+    pub fn pop_scissor(&mut self) -> Result<(), CommandError> {
+        self.push(RecordedCommand::PopScissor)
+    }
+
+    pub fn commands(&self) -> &[RecordedCommand] {
+        &self.commands
+    }
+
+    /// Records the commands into the command buffer at `command_buffer_index`.
+    pub fn finish(self, command_buffer_index: usize) -> Result<(), CommandError> {
+        self.command_buffers
+            .record_commands(command_buffer_index, &self.commands)
+    }
+}
+
+/// What [CommandBuffers::check] knows about the commands before the one it checks.
+#[derive(Debug, Default, Clone)]
+struct ValidationState {
+    /// The render area while inside a render pass.
+    render_area: Option<Extent2D>,
+    /// The scissor of every [RecordedCommand::PushScissor] not popped yet, clipped like
+    /// [CommandBuffers::execute] does.
+    scissors: Vec<Rect2D>,
+    index_buffer_bound: bool,
+}
+
+struct InnerCommandBuffers {
+    command_buffers: Vec<CommandBuffer>,
+    framebuffers: Framebuffers,
+    graphics_pipeline: GraphicsPipeline,
+    vertex_buffer: Buffer,
+    index_buffer: Option<IndexBuffer>,
+    command_pool: CommandPool,
+    trace: RefCell<Option<CommandTrace>>,
+    submit_tracer: RefCell<Option<SubmitTracer>>,
+    crash_diagnostics: RefCell<Option<CrashDiagnostics>>,
+    clear_color: Cell<Color>,
+    /// [RecordedCommand::PushConstants] for every range set.
+    push_constants: RefCell<Vec<RecordedCommand>>,
+    /// Per command buffer, the layout its commands leave every swapchain image in, see
+    /// [CommandBuffers::image_layout].
+    image_layouts: RefCell<Vec<Vec<Option<ImageLayout>>>>,
+}
+
+/// Moves `image` from `old_layout` to `new_layout` once every earlier command is done with it.
+unsafe fn transition(
+    device: &Device,
+    command_buffer: CommandBuffer,
+    image: Image,
+    old_layout: ImageLayout,
+    new_layout: ImageLayout,
+) {
+    let barrier = ImageMemoryBarrier::default()
+        .src_access_mask(AccessFlags::MEMORY_WRITE)
+        .dst_access_mask(AccessFlags::MEMORY_READ | AccessFlags::MEMORY_WRITE)
+        .old_layout(old_layout)
+        .new_layout(new_layout)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(
+            ImageSubresourceRange::default()
+                .aspect_mask(ImageAspectFlags::COLOR)
+                .level_count(REMAINING_MIP_LEVELS)
+                .layer_count(REMAINING_ARRAY_LAYERS),
+        );
+
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        PipelineStageFlags::ALL_COMMANDS,
+        PipelineStageFlags::ALL_COMMANDS,
+        DependencyFlags::empty(),
+        &[],
+        &[],
+        &[barrier],
+    );
+}
+
+fn rect_within(rect: &Rect2D, extent: Extent2D) -> bool {
+    rect.offset.x >= 0
+        && rect.offset.y >= 0
+        && rect.offset.x as u64 + rect.extent.width as u64 <= extent.width as u64
+        && rect.offset.y as u64 + rect.extent.height as u64 <= extent.height as u64
+}
+
+/// Whether the clear value has the type Vulkan reads for an attachment of the given format.
+fn clear_value_matches(clear_value: &AttachmentClear, format: Format) -> bool {
+    match clear_value {
+        AttachmentClear::DepthStencil { .. } => is_depth_stencil_format(format),
+        AttachmentClear::ColorInt(_) => is_sint_format(format),
+        AttachmentClear::ColorUint(_) => is_uint_format(format),
+        AttachmentClear::ColorFloat(_) => {
+            !is_depth_stencil_format(format) && !is_sint_format(format) && !is_uint_format(format)
+        }
+    }
+}
+
+fn is_depth_stencil_format(format: Format) -> bool {
+    matches!(
+        format,
+        Format::D16_UNORM
+            | Format::X8_D24_UNORM_PACK32
+            | Format::D32_SFLOAT
+            | Format::S8_UINT
+            | Format::D16_UNORM_S8_UINT
+            | Format::D24_UNORM_S8_UINT
+            | Format::D32_SFLOAT_S8_UINT
+    )
+}
+
+fn is_sint_format(format: Format) -> bool {
+    matches!(
+        format,
+        Format::R8_SINT
+            | Format::R8G8_SINT
+            | Format::R8G8B8A8_SINT
+            | Format::B8G8R8A8_SINT
+            | Format::A8B8G8R8_SINT_PACK32
+            | Format::A2R10G10B10_SINT_PACK32
+            | Format::A2B10G10R10_SINT_PACK32
+            | Format::R16_SINT
+            | Format::R16G16_SINT
+            | Format::R16G16B16A16_SINT
+            | Format::R32_SINT
+            | Format::R32G32_SINT
+            | Format::R32G32B32A32_SINT
+            | Format::R64_SINT
+            | Format::R64G64_SINT
+            | Format::R64G64B64A64_SINT
+    )
+}
+
+fn is_uint_format(format: Format) -> bool {
+    matches!(
+        format,
+        Format::R8_UINT
+            | Format::R8G8_UINT
+            | Format::R8G8B8A8_UINT
+            | Format::B8G8R8A8_UINT
+            | Format::A8B8G8R8_UINT_PACK32
+            | Format::A2R10G10B10_UINT_PACK32
+            | Format::A2B10G10R10_UINT_PACK32
+            | Format::R16_UINT
+            | Format::R16G16_UINT
+            | Format::R16G16B16A16_UINT
+            | Format::R32_UINT
+            | Format::R32G32_UINT
+            | Format::R32G32B32A32_UINT
+            | Format::R64_UINT
+            | Format::R64G64_UINT
+            | Format::R64G64B64A64_UINT
+    )
+}
+
+fn intersect_rects(a: &Rect2D, b: &Rect2D) -> Rect2D {
+    let x0 = a.offset.x.max(b.offset.x);
+    let y0 = a.offset.y.max(b.offset.y);
+    let x1 = (a.offset.x + a.extent.width as i32).min(b.offset.x + b.extent.width as i32);
+    let y1 = (a.offset.y + a.extent.height as i32).min(b.offset.y + b.extent.height as i32);
+
+    Rect2D {
+        offset: Offset2D { x: x0, y: y0 },
+        extent: Extent2D {
+            width: (x1 - x0).max(0) as u32,
+            height: (y1 - y0).max(0) as u32,
+        },
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CommandError {
+    Vulkan(vk::Result),
+    OutsideRenderPass,
+    InsideRenderPass,
+    EmptyClearRects,
+    ClearRectOutOfBounds,
+    InvalidClearLayout(ImageLayout),
+    ScissorOutOfBounds,
+    UnbalancedScissorStack,
+    ClearValueCount {
+        expected: usize,
+        found: usize,
+    },
+    ClearValueMismatch {
+        attachment: usize,
+        format: Format,
+    },
+    /// An indexed draw without an index buffer bound.
+    NoIndexBuffer,
+    IndexRangeOutOfBounds {
+        first: u32,
+        count: u32,
+        available: u32,
+    },
+    /// The push constants aren't 4-byte aligned or fall outside the pipeline layout's ranges.
+    PushConstantsOutOfRange {
+        stages: ShaderStageFlags,
+        offset: u32,
+        size: u32,
+    },
+    /// No swapchain image, or framebuffer, at the index.
+    ImageIndexOutOfRange {
+        index: usize,
+        available: usize,
+    },
+    /// No pipeline variant at the index.
+    PipelineIndexOutOfRange {
+        index: usize,
+        available: usize,
+    },
+    /// The render area is larger than the swapchain images.
+    RenderAreaOutOfBounds(Extent2D),
+    /// No color attachment in the subpass at the index.
+    AttachmentOutOfRange {
+        index: u32,
+        available: u32,
+    },
+}
+
+impl From<vk::Result> for CommandError {
+    fn from(value: vk::Result) -> Self {
+        Self::Vulkan(value)
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Vulkan(e) => e.fmt(f),
+            Self::OutsideRenderPass => write!(f, "command must be recorded inside a render pass"),
+            Self::InsideRenderPass => write!(f, "command must be recorded outside a render pass"),
+            Self::EmptyClearRects => write!(f, "clear_attachments needs at least one rect"),
+            Self::ClearRectOutOfBounds => {
+                write!(f, "clear rect is outside of the render area")
+            }
+            Self::InvalidClearLayout(layout) => write!(
+                f,
+                "image layout {:?} cannot be used to clear a color image",
+                layout
+            ),
+            Self::ScissorOutOfBounds => write!(f, "scissor rect is outside of the framebuffer"),
+            Self::UnbalancedScissorStack => {
+                write!(f, "push_scissor and pop_scissor calls are unbalanced")
+            }
+            Self::ClearValueCount { expected, found } => write!(
+                f,
+                "render pass has {} attachments but {} clear values were given",
+                expected, found
+            ),
+            Self::ClearValueMismatch { attachment, format } => write!(
+                f,
+                "clear value for attachment {} does not match its format {:?}",
+                attachment, format
+            ),
+            Self::NoIndexBuffer => write!(f, "indexed draw without an index buffer bound"),
+            Self::IndexRangeOutOfBounds {
+                first,
+                count,
+                available,
+            } => write!(
+                f,
+                "drawing {} indices from index {} overruns the {} indices of the index buffer",
+                count, first, available
+            ),
+            Self::PushConstantsOutOfRange {
+                stages,
+                offset,
+                size,
+            } => write!(
+                f,
+                "{} bytes of push constants at offset {} for {:?} are outside the pipeline layout's ranges",
+                size, offset, stages
+            ),
+            Self::ImageIndexOutOfRange { index, available } => write!(
+                f,
+                "swapchain image {} does not exist, there are {}",
+                index, available
+            ),
+            Self::PipelineIndexOutOfRange { index, available } => write!(
+                f,
+                "pipeline variant {} does not exist, there are {}",
+                index, available
+            ),
+            Self::RenderAreaOutOfBounds(extent) => write!(
+                f,
+                "render area of {}x{} is larger than the swapchain images",
+                extent.width, extent.height
+            ),
+            Self::AttachmentOutOfRange { index, available } => write!(
+                f,
+                "color attachment {} does not exist, the subpass has {}",
+                index, available
+            ),
+        }
+    }
+}
+
+impl error::Error for CommandError {}
@@ -2,10 +2,13 @@ use std::rc::Rc;
 
 use ash::{
     prelude::VkResult,
-    vk::{self, CommandPoolCreateFlags, CommandPoolCreateInfo},
+    vk::{self, CommandPoolCreateFlags, CommandPoolCreateInfo, Handle},
 };
 
-use crate::{logical_device::LogicalDevice, physical_device::PhysicalDevice};
+use crate::{
+    api2::host_allocation_callbacks,
+    renderer::{logical_device::LogicalDevice, physical_device::PhysicalDevice, teardown_trace},
+};
 
 #[derive(Clone)]
 pub struct CommandPool(Rc<InnerCommandPool>);
@@ -19,7 +22,7 @@ impl CommandPool {
         let command_pool = unsafe {
             logical_device
                 .device()
-                .create_command_pool(&command_pool_create_info, None)?
+                .create_command_pool(&command_pool_create_info, host_allocation_callbacks())?
         };
 
         Ok(Self(Rc::new(InnerCommandPool {
@@ -46,10 +49,12 @@ struct InnerCommandPool {
 
 impl Drop for InnerCommandPool {
     fn drop(&mut self) {
+        teardown_trace::record("CommandPool", [self.command_pool.as_raw()]);
+
         unsafe {
             self.logical_device
                 .device()
-                .destroy_command_pool(self.command_pool, None);
+                .destroy_command_pool(self.command_pool, host_allocation_callbacks());
         }
     }
 }
@@ -0,0 +1,468 @@
+//! Commands recorded into command buffers as plain data, so a run can be saved and replayed
+//! frame by frame, see [Renderer::replay_frame](crate::renderer::Renderer::replay_frame).
+//!
+//! With the `serde` feature a [CommandTrace] saves and loads as JSON. Vulkan's structs don't
+//! implement serde, so the ones the commands carry are mirrored next to them. Without it
+//! [CommandTrace::save] and [CommandTrace::load] fail with [TraceError::Unsupported].
+
+use std::{error, fmt, io, path::Path};
+
+use ash::vk::{
+    ClearColorValue, ClearDepthStencilValue, ClearValue, Extent2D, ImageLayout, Rect2D,
+    ShaderStageFlags, Viewport,
+};
+
+use crate::renderer::command_buffers::CommandError;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RecordedCommand {
+    BeginRenderPass {
+        image_index: usize,
+        #[cfg_attr(feature = "serde", serde(with = "vk_serde::Extent2DDef"))]
+        extent: Extent2D,
+        clear_values: Vec<AttachmentClear>,
+    },
+    SetViewport {
+        first: u32,
+        #[cfg_attr(feature = "serde", serde(with = "vk_serde::viewports"))]
+        viewports: Vec<Viewport>,
+    },
+    SetScissor {
+        first: u32,
+        #[cfg_attr(feature = "serde", serde(with = "vk_serde::rects"))]
+        scissors: Vec<Rect2D>,
+    },
+    BindPipeline {
+        pipeline_index: usize,
+    },
+    /// Binds the vertex buffer of the command buffers to binding 0.
+    BindVertexBuffer,
+    /// Binds the index buffer of the command buffers.
+    BindIndexBuffer,
+    PushConstants {
+        #[cfg_attr(feature = "serde", serde(with = "vk_serde::shader_stages"))]
+        stages: ShaderStageFlags,
+        offset: u32,
+        data: Vec<u8>,
+    },
+    Draw {
+        vertex_count: u32,
+        instance_count: u32,
+        first_vertex: u32,
+        first_instance: u32,
+    },
+    DrawIndexed {
+        index_count: u32,
+        instance_count: u32,
+        first_index: u32,
+        vertex_offset: i32,
+        first_instance: u32,
+    },
+    EndRenderPass,
+    /// Clears the color attachment `attachment` of the subpass in `rects`, in every view of
+    /// multiview render passes.
+    ClearAttachments {
+        #[cfg_attr(feature = "serde", serde(default))]
+        attachment: u32,
+        clear_color: [f32; 4],
+        #[cfg_attr(feature = "serde", serde(with = "vk_serde::rects"))]
+        rects: Vec<Rect2D>,
+    },
+    /// Clears every layer of a swapchain image, moving it to `layout` first. Swapchains without
+    /// `TRANSFER_DST` images clear it with an empty pass of the render pass instead and move it
+    /// to `layout` after, which can't be `TRANSFER_DST_OPTIMAL` then.
+    ClearColorImage {
+        image_index: usize,
+        #[cfg_attr(feature = "serde", serde(with = "vk_serde::image_layout"))]
+        layout: ImageLayout,
+        clear_color: [f32; 4],
+    },
+    PushScissor {
+        #[cfg_attr(feature = "serde", serde(with = "vk_serde::Rect2DDef"))]
+        rect: Rect2D,
+    },
+    PopScissor,
+    /// Hands the swapchain image to the present family, when the swapchain needs it.
+    ReleaseToPresent {
+        image_index: usize,
+    },
+}
+
+impl RecordedCommand {
+    /// Points the command at `image_index` when it names a swapchain image, so a frame recorded
+    /// for one image can be replayed on whichever one was acquired.
+    pub fn retarget(&mut self, image_index: usize) {
+        if let Self::BeginRenderPass { image_index: i, .. }
+        | Self::ClearColorImage { image_index: i, .. }
+        | Self::ReleaseToPresent { image_index: i } = self
+        {
+            *i = image_index;
+        }
+    }
+}
+
+/// The value an attachment is cleared to when a render pass begins, typed by the kind of format
+/// the attachment has.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum AttachmentClear {
+    ColorFloat([f32; 4]),
+    ColorInt([i32; 4]),
+    ColorUint([u32; 4]),
+    DepthStencil { depth: f32, stencil: u32 },
+}
+
+impl AttachmentClear {
+    pub fn to_vk(&self) -> ClearValue {
+        match *self {
+            Self::ColorFloat(float32) => ClearValue {
+                color: ClearColorValue { float32 },
+            },
+            Self::ColorInt(int32) => ClearValue {
+                color: ClearColorValue { int32 },
+            },
+            Self::ColorUint(uint32) => ClearValue {
+                color: ClearColorValue { uint32 },
+            },
+            Self::DepthStencil { depth, stencil } => ClearValue {
+                depth_stencil: ClearDepthStencilValue { depth, stencil },
+            },
+        }
+    }
+}
+
+/// The commands of every frame recorded since the trace started, one entry per command buffer
+/// recording.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommandTrace {
+    pub frames: Vec<Vec<RecordedCommand>>,
+}
+
+impl CommandTrace {
+    #[cfg(feature = "serde")]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), TraceError> {
+        let file = io::BufWriter::new(std::fs::File::create(path)?);
+
+        serde_json::to_writer(file, self).map_err(TraceError::from)
+    }
+
+    #[cfg(not(feature = "serde"))]
+    pub fn save<P: AsRef<Path>>(&self, _path: P) -> Result<(), TraceError> {
+        Err(TraceError::Unsupported)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, TraceError> {
+        let file = io::BufReader::new(std::fs::File::open(path)?);
+
+        serde_json::from_reader(file).map_err(TraceError::from)
+    }
+
+    #[cfg(not(feature = "serde"))]
+    pub fn load<P: AsRef<Path>>(_path: P) -> Result<Self, TraceError> {
+        Err(TraceError::Unsupported)
+    }
+}
+
+/// Serde mirrors of the Vulkan structs and flags the commands carry.
+#[cfg(feature = "serde")]
+mod vk_serde {
+    use ash::vk::{Extent2D, ImageLayout, Offset2D, Rect2D, ShaderStageFlags, Viewport};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "Extent2D")]
+    pub struct Extent2DDef {
+        pub width: u32,
+        pub height: u32,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "Offset2D")]
+    pub struct Offset2DDef {
+        pub x: i32,
+        pub y: i32,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "Rect2D")]
+    pub struct Rect2DDef {
+        #[serde(with = "Offset2DDef")]
+        pub offset: Offset2D,
+        #[serde(with = "Extent2DDef")]
+        pub extent: Extent2D,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "Viewport")]
+    pub struct ViewportDef {
+        pub x: f32,
+        pub y: f32,
+        pub width: f32,
+        pub height: f32,
+        pub min_depth: f32,
+        pub max_depth: f32,
+    }
+
+    pub mod viewports {
+        use super::*;
+
+        #[derive(Serialize, Deserialize)]
+        struct Wrapped(#[serde(with = "ViewportDef")] Viewport);
+
+        pub fn serialize<S: Serializer>(
+            viewports: &[Viewport],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(viewports.iter().map(|viewport| Wrapped(*viewport)))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<Viewport>, D::Error> {
+            let wrapped = Vec::<Wrapped>::deserialize(deserializer)?;
+
+            Ok(wrapped
+                .into_iter()
+                .map(|Wrapped(viewport)| viewport)
+                .collect())
+        }
+    }
+
+    pub mod rects {
+        use super::*;
+
+        #[derive(Serialize, Deserialize)]
+        struct Wrapped(#[serde(with = "Rect2DDef")] Rect2D);
+
+        pub fn serialize<S: Serializer>(
+            rects: &[Rect2D],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(rects.iter().map(|rect| Wrapped(*rect)))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<Rect2D>, D::Error> {
+            let wrapped = Vec::<Wrapped>::deserialize(deserializer)?;
+
+            Ok(wrapped.into_iter().map(|Wrapped(rect)| rect).collect())
+        }
+    }
+
+    /// The raw bits, new stages don't need a name here.
+    pub mod shader_stages {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            stages: &ShaderStageFlags,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            stages.as_raw().serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<ShaderStageFlags, D::Error> {
+            u32::deserialize(deserializer).map(ShaderStageFlags::from_raw)
+        }
+    }
+
+    /// The raw value, extension layouts don't need a name here.
+    pub mod image_layout {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            layout: &ImageLayout,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            layout.as_raw().serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<ImageLayout, D::Error> {
+            i32::deserialize(deserializer).map(ImageLayout::from_raw)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TraceError {
+    Io(io::Error),
+    #[cfg(feature = "serde")]
+    Json(serde_json::Error),
+    /// Saving and loading traces needs the `serde` feature.
+    Unsupported,
+    /// A replayed frame doesn't fit the command buffers replaying it.
+    Command(CommandError),
+}
+
+impl From<io::Error> for TraceError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for TraceError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+impl From<CommandError> for TraceError {
+    fn from(value: CommandError) -> Self {
+        Self::Command(value)
+    }
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            #[cfg(feature = "serde")]
+            Self::Json(e) => e.fmt(f),
+            Self::Unsupported => write!(f, "command traces need the serde feature"),
+            Self::Command(e) => write!(f, "cannot replay the traced frame: {}", e),
+        }
+    }
+}
+
+impl error::Error for TraceError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn save_load_round_trip() {
+        let rect = Rect2D {
+            offset: ash::vk::Offset2D { x: -4, y: 8 },
+            extent: Extent2D {
+                width: 640,
+                height: 480,
+            },
+        };
+
+        let trace = CommandTrace {
+            frames: vec![
+                vec![
+                    RecordedCommand::BeginRenderPass {
+                        image_index: 2,
+                        extent: rect.extent,
+                        clear_values: vec![
+                            AttachmentClear::ColorFloat([0.25, 0.5, 0.75, 1.0]),
+                            AttachmentClear::ColorInt([-1, 0, 1, i32::MAX]),
+                            AttachmentClear::ColorUint([0, 1, 2, u32::MAX]),
+                            AttachmentClear::DepthStencil {
+                                depth: 1.0,
+                                stencil: 0,
+                            },
+                        ],
+                    },
+                    RecordedCommand::SetViewport {
+                        first: 0,
+                        viewports: vec![Viewport {
+                            x: 0.0,
+                            y: 0.0,
+                            width: 640.0,
+                            height: 480.0,
+                            min_depth: 0.0,
+                            max_depth: 1.0,
+                        }],
+                    },
+                    RecordedCommand::SetScissor {
+                        first: 0,
+                        scissors: vec![rect],
+                    },
+                    RecordedCommand::BindPipeline { pipeline_index: 1 },
+                    RecordedCommand::BindVertexBuffer,
+                    RecordedCommand::BindIndexBuffer,
+                    RecordedCommand::PushConstants {
+                        stages: ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT,
+                        offset: 4,
+                        data: vec![0, 1, 0xfe, 0xff],
+                    },
+                    RecordedCommand::PushScissor { rect },
+                    RecordedCommand::ClearAttachments {
+                        attachment: 0,
+                        clear_color: [1.0, 0.0, 0.0, 1.0],
+                        rects: vec![rect, rect],
+                    },
+                    RecordedCommand::PopScissor,
+                    RecordedCommand::DrawIndexed {
+                        index_count: 6,
+                        instance_count: 1,
+                        first_index: 0,
+                        vertex_offset: -3,
+                        first_instance: 0,
+                    },
+                    RecordedCommand::EndRenderPass,
+                    RecordedCommand::ReleaseToPresent { image_index: 2 },
+                ],
+                vec![],
+                vec![
+                    RecordedCommand::ClearColorImage {
+                        image_index: 0,
+                        layout: ImageLayout::TRANSFER_DST_OPTIMAL,
+                        clear_color: [0.0; 4],
+                    },
+                    RecordedCommand::Draw {
+                        vertex_count: 3,
+                        instance_count: 1,
+                        first_vertex: 0,
+                        first_instance: 0,
+                    },
+                ],
+            ],
+        };
+
+        let path = std::env::temp_dir().join(format!(
+            "learnvulkan-command-trace-{}.json",
+            std::process::id()
+        ));
+
+        trace.save(&path).unwrap();
+        let loaded = CommandTrace::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        // The Vulkan structs don't implement PartialEq, their Debug output covers every field.
+        assert_eq!(format!("{:?}", loaded.unwrap()), format!("{:?}", trace));
+    }
+
+    #[test]
+    fn retarget_only_touches_image_indices() {
+        let mut frame = [
+            RecordedCommand::BeginRenderPass {
+                image_index: 0,
+                extent: Extent2D::default(),
+                clear_values: vec![],
+            },
+            RecordedCommand::BindPipeline { pipeline_index: 0 },
+            RecordedCommand::EndRenderPass,
+            RecordedCommand::ReleaseToPresent { image_index: 0 },
+        ];
+
+        frame.iter_mut().for_each(|command| command.retarget(3));
+
+        assert!(matches!(
+            frame[0],
+            RecordedCommand::BeginRenderPass { image_index: 3, .. }
+        ));
+        assert!(matches!(
+            frame[1],
+            RecordedCommand::BindPipeline { pipeline_index: 0 }
+        ));
+        assert!(matches!(
+            frame[3],
+            RecordedCommand::ReleaseToPresent { image_index: 3 }
+        ));
+    }
+}
@@ -0,0 +1,320 @@
+//! What's known about the GPU's last moments when the device is lost, written to a file for bug
+//! reports: the last submissions, the last checkpoints the GPU reached, what was allocated and the
+//! last validation messages.
+//!
+//! Checkpoints are named markers recorded in command buffers with [CrashDiagnostics::checkpoint].
+//! With `VK_NV_device_diagnostic_checkpoints` the driver reports the last one every pipeline stage
+//! reached. With `VK_AMD_buffer_marker` the GPU writes the last one every command buffer started
+//! and finished to a host-visible buffer. Without either, dumps have no checkpoints.
+
+use std::{cell::RefCell, collections::VecDeque, ffi::c_void, fmt, fs, io, path::Path, rc::Rc};
+
+use ash::{
+    amd, nv,
+    vk::{self, BufferUsageFlags, CheckpointDataNV, PipelineStageFlags},
+};
+
+use crate::{
+    api2::{self, ErrorCtx, ValidationMessage},
+    renderer::{
+        buffer::Buffer,
+        logical_device::LogicalDevice,
+        resource_stats::{ResourceKind, ResourceStats},
+    },
+};
+
+/// How many submissions a dump lists.
+pub const RECENT_SUBMITS: usize = 16;
+
+/// The size of a buffer marker, one started and one finished per slot.
+const MARKER_SIZE: vk::DeviceSize = 4;
+
+#[derive(Clone)]
+pub struct CrashDiagnostics(Rc<InnerCrashDiagnostics>);
+
+impl CrashDiagnostics {
+    /// Creates the diagnostics of `slots` command buffers recorded in turn, usually one per frame
+    /// in flight.
+    pub fn new(logical_device: LogicalDevice, slots: usize) -> Result<Self, ErrorCtx> {
+        let instance = logical_device.physical_device().instance().instance();
+
+        let markers = if logical_device.supports_diagnostic_checkpoints() {
+            Markers::Checkpoints(nv::device_diagnostic_checkpoints::Device::new(
+                instance,
+                logical_device.device(),
+            ))
+        } else if logical_device.supports_buffer_markers() {
+            let buffer = Buffer::with_data(
+                logical_device.clone(),
+                &vec![0u32; 2 * slots.max(1)],
+                BufferUsageFlags::TRANSFER_DST,
+            )?;
+
+            Markers::Buffer {
+                device: amd::buffer_marker::Device::new(instance, logical_device.device()),
+                buffer,
+            }
+        } else {
+            Markers::None
+        };
+
+        Ok(Self(Rc::new(InnerCrashDiagnostics {
+            logical_device,
+            markers,
+            names: RefCell::new(Vec::new()),
+            submits: RefCell::new(VecDeque::with_capacity(RECENT_SUBMITS)),
+        })))
+    }
+
+    /// Whether checkpoints are recorded at all.
+    pub fn has_checkpoints(&self) -> bool {
+        !matches!(self.0.markers, Markers::None)
+    }
+
+    /// Records a checkpoint named `name` in `command_buffer`, the command buffer of `slot`.
+    pub fn checkpoint(&self, command_buffer: vk::CommandBuffer, slot: usize, name: &'static str) {
+        // Markers are 1-based, so zero stays "none reached".
+        let marker = self.intern(name) + 1;
+
+        match &self.0.markers {
+            Markers::None => {}
+            Markers::Checkpoints(device) => unsafe {
+                device.cmd_set_checkpoint(command_buffer, marker as usize as *const c_void);
+            },
+            Markers::Buffer { device, buffer } => unsafe {
+                let offset = 2 * slot as vk::DeviceSize * MARKER_SIZE;
+
+                device.cmd_write_buffer_marker(
+                    command_buffer,
+                    PipelineStageFlags::TOP_OF_PIPE,
+                    *buffer.buffer(),
+                    offset,
+                    marker,
+                );
+                device.cmd_write_buffer_marker(
+                    command_buffer,
+                    PipelineStageFlags::BOTTOM_OF_PIPE,
+                    *buffer.buffer(),
+                    offset + MARKER_SIZE,
+                    marker,
+                );
+            },
+        }
+    }
+
+    /// Notes a submission under `label`, keeping the last [RECENT_SUBMITS].
+    pub fn note_submit(&self, label: impl Into<String>) {
+        let mut submits = self.0.submits.borrow_mut();
+
+        if submits.len() == RECENT_SUBMITS {
+            submits.pop_front();
+        }
+
+        submits.push_back(label.into());
+    }
+
+    /// Collects what's known after the device failed with `cause`. Everything the lost device
+    /// can't report anymore is left out.
+    pub fn dump(&self, cause: vk::Result) -> CrashDump {
+        let logical_device = &self.0.logical_device;
+
+        CrashDump {
+            cause,
+            device_name: logical_device
+                .physical_device()
+                .properties()
+                .device_name_as_c_str()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            submits: self.0.submits.borrow().iter().cloned().collect(),
+            checkpoints: self.checkpoints(),
+            resources: logical_device.resource_stats(),
+            validation: api2::recent_validation_messages(),
+        }
+    }
+
+    fn intern(&self, name: &'static str) -> u32 {
+        let mut names = self.0.names.borrow_mut();
+
+        let index = names
+            .iter()
+            .position(|&known| known == name)
+            .unwrap_or_else(|| {
+                names.push(name);
+                names.len() - 1
+            });
+
+        index as u32
+    }
+
+    fn name(&self, marker: usize) -> Option<&'static str> {
+        marker
+            .checked_sub(1)
+            .and_then(|index| self.0.names.borrow().get(index).copied())
+    }
+
+    fn checkpoints(&self) -> Vec<Checkpoint> {
+        match &self.0.markers {
+            Markers::None => Vec::new(),
+            Markers::Checkpoints(device) => {
+                let queue = *self.0.logical_device.queue();
+
+                let mut data = unsafe {
+                    vec![CheckpointDataNV::default(); device.get_queue_checkpoint_data_len(queue)]
+                };
+                unsafe { device.get_queue_checkpoint_data(queue, &mut data) };
+
+                data.iter()
+                    .map(|data| Checkpoint::Stage {
+                        stage: data.stage,
+                        name: self.name(data.p_checkpoint_marker as usize),
+                    })
+                    .collect()
+            }
+            Markers::Buffer { buffer, .. } => {
+                // Mapping may still work on a lost device, the markers are whatever landed.
+                let Ok(bytes) = buffer.read(0, buffer.size()) else {
+                    return Vec::new();
+                };
+
+                bytes
+                    .chunks_exact(2 * MARKER_SIZE as usize)
+                    .enumerate()
+                    .map(|(slot, markers)| {
+                        let marker = |bytes: &[u8]| {
+                            self.name(u32::from_ne_bytes(bytes.try_into().unwrap()) as usize)
+                        };
+
+                        Checkpoint::Slot {
+                            slot,
+                            started: marker(&markers[..4]),
+                            finished: marker(&markers[4..]),
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The last checkpoint reached somewhere, `None` when none was.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Checkpoint {
+    /// The last checkpoint a pipeline stage of the queue reached.
+    Stage {
+        stage: PipelineStageFlags,
+        name: Option<&'static str>,
+    },
+    /// The last checkpoint the GPU started and finished in the command buffer of `slot`. A
+    /// command buffer hung between the two.
+    Slot {
+        slot: usize,
+        started: Option<&'static str>,
+        finished: Option<&'static str>,
+    },
+}
+
+/// What [CrashDiagnostics::dump] collected.
+#[derive(Debug, Clone)]
+pub struct CrashDump {
+    pub cause: vk::Result,
+    pub device_name: String,
+    /// The labels of the last submissions, oldest first.
+    pub submits: Vec<String>,
+    pub checkpoints: Vec<Checkpoint>,
+    pub resources: ResourceStats,
+    /// The last validation warnings and errors, oldest first.
+    pub validation: Vec<ValidationMessage>,
+}
+
+impl CrashDump {
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for CrashDump {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# crash dump")?;
+        writeln!(f, "cause: {}", self.cause)?;
+        writeln!(f, "device: {}", self.device_name)?;
+
+        writeln!(f, "\n# last submissions, oldest first")?;
+
+        for label in &self.submits {
+            writeln!(f, "{}", label)?;
+        }
+
+        writeln!(f, "\n# checkpoints")?;
+
+        if self.checkpoints.is_empty() {
+            writeln!(f, "none, no checkpoint extension or none reported")?;
+        }
+
+        for checkpoint in &self.checkpoints {
+            match *checkpoint {
+                Checkpoint::Stage { stage, name } => {
+                    writeln!(f, "{:?}: {}", stage, name.unwrap_or("-"))?
+                }
+                Checkpoint::Slot {
+                    slot,
+                    started,
+                    finished,
+                } => writeln!(
+                    f,
+                    "slot {}: started {}, finished {}",
+                    slot,
+                    started.unwrap_or("-"),
+                    finished.unwrap_or("-")
+                )?,
+            }
+        }
+
+        writeln!(f, "\n# resources, live and bytes")?;
+
+        for kind in [
+            ResourceKind::Buffer,
+            ResourceKind::Image,
+            ResourceKind::ImageView,
+            ResourceKind::Framebuffer,
+            ResourceKind::ShaderModule,
+            ResourceKind::Pipeline,
+            ResourceKind::DescriptorSet,
+        ] {
+            let count = self.resources.get(kind);
+            writeln!(f, "{:?} {} {}", kind, count.live, count.bytes)?;
+        }
+
+        writeln!(f, "\n# last validation messages, oldest first")?;
+
+        for message in &self.validation {
+            writeln!(
+                f,
+                "{:?} [{}]: {}",
+                message.severity,
+                message.id.as_deref().unwrap_or("unknown"),
+                message.message
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+enum Markers {
+    None,
+    Checkpoints(nv::device_diagnostic_checkpoints::Device),
+    Buffer {
+        device: amd::buffer_marker::Device,
+        buffer: Buffer,
+    },
+}
+
+struct InnerCrashDiagnostics {
+    logical_device: LogicalDevice,
+    markers: Markers,
+    /// The checkpoint names, a marker being its index plus one.
+    names: RefCell<Vec<&'static str>>,
+    submits: RefCell<VecDeque<String>>,
+}
@@ -2,14 +2,17 @@ use ash::{
     ext::debug_utils,
     prelude::VkResult,
     vk::{
-        self, Bool32, DebugUtilsMessageSeverityFlagsEXT, DebugUtilsMessageTypeFlagsEXT,
+        Bool32, DebugUtilsMessageSeverityFlagsEXT, DebugUtilsMessageTypeFlagsEXT,
         DebugUtilsMessengerCallbackDataEXT, DebugUtilsMessengerCreateInfoEXT,
-        DebugUtilsMessengerEXT,
+        DebugUtilsMessengerEXT, Handle,
     },
 };
 use std::{ffi::c_void, rc::Rc};
 
-use crate::instance::Instance;
+use crate::{
+    api2::{self, host_allocation_callbacks},
+    renderer::{instance::Instance, teardown_trace},
+};
 
 #[derive(Clone)]
 #[allow(dead_code)]
@@ -20,8 +23,10 @@ impl DebugLayer {
         let create_info = create_debug_messenger();
 
         let debug_instance = debug_utils::Instance::new(instance.entry(), instance.instance());
-        let debug_messenger =
-            unsafe { debug_instance.create_debug_utils_messenger(&create_info, None)? };
+        let debug_messenger = unsafe {
+            debug_instance
+                .create_debug_utils_messenger(&create_info, host_allocation_callbacks())?
+        };
 
         Ok(Self(Rc::new(InnerDebugLayer {
             debug_instance,
@@ -41,9 +46,11 @@ struct InnerDebugLayer {
 
 impl Drop for InnerDebugLayer {
     fn drop(&mut self) {
+        teardown_trace::record("DebugLayer", [self.debug_messenger.as_raw()]);
+
         unsafe {
             self.debug_instance
-                .destroy_debug_utils_messenger(self.debug_messenger, None);
+                .destroy_debug_utils_messenger(self.debug_messenger, host_allocation_callbacks());
         }
     }
 }
@@ -65,21 +72,9 @@ pub fn create_debug_messenger<'a>() -> DebugUtilsMessengerCreateInfoEXT<'a> {
 
 unsafe extern "system" fn debug_callback(
     severity: DebugUtilsMessageSeverityFlagsEXT,
-    _: DebugUtilsMessageTypeFlagsEXT,
+    message_type: DebugUtilsMessageTypeFlagsEXT,
     callback_data: *const DebugUtilsMessengerCallbackDataEXT<'_>,
-    _: *mut c_void,
+    user_data: *mut c_void,
 ) -> Bool32 {
-    if severity >= DebugUtilsMessageSeverityFlagsEXT::WARNING {
-        println!(
-            "validation layer: {}",
-            callback_data
-                .read()
-                .message_as_c_str()
-                .unwrap()
-                .to_str()
-                .unwrap()
-        );
-    }
-
-    vk::TRUE
+    api2::print_warnings(severity, message_type, callback_data, user_data)
 }
@@ -0,0 +1,153 @@
+//! The frames the CPU records ahead of the GPU, each with its own command buffer, semaphores and
+//! fence, used in turn.
+//!
+//! [FrameSync::begin_frame] waits until the GPU is done with the next frame's resources and
+//! acquires a swapchain image for it, [FrameSync::end_frame] moves on to the following frame once
+//! it was submitted and presented. With two or three frames in flight the CPU records one while
+//! the GPU renders the others.
+
+use std::time::{Duration, Instant};
+
+use ash::{
+    prelude::VkResult,
+    vk::{CommandBuffer, Fence, Semaphore},
+};
+
+use crate::renderer::{
+    acquire_policy::{AcquireError, AcquireOutcome, FrameAcquirer},
+    command_buffers::CommandBuffers,
+    logical_device::LogicalDevice,
+    swapchain::Swapchain,
+    sync_objects::SyncObjects,
+    MAX_FRAMES_IN_FLIGHT,
+};
+
+/// What a frame records, submits and presents with, valid until its [FrameSync::end_frame].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Frame {
+    /// Which of the frames in flight this is, the command buffer and slot index of tracers.
+    pub index: usize,
+    pub image_index: u32,
+    /// The swapchain was suboptimal too many times in a row, see
+    /// [AcquirePolicy::suboptimal_limit](crate::renderer::acquire_policy::AcquirePolicy::suboptimal_limit).
+    pub needs_recreate: bool,
+    pub command_buffer: CommandBuffer,
+    /// Signalled when the image was acquired, the submission has to wait on it.
+    pub image_available: Semaphore,
+    /// For the submission to signal, presenting waits on it.
+    pub render_finished: Semaphore,
+    /// For the submission to signal, the next [FrameSync::begin_frame] of this frame waits on it.
+    pub fence: Fence,
+}
+
+pub struct FrameSync {
+    sync_objects: SyncObjects,
+    command_buffers: CommandBuffers,
+    logical_device: LogicalDevice,
+    frames_in_flight: usize,
+    current_frame: usize,
+    fence_wait: Duration,
+}
+
+impl FrameSync {
+    /// Creates the sync objects of [MAX_FRAMES_IN_FLIGHT] frames recording to `command_buffers`,
+    /// `frames_in_flight` of them being used.
+    pub fn new(
+        logical_device: LogicalDevice,
+        command_buffers: CommandBuffers,
+        frames_in_flight: usize,
+    ) -> VkResult<Self> {
+        Ok(Self {
+            sync_objects: SyncObjects::new(logical_device.clone(), MAX_FRAMES_IN_FLIGHT)?,
+            command_buffers,
+            logical_device,
+            frames_in_flight: frames_in_flight.clamp(1, MAX_FRAMES_IN_FLIGHT),
+            current_frame: 0,
+            fence_wait: Duration::ZERO,
+        })
+    }
+
+    /// Waits for the GPU to finish the last submission of the current frame and acquires the
+    /// next swapchain image, then resets the frame's fence and command buffer for recording.
+    /// Returns `None` when `acquirer` skipped the frame, the frame stays current and nothing was
+    /// reset.
+    pub fn begin_frame(
+        &mut self,
+        swapchain: &Swapchain,
+        acquirer: &mut FrameAcquirer,
+    ) -> Result<Option<Frame>, AcquireError> {
+        let index = self.current_frame;
+
+        let wait_start = Instant::now();
+        self.sync_objects.wait_in_flight_fence(index)?;
+        self.fence_wait += wait_start.elapsed();
+
+        let image_available = *self.sync_objects.image_available_semaphore(index);
+
+        let AcquireOutcome::Image {
+            index: image_index,
+            needs_recreate,
+        } = acquirer.acquire(swapchain, image_available)?
+        else {
+            return Ok(None);
+        };
+
+        // Only reset once an image was acquired, a skipped frame would never signal the fence.
+        self.sync_objects.reset_in_flight_fence(index)?;
+        self.command_buffers.reset_buffer(index)?;
+
+        Ok(Some(Frame {
+            index,
+            image_index,
+            needs_recreate,
+            command_buffer: self.command_buffers.command_buffers()[index],
+            image_available,
+            render_finished: *self.sync_objects.render_finished_semaphore(index),
+            fence: *self.sync_objects.in_flight_fence(index),
+        }))
+    }
+
+    /// Moves on to the next frame, once the current one was submitted.
+    pub fn end_frame(&mut self) {
+        self.current_frame = (self.current_frame + 1) % self.frames_in_flight;
+    }
+
+    /// The frame the next [FrameSync::begin_frame] begins.
+    pub fn current_frame(&self) -> usize {
+        self.current_frame
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames_in_flight
+    }
+
+    /// Sets how many frames may be recorded ahead of the GPU, between 1 and
+    /// [MAX_FRAMES_IN_FLIGHT], and returns the count in effect. Changing it waits for the device
+    /// to be idle, frames past the new count would never have their fences waited on again.
+    pub fn set_frames_in_flight(&mut self, count: usize) -> VkResult<usize> {
+        let count = count.clamp(1, MAX_FRAMES_IN_FLIGHT);
+
+        if count != self.frames_in_flight {
+            self.logical_device.wait_idle()?;
+            self.frames_in_flight = count;
+            self.current_frame = 0;
+        }
+
+        Ok(count)
+    }
+
+    pub fn command_buffers(&self) -> &CommandBuffers {
+        &self.command_buffers
+    }
+
+    /// Records to other command buffers from the next frame on, e.g. after the swapchain they
+    /// render to was recreated. Every frame must be done with the old ones.
+    pub fn set_command_buffers(&mut self, command_buffers: CommandBuffers) {
+        self.command_buffers = command_buffers;
+    }
+
+    /// The time [FrameSync::begin_frame] spent waiting for the GPU so far.
+    pub fn fence_wait(&self) -> Duration {
+        self.fence_wait
+    }
+}
@@ -0,0 +1,144 @@
+use std::rc::Rc;
+
+use ash::vk::{Framebuffer, FramebufferCreateInfo, Handle, ImageUsageFlags};
+
+use crate::{
+    api2::{host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        color_image::ColorImage, image_views::ImageViews, render_pass::RenderPass,
+        resource_stats::ResourceKind, teardown_trace,
+    },
+};
+
+/// Multiview framebuffers have one layer too, the views come from the attachments.
+const LAYERS: u32 = 1;
+
+#[derive(Clone)]
+pub struct Framebuffers(Rc<InnerFramebuffers>);
+
+impl Framebuffers {
+    /// Creates a framebuffer per image view, so one per swapchain image, or per layer of every
+    /// image with [ImageViews::per_layer], see [Framebuffers::framebuffer]. For multisampled
+    /// render passes, every framebuffer draws to one shared multisampled target and resolves into
+    /// its image, as only one frame renders at a time.
+    pub fn new(render_pass: RenderPass, image_views: ImageViews) -> Result<Self, ErrorCtx> {
+        let swapchain = render_pass.swapchain();
+
+        let multisampled = render_pass
+            .is_multisampled()
+            .then(|| {
+                ColorImage::layered(
+                    swapchain.device(),
+                    swapchain.format().format,
+                    swapchain.extent(),
+                    render_pass.samples(),
+                    ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSIENT_ATTACHMENT,
+                    render_pass.view_count(),
+                )
+            })
+            .transpose()?;
+
+        let mut framebuffers = Vec::with_capacity(image_views.image_views().len());
+
+        for image_view in image_views.image_views() {
+            let image_views: Vec<_> = multisampled
+                .iter()
+                .map(ColorImage::view)
+                .chain([*image_view])
+                .collect();
+
+            let framebuffer_create_info = FramebufferCreateInfo::default()
+                .render_pass(*render_pass.render_pass())
+                .attachments(&image_views)
+                .width(render_pass.swapchain().extent().width)
+                .height(render_pass.swapchain().extent().height)
+                .layers(LAYERS);
+
+            let framebuffer = unsafe {
+                render_pass
+                    .swapchain()
+                    .device()
+                    .device()
+                    .create_framebuffer(&framebuffer_create_info, host_allocation_callbacks())
+            }
+            .context("creating framebuffer")?;
+
+            framebuffers.push(framebuffer);
+        }
+
+        render_pass.swapchain().device().resource_tracker().track(
+            ResourceKind::Framebuffer,
+            framebuffers.len() as u64,
+            0,
+        );
+
+        Ok(Self(Rc::new(InnerFramebuffers {
+            framebuffers,
+            render_pass,
+            image_views,
+            multisampled,
+        })))
+    }
+
+    pub fn framebuffers(&self) -> &[Framebuffer] {
+        &self.0.framebuffers
+    }
+
+    /// The framebuffer rendering to `layer` of the swapchain image at `image_index`, layer 0
+    /// unless the views were created with [ImageViews::per_layer].
+    pub fn framebuffer(&self, image_index: u32, layer: u32) -> Framebuffer {
+        self.0.framebuffers[(image_index * self.0.image_views.views_per_image() + layer) as usize]
+    }
+
+    /// The layers of every framebuffer. Multiview render passes draw every view in their
+    /// [view mask](RenderPass::view_mask) of this one layer.
+    pub fn layers(&self) -> u32 {
+        LAYERS
+    }
+
+    pub fn image_views(&self) -> &ImageViews {
+        &self.0.image_views
+    }
+
+    pub fn render_pass(&self) -> &RenderPass {
+        &self.0.render_pass
+    }
+}
+
+struct InnerFramebuffers {
+    framebuffers: Vec<Framebuffer>,
+
+    #[allow(dead_code)]
+    render_pass: RenderPass,
+
+    image_views: ImageViews,
+
+    /// Destroyed after the framebuffers by being dropped after them.
+    #[allow(dead_code)]
+    multisampled: Option<ColorImage>,
+}
+
+impl Drop for InnerFramebuffers {
+    fn drop(&mut self) {
+        teardown_trace::record(
+            "Framebuffers",
+            self.framebuffers.iter().map(|handle| handle.as_raw()),
+        );
+
+        unsafe {
+            for framebuffer in self.framebuffers.iter() {
+                self.render_pass
+                    .swapchain()
+                    .device()
+                    .device()
+                    .destroy_framebuffer(*framebuffer, host_allocation_callbacks());
+            }
+        }
+
+        self.render_pass
+            .swapchain()
+            .device()
+            .resource_tracker()
+            .untrack(ResourceKind::Framebuffer, self.framebuffers.len() as u64, 0);
+    }
+}
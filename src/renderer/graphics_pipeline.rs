@@ -0,0 +1,469 @@
+use std::{
+    error, fmt,
+    io::{self, Cursor},
+    rc::Rc,
+};
+
+use ash::{
+    prelude::VkResult,
+    util::read_spv,
+    vk::{
+        CullModeFlags, DynamicState, FrontFace, GraphicsPipelineCreateInfo,
+        GraphicsPipelineLibraryCreateInfoEXT, GraphicsPipelineLibraryFlagsEXT, Handle, Offset2D,
+        Pipeline, PipelineCache, PipelineColorBlendStateCreateInfo, PipelineCreateFlags,
+        PipelineDynamicStateCreateInfo, PipelineInputAssemblyStateCreateInfo, PipelineLayout,
+        PipelineLayoutCreateInfo, PipelineLibraryCreateInfoKHR, PipelineMultisampleStateCreateInfo,
+        PipelineRasterizationStateCreateInfo, PipelineShaderStageCreateInfo,
+        PipelineVertexInputStateCreateInfo, PipelineViewportStateCreateInfo, PolygonMode,
+        PrimitiveTopology, PushConstantRange, Rect2D, ShaderStageFlags, Viewport,
+    },
+};
+
+use crate::{
+    api2::{host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        blend_mode::BlendMode, logical_device::LogicalDevice, pipeline_stats,
+        render_pass::RenderPass, resource_stats::ResourceKind, shader_cache::ShaderCache,
+        teardown_trace, vertex::Vertex, SHADER_FRAG, SHADER_VERT,
+    },
+};
+
+#[derive(Clone)]
+pub struct GraphicsPipeline(Rc<InnerGraphicsPipeline>);
+
+impl GraphicsPipeline {
+    pub fn new(render_pass: RenderPass, shader_cache: &ShaderCache) -> Result<Self, PipelineError> {
+        Self::with_variants(render_pass, shader_cache, &[PipelineVariant::default()])
+    }
+
+    /// Creates one pipeline per variant, in their order. The first is the base pipeline and the
+    /// others are created as its derivatives, so drivers that support it can reuse the state
+    /// they share. Creation times are recorded in
+    /// [pipeline_stats](crate::renderer::pipeline_stats).
+    ///
+    /// When the device supports graphics pipeline libraries, several variants are linked from
+    /// libraries instead, with the vertex input and fragment shader parts only created once.
+    /// The linking is done without link-time optimization, which trades some GPU performance
+    /// for faster creation.
+    pub fn with_variants(
+        render_pass: RenderPass,
+        shader_cache: &ShaderCache,
+        variants: &[PipelineVariant],
+    ) -> Result<Self, PipelineError> {
+        Self::with_push_constants(render_pass, shader_cache, variants, &[])
+    }
+
+    /// Creates the pipelines with `push_constant_ranges` in their layout, for the data
+    /// [CommandBuffers::push_constants](crate::renderer::command_buffers::CommandBuffers::push_constants)
+    /// pushes.
+    pub fn with_push_constants(
+        render_pass: RenderPass,
+        shader_cache: &ShaderCache,
+        variants: &[PipelineVariant],
+        push_constant_ranges: &[PushConstantRange],
+    ) -> Result<Self, PipelineError> {
+        let shader_modules = [
+            shader_cache
+                .get_or_create(
+                    &read_spv(&mut Cursor::new(SHADER_VERT)).context("reading vertex shader")?,
+                )
+                .context("creating vertex shader module")?,
+            shader_cache
+                .get_or_create(
+                    &read_spv(&mut Cursor::new(SHADER_FRAG)).context("reading fragment shader")?,
+                )
+                .context("creating fragment shader module")?,
+        ];
+
+        let main_function_name = c"main";
+
+        let pipeline_shader_info = [
+            PipelineShaderStageCreateInfo::default()
+                .stage(ShaderStageFlags::VERTEX)
+                .module(*shader_modules[0].shader_module())
+                .name(main_function_name),
+            PipelineShaderStageCreateInfo::default()
+                .stage(ShaderStageFlags::FRAGMENT)
+                .module(*shader_modules[1].shader_module())
+                .name(main_function_name),
+        ];
+
+        let dynamic_stages = [DynamicState::VIEWPORT, DynamicState::SCISSOR];
+
+        let dynamic_state_info =
+            PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_stages);
+
+        let vertex_bindings = [Vertex::binding_description()];
+        let vertex_attributes = Vertex::attribute_descriptions();
+
+        let vertex_input_info = PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&vertex_bindings)
+            .vertex_attribute_descriptions(&vertex_attributes);
+
+        let input_assembly_info = PipelineInputAssemblyStateCreateInfo::default()
+            .topology(PrimitiveTopology::TRIANGLE_LIST)
+            .primitive_restart_enable(false);
+
+        let viewports = vec![Viewport::default()
+            .x(0.0)
+            .y(0.0)
+            .height(render_pass.swapchain().extent().height as f32)
+            .width(render_pass.swapchain().extent().width as f32)
+            .min_depth(0.0)
+            .max_depth(1.0)];
+
+        let scissors = vec![Rect2D::default()
+            .extent(render_pass.swapchain().extent())
+            .offset(Offset2D::default().x(0).y(0))];
+
+        let viewport_info = PipelineViewportStateCreateInfo::default()
+            .viewports(&viewports)
+            .scissors(&scissors);
+
+        let rasterizer_infos: Vec<_> = variants
+            .iter()
+            .map(|variant| {
+                PipelineRasterizationStateCreateInfo::default()
+                    .depth_clamp_enable(false)
+                    .rasterizer_discard_enable(false)
+                    .polygon_mode(variant.polygon_mode)
+                    .line_width(1.0)
+                    .cull_mode(variant.cull_mode)
+                    .front_face(FrontFace::CLOCKWISE)
+                    .depth_bias_enable(false)
+            })
+            .collect();
+
+        let multisample_info = PipelineMultisampleStateCreateInfo::default()
+            .sample_shading_enable(false)
+            .rasterization_samples(render_pass.samples());
+
+        let color_blend_attachments: Vec<Vec<_>> = variants
+            .iter()
+            .map(|variant| {
+                variant
+                    .blend_modes
+                    .iter()
+                    .map(BlendMode::attachment_state)
+                    .collect()
+            })
+            .collect();
+
+        let color_blend_infos: Vec<_> = color_blend_attachments
+            .iter()
+            .map(|attachments| {
+                PipelineColorBlendStateCreateInfo::default()
+                    .logic_op_enable(false)
+                    .attachments(attachments)
+            })
+            .collect();
+
+        let pipeline_layout_info =
+            PipelineLayoutCreateInfo::default().push_constant_ranges(push_constant_ranges);
+
+        let pipeline_layout = unsafe {
+            render_pass
+                .swapchain()
+                .device()
+                .device()
+                .create_pipeline_layout(&pipeline_layout_info, host_allocation_callbacks())
+        }
+        .with_context("creating pipeline layout", || {
+            format!("push_constant_ranges={:?}", push_constant_ranges)
+        })?;
+
+        // The state every variant shares.
+        let shared_info = GraphicsPipelineCreateInfo::default()
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly_info)
+            .viewport_state(&viewport_info)
+            .multisample_state(&multisample_info)
+            .layout(pipeline_layout)
+            .dynamic_state(&dynamic_state_info)
+            .render_pass(*render_pass.render_pass());
+
+        let logical_device = render_pass.swapchain().device();
+
+        let pipeline = if variants.len() > 1 && logical_device.supports_graphics_pipeline_library()
+        {
+            unsafe {
+                link_variants(
+                    logical_device,
+                    shared_info,
+                    &pipeline_shader_info,
+                    &rasterizer_infos,
+                    &color_blend_infos,
+                )
+            }
+        } else {
+            // The first variant is the base pipeline, every other one is created as its
+            // derivative so the driver can reuse the state they share.
+            let pipeline_info: Vec<_> = rasterizer_infos
+                .iter()
+                .zip(color_blend_infos.iter())
+                .enumerate()
+                .map(|(i, (rasterizer_info, color_blend_info))| {
+                    let create_info = shared_info
+                        .stages(&pipeline_shader_info)
+                        .rasterization_state(rasterizer_info)
+                        .color_blend_state(color_blend_info);
+
+                    if i == 0 {
+                        create_info
+                            .flags(PipelineCreateFlags::ALLOW_DERIVATIVES)
+                            .base_pipeline_index(-1)
+                    } else {
+                        create_info
+                            .flags(PipelineCreateFlags::DERIVATIVE)
+                            .base_pipeline_index(0)
+                    }
+                })
+                .collect();
+
+            unsafe {
+                pipeline_stats::create_graphics_pipelines(
+                    logical_device,
+                    PipelineCache::null(),
+                    &pipeline_info,
+                )
+            }
+        }
+        .inspect_err(|_| unsafe {
+            logical_device
+                .device()
+                .destroy_pipeline_layout(pipeline_layout, host_allocation_callbacks());
+        })
+        .with_context("creating graphics pipelines", || {
+            format!("variants={:?}", variants)
+        })?;
+
+        render_pass.swapchain().device().resource_tracker().track(
+            ResourceKind::Pipeline,
+            pipeline.len() as u64,
+            0,
+        );
+
+        Ok(GraphicsPipeline(Rc::new(InnerGraphicsPipeline {
+            viewports,
+            scissors,
+            pipeline_layout,
+            push_constant_ranges: push_constant_ranges.to_vec(),
+            pipeline,
+            render_pass,
+        })))
+    }
+
+    pub fn pipeline(&self) -> &[Pipeline] {
+        &self.0.pipeline
+    }
+
+    pub fn pipeline_layout(&self) -> PipelineLayout {
+        self.0.pipeline_layout
+    }
+
+    pub fn push_constant_ranges(&self) -> &[PushConstantRange] {
+        &self.0.push_constant_ranges
+    }
+
+    pub fn viewports(&self) -> &[Viewport] {
+        &self.0.viewports
+    }
+
+    pub fn scissors(&self) -> &[Rect2D] {
+        &self.0.scissors
+    }
+}
+
+/// Creates a pipeline per variant by linking pipeline libraries: one each for the vertex input
+/// and the fragment shader, shared by every variant, and one each for the pre-rasterization
+/// shaders and the fragment output of every variant, with its rasterization and blending.
+///
+/// `shared_info` holds the state every variant shares and `stages` the vertex and fragment
+/// shader, in that order. The libraries are destroyed once linked.
+///
+/// # Safety
+///
+/// The device needs `VK_EXT_graphics_pipeline_library` enabled, and the infos have to be valid
+/// for `vkCreateGraphicsPipelines`.
+unsafe fn link_variants(
+    logical_device: &LogicalDevice,
+    shared_info: GraphicsPipelineCreateInfo,
+    stages: &[PipelineShaderStageCreateInfo; 2],
+    rasterizer_infos: &[PipelineRasterizationStateCreateInfo],
+    color_blend_infos: &[PipelineColorBlendStateCreateInfo],
+) -> VkResult<Vec<Pipeline>> {
+    let part = |flags| GraphicsPipelineLibraryCreateInfoEXT::default().flags(flags);
+
+    let mut vertex_input = part(GraphicsPipelineLibraryFlagsEXT::VERTEX_INPUT_INTERFACE);
+    let mut fragment_shader = part(GraphicsPipelineLibraryFlagsEXT::FRAGMENT_SHADER);
+    let mut pre_rasterization =
+        vec![
+            part(GraphicsPipelineLibraryFlagsEXT::PRE_RASTERIZATION_SHADERS);
+            rasterizer_infos.len()
+        ];
+    let mut fragment_output = vec![
+        part(GraphicsPipelineLibraryFlagsEXT::FRAGMENT_OUTPUT_INTERFACE);
+        color_blend_infos.len()
+    ];
+
+    // Every library only reads the state of its part, but the shader stages have to match it.
+    let library_info = shared_info.flags(PipelineCreateFlags::LIBRARY_KHR);
+    let mut library_infos = vec![
+        library_info.push_next(&mut vertex_input),
+        library_info
+            .stages(&stages[1..])
+            .push_next(&mut fragment_shader),
+    ];
+
+    library_infos.extend(pre_rasterization.iter_mut().zip(rasterizer_infos).map(
+        |(part, rasterizer_info)| {
+            library_info
+                .stages(&stages[..1])
+                .rasterization_state(rasterizer_info)
+                .push_next(part)
+        },
+    ));
+    library_infos.extend(fragment_output.iter_mut().zip(color_blend_infos).map(
+        |(part, color_blend_info)| {
+            library_info
+                .color_blend_state(color_blend_info)
+                .push_next(part)
+        },
+    ));
+
+    let libraries = pipeline_stats::create_pipeline_libraries(
+        logical_device,
+        PipelineCache::null(),
+        &library_infos,
+    )?;
+
+    let variants = rasterizer_infos.len();
+    let variant_libraries: Vec<_> = (0..variants)
+        .map(|i| {
+            [
+                libraries[0],
+                libraries[2 + i],
+                libraries[1],
+                libraries[2 + variants + i],
+            ]
+        })
+        .collect();
+    let mut link_infos: Vec<_> = variant_libraries
+        .iter()
+        .map(|libraries| PipelineLibraryCreateInfoKHR::default().libraries(libraries))
+        .collect();
+    let pipeline_infos: Vec<_> = link_infos
+        .iter_mut()
+        .map(|link_info| {
+            GraphicsPipelineCreateInfo::default()
+                .layout(shared_info.layout)
+                .push_next(link_info)
+        })
+        .collect();
+
+    let pipelines = pipeline_stats::create_graphics_pipelines(
+        logical_device,
+        PipelineCache::null(),
+        &pipeline_infos,
+    );
+
+    // Linked pipelines don't need their libraries anymore.
+    for library in libraries {
+        logical_device
+            .device()
+            .destroy_pipeline(library, host_allocation_callbacks());
+    }
+
+    pipelines
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineVariant {
+    pub polygon_mode: PolygonMode,
+    pub cull_mode: CullModeFlags,
+    pub blend_modes: Vec<BlendMode>,
+}
+
+impl Default for PipelineVariant {
+    fn default() -> Self {
+        Self {
+            polygon_mode: PolygonMode::FILL,
+            cull_mode: CullModeFlags::BACK,
+            blend_modes: vec![BlendMode::Opaque],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PipelineError {
+    Vulkan(ErrorCtx),
+    InvalidShader(ErrorCtx<io::Error>),
+}
+
+impl From<ErrorCtx> for PipelineError {
+    fn from(value: ErrorCtx) -> Self {
+        Self::Vulkan(value)
+    }
+}
+
+impl From<ErrorCtx<io::Error>> for PipelineError {
+    fn from(value: ErrorCtx<io::Error>) -> Self {
+        Self::InvalidShader(value)
+    }
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Vulkan(e) => e.fmt(f),
+            Self::InvalidShader(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for PipelineError {}
+
+struct InnerGraphicsPipeline {
+    pipeline_layout: PipelineLayout,
+    push_constant_ranges: Vec<PushConstantRange>,
+    pipeline: Vec<Pipeline>,
+    viewports: Vec<Viewport>,
+    scissors: Vec<Rect2D>,
+
+    #[allow(dead_code)]
+    render_pass: RenderPass,
+}
+
+impl Drop for InnerGraphicsPipeline {
+    fn drop(&mut self) {
+        teardown_trace::record(
+            "GraphicsPipeline",
+            self.pipeline
+                .iter()
+                .map(|handle| handle.as_raw())
+                .chain([self.pipeline_layout.as_raw()]),
+        );
+
+        unsafe {
+            for pipeline in self.pipeline.iter() {
+                self.render_pass
+                    .swapchain()
+                    .device()
+                    .device()
+                    .destroy_pipeline(*pipeline, host_allocation_callbacks());
+            }
+
+            self.render_pass
+                .swapchain()
+                .device()
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout, host_allocation_callbacks());
+        }
+
+        self.render_pass
+            .swapchain()
+            .device()
+            .resource_tracker()
+            .untrack(ResourceKind::Pipeline, self.pipeline.len() as u64, 0);
+    }
+}
@@ -0,0 +1,1074 @@
+//! Image-based lighting precomputed once at startup from an equirectangular HDR panorama: the
+//! environment cubemap, its diffuse irradiance, the specular environment prefiltered per
+//! roughness into mips, and the BRDF lookup table of the split-sum approximation.
+
+use std::{error, fmt, mem, rc::Rc};
+
+use ash::vk::{
+    self, AccessFlags, BufferImageCopy, CommandBuffer, CommandBufferAllocateInfo,
+    CommandBufferBeginInfo, CommandBufferLevel, CommandBufferUsageFlags, ComputePipelineCreateInfo,
+    DependencyFlags, DescriptorImageInfo, DescriptorPoolCreateInfo, DescriptorPoolSize,
+    DescriptorSetAllocateInfo, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo,
+    DescriptorType, FenceCreateInfo, Filter, Format, Handle, ImageBlit, ImageCreateFlags,
+    ImageCreateInfo, ImageLayout, ImageMemoryBarrier, ImageTiling, ImageType, ImageUsageFlags,
+    ImageViewCreateInfo, ImageViewType, MemoryAllocateInfo, MemoryPropertyFlags, PipelineBindPoint,
+    PipelineCache, PipelineLayoutCreateInfo, PipelineShaderStageCreateInfo, PipelineStageFlags,
+    PushConstantRange, SampleCountFlags, SamplerAddressMode, ShaderStageFlags, SharingMode,
+    SubmitInfo, WriteDescriptorSet,
+};
+
+use crate::{
+    api2::{div_round_up, host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        command_pool::CommandPool,
+        image_desc::{mip_level_count, ImageDesc},
+        logical_device::LogicalDevice,
+        pipeline_stats,
+        resource_stats::ResourceKind,
+        sampler::{Sampler, SamplerBuilder, SamplerCache},
+        shader_cache::ShaderCache,
+        staging_ring::{StagingError, StagingRing},
+        teardown_trace,
+    },
+    types::Pod,
+};
+
+/// The format of every IBL texture, which Vulkan guarantees for storage, filtering and blits.
+pub const IBL_FORMAT: Format = Format::R16G16B16A16_SFLOAT;
+
+/// The workgroup size of the IBL shaders along X and Y.
+const GROUP_SIZE: u32 = 8;
+
+/// The resolutions and quality of the precomputed textures.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct IblSettings {
+    /// The face size of the environment cubemap the panorama is projected onto.
+    pub environment_size: u32,
+    /// The face size of the irradiance cubemap. Irradiance is smooth, so this can be tiny.
+    pub irradiance_size: u32,
+    /// The face size of the base mip of the prefiltered cubemap.
+    pub prefiltered_size: u32,
+    /// Mips of the prefiltered cubemap, mip `i` holding roughness `i / (mips - 1)`.
+    pub prefiltered_mip_levels: u32,
+    pub brdf_lut_size: u32,
+    /// Importance samples per texel of the prefiltered cubemap and the BRDF LUT.
+    pub sample_count: u32,
+}
+
+impl Default for IblSettings {
+    fn default() -> Self {
+        Self {
+            environment_size: 512,
+            irradiance_size: 32,
+            prefiltered_size: 128,
+            prefiltered_mip_levels: 5,
+            brdf_lut_size: 512,
+            sample_count: 1024,
+        }
+    }
+}
+
+impl IblSettings {
+    /// The full mip chain of the environment cubemap, sampled by the prefilter pass.
+    pub fn environment_mip_levels(&self) -> u32 {
+        mip_level_count(self.environment_size, self.environment_size)
+    }
+
+    /// The prefiltered mips, capped at what the base size allows.
+    pub fn clamped_prefiltered_mip_levels(&self) -> u32 {
+        let full_chain = mip_level_count(self.prefiltered_size, self.prefiltered_size);
+        self.prefiltered_mip_levels.clamp(1, full_chain)
+    }
+}
+
+/// An equirectangular HDR panorama, e.g. decoded from a `.hdr` file.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EquirectangularImage<'a> {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA pixels row by row from the top, the alpha is ignored.
+    pub pixels: &'a [f32],
+}
+
+/// The SPIR-V of `shaders/equirect_to_cube.comp`, `shaders/irradiance.comp`,
+/// `shaders/prefilter.comp` and `shaders/brdf_lut.comp`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IblShaders<'a> {
+    pub equirect_to_cube: &'a [u32],
+    pub irradiance: &'a [u32],
+    pub prefilter: &'a [u32],
+    pub brdf_lut: &'a [u32],
+}
+
+/// The push constants of the IBL shaders, mirrors `ibl_common.glsl`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct IblPushConstants {
+    pub roughness: f32,
+    pub environment_size: f32,
+    pub sample_count: u32,
+}
+
+unsafe impl Pod for IblPushConstants {}
+
+/// The textures a PBR material samples for ambient lighting, all in the
+/// `SHADER_READ_ONLY_OPTIMAL` layout and sharing one clamping, trilinear sampler.
+#[derive(Clone)]
+pub struct IblTextures(Rc<InnerIblTextures>);
+
+impl IblTextures {
+    /// Uploads `source` through `staging` and renders every texture with compute passes on the
+    /// device's queue, waiting for them to finish before returning.
+    ///
+    /// The pipelines and intermediate images only live for the duration of the call.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate(
+        logical_device: LogicalDevice,
+        command_pool: &CommandPool,
+        shader_cache: &ShaderCache,
+        sampler_cache: &SamplerCache,
+        staging: &mut StagingRing,
+        source: EquirectangularImage,
+        shaders: IblShaders,
+        settings: IblSettings,
+    ) -> Result<Self, IblError> {
+        let expected = source.width as usize * source.height as usize * 4;
+
+        if source.pixels.len() != expected {
+            return Err(IblError::SizeMismatch {
+                expected,
+                found: source.pixels.len(),
+            });
+        }
+
+        let texels: Vec<u8> = source
+            .pixels
+            .iter()
+            .flat_map(|&value| f32_to_f16(value).to_ne_bytes())
+            .collect();
+
+        let upload_offset = staging
+            .write(&texels, 8)
+            .context("staging equirectangular image")?;
+
+        let equirect = IblImage::new(
+            logical_device.clone(),
+            ImageDesc::new(source.width, source.height),
+            ImageUsageFlags::SAMPLED | ImageUsageFlags::TRANSFER_DST,
+        )?;
+        let environment = IblImage::new(
+            logical_device.clone(),
+            ImageDesc::cube(settings.environment_size)
+                .mip_levels(settings.environment_mip_levels()),
+            ImageUsageFlags::STORAGE
+                | ImageUsageFlags::SAMPLED
+                | ImageUsageFlags::TRANSFER_SRC
+                | ImageUsageFlags::TRANSFER_DST,
+        )?;
+        let irradiance = IblImage::new(
+            logical_device.clone(),
+            ImageDesc::cube(settings.irradiance_size),
+            ImageUsageFlags::STORAGE | ImageUsageFlags::SAMPLED | ImageUsageFlags::TRANSFER_SRC,
+        )?;
+        let prefiltered = IblImage::new(
+            logical_device.clone(),
+            ImageDesc::cube(settings.prefiltered_size)
+                .mip_levels(settings.clamped_prefiltered_mip_levels()),
+            ImageUsageFlags::STORAGE | ImageUsageFlags::SAMPLED | ImageUsageFlags::TRANSFER_SRC,
+        )?;
+        let brdf_lut = IblImage::new(
+            logical_device.clone(),
+            ImageDesc::new(settings.brdf_lut_size, settings.brdf_lut_size),
+            ImageUsageFlags::STORAGE | ImageUsageFlags::SAMPLED | ImageUsageFlags::TRANSFER_SRC,
+        )?;
+
+        // The panorama wraps around horizontally but not over the poles.
+        let equirect_sampler = sampler_cache
+            .get_or_create(
+                SamplerBuilder::default()
+                    .address_modes(
+                        SamplerAddressMode::REPEAT,
+                        SamplerAddressMode::CLAMP_TO_EDGE,
+                        SamplerAddressMode::CLAMP_TO_EDGE,
+                    )
+                    .config,
+            )
+            .context("creating equirectangular sampler")?;
+        let sampler = sampler_cache
+            .get_or_create(
+                SamplerBuilder::default()
+                    .address_mode(SamplerAddressMode::CLAMP_TO_EDGE)
+                    .config,
+            )
+            .context("creating IBL sampler")?;
+
+        // One set per prefiltered mip and one for each other pass.
+        let mut passes = Passes::new(
+            logical_device.clone(),
+            command_pool,
+            shader_cache,
+            shaders,
+            prefiltered.desc.mip_levels + 3,
+        )?;
+
+        // Sampled views of the sources, storage views of the targets.
+        let equirect_set = passes.descriptor_set(
+            (equirect.view, &equirect_sampler),
+            environment.storage_view(0)?,
+        )?;
+        let irradiance_set =
+            passes.descriptor_set((environment.view, &sampler), irradiance.storage_view(0)?)?;
+        let prefilter_sets = (0..prefiltered.desc.mip_levels)
+            .map(|mip| {
+                passes.descriptor_set((environment.view, &sampler), prefiltered.storage_view(mip)?)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let brdf_lut_set = passes.descriptor_set((brdf_lut.view, &sampler), brdf_lut.view)?;
+
+        let device = logical_device.device();
+        let command_buffer = passes.command_buffer;
+
+        unsafe {
+            device
+                .begin_command_buffer(
+                    command_buffer,
+                    &CommandBufferBeginInfo::default()
+                        .flags(CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+                )
+                .context("beginning IBL command buffer")?;
+
+            // Upload the panorama.
+            equirect.barrier(
+                &passes,
+                (ImageLayout::UNDEFINED, ImageLayout::TRANSFER_DST_OPTIMAL),
+                (AccessFlags::empty(), AccessFlags::TRANSFER_WRITE),
+                (
+                    PipelineStageFlags::TOP_OF_PIPE,
+                    PipelineStageFlags::TRANSFER,
+                ),
+                0..1,
+            );
+            device.cmd_copy_buffer_to_image(
+                command_buffer,
+                staging.buffer(),
+                equirect.image,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[BufferImageCopy::default()
+                    .buffer_offset(upload_offset)
+                    .image_subresource(equirect.desc.subresource_layers(0))
+                    .image_extent(equirect.desc.extent(0))],
+            );
+            equirect.barrier(
+                &passes,
+                (
+                    ImageLayout::TRANSFER_DST_OPTIMAL,
+                    ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                ),
+                (AccessFlags::TRANSFER_WRITE, AccessFlags::SHADER_READ),
+                (
+                    PipelineStageFlags::TRANSFER,
+                    PipelineStageFlags::COMPUTE_SHADER,
+                ),
+                0..1,
+            );
+
+            // Every target is written by compute shaders first.
+            for target in [&environment, &irradiance, &prefiltered, &brdf_lut] {
+                target.barrier(
+                    &passes,
+                    (ImageLayout::UNDEFINED, ImageLayout::GENERAL),
+                    (AccessFlags::empty(), AccessFlags::SHADER_WRITE),
+                    (
+                        PipelineStageFlags::TOP_OF_PIPE,
+                        PipelineStageFlags::COMPUTE_SHADER,
+                    ),
+                    0..target.desc.mip_levels,
+                );
+            }
+
+            passes.dispatch(
+                Pass::EquirectToCube,
+                equirect_set,
+                IblPushConstants::default(),
+                &environment.desc,
+                0,
+            );
+
+            // Blit the mip chain so the passes below can sample the environment without aliasing.
+            environment.barrier(
+                &passes,
+                (ImageLayout::GENERAL, ImageLayout::TRANSFER_SRC_OPTIMAL),
+                (AccessFlags::SHADER_WRITE, AccessFlags::TRANSFER_READ),
+                (
+                    PipelineStageFlags::COMPUTE_SHADER,
+                    PipelineStageFlags::TRANSFER,
+                ),
+                0..1,
+            );
+            environment.barrier(
+                &passes,
+                (ImageLayout::GENERAL, ImageLayout::TRANSFER_DST_OPTIMAL),
+                (AccessFlags::empty(), AccessFlags::TRANSFER_WRITE),
+                (
+                    PipelineStageFlags::COMPUTE_SHADER,
+                    PipelineStageFlags::TRANSFER,
+                ),
+                1..environment.desc.mip_levels,
+            );
+
+            for mip in 1..environment.desc.mip_levels {
+                device.cmd_blit_image(
+                    command_buffer,
+                    environment.image,
+                    ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    environment.image,
+                    ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[ImageBlit::default()
+                        .src_subresource(environment.desc.subresource_layers(mip - 1))
+                        .src_offsets(environment.desc.bounds(mip - 1))
+                        .dst_subresource(environment.desc.subresource_layers(mip))
+                        .dst_offsets(environment.desc.bounds(mip))],
+                    Filter::LINEAR,
+                );
+                environment.barrier(
+                    &passes,
+                    (
+                        ImageLayout::TRANSFER_DST_OPTIMAL,
+                        ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    ),
+                    (AccessFlags::TRANSFER_WRITE, AccessFlags::TRANSFER_READ),
+                    (PipelineStageFlags::TRANSFER, PipelineStageFlags::TRANSFER),
+                    mip..mip + 1,
+                );
+            }
+
+            environment.barrier(
+                &passes,
+                (
+                    ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                ),
+                (AccessFlags::TRANSFER_WRITE, AccessFlags::SHADER_READ),
+                (
+                    PipelineStageFlags::TRANSFER,
+                    PipelineStageFlags::COMPUTE_SHADER,
+                ),
+                0..environment.desc.mip_levels,
+            );
+
+            let environment_size = settings.environment_size as f32;
+
+            passes.dispatch(
+                Pass::Irradiance,
+                irradiance_set,
+                IblPushConstants {
+                    environment_size,
+                    ..Default::default()
+                },
+                &irradiance.desc,
+                0,
+            );
+
+            let roughest_mip = (prefiltered.desc.mip_levels - 1).max(1) as f32;
+
+            for (mip, set) in prefilter_sets.into_iter().enumerate() {
+                passes.dispatch(
+                    Pass::Prefilter,
+                    set,
+                    IblPushConstants {
+                        roughness: mip as f32 / roughest_mip,
+                        environment_size,
+                        sample_count: settings.sample_count,
+                    },
+                    &prefiltered.desc,
+                    mip as u32,
+                );
+            }
+
+            passes.dispatch(
+                Pass::BrdfLut,
+                brdf_lut_set,
+                IblPushConstants {
+                    sample_count: settings.sample_count,
+                    ..Default::default()
+                },
+                &brdf_lut.desc,
+                0,
+            );
+
+            for target in [&irradiance, &prefiltered, &brdf_lut] {
+                target.barrier(
+                    &passes,
+                    (ImageLayout::GENERAL, ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+                    (AccessFlags::SHADER_WRITE, AccessFlags::SHADER_READ),
+                    (
+                        PipelineStageFlags::COMPUTE_SHADER,
+                        PipelineStageFlags::FRAGMENT_SHADER,
+                    ),
+                    0..target.desc.mip_levels,
+                );
+            }
+
+            device
+                .end_command_buffer(command_buffer)
+                .context("ending IBL command buffer")?;
+        }
+
+        passes.submit(staging)?;
+
+        Ok(Self(Rc::new(InnerIblTextures {
+            environment,
+            irradiance,
+            prefiltered,
+            brdf_lut,
+            sampler,
+        })))
+    }
+
+    /// The environment cubemap with its full mip chain, e.g. for a skybox.
+    pub fn environment(&self) -> DescriptorImageInfo {
+        self.0.image_info(&self.0.environment)
+    }
+
+    /// The cosine-convolved environment, sampled with the surface normal for diffuse lighting.
+    pub fn irradiance(&self) -> DescriptorImageInfo {
+        self.0.image_info(&self.0.irradiance)
+    }
+
+    /// The specular environment, sampled with the reflection vector at mip
+    /// `roughness * (prefiltered_mip_levels - 1)`.
+    pub fn prefiltered(&self) -> DescriptorImageInfo {
+        self.0.image_info(&self.0.prefiltered)
+    }
+
+    pub fn prefiltered_mip_levels(&self) -> u32 {
+        self.0.prefiltered.desc.mip_levels
+    }
+
+    /// The scale (red) and bias (green) to apply to F0, sampled at `(n·v, roughness)`.
+    pub fn brdf_lut(&self) -> DescriptorImageInfo {
+        self.0.image_info(&self.0.brdf_lut)
+    }
+
+    /// Every texture by name with its image, all in [IBL_FORMAT] and the
+    /// `SHADER_READ_ONLY_OPTIMAL` layout, and copyable from to save them.
+    pub fn images(&self) -> [(&'static str, vk::Image, ImageDesc); 4] {
+        [
+            ("environment", &self.0.environment),
+            ("irradiance", &self.0.irradiance),
+            ("prefiltered", &self.0.prefiltered),
+            ("brdf_lut", &self.0.brdf_lut),
+        ]
+        .map(|(name, image)| (name, image.image, image.desc))
+    }
+}
+
+struct InnerIblTextures {
+    environment: IblImage,
+    irradiance: IblImage,
+    prefiltered: IblImage,
+    brdf_lut: IblImage,
+    sampler: Sampler,
+}
+
+impl InnerIblTextures {
+    fn image_info(&self, image: &IblImage) -> DescriptorImageInfo {
+        DescriptorImageInfo::default()
+            .sampler(*self.sampler.sampler())
+            .image_view(image.view)
+            .image_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+    }
+}
+
+/// A device-local image with a sampled view of every mip and layer.
+struct IblImage {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+    size: vk::DeviceSize,
+    desc: ImageDesc,
+
+    logical_device: LogicalDevice,
+}
+
+impl IblImage {
+    fn new(
+        logical_device: LogicalDevice,
+        desc: ImageDesc,
+        usage: ImageUsageFlags,
+    ) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+
+        let image = unsafe {
+            device.create_image(
+                &ImageCreateInfo::default()
+                    .flags(if desc.cube {
+                        ImageCreateFlags::CUBE_COMPATIBLE
+                    } else {
+                        ImageCreateFlags::empty()
+                    })
+                    .image_type(ImageType::TYPE_2D)
+                    .format(IBL_FORMAT)
+                    .extent(desc.extent(0))
+                    .mip_levels(desc.mip_levels)
+                    .array_layers(desc.array_layers)
+                    .samples(SampleCountFlags::TYPE_1)
+                    .tiling(ImageTiling::OPTIMAL)
+                    .usage(usage)
+                    .sharing_mode(SharingMode::EXCLUSIVE)
+                    .initial_layout(ImageLayout::UNDEFINED),
+                host_allocation_callbacks(),
+            )
+        }
+        .with_context("creating IBL image", || format!("{:?}", desc))?;
+
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+
+        let mut ibl_image = Self {
+            image,
+            memory: vk::DeviceMemory::null(),
+            view: vk::ImageView::null(),
+            size: requirements.size,
+            desc,
+            logical_device: logical_device.clone(),
+        };
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::Image, 1, requirements.size);
+
+        let memory_type_index = logical_device
+            .physical_device()
+            .find_memory_type(
+                requirements.memory_type_bits,
+                MemoryPropertyFlags::DEVICE_LOCAL,
+            )
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            .with_context("finding IBL image memory", || {
+                format!("memory_type_bits={:#x}", requirements.memory_type_bits)
+            })?;
+
+        ibl_image.memory = unsafe {
+            device.allocate_memory(
+                &MemoryAllocateInfo::default()
+                    .allocation_size(requirements.size)
+                    .memory_type_index(memory_type_index),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("allocating IBL image memory")?;
+
+        unsafe { device.bind_image_memory(image, ibl_image.memory, 0) }
+            .context("binding IBL image memory")?;
+
+        ibl_image.view = ibl_image.create_view(
+            if desc.cube {
+                ImageViewType::CUBE
+            } else {
+                ImageViewType::TYPE_2D
+            },
+            0..desc.mip_levels,
+        )?;
+
+        Ok(ibl_image)
+    }
+
+    /// Creates a view of a single mip of every face for `imageStore`, which can't write to cube
+    /// views. The caller destroys it.
+    fn storage_view(&self, mip: u32) -> Result<vk::ImageView, ErrorCtx> {
+        self.create_view(ImageViewType::TYPE_2D_ARRAY, mip..mip + 1)
+    }
+
+    fn create_view(
+        &self,
+        view_type: ImageViewType,
+        mips: std::ops::Range<u32>,
+    ) -> Result<vk::ImageView, ErrorCtx> {
+        let view = unsafe {
+            self.logical_device.device().create_image_view(
+                &ImageViewCreateInfo::default()
+                    .image(self.image)
+                    .view_type(view_type)
+                    .format(IBL_FORMAT)
+                    .subresource_range(self.desc.subresource_range(mips.clone())),
+                host_allocation_callbacks(),
+            )
+        }
+        .with_context("creating IBL image view", || {
+            format!("view_type={:?}, mips={:?}", view_type, mips)
+        })?;
+
+        self.logical_device
+            .resource_tracker()
+            .track(ResourceKind::ImageView, 1, 0);
+
+        Ok(view)
+    }
+
+    /// Records a layout transition of `mips` of every layer.
+    fn barrier(
+        &self,
+        passes: &Passes,
+        (old_layout, new_layout): (ImageLayout, ImageLayout),
+        (src_access, dst_access): (AccessFlags, AccessFlags),
+        (src_stage, dst_stage): (PipelineStageFlags, PipelineStageFlags),
+        mips: std::ops::Range<u32>,
+    ) {
+        if mips.is_empty() {
+            return;
+        }
+
+        unsafe {
+            self.logical_device.device().cmd_pipeline_barrier(
+                passes.command_buffer,
+                src_stage,
+                dst_stage,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &[ImageMemoryBarrier::default()
+                    .old_layout(old_layout)
+                    .new_layout(new_layout)
+                    .src_access_mask(src_access)
+                    .dst_access_mask(dst_access)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .image(self.image)
+                    .subresource_range(self.desc.subresource_range(mips))],
+            );
+        }
+    }
+}
+
+impl Drop for IblImage {
+    fn drop(&mut self) {
+        teardown_trace::record("IblImage", [self.view.as_raw(), self.image.as_raw()]);
+
+        let device = self.logical_device.device();
+        let tracker = self.logical_device.resource_tracker();
+
+        unsafe {
+            if self.view != vk::ImageView::null() {
+                device.destroy_image_view(self.view, host_allocation_callbacks());
+                tracker.untrack(ResourceKind::ImageView, 1, 0);
+            }
+
+            device.destroy_image(self.image, host_allocation_callbacks());
+            device.free_memory(self.memory, host_allocation_callbacks());
+        }
+
+        tracker.untrack(ResourceKind::Image, 1, self.size);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Pass {
+    EquirectToCube,
+    Irradiance,
+    Prefilter,
+    BrdfLut,
+}
+
+/// The pipelines, descriptors and command buffer of the precompute passes, destroyed once the
+/// textures are done or generation fails.
+///
+/// Every pass reads a combined image sampler at binding 0 and writes a storage image at
+/// binding 1, so they share one descriptor set layout.
+struct Passes {
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_sets: u32,
+    storage_views: Vec<vk::ImageView>,
+    pipeline_layout: vk::PipelineLayout,
+    pipelines: Vec<vk::Pipeline>,
+    command_pool: vk::CommandPool,
+    command_buffer: CommandBuffer,
+    fence: vk::Fence,
+
+    logical_device: LogicalDevice,
+}
+
+impl Passes {
+    /// Creates the passes with room for `max_sets` descriptor sets.
+    fn new(
+        logical_device: LogicalDevice,
+        command_pool: &CommandPool,
+        shader_cache: &ShaderCache,
+        shaders: IblShaders,
+        max_sets: u32,
+    ) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+
+        let mut passes = Self {
+            descriptor_set_layout: vk::DescriptorSetLayout::null(),
+            descriptor_pool: vk::DescriptorPool::null(),
+            descriptor_sets: 0,
+            storage_views: Vec::new(),
+            pipeline_layout: vk::PipelineLayout::null(),
+            pipelines: Vec::new(),
+            command_pool: *command_pool.command_pool(),
+            command_buffer: CommandBuffer::null(),
+            fence: vk::Fence::null(),
+            logical_device: logical_device.clone(),
+        };
+
+        let bindings = [
+            DescriptorSetLayoutBinding::default()
+                .binding(0)
+                .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(1)
+                .stage_flags(ShaderStageFlags::COMPUTE),
+            DescriptorSetLayoutBinding::default()
+                .binding(1)
+                .descriptor_type(DescriptorType::STORAGE_IMAGE)
+                .descriptor_count(1)
+                .stage_flags(ShaderStageFlags::COMPUTE),
+        ];
+
+        passes.descriptor_set_layout = unsafe {
+            device.create_descriptor_set_layout(
+                &DescriptorSetLayoutCreateInfo::default().bindings(&bindings),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating IBL descriptor set layout")?;
+
+        let pool_sizes = [
+            DescriptorPoolSize::default()
+                .ty(DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(max_sets),
+            DescriptorPoolSize::default()
+                .ty(DescriptorType::STORAGE_IMAGE)
+                .descriptor_count(max_sets),
+        ];
+
+        passes.descriptor_pool = unsafe {
+            device.create_descriptor_pool(
+                &DescriptorPoolCreateInfo::default()
+                    .max_sets(max_sets)
+                    .pool_sizes(&pool_sizes),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating IBL descriptor pool")?;
+
+        let set_layouts = [passes.descriptor_set_layout];
+        let push_constant_ranges = [PushConstantRange::default()
+            .stage_flags(ShaderStageFlags::COMPUTE)
+            .size(mem::size_of::<IblPushConstants>() as u32)];
+
+        passes.pipeline_layout = unsafe {
+            device.create_pipeline_layout(
+                &PipelineLayoutCreateInfo::default()
+                    .set_layouts(&set_layouts)
+                    .push_constant_ranges(&push_constant_ranges),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating IBL pipeline layout")?;
+
+        // In the order of `Pass`.
+        let shader_modules = [
+            shaders.equirect_to_cube,
+            shaders.irradiance,
+            shaders.prefilter,
+            shaders.brdf_lut,
+        ]
+        .into_iter()
+        .map(|code| shader_cache.get_or_create(code))
+        .collect::<Result<Vec<_>, _>>()
+        .context("creating IBL shader modules")?;
+
+        let create_infos: Vec<_> = shader_modules
+            .iter()
+            .map(|shader_module| {
+                ComputePipelineCreateInfo::default()
+                    .stage(
+                        PipelineShaderStageCreateInfo::default()
+                            .stage(ShaderStageFlags::COMPUTE)
+                            .module(*shader_module.shader_module())
+                            .name(c"main"),
+                    )
+                    .layout(passes.pipeline_layout)
+            })
+            .collect();
+
+        passes.pipelines = unsafe {
+            pipeline_stats::create_compute_pipelines(
+                &logical_device,
+                PipelineCache::null(),
+                &create_infos,
+            )
+        }
+        .context("creating IBL compute pipelines")?;
+
+        logical_device.resource_tracker().track(
+            ResourceKind::Pipeline,
+            passes.pipelines.len() as u64,
+            0,
+        );
+
+        passes.command_buffer = unsafe {
+            device.allocate_command_buffers(
+                &CommandBufferAllocateInfo::default()
+                    .command_pool(passes.command_pool)
+                    .level(CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(1),
+            )
+        }
+        .context("allocating IBL command buffer")?[0];
+
+        passes.fence = unsafe {
+            device.create_fence(&FenceCreateInfo::default(), host_allocation_callbacks())
+        }
+        .context("creating IBL fence")?;
+
+        Ok(passes)
+    }
+
+    /// Allocates a set sampling `source` and writing `target`, taking ownership of `target`
+    /// unless it is the source's own view.
+    fn descriptor_set(
+        &mut self,
+        (source, sampler): (vk::ImageView, &Sampler),
+        target: vk::ImageView,
+    ) -> Result<vk::DescriptorSet, ErrorCtx> {
+        let device = self.logical_device.device();
+
+        if target != source {
+            self.storage_views.push(target);
+        }
+
+        let set_layouts = [self.descriptor_set_layout];
+        let descriptor_set = unsafe {
+            device.allocate_descriptor_sets(
+                &DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(self.descriptor_pool)
+                    .set_layouts(&set_layouts),
+            )
+        }
+        .with_context("allocating IBL descriptor set", || {
+            format!("allocated={}", self.descriptor_sets)
+        })?[0];
+
+        self.descriptor_sets += 1;
+        self.logical_device
+            .resource_tracker()
+            .track(ResourceKind::DescriptorSet, 1, 0);
+
+        let source_info = [DescriptorImageInfo::default()
+            .sampler(*sampler.sampler())
+            .image_view(source)
+            .image_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)];
+        let target_info = [DescriptorImageInfo::default()
+            .image_view(target)
+            .image_layout(ImageLayout::GENERAL)];
+
+        let mut writes = vec![WriteDescriptorSet::default()
+            .dst_set(descriptor_set)
+            .dst_binding(1)
+            .descriptor_type(DescriptorType::STORAGE_IMAGE)
+            .image_info(&target_info)];
+
+        // The BRDF LUT has no source, and its own view is in the wrong layout to be one.
+        if target != source {
+            writes.push(
+                WriteDescriptorSet::default()
+                    .dst_set(descriptor_set)
+                    .dst_binding(0)
+                    .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(&source_info),
+            );
+        }
+
+        unsafe { device.update_descriptor_sets(&writes, &[]) };
+
+        Ok(descriptor_set)
+    }
+
+    /// Records `pass` writing `mip` of the target described by `desc`, then makes the writes
+    /// visible to the following passes and transfers.
+    fn dispatch(
+        &self,
+        pass: Pass,
+        descriptor_set: vk::DescriptorSet,
+        push_constants: IblPushConstants,
+        desc: &ImageDesc,
+        mip: u32,
+    ) {
+        let device = self.logical_device.device();
+        let extent = desc.extent(mip);
+
+        unsafe {
+            device.cmd_bind_pipeline(
+                self.command_buffer,
+                PipelineBindPoint::COMPUTE,
+                self.pipelines[pass as usize],
+            );
+            device.cmd_bind_descriptor_sets(
+                self.command_buffer,
+                PipelineBindPoint::COMPUTE,
+                self.pipeline_layout,
+                0,
+                &[descriptor_set],
+                &[],
+            );
+            device.cmd_push_constants(
+                self.command_buffer,
+                self.pipeline_layout,
+                ShaderStageFlags::COMPUTE,
+                0,
+                push_constants.bytes_of(),
+            );
+            device.cmd_dispatch(
+                self.command_buffer,
+                div_round_up(extent.width, GROUP_SIZE),
+                div_round_up(extent.height, GROUP_SIZE),
+                desc.array_layers,
+            );
+        }
+    }
+
+    /// Submits the recorded passes and waits for them, so the staging space can be reused.
+    fn submit(&mut self, staging: &mut StagingRing) -> Result<(), IblError> {
+        let device = self.logical_device.device();
+        let command_buffers = [self.command_buffer];
+
+        unsafe {
+            device
+                .queue_submit(
+                    *self.logical_device.queue(),
+                    &[SubmitInfo::default().command_buffers(&command_buffers)],
+                    self.fence,
+                )
+                .context("submitting IBL passes")?;
+
+            staging.submit(self.fence);
+
+            device
+                .wait_for_fences(&[self.fence], true, u64::MAX)
+                .context("waiting for IBL passes")?;
+        }
+
+        staging.retire().context("retiring IBL upload")?;
+
+        Ok(())
+    }
+}
+
+impl Drop for Passes {
+    fn drop(&mut self) {
+        teardown_trace::record(
+            "IblPasses",
+            self.pipelines.iter().map(|pipeline| pipeline.as_raw()),
+        );
+
+        let device = self.logical_device.device();
+        let tracker = self.logical_device.resource_tracker();
+
+        unsafe {
+            for &pipeline in &self.pipelines {
+                device.destroy_pipeline(pipeline, host_allocation_callbacks());
+            }
+
+            for &view in &self.storage_views {
+                device.destroy_image_view(view, host_allocation_callbacks());
+            }
+
+            if self.command_buffer != CommandBuffer::null() {
+                device.free_command_buffers(self.command_pool, &[self.command_buffer]);
+            }
+
+            device.destroy_fence(self.fence, host_allocation_callbacks());
+            device.destroy_pipeline_layout(self.pipeline_layout, host_allocation_callbacks());
+            device.destroy_descriptor_pool(self.descriptor_pool, host_allocation_callbacks());
+            device.destroy_descriptor_set_layout(
+                self.descriptor_set_layout,
+                host_allocation_callbacks(),
+            );
+        }
+
+        tracker.untrack(ResourceKind::Pipeline, self.pipelines.len() as u64, 0);
+        tracker.untrack(ResourceKind::ImageView, self.storage_views.len() as u64, 0);
+        tracker.untrack(ResourceKind::DescriptorSet, self.descriptor_sets as u64, 0);
+    }
+}
+
+/// Converts to a half float, rounding to nearest and saturating to infinity.
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+
+    if exponent == 0xff {
+        // Infinity, or NaN with a mantissa bit kept set.
+        let nan = if mantissa != 0 { 0x0200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+
+    let exponent = exponent - 127 + 15;
+
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+
+        // Subnormal, shift in the implicit bit and round.
+        let mantissa = mantissa | 0x0080_0000;
+        let shift = (14 - exponent) as u32;
+        let half = mantissa >> shift;
+        let round = (mantissa >> (shift - 1)) & 1;
+
+        return sign | (half + round) as u16;
+    }
+
+    let half = ((exponent as u32) << 10) | (mantissa >> 13);
+    let round = (mantissa >> 12) & 1;
+
+    // A carry out of the mantissa correctly bumps the exponent, up to infinity.
+    sign | (half + round) as u16
+}
+
+#[derive(Debug)]
+pub enum IblError {
+    Vulkan(ErrorCtx),
+    Staging(ErrorCtx<StagingError>),
+    /// The panorama doesn't have 4 floats per pixel.
+    SizeMismatch {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl From<ErrorCtx> for IblError {
+    fn from(value: ErrorCtx) -> Self {
+        Self::Vulkan(value)
+    }
+}
+
+impl From<ErrorCtx<StagingError>> for IblError {
+    fn from(value: ErrorCtx<StagingError>) -> Self {
+        Self::Staging(value)
+    }
+}
+
+impl fmt::Display for IblError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Vulkan(e) => e.fmt(f),
+            Self::Staging(e) => e.fmt(f),
+            Self::SizeMismatch { expected, found } => write!(
+                f,
+                "expected {} floats of equirectangular pixels but got {}",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl error::Error for IblError {}
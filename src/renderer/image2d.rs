@@ -0,0 +1,358 @@
+//! Sampled 2D textures uploaded once from host data, what the texture mapping chapter builds: an
+//! image in device-local memory, a view of every mip and a sampler, bound through a
+//! `COMBINED_IMAGE_SAMPLER` descriptor. PNG and JPEG files are decoded with the `image-loading`
+//! feature.
+//!
+//! `examples/pbr.rs` makes the 1x1 textures of its flat materials with [Image2D::new], and loads
+//! the albedo given with `--albedo` through [Image2D::from_file].
+
+use std::{error, fmt, io, path::Path, rc::Rc};
+
+use ash::vk::{
+    self, DescriptorImageInfo, DescriptorSetLayoutBinding, DescriptorType, Format, Handle,
+    ImageCreateInfo, ImageLayout, ImageTiling, ImageType, ImageUsageFlags, ImageViewCreateInfo,
+    ImageViewType, MemoryAllocateInfo, MemoryPropertyFlags, SampleCountFlags, ShaderStageFlags,
+    SharingMode, WriteDescriptorSet,
+};
+
+use crate::{
+    api2::{host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        image_desc::{ImageDesc, TexelBlock},
+        logical_device::LogicalDevice,
+        resource_stats::ResourceKind,
+        sampler::{Sampler, SamplerBuilder},
+        staging_uploader::{StagingUploader, UploadError},
+        teardown_trace,
+    },
+};
+
+/// A texture in the `SHADER_READ_ONLY_OPTIMAL` layout with its own sampler.
+#[derive(Clone)]
+pub struct Image2D(Rc<InnerImage2D>);
+
+impl Image2D {
+    /// Creates an image of `desc` in `format` and uploads `data` to it, holding every mip and
+    /// layer packed as [ImageDesc::mip_offset] lays them out. The sampler of `sampler` has its
+    /// maximum LOD capped to the mips of the image.
+    pub fn new(
+        uploader: &StagingUploader,
+        desc: ImageDesc,
+        format: Format,
+        data: &[u8],
+        sampler: SamplerBuilder,
+    ) -> Result<Self, UploadError> {
+        let block = TexelBlock::of(format).ok_or(UploadError::UnsupportedFormat(format))?;
+        let logical_device = uploader.logical_device();
+
+        let image = TextureImage::new(logical_device, desc, format)?;
+        uploader.upload_image(image.image, &desc, block, data)?;
+
+        let max_lod = sampler.config.max_lod.unwrap_or(desc.mip_levels);
+        let sampler = sampler
+            .max_lod(max_lod.min(desc.mip_levels))
+            .build(logical_device.clone())
+            .with_context("creating texture sampler", || {
+                format!("{:?}", sampler.config)
+            })?;
+
+        Ok(Self(Rc::new(InnerImage2D {
+            image,
+            format,
+            sampler,
+        })))
+    }
+
+    /// Decodes a PNG or JPEG file and uploads it as RGBA8, see [Image2D::from_bytes].
+    pub fn from_file<P: AsRef<Path>>(
+        uploader: &StagingUploader,
+        path: P,
+        color_space: ColorSpace,
+        sampler: SamplerBuilder,
+    ) -> Result<Self, ImageLoadError> {
+        let bytes = std::fs::read(path)?;
+
+        Self::from_bytes(uploader, &bytes, color_space, sampler)
+    }
+
+    /// Decodes a PNG or JPEG image held in `bytes` and uploads it as RGBA8, in the sRGB or UNORM
+    /// format depending on `color_space`.
+    #[cfg(feature = "image-loading")]
+    pub fn from_bytes(
+        uploader: &StagingUploader,
+        bytes: &[u8],
+        color_space: ColorSpace,
+        sampler: SamplerBuilder,
+    ) -> Result<Self, ImageLoadError> {
+        let decoded = image::load_from_memory(bytes)?;
+        let format = color_space.format(decoded.color().has_color());
+        let pixels = decoded.into_rgba8();
+
+        Self::new(
+            uploader,
+            ImageDesc::new(pixels.width(), pixels.height()),
+            format,
+            pixels.as_raw(),
+            sampler,
+        )
+        .map_err(ImageLoadError::from)
+    }
+
+    #[cfg(not(feature = "image-loading"))]
+    pub fn from_bytes(
+        _uploader: &StagingUploader,
+        _bytes: &[u8],
+        _color_space: ColorSpace,
+        _sampler: SamplerBuilder,
+    ) -> Result<Self, ImageLoadError> {
+        Err(ImageLoadError::Unsupported)
+    }
+
+    /// The binding of a combined image sampler for a layout, seen by `stage_flags`.
+    pub fn layout_binding(
+        binding: u32,
+        stage_flags: ShaderStageFlags,
+    ) -> DescriptorSetLayoutBinding<'static> {
+        DescriptorSetLayoutBinding::default()
+            .binding(binding)
+            .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(stage_flags)
+    }
+
+    pub fn descriptor(&self) -> DescriptorImageInfo {
+        DescriptorImageInfo::default()
+            .sampler(*self.0.sampler.sampler())
+            .image_view(self.0.image.view)
+            .image_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+    }
+
+    /// Points `binding` of `descriptor_set`, declared by [Image2D::layout_binding], at this
+    /// texture. The set must not be in use by the GPU.
+    pub fn bind(&self, descriptor_set: vk::DescriptorSet, binding: u32) {
+        let image_info = [self.descriptor()];
+
+        unsafe {
+            self.0.image.logical_device.device().update_descriptor_sets(
+                &[WriteDescriptorSet::default()
+                    .dst_set(descriptor_set)
+                    .dst_binding(binding)
+                    .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(&image_info)],
+                &[],
+            )
+        };
+    }
+
+    pub fn image(&self) -> vk::Image {
+        self.0.image.image
+    }
+
+    pub fn view(&self) -> vk::ImageView {
+        self.0.image.view
+    }
+
+    pub fn sampler(&self) -> &Sampler {
+        &self.0.sampler
+    }
+
+    pub fn format(&self) -> Format {
+        self.0.format
+    }
+
+    pub fn desc(&self) -> &ImageDesc {
+        &self.0.image.desc
+    }
+}
+
+/// How the texels of a decoded image are interpreted when sampled.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// sRGB for images with color and UNORM for grayscale ones, which are mostly masks and
+    /// heightmaps rather than pictures.
+    #[default]
+    Auto,
+    /// Colors to be converted to linear when sampled, e.g. albedo.
+    Srgb,
+    /// Data sampled as stored, e.g. normal and roughness maps.
+    Linear,
+}
+
+impl ColorSpace {
+    /// The RGBA8 format of an image, which `has_color` or is grayscale.
+    pub fn format(self, has_color: bool) -> Format {
+        match self {
+            Self::Auto if has_color => Format::R8G8B8A8_SRGB,
+            Self::Srgb => Format::R8G8B8A8_SRGB,
+            Self::Auto | Self::Linear => Format::R8G8B8A8_UNORM,
+        }
+    }
+}
+
+struct InnerImage2D {
+    image: TextureImage,
+    format: Format,
+    sampler: Sampler,
+}
+
+/// A device-local image with a view of every mip and layer.
+struct TextureImage {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+    size: vk::DeviceSize,
+    desc: ImageDesc,
+
+    logical_device: LogicalDevice,
+}
+
+impl TextureImage {
+    fn new(
+        logical_device: &LogicalDevice,
+        desc: ImageDesc,
+        format: Format,
+    ) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+        let tracker = logical_device.resource_tracker();
+
+        let mut texture = Self {
+            image: vk::Image::null(),
+            memory: vk::DeviceMemory::null(),
+            view: vk::ImageView::null(),
+            size: 0,
+            desc,
+            logical_device: logical_device.clone(),
+        };
+
+        texture.image = unsafe {
+            device.create_image(
+                &ImageCreateInfo::default()
+                    .image_type(ImageType::TYPE_2D)
+                    .format(format)
+                    .extent(desc.extent(0))
+                    .mip_levels(desc.mip_levels)
+                    .array_layers(desc.array_layers)
+                    .samples(SampleCountFlags::TYPE_1)
+                    .tiling(ImageTiling::OPTIMAL)
+                    .usage(ImageUsageFlags::SAMPLED | ImageUsageFlags::TRANSFER_DST)
+                    .sharing_mode(SharingMode::EXCLUSIVE)
+                    .initial_layout(ImageLayout::UNDEFINED),
+                host_allocation_callbacks(),
+            )
+        }
+        .with_context("creating texture image", || {
+            format!("format={:?}, desc={:?}", format, desc)
+        })?;
+
+        let requirements = unsafe { device.get_image_memory_requirements(texture.image) };
+
+        texture.memory = logical_device
+            .physical_device()
+            .find_memory_type(
+                requirements.memory_type_bits,
+                MemoryPropertyFlags::DEVICE_LOCAL,
+            )
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            .and_then(|memory_type_index| unsafe {
+                device.allocate_memory(
+                    &MemoryAllocateInfo::default()
+                        .allocation_size(requirements.size)
+                        .memory_type_index(memory_type_index),
+                    host_allocation_callbacks(),
+                )
+            })
+            .context("allocating texture memory")?;
+
+        texture.size = requirements.size;
+        tracker.track(ResourceKind::Image, 1, requirements.size);
+
+        let view_type = if desc.array_layers > 1 {
+            ImageViewType::TYPE_2D_ARRAY
+        } else {
+            ImageViewType::TYPE_2D
+        };
+
+        texture.view = unsafe { device.bind_image_memory(texture.image, texture.memory, 0) }
+            .and_then(|_| unsafe {
+                device.create_image_view(
+                    &ImageViewCreateInfo::default()
+                        .image(texture.image)
+                        .view_type(view_type)
+                        .format(format)
+                        .subresource_range(desc.full_range()),
+                    host_allocation_callbacks(),
+                )
+            })
+            .context("creating texture view")?;
+
+        tracker.track(ResourceKind::ImageView, 1, 0);
+
+        Ok(texture)
+    }
+}
+
+impl Drop for TextureImage {
+    fn drop(&mut self) {
+        teardown_trace::record("Image2D", [self.image.as_raw()]);
+
+        let device = self.logical_device.device();
+        let tracker = self.logical_device.resource_tracker();
+
+        unsafe {
+            device.destroy_image_view(self.view, host_allocation_callbacks());
+            device.destroy_image(self.image, host_allocation_callbacks());
+            device.free_memory(self.memory, host_allocation_callbacks());
+        }
+
+        if self.view != vk::ImageView::null() {
+            tracker.untrack(ResourceKind::ImageView, 1, 0);
+        }
+
+        if self.size > 0 {
+            tracker.untrack(ResourceKind::Image, 1, self.size);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ImageLoadError {
+    Io(io::Error),
+    #[cfg(feature = "image-loading")]
+    Decode(image::ImageError),
+    Upload(UploadError),
+    /// Built without the `image-loading` feature.
+    Unsupported,
+}
+
+impl From<io::Error> for ImageLoadError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+#[cfg(feature = "image-loading")]
+impl From<image::ImageError> for ImageLoadError {
+    fn from(value: image::ImageError) -> Self {
+        Self::Decode(value)
+    }
+}
+
+impl From<UploadError> for ImageLoadError {
+    fn from(value: UploadError) -> Self {
+        Self::Upload(value)
+    }
+}
+
+impl fmt::Display for ImageLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            #[cfg(feature = "image-loading")]
+            Self::Decode(e) => write!(f, "failed to decode image: {}", e),
+            Self::Upload(e) => e.fmt(f),
+            Self::Unsupported => write!(f, "loading images needs the image-loading feature"),
+        }
+    }
+}
+
+impl error::Error for ImageLoadError {}
@@ -0,0 +1,388 @@
+//! The size math of 2D images and their mip chains, shared by everything creating, uploading or
+//! blitting mips, so it's written once.
+
+use std::ops::Range;
+
+use ash::vk::{
+    BufferImageCopy, DeviceSize, Extent2D, Extent3D, Format, ImageAspectFlags,
+    ImageSubresourceLayers, ImageSubresourceRange, Offset3D,
+};
+
+use crate::api2::div_round_up;
+
+/// The mips of a full chain for an image of `width` by `height`, down to 1x1.
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    u32::BITS - width.max(height).max(1).leading_zeros()
+}
+
+/// The smallest unit a format stores, a single texel for uncompressed formats.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TexelBlock {
+    pub width: u32,
+    pub height: u32,
+    /// The bytes of a block.
+    pub size: u32,
+}
+
+impl TexelBlock {
+    /// A block of a single texel of `size` bytes.
+    pub const fn texel(size: u32) -> Self {
+        Self {
+            width: 1,
+            height: 1,
+            size,
+        }
+    }
+
+    pub const fn compressed(width: u32, height: u32, size: u32) -> Self {
+        Self {
+            width,
+            height,
+            size,
+        }
+    }
+
+    /// The block of the common color formats, `None` for the others.
+    pub fn of(format: Format) -> Option<Self> {
+        Some(match format {
+            Format::R8_UNORM | Format::R8_SRGB => Self::texel(1),
+            Format::R8G8_UNORM | Format::R8G8_SRGB | Format::R16_SFLOAT => Self::texel(2),
+            Format::R8G8B8A8_UNORM
+            | Format::R8G8B8A8_SRGB
+            | Format::B8G8R8A8_UNORM
+            | Format::B8G8R8A8_SRGB
+            | Format::A2B10G10R10_UNORM_PACK32
+            | Format::B10G11R11_UFLOAT_PACK32
+            | Format::R16G16_SFLOAT
+            | Format::R32_SFLOAT
+            | Format::R32_UINT => Self::texel(4),
+            Format::R16G16B16A16_SFLOAT | Format::R32G32_SFLOAT => Self::texel(8),
+            Format::R32G32B32A32_SFLOAT => Self::texel(16),
+            Format::BC1_RGB_UNORM_BLOCK
+            | Format::BC1_RGB_SRGB_BLOCK
+            | Format::BC1_RGBA_UNORM_BLOCK
+            | Format::BC1_RGBA_SRGB_BLOCK
+            | Format::BC4_UNORM_BLOCK
+            | Format::BC4_SNORM_BLOCK
+            | Format::ETC2_R8G8B8_UNORM_BLOCK
+            | Format::ETC2_R8G8B8_SRGB_BLOCK
+            | Format::ETC2_R8G8B8A1_UNORM_BLOCK
+            | Format::ETC2_R8G8B8A1_SRGB_BLOCK => Self::compressed(4, 4, 8),
+            Format::BC2_UNORM_BLOCK
+            | Format::BC2_SRGB_BLOCK
+            | Format::BC3_UNORM_BLOCK
+            | Format::BC3_SRGB_BLOCK
+            | Format::BC5_UNORM_BLOCK
+            | Format::BC5_SNORM_BLOCK
+            | Format::BC6H_UFLOAT_BLOCK
+            | Format::BC6H_SFLOAT_BLOCK
+            | Format::BC7_UNORM_BLOCK
+            | Format::BC7_SRGB_BLOCK
+            | Format::ETC2_R8G8B8A8_UNORM_BLOCK
+            | Format::ETC2_R8G8B8A8_SRGB_BLOCK
+            | Format::ASTC_4X4_UNORM_BLOCK
+            | Format::ASTC_4X4_SRGB_BLOCK => Self::compressed(4, 4, 16),
+            _ => return None,
+        })
+    }
+}
+
+/// The size, mips and layers of a 2D image.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ImageDesc {
+    pub width: u32,
+    pub height: u32,
+    pub mip_levels: u32,
+    pub array_layers: u32,
+    /// Whether the layers are the six faces of a cubemap.
+    pub cube: bool,
+    pub aspect_mask: ImageAspectFlags,
+}
+
+impl ImageDesc {
+    /// A color image with a single mip and layer.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            mip_levels: 1,
+            array_layers: 1,
+            cube: false,
+            aspect_mask: ImageAspectFlags::COLOR,
+        }
+    }
+
+    /// A color cubemap with faces of `size` by `size` and a single mip.
+    pub fn cube(size: u32) -> Self {
+        Self {
+            array_layers: 6,
+            cube: true,
+            ..Self::new(size, size)
+        }
+    }
+
+    /// Sets the mips, clamped between one and the full chain.
+    pub fn mip_levels(mut self, mip_levels: u32) -> Self {
+        self.mip_levels = mip_levels.clamp(1, mip_level_count(self.width, self.height));
+        self
+    }
+
+    /// Sets the mips to the full chain, down to 1x1.
+    pub fn full_mip_chain(mut self) -> Self {
+        self.mip_levels = mip_level_count(self.width, self.height);
+        self
+    }
+
+    pub fn array_layers(mut self, array_layers: u32) -> Self {
+        self.array_layers = array_layers.max(1);
+        self
+    }
+
+    pub fn aspect_mask(mut self, aspect_mask: ImageAspectFlags) -> Self {
+        self.aspect_mask = aspect_mask;
+        self
+    }
+
+    pub fn extent(&self, mip: u32) -> Extent3D {
+        Extent3D {
+            width: self.width.checked_shr(mip).unwrap_or(0).max(1),
+            height: self.height.checked_shr(mip).unwrap_or(0).max(1),
+            depth: 1,
+        }
+    }
+
+    pub fn extent_2d(&self, mip: u32) -> Extent2D {
+        let extent = self.extent(mip);
+
+        Extent2D {
+            width: extent.width,
+            height: extent.height,
+        }
+    }
+
+    /// The corners of a mip, as blits take them.
+    pub fn bounds(&self, mip: u32) -> [Offset3D; 2] {
+        let extent = self.extent(mip);
+
+        [
+            Offset3D::default(),
+            Offset3D {
+                x: extent.width as i32,
+                y: extent.height as i32,
+                z: 1,
+            },
+        ]
+    }
+
+    /// Every layer of a mip.
+    pub fn subresource_layers(&self, mip: u32) -> ImageSubresourceLayers {
+        ImageSubresourceLayers::default()
+            .aspect_mask(self.aspect_mask)
+            .mip_level(mip)
+            .layer_count(self.array_layers)
+    }
+
+    /// Every layer of the mips in `mips`.
+    pub fn subresource_range(&self, mips: Range<u32>) -> ImageSubresourceRange {
+        ImageSubresourceRange::default()
+            .aspect_mask(self.aspect_mask)
+            .base_mip_level(mips.start)
+            .level_count(mips.end - mips.start)
+            .layer_count(self.array_layers)
+    }
+
+    /// Every mip and layer.
+    pub fn full_range(&self) -> ImageSubresourceRange {
+        self.subresource_range(0..self.mip_levels)
+    }
+
+    /// The bytes of every layer of a mip, tightly packed in blocks of `block`.
+    pub fn mip_size(&self, mip: u32, block: TexelBlock) -> DeviceSize {
+        let extent = self.extent(mip);
+
+        div_round_up(extent.width, block.width) as DeviceSize
+            * div_round_up(extent.height, block.height) as DeviceSize
+            * block.size as DeviceSize
+            * self.array_layers as DeviceSize
+    }
+
+    /// Where a mip starts when the mips are packed one after the other, largest first.
+    pub fn mip_offset(&self, mip: u32, block: TexelBlock) -> DeviceSize {
+        (0..mip).map(|mip| self.mip_size(mip, block)).sum()
+    }
+
+    /// The bytes of every mip and layer packed one after the other, what staging the whole image
+    /// takes.
+    pub fn staging_size(&self, block: TexelBlock) -> DeviceSize {
+        self.mip_offset(self.mip_levels, block)
+    }
+
+    /// The copies of every mip from a buffer holding them packed as [ImageDesc::mip_offset] lays
+    /// them out, starting at `buffer_offset`.
+    pub fn copy_regions(
+        &self,
+        buffer_offset: DeviceSize,
+        block: TexelBlock,
+    ) -> Vec<BufferImageCopy> {
+        (0..self.mip_levels)
+            .map(|mip| {
+                BufferImageCopy::default()
+                    .buffer_offset(buffer_offset + self.mip_offset(mip, block))
+                    .image_subresource(self.subresource_layers(mip))
+                    .image_extent(self.extent(mip))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mip_counts() {
+        assert_eq!(mip_level_count(0, 0), 1);
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(2, 1), 2);
+        assert_eq!(mip_level_count(256, 256), 9);
+        assert_eq!(mip_level_count(257, 1), 9);
+        assert_eq!(mip_level_count(640, 480), 10);
+        assert_eq!(mip_level_count(1, 1000), 10);
+        assert_eq!(mip_level_count(u32::MAX, 1), 32);
+    }
+
+    #[test]
+    fn mip_extents_halve_down_to_one() {
+        let desc = ImageDesc::new(640, 480).full_mip_chain();
+
+        assert_eq!(
+            desc.extent_2d(0),
+            Extent2D {
+                width: 640,
+                height: 480
+            }
+        );
+        assert_eq!(
+            desc.extent_2d(1),
+            Extent2D {
+                width: 320,
+                height: 240
+            }
+        );
+        assert_eq!(
+            desc.extent_2d(7),
+            Extent2D {
+                width: 5,
+                height: 3
+            }
+        );
+        assert_eq!(
+            desc.extent_2d(9),
+            Extent2D {
+                width: 1,
+                height: 1
+            }
+        );
+        assert_eq!(
+            desc.extent_2d(31),
+            Extent2D {
+                width: 1,
+                height: 1
+            }
+        );
+        assert_eq!(
+            desc.extent_2d(u32::MAX),
+            Extent2D {
+                width: 1,
+                height: 1
+            }
+        );
+        assert_eq!(
+            desc.bounds(1)[1],
+            Offset3D {
+                x: 320,
+                y: 240,
+                z: 1
+            }
+        );
+
+        let empty = ImageDesc::new(0, 0);
+        assert_eq!(
+            empty.extent_2d(0),
+            Extent2D {
+                width: 1,
+                height: 1
+            }
+        );
+    }
+
+    #[test]
+    fn mip_levels_are_clamped_to_the_chain() {
+        assert_eq!(ImageDesc::new(16, 16).mip_levels(0).mip_levels, 1);
+        assert_eq!(ImageDesc::new(16, 16).mip_levels(3).mip_levels, 3);
+        assert_eq!(ImageDesc::new(16, 16).mip_levels(u32::MAX).mip_levels, 5);
+        assert_eq!(ImageDesc::new(1, 1).full_mip_chain().mip_levels, 1);
+        assert_eq!(ImageDesc::new(16, 16).array_layers(0).array_layers, 1);
+    }
+
+    #[test]
+    fn sizes_of_uncompressed_mips() {
+        let block = TexelBlock::texel(4);
+        let desc = ImageDesc::new(4, 2).full_mip_chain();
+
+        assert_eq!(desc.mip_size(0, block), 32);
+        assert_eq!(desc.mip_size(1, block), 8);
+        assert_eq!(desc.mip_size(2, block), 4);
+        assert_eq!(desc.mip_offset(2, block), 40);
+        assert_eq!(desc.staging_size(block), 44);
+
+        // Every face of a cubemap is counted.
+        let cube = ImageDesc::cube(3).full_mip_chain();
+        assert_eq!(cube.mip_levels, 2);
+        assert_eq!(cube.staging_size(block), (9 + 1) * 4 * 6);
+    }
+
+    #[test]
+    fn compressed_mips_round_up_to_whole_blocks() {
+        let block = TexelBlock::of(Format::BC7_UNORM_BLOCK).unwrap();
+        let desc = ImageDesc::new(10, 6).full_mip_chain();
+
+        // 3x2 blocks, then 2x1 for 5x3, then a single block down to 1x1.
+        assert_eq!(desc.mip_size(0, block), 6 * 16);
+        assert_eq!(desc.mip_size(1, block), 2 * 16);
+        assert_eq!(desc.mip_size(2, block), 16);
+        assert_eq!(desc.mip_size(3, block), 16);
+        assert_eq!(desc.staging_size(block), (6 + 2 + 1 + 1) * 16);
+    }
+
+    #[test]
+    fn texel_blocks_of_formats() {
+        assert_eq!(TexelBlock::of(Format::R8_UNORM), Some(TexelBlock::texel(1)));
+        assert_eq!(
+            TexelBlock::of(Format::R32G32B32A32_SFLOAT),
+            Some(TexelBlock::texel(16))
+        );
+        assert_eq!(
+            TexelBlock::of(Format::BC1_RGB_SRGB_BLOCK),
+            Some(TexelBlock::compressed(4, 4, 8))
+        );
+        assert_eq!(TexelBlock::of(Format::UNDEFINED), None);
+    }
+
+    #[test]
+    fn copy_regions_follow_the_packed_mips() {
+        let block = TexelBlock::texel(1);
+        let desc = ImageDesc::new(4, 4).full_mip_chain().array_layers(2);
+        let regions = desc.copy_regions(100, block);
+
+        let offsets: Vec<_> = regions.iter().map(|region| region.buffer_offset).collect();
+        assert_eq!(offsets, [100, 132, 140]);
+        assert_eq!(regions[1].image_subresource.mip_level, 1);
+        assert_eq!(regions[1].image_subresource.layer_count, 2);
+        assert_eq!(regions[2].image_extent.width, 1);
+
+        let range = desc.full_range();
+        assert_eq!((range.base_mip_level, range.level_count), (0, 3));
+        assert_eq!(desc.subresource_range(1..3).level_count, 2);
+    }
+}
@@ -0,0 +1,168 @@
+use std::rc::Rc;
+
+use ash::{
+    prelude::VkResult,
+    vk::{
+        ComponentMapping, ComponentSwizzle, Handle, Image, ImageAspectFlags, ImageSubresourceRange,
+        ImageView, ImageViewCreateInfo, ImageViewType, SurfaceFormatKHR,
+    },
+};
+
+use crate::{
+    api2::host_allocation_callbacks,
+    renderer::{
+        logical_device::LogicalDevice, resource_stats::ResourceKind, swapchain::Swapchain,
+        teardown_trace,
+    },
+};
+
+#[derive(Clone)]
+pub struct ImageViews(Rc<InnerImageViews>);
+
+impl ImageViews {
+    /// Creates a view of every swapchain image. Views of layered images cover all their layers,
+    /// for a [multiview](crate::renderer::render_pass::RenderPass::multiview) render pass.
+    pub fn new(swapchain: &Swapchain, logical_device: LogicalDevice) -> VkResult<Self> {
+        let layers = swapchain.array_layers();
+        let view_type = if layers > 1 {
+            ImageViewType::TYPE_2D_ARRAY
+        } else {
+            ImageViewType::TYPE_2D
+        };
+
+        Self::create(swapchain, logical_device, 1, |image| {
+            vec![image_view_create_info(
+                image,
+                swapchain.format(),
+                view_type,
+                0,
+                layers,
+            )]
+        })
+    }
+
+    /// Creates a view of every layer of every swapchain image, to render the layers one at a
+    /// time. The views of an image follow each other, see [ImageViews::view].
+    pub fn per_layer(swapchain: &Swapchain, logical_device: LogicalDevice) -> VkResult<Self> {
+        let layers = swapchain.array_layers();
+
+        Self::create(swapchain, logical_device, layers, |image| {
+            (0..layers)
+                .map(|layer| {
+                    image_view_create_info(
+                        image,
+                        swapchain.format(),
+                        ImageViewType::TYPE_2D,
+                        layer,
+                        1,
+                    )
+                })
+                .collect()
+        })
+    }
+
+    fn create(
+        swapchain: &Swapchain,
+        logical_device: LogicalDevice,
+        views_per_image: u32,
+        create_infos: impl Fn(Image) -> Vec<ImageViewCreateInfo<'static>>,
+    ) -> VkResult<Self> {
+        let mut image_views =
+            Vec::with_capacity(swapchain.images().len() * views_per_image as usize);
+
+        for image in swapchain.images() {
+            for image_view_create_info in create_infos(image.image()) {
+                let image_view = unsafe {
+                    logical_device
+                        .device()
+                        .create_image_view(&image_view_create_info, host_allocation_callbacks())?
+                };
+
+                image_views.push(image_view);
+            }
+        }
+
+        logical_device.resource_tracker().track(
+            ResourceKind::ImageView,
+            image_views.len() as u64,
+            0,
+        );
+
+        Ok(ImageViews(Rc::new(InnerImageViews {
+            image_views,
+            views_per_image,
+            logical_device,
+        })))
+    }
+
+    pub fn image_views(&self) -> &[ImageView] {
+        &self.0.image_views
+    }
+
+    /// How many views every image has, its layer count for [ImageViews::per_layer] and 1
+    /// otherwise.
+    pub fn views_per_image(&self) -> u32 {
+        self.0.views_per_image
+    }
+
+    /// The view of `layer` of the image at `image_index`, layer 0 being the whole image unless
+    /// created with [ImageViews::per_layer].
+    pub fn view(&self, image_index: u32, layer: u32) -> ImageView {
+        self.0.image_views[(image_index * self.0.views_per_image + layer) as usize]
+    }
+}
+
+struct InnerImageViews {
+    image_views: Vec<ImageView>,
+    views_per_image: u32,
+    logical_device: LogicalDevice,
+}
+
+impl Drop for InnerImageViews {
+    fn drop(&mut self) {
+        teardown_trace::record(
+            "ImageViews",
+            self.image_views.iter().map(|handle| handle.as_raw()),
+        );
+
+        unsafe {
+            for image_view in self.image_views.iter() {
+                self.logical_device
+                    .device()
+                    .destroy_image_view(*image_view, host_allocation_callbacks());
+            }
+        }
+
+        self.logical_device.resource_tracker().untrack(
+            ResourceKind::ImageView,
+            self.image_views.len() as u64,
+            0,
+        );
+    }
+}
+
+fn image_view_create_info(
+    image: Image,
+    format: SurfaceFormatKHR,
+    view_type: ImageViewType,
+    base_array_layer: u32,
+    layer_count: u32,
+) -> ImageViewCreateInfo<'static> {
+    ImageViewCreateInfo::default()
+        .image(image)
+        .view_type(view_type)
+        .format(format.format)
+        .components(ComponentMapping {
+            r: ComponentSwizzle::IDENTITY,
+            g: ComponentSwizzle::IDENTITY,
+            b: ComponentSwizzle::IDENTITY,
+            a: ComponentSwizzle::IDENTITY,
+        })
+        .subresource_range(ImageSubresourceRange {
+            aspect_mask: ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer,
+            layer_count,
+        })
+}
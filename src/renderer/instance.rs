@@ -0,0 +1,195 @@
+use std::{
+    error,
+    ffi::{CString, NulError},
+    fmt,
+    rc::Rc,
+};
+
+use ash::{
+    ext, khr,
+    vk::{
+        ApplicationInfo, Handle, InstanceCreateFlags, InstanceCreateInfo, API_VERSION_1_0,
+        API_VERSION_1_1,
+    },
+    Entry,
+};
+
+use crate::{
+    api2::{host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        debug_layer::create_debug_messenger,
+        teardown_trace,
+        utils::{to_vec_cstring, to_vec_pointer},
+        ENABLE_VALIDATION_LAYERS, VALIDATION_LAYERS,
+    },
+};
+
+#[derive(Clone)]
+pub struct Instance(Rc<InnerInstance>);
+
+impl Instance {
+    /// Creates the instance with the validation layer in debug builds.
+    pub fn new(
+        entry: Entry,
+        required_extensions: Vec<String>,
+        application_name: &str,
+        application_version: u32,
+        engine_name: &str,
+        engine_version: u32,
+    ) -> Result<Self, InstanceError> {
+        Self::with_validation(
+            entry,
+            required_extensions,
+            application_name,
+            application_version,
+            engine_name,
+            engine_version,
+            ENABLE_VALIDATION_LAYERS,
+        )
+    }
+
+    /// Creates the instance with the validation layer and `VK_EXT_debug_utils` when `validation`
+    /// is set, which the layer has to be installed for.
+    pub fn with_validation(
+        entry: Entry,
+        required_extensions: Vec<String>,
+        application_name: &str,
+        application_version: u32,
+        engine_name: &str,
+        engine_version: u32,
+        validation: bool,
+    ) -> Result<Self, InstanceError> {
+        let application_name = CString::new(application_name)?;
+        let engine_name = CString::new(engine_name)?;
+
+        // Vulkan 1.1 for vkGetPhysicalDeviceFeatures2 and multiview, where the loader has it.
+        // Vulkan 1.0 loaders fail instance creation with any other version.
+        let api_version = match unsafe { entry.try_enumerate_instance_version() } {
+            Ok(Some(version)) if version >= API_VERSION_1_1 => API_VERSION_1_1,
+            _ => API_VERSION_1_0,
+        };
+
+        let app_info = ApplicationInfo::default()
+            .application_name(&application_name)
+            .application_version(application_version)
+            .engine_name(&engine_name)
+            .engine_version(engine_version)
+            .api_version(api_version);
+
+        let required_extensions = to_vec_cstring(required_extensions)?;
+        let extensions = get_extensions(&required_extensions, validation);
+
+        let mut create_info = InstanceCreateInfo::default()
+            .application_info(&app_info)
+            .enabled_extension_names(extensions.as_slice());
+
+        let validation_layers;
+        let layers;
+        let mut debug_messenger;
+
+        if validation {
+            validation_layers = to_vec_cstring(VALIDATION_LAYERS)?;
+            debug_messenger = create_debug_messenger();
+            layers = get_layers(&validation_layers);
+
+            create_info = create_info
+                .enabled_layer_names(&layers)
+                .push_next(&mut debug_messenger);
+        }
+
+        if cfg!(target_os = "macos") {
+            create_info = create_info.flags(InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR);
+        }
+
+        let instance = unsafe { entry.create_instance(&create_info, host_allocation_callbacks()) }
+            .with_context("creating instance", || {
+                format!(
+                    "extensions={:?}, validation_layers={}",
+                    required_extensions, validation
+                )
+            })?;
+
+        Ok(Self(Rc::new(InnerInstance {
+            entry,
+            instance,
+            api_version,
+        })))
+    }
+
+    pub fn entry(&self) -> &Entry {
+        &self.0.entry
+    }
+
+    pub fn instance(&self) -> &ash::Instance {
+        &self.0.instance
+    }
+
+    /// The Vulkan version the instance was created with, capping the device functionality
+    /// usable through it.
+    pub fn api_version(&self) -> u32 {
+        self.0.api_version
+    }
+}
+
+#[derive(Debug)]
+pub enum InstanceError {
+    Vulkan(ErrorCtx),
+    Nul(NulError),
+}
+
+impl From<ErrorCtx> for InstanceError {
+    fn from(value: ErrorCtx) -> Self {
+        Self::Vulkan(value)
+    }
+}
+
+impl From<NulError> for InstanceError {
+    fn from(value: NulError) -> Self {
+        Self::Nul(value)
+    }
+}
+
+impl fmt::Display for InstanceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Vulkan(e) => e.fmt(f),
+            Self::Nul(e) => write!(f, "name contains a nul byte: {}", e),
+        }
+    }
+}
+
+impl error::Error for InstanceError {}
+
+struct InnerInstance {
+    entry: Entry,
+    instance: ash::Instance,
+    api_version: u32,
+}
+
+impl Drop for InnerInstance {
+    fn drop(&mut self) {
+        teardown_trace::record("Instance", [self.instance.handle().as_raw()]);
+
+        unsafe {
+            self.instance.destroy_instance(host_allocation_callbacks());
+        }
+    }
+}
+
+fn get_extensions(base: &[CString], validation: bool) -> Vec<*const i8> {
+    let mut extensions = to_vec_pointer(base);
+
+    if cfg!(target_os = "macos") {
+        extensions.push(khr::portability_enumeration::NAME.as_ptr());
+    }
+
+    if validation {
+        extensions.push(ext::debug_utils::NAME.as_ptr());
+    }
+
+    extensions
+}
+
+fn get_layers(base: &[CString]) -> Vec<*const i8> {
+    to_vec_pointer(base)
+}
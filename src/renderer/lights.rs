@@ -0,0 +1,653 @@
+//! The lights of a scene: a CPU-side list uploaded to a storage buffer every frame and, for
+//! scenes with many lights, sorted into a froxel grid by a compute pass so shading only visits
+//! the lights near each fragment.
+//!
+//! `examples/pbr.rs` lights its spheres through [LightBuffers], and sorts a few hundred more
+//! lights with [LightClusters] when run with `--many-lights`.
+
+use std::{cell::RefCell, mem, rc::Rc};
+
+use ash::vk::{
+    self, AccessFlags, BufferCreateInfo, BufferMemoryBarrier, BufferUsageFlags, CommandBuffer,
+    ComputePipelineCreateInfo, DependencyFlags, DescriptorBufferInfo, DescriptorPoolCreateInfo,
+    DescriptorPoolSize, DescriptorSetAllocateInfo, DescriptorSetLayoutBinding,
+    DescriptorSetLayoutCreateInfo, DescriptorType, DeviceSize, Handle, MemoryAllocateInfo,
+    MemoryMapFlags, MemoryPropertyFlags, PipelineBindPoint, PipelineCache,
+    PipelineLayoutCreateInfo, PipelineShaderStageCreateInfo, PipelineStageFlags, PushConstantRange,
+    ShaderStageFlags, SharingMode, WriteDescriptorSet, WHOLE_SIZE,
+};
+
+use crate::{
+    api2::{host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        logical_device::LogicalDevice, pipeline_stats, resource_stats::ResourceKind,
+        shader_cache::ShaderCache, teardown_trace, MAX_FRAMES_IN_FLIGHT,
+    },
+    types::Pod,
+};
+
+/// The froxel grid along X, Y and depth, matches `CLUSTER_X`, `CLUSTER_Y` and `CLUSTER_Z` in
+/// `shaders/lights.glsl`.
+pub const CLUSTER_GRID: [u32; 3] = [16, 9, 24];
+
+/// Lights beyond this many in one cluster are dropped, matches `MAX_LIGHTS_PER_CLUSTER`.
+pub const MAX_LIGHTS_PER_CLUSTER: u32 = 32;
+
+const CLUSTER_COUNT: u32 = CLUSTER_GRID[0] * CLUSTER_GRID[1] * CLUSTER_GRID[2];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Light {
+    /// A light infinitely far away, like the sun.
+    Directional {
+        /// The direction the light travels in.
+        direction: [f32; 3],
+        /// Linear RGB.
+        color: [f32; 3],
+        intensity: f32,
+    },
+    Point {
+        position: [f32; 3],
+        /// The distance at which the light has faded out.
+        range: f32,
+        color: [f32; 3],
+        intensity: f32,
+    },
+    /// A point light limited to a cone, fading from the inner to the outer angle.
+    Spot {
+        position: [f32; 3],
+        direction: [f32; 3],
+        range: f32,
+        /// Half-angles of the cone in radians.
+        inner_angle: f32,
+        outer_angle: f32,
+        color: [f32; 3],
+        intensity: f32,
+    },
+}
+
+/// A light as the shaders read it, mirrors `Light` in `shaders/lights.glsl`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct GpuLight {
+    pub position_range: [f32; 4],
+    pub direction_kind: [f32; 4],
+    pub color_intensity: [f32; 4],
+    pub spot_cosines: [f32; 4],
+}
+
+impl From<&Light> for GpuLight {
+    fn from(value: &Light) -> Self {
+        let [r, g, b] = match *value {
+            Light::Directional { color, .. }
+            | Light::Point { color, .. }
+            | Light::Spot { color, .. } => color,
+        };
+
+        match *value {
+            Light::Directional {
+                direction: [x, y, z],
+                intensity,
+                ..
+            } => Self {
+                direction_kind: [x, y, z, 0.0],
+                color_intensity: [r, g, b, intensity],
+                ..Default::default()
+            },
+            Light::Point {
+                position: [x, y, z],
+                range,
+                intensity,
+                ..
+            } => Self {
+                position_range: [x, y, z, range],
+                direction_kind: [0.0, 0.0, 0.0, 1.0],
+                color_intensity: [r, g, b, intensity],
+                ..Default::default()
+            },
+            Light::Spot {
+                position: [x, y, z],
+                direction: [dx, dy, dz],
+                range,
+                inner_angle,
+                outer_angle,
+                intensity,
+                ..
+            } => {
+                let (cos_inner, cos_outer) = (inner_angle.cos(), outer_angle.cos());
+
+                Self {
+                    position_range: [x, y, z, range],
+                    direction_kind: [dx, dy, dz, 2.0],
+                    color_intensity: [r, g, b, intensity],
+                    spot_cosines: [
+                        cos_outer,
+                        1.0 / (cos_inner - cos_outer).max(0.0001),
+                        0.0,
+                        0.0,
+                    ],
+                }
+            }
+        }
+    }
+}
+
+/// The lights of the current frame.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LightList {
+    lights: Vec<Light>,
+}
+
+impl LightList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
+    pub fn clear(&mut self) {
+        self.lights.clear();
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    pub fn lights_mut(&mut self) -> &mut Vec<Light> {
+        &mut self.lights
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+}
+
+/// The start of the light storage buffer before the lights, mirrors `Lights`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct LightsHeader {
+    light_count: u32,
+    _padding: [u32; 3],
+}
+
+/// A host-visible storage buffer of lights per frame in flight, bound where
+/// `shaders/lights.glsl` expects `LIGHTS_BINDING`.
+#[derive(Clone)]
+pub struct LightBuffers(Rc<InnerLightBuffers>);
+
+impl LightBuffers {
+    /// Creates buffers holding up to `capacity` lights each.
+    pub fn new(logical_device: LogicalDevice, capacity: u32) -> Result<Self, ErrorCtx> {
+        let frame_size = (mem::size_of::<LightsHeader>()
+            + capacity as usize * mem::size_of::<GpuLight>())
+            as DeviceSize;
+
+        let buffers = (0..MAX_FRAMES_IN_FLIGHT)
+            .map(|_| {
+                let buffer = LightBuffer::new(
+                    &logical_device,
+                    frame_size,
+                    BufferUsageFlags::STORAGE_BUFFER,
+                    MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+                )?;
+
+                let mapped = unsafe {
+                    logical_device.device().map_memory(
+                        buffer.memory,
+                        0,
+                        WHOLE_SIZE,
+                        MemoryMapFlags::empty(),
+                    )
+                }
+                .context("mapping light buffer memory")?;
+
+                // Nothing is lit until the first upload.
+                unsafe { mapped.cast::<LightsHeader>().write(LightsHeader::default()) };
+
+                Ok((buffer, mapped.cast::<u8>()))
+            })
+            .collect::<Result<Vec<_>, ErrorCtx>>()?;
+
+        Ok(Self(Rc::new(InnerLightBuffers {
+            buffers,
+            capacity,
+            logical_device,
+        })))
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.0.capacity
+    }
+
+    /// Copies `lights` into the buffer of frame `frame`, whose previous submission must have
+    /// finished, and returns how many fit.
+    pub fn upload(&self, frame: usize, lights: &LightList) -> usize {
+        let (_, mapped) = self.0.buffers[frame];
+        let count = lights.len().min(self.0.capacity as usize);
+
+        unsafe {
+            mapped.cast::<LightsHeader>().write(LightsHeader {
+                light_count: count as u32,
+                _padding: [0; 3],
+            });
+
+            let destination = mapped
+                .add(mem::size_of::<LightsHeader>())
+                .cast::<GpuLight>();
+
+            for (i, light) in lights.lights()[..count].iter().enumerate() {
+                destination.add(i).write(GpuLight::from(light));
+            }
+        }
+
+        count
+    }
+
+    pub fn descriptor(&self, frame: usize) -> DescriptorBufferInfo {
+        DescriptorBufferInfo::default()
+            .buffer(self.0.buffers[frame].0.buffer)
+            .offset(0)
+            .range(WHOLE_SIZE)
+    }
+}
+
+struct InnerLightBuffers {
+    buffers: Vec<(LightBuffer, *mut u8)>,
+    capacity: u32,
+
+    logical_device: LogicalDevice,
+}
+
+impl Drop for InnerLightBuffers {
+    fn drop(&mut self) {
+        for (buffer, _) in &self.buffers {
+            unsafe { self.logical_device.device().unmap_memory(buffer.memory) };
+        }
+    }
+}
+
+/// The camera the clusters are built for, a symmetric perspective projection.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ClusterView {
+    /// The world to view space transform, looking down -Z with Y up.
+    pub view: [[f32; 4]; 4],
+    /// The vertical field of view in radians.
+    pub fov_y: f32,
+    /// Width over height.
+    pub aspect_ratio: f32,
+    /// The depth range the slices span, usually the projection's clip planes.
+    pub near: f32,
+    pub far: f32,
+}
+
+/// The push constants of `shaders/light_cull.comp`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ClusterPushConstants {
+    pub view: [[f32; 4]; 4],
+    pub tan_half_fov: [f32; 2],
+    pub near: f32,
+    pub far: f32,
+}
+
+impl From<&ClusterView> for ClusterPushConstants {
+    fn from(value: &ClusterView) -> Self {
+        let tan_half_fov_y = (value.fov_y / 2.0).tan();
+
+        Self {
+            view: value.view,
+            tan_half_fov: [tan_half_fov_y * value.aspect_ratio, tan_half_fov_y],
+            near: value.near,
+            far: value.far,
+        }
+    }
+}
+
+unsafe impl Pod for ClusterPushConstants {}
+
+/// The compute pass sorting the lights of [LightBuffers] into a froxel grid per frame in flight,
+/// read by shaders compiled with `CLUSTERED_LIGHTS` through `CLUSTERS_BINDING`.
+#[derive(Clone)]
+pub struct LightClusters(Rc<InnerLightClusters>);
+
+impl LightClusters {
+    /// Creates the pass from the SPIR-V of `shaders/light_cull.comp`.
+    pub fn new(
+        logical_device: LogicalDevice,
+        shader_cache: &ShaderCache,
+        lights: &LightBuffers,
+        cull_shader: &[u32],
+    ) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+
+        let cluster_size = (CLUSTER_COUNT as usize
+            * (1 + MAX_LIGHTS_PER_CLUSTER as usize)
+            * mem::size_of::<u32>()) as DeviceSize;
+
+        let buffers = (0..MAX_FRAMES_IN_FLIGHT)
+            .map(|_| {
+                LightBuffer::new(
+                    &logical_device,
+                    cluster_size,
+                    BufferUsageFlags::STORAGE_BUFFER,
+                    MemoryPropertyFlags::DEVICE_LOCAL,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let bindings = [0, 1].map(|binding| {
+            DescriptorSetLayoutBinding::default()
+                .binding(binding)
+                .descriptor_type(DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(ShaderStageFlags::COMPUTE)
+        });
+
+        let descriptor_set_layout = unsafe {
+            device.create_descriptor_set_layout(
+                &DescriptorSetLayoutCreateInfo::default().bindings(&bindings),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating light cluster descriptor set layout")?;
+
+        let pool_sizes = [DescriptorPoolSize::default()
+            .ty(DescriptorType::STORAGE_BUFFER)
+            .descriptor_count(2 * MAX_FRAMES_IN_FLIGHT as u32)];
+
+        let descriptor_pool = unsafe {
+            device.create_descriptor_pool(
+                &DescriptorPoolCreateInfo::default()
+                    .max_sets(MAX_FRAMES_IN_FLIGHT as u32)
+                    .pool_sizes(&pool_sizes),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating light cluster descriptor pool")?;
+
+        let set_layouts = [descriptor_set_layout; MAX_FRAMES_IN_FLIGHT];
+        let descriptor_sets = unsafe {
+            device.allocate_descriptor_sets(
+                &DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(descriptor_pool)
+                    .set_layouts(&set_layouts),
+            )
+        }
+        .context("allocating light cluster descriptor sets")?;
+
+        logical_device.resource_tracker().track(
+            ResourceKind::DescriptorSet,
+            descriptor_sets.len() as u64,
+            0,
+        );
+
+        let push_constant_ranges = [PushConstantRange::default()
+            .stage_flags(ShaderStageFlags::COMPUTE)
+            .size(mem::size_of::<ClusterPushConstants>() as u32)];
+
+        let pipeline_layout = unsafe {
+            device.create_pipeline_layout(
+                &PipelineLayoutCreateInfo::default()
+                    .set_layouts(&set_layouts[..1])
+                    .push_constant_ranges(&push_constant_ranges),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating light cluster pipeline layout")?;
+
+        let shader_module = shader_cache
+            .get_or_create(cull_shader)
+            .context("creating light cull shader module")?;
+
+        let create_info = ComputePipelineCreateInfo::default()
+            .stage(
+                PipelineShaderStageCreateInfo::default()
+                    .stage(ShaderStageFlags::COMPUTE)
+                    .module(*shader_module.shader_module())
+                    .name(c"main"),
+            )
+            .layout(pipeline_layout);
+
+        let pipeline = unsafe {
+            pipeline_stats::create_compute_pipelines(
+                &logical_device,
+                PipelineCache::null(),
+                &[create_info],
+            )
+        }
+        .context("creating light cull pipeline")?[0];
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::Pipeline, 1, 0);
+
+        let light_clusters = Self(Rc::new(InnerLightClusters {
+            buffers,
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_sets,
+            pipeline_layout,
+            pipeline,
+            lights: RefCell::new(lights.clone()),
+            logical_device,
+        }));
+
+        light_clusters.set_lights(lights);
+
+        Ok(light_clusters)
+    }
+
+    /// Sorts a different set of light buffers. The descriptor sets must not be in use by a
+    /// pending command buffer.
+    pub fn set_lights(&self, lights: &LightBuffers) {
+        for (frame, &descriptor_set) in self.0.descriptor_sets.iter().enumerate() {
+            let light_info = [lights.descriptor(frame)];
+            let cluster_info = [self.descriptor(frame)];
+
+            let writes = [
+                WriteDescriptorSet::default()
+                    .dst_set(descriptor_set)
+                    .dst_binding(0)
+                    .descriptor_type(DescriptorType::STORAGE_BUFFER)
+                    .buffer_info(&light_info),
+                WriteDescriptorSet::default()
+                    .dst_set(descriptor_set)
+                    .dst_binding(1)
+                    .descriptor_type(DescriptorType::STORAGE_BUFFER)
+                    .buffer_info(&cluster_info),
+            ];
+
+            unsafe {
+                self.0
+                    .logical_device
+                    .device()
+                    .update_descriptor_sets(&writes, &[])
+            };
+        }
+
+        *self.0.lights.borrow_mut() = lights.clone();
+    }
+
+    /// The clusters of frame `frame`.
+    pub fn descriptor(&self, frame: usize) -> DescriptorBufferInfo {
+        DescriptorBufferInfo::default()
+            .buffer(self.0.buffers[frame].buffer)
+            .offset(0)
+            .range(WHOLE_SIZE)
+    }
+
+    /// Records the culling of frame `frame`'s lights for `view`, outside a render pass, and makes
+    /// the clusters visible to fragment shaders.
+    pub fn record(&self, command_buffer: CommandBuffer, frame: usize, view: &ClusterView) {
+        let device = self.0.logical_device.device();
+        let push_constants = ClusterPushConstants::from(view);
+
+        unsafe {
+            device.cmd_bind_pipeline(command_buffer, PipelineBindPoint::COMPUTE, self.0.pipeline);
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                PipelineBindPoint::COMPUTE,
+                self.0.pipeline_layout,
+                0,
+                &[self.0.descriptor_sets[frame]],
+                &[],
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.0.pipeline_layout,
+                ShaderStageFlags::COMPUTE,
+                0,
+                push_constants.bytes_of(),
+            );
+            // One workgroup covers a whole depth slice.
+            device.cmd_dispatch(command_buffer, 1, 1, CLUSTER_GRID[2]);
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::COMPUTE_SHADER,
+                PipelineStageFlags::FRAGMENT_SHADER,
+                DependencyFlags::empty(),
+                &[],
+                &[BufferMemoryBarrier::default()
+                    .src_access_mask(AccessFlags::SHADER_WRITE)
+                    .dst_access_mask(AccessFlags::SHADER_READ)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .buffer(self.0.buffers[frame].buffer)
+                    .size(WHOLE_SIZE)],
+                &[],
+            );
+        }
+    }
+}
+
+struct InnerLightClusters {
+    buffers: Vec<LightBuffer>,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    /// Kept alive while the descriptor sets point at it.
+    #[allow(dead_code)]
+    lights: RefCell<LightBuffers>,
+
+    logical_device: LogicalDevice,
+}
+
+impl Drop for InnerLightClusters {
+    fn drop(&mut self) {
+        teardown_trace::record("LightClusters", [self.pipeline.as_raw()]);
+
+        let device = self.logical_device.device();
+
+        unsafe {
+            device.destroy_pipeline(self.pipeline, host_allocation_callbacks());
+            device.destroy_pipeline_layout(self.pipeline_layout, host_allocation_callbacks());
+            device.destroy_descriptor_pool(self.descriptor_pool, host_allocation_callbacks());
+            device.destroy_descriptor_set_layout(
+                self.descriptor_set_layout,
+                host_allocation_callbacks(),
+            );
+        }
+
+        let tracker = self.logical_device.resource_tracker();
+        tracker.untrack(ResourceKind::Pipeline, 1, 0);
+        tracker.untrack(
+            ResourceKind::DescriptorSet,
+            self.descriptor_sets.len() as u64,
+            0,
+        );
+    }
+}
+
+/// A storage buffer with its own memory allocation.
+struct LightBuffer {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    size: DeviceSize,
+
+    logical_device: LogicalDevice,
+}
+
+impl LightBuffer {
+    fn new(
+        logical_device: &LogicalDevice,
+        size: DeviceSize,
+        usage: BufferUsageFlags,
+        memory_properties: MemoryPropertyFlags,
+    ) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+
+        let buffer = unsafe {
+            device.create_buffer(
+                &BufferCreateInfo::default()
+                    .size(size)
+                    .usage(usage)
+                    .sharing_mode(SharingMode::EXCLUSIVE),
+                host_allocation_callbacks(),
+            )
+        }
+        .with_context("creating light buffer", || format!("size={}", size))?;
+
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+
+        let mut light_buffer = Self {
+            buffer,
+            memory: vk::DeviceMemory::null(),
+            size: requirements.size,
+            logical_device: logical_device.clone(),
+        };
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::Buffer, 1, requirements.size);
+
+        let memory_type_index = logical_device
+            .physical_device()
+            .find_memory_type(requirements.memory_type_bits, memory_properties)
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            .with_context("finding light buffer memory", || {
+                format!(
+                    "memory_type_bits={:#x}, properties={:?}",
+                    requirements.memory_type_bits, memory_properties
+                )
+            })?;
+
+        light_buffer.memory = unsafe {
+            device.allocate_memory(
+                &MemoryAllocateInfo::default()
+                    .allocation_size(requirements.size)
+                    .memory_type_index(memory_type_index),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("allocating light buffer memory")?;
+
+        unsafe { device.bind_buffer_memory(buffer, light_buffer.memory, 0) }
+            .context("binding light buffer memory")?;
+
+        Ok(light_buffer)
+    }
+}
+
+impl Drop for LightBuffer {
+    fn drop(&mut self) {
+        teardown_trace::record("LightBuffer", [self.buffer.as_raw()]);
+
+        unsafe {
+            let device = self.logical_device.device();
+            device.destroy_buffer(self.buffer, host_allocation_callbacks());
+            device.free_memory(self.memory, host_allocation_callbacks());
+        }
+
+        self.logical_device
+            .resource_tracker()
+            .untrack(ResourceKind::Buffer, 1, self.size);
+    }
+}
@@ -0,0 +1,475 @@
+use std::{cell::Cell, ffi::CStr, rc::Rc};
+
+use ash::{
+    prelude::VkResult,
+    vk::{
+        DeviceCreateInfo, DeviceQueueCreateInfo, Handle, PhysicalDeviceFeatures,
+        PhysicalDeviceFeatures2, PhysicalDeviceGraphicsPipelineLibraryFeaturesEXT,
+        PhysicalDeviceMultiviewFeatures, PhysicalDeviceRobustness2FeaturesEXT, Queue,
+        AMD_BUFFER_MARKER_NAME, API_VERSION_1_1, EXT_GRAPHICS_PIPELINE_LIBRARY_NAME,
+        EXT_PIPELINE_CREATION_FEEDBACK_NAME, EXT_ROBUSTNESS2_NAME, KHR_MAINTENANCE1_NAME,
+        KHR_PIPELINE_LIBRARY_NAME, KHR_SWAPCHAIN_NAME, NV_DEVICE_DIAGNOSTIC_CHECKPOINTS_NAME, TRUE,
+    },
+    Device,
+};
+
+use crate::{
+    api2::{host_allocation_callbacks, ClipSpaceY, ErrorCtx, ResultExt},
+    renderer::{
+        physical_device::PhysicalDevice,
+        pipeline_stats::{PipelineStats, PipelineStatsTracker},
+        resource_stats::{ResourceStats, ResourceTracker},
+        teardown_trace,
+    },
+};
+
+pub static REQUIRED_EXTENSIONS: [&CStr; 1] = [KHR_SWAPCHAIN_NAME];
+
+#[derive(Clone)]
+#[allow(dead_code)]
+pub struct LogicalDevice(Rc<InnerLogicalDevice>);
+
+impl LogicalDevice {
+    pub fn new(physical_device: PhysicalDevice) -> Result<Self, ErrorCtx> {
+        Self::with_robustness(physical_device, Robustness::default())
+    }
+
+    /// Creates the device with the requested robustness features, each one only enabled when the
+    /// device supports it.
+    pub fn with_robustness(
+        physical_device: PhysicalDevice,
+        robustness: Robustness,
+    ) -> Result<Self, ErrorCtx> {
+        Self::create(physical_device, robustness, false, &REQUIRED_EXTENSIONS)
+    }
+
+    /// Like [LogicalDevice::with_robustness], also enabling the `multiview` feature when
+    /// `multiview` is set and the device supports it, for layered render passes.
+    pub fn with_multiview(
+        physical_device: PhysicalDevice,
+        robustness: Robustness,
+        multiview: bool,
+    ) -> Result<Self, ErrorCtx> {
+        Self::create(physical_device, robustness, multiview, &REQUIRED_EXTENSIONS)
+    }
+
+    /// Creates a device without the swapchain extension, for a physical device from
+    /// [PhysicalDevice::compute_only]. Its queue runs compute and transfers, and nothing can be
+    /// presented.
+    pub fn compute_only(physical_device: PhysicalDevice) -> Result<Self, ErrorCtx> {
+        Self::create(physical_device, Robustness::default(), false, &[])
+    }
+
+    fn create(
+        physical_device: PhysicalDevice,
+        robustness: Robustness,
+        multiview: bool,
+        required_extensions: &[&CStr],
+    ) -> Result<Self, ErrorCtx> {
+        let queue_priority = [1.0];
+        let queue_family_indices = [
+            physical_device.graphics_family_u32(),
+            physical_device.present_family_u32(),
+        ];
+
+        let queue_create_infos = create_queue_create_infos(&queue_family_indices, &queue_priority);
+
+        let supported_features = unsafe {
+            physical_device
+                .instance()
+                .instance()
+                .get_physical_device_features(*physical_device.device())
+        };
+
+        let enabled_robustness = Robustness {
+            robust_buffer_access: robustness.robust_buffer_access
+                && supported_features.robust_buffer_access == TRUE,
+            null_descriptor: robustness.null_descriptor
+                && supports_null_descriptor(&physical_device)
+                    .context("querying nullDescriptor support")?,
+        };
+
+        if enabled_robustness.robust_buffer_access {
+            println!("robustBufferAccess enabled, out-of-bounds accesses are bounds-checked at some performance cost");
+        }
+
+        if enabled_robustness.null_descriptor {
+            println!(
+                "nullDescriptor enabled, descriptors may be left empty at some performance cost"
+            );
+        }
+
+        let device_features = PhysicalDeviceFeatures::default()
+            .fill_mode_non_solid(supported_features.fill_mode_non_solid == TRUE)
+            .sampler_anisotropy(supported_features.sampler_anisotropy == TRUE)
+            .robust_buffer_access(enabled_robustness.robust_buffer_access);
+
+        let mut extensions: Vec<_> = required_extensions.iter().map(|s| s.as_ptr()).collect();
+
+        let mut robustness2_features =
+            PhysicalDeviceRobustness2FeaturesEXT::default().null_descriptor(true);
+        let mut multiview_features = PhysicalDeviceMultiviewFeatures::default().multiview(true);
+        let mut graphics_pipeline_library_features =
+            PhysicalDeviceGraphicsPipelineLibraryFeaturesEXT::default()
+                .graphics_pipeline_library(true);
+
+        let mut create_info = DeviceCreateInfo::default()
+            .queue_create_infos(queue_create_infos.as_slice())
+            .enabled_features(&device_features);
+
+        // Feedback is only informational, so it's enabled whenever available.
+        let creation_feedback =
+            supports_extension(&physical_device, EXT_PIPELINE_CREATION_FEEDBACK_NAME)
+                .context("querying pipeline creation feedback support")?;
+
+        if creation_feedback {
+            extensions.push(EXT_PIPELINE_CREATION_FEEDBACK_NAME.as_ptr());
+        }
+
+        // Needed for viewports with a negative height.
+        let maintenance1 = supports_extension(&physical_device, KHR_MAINTENANCE1_NAME)
+            .context("querying VK_KHR_maintenance1 support")?;
+
+        if maintenance1 {
+            extensions.push(KHR_MAINTENANCE1_NAME.as_ptr());
+        }
+
+        // Markers for crash dumps, preferring the checkpoints reporting every stage. They need
+        // vkGetPhysicalDeviceProperties2, like the robustness2 query.
+        let diagnostic_checkpoints = supports_vulkan_1_1(&physical_device)
+            && supports_extension(&physical_device, NV_DEVICE_DIAGNOSTIC_CHECKPOINTS_NAME)
+                .context("querying VK_NV_device_diagnostic_checkpoints support")?;
+        let buffer_markers = !diagnostic_checkpoints
+            && supports_extension(&physical_device, AMD_BUFFER_MARKER_NAME)
+                .context("querying VK_AMD_buffer_marker support")?;
+
+        if diagnostic_checkpoints {
+            extensions.push(NV_DEVICE_DIAGNOSTIC_CHECKPOINTS_NAME.as_ptr());
+        }
+
+        if buffer_markers {
+            extensions.push(AMD_BUFFER_MARKER_NAME.as_ptr());
+        }
+
+        if enabled_robustness.null_descriptor {
+            extensions.push(EXT_ROBUSTNESS2_NAME.as_ptr());
+            create_info = create_info.push_next(&mut robustness2_features);
+        }
+
+        // Rendering to every layer of layered swapchain images at once, core since Vulkan 1.1.
+        let multiview = multiview && supports_multiview(&physical_device);
+
+        if multiview {
+            create_info = create_info.push_next(&mut multiview_features);
+        }
+
+        // Creating pipelines from separately compiled parts, only used to speed creation up.
+        let graphics_pipeline_library = supports_graphics_pipeline_library(&physical_device)
+            .context("querying VK_EXT_graphics_pipeline_library support")?;
+
+        if graphics_pipeline_library {
+            extensions.push(KHR_PIPELINE_LIBRARY_NAME.as_ptr());
+            extensions.push(EXT_GRAPHICS_PIPELINE_LIBRARY_NAME.as_ptr());
+            create_info = create_info.push_next(&mut graphics_pipeline_library_features);
+        }
+
+        let create_info = create_info.enabled_extension_names(&extensions);
+
+        let device = unsafe {
+            physical_device.instance().instance().create_device(
+                *physical_device.device(),
+                &create_info,
+                host_allocation_callbacks(),
+            )
+        }
+        .with_context("creating logical device", || {
+            format!(
+                "queue_families={:?}, robustness={:?}",
+                queue_family_indices, enabled_robustness
+            )
+        })?;
+
+        let queue = unsafe { device.get_device_queue(physical_device.graphics_family_u32(), 0) };
+        let present_queue =
+            unsafe { device.get_device_queue(physical_device.present_family_u32(), 0) };
+
+        Ok(Self(Rc::new(InnerLogicalDevice {
+            device,
+            physical_device,
+            queue,
+            present_queue,
+            enabled_features: device_features,
+            robustness: enabled_robustness,
+            creation_feedback,
+            maintenance1,
+            diagnostic_checkpoints,
+            buffer_markers,
+            multiview,
+            graphics_pipeline_library,
+            clip_space_y: Cell::new(if maintenance1 {
+                ClipSpaceY::Up
+            } else {
+                ClipSpaceY::Down
+            }),
+            resource_tracker: ResourceTracker::default(),
+            pipeline_tracker: PipelineStatsTracker::default(),
+        })))
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.0.device
+    }
+
+    pub fn queue(&self) -> &Queue {
+        &self.0.queue
+    }
+
+    /// The queue of the present family, the same as [LogicalDevice::queue] when the graphics
+    /// family can present.
+    pub fn present_queue(&self) -> &Queue {
+        &self.0.present_queue
+    }
+
+    pub fn enabled_features(&self) -> &PhysicalDeviceFeatures {
+        &self.0.enabled_features
+    }
+
+    /// The robustness features that were actually enabled.
+    pub fn robustness(&self) -> Robustness {
+        self.0.robustness
+    }
+
+    pub fn supports_wireframe(&self) -> bool {
+        self.0.enabled_features.fill_mode_non_solid == TRUE
+    }
+
+    pub fn physical_device(&self) -> &PhysicalDevice {
+        &self.0.physical_device
+    }
+
+    pub fn resource_tracker(&self) -> &ResourceTracker {
+        &self.0.resource_tracker
+    }
+
+    pub fn resource_stats(&self) -> ResourceStats {
+        self.0.resource_tracker.snapshot()
+    }
+
+    /// Whether `VK_EXT_pipeline_creation_feedback` is enabled.
+    pub fn supports_creation_feedback(&self) -> bool {
+        self.0.creation_feedback
+    }
+
+    /// Whether viewports can be flipped with a negative height.
+    pub fn supports_flipped_viewport(&self) -> bool {
+        self.0.maintenance1
+    }
+
+    /// Whether `VK_NV_device_diagnostic_checkpoints` is enabled.
+    pub fn supports_diagnostic_checkpoints(&self) -> bool {
+        self.0.diagnostic_checkpoints
+    }
+
+    /// Whether `VK_AMD_buffer_marker` is enabled, only when the NV checkpoints aren't.
+    pub fn supports_buffer_markers(&self) -> bool {
+        self.0.buffer_markers
+    }
+
+    /// Whether the `multiview` feature is enabled, so render passes can have view masks. Only
+    /// when it was asked for with [LogicalDevice::with_multiview].
+    pub fn supports_multiview(&self) -> bool {
+        self.0.multiview
+    }
+
+    /// Whether `VK_EXT_graphics_pipeline_library` is enabled, so graphics pipelines can be linked
+    /// from pipeline libraries.
+    pub fn supports_graphics_pipeline_library(&self) -> bool {
+        self.0.graphics_pipeline_library
+    }
+
+    /// The clip-space orientation pipelines and cameras should be built for, [ClipSpaceY::Up]
+    /// by default when the viewport can be flipped.
+    pub fn clip_space_y(&self) -> ClipSpaceY {
+        self.0.clip_space_y.get()
+    }
+
+    /// Chooses whether to flip viewports, falling back to [ClipSpaceY::Down] when not supported,
+    /// and returns the orientation in effect. Pipelines created before keep their orientation.
+    pub fn set_flipped_viewport(&self, enabled: bool) -> ClipSpaceY {
+        let clip_space_y = if enabled && self.0.maintenance1 {
+            ClipSpaceY::Up
+        } else {
+            ClipSpaceY::Down
+        };
+
+        self.0.clip_space_y.set(clip_space_y);
+        clip_space_y
+    }
+
+    pub fn pipeline_tracker(&self) -> &PipelineStatsTracker {
+        &self.0.pipeline_tracker
+    }
+
+    pub fn pipeline_stats(&self) -> PipelineStats {
+        self.0.pipeline_tracker.snapshot()
+    }
+
+    pub fn wait_idle(&self) -> VkResult<()> {
+        unsafe { self.0.device.device_wait_idle() }
+    }
+}
+
+/// Opt-in features making out-of-bounds and unbound resource accesses well defined, which helps
+/// while learning at the cost of some GPU performance.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Robustness {
+    /// Out-of-bounds buffer accesses return zero or are discarded instead of being undefined.
+    pub robust_buffer_access: bool,
+    /// Descriptors may be written with null handles, reading them returns zero
+    /// (`VK_EXT_robustness2`).
+    pub null_descriptor: bool,
+}
+
+/// Whether `VK_EXT_robustness2` is available with `nullDescriptor`.
+///
+/// The feature query needs `vkGetPhysicalDeviceFeatures2`, so devices and instances older than
+/// Vulkan 1.1 report no support.
+fn supports_null_descriptor(physical_device: &PhysicalDevice) -> VkResult<bool> {
+    let instance = physical_device.instance().instance();
+
+    if !supports_vulkan_1_1(physical_device) {
+        return Ok(false);
+    }
+
+    let extensions =
+        unsafe { instance.enumerate_device_extension_properties(*physical_device.device())? };
+
+    if !extensions
+        .iter()
+        .any(|e| e.extension_name_as_c_str() == Ok(EXT_ROBUSTNESS2_NAME))
+    {
+        return Ok(false);
+    }
+
+    let mut robustness2_features = PhysicalDeviceRobustness2FeaturesEXT::default();
+    let mut features = PhysicalDeviceFeatures2::default().push_next(&mut robustness2_features);
+
+    unsafe { instance.get_physical_device_features2(*physical_device.device(), &mut features) };
+
+    Ok(robustness2_features.null_descriptor == TRUE)
+}
+
+/// Whether the Vulkan 1.1 `multiview` feature is supported, never on older devices or instances.
+fn supports_multiview(physical_device: &PhysicalDevice) -> bool {
+    if !supports_vulkan_1_1(physical_device) {
+        return false;
+    }
+
+    let mut multiview_features = PhysicalDeviceMultiviewFeatures::default();
+    let mut features = PhysicalDeviceFeatures2::default().push_next(&mut multiview_features);
+
+    unsafe {
+        physical_device
+            .instance()
+            .instance()
+            .get_physical_device_features2(*physical_device.device(), &mut features)
+    };
+
+    multiview_features.multiview == TRUE
+}
+
+/// Whether `VK_EXT_graphics_pipeline_library` and the `VK_KHR_pipeline_library` it builds on are
+/// available with the `graphicsPipelineLibrary` feature, never on devices or instances older than
+/// Vulkan 1.1.
+fn supports_graphics_pipeline_library(physical_device: &PhysicalDevice) -> VkResult<bool> {
+    if !supports_vulkan_1_1(physical_device)
+        || !supports_extension(physical_device, KHR_PIPELINE_LIBRARY_NAME)?
+        || !supports_extension(physical_device, EXT_GRAPHICS_PIPELINE_LIBRARY_NAME)?
+    {
+        return Ok(false);
+    }
+
+    let mut library_features = PhysicalDeviceGraphicsPipelineLibraryFeaturesEXT::default();
+    let mut features = PhysicalDeviceFeatures2::default().push_next(&mut library_features);
+
+    unsafe {
+        physical_device
+            .instance()
+            .instance()
+            .get_physical_device_features2(*physical_device.device(), &mut features)
+    };
+
+    Ok(library_features.graphics_pipeline_library == TRUE)
+}
+
+/// Whether Vulkan 1.1 functionality of the device can be used, which needs both the device and
+/// the instance to be 1.1.
+fn supports_vulkan_1_1(physical_device: &PhysicalDevice) -> bool {
+    physical_device.properties().api_version >= API_VERSION_1_1
+        && physical_device.instance().api_version() >= API_VERSION_1_1
+}
+
+fn supports_extension(physical_device: &PhysicalDevice, name: &CStr) -> VkResult<bool> {
+    let extensions = unsafe {
+        physical_device
+            .instance()
+            .instance()
+            .enumerate_device_extension_properties(*physical_device.device())?
+    };
+
+    Ok(extensions
+        .iter()
+        .any(|e| e.extension_name_as_c_str() == Ok(name)))
+}
+
+fn create_queue_create_infos<'a>(
+    indices: &'a [u32],
+    queue_priority: &'a [f32],
+) -> Vec<DeviceQueueCreateInfo<'a>> {
+    let mut queue_create_infos = Vec::new();
+    let mut processed_indices = Vec::new();
+
+    for &index in indices {
+        if !processed_indices.contains(&index) {
+            let queue_create_info = DeviceQueueCreateInfo::default()
+                .queue_family_index(index)
+                .queue_priorities(queue_priority);
+
+            queue_create_infos.push(queue_create_info);
+            processed_indices.push(index);
+        }
+    }
+
+    queue_create_infos
+}
+
+struct InnerLogicalDevice {
+    device: Device,
+    physical_device: PhysicalDevice,
+
+    #[allow(dead_code)]
+    queue: Queue,
+    present_queue: Queue,
+
+    enabled_features: PhysicalDeviceFeatures,
+    robustness: Robustness,
+    creation_feedback: bool,
+    maintenance1: bool,
+    diagnostic_checkpoints: bool,
+    buffer_markers: bool,
+    multiview: bool,
+    graphics_pipeline_library: bool,
+    clip_space_y: Cell<ClipSpaceY>,
+    resource_tracker: ResourceTracker,
+    pipeline_tracker: PipelineStatsTracker,
+}
+
+impl Drop for InnerLogicalDevice {
+    fn drop(&mut self) {
+        teardown_trace::record("LogicalDevice", [self.device.handle().as_raw()]);
+
+        unsafe {
+            self.device.destroy_device(host_allocation_callbacks());
+        }
+    }
+}
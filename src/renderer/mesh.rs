@@ -0,0 +1,592 @@
+//! Meshes on the CPU side and the bounding volumes culling, picking and debug drawing test
+//! against.
+
+use crate::renderer::pbr::PbrVertex;
+
+/// A column-major transform, like the ones pushed to the shaders.
+pub type Matrix = [[f32; 4]; 4];
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Aabb {
+    /// A box containing nothing, which merging anything into replaces.
+    pub const EMPTY: Self = Self {
+        min: [f32::INFINITY; 3],
+        max: [f32::NEG_INFINITY; 3],
+    };
+
+    pub fn from_points<I: IntoIterator<Item = [f32; 3]>>(points: I) -> Self {
+        points.into_iter().fold(Self::EMPTY, Self::including)
+    }
+
+    pub fn from_center_half_extents(center: [f32; 3], half_extents: [f32; 3]) -> Self {
+        Self {
+            min: [0, 1, 2].map(|i| center[i] - half_extents[i]),
+            max: [0, 1, 2].map(|i| center[i] + half_extents[i]),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..3).any(|i| self.min[i] > self.max[i])
+    }
+
+    pub fn center(&self) -> [f32; 3] {
+        [0, 1, 2].map(|i| (self.min[i] + self.max[i]) * 0.5)
+    }
+
+    pub fn half_extents(&self) -> [f32; 3] {
+        [0, 1, 2].map(|i| (self.max[i] - self.min[i]) * 0.5)
+    }
+
+    /// Grows the box to contain `point`.
+    pub fn including(self, point: [f32; 3]) -> Self {
+        Self {
+            min: [0, 1, 2].map(|i| self.min[i].min(point[i])),
+            max: [0, 1, 2].map(|i| self.max[i].max(point[i])),
+        }
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            min: [0, 1, 2].map(|i| self.min[i].min(other.min[i])),
+            max: [0, 1, 2].map(|i| self.max[i].max(other.max[i])),
+        }
+    }
+
+    pub fn contains(&self, point: [f32; 3]) -> bool {
+        (0..3).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i])
+    }
+
+    pub fn intersects(&self, other: &Self) -> bool {
+        (0..3).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+
+    /// The box around this one after `transform`, using Arvo's method: the half extents are
+    /// rotated through the absolute values of the matrix.
+    pub fn transformed(&self, transform: &Matrix) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+
+        let (center, half_extents) = transform_box(transform, self.center(), self.half_extents());
+        Self::from_center_half_extents(center, half_extents)
+    }
+
+    /// The distance along a ray from `origin` in `direction` at which it enters the box, if it
+    /// does, using the slab method. `direction` doesn't have to be normalized.
+    pub fn ray_intersection(&self, origin: [f32; 3], direction: [f32; 3]) -> Option<f32> {
+        let mut near = 0.0f32;
+        let mut far = f32::INFINITY;
+
+        for i in 0..3 {
+            let inverse = 1.0 / direction[i];
+            let t0 = (self.min[i] - origin[i]) * inverse;
+            let t1 = (self.max[i] - origin[i]) * inverse;
+
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+        }
+
+        (near <= far).then_some(near)
+    }
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct BoundingSphere {
+    pub center: [f32; 3],
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// A sphere around `points`, the smaller of Ritter's approximation and the sphere around
+    /// their box's center. Neither is minimal, but both are within a few percent for meshes.
+    pub fn from_points(points: &[[f32; 3]]) -> Self {
+        let Some(&first) = points.first() else {
+            return Self::default();
+        };
+
+        let aabb = Aabb::from_points(points.iter().copied());
+        let center = aabb.center();
+        let boxed = Self {
+            center,
+            radius: max_distance(center, points),
+        };
+
+        // Ritter: start from two far apart points and grow the sphere over the outliers.
+        let farthest_from = |from: [f32; 3]| {
+            points
+                .iter()
+                .copied()
+                .max_by(|a, b| distance_squared(from, *a).total_cmp(&distance_squared(from, *b)))
+                .unwrap_or(from)
+        };
+
+        let a = farthest_from(first);
+        let b = farthest_from(a);
+        let mut ritter = Self {
+            center: [0, 1, 2].map(|i| (a[i] + b[i]) * 0.5),
+            radius: distance_squared(a, b).sqrt() * 0.5,
+        };
+
+        for &point in points {
+            ritter = ritter.including(point);
+        }
+
+        if ritter.radius < boxed.radius {
+            ritter
+        } else {
+            boxed
+        }
+    }
+
+    /// Grows the sphere just enough to contain `point`, moving its center towards it.
+    pub fn including(self, point: [f32; 3]) -> Self {
+        let distance = distance_squared(self.center, point).sqrt();
+
+        if distance <= self.radius {
+            return self;
+        }
+
+        let radius = (self.radius + distance) * 0.5;
+        let shift = (radius - self.radius) / distance;
+
+        Self {
+            center: [0, 1, 2].map(|i| self.center[i] + (point[i] - self.center[i]) * shift),
+            radius,
+        }
+    }
+
+    pub fn contains(&self, point: [f32; 3]) -> bool {
+        distance_squared(self.center, point) <= self.radius * self.radius
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let closest = [0, 1, 2].map(|i| self.center[i].clamp(aabb.min[i], aabb.max[i]));
+        distance_squared(self.center, closest) <= self.radius * self.radius
+    }
+
+    /// The sphere after `transform`, scaling the radius by the largest axis scale so it stays
+    /// conservative under non-uniform scaling.
+    pub fn transformed(&self, transform: &Matrix) -> Self {
+        Self {
+            center: transform_point(transform, self.center),
+            radius: self.radius * max_scale(transform),
+        }
+    }
+}
+
+/// Both bounding volumes of a mesh, the box being tighter for elongated shapes and the sphere
+/// cheaper to test and to transform.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct MeshBounds {
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+}
+
+impl MeshBounds {
+    pub fn from_points(points: &[[f32; 3]]) -> Self {
+        Self {
+            aabb: Aabb::from_points(points.iter().copied()),
+            sphere: BoundingSphere::from_points(points),
+        }
+    }
+
+    pub fn transformed(&self, transform: &Matrix) -> Self {
+        Self {
+            aabb: self.aabb.transformed(transform),
+            sphere: self.sphere.transformed(transform),
+        }
+    }
+}
+
+/// An indexed triangle mesh in memory, with the bounds of its vertices computed when it's
+/// created.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<PbrVertex>,
+    pub indices: Vec<u32>,
+    bounds: MeshBounds,
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<PbrVertex>, indices: Vec<u32>) -> Self {
+        let mut mesh = Self {
+            vertices,
+            indices,
+            bounds: MeshBounds::default(),
+        };
+
+        mesh.recompute_bounds();
+        mesh
+    }
+
+    /// The bounds in the mesh's own space.
+    pub fn bounds(&self) -> MeshBounds {
+        self.bounds
+    }
+
+    /// Updates the bounds after editing the vertices.
+    pub fn recompute_bounds(&mut self) {
+        let positions: Vec<_> = self.vertices.iter().map(|vertex| vertex.position).collect();
+        self.bounds = MeshBounds::from_points(&positions);
+    }
+
+    /// Sets the normals to the average of the faces around each vertex, weighted by their area,
+    /// for meshes that came without normals. Vertices shared between faces are smoothed.
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![[0.0f32; 3]; self.vertices.len()];
+
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| self.vertices[triangle[i] as usize].position);
+            // Not normalized, so bigger faces weigh more.
+            let normal = cross(sub(b, a), sub(c, a));
+
+            for &index in triangle {
+                normals[index as usize] = add(normals[index as usize], normal);
+            }
+        }
+
+        for (vertex, normal) in self.vertices.iter_mut().zip(normals) {
+            vertex.normal = normalize(normal).unwrap_or([0.0, 0.0, 1.0]);
+        }
+    }
+
+    /// Computes tangents from the UVs, the basis normal maps are sampled in. Vertices without a
+    /// usable UV mapping get any tangent perpendicular to their normal.
+    pub fn compute_tangents(&mut self) {
+        let mut tangents = vec![[0.0f32; 3]; self.vertices.len()];
+        let mut bitangents = vec![[0.0f32; 3]; self.vertices.len()];
+
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| self.vertices[triangle[i] as usize]);
+
+            let edge1 = sub(b.position, a.position);
+            let edge2 = sub(c.position, a.position);
+            let duv1 = [b.uv[0] - a.uv[0], b.uv[1] - a.uv[1]];
+            let duv2 = [c.uv[0] - a.uv[0], c.uv[1] - a.uv[1]];
+
+            let determinant = duv1[0] * duv2[1] - duv2[0] * duv1[1];
+
+            if determinant.abs() < f32::EPSILON {
+                continue;
+            }
+
+            let r = 1.0 / determinant;
+            let tangent = scale(sub(scale(edge1, duv2[1]), scale(edge2, duv1[1])), r);
+            let bitangent = scale(sub(scale(edge2, duv1[0]), scale(edge1, duv2[0])), r);
+
+            for &index in triangle {
+                tangents[index as usize] = add(tangents[index as usize], tangent);
+                bitangents[index as usize] = add(bitangents[index as usize], bitangent);
+            }
+        }
+
+        for ((vertex, tangent), bitangent) in self.vertices.iter_mut().zip(tangents).zip(bitangents)
+        {
+            let normal = vertex.normal;
+            // Gram-Schmidt, keeping the tangent perpendicular to the normal.
+            let tangent = sub(tangent, scale(normal, dot(normal, tangent)));
+            let tangent = normalize(tangent).unwrap_or_else(|| any_perpendicular(normal));
+            let handedness = if dot(cross(normal, tangent), bitangent) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+
+            vertex.tangent = [tangent[0], tangent[1], tangent[2], handedness];
+        }
+    }
+}
+
+/// The world-space bounds of many objects, stored as a structure of arrays so recomputing them
+/// all after the transforms change runs as straight loops the compiler can vectorize.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BoundsBatch {
+    local: BoundsLanes,
+    world: BoundsLanes,
+}
+
+/// One array per scalar of a box given by its center and half extents and a sphere sharing the
+/// center of the box.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct BoundsLanes {
+    center: [Vec<f32>; 3],
+    half_extents: [Vec<f32>; 3],
+    sphere_center: [Vec<f32>; 3],
+    radius: Vec<f32>,
+}
+
+impl BoundsLanes {
+    fn push(&mut self, bounds: &MeshBounds) {
+        let (center, half_extents) = (bounds.aabb.center(), bounds.aabb.half_extents());
+
+        for i in 0..3 {
+            self.center[i].push(center[i]);
+            self.half_extents[i].push(half_extents[i]);
+            self.sphere_center[i].push(bounds.sphere.center[i]);
+        }
+
+        self.radius.push(bounds.sphere.radius);
+    }
+
+    fn set(&mut self, index: usize, bounds: &MeshBounds) {
+        let (center, half_extents) = (bounds.aabb.center(), bounds.aabb.half_extents());
+
+        for i in 0..3 {
+            self.center[i][index] = center[i];
+            self.half_extents[i][index] = half_extents[i];
+            self.sphere_center[i][index] = bounds.sphere.center[i];
+        }
+
+        self.radius[index] = bounds.sphere.radius;
+    }
+
+    fn get(&self, index: usize) -> MeshBounds {
+        MeshBounds {
+            aabb: Aabb::from_center_half_extents(
+                [0, 1, 2].map(|i| self.center[i][index]),
+                [0, 1, 2].map(|i| self.half_extents[i][index]),
+            ),
+            sphere: BoundingSphere {
+                center: [0, 1, 2].map(|i| self.sphere_center[i][index]),
+                radius: self.radius[index],
+            },
+        }
+    }
+}
+
+impl BoundsBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.local.radius.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds an object with `bounds` in its own space and returns its index. Its world bounds
+    /// equal its local ones until the next [BoundsBatch::recompute].
+    ///
+    /// Empty boxes aren't supported, give empty meshes a zero-sized box instead.
+    pub fn push(&mut self, bounds: MeshBounds) -> usize {
+        self.local.push(&bounds);
+        self.world.push(&bounds);
+        self.len() - 1
+    }
+
+    /// Replaces the local bounds of object `index`, e.g. after its mesh changed.
+    pub fn set_local(&mut self, index: usize, bounds: MeshBounds) {
+        self.local.set(index, &bounds);
+    }
+
+    pub fn local(&self, index: usize) -> MeshBounds {
+        self.local.get(index)
+    }
+
+    pub fn world(&self, index: usize) -> MeshBounds {
+        self.world.get(index)
+    }
+
+    /// Recomputes every world bound from `transforms`, the world transform of each object in
+    /// order, e.g. after the scene's transforms were updated.
+    pub fn recompute(&mut self, transforms: &[Matrix]) {
+        assert_eq!(
+            transforms.len(),
+            self.len(),
+            "one transform per object is needed"
+        );
+
+        let local = &self.local;
+        let world = &mut self.world;
+
+        for (index, transform) in transforms.iter().enumerate() {
+            let scale = max_scale(transform);
+
+            for row in 0..3 {
+                let (mut center, mut half_extent, mut sphere_center) =
+                    (transform[3][row], 0.0, transform[3][row]);
+
+                for (column, axis) in transform[..3].iter().enumerate() {
+                    let m = axis[row];
+                    center += m * local.center[column][index];
+                    half_extent += m.abs() * local.half_extents[column][index];
+                    sphere_center += m * local.sphere_center[column][index];
+                }
+
+                world.center[row][index] = center;
+                world.half_extents[row][index] = half_extent;
+                world.sphere_center[row][index] = sphere_center;
+            }
+
+            world.radius[index] = local.radius[index] * scale;
+        }
+    }
+
+    /// The indices of the objects whose world sphere is at least partly inside every plane, given
+    /// as `[a, b, c, d]` with `ax + by + cz + d >= 0` inside, e.g. the six planes of a frustum.
+    pub fn cull_spheres(&self, planes: &[[f32; 4]], visible: &mut Vec<usize>) {
+        visible.clear();
+
+        let world = &self.world;
+
+        visible.extend((0..self.len()).filter(|&index| {
+            planes.iter().all(|plane| {
+                plane[0] * world.sphere_center[0][index]
+                    + plane[1] * world.sphere_center[1][index]
+                    + plane[2] * world.sphere_center[2][index]
+                    + plane[3]
+                    >= -world.radius[index]
+            })
+        }));
+    }
+}
+
+/// The six planes of a view frustum, each `[a, b, c, d]` with `ax + by + cz + d >= 0` inside and
+/// `(a, b, c)` normalized.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Frustum {
+    pub planes: [[f32; 4]; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of a Vulkan view-projection matrix, whose depth goes from 0 to 1.
+    pub fn from_view_projection(view_projection: &Matrix) -> Self {
+        let row = |i: usize| [0, 1, 2, 3].map(|column| view_projection[column][i]);
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        let add = |a: [f32; 4], b: [f32; 4]| [0, 1, 2, 3].map(|i| a[i] + b[i]);
+        let sub = |a: [f32; 4], b: [f32; 4]| [0, 1, 2, 3].map(|i| a[i] - b[i]);
+
+        let planes = [add(w, x), sub(w, x), add(w, y), sub(w, y), z, sub(w, z)].map(|plane| {
+            let length = (plane[0] * plane[0] + plane[1] * plane[1] + plane[2] * plane[2]).sqrt();
+            plane.map(|value| value / length)
+        });
+
+        Self { planes }
+    }
+
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes.iter().all(|plane| {
+            plane[0] * sphere.center[0]
+                + plane[1] * sphere.center[1]
+                + plane[2] * sphere.center[2]
+                + plane[3]
+                >= -sphere.radius
+        })
+    }
+
+    /// Whether any part of `aabb` may be inside, testing the corner furthest along each plane's
+    /// normal. Boxes near the frustum's edges can pass without being visible.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            let corner = [0, 1, 2].map(|i| {
+                if plane[i] >= 0.0 {
+                    aabb.max[i]
+                } else {
+                    aabb.min[i]
+                }
+            });
+
+            plane[0] * corner[0] + plane[1] * corner[1] + plane[2] * corner[2] + plane[3] >= 0.0
+        })
+    }
+}
+
+fn transform_point(transform: &Matrix, point: [f32; 3]) -> [f32; 3] {
+    [0, 1, 2].map(|row| {
+        transform[0][row] * point[0]
+            + transform[1][row] * point[1]
+            + transform[2][row] * point[2]
+            + transform[3][row]
+    })
+}
+
+fn transform_box(
+    transform: &Matrix,
+    center: [f32; 3],
+    half_extents: [f32; 3],
+) -> ([f32; 3], [f32; 3]) {
+    let half_extents = [0, 1, 2].map(|row| {
+        (0..3)
+            .map(|column| transform[column][row].abs() * half_extents[column])
+            .sum()
+    });
+
+    (transform_point(transform, center), half_extents)
+}
+
+/// The largest length of the transform's basis vectors.
+fn max_scale(transform: &Matrix) -> f32 {
+    (0..3)
+        .map(|column| {
+            let [x, y, z, _] = transform[column];
+            x * x + y * y + z * z
+        })
+        .fold(0.0f32, f32::max)
+        .sqrt()
+}
+
+fn distance_squared(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3).map(|i| (a[i] - b[i]) * (a[i] - b[i])).sum()
+}
+
+fn max_distance(from: [f32; 3], points: &[[f32; 3]]) -> f32 {
+    points
+        .iter()
+        .map(|&point| distance_squared(from, point))
+        .fold(0.0f32, f32::max)
+        .sqrt()
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f32; 3], factor: f32) -> [f32; 3] {
+    [a[0] * factor, a[1] * factor, a[2] * factor]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(a: [f32; 3]) -> Option<[f32; 3]> {
+    let length = dot(a, a).sqrt();
+    (length > f32::EPSILON).then(|| scale(a, 1.0 / length))
+}
+
+fn any_perpendicular(normal: [f32; 3]) -> [f32; 3] {
+    let axis = if normal[0].abs() < 0.9 {
+        [1.0, 0.0, 0.0]
+    } else {
+        [0.0, 1.0, 0.0]
+    };
+
+    normalize(cross(normal, axis)).unwrap_or([1.0, 0.0, 0.0])
+}
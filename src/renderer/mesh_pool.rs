@@ -0,0 +1,592 @@
+//! Packing many small meshes into a few large device-local buffers.
+//!
+//! Every mesh in a [MeshBufferPool] is a range of vertices and a range of indices inside one of
+//! its blocks, each block being one vertex and one index buffer. Draws pick their mesh with the
+//! first index and vertex offset of `vkCmdDrawIndexed`, so consecutive meshes of the same block
+//! share a single bind, and hundreds of meshes only take a handful of allocations.
+//!
+//! `examples/mesh_pool.rs` draws a few hundred polygons from a pool of small blocks.
+
+use std::{error, fmt, mem};
+
+use ash::vk::{
+    self, AccessFlags, BufferCopy, BufferCreateInfo, BufferUsageFlags, CommandBuffer,
+    DependencyFlags, DeviceSize, Handle, IndexType, MemoryAllocateInfo, MemoryBarrier,
+    MemoryPropertyFlags, PipelineStageFlags, SharingMode,
+};
+
+use crate::{
+    api2::{host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        logical_device::LogicalDevice,
+        resource_stats::ResourceKind,
+        staging_ring::{StagingError, StagingRing},
+        teardown_trace,
+    },
+    types::slice_bytes,
+};
+
+/// Where a mesh lives in a [MeshBufferPool].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PooledMesh {
+    pub block: usize,
+    /// The first vertex, in vertices, to pass as the vertex offset of the draw.
+    pub first_vertex: u32,
+    pub vertex_count: u32,
+    /// The first index, in indices, to pass as the first index of the draw.
+    pub first_index: u32,
+    pub index_count: u32,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MeshPoolStats {
+    /// The blocks allocated, each one vertex and one index buffer.
+    pub blocks: u32,
+    pub meshes: u32,
+    pub vertices: u64,
+    pub indices: u64,
+    /// The vertex and index buffers bound by [MeshBufferPool::draw], once per block change.
+    pub binds: u64,
+    pub draws: u64,
+}
+
+/// A pool of device-local vertex and index buffers that meshes are suballocated from.
+///
+/// All meshes share one vertex layout and 32-bit indices. Uploads go through a [StagingRing] and
+/// are recorded into a command buffer, so the meshes can be drawn once it was submitted.
+pub struct MeshBufferPool {
+    blocks: Vec<MeshBlock>,
+    vertex_stride: u32,
+    /// How many vertices and indices new blocks hold, unless a mesh needs more.
+    block_vertices: u32,
+    block_indices: u32,
+    stats: MeshPoolStats,
+
+    logical_device: LogicalDevice,
+}
+
+impl MeshBufferPool {
+    /// Creates an empty pool for vertices of `vertex_stride` bytes, which can't be 0. Blocks are
+    /// only allocated when meshes are added, each with room for `block_vertices` and
+    /// `block_indices`.
+    pub fn new(
+        logical_device: LogicalDevice,
+        vertex_stride: u32,
+        block_vertices: u32,
+        block_indices: u32,
+    ) -> Result<Self, MeshPoolError> {
+        if vertex_stride == 0 {
+            return Err(MeshPoolError::ZeroStride);
+        }
+
+        Ok(Self {
+            blocks: Vec::new(),
+            vertex_stride,
+            block_vertices,
+            block_indices,
+            stats: MeshPoolStats::default(),
+            logical_device,
+        })
+    }
+
+    pub fn vertex_stride(&self) -> u32 {
+        self.vertex_stride
+    }
+
+    pub fn stats(&self) -> MeshPoolStats {
+        self.stats
+    }
+
+    /// Records the upload of a mesh into `command_buffer`, staging its data in `staging`.
+    ///
+    /// `vertices` holds whole vertices of [MeshBufferPool::vertex_stride] bytes and the indices
+    /// are relative to the mesh's first vertex. The command buffer has to be submitted, and the
+    /// staging ring told about it, before the mesh is drawn.
+    pub fn add(
+        &mut self,
+        staging: &mut StagingRing,
+        command_buffer: CommandBuffer,
+        vertices: &[u8],
+        indices: &[u32],
+    ) -> Result<PooledMesh, MeshPoolError> {
+        let stride = self.vertex_stride as usize;
+
+        if vertices.len() % stride != 0 {
+            return Err(MeshPoolError::PartialVertex {
+                size: vertices.len(),
+                stride: self.vertex_stride,
+            });
+        }
+
+        let vertex_count = (vertices.len() / stride) as u32;
+        let index_count = indices.len() as u32;
+
+        let found = self.blocks.iter_mut().enumerate().find_map(|(block, b)| {
+            let first_vertex = b.vertices.allocate(vertex_count)?;
+
+            match b.indices.allocate(index_count) {
+                Some(first_index) => Some((block, first_vertex, first_index)),
+                None => {
+                    b.vertices.free(first_vertex, vertex_count);
+                    None
+                }
+            }
+        });
+
+        let (block, first_vertex, first_index) = match found {
+            Some(found) => found,
+            None => {
+                let mut new_block = MeshBlock::new(
+                    &self.logical_device,
+                    self.vertex_stride,
+                    self.block_vertices.max(vertex_count),
+                    self.block_indices.max(index_count),
+                )?;
+
+                let first_vertex = new_block.vertices.allocate(vertex_count).unwrap_or(0);
+                let first_index = new_block.indices.allocate(index_count).unwrap_or(0);
+
+                self.blocks.push(new_block);
+                self.stats.blocks += 1;
+
+                (self.blocks.len() - 1, first_vertex, first_index)
+            }
+        };
+
+        let mesh = PooledMesh {
+            block,
+            first_vertex,
+            vertex_count,
+            first_index,
+            index_count,
+        };
+
+        if let Err(e) = self.record_upload(staging, command_buffer, &mesh, vertices, indices) {
+            self.free(&mesh);
+            return Err(e);
+        }
+
+        self.stats.meshes += 1;
+        self.stats.vertices += vertex_count as u64;
+        self.stats.indices += index_count as u64;
+
+        Ok(mesh)
+    }
+
+    /// Gives the mesh's ranges back to the pool, once the GPU is done drawing it. Blocks are
+    /// kept even when they become empty.
+    pub fn remove(&mut self, mesh: &PooledMesh) {
+        self.free(mesh);
+
+        self.stats.meshes -= 1;
+        self.stats.vertices -= mesh.vertex_count as u64;
+        self.stats.indices -= mesh.index_count as u64;
+    }
+
+    /// Draws `instances` instances of the mesh, binding its block's buffers unless `bound_block`
+    /// says they already are. Start a command buffer with `None`, and draw meshes sorted by
+    /// block to bind as rarely as possible.
+    pub fn draw(
+        &mut self,
+        command_buffer: CommandBuffer,
+        mesh: &PooledMesh,
+        instances: u32,
+        bound_block: &mut Option<usize>,
+    ) {
+        let device = self.logical_device.device();
+
+        if *bound_block != Some(mesh.block) {
+            let block = &self.blocks[mesh.block];
+
+            unsafe {
+                device.cmd_bind_vertex_buffers(command_buffer, 0, &[block.vertex_buffer], &[0]);
+                device.cmd_bind_index_buffer(
+                    command_buffer,
+                    block.index_buffer,
+                    0,
+                    IndexType::UINT32,
+                );
+            }
+
+            *bound_block = Some(mesh.block);
+            self.stats.binds += 1;
+        }
+
+        unsafe {
+            device.cmd_draw_indexed(
+                command_buffer,
+                mesh.index_count,
+                instances,
+                mesh.first_index,
+                mesh.first_vertex as i32,
+                0,
+            );
+        }
+
+        self.stats.draws += 1;
+    }
+
+    fn free(&mut self, mesh: &PooledMesh) {
+        let block = &mut self.blocks[mesh.block];
+        block.vertices.free(mesh.first_vertex, mesh.vertex_count);
+        block.indices.free(mesh.first_index, mesh.index_count);
+    }
+
+    fn record_upload(
+        &self,
+        staging: &mut StagingRing,
+        command_buffer: CommandBuffer,
+        mesh: &PooledMesh,
+        vertices: &[u8],
+        indices: &[u32],
+    ) -> Result<(), MeshPoolError> {
+        let device = self.logical_device.device();
+        let block = &self.blocks[mesh.block];
+        let index_size = mem::size_of::<u32>() as DeviceSize;
+
+        let copies = [
+            (
+                vertices,
+                block.vertex_buffer,
+                mesh.first_vertex as DeviceSize * self.vertex_stride as DeviceSize,
+            ),
+            (
+                slice_bytes(indices),
+                block.index_buffer,
+                mesh.first_index as DeviceSize * index_size,
+            ),
+        ];
+
+        for (data, buffer, dst_offset) in copies {
+            if data.is_empty() {
+                continue;
+            }
+
+            let src_offset = staging.write(data, index_size)?;
+
+            unsafe {
+                device.cmd_copy_buffer(
+                    command_buffer,
+                    staging.buffer(),
+                    buffer,
+                    &[BufferCopy::default()
+                        .src_offset(src_offset)
+                        .dst_offset(dst_offset)
+                        .size(data.len() as DeviceSize)],
+                );
+            }
+        }
+
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::VERTEX_INPUT,
+                DependencyFlags::empty(),
+                &[MemoryBarrier::default()
+                    .src_access_mask(AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(AccessFlags::VERTEX_ATTRIBUTE_READ | AccessFlags::INDEX_READ)],
+                &[],
+                &[],
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// One vertex and one index buffer with the ranges of them in use.
+struct MeshBlock {
+    vertex_buffer: vk::Buffer,
+    vertex_memory: vk::DeviceMemory,
+    vertex_size: DeviceSize,
+    index_buffer: vk::Buffer,
+    index_memory: vk::DeviceMemory,
+    index_size: DeviceSize,
+    vertices: RangeAllocator,
+    indices: RangeAllocator,
+
+    logical_device: LogicalDevice,
+}
+
+impl MeshBlock {
+    fn new(
+        logical_device: &LogicalDevice,
+        vertex_stride: u32,
+        vertex_capacity: u32,
+        index_capacity: u32,
+    ) -> Result<Self, ErrorCtx> {
+        // Every handle starts null so a failure halfway only destroys what was created.
+        let mut block = Self {
+            vertex_buffer: vk::Buffer::null(),
+            vertex_memory: vk::DeviceMemory::null(),
+            vertex_size: 0,
+            index_buffer: vk::Buffer::null(),
+            index_memory: vk::DeviceMemory::null(),
+            index_size: 0,
+            vertices: RangeAllocator::new(vertex_capacity),
+            indices: RangeAllocator::new(index_capacity),
+            logical_device: logical_device.clone(),
+        };
+
+        (block.vertex_buffer, block.vertex_memory, block.vertex_size) = create_device_buffer(
+            logical_device,
+            vertex_capacity as DeviceSize * vertex_stride as DeviceSize,
+            BufferUsageFlags::VERTEX_BUFFER,
+        )?;
+
+        (block.index_buffer, block.index_memory, block.index_size) = create_device_buffer(
+            logical_device,
+            index_capacity as DeviceSize * mem::size_of::<u32>() as DeviceSize,
+            BufferUsageFlags::INDEX_BUFFER,
+        )?;
+
+        Ok(block)
+    }
+}
+
+impl Drop for MeshBlock {
+    fn drop(&mut self) {
+        teardown_trace::record(
+            "MeshBlock",
+            [self.vertex_buffer.as_raw(), self.index_buffer.as_raw()],
+        );
+
+        let device = self.logical_device.device();
+        let tracker = self.logical_device.resource_tracker();
+
+        // Destroying and freeing null handles does nothing, only the tracking needs checks.
+        unsafe {
+            device.destroy_buffer(self.index_buffer, host_allocation_callbacks());
+            device.free_memory(self.index_memory, host_allocation_callbacks());
+            device.destroy_buffer(self.vertex_buffer, host_allocation_callbacks());
+            device.free_memory(self.vertex_memory, host_allocation_callbacks());
+        }
+
+        for size in [self.vertex_size, self.index_size] {
+            if size > 0 {
+                tracker.untrack(ResourceKind::Buffer, 1, size);
+            }
+        }
+    }
+}
+
+/// Creates a device-local buffer that can be copied into, returning it with its memory and the
+/// size of the memory. Zero-sized buffers are created with one byte, which Vulkan requires.
+fn create_device_buffer(
+    logical_device: &LogicalDevice,
+    size: DeviceSize,
+    usage: BufferUsageFlags,
+) -> Result<(vk::Buffer, vk::DeviceMemory, DeviceSize), ErrorCtx> {
+    let device = logical_device.device();
+
+    let buffer = unsafe {
+        device.create_buffer(
+            &BufferCreateInfo::default()
+                .size(size.max(1))
+                .usage(usage | BufferUsageFlags::TRANSFER_DST)
+                .sharing_mode(SharingMode::EXCLUSIVE),
+            host_allocation_callbacks(),
+        )
+    }
+    .with_context("creating mesh pool buffer", || {
+        format!("size={}, usage={:?}", size, usage)
+    })?;
+
+    let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+
+    let memory = logical_device
+        .physical_device()
+        .find_memory_type(
+            requirements.memory_type_bits,
+            MemoryPropertyFlags::DEVICE_LOCAL,
+        )
+        .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+        .and_then(|memory_type_index| unsafe {
+            device.allocate_memory(
+                &MemoryAllocateInfo::default()
+                    .allocation_size(requirements.size)
+                    .memory_type_index(memory_type_index),
+                host_allocation_callbacks(),
+            )
+        })
+        .and_then(|memory| {
+            unsafe { device.bind_buffer_memory(buffer, memory, 0) }.inspect_err(|_| {
+                unsafe { device.free_memory(memory, host_allocation_callbacks()) };
+            })?;
+
+            Ok(memory)
+        });
+
+    let memory = match memory {
+        Ok(memory) => memory,
+        Err(e) => {
+            unsafe { device.destroy_buffer(buffer, host_allocation_callbacks()) };
+            return Err(ErrorCtx::new("allocating mesh pool memory", e));
+        }
+    };
+
+    logical_device
+        .resource_tracker()
+        .track(ResourceKind::Buffer, 1, requirements.size);
+
+    Ok((buffer, memory, requirements.size))
+}
+
+/// First-fit allocation of ranges of elements, merging freed ranges with their neighbors.
+struct RangeAllocator {
+    /// The free ranges as `(start, len)`, sorted and never adjacent.
+    free: Vec<(u32, u32)>,
+}
+
+impl RangeAllocator {
+    fn new(capacity: u32) -> Self {
+        Self {
+            free: if capacity > 0 {
+                vec![(0, capacity)]
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    fn allocate(&mut self, len: u32) -> Option<u32> {
+        if len == 0 {
+            return Some(0);
+        }
+
+        let i = self.free.iter().position(|&(_, free)| free >= len)?;
+        let (start, free) = self.free[i];
+
+        if free == len {
+            self.free.remove(i);
+        } else {
+            self.free[i] = (start + len, free - len);
+        }
+
+        Some(start)
+    }
+
+    fn free(&mut self, start: u32, len: u32) {
+        if len == 0 {
+            return;
+        }
+
+        let i = self.free.partition_point(|&(free, _)| free < start);
+        self.free.insert(i, (start, len));
+
+        if i + 1 < self.free.len() && start + len == self.free[i + 1].0 {
+            self.free[i].1 += self.free.remove(i + 1).1;
+        }
+
+        if i > 0 && self.free[i - 1].0 + self.free[i - 1].1 == start {
+            self.free[i - 1].1 += self.free.remove(i).1;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MeshPoolError {
+    Context(ErrorCtx),
+    Staging(StagingError),
+    /// The pool was created with a vertex stride of 0.
+    ZeroStride,
+    /// The vertex data doesn't end on a whole vertex.
+    PartialVertex {
+        size: usize,
+        stride: u32,
+    },
+}
+
+impl From<ErrorCtx> for MeshPoolError {
+    fn from(value: ErrorCtx) -> Self {
+        Self::Context(value)
+    }
+}
+
+impl From<StagingError> for MeshPoolError {
+    fn from(value: StagingError) -> Self {
+        Self::Staging(value)
+    }
+}
+
+impl fmt::Display for MeshPoolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Context(e) => e.fmt(f),
+            Self::Staging(e) => write!(f, "staging mesh data: {}", e),
+            Self::ZeroStride => write!(f, "vertices can't be 0 bytes long"),
+            Self::PartialVertex { size, stride } => write!(
+                f,
+                "{} bytes of vertex data aren't a multiple of the {} byte stride",
+                size, stride
+            ),
+        }
+    }
+}
+
+impl error::Error for MeshPoolError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocates_first_fit() {
+        let mut ranges = RangeAllocator::new(10);
+
+        assert_eq!(ranges.allocate(4), Some(0));
+        assert_eq!(ranges.allocate(4), Some(4));
+        assert_eq!(ranges.allocate(4), None);
+        assert_eq!(ranges.allocate(2), Some(8));
+        assert_eq!(ranges.allocate(1), None);
+        assert!(ranges.free.is_empty());
+
+        // Empty ranges always fit, even in a full allocator.
+        assert_eq!(ranges.allocate(0), Some(0));
+        assert_eq!(RangeAllocator::new(0).allocate(1), None);
+    }
+
+    #[test]
+    fn reuses_freed_ranges() {
+        let mut ranges = RangeAllocator::new(10);
+        ranges.allocate(3);
+        ranges.allocate(3);
+        ranges.allocate(4);
+
+        ranges.free(3, 3);
+        assert_eq!(ranges.free, [(3, 3)]);
+
+        assert_eq!(ranges.allocate(4), None);
+        assert_eq!(ranges.allocate(2), Some(3));
+        assert_eq!(ranges.free, [(5, 1)]);
+    }
+
+    #[test]
+    fn merges_freed_ranges_with_neighbors() {
+        let mut ranges = RangeAllocator::new(12);
+
+        for _ in 0..4 {
+            ranges.allocate(3);
+        }
+
+        // Neither neighbor is free.
+        ranges.free(3, 3);
+        ranges.free(9, 3);
+        assert_eq!(ranges.free, [(3, 3), (9, 3)]);
+
+        // Between two free ranges, merging with both.
+        ranges.free(6, 3);
+        assert_eq!(ranges.free, [(3, 9)]);
+
+        // Only the following neighbor is free.
+        ranges.free(0, 3);
+        assert_eq!(ranges.free, [(0, 12)]);
+        assert_eq!(ranges.allocate(12), Some(0));
+
+        // Only the preceding neighbor is free.
+        ranges.free(0, 6);
+        ranges.free(6, 6);
+        assert_eq!(ranges.free, [(0, 12)]);
+    }
+}
@@ -0,0 +1,937 @@
+//! The renderer the tutorial builds, from the instance to presenting, on top of the [api2]
+//! wrappers, and the subsystems added to it since.
+//!
+//! Each module is one step or subsystem, wired by hand the way the tutorial's chapters do. The
+//! [Renderer] in this module puts the whole chain in one place, for when the individual steps
+//! aren't what's being learned: [Renderer::builder] creates it from the instance to the frames in
+//! flight as a [RendererConfig] describes, and [Renderer::draw_frame] renders and presents the mesh
+//! it was given. The swapchain and everything sized after it are recreated when the window is
+//! resized or the surface reports them out of date, and skipped while the window is minimized or
+//! rendering is suspended, as its [Lifecycle] tracks. A lost surface is recreated from the window.
+//!
+//! `src/main.rs` runs the tutorial through it, and the `capi` feature's C ABI embeds it into
+//! native windows with [RendererBuilder::build_with_surface].
+//!
+//! [api2]: crate::api2
+
+use std::{
+    error::Error,
+    time::{Duration, Instant},
+};
+
+use ash::{
+    vk::{
+        self, make_api_version, BufferUsageFlags, CullModeFlags, PipelineStageFlags, PolygonMode,
+        SampleCountFlags, SubmitInfo,
+    },
+    Entry,
+};
+use glfw::WindowEvent;
+
+use crate::api2::{Color, Lifecycle, LifecycleState, RenderEvent, SwapchainExtent};
+use acquire_policy::{AcquireError, AcquirePolicy, AcquireStats, FrameAcquirer};
+use buffer::{Buffer, IndexBuffer};
+use command_buffers::CommandBuffers;
+use command_pool::CommandPool;
+use command_trace::{CommandTrace, RecordedCommand};
+use crash_dump::CrashDiagnostics;
+use debug_layer::DebugLayer;
+use frame_sync::FrameSync;
+use framebuffers::Framebuffers;
+use graphics_pipeline::{GraphicsPipeline, PipelineVariant};
+use image_views::ImageViews;
+use instance::Instance;
+use logical_device::LogicalDevice;
+use physical_device::PhysicalDevice;
+use present_benchmark::{PresentModeReport, PresentModeResult};
+use present_transfer::PresentTransfer;
+use render_pass::RenderPass;
+use renderer_config::{RendererConfig, RendererPreset};
+use shader_cache::ShaderCache;
+use staging_uploader::StagingUploader;
+use submit_trace::{SubmitTrace, SubmitTracer};
+use surface::Surface;
+use swapchain::{Swapchain, SwapchainPreferences};
+use utils::check_validation_layer_support;
+use vertex::Vertex;
+use window::Window;
+
+pub mod acquire_policy;
+pub mod assets;
+pub mod auto_exposure;
+pub mod batch_render;
+pub mod blend_mode;
+pub mod buffer;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod color_image;
+pub mod command_buffers;
+pub mod command_pool;
+pub mod command_trace;
+pub mod crash_dump;
+pub mod debug_layer;
+pub mod frame_sync;
+pub mod framebuffers;
+pub mod graphics_pipeline;
+pub mod ibl;
+pub mod image2d;
+pub mod image_desc;
+pub mod image_views;
+pub mod instance;
+pub mod lights;
+pub mod logical_device;
+pub mod mesh;
+pub mod mesh_pool;
+pub mod model;
+pub mod overlay;
+pub mod pbr;
+pub mod physical_device;
+pub mod picking;
+pub mod pipeline_stats;
+pub mod present_benchmark;
+pub mod present_transfer;
+pub mod procedural_texture;
+pub mod render_desc;
+pub mod render_pass;
+pub mod renderer_config;
+pub mod resource_stats;
+pub mod sampler;
+pub mod scope;
+pub mod shader_cache;
+pub mod shader_compiler;
+pub mod shader_include;
+pub mod shader_module;
+pub mod skinning;
+pub mod staging_ring;
+pub mod staging_uploader;
+pub mod submit_trace;
+pub mod surface;
+pub mod swapchain;
+pub mod sync_objects;
+pub mod teardown_trace;
+pub mod terrain;
+pub mod testing;
+pub mod tint_pass;
+pub mod utils;
+pub mod vertex;
+pub mod window;
+
+pub const VALIDATION_LAYERS: [&str; 1] = ["VK_LAYER_KHRONOS_validation"];
+
+pub const ENABLE_VALIDATION_LAYERS: bool = cfg!(debug_assertions);
+
+pub const SHADER_VERT: &[u8; 1008] = include_bytes!("../../shaders/vert.spv");
+pub const SHADER_FRAG: &[u8; 572] = include_bytes!("../../shaders/frag.spv");
+/// The most frames the CPU can record ahead of the GPU, per-frame resources are allocated for
+/// this many.
+pub const MAX_FRAMES_IN_FLIGHT: usize = 3;
+pub const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
+
+/// Creates a [Renderer], drawing [Vertex::TRIANGLE] with the [RendererPreset::Tutorial] preset
+/// unless told otherwise.
+#[derive(Debug, Clone)]
+pub struct RendererBuilder {
+    config: RendererConfig,
+    application_name: String,
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+    clear_color: Color,
+    stereo: bool,
+}
+
+impl Default for RendererBuilder {
+    fn default() -> Self {
+        Self {
+            config: RendererConfig::default(),
+            application_name: "Vulkan Tutorial".to_owned(),
+            vertices: Vertex::TRIANGLE.to_vec(),
+            indices: Vertex::TRIANGLE_INDICES.to_vec(),
+            clear_color: Color::BLACK,
+            stereo: false,
+        }
+    }
+}
+
+impl RendererBuilder {
+    pub fn config(mut self, config: RendererConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn preset(self, preset: RendererPreset) -> Self {
+        self.config(preset.config())
+    }
+
+    pub fn application_name(mut self, name: &str) -> Self {
+        self.application_name = name.to_owned();
+        self
+    }
+
+    /// The indexed triangle list to draw.
+    pub fn mesh(mut self, vertices: &[Vertex], indices: &[u16]) -> Self {
+        self.vertices = vertices.to_vec();
+        self.indices = indices.to_vec();
+        self
+    }
+
+    pub fn clear_color(mut self, color: Color) -> Self {
+        self.clear_color = color;
+        self
+    }
+
+    /// Presents a stereoscopic pair with a multiview render pass, where the device supports
+    /// multiview and swapchains with two layers. Both eyes get the same picture, the shaders
+    /// don't look at `gl_ViewIndex`.
+    pub fn stereo(mut self, stereo: bool) -> Self {
+        self.stereo = stereo;
+        self
+    }
+
+    /// Creates the renderer for `window`. Validation is left off where the layer isn't
+    /// installed, and the multisampling, wireframe pipeline and stereo are lowered to what the
+    /// device supports.
+    pub fn build(self, window: &Window) -> Result<Renderer, Box<dyn Error>> {
+        let entry = unsafe { Entry::load() }?;
+
+        // GLFW looks for the loader on its own, so it can still miss it after Entry::load.
+        let required_extensions = window
+            .get_required_instance_extensions()
+            .ok_or("GLFW found no Vulkan loader for window surfaces")?;
+
+        let framebuffer_size = window.get_framebuffer_size();
+
+        self.build_inner(
+            entry,
+            required_extensions,
+            framebuffer_size,
+            Some(window.clone()),
+            |instance| Ok(Surface::new(instance.clone(), window.clone())?),
+        )
+    }
+
+    /// Creates the renderer for a surface made outside of GLFW, e.g. from a native window
+    /// handle. The instance is created with `instance_extensions` and given to `create_surface`,
+    /// and the renderer takes ownership of the surface it returns. Without a window to ask, the
+    /// framebuffer is `framebuffer_size` until [Renderer::resize] reports another size.
+    pub fn build_with_surface(
+        self,
+        instance_extensions: Vec<String>,
+        framebuffer_size: (u32, u32),
+        create_surface: impl FnOnce(&Instance) -> Result<vk::SurfaceKHR, Box<dyn Error>>,
+    ) -> Result<Renderer, Box<dyn Error>> {
+        let entry = unsafe { Entry::load() }?;
+
+        self.build_inner(
+            entry,
+            instance_extensions,
+            (framebuffer_size.0 as i32, framebuffer_size.1 as i32),
+            None,
+            |instance| {
+                Ok(Surface::from_raw(
+                    instance.clone(),
+                    create_surface(instance)?,
+                ))
+            },
+        )
+    }
+
+    fn build_inner(
+        self,
+        entry: Entry,
+        instance_extensions: Vec<String>,
+        framebuffer_size: (i32, i32),
+        window: Option<Window>,
+        create_surface: impl FnOnce(&Instance) -> Result<Surface, Box<dyn Error>>,
+    ) -> Result<Renderer, Box<dyn Error>> {
+        let validation = self.config.validation && check_validation_layer_support(&entry)?;
+
+        if self.config.validation && !validation {
+            eprintln!("validation layers requested, but not available");
+        }
+
+        let instance = Instance::with_validation(
+            entry,
+            instance_extensions,
+            &self.application_name,
+            crate::cargo_version!().to_vulkan(),
+            "No Engine",
+            make_api_version(0, 1, 0, 0),
+            validation,
+        )?;
+
+        let debug_layer = if validation {
+            Some(DebugLayer::new(instance.clone())?)
+        } else {
+            None
+        };
+
+        let surface = create_surface(&instance)?;
+        let physical_device = PhysicalDevice::new(instance, &surface)?;
+        let logical_device = LogicalDevice::with_multiview(
+            physical_device.clone(),
+            self.config.robustness,
+            self.stereo,
+        )?;
+        let command_pool = CommandPool::new(logical_device.clone(), &physical_device)?;
+
+        let uploader = StagingUploader::new(command_pool.clone())?;
+        let vertex_buffer =
+            uploader.create_buffer(&self.vertices, BufferUsageFlags::VERTEX_BUFFER)?;
+        let index_buffer = IndexBuffer::new(&uploader, &self.indices)?;
+
+        let mut pipeline_variants = vec![PipelineVariant::default()];
+
+        if self.config.wireframe_variant && logical_device.supports_wireframe() {
+            pipeline_variants.push(PipelineVariant {
+                polygon_mode: PolygonMode::LINE,
+                cull_mode: CullModeFlags::NONE,
+                ..Default::default()
+            });
+        }
+
+        let stereo = self.stereo && {
+            let supported = logical_device.supports_multiview()
+                && physical_device
+                    .swapchain_support()
+                    .capabilities
+                    .max_image_array_layers
+                    >= 2;
+
+            if !supported {
+                println!(
+                    "stereoscopic swapchains or multiview are not supported, presenting one layer"
+                );
+            }
+
+            supported
+        };
+
+        let mut swapchain_preferences = self.config.swapchain;
+
+        if stereo {
+            swapchain_preferences.array_layers = Some(2);
+        }
+
+        let crash_diagnostics = if self.config.crash_diagnostics {
+            let crash_diagnostics =
+                CrashDiagnostics::new(logical_device.clone(), MAX_FRAMES_IN_FLIGHT)?;
+
+            if !crash_diagnostics.has_checkpoints() {
+                println!("no checkpoint extension, crash dumps won't tell where the GPU stopped");
+            }
+
+            Some(crash_diagnostics)
+        } else {
+            None
+        };
+
+        let mut renderer = Renderer {
+            window,
+            framebuffer_size,
+            shader_cache: ShaderCache::new(logical_device.clone()),
+            samples: self
+                .config
+                .samples
+                .min(physical_device.max_usable_sample_count()),
+            logical_device,
+            physical_device,
+            surface,
+            command_pool,
+            vertex_buffer,
+            index_buffer,
+            swapchain_preferences,
+            stereo,
+            pipeline_variants,
+            frames_in_flight: self.config.frames_in_flight,
+            crash_diagnostics,
+            submit_tracer: None,
+            pending_trace: None,
+            frame_acquirer: FrameAcquirer::new(AcquirePolicy::default()),
+            fence_wait: Duration::ZERO,
+            frame_number: 0,
+            clear_color: self.clear_color,
+            wireframe: false,
+            needs_recreate: false,
+            lifecycle: Lifecycle::default(),
+            chain: None,
+            debug_layer,
+        };
+
+        renderer.recreate_chain()?;
+
+        Ok(renderer)
+    }
+}
+
+/// Owns the whole Vulkan chain of a window, see the [module](self).
+pub struct Renderer {
+    /// The GLFW window presented to, `None` for surfaces made from native windows.
+    window: Option<Window>,
+    /// The framebuffer size of a surface without a [Window], from the last [Renderer::resize].
+    framebuffer_size: (i32, i32),
+    logical_device: LogicalDevice,
+    physical_device: PhysicalDevice,
+    surface: Surface,
+    command_pool: CommandPool,
+    shader_cache: ShaderCache,
+    vertex_buffer: Buffer,
+    index_buffer: IndexBuffer,
+    swapchain_preferences: SwapchainPreferences,
+    stereo: bool,
+    samples: SampleCountFlags,
+    pipeline_variants: Vec<PipelineVariant>,
+    frames_in_flight: usize,
+    crash_diagnostics: Option<CrashDiagnostics>,
+    submit_tracer: Option<SubmitTracer>,
+    /// The command trace while there are no command buffers to record it, see
+    /// [Renderer::start_trace].
+    pending_trace: Option<CommandTrace>,
+    frame_acquirer: FrameAcquirer,
+    /// The time spent waiting for the GPU by the chains recreated so far.
+    fence_wait: Duration,
+    /// Counts the submitted frames, to label them.
+    frame_number: u64,
+    clear_color: Color,
+    wireframe: bool,
+    /// Recreate the chain before the next frame.
+    needs_recreate: bool,
+    lifecycle: Lifecycle,
+
+    /// Everything sized after the window, `None` while the window is minimized.
+    chain: Option<RenderChain>,
+
+    #[allow(dead_code)]
+    debug_layer: Option<DebugLayer>,
+}
+
+struct RenderChain {
+    swapchain: Swapchain,
+    present_transfer: Option<PresentTransfer>,
+    frame_sync: FrameSync,
+}
+
+impl Renderer {
+    pub fn builder() -> RendererBuilder {
+        RendererBuilder::default()
+    }
+
+    /// A renderer with the [RendererPreset::Minimal] preset.
+    pub fn minimal(window: &Window) -> Result<Self, Box<dyn Error>> {
+        Self::builder()
+            .preset(RendererPreset::Minimal)
+            .build(window)
+    }
+
+    /// A renderer with the [RendererPreset::Tutorial] preset.
+    pub fn tutorial(window: &Window) -> Result<Self, Box<dyn Error>> {
+        Self::builder()
+            .preset(RendererPreset::Tutorial)
+            .build(window)
+    }
+
+    /// A renderer with the [RendererPreset::Sandbox] preset.
+    pub fn sandbox(window: &Window) -> Result<Self, Box<dyn Error>> {
+        Self::builder()
+            .preset(RendererPreset::Sandbox)
+            .build(window)
+    }
+
+    /// Renders and presents one frame, recreating the swapchain first when it's out of date.
+    /// Does nothing while the window is minimized, see [Renderer::needs_wait].
+    ///
+    /// Vulkan failures are returned as the [vk::Result], so a lost device can be told apart.
+    pub fn draw_frame(&mut self) -> Result<(), Box<dyn Error>> {
+        let pipeline_index = self.wireframe as usize;
+
+        self.draw_frame_with(|command_buffers, index, image_index| {
+            command_buffers
+                .record(index, image_index, pipeline_index, 0, 0)
+                .map_err(Into::into)
+        })
+    }
+
+    /// Like [Renderer::draw_frame], recording a frame of a [CommandTrace] instead, see
+    /// [CommandBuffers::replay].
+    pub fn replay_frame(&mut self, frame: &[RecordedCommand]) -> Result<(), Box<dyn Error>> {
+        self.draw_frame_with(|command_buffers, index, image_index| {
+            command_buffers
+                .replay(index, image_index, frame)
+                .map_err(Into::into)
+        })
+    }
+
+    /// Acquires, records with `record` into the given command buffer for the given swapchain
+    /// image, submits and presents.
+    fn draw_frame_with(
+        &mut self,
+        record: impl FnOnce(&CommandBuffers, usize, usize) -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.lifecycle.state() == LifecycleState::SurfaceLost {
+            self.recreate_window_surface()?;
+        }
+
+        self.needs_recreate |= self.lifecycle.take_revalidation();
+
+        if !self.lifecycle.should_render() {
+            return Ok(());
+        }
+
+        if self.needs_recreate || self.chain.is_none() {
+            self.recreate_chain()?;
+        }
+
+        let Some(chain) = &mut self.chain else {
+            return Ok(());
+        };
+
+        let frame = match chain
+            .frame_sync
+            .begin_frame(&chain.swapchain, &mut self.frame_acquirer)
+        {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return Ok(()),
+            Err(AcquireError::Vulkan(vk::Result::ERROR_OUT_OF_DATE_KHR)) => {
+                self.needs_recreate = true;
+                return Ok(());
+            }
+            Err(AcquireError::Vulkan(vk::Result::ERROR_SURFACE_LOST_KHR)) => {
+                self.lifecycle.surface_lost();
+                return Ok(());
+            }
+            Err(AcquireError::Vulkan(e)) => return Err(e.into()),
+            Err(e) => return Err(e.into()),
+        };
+
+        if let Some(tracer) = &self.submit_tracer {
+            tracer.collect(frame.index);
+        }
+
+        record(
+            chain.frame_sync.command_buffers(),
+            frame.index,
+            frame.image_index as usize,
+        )?;
+
+        let wait_semaphores = [frame.image_available];
+        let signal_semaphores = [frame.render_finished];
+        let command_buffers = [frame.command_buffer];
+        let wait_stages = [PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+
+        let submit_infos = [SubmitInfo::default()
+            .wait_semaphores(&wait_semaphores)
+            .wait_dst_stage_mask(&wait_stages)
+            .command_buffers(&command_buffers)
+            .signal_semaphores(&signal_semaphores)];
+
+        let queue = *self.logical_device.queue();
+        let label = format!("frame {}", self.frame_number);
+        self.frame_number += 1;
+
+        if let Some(crash_diagnostics) = &self.crash_diagnostics {
+            crash_diagnostics.note_submit(label.as_str());
+        }
+
+        match &self.submit_tracer {
+            Some(tracer) => tracer.submit(queue, frame.index, &label, &submit_infos, frame.fence),
+            None => unsafe {
+                self.logical_device
+                    .device()
+                    .queue_submit(queue, &submit_infos, frame.fence)
+            },
+        }?;
+
+        // A render pass leaves the image ready to present.
+        let layout = chain
+            .frame_sync
+            .command_buffers()
+            .image_layout(frame.index, frame.image_index as usize)
+            .unwrap_or(vk::ImageLayout::PRESENT_SRC_KHR);
+        chain
+            .swapchain
+            .image(frame.image_index)
+            .submitted(frame.fence, layout);
+
+        let present_wait_semaphores = match &chain.present_transfer {
+            Some(present_transfer) => {
+                [present_transfer.submit(frame.image_index, frame.render_finished)?]
+            }
+            None => signal_semaphores,
+        };
+
+        let suboptimal = match chain
+            .swapchain
+            .queue_present(&present_wait_semaphores, &[frame.image_index])
+        {
+            Ok(suboptimal) => suboptimal,
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => true,
+            Err(vk::Result::ERROR_SURFACE_LOST_KHR) => {
+                self.lifecycle.surface_lost();
+                false
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        chain.frame_sync.end_frame();
+
+        self.needs_recreate |= frame.needs_recreate || suboptimal;
+
+        Ok(())
+    }
+
+    /// Notices resizes and minimizing, call it with every event of the window.
+    pub fn handle_glfw_event(&mut self, event: &WindowEvent) {
+        self.lifecycle.handle_glfw_event(event);
+    }
+
+    /// Notices resizes and minimizing forwarded to a render thread, see
+    /// [Window::forward_to_render_thread]. The renderer has to be made with
+    /// [RendererBuilder::build_with_surface], the window belongs to the other thread.
+    pub fn handle_render_event(&mut self, event: &RenderEvent) {
+        match *event {
+            RenderEvent::Resized { width, height } => self.resize(width, height),
+            event => {
+                self.lifecycle.handle_render_event(&event);
+            }
+        }
+    }
+
+    /// Recreates the swapchain at the window's framebuffer size before the next frame.
+    pub fn resized(&mut self) {
+        self.needs_recreate = true;
+    }
+
+    /// Sets the framebuffer size of a surface made with [RendererBuilder::build_with_surface],
+    /// recreating the swapchain at it before the next frame. A zero size pauses rendering until
+    /// the next resize. Renderers with a [Window] ask it instead.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.framebuffer_size = (width as i32, height as i32);
+        self.lifecycle.framebuffer_resized(width, height);
+        self.resized();
+    }
+
+    /// Stops drawing until [Renderer::resume], e.g. while the application is in the background.
+    pub fn suspend(&mut self) {
+        self.lifecycle.suspend();
+    }
+
+    /// Draws again after [Renderer::suspend], unless the window is still minimized.
+    pub fn resume(&mut self) {
+        self.lifecycle.resume();
+    }
+
+    /// Whether frames are drawn, and why not when they aren't.
+    pub fn lifecycle(&self) -> &Lifecycle {
+        &self.lifecycle
+    }
+
+    /// Replaces a surface made with [RendererBuilder::build_with_surface] after
+    /// [Renderer::draw_frame] failed with `VK_ERROR_SURFACE_LOST_KHR`. Renderers with a [Window]
+    /// recreate theirs on their own.
+    pub fn recreate_surface(&mut self, surface: vk::SurfaceKHR) -> Result<(), Box<dyn Error>> {
+        let surface = Surface::from_raw(self.physical_device.instance().clone(), surface);
+        self.replace_surface(surface)
+    }
+
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = color;
+
+        if let Some(chain) = &self.chain {
+            chain.frame_sync.command_buffers().set_clear_color(color);
+        }
+    }
+
+    /// Draws with the wireframe pipeline, when the config asked for it and the device supports
+    /// it. Returns whether wireframe is on.
+    pub fn set_wireframe(&mut self, wireframe: bool) -> bool {
+        self.wireframe = wireframe && self.pipeline_variants.len() > 1;
+        self.wireframe
+    }
+
+    /// Sets how many frames may be recorded ahead of the GPU, see
+    /// [FrameSync::set_frames_in_flight], and returns the count in effect.
+    pub fn set_frames_in_flight(&mut self, count: usize) -> Result<usize, Box<dyn Error>> {
+        self.frames_in_flight = match &mut self.chain {
+            Some(chain) => chain.frame_sync.set_frames_in_flight(count)?,
+            None => count.clamp(1, MAX_FRAMES_IN_FLIGHT),
+        };
+
+        Ok(self.frames_in_flight)
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames_in_flight
+    }
+
+    /// How acquiring swapchain images went so far.
+    pub fn acquire_stats(&self) -> AcquireStats {
+        self.frame_acquirer.stats()
+    }
+
+    /// The time spent waiting for the GPU before recording frames so far.
+    pub fn fence_wait(&self) -> Duration {
+        self.fence_wait
+            + self
+                .chain
+                .as_ref()
+                .map_or(Duration::ZERO, |chain| chain.frame_sync.fence_wait())
+    }
+
+    /// Records every command of the following frames into a [CommandTrace], until
+    /// [Renderer::take_trace]. Recreating the swapchain keeps the trace going.
+    pub fn start_trace(&mut self) {
+        match &self.chain {
+            Some(chain) => chain.frame_sync.command_buffers().start_trace(),
+            None => self.pending_trace = Some(CommandTrace::default()),
+        }
+    }
+
+    /// Stops tracing and returns the commands recorded since [Renderer::start_trace].
+    pub fn take_trace(&mut self) -> Option<CommandTrace> {
+        self.chain
+            .as_ref()
+            .and_then(|chain| chain.frame_sync.command_buffers().take_trace())
+            .or_else(|| self.pending_trace.take())
+    }
+
+    /// Timestamps every following submission, see [SubmitTracer].
+    pub fn enable_submit_trace(&mut self) -> Result<(), Box<dyn Error>> {
+        let tracer = SubmitTracer::new(self.logical_device.clone(), MAX_FRAMES_IN_FLIGHT)?;
+
+        if let Some(chain) = &self.chain {
+            chain
+                .frame_sync
+                .command_buffers()
+                .set_submit_tracer(Some(tracer.clone()));
+        }
+
+        self.submit_tracer = Some(tracer);
+
+        Ok(())
+    }
+
+    /// Waits for the traced submissions and returns them, if [Renderer::enable_submit_trace]
+    /// was called.
+    pub fn take_submit_trace(&self) -> Option<SubmitTrace> {
+        self.submit_tracer.as_ref().map(|tracer| {
+            tracer.collect_all();
+            tracer.take_trace()
+        })
+    }
+
+    /// Whether the surface has no area, usually because the window is minimized, or rendering is
+    /// suspended, so [Renderer::draw_frame] has nothing to draw. Waiting for events instead of
+    /// polling them keeps the loop from spinning until the window is restored.
+    pub fn needs_wait(&self) -> bool {
+        matches!(
+            self.lifecycle.state(),
+            LifecycleState::Minimized | LifecycleState::Suspended
+        )
+    }
+
+    /// The current swapchain, `None` while the window is minimized.
+    pub fn swapchain(&self) -> Option<&Swapchain> {
+        self.chain.as_ref().map(|chain| &chain.swapchain)
+    }
+
+    pub fn logical_device(&self) -> &LogicalDevice {
+        &self.logical_device
+    }
+
+    /// The diagnostics to [dump](CrashDiagnostics::dump) when a frame failed with a lost device,
+    /// if the config enabled them.
+    pub fn crash_diagnostics(&self) -> Option<&CrashDiagnostics> {
+        self.crash_diagnostics.as_ref()
+    }
+
+    pub fn wait_idle(&self) -> Result<(), Box<dyn Error>> {
+        Ok(self.logical_device.wait_idle()?)
+    }
+
+    /// Renders with every present mode the surface supports for `duration` each and reports how
+    /// they did, then goes back to the present mode used before. The caller's event loop is
+    /// blocked meanwhile, and a minimized window skips every mode.
+    pub fn benchmark_present_modes(
+        &mut self,
+        duration: Duration,
+    ) -> Result<PresentModeReport, Box<dyn Error>> {
+        let previous_present_mode = self.swapchain_preferences.present_mode;
+        let present_modes = self
+            .physical_device
+            .swapchain_support()
+            .present_modes
+            .clone();
+
+        let mut report = PresentModeReport::default();
+
+        for present_mode in present_modes {
+            self.swapchain_preferences.present_mode = Some(present_mode);
+
+            if let Err(e) = self.recreate_chain() {
+                self.swapchain_preferences.present_mode = previous_present_mode;
+                return Err(e);
+            }
+
+            if let Some(result) = self.benchmark_chain(duration)? {
+                report.results.push(result);
+            }
+        }
+
+        self.swapchain_preferences.present_mode = previous_present_mode;
+        self.recreate_chain()?;
+
+        Ok(report)
+    }
+
+    fn benchmark_chain(
+        &mut self,
+        duration: Duration,
+    ) -> Result<Option<PresentModeResult>, Box<dyn Error>> {
+        let Some(chain) = &self.chain else {
+            return Ok(None);
+        };
+
+        let present_mode = chain.swapchain.present_mode();
+        let queued_frames =
+            (self.frames_in_flight as u32).min(chain.swapchain.image_count().saturating_sub(1));
+
+        // Rendering a few frames first keeps swapchain creation out of the measurements.
+        for _ in 0..self.frames_in_flight {
+            self.draw_frame()?;
+        }
+
+        let mut frame_times = Vec::new();
+        let start = Instant::now();
+        let mut last_frame = start;
+
+        while last_frame - start < duration {
+            self.draw_frame()?;
+
+            let now = Instant::now();
+            frame_times.push(now - last_frame);
+            last_frame = now;
+        }
+
+        self.logical_device.wait_idle()?;
+
+        Ok(Some(PresentModeResult::new(
+            present_mode,
+            &frame_times,
+            queued_frames,
+        )))
+    }
+
+    /// Recreates the surface from the window after it was lost. Surfaces made outside of GLFW
+    /// are replaced by the caller with [Renderer::recreate_surface].
+    fn recreate_window_surface(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(window) = &self.window else {
+            return Err(vk::Result::ERROR_SURFACE_LOST_KHR.into());
+        };
+
+        let surface = Surface::new(self.physical_device.instance().clone(), window.clone())?;
+        self.replace_surface(surface)
+    }
+
+    fn replace_surface(&mut self, surface: Surface) -> Result<(), Box<dyn Error>> {
+        // The swapchain keeps the old surface alive until it's dropped.
+        self.drop_chain()?;
+        self.surface = surface;
+        self.lifecycle.surface_recreated();
+        self.needs_recreate = true;
+
+        Ok(())
+    }
+
+    /// Waits for the chain to be done with and drops it, keeping its stats and trace.
+    fn drop_chain(&mut self) -> Result<(), Box<dyn Error>> {
+        // Only the old chain can still be in use, nothing was submitted while minimized.
+        if let Some(chain) = &self.chain {
+            self.logical_device.wait_idle()?;
+
+            self.fence_wait += chain.frame_sync.fence_wait();
+
+            if let Some(trace) = chain.frame_sync.command_buffers().take_trace() {
+                self.pending_trace = Some(trace);
+            }
+        }
+
+        self.chain = None;
+
+        Ok(())
+    }
+
+    fn recreate_chain(&mut self) -> Result<(), Box<dyn Error>> {
+        // The old swapchain has to be gone before the surface accepts a new one.
+        self.drop_chain()?;
+        self.needs_recreate = false;
+
+        let framebuffer_size = match &self.window {
+            Some(window) => window.get_framebuffer_size(),
+            None => self.framebuffer_size,
+        };
+
+        let extent = self
+            .physical_device
+            .swapchain_extent(&self.surface, framebuffer_size)?;
+        self.lifecycle.set_extent(extent);
+
+        if extent == SwapchainExtent::NeedsWait {
+            return Ok(());
+        }
+
+        let swapchain = Swapchain::with_preferences(
+            self.physical_device.clone(),
+            self.logical_device.clone(),
+            self.surface.clone(),
+            framebuffer_size,
+            self.swapchain_preferences,
+        )?;
+        let present_transfer = PresentTransfer::new(swapchain.clone())?;
+        let image_views = ImageViews::new(&swapchain, self.logical_device.clone())?;
+        let render_pass = if self.stereo {
+            RenderPass::multiview(swapchain.clone(), self.samples)?
+        } else {
+            RenderPass::with_samples(swapchain.clone(), self.samples)?
+        };
+        let graphics_pipeline = GraphicsPipeline::with_variants(
+            render_pass.clone(),
+            &self.shader_cache,
+            &self.pipeline_variants,
+        )?;
+        let framebuffers = Framebuffers::new(render_pass, image_views)?;
+        let command_buffers = CommandBuffers::new(
+            self.command_pool.clone(),
+            framebuffers,
+            graphics_pipeline,
+            self.vertex_buffer.clone(),
+            Some(self.index_buffer.clone()),
+        )?;
+
+        command_buffers.set_clear_color(self.clear_color);
+        command_buffers.set_crash_diagnostics(self.crash_diagnostics.clone());
+        command_buffers.set_submit_tracer(self.submit_tracer.clone());
+
+        if let Some(trace) = self.pending_trace.take() {
+            command_buffers.resume_trace(trace);
+        }
+
+        let frame_sync = FrameSync::new(
+            self.logical_device.clone(),
+            command_buffers,
+            self.frames_in_flight,
+        )?;
+
+        self.chain = Some(RenderChain {
+            swapchain,
+            present_transfer,
+            frame_sync,
+        });
+
+        Ok(())
+    }
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        let _ = self.logical_device.wait_idle();
+    }
+}
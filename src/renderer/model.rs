@@ -0,0 +1,191 @@
+//! Models loaded from Wavefront OBJ files with the `tobj` feature, uploaded as an interleaved
+//! [PbrVertex] buffer and a 32-bit index buffer the [PbrPipeline](crate::renderer::pbr::PbrPipeline)
+//! draws.
+//!
+//! OBJ faces index positions, normals and UVs separately, so every distinct combination becomes
+//! one vertex, shared by all the faces using it. Faces with more than 3 corners are triangulated,
+//! and the objects of a file are merged into one mesh. Materials are ignored.
+//!
+//! `examples/pbr.rs` draws the model given on its command line in place of its spheres.
+
+use std::{error, fmt, path::Path};
+
+use ash::vk::BufferUsageFlags;
+
+use crate::renderer::{
+    buffer::{Buffer, IndexBuffer},
+    mesh::{Mesh, MeshBounds},
+    pbr::PbrMesh,
+    staging_uploader::{StagingUploader, UploadError},
+};
+
+#[cfg(feature = "tobj")]
+use {crate::renderer::pbr::PbrVertex, std::collections::HashMap};
+
+/// A mesh in device-local vertex and index buffers.
+#[derive(Clone)]
+pub struct Model {
+    vertex_buffer: Buffer,
+    index_buffer: IndexBuffer,
+    bounds: MeshBounds,
+}
+
+impl Model {
+    /// Loads an OBJ file and uploads it, see [load_obj].
+    pub fn from_file<P: AsRef<Path>>(
+        uploader: &StagingUploader,
+        path: P,
+    ) -> Result<Self, ModelError> {
+        let mesh = load_obj(path)?;
+
+        Self::new(uploader, &mesh).map_err(ModelError::from)
+    }
+
+    /// Uploads a mesh loaded or built on the CPU.
+    pub fn new(uploader: &StagingUploader, mesh: &Mesh) -> Result<Self, UploadError> {
+        Ok(Self {
+            vertex_buffer: uploader
+                .create_buffer(&mesh.vertices, BufferUsageFlags::VERTEX_BUFFER)?,
+            index_buffer: IndexBuffer::new(uploader, &mesh.indices)?,
+            bounds: mesh.bounds(),
+        })
+    }
+
+    /// The buffers to draw, valid as long as the model is.
+    pub fn pbr_mesh(&self) -> PbrMesh {
+        PbrMesh {
+            vertex_buffer: *self.vertex_buffer.buffer(),
+            index_buffer: *self.index_buffer.buffer().buffer(),
+            index_type: self.index_buffer.index_type(),
+            index_count: self.index_buffer.count(),
+        }
+    }
+
+    pub fn vertex_buffer(&self) -> &Buffer {
+        &self.vertex_buffer
+    }
+
+    pub fn index_buffer(&self) -> &IndexBuffer {
+        &self.index_buffer
+    }
+
+    /// The bounds in the model's own space.
+    pub fn bounds(&self) -> MeshBounds {
+        self.bounds
+    }
+}
+
+/// Loads every object of an OBJ file into one mesh, deduplicating its vertices. Normals are
+/// computed when the file has none, and tangents always, from the UVs. UVs are flipped
+/// vertically, OBJ having them start at the bottom of the texture.
+#[cfg(feature = "tobj")]
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<Mesh, ModelError> {
+    let (models, _materials) = tobj::load_obj(
+        path.as_ref(),
+        &tobj::LoadOptions {
+            triangulate: true,
+            ignore_points: true,
+            ignore_lines: true,
+            ..Default::default()
+        },
+    )?;
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut has_normals = true;
+
+    for model in &models {
+        let mesh = &model.mesh;
+        // The attribute indices of the corners of this object, to the merged vertex they became.
+        let mut unique = HashMap::new();
+
+        has_normals &= !mesh.normal_indices.is_empty() || mesh.indices.is_empty();
+
+        for (corner, &position) in mesh.indices.iter().enumerate() {
+            let normal = mesh.normal_indices.get(corner).copied();
+            let uv = mesh.texcoord_indices.get(corner).copied();
+
+            let index = *unique.entry((position, normal, uv)).or_insert_with(|| {
+                vertices.push(obj_vertex(mesh, position, normal, uv));
+                vertices.len() as u32 - 1
+            });
+
+            indices.push(index);
+        }
+    }
+
+    if indices.is_empty() {
+        return Err(ModelError::Empty);
+    }
+
+    let mut mesh = Mesh::new(vertices, indices);
+
+    if !has_normals {
+        mesh.compute_normals();
+    }
+
+    mesh.compute_tangents();
+
+    Ok(mesh)
+}
+
+#[cfg(not(feature = "tobj"))]
+pub fn load_obj<P: AsRef<Path>>(_path: P) -> Result<Mesh, ModelError> {
+    Err(ModelError::Unsupported)
+}
+
+#[cfg(feature = "tobj")]
+fn obj_vertex(mesh: &tobj::Mesh, position: u32, normal: Option<u32>, uv: Option<u32>) -> PbrVertex {
+    let vec3 = |values: &[f32], index: u32| {
+        let index = 3 * index as usize;
+        [values[index], values[index + 1], values[index + 2]]
+    };
+
+    PbrVertex {
+        position: vec3(&mesh.positions, position),
+        normal: normal.map_or([0.0; 3], |normal| vec3(&mesh.normals, normal)),
+        tangent: [1.0, 0.0, 0.0, 1.0],
+        uv: uv.map_or([0.0; 2], |uv| {
+            let index = 2 * uv as usize;
+            [mesh.texcoords[index], 1.0 - mesh.texcoords[index + 1]]
+        }),
+    }
+}
+
+#[derive(Debug)]
+pub enum ModelError {
+    #[cfg(feature = "tobj")]
+    Load(tobj::LoadError),
+    /// The file has no faces.
+    Empty,
+    Upload(UploadError),
+    /// Built without the `tobj` feature.
+    Unsupported,
+}
+
+#[cfg(feature = "tobj")]
+impl From<tobj::LoadError> for ModelError {
+    fn from(value: tobj::LoadError) -> Self {
+        Self::Load(value)
+    }
+}
+
+impl From<UploadError> for ModelError {
+    fn from(value: UploadError) -> Self {
+        Self::Upload(value)
+    }
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "tobj")]
+            Self::Load(e) => write!(f, "failed to load OBJ file: {}", e),
+            Self::Empty => write!(f, "the model has no faces"),
+            Self::Upload(e) => e.fmt(f),
+            Self::Unsupported => write!(f, "loading models needs the tobj feature"),
+        }
+    }
+}
+
+impl error::Error for ModelError {}
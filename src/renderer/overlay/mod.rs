@@ -0,0 +1,384 @@
+//! A debug overlay of graphs and bars drawn over the frame, fed from the stats the renderer
+//! already keeps, for quick visual feedback while experimenting.
+//!
+//! Widgets add colored rectangles in pixels to an [OverlayBatch], which [Overlay] draws with
+//! `shaders/overlay.vert` and `shaders/overlay.frag` at the end of the render pass. Without the
+//! `overlay` feature the widgets are still built, but [Overlay] fails to create.
+//!
+//! `examples/terrain.rs` graphs its frame times, memory use and chunk count with the `overlay`
+//! feature.
+
+use std::time::Duration;
+
+use ash::vk;
+
+#[cfg(not(feature = "overlay"))]
+use crate::{
+    api2::ErrorCtx,
+    renderer::{render_pass::RenderPass, shader_cache::ShaderCache},
+};
+use crate::{
+    api2::FrameClock,
+    renderer::{logical_device::LogicalDevice, resource_stats::ResourceStats},
+    types::Color,
+};
+#[cfg(feature = "overlay")]
+pub use renderer::Overlay;
+
+#[cfg(feature = "overlay")]
+mod renderer;
+
+const BACKGROUND: Color = Color::linear(0.0, 0.0, 0.0, 0.6);
+const TRACK: Color = Color::linear(0.1, 0.1, 0.1, 0.8);
+const GOOD: Color = Color::linear(0.1, 0.7, 0.1, 1.0);
+const WARNING: Color = Color::linear(0.9, 0.6, 0.0, 1.0);
+const BAD: Color = Color::linear(0.9, 0.1, 0.1, 1.0);
+const MARKER: Color = Color::linear(1.0, 1.0, 1.0, 0.8);
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct OverlayVertex {
+    /// In pixels from the top-left corner.
+    pub position: [f32; 2],
+    /// Linear color with straight alpha.
+    pub color: [f32; 4],
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct OverlayRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl OverlayRect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The rect shrunk by `amount` on every side.
+    pub fn inset(&self, amount: f32) -> Self {
+        Self::new(
+            self.x + amount,
+            self.y + amount,
+            (self.width - 2.0 * amount).max(0.0),
+            (self.height - 2.0 * amount).max(0.0),
+        )
+    }
+}
+
+/// The rectangles of one frame's overlay, drawn in the order they were added.
+#[derive(Debug, Default, Clone)]
+pub struct OverlayBatch {
+    vertices: Vec<OverlayVertex>,
+}
+
+impl OverlayBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn vertices(&self) -> &[OverlayVertex] {
+        &self.vertices
+    }
+
+    pub fn rect(&mut self, rect: OverlayRect, color: Color) {
+        if rect.width <= 0.0 || rect.height <= 0.0 {
+            return;
+        }
+
+        let color = color.to_linear_array();
+        let (left, top) = (rect.x, rect.y);
+        let (right, bottom) = (rect.x + rect.width, rect.y + rect.height);
+
+        self.vertices.extend(
+            [
+                [left, top],
+                [left, bottom],
+                [right, top],
+                [right, top],
+                [left, bottom],
+                [right, bottom],
+            ]
+            .map(|position| OverlayVertex { position, color }),
+        );
+    }
+
+    /// A horizontal bar filled to `fraction` of `rect`, on a dark track.
+    pub fn bar(&mut self, rect: OverlayRect, fraction: f32, color: Color) {
+        self.rect(rect, TRACK);
+        self.rect(
+            OverlayRect {
+                width: rect.width * fraction.clamp(0.0, 1.0),
+                ..rect
+            },
+            color,
+        );
+    }
+
+    /// Hands out rects stacked downwards from `(x, y)`, all `width` wide.
+    pub fn column(x: f32, y: f32, width: f32) -> OverlayColumn {
+        OverlayColumn {
+            x,
+            y,
+            width,
+            spacing: 4.0,
+        }
+    }
+}
+
+/// Lays widgets out one below the other, see [OverlayBatch::column].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OverlayColumn {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub spacing: f32,
+}
+
+impl OverlayColumn {
+    pub fn next(&mut self, height: f32) -> OverlayRect {
+        let rect = OverlayRect::new(self.x, self.y, self.width, height);
+        self.y += height + self.spacing;
+        rect
+    }
+}
+
+/// A scrolling graph of recent frame times, newest on the right. Frames within `target` are
+/// green, up to twice as long orange, and longer ones red.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameTimeGraph {
+    pub samples: Vec<Duration>,
+    pub target: Duration,
+    /// The frame time at the top of the graph.
+    pub max: Duration,
+}
+
+impl FrameTimeGraph {
+    pub fn from_clock(clock: &FrameClock) -> Self {
+        Self {
+            samples: clock.samples.iter().copied().collect(),
+            target: Duration::from_micros(16_667),
+            max: Duration::from_micros(50_000),
+        }
+    }
+
+    pub fn draw(&self, batch: &mut OverlayBatch, rect: OverlayRect) {
+        batch.rect(rect, BACKGROUND);
+
+        if self.samples.is_empty() {
+            return;
+        }
+
+        let max = self.max.as_secs_f32().max(f32::EPSILON);
+        let inner = rect.inset(2.0);
+        let bar_width = inner.width / self.samples.len() as f32;
+
+        for (i, sample) in self.samples.iter().enumerate() {
+            let height = inner.height * (sample.as_secs_f32() / max).min(1.0);
+            let color = if *sample <= self.target {
+                GOOD
+            } else if *sample <= self.target * 2 {
+                WARNING
+            } else {
+                BAD
+            };
+
+            batch.rect(
+                OverlayRect::new(
+                    inner.x + i as f32 * bar_width,
+                    inner.y + inner.height - height,
+                    (bar_width - 1.0).max(1.0),
+                    height,
+                ),
+                color,
+            );
+        }
+
+        let target_y = inner.y + inner.height * (1.0 - (self.target.as_secs_f32() / max).min(1.0));
+        batch.rect(
+            OverlayRect::new(inner.x, target_y, inner.width, 1.0),
+            MARKER,
+        );
+    }
+}
+
+/// One bar per GPU pass, scaled to a frame `budget`.
+///
+/// There's no GPU timestamp profiler yet, the durations come from whatever measured them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassDurationBars {
+    pub passes: Vec<Duration>,
+    pub budget: Duration,
+}
+
+impl PassDurationBars {
+    pub fn new(passes: impl IntoIterator<Item = Duration>, budget: Duration) -> Self {
+        Self {
+            passes: passes.into_iter().collect(),
+            budget,
+        }
+    }
+
+    pub fn height(&self, bar_height: f32) -> f32 {
+        self.passes.len() as f32 * (bar_height + 2.0) + 2.0
+    }
+
+    pub fn draw(&self, batch: &mut OverlayBatch, rect: OverlayRect) {
+        batch.rect(rect, BACKGROUND);
+
+        let inner = rect.inset(2.0);
+        let bar_height = (inner.height / self.passes.len().max(1) as f32 - 2.0).max(1.0);
+        let budget = self.budget.as_secs_f32().max(f32::EPSILON);
+
+        for (i, duration) in self.passes.iter().enumerate() {
+            let fraction = duration.as_secs_f32() / budget;
+
+            batch.bar(
+                OverlayRect::new(
+                    inner.x,
+                    inner.y + i as f32 * (bar_height + 2.0),
+                    inner.width,
+                    bar_height,
+                ),
+                fraction,
+                if fraction <= 0.5 { GOOD } else { WARNING },
+            );
+        }
+    }
+}
+
+/// One bar per memory heap, its length the heap's size.
+///
+/// The resource tracker doesn't know which heap an allocation came from, so the tracked buffer
+/// and image bytes fill every device-local heap, buffers first.
+#[derive(Debug, Clone)]
+pub struct MemoryUsageBars {
+    pub heaps: Vec<vk::MemoryHeap>,
+    pub stats: ResourceStats,
+}
+
+impl MemoryUsageBars {
+    pub fn from_device(logical_device: &LogicalDevice) -> Self {
+        let properties = logical_device.physical_device().memory_properties();
+
+        Self {
+            heaps: properties.memory_heaps[..properties.memory_heap_count as usize].to_vec(),
+            stats: logical_device.resource_stats(),
+        }
+    }
+
+    pub fn draw(&self, batch: &mut OverlayBatch, rect: OverlayRect) {
+        batch.rect(rect, BACKGROUND);
+
+        let inner = rect.inset(2.0);
+        let bar_height = (inner.height / self.heaps.len().max(1) as f32 - 2.0).max(1.0);
+
+        for (i, heap) in self.heaps.iter().enumerate() {
+            let bar = OverlayRect::new(
+                inner.x,
+                inner.y + i as f32 * (bar_height + 2.0),
+                inner.width,
+                bar_height,
+            );
+
+            batch.rect(bar, TRACK);
+
+            if !heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL) || heap.size == 0 {
+                continue;
+            }
+
+            let buffers = self.stats.buffers.bytes as f32 / heap.size as f32;
+            let images = self.stats.images.bytes as f32 / heap.size as f32;
+
+            batch.rect(
+                OverlayRect {
+                    width: bar.width * buffers.min(1.0),
+                    ..bar
+                },
+                GOOD,
+            );
+            batch.rect(
+                OverlayRect {
+                    x: bar.x + bar.width * buffers.min(1.0),
+                    width: bar.width * images.min(1.0 - buffers.min(1.0)),
+                    ..bar
+                },
+                WARNING,
+            );
+        }
+    }
+}
+
+/// Bars for counters against their expected maximum, like draws or primitives per frame or
+/// [crate::renderer::terrain::Terrain::chunks_drawn] against the chunk count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CounterBars {
+    /// Each counter's value and the value filling its bar.
+    pub counters: Vec<(u64, u64)>,
+}
+
+impl CounterBars {
+    pub fn new(counters: impl IntoIterator<Item = (u64, u64)>) -> Self {
+        Self {
+            counters: counters.into_iter().collect(),
+        }
+    }
+
+    pub fn draw(&self, batch: &mut OverlayBatch, rect: OverlayRect) {
+        batch.rect(rect, BACKGROUND);
+
+        let inner = rect.inset(2.0);
+        let bar_height = (inner.height / self.counters.len().max(1) as f32 - 2.0).max(1.0);
+
+        for (i, (value, max)) in self.counters.iter().enumerate() {
+            let fraction = *value as f32 / (*max).max(1) as f32;
+
+            batch.bar(
+                OverlayRect::new(
+                    inner.x,
+                    inner.y + i as f32 * (bar_height + 2.0),
+                    inner.width,
+                    bar_height,
+                ),
+                fraction,
+                if fraction <= 1.0 { GOOD } else { BAD },
+            );
+        }
+    }
+}
+
+/// Stands in for the overlay renderer without the `overlay` feature.
+#[cfg(not(feature = "overlay"))]
+#[derive(Clone)]
+pub struct Overlay(());
+
+#[cfg(not(feature = "overlay"))]
+impl Overlay {
+    /// Always fails with `ERROR_FEATURE_NOT_PRESENT`.
+    pub fn new(
+        _render_pass: RenderPass,
+        _shader_cache: &ShaderCache,
+        _vertex_shader: &[u32],
+        _fragment_shader: &[u32],
+        _max_rects: u32,
+    ) -> Result<Self, ErrorCtx> {
+        Err(
+            ErrorCtx::new("creating overlay", vk::Result::ERROR_FEATURE_NOT_PRESENT)
+                .details("built without the overlay feature"),
+        )
+    }
+
+    pub fn record(&self, _command_buffer: vk::CommandBuffer, _frame: usize, _batch: &OverlayBatch) {
+    }
+}
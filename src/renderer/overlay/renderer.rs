@@ -0,0 +1,372 @@
+//! The pipeline drawing [OverlayBatch]es, only built with the `overlay` feature.
+
+use std::{mem, rc::Rc};
+
+use ash::vk::{
+    self, BufferCreateInfo, BufferUsageFlags, CommandBuffer, CullModeFlags, DeviceSize,
+    DynamicState, Format, FrontFace, GraphicsPipelineCreateInfo, Handle, MemoryAllocateInfo,
+    MemoryMapFlags, MemoryPropertyFlags, Offset2D, PipelineBindPoint, PipelineCache,
+    PipelineColorBlendStateCreateInfo, PipelineDynamicStateCreateInfo,
+    PipelineInputAssemblyStateCreateInfo, PipelineLayoutCreateInfo,
+    PipelineMultisampleStateCreateInfo, PipelineRasterizationStateCreateInfo,
+    PipelineShaderStageCreateInfo, PipelineVertexInputStateCreateInfo,
+    PipelineViewportStateCreateInfo, PolygonMode, PrimitiveTopology, PushConstantRange, Rect2D,
+    ShaderStageFlags, SharingMode, VertexInputAttributeDescription, VertexInputBindingDescription,
+    VertexInputRate, WHOLE_SIZE,
+};
+
+use super::{OverlayBatch, OverlayVertex};
+use crate::{
+    api2::{host_allocation_callbacks, ClipSpaceY, ErrorCtx, ResultExt},
+    renderer::{
+        blend_mode::BlendMode, logical_device::LogicalDevice, pipeline_stats,
+        render_pass::RenderPass, resource_stats::ResourceKind, shader_cache::ShaderCache,
+        teardown_trace, MAX_FRAMES_IN_FLIGHT,
+    },
+    types::Pod,
+};
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+struct OverlayPushConstants {
+    pixel_to_clip: [f32; 4],
+}
+
+unsafe impl Pod for OverlayPushConstants {}
+
+/// Draws [OverlayBatch]es over subpass 0 of a render pass.
+#[derive(Clone)]
+pub struct Overlay(Rc<InnerOverlay>);
+
+impl Overlay {
+    /// Creates the overlay from the SPIR-V of `shaders/overlay.vert` and `shaders/overlay.frag`,
+    /// drawing up to `max_rects` rectangles a frame.
+    pub fn new(
+        render_pass: RenderPass,
+        shader_cache: &ShaderCache,
+        vertex_shader: &[u32],
+        fragment_shader: &[u32],
+        max_rects: u32,
+    ) -> Result<Self, ErrorCtx> {
+        let logical_device = render_pass.swapchain().device().clone();
+        let capacity = max_rects.max(1) * 6;
+        let frame_size = capacity as DeviceSize * mem::size_of::<OverlayVertex>() as DeviceSize;
+
+        let push_constant_ranges = [PushConstantRange::default()
+            .stage_flags(ShaderStageFlags::VERTEX)
+            .size(mem::size_of::<OverlayPushConstants>() as u32)];
+
+        let pipeline_layout = unsafe {
+            logical_device.device().create_pipeline_layout(
+                &PipelineLayoutCreateInfo::default().push_constant_ranges(&push_constant_ranges),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating overlay pipeline layout")?;
+
+        let mut overlay = InnerOverlay {
+            pipeline_layout,
+            pipeline: vk::Pipeline::null(),
+            buffers: Vec::new(),
+            capacity,
+            clip_space_y: logical_device.clip_space_y(),
+            logical_device: logical_device.clone(),
+            render_pass: render_pass.clone(),
+        };
+
+        overlay.pipeline = create_pipeline(
+            &render_pass,
+            shader_cache,
+            pipeline_layout,
+            vertex_shader,
+            fragment_shader,
+        )?;
+
+        for _ in 0..MAX_FRAMES_IN_FLIGHT {
+            let buffer = OverlayBuffer::new(&logical_device, frame_size)?;
+
+            let mapped = unsafe {
+                logical_device.device().map_memory(
+                    buffer.memory,
+                    0,
+                    WHOLE_SIZE,
+                    MemoryMapFlags::empty(),
+                )
+            }
+            .context("mapping overlay vertex memory")?;
+
+            overlay
+                .buffers
+                .push((buffer, mapped.cast::<OverlayVertex>()));
+        }
+
+        Ok(Self(Rc::new(overlay)))
+    }
+
+    /// Draws `batch` over the whole render area, inside the render pass. Sets its own viewport
+    /// and scissor. Rectangles past the capacity are dropped, and the frame's previous
+    /// submission must have finished.
+    pub fn record(&self, command_buffer: CommandBuffer, frame: usize, batch: &OverlayBatch) {
+        let vertices = batch.vertices();
+        let count = vertices.len().min(self.0.capacity as usize) / 6 * 6;
+
+        if count == 0 {
+            return;
+        }
+
+        let (buffer, mapped) = &self.0.buffers[frame];
+        unsafe { mapped.copy_from_nonoverlapping(vertices.as_ptr(), count) };
+
+        let extent = self.0.render_pass.swapchain().extent();
+        let (width, height) = (extent.width.max(1) as f32, extent.height.max(1) as f32);
+
+        // Pixels count down from the top, so with Y pointing up in clip space they're negated.
+        let y_sign = match self.0.clip_space_y {
+            ClipSpaceY::Down => 1.0,
+            ClipSpaceY::Up => -1.0,
+        };
+
+        let push_constants = OverlayPushConstants {
+            pixel_to_clip: [2.0 / width, y_sign * 2.0 / height, -1.0, -y_sign],
+        };
+
+        let device = self.0.logical_device.device();
+
+        unsafe {
+            device.cmd_bind_pipeline(command_buffer, PipelineBindPoint::GRAPHICS, self.0.pipeline);
+            device.cmd_set_viewport(command_buffer, 0, &[self.0.clip_space_y.viewport(extent)]);
+            device.cmd_set_scissor(
+                command_buffer,
+                0,
+                &[Rect2D::default().offset(Offset2D::default()).extent(extent)],
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.0.pipeline_layout,
+                ShaderStageFlags::VERTEX,
+                0,
+                push_constants.bytes_of(),
+            );
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[buffer.buffer], &[0]);
+            device.cmd_draw(command_buffer, count as u32, 1, 0, 0);
+        }
+    }
+}
+
+struct InnerOverlay {
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    buffers: Vec<(OverlayBuffer, *mut OverlayVertex)>,
+    capacity: u32,
+    clip_space_y: ClipSpaceY,
+
+    logical_device: LogicalDevice,
+    render_pass: RenderPass,
+}
+
+impl Drop for InnerOverlay {
+    fn drop(&mut self) {
+        teardown_trace::record("Overlay", [self.pipeline.as_raw()]);
+
+        let device = self.logical_device.device();
+
+        unsafe {
+            for (buffer, _) in &self.buffers {
+                device.unmap_memory(buffer.memory);
+            }
+
+            if self.pipeline != vk::Pipeline::null() {
+                device.destroy_pipeline(self.pipeline, host_allocation_callbacks());
+                self.logical_device
+                    .resource_tracker()
+                    .untrack(ResourceKind::Pipeline, 1, 0);
+            }
+
+            device.destroy_pipeline_layout(self.pipeline_layout, host_allocation_callbacks());
+        }
+    }
+}
+
+fn create_pipeline(
+    render_pass: &RenderPass,
+    shader_cache: &ShaderCache,
+    pipeline_layout: vk::PipelineLayout,
+    vertex_shader: &[u32],
+    fragment_shader: &[u32],
+) -> Result<vk::Pipeline, ErrorCtx> {
+    let logical_device = render_pass.swapchain().device();
+
+    let shader_modules = [
+        shader_cache
+            .get_or_create(vertex_shader)
+            .context("creating overlay vertex shader module")?,
+        shader_cache
+            .get_or_create(fragment_shader)
+            .context("creating overlay fragment shader module")?,
+    ];
+
+    let stages = [
+        PipelineShaderStageCreateInfo::default()
+            .stage(ShaderStageFlags::VERTEX)
+            .module(*shader_modules[0].shader_module())
+            .name(c"main"),
+        PipelineShaderStageCreateInfo::default()
+            .stage(ShaderStageFlags::FRAGMENT)
+            .module(*shader_modules[1].shader_module())
+            .name(c"main"),
+    ];
+
+    let bindings = [VertexInputBindingDescription::default()
+        .binding(0)
+        .stride(mem::size_of::<OverlayVertex>() as u32)
+        .input_rate(VertexInputRate::VERTEX)];
+    let attributes = [
+        VertexInputAttributeDescription::default()
+            .binding(0)
+            .location(0)
+            .format(Format::R32G32_SFLOAT)
+            .offset(mem::offset_of!(OverlayVertex, position) as u32),
+        VertexInputAttributeDescription::default()
+            .binding(0)
+            .location(1)
+            .format(Format::R32G32B32A32_SFLOAT)
+            .offset(mem::offset_of!(OverlayVertex, color) as u32),
+    ];
+    let vertex_input_info = PipelineVertexInputStateCreateInfo::default()
+        .vertex_binding_descriptions(&bindings)
+        .vertex_attribute_descriptions(&attributes);
+
+    let input_assembly_info =
+        PipelineInputAssemblyStateCreateInfo::default().topology(PrimitiveTopology::TRIANGLE_LIST);
+
+    let dynamic_states = [DynamicState::VIEWPORT, DynamicState::SCISSOR];
+    let dynamic_state_info =
+        PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+    let viewport_info = PipelineViewportStateCreateInfo::default()
+        .viewport_count(1)
+        .scissor_count(1);
+
+    let rasterizer_info = PipelineRasterizationStateCreateInfo::default()
+        .polygon_mode(PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(CullModeFlags::NONE)
+        .front_face(FrontFace::COUNTER_CLOCKWISE);
+
+    let multisample_info =
+        PipelineMultisampleStateCreateInfo::default().rasterization_samples(render_pass.samples());
+
+    // No depth state, the overlay is drawn over everything.
+    let color_blend_attachments = [BlendMode::AlphaBlend.attachment_state()];
+    let color_blend_info =
+        PipelineColorBlendStateCreateInfo::default().attachments(&color_blend_attachments);
+
+    let create_info = GraphicsPipelineCreateInfo::default()
+        .stages(&stages)
+        .vertex_input_state(&vertex_input_info)
+        .input_assembly_state(&input_assembly_info)
+        .viewport_state(&viewport_info)
+        .rasterization_state(&rasterizer_info)
+        .multisample_state(&multisample_info)
+        .color_blend_state(&color_blend_info)
+        .dynamic_state(&dynamic_state_info)
+        .layout(pipeline_layout)
+        .render_pass(*render_pass.render_pass());
+
+    let pipeline = unsafe {
+        pipeline_stats::create_graphics_pipelines(
+            logical_device,
+            PipelineCache::null(),
+            &[create_info],
+        )
+    }
+    .context("creating overlay pipeline")?[0];
+
+    logical_device
+        .resource_tracker()
+        .track(ResourceKind::Pipeline, 1, 0);
+
+    Ok(pipeline)
+}
+
+/// A host-visible vertex buffer with its own memory allocation.
+struct OverlayBuffer {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    size: DeviceSize,
+
+    logical_device: LogicalDevice,
+}
+
+impl OverlayBuffer {
+    fn new(logical_device: &LogicalDevice, size: DeviceSize) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+
+        let buffer = unsafe {
+            device.create_buffer(
+                &BufferCreateInfo::default()
+                    .size(size)
+                    .usage(BufferUsageFlags::VERTEX_BUFFER)
+                    .sharing_mode(SharingMode::EXCLUSIVE),
+                host_allocation_callbacks(),
+            )
+        }
+        .with_context("creating overlay vertex buffer", || {
+            format!("size={}", size)
+        })?;
+
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+
+        let mut overlay_buffer = Self {
+            buffer,
+            memory: vk::DeviceMemory::null(),
+            size: requirements.size,
+            logical_device: logical_device.clone(),
+        };
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::Buffer, 1, requirements.size);
+
+        let memory_type_index = logical_device
+            .physical_device()
+            .find_memory_type(
+                requirements.memory_type_bits,
+                MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+            )
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            .with_context("finding overlay vertex memory", || {
+                format!("memory_type_bits={:#x}", requirements.memory_type_bits)
+            })?;
+
+        overlay_buffer.memory = unsafe {
+            device.allocate_memory(
+                &MemoryAllocateInfo::default()
+                    .allocation_size(requirements.size)
+                    .memory_type_index(memory_type_index),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("allocating overlay vertex memory")?;
+
+        unsafe { device.bind_buffer_memory(buffer, overlay_buffer.memory, 0) }
+            .context("binding overlay vertex memory")?;
+
+        Ok(overlay_buffer)
+    }
+}
+
+impl Drop for OverlayBuffer {
+    fn drop(&mut self) {
+        teardown_trace::record("OverlayBuffer", [self.buffer.as_raw()]);
+
+        unsafe {
+            let device = self.logical_device.device();
+            device.destroy_buffer(self.buffer, host_allocation_callbacks());
+            device.free_memory(self.memory, host_allocation_callbacks());
+        }
+
+        self.logical_device
+            .resource_tracker()
+            .untrack(ResourceKind::Buffer, 1, self.size);
+    }
+}
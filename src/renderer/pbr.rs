@@ -0,0 +1,903 @@
+//! Forward physically based shading of the glTF metallic-roughness model, lit by the scene's
+//! [LightBuffers] and the image-based lighting from [IblTextures].
+//!
+//! `examples/pbr.rs` draws a grid of spheres going through the metallic and roughness range.
+
+use std::{
+    cell::{Cell, RefCell},
+    mem,
+    rc::Rc,
+};
+
+use ash::vk::{
+    self, BufferCreateInfo, BufferUsageFlags, CommandBuffer, CullModeFlags, DescriptorBufferInfo,
+    DescriptorImageInfo, DescriptorPoolCreateInfo, DescriptorPoolSize, DescriptorSetAllocateInfo,
+    DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo, DescriptorType, DeviceSize,
+    DynamicState, Format, FrontFace, GraphicsPipelineCreateInfo, Handle, IndexType,
+    MemoryAllocateInfo, MemoryMapFlags, MemoryPropertyFlags, PipelineBindPoint, PipelineCache,
+    PipelineColorBlendStateCreateInfo, PipelineDynamicStateCreateInfo,
+    PipelineInputAssemblyStateCreateInfo, PipelineLayoutCreateInfo,
+    PipelineMultisampleStateCreateInfo, PipelineRasterizationStateCreateInfo,
+    PipelineShaderStageCreateInfo, PipelineVertexInputStateCreateInfo,
+    PipelineViewportStateCreateInfo, PolygonMode, PrimitiveTopology, PushConstantRange,
+    ShaderStageFlags, SharingMode, VertexInputAttributeDescription, VertexInputBindingDescription,
+    VertexInputRate, WriteDescriptorSet, WHOLE_SIZE,
+};
+
+use crate::{
+    api2::{align_up, host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        blend_mode::BlendMode,
+        ibl::IblTextures,
+        lights::{LightBuffers, LightClusters},
+        logical_device::LogicalDevice,
+        pipeline_stats,
+        render_pass::RenderPass,
+        resource_stats::ResourceKind,
+        shader_cache::ShaderCache,
+        skinning::{JointPalettes, SkinnedVertex},
+        teardown_trace, MAX_FRAMES_IN_FLIGHT,
+    },
+    types::Pod,
+};
+
+/// The vertex layout `shaders/pbr.vert` reads.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct PbrVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    /// xyz is the tangent, w the handedness of the bitangent, like glTF.
+    pub tangent: [f32; 4],
+    pub uv: [f32; 2],
+}
+
+unsafe impl Pod for PbrVertex {}
+
+impl PbrVertex {
+    pub fn binding_description() -> VertexInputBindingDescription {
+        VertexInputBindingDescription::default()
+            .binding(0)
+            .stride(mem::size_of::<Self>() as u32)
+            .input_rate(VertexInputRate::VERTEX)
+    }
+
+    pub fn attribute_descriptions() -> [VertexInputAttributeDescription; 4] {
+        let attribute = |location, format, offset| {
+            VertexInputAttributeDescription::default()
+                .binding(0)
+                .location(location)
+                .format(format)
+                .offset(offset as u32)
+        };
+
+        [
+            attribute(0, Format::R32G32B32_SFLOAT, mem::offset_of!(Self, position)),
+            attribute(1, Format::R32G32B32_SFLOAT, mem::offset_of!(Self, normal)),
+            attribute(
+                2,
+                Format::R32G32B32A32_SFLOAT,
+                mem::offset_of!(Self, tangent),
+            ),
+            attribute(3, Format::R32G32_SFLOAT, mem::offset_of!(Self, uv)),
+        ]
+    }
+}
+
+/// The per-frame uniform buffer of the PBR shaders, mirrors `pbr_frame.glsl`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PbrFrameUniforms {
+    pub view_projection: [[f32; 4]; 4],
+    /// The world to view space transform, used to find the light cluster of a fragment.
+    pub view: [[f32; 4]; 4],
+    pub camera_position: [f32; 4],
+    /// The near and far planes the light clusters span, then the viewport width and height.
+    pub cluster_params: [f32; 4],
+    prefiltered_mip_levels: f32,
+    _padding: [u32; 3],
+}
+
+impl Default for PbrFrameUniforms {
+    fn default() -> Self {
+        let identity = [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+
+        Self {
+            view_projection: identity,
+            view: identity,
+            camera_position: [0.0; 4],
+            cluster_params: [0.1, 100.0, 1.0, 1.0],
+            prefiltered_mip_levels: 1.0,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// Constant factors the material's textures are multiplied with.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MaterialFactors {
+    /// Linear RGBA.
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    /// How much of the occlusion texture applies, from none at 0 to all at 1.
+    pub occlusion_strength: f32,
+    /// Scales the X and Y of the sampled normals.
+    pub normal_scale: f32,
+}
+
+impl Default for MaterialFactors {
+    fn default() -> Self {
+        Self {
+            base_color: [1.0; 4],
+            metallic: 1.0,
+            roughness: 1.0,
+            occlusion_strength: 1.0,
+            normal_scale: 1.0,
+        }
+    }
+}
+
+/// The push constants of the PBR shaders, mirrors `PbrDraw` in `pbr_frame.glsl`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct PbrPushConstants {
+    /// Has to scale uniformly, the shaders transform normals with it too.
+    pub model: [[f32; 4]; 4],
+    pub factors: MaterialFactors,
+    /// Where the skeleton's matrices start in the [JointPalettes], for skinned draws.
+    pub first_joint: u32,
+}
+
+unsafe impl Pod for PbrPushConstants {}
+
+/// The textures of a material, in the `SHADER_READ_ONLY_OPTIMAL` layout.
+#[derive(Debug, Copy, Clone)]
+pub struct PbrTextures {
+    /// sRGB base color with alpha.
+    pub albedo: DescriptorImageInfo,
+    /// Tangent space normals.
+    pub normal: DescriptorImageInfo,
+    /// Roughness in green and metallic in blue, like glTF.
+    pub metallic_roughness: DescriptorImageInfo,
+    /// Ambient occlusion in red.
+    pub occlusion: DescriptorImageInfo,
+}
+
+/// A material created by [PbrPipeline::create_material], valid as long as the pipeline is.
+#[derive(Debug, Copy, Clone)]
+pub struct PbrMaterial {
+    descriptor_set: vk::DescriptorSet,
+    pub factors: MaterialFactors,
+}
+
+/// Indexed geometry made of [PbrVertex]es.
+#[derive(Debug, Copy, Clone)]
+pub struct PbrMesh {
+    pub vertex_buffer: vk::Buffer,
+    pub index_buffer: vk::Buffer,
+    pub index_type: IndexType,
+    pub index_count: u32,
+}
+
+/// The PBR graphics pipeline, with the per-frame uniform buffers and the descriptor sets of its
+/// materials.
+///
+/// Descriptor set 0 holds the frame uniforms and the IBL textures, set 1 the textures of the
+/// material being drawn. The lights are storage buffers at bindings 4 and 5 of set 0, see
+/// `shaders/lights.glsl`, and the joint palette of skinned pipelines is one at binding 6. The
+/// shaders write linear HDR radiance, so the render pass should target an HDR image that gets
+/// tonemapped afterwards.
+#[derive(Clone)]
+pub struct PbrPipeline(Rc<InnerPbrPipeline>);
+
+impl PbrPipeline {
+    /// Creates the pipeline for subpass 0 of `render_pass` from the SPIR-V of `shaders/pbr.vert`
+    /// and `shaders/pbr.frag`, with room for `max_materials` materials.
+    pub fn new(
+        render_pass: RenderPass,
+        shader_cache: &ShaderCache,
+        vertex_shader: &[u32],
+        fragment_shader: &[u32],
+        max_materials: u32,
+    ) -> Result<Self, ErrorCtx> {
+        Self::create(
+            render_pass,
+            shader_cache,
+            vertex_shader,
+            fragment_shader,
+            max_materials,
+            false,
+        )
+    }
+
+    /// Like [PbrPipeline::new] for meshes made of [SkinnedVertex]es, with the SPIR-V of
+    /// `shaders/pbr_skinned.vert` as the vertex shader.
+    pub fn new_skinned(
+        render_pass: RenderPass,
+        shader_cache: &ShaderCache,
+        vertex_shader: &[u32],
+        fragment_shader: &[u32],
+        max_materials: u32,
+    ) -> Result<Self, ErrorCtx> {
+        Self::create(
+            render_pass,
+            shader_cache,
+            vertex_shader,
+            fragment_shader,
+            max_materials,
+            true,
+        )
+    }
+
+    fn create(
+        render_pass: RenderPass,
+        shader_cache: &ShaderCache,
+        vertex_shader: &[u32],
+        fragment_shader: &[u32],
+        max_materials: u32,
+        skinned: bool,
+    ) -> Result<Self, ErrorCtx> {
+        let logical_device = render_pass.swapchain().device().clone();
+        let device = logical_device.device();
+
+        let frame_bindings = [
+            DescriptorSetLayoutBinding::default()
+                .binding(0)
+                .descriptor_type(DescriptorType::UNIFORM_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT),
+            sampler_binding(1),
+            sampler_binding(2),
+            sampler_binding(3),
+            storage_binding(4),
+            storage_binding(5),
+            DescriptorSetLayoutBinding::default()
+                .binding(6)
+                .descriptor_type(DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(ShaderStageFlags::VERTEX),
+        ];
+        let material_bindings = [0, 1, 2, 3].map(sampler_binding);
+
+        let set_layouts = [&frame_bindings[..], &material_bindings[..]]
+            .map(|bindings| {
+                unsafe {
+                    device.create_descriptor_set_layout(
+                        &DescriptorSetLayoutCreateInfo::default().bindings(bindings),
+                        host_allocation_callbacks(),
+                    )
+                }
+                .context("creating PBR descriptor set layout")
+            })
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let frame_sets = MAX_FRAMES_IN_FLIGHT as u32;
+        let pool_sizes = [
+            DescriptorPoolSize::default()
+                .ty(DescriptorType::UNIFORM_BUFFER)
+                .descriptor_count(frame_sets),
+            DescriptorPoolSize::default()
+                .ty(DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(frame_sets * 3 + max_materials * 4),
+            DescriptorPoolSize::default()
+                .ty(DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(frame_sets * 3),
+        ];
+
+        let descriptor_pool = unsafe {
+            device.create_descriptor_pool(
+                &DescriptorPoolCreateInfo::default()
+                    .max_sets(frame_sets + max_materials)
+                    .pool_sizes(&pool_sizes),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating PBR descriptor pool")?;
+
+        let frame_set_layouts = [set_layouts[0]; MAX_FRAMES_IN_FLIGHT];
+        let frame_descriptor_sets = unsafe {
+            device.allocate_descriptor_sets(
+                &DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(descriptor_pool)
+                    .set_layouts(&frame_set_layouts),
+            )
+        }
+        .context("allocating PBR frame descriptor sets")?;
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::DescriptorSet, frame_sets as u64, 0);
+
+        let push_constant_ranges = [PushConstantRange::default()
+            .stage_flags(ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT)
+            .size(mem::size_of::<PbrPushConstants>() as u32)];
+
+        let pipeline_layout = unsafe {
+            device.create_pipeline_layout(
+                &PipelineLayoutCreateInfo::default()
+                    .set_layouts(&set_layouts)
+                    .push_constant_ranges(&push_constant_ranges),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating PBR pipeline layout")?;
+
+        let pipeline = create_pipeline(
+            &render_pass,
+            shader_cache,
+            pipeline_layout,
+            vertex_shader,
+            fragment_shader,
+            skinned,
+        )?;
+
+        let frame_buffer = FrameBuffer::new(&logical_device)?;
+
+        for (frame, &descriptor_set) in frame_descriptor_sets.iter().enumerate() {
+            let buffer_info = [DescriptorBufferInfo::default()
+                .buffer(frame_buffer.buffer)
+                .offset(frame_buffer.stride * frame as DeviceSize)
+                .range(mem::size_of::<PbrFrameUniforms>() as DeviceSize)];
+
+            unsafe {
+                device.update_descriptor_sets(
+                    &[WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(0)
+                        .descriptor_type(DescriptorType::UNIFORM_BUFFER)
+                        .buffer_info(&buffer_info)],
+                    &[],
+                )
+            };
+        }
+
+        Ok(Self(Rc::new(InnerPbrPipeline {
+            set_layouts,
+            descriptor_pool,
+            frame_descriptor_sets,
+            material_count: Cell::new(0),
+            pipeline_layout,
+            pipeline,
+            frame_buffer,
+            environment: RefCell::new(None),
+            lights: RefCell::new(None),
+            light_clusters: RefCell::new(None),
+            joint_palettes: RefCell::new(None),
+            skinned,
+            logical_device,
+            render_pass,
+        })))
+    }
+
+    /// Lights every frame with `environment`. The frame descriptor sets must not be in use by a
+    /// pending command buffer.
+    pub fn set_environment(&self, environment: &IblTextures) {
+        let image_infos = [
+            [environment.irradiance()],
+            [environment.prefiltered()],
+            [environment.brdf_lut()],
+        ];
+
+        let writes: Vec<_> = self
+            .0
+            .frame_descriptor_sets
+            .iter()
+            .flat_map(|&descriptor_set| {
+                image_infos.iter().zip(1..).map(move |(info, binding)| {
+                    WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(binding)
+                        .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+                        .image_info(info)
+                })
+            })
+            .collect();
+
+        unsafe {
+            self.0
+                .logical_device
+                .device()
+                .update_descriptor_sets(&writes, &[]);
+        }
+
+        *self.0.environment.borrow_mut() = Some(environment.clone());
+    }
+
+    /// Shades every frame with the lights in `lights`. The frame descriptor sets must not be in
+    /// use by a pending command buffer.
+    pub fn set_lights(&self, lights: &LightBuffers) {
+        self.write_frame_buffers(4, |frame| lights.descriptor(frame));
+        *self.0.lights.borrow_mut() = Some(lights.clone());
+    }
+
+    /// Looks the lights of each fragment up in `light_clusters`, which the fragment shader has to
+    /// be compiled with `CLUSTERED_LIGHTS` for.
+    pub fn set_light_clusters(&self, light_clusters: &LightClusters) {
+        self.write_frame_buffers(5, |frame| light_clusters.descriptor(frame));
+        *self.0.light_clusters.borrow_mut() = Some(light_clusters.clone());
+    }
+
+    /// Deforms the meshes of skinned draws with the matrices in `joint_palettes`. The frame
+    /// descriptor sets must not be in use by a pending command buffer.
+    pub fn set_joint_palettes(&self, joint_palettes: &JointPalettes) {
+        self.write_frame_buffers(6, |frame| joint_palettes.descriptor(frame));
+        *self.0.joint_palettes.borrow_mut() = Some(joint_palettes.clone());
+    }
+
+    /// Whether the pipeline was created for [SkinnedVertex]es.
+    pub fn is_skinned(&self) -> bool {
+        self.0.skinned
+    }
+
+    fn write_frame_buffers(
+        &self,
+        binding: u32,
+        descriptor: impl Fn(usize) -> DescriptorBufferInfo,
+    ) {
+        let buffer_infos: Vec<_> = (0..self.0.frame_descriptor_sets.len())
+            .map(|frame| [descriptor(frame)])
+            .collect();
+
+        let writes: Vec<_> = self
+            .0
+            .frame_descriptor_sets
+            .iter()
+            .zip(&buffer_infos)
+            .map(|(&descriptor_set, info)| {
+                WriteDescriptorSet::default()
+                    .dst_set(descriptor_set)
+                    .dst_binding(binding)
+                    .descriptor_type(DescriptorType::STORAGE_BUFFER)
+                    .buffer_info(info)
+            })
+            .collect();
+
+        unsafe {
+            self.0
+                .logical_device
+                .device()
+                .update_descriptor_sets(&writes, &[]);
+        }
+    }
+
+    /// Allocates the descriptor set of a material using `textures`.
+    pub fn create_material(
+        &self,
+        textures: &PbrTextures,
+        factors: MaterialFactors,
+    ) -> Result<PbrMaterial, ErrorCtx> {
+        let device = self.0.logical_device.device();
+        let set_layouts = [self.0.set_layouts[1]];
+
+        let descriptor_set = unsafe {
+            device.allocate_descriptor_sets(
+                &DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(self.0.descriptor_pool)
+                    .set_layouts(&set_layouts),
+            )
+        }
+        .with_context("allocating PBR material descriptor set", || {
+            format!("materials={}", self.0.material_count.get())
+        })?[0];
+
+        self.0.material_count.set(self.0.material_count.get() + 1);
+        self.0
+            .logical_device
+            .resource_tracker()
+            .track(ResourceKind::DescriptorSet, 1, 0);
+
+        let image_infos = [
+            [textures.albedo],
+            [textures.normal],
+            [textures.metallic_roughness],
+            [textures.occlusion],
+        ];
+
+        let writes: Vec<_> = image_infos
+            .iter()
+            .zip(0..)
+            .map(|(info, binding)| {
+                WriteDescriptorSet::default()
+                    .dst_set(descriptor_set)
+                    .dst_binding(binding)
+                    .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(info)
+            })
+            .collect();
+
+        unsafe { device.update_descriptor_sets(&writes, &[]) };
+
+        Ok(PbrMaterial {
+            descriptor_set,
+            factors,
+        })
+    }
+
+    /// Writes the uniforms of frame `frame`, whose previous submission must have finished.
+    pub fn update_frame(&self, frame: usize, uniforms: &PbrFrameUniforms) {
+        let mut uniforms = *uniforms;
+
+        if let Some(environment) = self.0.environment.borrow().as_ref() {
+            uniforms.prefiltered_mip_levels = environment.prefiltered_mip_levels() as f32;
+        }
+
+        // SAFETY: every frame has its own slot of the persistently mapped buffer.
+        unsafe {
+            self.0
+                .frame_buffer
+                .mapped
+                .add(self.0.frame_buffer.stride as usize * frame)
+                .cast::<PbrFrameUniforms>()
+                .write_unaligned(uniforms);
+        }
+    }
+
+    /// Binds the pipeline and the uniforms of frame `frame`, inside the render pass.
+    pub fn bind(&self, command_buffer: CommandBuffer, frame: usize) {
+        let device = self.0.logical_device.device();
+
+        unsafe {
+            device.cmd_bind_pipeline(command_buffer, PipelineBindPoint::GRAPHICS, self.0.pipeline);
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                PipelineBindPoint::GRAPHICS,
+                self.0.pipeline_layout,
+                0,
+                &[self.0.frame_descriptor_sets[frame]],
+                &[],
+            );
+        }
+    }
+
+    /// Draws `mesh` with `material` after [PbrPipeline::bind].
+    pub fn draw(
+        &self,
+        command_buffer: CommandBuffer,
+        material: &PbrMaterial,
+        model: [[f32; 4]; 4],
+        mesh: &PbrMesh,
+    ) {
+        self.record_draw(command_buffer, material, model, mesh, 0);
+    }
+
+    /// Draws the [SkinnedVertex]es of `mesh` deformed by the skeleton whose matrices start at
+    /// `first_joint` in the joint palette, after [PbrPipeline::bind] on a skinned pipeline.
+    pub fn draw_skinned(
+        &self,
+        command_buffer: CommandBuffer,
+        material: &PbrMaterial,
+        model: [[f32; 4]; 4],
+        mesh: &PbrMesh,
+        first_joint: u32,
+    ) {
+        debug_assert!(self.0.skinned, "skinned draw with a static PBR pipeline");
+        self.record_draw(command_buffer, material, model, mesh, first_joint);
+    }
+
+    fn record_draw(
+        &self,
+        command_buffer: CommandBuffer,
+        material: &PbrMaterial,
+        model: [[f32; 4]; 4],
+        mesh: &PbrMesh,
+        first_joint: u32,
+    ) {
+        let device = self.0.logical_device.device();
+        let push_constants = PbrPushConstants {
+            model,
+            factors: material.factors,
+            first_joint,
+        };
+
+        unsafe {
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                PipelineBindPoint::GRAPHICS,
+                self.0.pipeline_layout,
+                1,
+                &[material.descriptor_set],
+                &[],
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.0.pipeline_layout,
+                ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT,
+                0,
+                push_constants.bytes_of(),
+            );
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[mesh.vertex_buffer], &[0]);
+            device.cmd_bind_index_buffer(command_buffer, mesh.index_buffer, 0, mesh.index_type);
+            device.cmd_draw_indexed(command_buffer, mesh.index_count, 1, 0, 0, 0);
+        }
+    }
+}
+
+fn storage_binding(binding: u32) -> DescriptorSetLayoutBinding<'static> {
+    DescriptorSetLayoutBinding::default()
+        .binding(binding)
+        .descriptor_type(DescriptorType::STORAGE_BUFFER)
+        .descriptor_count(1)
+        .stage_flags(ShaderStageFlags::FRAGMENT)
+}
+
+fn sampler_binding(binding: u32) -> DescriptorSetLayoutBinding<'static> {
+    DescriptorSetLayoutBinding::default()
+        .binding(binding)
+        .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(1)
+        .stage_flags(ShaderStageFlags::FRAGMENT)
+}
+
+fn create_pipeline(
+    render_pass: &RenderPass,
+    shader_cache: &ShaderCache,
+    pipeline_layout: vk::PipelineLayout,
+    vertex_shader: &[u32],
+    fragment_shader: &[u32],
+    skinned: bool,
+) -> Result<vk::Pipeline, ErrorCtx> {
+    let logical_device = render_pass.swapchain().device();
+
+    let shader_modules = [
+        shader_cache
+            .get_or_create(vertex_shader)
+            .context("creating PBR vertex shader module")?,
+        shader_cache
+            .get_or_create(fragment_shader)
+            .context("creating PBR fragment shader module")?,
+    ];
+
+    let stages = [
+        PipelineShaderStageCreateInfo::default()
+            .stage(ShaderStageFlags::VERTEX)
+            .module(*shader_modules[0].shader_module())
+            .name(c"main"),
+        PipelineShaderStageCreateInfo::default()
+            .stage(ShaderStageFlags::FRAGMENT)
+            .module(*shader_modules[1].shader_module())
+            .name(c"main"),
+    ];
+
+    let (bindings, attributes): (_, &[_]) = if skinned {
+        (
+            [SkinnedVertex::binding_description()],
+            &SkinnedVertex::attribute_descriptions(),
+        )
+    } else {
+        (
+            [PbrVertex::binding_description()],
+            &PbrVertex::attribute_descriptions(),
+        )
+    };
+    let vertex_input_info = PipelineVertexInputStateCreateInfo::default()
+        .vertex_binding_descriptions(&bindings)
+        .vertex_attribute_descriptions(attributes);
+
+    let input_assembly_info =
+        PipelineInputAssemblyStateCreateInfo::default().topology(PrimitiveTopology::TRIANGLE_LIST);
+
+    // Set when recording, like the triangle pipeline.
+    let dynamic_states = [DynamicState::VIEWPORT, DynamicState::SCISSOR];
+    let dynamic_state_info =
+        PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+    let viewport_info = PipelineViewportStateCreateInfo::default()
+        .viewport_count(1)
+        .scissor_count(1);
+
+    // Meshes wind their front faces counter-clockwise, like glTF.
+    let rasterizer_info = PipelineRasterizationStateCreateInfo::default()
+        .polygon_mode(PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(CullModeFlags::BACK)
+        .front_face(FrontFace::COUNTER_CLOCKWISE);
+
+    let multisample_info =
+        PipelineMultisampleStateCreateInfo::default().rasterization_samples(render_pass.samples());
+
+    let color_blend_attachments = [BlendMode::Opaque.attachment_state()];
+    let color_blend_info =
+        PipelineColorBlendStateCreateInfo::default().attachments(&color_blend_attachments);
+
+    let create_info = GraphicsPipelineCreateInfo::default()
+        .stages(&stages)
+        .vertex_input_state(&vertex_input_info)
+        .input_assembly_state(&input_assembly_info)
+        .viewport_state(&viewport_info)
+        .rasterization_state(&rasterizer_info)
+        .multisample_state(&multisample_info)
+        .color_blend_state(&color_blend_info)
+        .dynamic_state(&dynamic_state_info)
+        .layout(pipeline_layout)
+        .render_pass(*render_pass.render_pass());
+
+    let pipeline = unsafe {
+        pipeline_stats::create_graphics_pipelines(
+            logical_device,
+            PipelineCache::null(),
+            &[create_info],
+        )
+    }
+    .context("creating PBR pipeline")?[0];
+
+    logical_device
+        .resource_tracker()
+        .track(ResourceKind::Pipeline, 1, 0);
+
+    Ok(pipeline)
+}
+
+/// The uniform buffers of every frame in flight, in one persistently mapped allocation.
+struct FrameBuffer {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    mapped: *mut u8,
+    /// The distance between the uniforms of two frames, respecting the offset alignment.
+    stride: DeviceSize,
+    size: DeviceSize,
+
+    logical_device: LogicalDevice,
+}
+
+impl FrameBuffer {
+    fn new(logical_device: &LogicalDevice) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+
+        let alignment = logical_device
+            .physical_device()
+            .properties()
+            .limits
+            .min_uniform_buffer_offset_alignment;
+        let stride = align_up(mem::size_of::<PbrFrameUniforms>() as DeviceSize, alignment);
+
+        let buffer = unsafe {
+            device.create_buffer(
+                &BufferCreateInfo::default()
+                    .size(stride * MAX_FRAMES_IN_FLIGHT as DeviceSize)
+                    .usage(BufferUsageFlags::UNIFORM_BUFFER)
+                    .sharing_mode(SharingMode::EXCLUSIVE),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating PBR frame uniform buffer")?;
+
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+
+        let mut frame_buffer = Self {
+            buffer,
+            memory: vk::DeviceMemory::null(),
+            mapped: std::ptr::null_mut(),
+            stride,
+            size: requirements.size,
+            logical_device: logical_device.clone(),
+        };
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::Buffer, 1, requirements.size);
+
+        let memory_type_index = logical_device
+            .physical_device()
+            .find_memory_type(
+                requirements.memory_type_bits,
+                MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+            )
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            .with_context("finding PBR frame uniform memory", || {
+                format!("memory_type_bits={:#x}", requirements.memory_type_bits)
+            })?;
+
+        frame_buffer.memory = unsafe {
+            device.allocate_memory(
+                &MemoryAllocateInfo::default()
+                    .allocation_size(requirements.size)
+                    .memory_type_index(memory_type_index),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("allocating PBR frame uniform memory")?;
+
+        frame_buffer.mapped = unsafe {
+            device
+                .bind_buffer_memory(buffer, frame_buffer.memory, 0)
+                .context("binding PBR frame uniform memory")?;
+
+            device
+                .map_memory(frame_buffer.memory, 0, WHOLE_SIZE, MemoryMapFlags::empty())
+                .context("mapping PBR frame uniform memory")?
+                .cast()
+        };
+
+        // Every frame starts out with valid uniforms.
+        for frame in 0..MAX_FRAMES_IN_FLIGHT {
+            unsafe {
+                frame_buffer
+                    .mapped
+                    .add(stride as usize * frame)
+                    .cast::<PbrFrameUniforms>()
+                    .write_unaligned(PbrFrameUniforms::default());
+            }
+        }
+
+        Ok(frame_buffer)
+    }
+}
+
+impl Drop for FrameBuffer {
+    fn drop(&mut self) {
+        let device = self.logical_device.device();
+
+        unsafe {
+            if !self.mapped.is_null() {
+                device.unmap_memory(self.memory);
+            }
+
+            device.destroy_buffer(self.buffer, host_allocation_callbacks());
+            device.free_memory(self.memory, host_allocation_callbacks());
+        }
+
+        self.logical_device
+            .resource_tracker()
+            .untrack(ResourceKind::Buffer, 1, self.size);
+    }
+}
+
+struct InnerPbrPipeline {
+    set_layouts: Vec<vk::DescriptorSetLayout>,
+    descriptor_pool: vk::DescriptorPool,
+    frame_descriptor_sets: Vec<vk::DescriptorSet>,
+    material_count: Cell<u32>,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    frame_buffer: FrameBuffer,
+    /// Kept alive while the frame descriptor sets point at it.
+    environment: RefCell<Option<IblTextures>>,
+    #[allow(dead_code)]
+    lights: RefCell<Option<LightBuffers>>,
+    #[allow(dead_code)]
+    light_clusters: RefCell<Option<LightClusters>>,
+    #[allow(dead_code)]
+    joint_palettes: RefCell<Option<JointPalettes>>,
+    skinned: bool,
+
+    logical_device: LogicalDevice,
+
+    #[allow(dead_code)]
+    render_pass: RenderPass,
+}
+
+impl Drop for InnerPbrPipeline {
+    fn drop(&mut self) {
+        teardown_trace::record(
+            "PbrPipeline",
+            [self.pipeline.as_raw(), self.frame_buffer.buffer.as_raw()],
+        );
+
+        let device = self.logical_device.device();
+
+        unsafe {
+            device.destroy_pipeline(self.pipeline, host_allocation_callbacks());
+            device.destroy_pipeline_layout(self.pipeline_layout, host_allocation_callbacks());
+            device.destroy_descriptor_pool(self.descriptor_pool, host_allocation_callbacks());
+
+            for &set_layout in &self.set_layouts {
+                device.destroy_descriptor_set_layout(set_layout, host_allocation_callbacks());
+            }
+        }
+
+        let tracker = self.logical_device.resource_tracker();
+        tracker.untrack(ResourceKind::Pipeline, 1, 0);
+        tracker.untrack(
+            ResourceKind::DescriptorSet,
+            self.frame_descriptor_sets.len() as u64 + self.material_count.get() as u64,
+            0,
+        );
+    }
+}
@@ -0,0 +1,390 @@
+use std::{error, ffi::CStr, fmt, rc::Rc};
+
+use ash::{
+    prelude::VkResult,
+    vk::{
+        self, ColorSpaceKHR, PresentModeKHR, QueueFlags, SampleCountFlags, SurfaceCapabilitiesKHR,
+        SurfaceFormatKHR,
+    },
+};
+
+use crate::{
+    api2::{choose_swapchain_extent, is_srgb_format, SwapchainExtent},
+    renderer::{
+        instance::Instance, logical_device::REQUIRED_EXTENSIONS, surface::Surface, window::Window,
+    },
+};
+
+#[derive(Clone)]
+pub struct PhysicalDevice(Rc<InnerPhysicalDevice>);
+
+impl PhysicalDevice {
+    pub fn new(instance: Instance, surface: &Surface) -> Result<Self, PhysicalDeviceError> {
+        let devices = unsafe {
+            instance
+                .instance()
+                .enumerate_physical_devices()
+                .map_err(PhysicalDeviceError::from)?
+        };
+
+        if devices.is_empty() {
+            return Err(PhysicalDeviceError::NoDevices);
+        }
+
+        for physical_device in devices {
+            if let Ok(QueueFamilyIndices {
+                graphics_family: Some(graphics_family),
+                present_family: Some(present_family),
+            }) = QueueFamilyIndices::find_queue_families(&instance, &physical_device, surface)
+            {
+                if check_device_extension_support(&instance, physical_device)
+                    .map_err(PhysicalDeviceError::from)?
+                {
+                    let swapchain_support =
+                        SwapchainSupportDetails::query_support(surface, &physical_device)?;
+
+                    if !swapchain_support.formats.is_empty()
+                        && !swapchain_support.present_modes.is_empty()
+                    {
+                        let properties = unsafe {
+                            instance
+                                .instance()
+                                .get_physical_device_properties(physical_device)
+                        };
+                        let memory_properties = unsafe {
+                            instance
+                                .instance()
+                                .get_physical_device_memory_properties(physical_device)
+                        };
+
+                        return Ok(Self(Rc::new(InnerPhysicalDevice {
+                            instance,
+                            physical_device,
+                            properties,
+                            memory_properties,
+                            graphics_family,
+                            present_family,
+                            swapchain_support,
+                        })));
+                    }
+                }
+            }
+        }
+
+        Err(PhysicalDeviceError::NoSuitableDevices)
+    }
+
+    /// Picks the first device with a queue family for both compute and graphics, without
+    /// checking for presentation support, for jobs that never open a window. Graphics is still
+    /// required as mips are blitted, which compute-only families can't do.
+    ///
+    /// Both families are that one, and the device has no swapchain support.
+    pub fn compute_only(instance: Instance) -> Result<Self, PhysicalDeviceError> {
+        let devices = unsafe {
+            instance
+                .instance()
+                .enumerate_physical_devices()
+                .map_err(PhysicalDeviceError::from)?
+        };
+
+        if devices.is_empty() {
+            return Err(PhysicalDeviceError::NoDevices);
+        }
+
+        for physical_device in devices {
+            let queue_families = unsafe {
+                instance
+                    .instance()
+                    .get_physical_device_queue_family_properties(physical_device)
+            };
+
+            let Some(family) = queue_families.iter().position(|family| {
+                family
+                    .queue_flags
+                    .contains(QueueFlags::COMPUTE | QueueFlags::GRAPHICS)
+            }) else {
+                continue;
+            };
+
+            let properties = unsafe {
+                instance
+                    .instance()
+                    .get_physical_device_properties(physical_device)
+            };
+            let memory_properties = unsafe {
+                instance
+                    .instance()
+                    .get_physical_device_memory_properties(physical_device)
+            };
+
+            return Ok(Self(Rc::new(InnerPhysicalDevice {
+                instance,
+                physical_device,
+                properties,
+                memory_properties,
+                graphics_family: family,
+                present_family: family,
+                swapchain_support: SwapchainSupportDetails {
+                    capabilities: SurfaceCapabilitiesKHR::default(),
+                    formats: Vec::new(),
+                    present_modes: Vec::new(),
+                },
+            })));
+        }
+
+        Err(PhysicalDeviceError::NoSuitableDevices)
+    }
+
+    pub fn device(&self) -> &vk::PhysicalDevice {
+        &self.0.physical_device
+    }
+
+    pub fn instance(&self) -> &Instance {
+        &self.0.instance
+    }
+
+    pub fn properties(&self) -> &vk::PhysicalDeviceProperties {
+        &self.0.properties
+    }
+
+    pub fn memory_properties(&self) -> &vk::PhysicalDeviceMemoryProperties {
+        &self.0.memory_properties
+    }
+
+    /// Finds a memory type allowed by `type_bits`, from [vk::MemoryRequirements], that has all of
+    /// `properties`.
+    pub fn find_memory_type(
+        &self,
+        type_bits: u32,
+        properties: vk::MemoryPropertyFlags,
+    ) -> Option<u32> {
+        let memory_properties = &self.0.memory_properties;
+
+        memory_properties.memory_types[..memory_properties.memory_type_count as usize]
+            .iter()
+            .enumerate()
+            .position(|(index, memory_type)| {
+                type_bits & (1 << index) != 0 && memory_type.property_flags.contains(properties)
+            })
+            .map(|index| index as u32)
+    }
+
+    /// The highest sample count color attachments support, for MSAA render passes.
+    pub fn max_usable_sample_count(&self) -> SampleCountFlags {
+        let counts = self.0.properties.limits.framebuffer_color_sample_counts;
+
+        [
+            SampleCountFlags::TYPE_64,
+            SampleCountFlags::TYPE_32,
+            SampleCountFlags::TYPE_16,
+            SampleCountFlags::TYPE_8,
+            SampleCountFlags::TYPE_4,
+            SampleCountFlags::TYPE_2,
+        ]
+        .into_iter()
+        .find(|&samples| counts.contains(samples))
+        .unwrap_or(SampleCountFlags::TYPE_1)
+    }
+
+    pub fn graphics_family_u32(&self) -> u32 {
+        self.0.graphics_family as u32
+    }
+
+    pub fn present_family_u32(&self) -> u32 {
+        self.0.present_family as u32
+    }
+
+    /// The swapchain support of the surface when the device was picked. Its capabilities' extents
+    /// go stale when the window is resized, see [PhysicalDevice::query_swapchain_support].
+    pub fn swapchain_support(&self) -> &SwapchainSupportDetails {
+        &self.0.swapchain_support
+    }
+
+    /// Queries the swapchain support of `surface` again, with its current extents.
+    pub fn query_swapchain_support(&self, surface: &Surface) -> VkResult<SwapchainSupportDetails> {
+        SwapchainSupportDetails::query_support(surface, &self.0.physical_device)
+    }
+
+    /// The extent a swapchain for `surface` would be created with at `framebuffer_size`, or
+    /// [SwapchainExtent::NeedsWait] while the window is minimized.
+    pub fn swapchain_extent(
+        &self,
+        surface: &Surface,
+        framebuffer_size: (i32, i32),
+    ) -> VkResult<SwapchainExtent> {
+        Ok(self
+            .query_swapchain_support(surface)?
+            .choose_extent_for_size(framebuffer_size))
+    }
+}
+
+struct InnerPhysicalDevice {
+    instance: Instance,
+    physical_device: vk::PhysicalDevice,
+    properties: vk::PhysicalDeviceProperties,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    graphics_family: usize,
+    present_family: usize,
+    swapchain_support: SwapchainSupportDetails,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+struct QueueFamilyIndices {
+    graphics_family: Option<usize>,
+    present_family: Option<usize>,
+}
+
+impl QueueFamilyIndices {
+    pub fn find_queue_families(
+        instance: &Instance,
+        device: &vk::PhysicalDevice,
+        surface: &Surface,
+    ) -> VkResult<Self> {
+        let queue_family = unsafe {
+            instance
+                .instance()
+                .get_physical_device_queue_family_properties(*device)
+        };
+
+        let mut indices = Self::default();
+
+        for (i, v) in queue_family.iter().enumerate() {
+            if v.queue_flags.contains(QueueFlags::GRAPHICS) {
+                indices.graphics_family = Some(i);
+            }
+
+            if unsafe {
+                surface
+                    .surface_instance()
+                    .get_physical_device_surface_support(*device, i as u32, surface.surface())
+            }? {
+                indices.present_family = Some(i);
+            }
+        }
+
+        Ok(indices)
+    }
+}
+
+fn check_device_extension_support(
+    instance: &Instance,
+    device: vk::PhysicalDevice,
+) -> VkResult<bool> {
+    let available_extensions = unsafe {
+        instance
+            .instance()
+            .enumerate_device_extension_properties(device)
+    }?;
+
+    let supported = REQUIRED_EXTENSIONS.iter().all(|v| {
+        available_extensions.iter().any(|extension| {
+            let name = unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) };
+            *v == name
+        })
+    });
+
+    Ok(supported)
+}
+
+pub struct SwapchainSupportDetails {
+    #[allow(dead_code)]
+    pub capabilities: SurfaceCapabilitiesKHR,
+
+    pub formats: Vec<SurfaceFormatKHR>,
+    pub present_modes: Vec<PresentModeKHR>,
+}
+
+impl SwapchainSupportDetails {
+    pub fn query_support(
+        surface: &Surface,
+        physical_device: &vk::PhysicalDevice,
+    ) -> VkResult<SwapchainSupportDetails> {
+        let capabilities = unsafe {
+            surface
+                .surface_instance()
+                .get_physical_device_surface_capabilities(*physical_device, surface.surface())?
+        };
+
+        let formats = unsafe {
+            surface
+                .surface_instance()
+                .get_physical_device_surface_formats(*physical_device, surface.surface())?
+        };
+
+        let present_modes = unsafe {
+            surface
+                .surface_instance()
+                .get_physical_device_surface_present_modes(*physical_device, surface.surface())?
+        };
+
+        Ok(SwapchainSupportDetails {
+            capabilities,
+            formats,
+            present_modes,
+        })
+    }
+
+    pub fn choose_format(&self) -> &SurfaceFormatKHR {
+        self.formats
+            .iter()
+            .find(|format| {
+                format.format == vk::Format::B8G8R8A8_SRGB
+                    && format.color_space == ColorSpaceKHR::SRGB_NONLINEAR
+            })
+            .or_else(|| {
+                self.formats.iter().find(|format| {
+                    is_srgb_format(format.format)
+                        && format.color_space == ColorSpaceKHR::SRGB_NONLINEAR
+                })
+            })
+            .unwrap_or(&self.formats[0])
+    }
+
+    pub fn choose_present_mode(&self) -> PresentModeKHR {
+        for present_mode in &self.present_modes {
+            if *present_mode == PresentModeKHR::MAILBOX {
+                return *present_mode;
+            }
+        }
+
+        PresentModeKHR::FIFO
+    }
+
+    pub fn choose_extent(&self, window: &Window) -> SwapchainExtent {
+        self.choose_extent_for_size(window.get_framebuffer_size())
+    }
+
+    /// See [choose_swapchain_extent], negative sizes count as zero.
+    pub fn choose_extent_for_size(&self, size: (i32, i32)) -> SwapchainExtent {
+        choose_swapchain_extent(
+            &self.capabilities,
+            size.0.max(0) as u32,
+            size.1.max(0) as u32,
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PhysicalDeviceError {
+    Vulkan(vk::Result),
+    NoDevices,
+    NoSuitableDevices,
+}
+
+impl From<vk::Result> for PhysicalDeviceError {
+    fn from(value: vk::Result) -> Self {
+        Self::Vulkan(value)
+    }
+}
+
+impl fmt::Display for PhysicalDeviceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Vulkan(e) => e.fmt(f),
+            Self::NoDevices => write!(f, "failed to find GPUs with Vulkan support!"),
+            Self::NoSuitableDevices => write!(f, "failed to find a suitable GPU!"),
+        }
+    }
+}
+
+impl error::Error for PhysicalDeviceError {}
@@ -0,0 +1,868 @@
+//! Mouse picking: object IDs are rendered into an `R32_UINT` image and the pixel under the cursor
+//! is copied back to the host once the frame's fence has signaled.
+//!
+//! `examples/pbr.rs` picks the sphere under the cursor when it's clicked.
+
+use std::{mem, num::NonZeroU32};
+
+use ash::vk::{
+    self, AccessFlags, AttachmentDescription, AttachmentLoadOp, AttachmentReference,
+    AttachmentStoreOp, BufferCreateInfo, BufferImageCopy, BufferMemoryBarrier, BufferUsageFlags,
+    ClearColorValue, ClearDepthStencilValue, ClearValue, ColorComponentFlags, CommandBuffer,
+    CompareOp, CullModeFlags, DependencyFlags, DynamicState, Extent2D, Extent3D, Format,
+    FormatFeatureFlags, FramebufferCreateInfo, FrontFace, GraphicsPipelineCreateInfo, Handle,
+    ImageAspectFlags, ImageCreateInfo, ImageLayout, ImageSubresourceLayers, ImageSubresourceRange,
+    ImageTiling, ImageType, ImageUsageFlags, ImageViewCreateInfo, ImageViewType,
+    MemoryAllocateInfo, MemoryMapFlags, MemoryPropertyFlags, Offset2D, Offset3D, PipelineBindPoint,
+    PipelineCache, PipelineColorBlendAttachmentState, PipelineColorBlendStateCreateInfo,
+    PipelineDepthStencilStateCreateInfo, PipelineDynamicStateCreateInfo,
+    PipelineInputAssemblyStateCreateInfo, PipelineLayoutCreateInfo,
+    PipelineMultisampleStateCreateInfo, PipelineRasterizationStateCreateInfo,
+    PipelineShaderStageCreateInfo, PipelineStageFlags, PipelineVertexInputStateCreateInfo,
+    PipelineViewportStateCreateInfo, PolygonMode, PrimitiveTopology, PushConstantRange, Rect2D,
+    RenderPassBeginInfo, RenderPassCreateInfo, SampleCountFlags, ShaderStageFlags, SharingMode,
+    SubpassContents, SubpassDependency, SubpassDescription, VertexInputAttributeDescription,
+    VertexInputBindingDescription, VertexInputRate, SUBPASS_EXTERNAL, WHOLE_SIZE,
+};
+
+use crate::{
+    api2::{host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        logical_device::LogicalDevice, pbr::PbrMesh, pipeline_stats, resource_stats::ResourceKind,
+        shader_cache::ShaderCache, teardown_trace, MAX_FRAMES_IN_FLIGHT,
+    },
+    types::Pod,
+};
+
+const ID_FORMAT: Format = Format::R32_UINT;
+
+/// Identifies a pickable object. 0 is reserved for the background.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectId(pub NonZeroU32);
+
+impl ObjectId {
+    pub fn new(id: u32) -> Option<Self> {
+        NonZeroU32::new(id).map(Self)
+    }
+
+    pub fn get(self) -> u32 {
+        self.0.get()
+    }
+}
+
+/// The push constants of the picking shaders, mirrors `picking_draw.glsl`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct PickingPushConstants {
+    pub model_view_projection: [[f32; 4]; 4],
+    pub object_id: u32,
+}
+
+unsafe impl Pod for PickingPushConstants {}
+
+/// The picking render pass with its attachments and readback buffers.
+///
+/// Each frame, record [PickingPass::begin], a [PickingPass::draw] per pickable object and
+/// [PickingPass::end], which copies the pixel under the cursor. After waiting for that frame's
+/// fence, [PickingPass::resolve] reads the copy back, so picks arrive a frame or two late.
+pub struct PickingPass {
+    render_pass: vk::RenderPass,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    depth_format: Format,
+    target: PickingTarget,
+    readbacks: Vec<Readback>,
+    cursor: Option<(u32, u32)>,
+    /// The latest resolved pick and the pixel it was read from.
+    last_pick: Option<((u32, u32), Option<ObjectId>)>,
+
+    logical_device: LogicalDevice,
+}
+
+impl PickingPass {
+    /// Creates the pass for a target of size `extent`, from the SPIR-V of `shaders/picking.vert`
+    /// and `shaders/picking.frag`, drawing vertices `vertex_stride` bytes apart that start with
+    /// their position.
+    pub fn new(
+        logical_device: LogicalDevice,
+        shader_cache: &ShaderCache,
+        extent: Extent2D,
+        vertex_shader: &[u32],
+        fragment_shader: &[u32],
+        vertex_stride: u32,
+    ) -> Result<Self, ErrorCtx> {
+        let depth_format = find_depth_format(&logical_device);
+        let render_pass = create_render_pass(&logical_device, depth_format)?;
+
+        let push_constant_ranges = [PushConstantRange::default()
+            .stage_flags(ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT)
+            .size(mem::size_of::<PickingPushConstants>() as u32)];
+
+        let pipeline_layout = unsafe {
+            logical_device.device().create_pipeline_layout(
+                &PipelineLayoutCreateInfo::default().push_constant_ranges(&push_constant_ranges),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating picking pipeline layout")?;
+
+        let pipeline = create_pipeline(
+            &logical_device,
+            shader_cache,
+            render_pass,
+            pipeline_layout,
+            [vertex_shader, fragment_shader],
+            vertex_stride,
+        )?;
+
+        let target = PickingTarget::new(&logical_device, render_pass, depth_format, extent)?;
+
+        let readbacks = (0..MAX_FRAMES_IN_FLIGHT)
+            .map(|_| Readback::new(&logical_device))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            render_pass,
+            pipeline_layout,
+            pipeline,
+            depth_format,
+            target,
+            readbacks,
+            cursor: None,
+            last_pick: None,
+            logical_device,
+        })
+    }
+
+    pub fn extent(&self) -> Extent2D {
+        self.target.extent
+    }
+
+    /// Recreates the attachments for a new size, e.g. with the swapchain. They must not be in use
+    /// by a pending command buffer.
+    pub fn resize(&mut self, extent: Extent2D) -> Result<(), ErrorCtx> {
+        self.target = PickingTarget::new(
+            &self.logical_device,
+            self.render_pass,
+            self.depth_format,
+            extent,
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the object at pixel `(x, y)` if the latest readback was taken there, and reads
+    /// that pixel back from the next frames on.
+    ///
+    /// Calling this with the cursor position every frame gives the object under the cursor with
+    /// the latency of the frames in flight.
+    pub fn pick(&mut self, x: u32, y: u32) -> Option<ObjectId> {
+        self.cursor = Some((x, y));
+
+        match self.last_pick {
+            Some((position, object)) if position == (x, y) => object,
+            _ => None,
+        }
+    }
+
+    /// Stops reading pixels back, e.g. when the cursor leaves the window.
+    pub fn clear_cursor(&mut self) {
+        self.cursor = None;
+        self.last_pick = None;
+    }
+
+    /// Begins the picking render pass, outside of any other render pass, and binds the pipeline
+    /// with a viewport covering the target.
+    pub fn begin(&self, command_buffer: CommandBuffer) {
+        let device = self.logical_device.device();
+        let extent = self.target.extent;
+
+        let clear_values = [
+            ClearValue {
+                color: ClearColorValue { uint32: [0; 4] },
+            },
+            ClearValue {
+                depth_stencil: ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            },
+        ];
+
+        unsafe {
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &RenderPassBeginInfo::default()
+                    .render_pass(self.render_pass)
+                    .framebuffer(self.target.framebuffer)
+                    .render_area(Rect2D::default().extent(extent))
+                    .clear_values(&clear_values),
+                SubpassContents::INLINE,
+            );
+            device.cmd_bind_pipeline(command_buffer, PipelineBindPoint::GRAPHICS, self.pipeline);
+            // The same orientation as the pass the objects are drawn in, so IDs line up with
+            // what's on screen.
+            device.cmd_set_viewport(
+                command_buffer,
+                0,
+                &[self.logical_device.clip_space_y().viewport(extent)],
+            );
+            device.cmd_set_scissor(
+                command_buffer,
+                0,
+                &[Rect2D::default().offset(Offset2D::default()).extent(extent)],
+            );
+        }
+    }
+
+    /// Draws `mesh` as `object` after [PickingPass::begin].
+    pub fn draw(
+        &self,
+        command_buffer: CommandBuffer,
+        object: ObjectId,
+        model_view_projection: [[f32; 4]; 4],
+        mesh: &PbrMesh,
+    ) {
+        let device = self.logical_device.device();
+        let push_constants = PickingPushConstants {
+            model_view_projection,
+            object_id: object.get(),
+        };
+
+        unsafe {
+            device.cmd_push_constants(
+                command_buffer,
+                self.pipeline_layout,
+                ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT,
+                0,
+                push_constants.bytes_of(),
+            );
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[mesh.vertex_buffer], &[0]);
+            device.cmd_bind_index_buffer(command_buffer, mesh.index_buffer, 0, mesh.index_type);
+            device.cmd_draw_indexed(command_buffer, mesh.index_count, 1, 0, 0, 0);
+        }
+    }
+
+    /// Ends the render pass and copies the pixel under the cursor into frame `frame`'s readback
+    /// buffer.
+    pub fn end(&mut self, command_buffer: CommandBuffer, frame: usize) {
+        let device = self.logical_device.device();
+        let extent = self.target.extent;
+
+        unsafe { device.cmd_end_render_pass(command_buffer) };
+
+        let cursor = self
+            .cursor
+            .filter(|&(x, y)| x < extent.width && y < extent.height);
+
+        self.readbacks[frame].position = cursor;
+
+        let Some((x, y)) = cursor else {
+            return;
+        };
+
+        unsafe {
+            device.cmd_copy_image_to_buffer(
+                command_buffer,
+                self.target.id.image,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                self.readbacks[frame].buffer,
+                &[BufferImageCopy::default()
+                    .image_subresource(
+                        ImageSubresourceLayers::default()
+                            .aspect_mask(ImageAspectFlags::COLOR)
+                            .layer_count(1),
+                    )
+                    .image_offset(Offset3D {
+                        x: x as i32,
+                        y: y as i32,
+                        z: 0,
+                    })
+                    .image_extent(Extent3D {
+                        width: 1,
+                        height: 1,
+                        depth: 1,
+                    })],
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::HOST,
+                DependencyFlags::empty(),
+                &[],
+                &[BufferMemoryBarrier::default()
+                    .src_access_mask(AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(AccessFlags::HOST_READ)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .buffer(self.readbacks[frame].buffer)
+                    .size(WHOLE_SIZE)],
+                &[],
+            );
+        }
+    }
+
+    /// Reads frame `frame`'s copy back, once its fence has signaled.
+    pub fn resolve(&mut self, frame: usize) {
+        let readback = &mut self.readbacks[frame];
+
+        if let Some(position) = readback.position.take() {
+            // SAFETY: the fence guarantees the copy finished, and the memory is coherent.
+            let id = unsafe { readback.mapped.read() };
+            self.last_pick = Some((position, ObjectId::new(id)));
+        }
+    }
+}
+
+impl Drop for PickingPass {
+    fn drop(&mut self) {
+        teardown_trace::record(
+            "PickingPass",
+            [self.pipeline.as_raw(), self.render_pass.as_raw()],
+        );
+
+        let device = self.logical_device.device();
+
+        unsafe {
+            device.destroy_pipeline(self.pipeline, host_allocation_callbacks());
+            device.destroy_pipeline_layout(self.pipeline_layout, host_allocation_callbacks());
+        }
+
+        self.logical_device
+            .resource_tracker()
+            .untrack(ResourceKind::Pipeline, 1, 0);
+
+        // The framebuffer has to go before the render pass it was created for.
+        self.target.destroy();
+
+        unsafe { device.destroy_render_pass(self.render_pass, host_allocation_callbacks()) };
+    }
+}
+
+/// The first depth format the device can render to. `D16_UNORM` is always supported.
+fn find_depth_format(logical_device: &LogicalDevice) -> Format {
+    let physical_device = logical_device.physical_device();
+
+    [Format::D32_SFLOAT, Format::X8_D24_UNORM_PACK32]
+        .into_iter()
+        .find(|&format| {
+            let properties = unsafe {
+                physical_device
+                    .instance()
+                    .instance()
+                    .get_physical_device_format_properties(*physical_device.device(), format)
+            };
+
+            properties
+                .optimal_tiling_features
+                .contains(FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT)
+        })
+        .unwrap_or(Format::D16_UNORM)
+}
+
+fn create_render_pass(
+    logical_device: &LogicalDevice,
+    depth_format: Format,
+) -> Result<vk::RenderPass, ErrorCtx> {
+    let attachments = [
+        AttachmentDescription::default()
+            .format(ID_FORMAT)
+            .samples(SampleCountFlags::TYPE_1)
+            .load_op(AttachmentLoadOp::CLEAR)
+            .store_op(AttachmentStoreOp::STORE)
+            .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+            .initial_layout(ImageLayout::UNDEFINED)
+            .final_layout(ImageLayout::TRANSFER_SRC_OPTIMAL),
+        AttachmentDescription::default()
+            .format(depth_format)
+            .samples(SampleCountFlags::TYPE_1)
+            .load_op(AttachmentLoadOp::CLEAR)
+            .store_op(AttachmentStoreOp::DONT_CARE)
+            .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+            .initial_layout(ImageLayout::UNDEFINED)
+            .final_layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+    ];
+
+    let color_references = [AttachmentReference::default()
+        .attachment(0)
+        .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)];
+    let depth_reference = AttachmentReference::default()
+        .attachment(1)
+        .layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
+
+    let subpasses = [SubpassDescription::default()
+        .pipeline_bind_point(PipelineBindPoint::GRAPHICS)
+        .color_attachments(&color_references)
+        .depth_stencil_attachment(&depth_reference)];
+
+    let attachment_stages =
+        PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | PipelineStageFlags::EARLY_FRAGMENT_TESTS;
+
+    let dependencies = [
+        // The previous frame's readback copy and depth tests have to finish before clearing.
+        SubpassDependency::default()
+            .src_subpass(SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(PipelineStageFlags::TRANSFER | PipelineStageFlags::LATE_FRAGMENT_TESTS)
+            .dst_stage_mask(attachment_stages)
+            .dst_access_mask(
+                AccessFlags::COLOR_ATTACHMENT_WRITE | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            ),
+        SubpassDependency::default()
+            .src_subpass(0)
+            .dst_subpass(SUBPASS_EXTERNAL)
+            .src_stage_mask(PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_stage_mask(PipelineStageFlags::TRANSFER)
+            .dst_access_mask(AccessFlags::TRANSFER_READ),
+    ];
+
+    unsafe {
+        logical_device.device().create_render_pass(
+            &RenderPassCreateInfo::default()
+                .attachments(&attachments)
+                .subpasses(&subpasses)
+                .dependencies(&dependencies),
+            host_allocation_callbacks(),
+        )
+    }
+    .context("creating picking render pass")
+}
+
+fn create_pipeline(
+    logical_device: &LogicalDevice,
+    shader_cache: &ShaderCache,
+    render_pass: vk::RenderPass,
+    pipeline_layout: vk::PipelineLayout,
+    [vertex_shader, fragment_shader]: [&[u32]; 2],
+    vertex_stride: u32,
+) -> Result<vk::Pipeline, ErrorCtx> {
+    let shader_modules = [
+        shader_cache
+            .get_or_create(vertex_shader)
+            .context("creating picking vertex shader module")?,
+        shader_cache
+            .get_or_create(fragment_shader)
+            .context("creating picking fragment shader module")?,
+    ];
+
+    let stages = [
+        PipelineShaderStageCreateInfo::default()
+            .stage(ShaderStageFlags::VERTEX)
+            .module(*shader_modules[0].shader_module())
+            .name(c"main"),
+        PipelineShaderStageCreateInfo::default()
+            .stage(ShaderStageFlags::FRAGMENT)
+            .module(*shader_modules[1].shader_module())
+            .name(c"main"),
+    ];
+
+    let bindings = [VertexInputBindingDescription::default()
+        .binding(0)
+        .stride(vertex_stride)
+        .input_rate(VertexInputRate::VERTEX)];
+    let attributes = [VertexInputAttributeDescription::default()
+        .binding(0)
+        .location(0)
+        .format(Format::R32G32B32_SFLOAT)
+        .offset(0)];
+    let vertex_input_info = PipelineVertexInputStateCreateInfo::default()
+        .vertex_binding_descriptions(&bindings)
+        .vertex_attribute_descriptions(&attributes);
+
+    let input_assembly_info =
+        PipelineInputAssemblyStateCreateInfo::default().topology(PrimitiveTopology::TRIANGLE_LIST);
+
+    let dynamic_states = [DynamicState::VIEWPORT, DynamicState::SCISSOR];
+    let dynamic_state_info =
+        PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+    let viewport_info = PipelineViewportStateCreateInfo::default()
+        .viewport_count(1)
+        .scissor_count(1);
+
+    let rasterizer_info = PipelineRasterizationStateCreateInfo::default()
+        .polygon_mode(PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(CullModeFlags::BACK)
+        .front_face(FrontFace::COUNTER_CLOCKWISE);
+
+    let multisample_info = PipelineMultisampleStateCreateInfo::default()
+        .rasterization_samples(SampleCountFlags::TYPE_1);
+
+    let depth_stencil_info = PipelineDepthStencilStateCreateInfo::default()
+        .depth_test_enable(true)
+        .depth_write_enable(true)
+        .depth_compare_op(CompareOp::LESS);
+
+    // Integer attachments can't blend.
+    let color_blend_attachments =
+        [PipelineColorBlendAttachmentState::default().color_write_mask(ColorComponentFlags::R)];
+    let color_blend_info =
+        PipelineColorBlendStateCreateInfo::default().attachments(&color_blend_attachments);
+
+    let create_info = GraphicsPipelineCreateInfo::default()
+        .stages(&stages)
+        .vertex_input_state(&vertex_input_info)
+        .input_assembly_state(&input_assembly_info)
+        .viewport_state(&viewport_info)
+        .rasterization_state(&rasterizer_info)
+        .multisample_state(&multisample_info)
+        .depth_stencil_state(&depth_stencil_info)
+        .color_blend_state(&color_blend_info)
+        .dynamic_state(&dynamic_state_info)
+        .layout(pipeline_layout)
+        .render_pass(render_pass);
+
+    let pipeline = unsafe {
+        pipeline_stats::create_graphics_pipelines(
+            logical_device,
+            PipelineCache::null(),
+            &[create_info],
+        )
+    }
+    .context("creating picking pipeline")?[0];
+
+    logical_device
+        .resource_tracker()
+        .track(ResourceKind::Pipeline, 1, 0);
+
+    Ok(pipeline)
+}
+
+/// An image with its view and memory.
+struct Attachment {
+    image: vk::Image,
+    view: vk::ImageView,
+    memory: vk::DeviceMemory,
+    size: vk::DeviceSize,
+}
+
+impl Attachment {
+    fn new(
+        logical_device: &LogicalDevice,
+        format: Format,
+        aspect: ImageAspectFlags,
+        usage: ImageUsageFlags,
+        extent: Extent2D,
+    ) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+
+        let image = unsafe {
+            device.create_image(
+                &ImageCreateInfo::default()
+                    .image_type(ImageType::TYPE_2D)
+                    .format(format)
+                    .extent(Extent3D {
+                        width: extent.width,
+                        height: extent.height,
+                        depth: 1,
+                    })
+                    .mip_levels(1)
+                    .array_layers(1)
+                    .samples(SampleCountFlags::TYPE_1)
+                    .tiling(ImageTiling::OPTIMAL)
+                    .usage(usage)
+                    .sharing_mode(SharingMode::EXCLUSIVE)
+                    .initial_layout(ImageLayout::UNDEFINED),
+                host_allocation_callbacks(),
+            )
+        }
+        .with_context("creating picking attachment", || {
+            format!("format={:?}, extent={:?}", format, extent)
+        })?;
+
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+
+        let memory = logical_device
+            .physical_device()
+            .find_memory_type(
+                requirements.memory_type_bits,
+                MemoryPropertyFlags::DEVICE_LOCAL,
+            )
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            .and_then(|memory_type_index| unsafe {
+                device.allocate_memory(
+                    &MemoryAllocateInfo::default()
+                        .allocation_size(requirements.size)
+                        .memory_type_index(memory_type_index),
+                    host_allocation_callbacks(),
+                )
+            })
+            .context("allocating picking attachment memory");
+
+        let memory = match memory {
+            Ok(memory) => memory,
+            Err(e) => {
+                unsafe { device.destroy_image(image, host_allocation_callbacks()) };
+                return Err(e);
+            }
+        };
+
+        let mut attachment = Self {
+            image,
+            view: vk::ImageView::null(),
+            memory,
+            size: requirements.size,
+        };
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::Image, 1, requirements.size);
+
+        let view = unsafe { device.bind_image_memory(image, memory, 0) }
+            .and_then(|_| unsafe {
+                device.create_image_view(
+                    &ImageViewCreateInfo::default()
+                        .image(image)
+                        .view_type(ImageViewType::TYPE_2D)
+                        .format(format)
+                        .subresource_range(
+                            ImageSubresourceRange::default()
+                                .aspect_mask(aspect)
+                                .level_count(1)
+                                .layer_count(1),
+                        ),
+                    host_allocation_callbacks(),
+                )
+            })
+            .context("creating picking attachment view");
+
+        match view {
+            Ok(view) => attachment.view = view,
+            Err(e) => {
+                attachment.destroy(logical_device);
+                return Err(e);
+            }
+        }
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::ImageView, 1, 0);
+
+        Ok(attachment)
+    }
+
+    fn destroy(&self, logical_device: &LogicalDevice) {
+        let device = logical_device.device();
+        let tracker = logical_device.resource_tracker();
+
+        unsafe {
+            if self.view != vk::ImageView::null() {
+                device.destroy_image_view(self.view, host_allocation_callbacks());
+                tracker.untrack(ResourceKind::ImageView, 1, 0);
+            }
+
+            device.destroy_image(self.image, host_allocation_callbacks());
+            device.free_memory(self.memory, host_allocation_callbacks());
+        }
+
+        tracker.untrack(ResourceKind::Image, 1, self.size);
+    }
+}
+
+/// The ID and depth attachments and their framebuffer, recreated on resize.
+struct PickingTarget {
+    id: Attachment,
+    depth: Attachment,
+    framebuffer: vk::Framebuffer,
+    extent: Extent2D,
+
+    logical_device: LogicalDevice,
+}
+
+impl PickingTarget {
+    fn new(
+        logical_device: &LogicalDevice,
+        render_pass: vk::RenderPass,
+        depth_format: Format,
+        extent: Extent2D,
+    ) -> Result<Self, ErrorCtx> {
+        let id = Attachment::new(
+            logical_device,
+            ID_FORMAT,
+            ImageAspectFlags::COLOR,
+            ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSFER_SRC,
+            extent,
+        )?;
+
+        let depth = match Attachment::new(
+            logical_device,
+            depth_format,
+            ImageAspectFlags::DEPTH,
+            ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            extent,
+        ) {
+            Ok(depth) => depth,
+            Err(e) => {
+                id.destroy(logical_device);
+                return Err(e);
+            }
+        };
+
+        let mut target = Self {
+            id,
+            depth,
+            framebuffer: vk::Framebuffer::null(),
+            extent,
+            logical_device: logical_device.clone(),
+        };
+
+        let attachments = [target.id.view, target.depth.view];
+
+        target.framebuffer = unsafe {
+            logical_device.device().create_framebuffer(
+                &FramebufferCreateInfo::default()
+                    .render_pass(render_pass)
+                    .attachments(&attachments)
+                    .width(extent.width)
+                    .height(extent.height)
+                    .layers(1),
+                host_allocation_callbacks(),
+            )
+        }
+        .with_context("creating picking framebuffer", || {
+            format!("extent={:?}", extent)
+        })?;
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::Framebuffer, 1, 0);
+
+        Ok(target)
+    }
+
+    /// Destroys everything, leaving the handles null so destroying again does nothing.
+    fn destroy(&mut self) {
+        if self.id.image == vk::Image::null() {
+            return;
+        }
+
+        teardown_trace::record(
+            "PickingTarget",
+            [self.framebuffer.as_raw(), self.id.image.as_raw()],
+        );
+
+        let tracker = self.logical_device.resource_tracker();
+
+        if self.framebuffer != vk::Framebuffer::null() {
+            unsafe {
+                self.logical_device
+                    .device()
+                    .destroy_framebuffer(self.framebuffer, host_allocation_callbacks())
+            };
+            tracker.untrack(ResourceKind::Framebuffer, 1, 0);
+            self.framebuffer = vk::Framebuffer::null();
+        }
+
+        for attachment in [&mut self.id, &mut self.depth] {
+            attachment.destroy(&self.logical_device);
+            attachment.image = vk::Image::null();
+            attachment.view = vk::ImageView::null();
+        }
+    }
+}
+
+impl Drop for PickingTarget {
+    fn drop(&mut self) {
+        self.destroy();
+    }
+}
+
+/// A host-visible buffer receiving one pixel of the ID attachment.
+struct Readback {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    mapped: *const u32,
+    size: vk::DeviceSize,
+    /// The pixel the pending copy reads, if any.
+    position: Option<(u32, u32)>,
+
+    logical_device: LogicalDevice,
+}
+
+impl Readback {
+    fn new(logical_device: &LogicalDevice) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+
+        let buffer = unsafe {
+            device.create_buffer(
+                &BufferCreateInfo::default()
+                    .size(mem::size_of::<u32>() as u64)
+                    .usage(BufferUsageFlags::TRANSFER_DST)
+                    .sharing_mode(SharingMode::EXCLUSIVE),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating picking readback buffer")?;
+
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+
+        let mut readback = Self {
+            buffer,
+            memory: vk::DeviceMemory::null(),
+            mapped: std::ptr::null(),
+            size: requirements.size,
+            position: None,
+            logical_device: logical_device.clone(),
+        };
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::Buffer, 1, requirements.size);
+
+        let memory_type_index = logical_device
+            .physical_device()
+            .find_memory_type(
+                requirements.memory_type_bits,
+                MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+            )
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            .with_context("finding picking readback memory", || {
+                format!("memory_type_bits={:#x}", requirements.memory_type_bits)
+            })?;
+
+        readback.memory = unsafe {
+            device.allocate_memory(
+                &MemoryAllocateInfo::default()
+                    .allocation_size(requirements.size)
+                    .memory_type_index(memory_type_index),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("allocating picking readback memory")?;
+
+        readback.mapped = unsafe {
+            device
+                .bind_buffer_memory(buffer, readback.memory, 0)
+                .context("binding picking readback memory")?;
+
+            device
+                .map_memory(readback.memory, 0, WHOLE_SIZE, MemoryMapFlags::empty())
+                .context("mapping picking readback memory")?
+                .cast()
+        };
+
+        Ok(readback)
+    }
+}
+
+impl Drop for Readback {
+    fn drop(&mut self) {
+        let device = self.logical_device.device();
+
+        unsafe {
+            if !self.mapped.is_null() {
+                device.unmap_memory(self.memory);
+            }
+
+            device.destroy_buffer(self.buffer, host_allocation_callbacks());
+            device.free_memory(self.memory, host_allocation_callbacks());
+        }
+
+        self.logical_device
+            .resource_tracker()
+            .untrack(ResourceKind::Buffer, 1, self.size);
+    }
+}
@@ -0,0 +1,273 @@
+//! Timing and cache counters for pipeline creation, to see what pipeline compilation costs and
+//! how much caching saves.
+
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use ash::{
+    prelude::VkResult,
+    vk::{
+        self, ComputePipelineCreateInfo, GraphicsPipelineCreateInfo, Handle, Pipeline,
+        PipelineCache, PipelineCreationFeedback, PipelineCreationFeedbackCreateInfo,
+        PipelineCreationFeedbackFlags,
+    },
+};
+
+use crate::{api2::host_allocation_callbacks, renderer::logical_device::LogicalDevice};
+
+#[derive(Clone, Default)]
+pub struct PipelineStatsTracker(Rc<RefCell<PipelineStats>>);
+
+impl PipelineStatsTracker {
+    /// Records one successful create call that made a pipeline per entry of `feedbacks`.
+    pub fn record(
+        &self,
+        kind: PipelineKind,
+        elapsed: Duration,
+        feedbacks: &[PipelineCreationFeedback],
+    ) {
+        let mut stats = self.0.borrow_mut();
+        let entry = stats.entry_mut(kind);
+
+        entry.pipelines += feedbacks.len() as u64;
+        entry.calls += 1;
+        entry.total_time += elapsed;
+        entry.slowest_call = entry.slowest_call.max(elapsed);
+
+        for feedback in feedbacks.iter().filter(|feedback| {
+            feedback
+                .flags
+                .contains(PipelineCreationFeedbackFlags::VALID)
+        }) {
+            entry.with_feedback += 1;
+            entry.driver_time += Duration::from_nanos(feedback.duration);
+
+            if feedback
+                .flags
+                .contains(PipelineCreationFeedbackFlags::APPLICATION_PIPELINE_CACHE_HIT)
+            {
+                entry.cache_hits += 1;
+            } else {
+                entry.cache_misses += 1;
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> PipelineStats {
+        *self.0.borrow()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PipelineKind {
+    Graphics,
+    Compute,
+    /// Parts of graphics pipelines created with `VK_EXT_graphics_pipeline_library`, which
+    /// graphics pipelines are then linked from.
+    Library,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PipelineCreationStats {
+    pub pipelines: u64,
+    /// Create calls, each possibly making several pipelines.
+    pub calls: u64,
+    /// Wall-clock time spent in create calls.
+    pub total_time: Duration,
+    pub slowest_call: Duration,
+    /// Pipelines the driver reported creation feedback for, which needs
+    /// `VK_EXT_pipeline_creation_feedback`. The fields below only count these.
+    pub with_feedback: u64,
+    /// Creation time as reported by the driver.
+    pub driver_time: Duration,
+    /// Pipelines found in the pipeline cache passed at creation. Without a cache every pipeline
+    /// is a miss.
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+}
+
+impl PipelineCreationStats {
+    pub fn average_time(&self) -> Duration {
+        if self.pipelines == 0 {
+            Duration::ZERO
+        } else {
+            self.total_time / self.pipelines as u32
+        }
+    }
+
+    /// The fraction of pipelines with feedback that hit the cache, `None` without feedback.
+    pub fn hit_rate(&self) -> Option<f64> {
+        (self.with_feedback > 0).then(|| self.cache_hits as f64 / self.with_feedback as f64)
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PipelineStats {
+    pub graphics: PipelineCreationStats,
+    pub compute: PipelineCreationStats,
+    pub library: PipelineCreationStats,
+}
+
+impl PipelineStats {
+    pub fn get(&self, kind: PipelineKind) -> PipelineCreationStats {
+        match kind {
+            PipelineKind::Graphics => self.graphics,
+            PipelineKind::Compute => self.compute,
+            PipelineKind::Library => self.library,
+        }
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.graphics.total_time + self.compute.total_time + self.library.total_time
+    }
+
+    fn entry_mut(&mut self, kind: PipelineKind) -> &mut PipelineCreationStats {
+        match kind {
+            PipelineKind::Graphics => &mut self.graphics,
+            PipelineKind::Compute => &mut self.compute,
+            PipelineKind::Library => &mut self.library,
+        }
+    }
+}
+
+/// Creates graphics pipelines, recording the time taken and, when the device supports it, the
+/// driver's creation feedback in the device's [PipelineStatsTracker].
+///
+/// # Safety
+///
+/// The same as `vkCreateGraphicsPipelines`. `infos` must not already chain a
+/// `VkPipelineCreationFeedbackCreateInfo`.
+pub unsafe fn create_graphics_pipelines(
+    logical_device: &LogicalDevice,
+    cache: PipelineCache,
+    infos: &[GraphicsPipelineCreateInfo],
+) -> VkResult<Vec<Pipeline>> {
+    create_graphics(logical_device, PipelineKind::Graphics, cache, infos)
+}
+
+/// Creates pipeline libraries, recorded as [PipelineKind::Library] apart from the graphics
+/// pipelines linked from them.
+///
+/// # Safety
+///
+/// The same as [create_graphics_pipelines], and every info has to create a library.
+pub unsafe fn create_pipeline_libraries(
+    logical_device: &LogicalDevice,
+    cache: PipelineCache,
+    infos: &[GraphicsPipelineCreateInfo],
+) -> VkResult<Vec<Pipeline>> {
+    create_graphics(logical_device, PipelineKind::Library, cache, infos)
+}
+
+unsafe fn create_graphics(
+    logical_device: &LogicalDevice,
+    kind: PipelineKind,
+    cache: PipelineCache,
+    infos: &[GraphicsPipelineCreateInfo],
+) -> VkResult<Vec<Pipeline>> {
+    let mut feedbacks = vec![PipelineCreationFeedback::default(); infos.len()];
+    let mut feedback_infos = feedback_infos(&mut feedbacks);
+    let start = Instant::now();
+
+    let result = if logical_device.supports_creation_feedback() {
+        let infos: Vec<_> = infos
+            .iter()
+            .zip(&mut feedback_infos)
+            .map(|(info, feedback_info)| info.push_next(feedback_info))
+            .collect();
+
+        logical_device.device().create_graphics_pipelines(
+            cache,
+            &infos,
+            host_allocation_callbacks(),
+        )
+    } else {
+        logical_device
+            .device()
+            .create_graphics_pipelines(cache, infos, host_allocation_callbacks())
+    };
+
+    let elapsed = start.elapsed();
+    let pipelines = destroy_on_error(logical_device, result)?;
+
+    logical_device
+        .pipeline_tracker()
+        .record(kind, elapsed, &feedbacks);
+
+    Ok(pipelines)
+}
+
+/// The compute counterpart of [create_graphics_pipelines].
+///
+/// # Safety
+///
+/// The same as `vkCreateComputePipelines`. `infos` must not already chain a
+/// `VkPipelineCreationFeedbackCreateInfo`.
+pub unsafe fn create_compute_pipelines(
+    logical_device: &LogicalDevice,
+    cache: PipelineCache,
+    infos: &[ComputePipelineCreateInfo],
+) -> VkResult<Vec<Pipeline>> {
+    let mut feedbacks = vec![PipelineCreationFeedback::default(); infos.len()];
+    let mut feedback_infos = feedback_infos(&mut feedbacks);
+    let start = Instant::now();
+
+    let result = if logical_device.supports_creation_feedback() {
+        let infos: Vec<_> = infos
+            .iter()
+            .zip(&mut feedback_infos)
+            .map(|(info, feedback_info)| info.push_next(feedback_info))
+            .collect();
+
+        logical_device
+            .device()
+            .create_compute_pipelines(cache, &infos, host_allocation_callbacks())
+    } else {
+        logical_device
+            .device()
+            .create_compute_pipelines(cache, infos, host_allocation_callbacks())
+    };
+
+    let elapsed = start.elapsed();
+    let pipelines = destroy_on_error(logical_device, result)?;
+
+    logical_device
+        .pipeline_tracker()
+        .record(PipelineKind::Compute, elapsed, &feedbacks);
+
+    Ok(pipelines)
+}
+
+/// The pipelines of a successful create call. A failed call may still have created some, which
+/// are destroyed before returning the error.
+fn destroy_on_error(
+    logical_device: &LogicalDevice,
+    result: Result<Vec<Pipeline>, (Vec<Pipeline>, vk::Result)>,
+) -> VkResult<Vec<Pipeline>> {
+    result.map_err(|(pipelines, e)| {
+        for pipeline in pipelines.into_iter().filter(|pipeline| !pipeline.is_null()) {
+            unsafe {
+                logical_device
+                    .device()
+                    .destroy_pipeline(pipeline, host_allocation_callbacks())
+            };
+        }
+
+        e
+    })
+}
+
+// Only whole-pipeline feedback is requested, per-stage feedback is optional.
+fn feedback_infos(
+    feedbacks: &mut [PipelineCreationFeedback],
+) -> Vec<PipelineCreationFeedbackCreateInfo<'_>> {
+    feedbacks
+        .iter_mut()
+        .map(|feedback| {
+            PipelineCreationFeedbackCreateInfo::default().pipeline_creation_feedback(feedback)
+        })
+        .collect()
+}
@@ -0,0 +1,106 @@
+//! What each present mode achieves on this machine, measured by rendering with it for a while,
+//! since the best mode depends on the driver and compositor more than on the spec.
+
+use std::{fmt, time::Duration};
+
+use ash::vk::PresentModeKHR;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PresentModeResult {
+    pub present_mode: PresentModeKHR,
+    pub frames: u32,
+    pub fps: f64,
+    pub mean_frame_time: Duration,
+    /// The variance of the frame times in ms², how much frame pacing jitters.
+    pub frame_time_variance: f64,
+    /// From recording a frame to it reaching the display, estimated as the frames queued ahead
+    /// of it times the mean frame time, since measuring it needs presentation timing extensions.
+    pub estimated_latency: Duration,
+}
+
+impl PresentModeResult {
+    /// Computes the statistics of the frame times measured for `present_mode`, where up to
+    /// `queued_frames` frames can wait in line to be displayed.
+    pub fn new(present_mode: PresentModeKHR, frame_times: &[Duration], queued_frames: u32) -> Self {
+        let frames = frame_times.len() as u32;
+        let total: Duration = frame_times.iter().sum();
+        let mean_frame_time = total.checked_div(frames).unwrap_or_default();
+
+        let mean_ms = mean_frame_time.as_secs_f64() * 1000.0;
+        let frame_time_variance = frame_times
+            .iter()
+            .map(|time| (time.as_secs_f64() * 1000.0 - mean_ms).powi(2))
+            .sum::<f64>()
+            / frames.max(1) as f64;
+
+        Self {
+            present_mode,
+            frames,
+            fps: if total.is_zero() {
+                0.0
+            } else {
+                frames as f64 / total.as_secs_f64()
+            },
+            mean_frame_time,
+            frame_time_variance,
+            estimated_latency: mean_frame_time * queued_frames_behind(present_mode, queued_frames),
+        }
+    }
+}
+
+/// The frames a new frame waits behind, itself included. FIFO modes display every queued frame
+/// in turn, MAILBOX replaces the queued frame and IMMEDIATE doesn't queue at all.
+fn queued_frames_behind(present_mode: PresentModeKHR, queued_frames: u32) -> u32 {
+    match present_mode {
+        PresentModeKHR::FIFO | PresentModeKHR::FIFO_RELAXED => queued_frames.max(1) + 1,
+        _ => 1,
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PresentModeReport {
+    pub results: Vec<PresentModeResult>,
+}
+
+impl PresentModeReport {
+    pub fn fastest(&self) -> Option<&PresentModeResult> {
+        self.results.iter().max_by(|a, b| a.fps.total_cmp(&b.fps))
+    }
+
+    pub fn lowest_latency(&self) -> Option<&PresentModeResult> {
+        self.results
+            .iter()
+            .min_by_key(|result| result.estimated_latency)
+    }
+
+    /// The mode with the most even frame pacing.
+    pub fn steadiest(&self) -> Option<&PresentModeResult> {
+        self.results
+            .iter()
+            .min_by(|a, b| a.frame_time_variance.total_cmp(&b.frame_time_variance))
+    }
+}
+
+impl fmt::Display for PresentModeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<14} {:>7} {:>9} {:>12} {:>13}",
+            "present mode", "frames", "fps", "variance", "est. latency"
+        )?;
+
+        for result in &self.results {
+            writeln!(
+                f,
+                "{:<14} {:>7} {:>9.1} {:>9.3}ms² {:>11.2}ms",
+                format!("{:?}", result.present_mode),
+                result.frames,
+                result.fps,
+                result.frame_time_variance,
+                result.estimated_latency.as_secs_f64() * 1000.0
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+//! Hands swapchain images from the graphics to the present family when the swapchain is
+//! exclusive to the graphics family, see [crate::renderer::swapchain::SwapchainSharing::Exclusive].
+//!
+//! The graphics queue releases an image at the end of its frame with [release_barrier], then a
+//! command buffer on the present queue acquires it before presenting. Nothing has to be handed
+//! back after acquiring an image from the swapchain: the render pass starts from `UNDEFINED`,
+//! discarding the contents, and contents that are discarded don't need an ownership transfer.
+
+use std::rc::Rc;
+
+use ash::{
+    prelude::VkResult,
+    vk::{
+        AccessFlags, CommandBuffer, CommandBufferAllocateInfo, CommandBufferBeginInfo,
+        CommandBufferLevel, CommandBufferUsageFlags, CommandPool, CommandPoolCreateInfo,
+        DependencyFlags, Fence, Handle, Image, ImageAspectFlags, ImageLayout, ImageMemoryBarrier,
+        ImageSubresourceRange, PipelineStageFlags, Semaphore, SemaphoreCreateInfo, SubmitInfo,
+        REMAINING_ARRAY_LAYERS,
+    },
+};
+
+use crate::{
+    api2::{host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        swapchain::{QueueFamilyTransfer, Swapchain},
+        teardown_trace,
+    },
+};
+
+#[derive(Clone)]
+pub struct PresentTransfer(Rc<InnerPresentTransfer>);
+
+impl PresentTransfer {
+    /// Prepares the transfers for every image of `swapchain`, or returns `None` if it doesn't
+    /// need any.
+    pub fn new(swapchain: Swapchain) -> Result<Option<Self>, ErrorCtx> {
+        let Some(transfer) = swapchain.ownership_transfer() else {
+            return Ok(None);
+        };
+
+        let logical_device = swapchain.device();
+        let device = logical_device.device();
+        let images = swapchain.images();
+
+        let command_pool = unsafe {
+            device.create_command_pool(
+                &CommandPoolCreateInfo::default().queue_family_index(transfer.present_family),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating present family command pool")?;
+
+        let mut present_transfer = InnerPresentTransfer {
+            swapchain: swapchain.clone(),
+            command_pool,
+            command_buffers: Vec::new(),
+            semaphores: Vec::new(),
+        };
+
+        present_transfer.command_buffers = unsafe {
+            device.allocate_command_buffers(
+                &CommandBufferAllocateInfo::default()
+                    .command_pool(command_pool)
+                    .level(CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(images.len() as u32),
+            )
+        }
+        .context("allocating present transfer command buffers")?;
+
+        for (command_buffer, image) in present_transfer.command_buffers.iter().zip(images) {
+            // The same image only comes back after its previous present, which waited for this
+            // command buffer, but the validation layers can't see that without a fence.
+            let begin_info =
+                CommandBufferBeginInfo::default().flags(CommandBufferUsageFlags::SIMULTANEOUS_USE);
+
+            unsafe {
+                device
+                    .begin_command_buffer(*command_buffer, &begin_info)
+                    .context("beginning present transfer command buffer")?;
+
+                device.cmd_pipeline_barrier(
+                    *command_buffer,
+                    PipelineStageFlags::ALL_COMMANDS,
+                    PipelineStageFlags::BOTTOM_OF_PIPE,
+                    DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[acquire_barrier(image.image(), transfer)],
+                );
+
+                device
+                    .end_command_buffer(*command_buffer)
+                    .context("ending present transfer command buffer")?;
+            }
+        }
+
+        for _ in images {
+            let semaphore = unsafe {
+                device
+                    .create_semaphore(&SemaphoreCreateInfo::default(), host_allocation_callbacks())
+            }
+            .context("creating present transfer semaphore")?;
+
+            present_transfer.semaphores.push(semaphore);
+        }
+
+        Ok(Some(Self(Rc::new(present_transfer))))
+    }
+
+    /// Acquires the image on the present queue once `wait_semaphore`, signaled by the graphics
+    /// submission that released it, is signaled. Returns the semaphore to wait on when presenting.
+    pub fn submit(&self, image_index: u32, wait_semaphore: Semaphore) -> VkResult<Semaphore> {
+        let logical_device = self.0.swapchain.device();
+
+        let wait_semaphores = [wait_semaphore];
+        let wait_stages = [PipelineStageFlags::ALL_COMMANDS];
+        let command_buffers = [self.0.command_buffers[image_index as usize]];
+        let signal_semaphores = [self.0.semaphores[image_index as usize]];
+
+        let submit_info = SubmitInfo::default()
+            .wait_semaphores(&wait_semaphores)
+            .wait_dst_stage_mask(&wait_stages)
+            .command_buffers(&command_buffers)
+            .signal_semaphores(&signal_semaphores);
+
+        unsafe {
+            logical_device.device().queue_submit(
+                *logical_device.present_queue(),
+                &[submit_info],
+                Fence::null(),
+            )?;
+        }
+
+        Ok(signal_semaphores[0])
+    }
+}
+
+/// The barrier releasing `image` from the graphics family, recorded on the graphics queue after
+/// the render pass left it in `PRESENT_SRC_KHR`.
+pub fn release_barrier(image: Image, transfer: QueueFamilyTransfer) -> ImageMemoryBarrier<'static> {
+    ownership_barrier(image, transfer)
+        .src_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE)
+        .dst_access_mask(AccessFlags::empty())
+}
+
+fn acquire_barrier(image: Image, transfer: QueueFamilyTransfer) -> ImageMemoryBarrier<'static> {
+    ownership_barrier(image, transfer)
+        .src_access_mask(AccessFlags::empty())
+        .dst_access_mask(AccessFlags::empty())
+}
+
+// The release and acquire have to match apart from their access masks. Every layer moves, so
+// stereo swapchains are handed over whole.
+fn ownership_barrier(image: Image, transfer: QueueFamilyTransfer) -> ImageMemoryBarrier<'static> {
+    ImageMemoryBarrier::default()
+        .old_layout(ImageLayout::PRESENT_SRC_KHR)
+        .new_layout(ImageLayout::PRESENT_SRC_KHR)
+        .src_queue_family_index(transfer.graphics_family)
+        .dst_queue_family_index(transfer.present_family)
+        .image(image)
+        .subresource_range(
+            ImageSubresourceRange::default()
+                .aspect_mask(ImageAspectFlags::COLOR)
+                .level_count(1)
+                .layer_count(REMAINING_ARRAY_LAYERS),
+        )
+}
+
+struct InnerPresentTransfer {
+    swapchain: Swapchain,
+    command_pool: CommandPool,
+    command_buffers: Vec<CommandBuffer>,
+    semaphores: Vec<Semaphore>,
+}
+
+impl Drop for InnerPresentTransfer {
+    fn drop(&mut self) {
+        teardown_trace::record(
+            "PresentTransfer",
+            self.semaphores
+                .iter()
+                .map(|handle| handle.as_raw())
+                .chain([self.command_pool.as_raw()]),
+        );
+
+        let device = self.swapchain.device().device();
+
+        unsafe {
+            for semaphore in &self.semaphores {
+                device.destroy_semaphore(*semaphore, host_allocation_callbacks());
+            }
+
+            device.destroy_command_pool(self.command_pool, host_allocation_callbacks());
+        }
+    }
+}
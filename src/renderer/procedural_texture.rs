@@ -0,0 +1,769 @@
+//! Textures generated on the GPU by a compute shader, like noise, gradients and checkerboards,
+//! without any pixel data on the CPU.
+//!
+//! The shader writes every texel of binding 0, a `writeonly image2D` of the texture's storage
+//! format, from invocations of 8x8 work groups over the texture's first mip. Its parameters are
+//! push constants of up to [MAX_PARAMS_SIZE] bytes. `shaders/procedural_texture.glsl` declares
+//! both.
+
+use std::{error, fmt, rc::Rc};
+
+use ash::vk::{
+    self, AccessFlags, CommandBuffer, CommandBufferAllocateInfo, CommandBufferBeginInfo,
+    CommandBufferLevel, CommandBufferUsageFlags, ComputePipelineCreateInfo, DependencyFlags,
+    DescriptorImageInfo, DescriptorPoolCreateInfo, DescriptorPoolResetFlags, DescriptorPoolSize,
+    DescriptorSetAllocateInfo, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo,
+    DescriptorType, FenceCreateInfo, Filter, Format, FormatFeatureFlags, Handle, ImageBlit,
+    ImageCreateFlags, ImageCreateInfo, ImageLayout, ImageMemoryBarrier, ImageTiling, ImageType,
+    ImageUsageFlags, ImageViewCreateInfo, ImageViewType, MemoryAllocateInfo, MemoryPropertyFlags,
+    PipelineBindPoint, PipelineCache, PipelineLayoutCreateInfo, PipelineShaderStageCreateInfo,
+    PipelineStageFlags, PushConstantRange, SampleCountFlags, SamplerAddressMode, ShaderStageFlags,
+    SharingMode, SubmitInfo, WriteDescriptorSet,
+};
+
+use crate::{
+    api2::{div_round_up, host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        command_pool::CommandPool,
+        image_desc::{mip_level_count, ImageDesc},
+        logical_device::LogicalDevice,
+        pipeline_stats,
+        resource_stats::ResourceKind,
+        sampler::{Sampler, SamplerBuilder, SamplerCache},
+        shader_cache::ShaderCache,
+        teardown_trace,
+    },
+};
+
+/// The work group size of the shaders along X and Y, matches `local_size_x` and `local_size_y`.
+const GROUP_SIZE: u32 = 8;
+
+/// The most push constant bytes a generator gets, the smallest limit Vulkan allows.
+pub const MAX_PARAMS_SIZE: usize = 128;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ProceduralTextureDesc {
+    pub width: u32,
+    pub height: u32,
+    /// The format the texture is sampled as. sRGB formats are written through a view of their
+    /// UNORM counterpart, since devices can't store to them, which needs Vulkan 1.1.
+    pub format: Format,
+    /// Mips below the first are blitted from it after the shader ran.
+    pub mip_levels: u32,
+    /// How the texture repeats past its edges when sampled.
+    pub address_mode: SamplerAddressMode,
+}
+
+impl ProceduralTextureDesc {
+    /// A `width` by `height` texture of `format` with a full mip chain that tiles.
+    pub fn new(width: u32, height: u32, format: Format) -> Self {
+        Self {
+            width,
+            height,
+            format,
+            mip_levels: mip_level_count(width, height),
+            address_mode: SamplerAddressMode::REPEAT,
+        }
+    }
+
+    pub fn mip_levels(mut self, mip_levels: u32) -> Self {
+        self.mip_levels = mip_levels.max(1);
+        self
+    }
+
+    pub fn address_mode(mut self, address_mode: SamplerAddressMode) -> Self {
+        self.address_mode = address_mode;
+        self
+    }
+
+    /// The format the shader writes.
+    fn storage_format(&self) -> Format {
+        match self.format {
+            Format::R8G8B8A8_SRGB => Format::R8G8B8A8_UNORM,
+            Format::B8G8R8A8_SRGB => Format::B8G8R8A8_UNORM,
+            Format::R8_SRGB => Format::R8_UNORM,
+            Format::R8G8_SRGB => Format::R8G8_UNORM,
+            format => format,
+        }
+    }
+
+    /// The image the texture is stored in, its mips clamped to the full chain.
+    fn image(&self) -> ImageDesc {
+        ImageDesc::new(self.width, self.height).mip_levels(self.mip_levels)
+    }
+}
+
+/// Runs generator shaders, reusing one command buffer, descriptor pool and fence for every
+/// texture.
+pub struct ProceduralTextureGenerator {
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    pipeline_layout: vk::PipelineLayout,
+    command_pool: vk::CommandPool,
+    command_buffer: CommandBuffer,
+    fence: vk::Fence,
+    shader_cache: ShaderCache,
+    sampler_cache: SamplerCache,
+
+    logical_device: LogicalDevice,
+}
+
+impl ProceduralTextureGenerator {
+    pub fn new(
+        logical_device: LogicalDevice,
+        command_pool: &CommandPool,
+        shader_cache: ShaderCache,
+        sampler_cache: SamplerCache,
+    ) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+
+        let mut generator = Self {
+            descriptor_set_layout: vk::DescriptorSetLayout::null(),
+            descriptor_pool: vk::DescriptorPool::null(),
+            pipeline_layout: vk::PipelineLayout::null(),
+            command_pool: *command_pool.command_pool(),
+            command_buffer: CommandBuffer::null(),
+            fence: vk::Fence::null(),
+            shader_cache,
+            sampler_cache,
+            logical_device: logical_device.clone(),
+        };
+
+        let bindings = [DescriptorSetLayoutBinding::default()
+            .binding(0)
+            .descriptor_type(DescriptorType::STORAGE_IMAGE)
+            .descriptor_count(1)
+            .stage_flags(ShaderStageFlags::COMPUTE)];
+
+        generator.descriptor_set_layout = unsafe {
+            device.create_descriptor_set_layout(
+                &DescriptorSetLayoutCreateInfo::default().bindings(&bindings),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating procedural texture descriptor set layout")?;
+
+        let pool_sizes = [DescriptorPoolSize::default()
+            .ty(DescriptorType::STORAGE_IMAGE)
+            .descriptor_count(1)];
+
+        generator.descriptor_pool = unsafe {
+            device.create_descriptor_pool(
+                &DescriptorPoolCreateInfo::default()
+                    .max_sets(1)
+                    .pool_sizes(&pool_sizes),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating procedural texture descriptor pool")?;
+
+        let set_layouts = [generator.descriptor_set_layout];
+        let push_constant_ranges = [PushConstantRange::default()
+            .stage_flags(ShaderStageFlags::COMPUTE)
+            .size(MAX_PARAMS_SIZE as u32)];
+
+        generator.pipeline_layout = unsafe {
+            device.create_pipeline_layout(
+                &PipelineLayoutCreateInfo::default()
+                    .set_layouts(&set_layouts)
+                    .push_constant_ranges(&push_constant_ranges),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating procedural texture pipeline layout")?;
+
+        generator.command_buffer = unsafe {
+            device.allocate_command_buffers(
+                &CommandBufferAllocateInfo::default()
+                    .command_pool(generator.command_pool)
+                    .level(CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(1),
+            )
+        }
+        .context("allocating procedural texture command buffer")?[0];
+
+        generator.fence = unsafe {
+            device.create_fence(&FenceCreateInfo::default(), host_allocation_callbacks())
+        }
+        .context("creating procedural texture fence")?;
+
+        Ok(generator)
+    }
+
+    /// Creates a device-local texture described by `desc` and fills it by running `shader`, the
+    /// SPIR-V of a generator, with `params` as push constants. Waits for the GPU before
+    /// returning, so the texture can be used right away.
+    pub fn generate(
+        &self,
+        shader: &[u32],
+        desc: ProceduralTextureDesc,
+        params: &[u8],
+    ) -> Result<ProceduralTexture, ProceduralTextureError> {
+        if params.len() > MAX_PARAMS_SIZE {
+            return Err(ProceduralTextureError::ParamsTooLarge { size: params.len() });
+        }
+
+        let mip_levels = desc.image().mip_levels;
+        let desc = ProceduralTextureDesc { mip_levels, ..desc };
+
+        let mut sampled_features = FormatFeatureFlags::SAMPLED_IMAGE;
+        // Copying from the texture lets it be saved, see `bake`.
+        let mut usage =
+            ImageUsageFlags::STORAGE | ImageUsageFlags::SAMPLED | ImageUsageFlags::TRANSFER_SRC;
+
+        if mip_levels > 1 {
+            sampled_features |= FormatFeatureFlags::BLIT_SRC
+                | FormatFeatureFlags::BLIT_DST
+                | FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR;
+            usage |= ImageUsageFlags::TRANSFER_DST;
+        }
+
+        self.check_format(desc.storage_format(), FormatFeatureFlags::STORAGE_IMAGE)?;
+        self.check_format(desc.format, sampled_features)?;
+
+        let image = TextureImage::new(self.logical_device.clone(), desc, usage)?;
+
+        let sampler = self
+            .sampler_cache
+            .get_or_create(
+                SamplerBuilder::default()
+                    .address_mode(desc.address_mode)
+                    .config,
+            )
+            .context("creating procedural texture sampler")?;
+
+        let shader_module = self
+            .shader_cache
+            .get_or_create(shader)
+            .context("creating procedural texture shader module")?;
+
+        let pipeline = unsafe {
+            pipeline_stats::create_compute_pipelines(
+                &self.logical_device,
+                PipelineCache::null(),
+                &[ComputePipelineCreateInfo::default()
+                    .stage(
+                        PipelineShaderStageCreateInfo::default()
+                            .stage(ShaderStageFlags::COMPUTE)
+                            .module(*shader_module.shader_module())
+                            .name(c"main"),
+                    )
+                    .layout(self.pipeline_layout)],
+            )
+        }
+        .context("creating procedural texture pipeline")?[0];
+
+        self.logical_device
+            .resource_tracker()
+            .track(ResourceKind::Pipeline, 1, 0);
+
+        let result = self.run(pipeline, &image, params);
+
+        unsafe {
+            self.logical_device
+                .device()
+                .destroy_pipeline(pipeline, host_allocation_callbacks())
+        };
+        self.logical_device
+            .resource_tracker()
+            .untrack(ResourceKind::Pipeline, 1, 0);
+
+        result?;
+
+        Ok(ProceduralTexture(Rc::new(InnerProceduralTexture {
+            image,
+            sampler,
+        })))
+    }
+
+    fn check_format(
+        &self,
+        format: Format,
+        required: FormatFeatureFlags,
+    ) -> Result<(), ProceduralTextureError> {
+        let physical_device = self.logical_device.physical_device();
+        let properties = unsafe {
+            physical_device
+                .instance()
+                .instance()
+                .get_physical_device_format_properties(*physical_device.device(), format)
+        };
+
+        if properties.optimal_tiling_features.contains(required) {
+            Ok(())
+        } else {
+            Err(ProceduralTextureError::UnsupportedFormat { format, required })
+        }
+    }
+
+    /// Records, submits and waits for the dispatch and the mip chain.
+    fn run(
+        &self,
+        pipeline: vk::Pipeline,
+        image: &TextureImage,
+        params: &[u8],
+    ) -> Result<(), ErrorCtx> {
+        let device = self.logical_device.device();
+        let desc = &image.desc;
+        let command_buffer = self.command_buffer;
+
+        let set_layouts = [self.descriptor_set_layout];
+        let descriptor_set = unsafe {
+            device.allocate_descriptor_sets(
+                &DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(self.descriptor_pool)
+                    .set_layouts(&set_layouts),
+            )
+        }
+        .context("allocating procedural texture descriptor set")?[0];
+
+        let image_info = [DescriptorImageInfo::default()
+            .image_view(image.storage_view)
+            .image_layout(ImageLayout::GENERAL)];
+
+        // Push constants the shader declares beyond `params` are left zeroed.
+        let mut push_constants = [0u8; MAX_PARAMS_SIZE];
+        push_constants[..params.len()].copy_from_slice(params);
+
+        unsafe {
+            device.update_descriptor_sets(
+                &[WriteDescriptorSet::default()
+                    .dst_set(descriptor_set)
+                    .dst_binding(0)
+                    .descriptor_type(DescriptorType::STORAGE_IMAGE)
+                    .image_info(&image_info)],
+                &[],
+            );
+
+            device
+                .begin_command_buffer(
+                    command_buffer,
+                    &CommandBufferBeginInfo::default()
+                        .flags(CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+                )
+                .context("beginning procedural texture command buffer")?;
+
+            image.barrier(
+                command_buffer,
+                (ImageLayout::UNDEFINED, ImageLayout::GENERAL),
+                (AccessFlags::empty(), AccessFlags::SHADER_WRITE),
+                (
+                    PipelineStageFlags::TOP_OF_PIPE,
+                    PipelineStageFlags::COMPUTE_SHADER,
+                ),
+                0..1,
+            );
+
+            device.cmd_bind_pipeline(command_buffer, PipelineBindPoint::COMPUTE, pipeline);
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                PipelineBindPoint::COMPUTE,
+                self.pipeline_layout,
+                0,
+                &[descriptor_set],
+                &[],
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.pipeline_layout,
+                ShaderStageFlags::COMPUTE,
+                0,
+                &push_constants,
+            );
+            device.cmd_dispatch(
+                command_buffer,
+                div_round_up(desc.width, GROUP_SIZE),
+                div_round_up(desc.height, GROUP_SIZE),
+                1,
+            );
+
+            if desc.mip_levels == 1 {
+                image.barrier(
+                    command_buffer,
+                    (ImageLayout::GENERAL, ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+                    (AccessFlags::SHADER_WRITE, AccessFlags::SHADER_READ),
+                    (
+                        PipelineStageFlags::COMPUTE_SHADER,
+                        PipelineStageFlags::FRAGMENT_SHADER,
+                    ),
+                    0..1,
+                );
+            } else {
+                self.record_mip_chain(command_buffer, image);
+            }
+
+            device
+                .end_command_buffer(command_buffer)
+                .context("ending procedural texture command buffer")?;
+
+            let command_buffers = [command_buffer];
+
+            device
+                .reset_fences(&[self.fence])
+                .context("resetting procedural texture fence")?;
+            device
+                .queue_submit(
+                    *self.logical_device.queue(),
+                    &[SubmitInfo::default().command_buffers(&command_buffers)],
+                    self.fence,
+                )
+                .context("submitting procedural texture")?;
+            device
+                .wait_for_fences(&[self.fence], true, u64::MAX)
+                .context("waiting for procedural texture")?;
+
+            device
+                .reset_descriptor_pool(self.descriptor_pool, DescriptorPoolResetFlags::empty())
+                .context("resetting procedural texture descriptor pool")?;
+        }
+
+        Ok(())
+    }
+
+    /// Blits every mip from the one above, leaving the whole image ready to sample.
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` must be recording, with the first mip written in the `GENERAL` layout.
+    unsafe fn record_mip_chain(&self, command_buffer: CommandBuffer, image: &TextureImage) {
+        let device = self.logical_device.device();
+        let desc = &image.desc;
+
+        image.barrier(
+            command_buffer,
+            (ImageLayout::GENERAL, ImageLayout::TRANSFER_SRC_OPTIMAL),
+            (AccessFlags::SHADER_WRITE, AccessFlags::TRANSFER_READ),
+            (
+                PipelineStageFlags::COMPUTE_SHADER,
+                PipelineStageFlags::TRANSFER,
+            ),
+            0..1,
+        );
+        image.barrier(
+            command_buffer,
+            (ImageLayout::UNDEFINED, ImageLayout::TRANSFER_DST_OPTIMAL),
+            (AccessFlags::empty(), AccessFlags::TRANSFER_WRITE),
+            (
+                PipelineStageFlags::TOP_OF_PIPE,
+                PipelineStageFlags::TRANSFER,
+            ),
+            1..desc.mip_levels,
+        );
+
+        for mip in 1..desc.mip_levels {
+            device.cmd_blit_image(
+                command_buffer,
+                image.image,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                image.image,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[ImageBlit::default()
+                    .src_subresource(desc.image().subresource_layers(mip - 1))
+                    .src_offsets(desc.image().bounds(mip - 1))
+                    .dst_subresource(desc.image().subresource_layers(mip))
+                    .dst_offsets(desc.image().bounds(mip))],
+                Filter::LINEAR,
+            );
+            image.barrier(
+                command_buffer,
+                (
+                    ImageLayout::TRANSFER_DST_OPTIMAL,
+                    ImageLayout::TRANSFER_SRC_OPTIMAL,
+                ),
+                (AccessFlags::TRANSFER_WRITE, AccessFlags::TRANSFER_READ),
+                (PipelineStageFlags::TRANSFER, PipelineStageFlags::TRANSFER),
+                mip..mip + 1,
+            );
+        }
+
+        image.barrier(
+            command_buffer,
+            (
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ),
+            (AccessFlags::TRANSFER_WRITE, AccessFlags::SHADER_READ),
+            (
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::FRAGMENT_SHADER,
+            ),
+            0..desc.mip_levels,
+        );
+    }
+}
+
+impl Drop for ProceduralTextureGenerator {
+    fn drop(&mut self) {
+        let device = self.logical_device.device();
+
+        unsafe {
+            if self.command_buffer != CommandBuffer::null() {
+                device.free_command_buffers(self.command_pool, &[self.command_buffer]);
+            }
+
+            device.destroy_fence(self.fence, host_allocation_callbacks());
+            device.destroy_pipeline_layout(self.pipeline_layout, host_allocation_callbacks());
+            device.destroy_descriptor_pool(self.descriptor_pool, host_allocation_callbacks());
+            device.destroy_descriptor_set_layout(
+                self.descriptor_set_layout,
+                host_allocation_callbacks(),
+            );
+        }
+    }
+}
+
+/// A generated texture in the `SHADER_READ_ONLY_OPTIMAL` layout with its own sampler, e.g. for
+/// [PbrTextures](crate::renderer::pbr::PbrTextures).
+#[derive(Clone)]
+pub struct ProceduralTexture(Rc<InnerProceduralTexture>);
+
+impl ProceduralTexture {
+    pub fn descriptor(&self) -> DescriptorImageInfo {
+        DescriptorImageInfo::default()
+            .sampler(*self.0.sampler.sampler())
+            .image_view(self.0.image.view)
+            .image_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+    }
+
+    pub fn image(&self) -> vk::Image {
+        self.0.image.image
+    }
+
+    pub fn desc(&self) -> &ProceduralTextureDesc {
+        &self.0.image.desc
+    }
+
+    /// The size and mips of the image, in the `SHADER_READ_ONLY_OPTIMAL` layout.
+    pub fn image_desc(&self) -> ImageDesc {
+        self.0.image.desc.image()
+    }
+}
+
+struct InnerProceduralTexture {
+    image: TextureImage,
+    sampler: Sampler,
+}
+
+/// A device-local image with a sampled view of every mip and a storage view of the first.
+struct TextureImage {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+    storage_view: vk::ImageView,
+    size: vk::DeviceSize,
+    desc: ProceduralTextureDesc,
+
+    logical_device: LogicalDevice,
+}
+
+impl TextureImage {
+    fn new(
+        logical_device: LogicalDevice,
+        desc: ProceduralTextureDesc,
+        usage: ImageUsageFlags,
+    ) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+
+        // sRGB images can't have the storage usage themselves, only their UNORM view.
+        let flags = if desc.storage_format() != desc.format {
+            ImageCreateFlags::MUTABLE_FORMAT | ImageCreateFlags::EXTENDED_USAGE
+        } else {
+            ImageCreateFlags::empty()
+        };
+
+        let image = unsafe {
+            device.create_image(
+                &ImageCreateInfo::default()
+                    .flags(flags)
+                    .image_type(ImageType::TYPE_2D)
+                    .format(desc.format)
+                    .extent(desc.image().extent(0))
+                    .mip_levels(desc.mip_levels)
+                    .array_layers(1)
+                    .samples(SampleCountFlags::TYPE_1)
+                    .tiling(ImageTiling::OPTIMAL)
+                    .usage(usage)
+                    .sharing_mode(SharingMode::EXCLUSIVE)
+                    .initial_layout(ImageLayout::UNDEFINED),
+                host_allocation_callbacks(),
+            )
+        }
+        .with_context("creating procedural texture image", || {
+            format!("{:?}", desc)
+        })?;
+
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+
+        let mut texture_image = Self {
+            image,
+            memory: vk::DeviceMemory::null(),
+            view: vk::ImageView::null(),
+            storage_view: vk::ImageView::null(),
+            size: requirements.size,
+            desc,
+            logical_device: logical_device.clone(),
+        };
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::Image, 1, requirements.size);
+
+        let memory_type_index = logical_device
+            .physical_device()
+            .find_memory_type(
+                requirements.memory_type_bits,
+                MemoryPropertyFlags::DEVICE_LOCAL,
+            )
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            .with_context("finding procedural texture memory", || {
+                format!("memory_type_bits={:#x}", requirements.memory_type_bits)
+            })?;
+
+        texture_image.memory = unsafe {
+            device.allocate_memory(
+                &MemoryAllocateInfo::default()
+                    .allocation_size(requirements.size)
+                    .memory_type_index(memory_type_index),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("allocating procedural texture memory")?;
+
+        unsafe { device.bind_image_memory(image, texture_image.memory, 0) }
+            .context("binding procedural texture memory")?;
+
+        texture_image.view = texture_image.create_view(desc.format, 0..desc.mip_levels)?;
+        texture_image.storage_view = texture_image.create_view(desc.storage_format(), 0..1)?;
+
+        Ok(texture_image)
+    }
+
+    fn create_view(
+        &self,
+        format: Format,
+        mips: std::ops::Range<u32>,
+    ) -> Result<vk::ImageView, ErrorCtx> {
+        let view = unsafe {
+            self.logical_device.device().create_image_view(
+                &ImageViewCreateInfo::default()
+                    .image(self.image)
+                    .view_type(ImageViewType::TYPE_2D)
+                    .format(format)
+                    .subresource_range(self.desc.image().subresource_range(mips.clone())),
+                host_allocation_callbacks(),
+            )
+        }
+        .with_context("creating procedural texture view", || {
+            format!("format={:?}, mips={:?}", format, mips)
+        })?;
+
+        self.logical_device
+            .resource_tracker()
+            .track(ResourceKind::ImageView, 1, 0);
+
+        Ok(view)
+    }
+
+    /// Records a layout transition of `mips`.
+    fn barrier(
+        &self,
+        command_buffer: CommandBuffer,
+        (old_layout, new_layout): (ImageLayout, ImageLayout),
+        (src_access, dst_access): (AccessFlags, AccessFlags),
+        (src_stage, dst_stage): (PipelineStageFlags, PipelineStageFlags),
+        mips: std::ops::Range<u32>,
+    ) {
+        if mips.is_empty() {
+            return;
+        }
+
+        unsafe {
+            self.logical_device.device().cmd_pipeline_barrier(
+                command_buffer,
+                src_stage,
+                dst_stage,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &[ImageMemoryBarrier::default()
+                    .old_layout(old_layout)
+                    .new_layout(new_layout)
+                    .src_access_mask(src_access)
+                    .dst_access_mask(dst_access)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .image(self.image)
+                    .subresource_range(self.desc.image().subresource_range(mips))],
+            );
+        }
+    }
+}
+
+impl Drop for TextureImage {
+    fn drop(&mut self) {
+        teardown_trace::record(
+            "ProceduralTexture",
+            [
+                self.view.as_raw(),
+                self.storage_view.as_raw(),
+                self.image.as_raw(),
+            ],
+        );
+
+        let device = self.logical_device.device();
+        let tracker = self.logical_device.resource_tracker();
+
+        unsafe {
+            for view in [self.view, self.storage_view] {
+                if view != vk::ImageView::null() {
+                    device.destroy_image_view(view, host_allocation_callbacks());
+                    tracker.untrack(ResourceKind::ImageView, 1, 0);
+                }
+            }
+
+            device.destroy_image(self.image, host_allocation_callbacks());
+            device.free_memory(self.memory, host_allocation_callbacks());
+        }
+
+        tracker.untrack(ResourceKind::Image, 1, self.size);
+    }
+}
+
+#[derive(Debug)]
+pub enum ProceduralTextureError {
+    Vulkan(ErrorCtx),
+    /// The device can't use the format for everything generating the texture needs.
+    UnsupportedFormat {
+        format: Format,
+        required: FormatFeatureFlags,
+    },
+    ParamsTooLarge {
+        size: usize,
+    },
+}
+
+impl From<ErrorCtx> for ProceduralTextureError {
+    fn from(value: ErrorCtx) -> Self {
+        Self::Vulkan(value)
+    }
+}
+
+impl fmt::Display for ProceduralTextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vulkan(e) => e.fmt(f),
+            Self::UnsupportedFormat { format, required } => write!(
+                f,
+                "format {:?} doesn't support {:?} with optimal tiling",
+                format, required
+            ),
+            Self::ParamsTooLarge { size } => write!(
+                f,
+                "procedural texture parameters are {} bytes, at most {} fit in push constants",
+                size, MAX_PARAMS_SIZE
+            ),
+        }
+    }
+}
+
+impl error::Error for ProceduralTextureError {}
@@ -0,0 +1,428 @@
+//! Pipeline and material descriptions as plain data, so render setups can live in files and be
+//! diffed between versions instead of being hard-coded.
+//!
+//! With the `serde` feature every description (de)serializes with any serde format. Vulkan's
+//! enums don't implement serde, so the descriptions mirror the values they use under readable
+//! names and convert to the Vulkan ones. Every top-level description carries the
+//! [SCHEMA_VERSION] it was written with, checked with [PipelineDesc::check_version] and
+//! [MaterialDesc::check_version] after loading.
+
+use std::{collections::BTreeMap, error, fmt};
+
+use ash::vk::{
+    self, CompareOp, CullModeFlags, DescriptorSetLayoutBinding, DescriptorType, FrontFace,
+    PipelineDepthStencilStateCreateInfo, PolygonMode, PrimitiveTopology, ShaderStageFlags,
+};
+
+use crate::renderer::{
+    blend_mode::BlendMode, graphics_pipeline::PipelineVariant, pbr::MaterialFactors,
+};
+
+/// The version of the descriptions written by this build. Bumped whenever a field changes
+/// meaning or a required one is added.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The fixed-function state, shaders and resource bindings of a graphics pipeline.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PipelineDesc {
+    pub version: u32,
+    /// Paths of the SPIR-V, or sources with the `shader-compiler` feature.
+    pub vertex_shader: String,
+    pub fragment_shader: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub topology: Topology,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub polygon_mode: FillMode,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cull_mode: CullMode,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub front_face: Winding,
+    /// The blending of each color attachment.
+    #[cfg_attr(feature = "serde", serde(default = "default_blend"))]
+    pub blend: Vec<BlendDesc>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub depth: DepthDesc,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bindings: Vec<BindingDesc>,
+}
+
+impl PipelineDesc {
+    /// An opaque, back-face culled pipeline without depth testing or bindings.
+    pub fn new(vertex_shader: impl Into<String>, fragment_shader: impl Into<String>) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            vertex_shader: vertex_shader.into(),
+            fragment_shader: fragment_shader.into(),
+            topology: Topology::default(),
+            polygon_mode: FillMode::default(),
+            cull_mode: CullMode::default(),
+            front_face: Winding::default(),
+            blend: default_blend(),
+            depth: DepthDesc::default(),
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Fails for descriptions written by a newer build, which may rely on fields this one
+    /// doesn't know.
+    pub fn check_version(&self) -> Result<(), DescError> {
+        check_version(self.version)
+    }
+
+    /// The variant of [crate::renderer::graphics_pipeline::GraphicsPipeline] with this state.
+    pub fn variant(&self) -> PipelineVariant {
+        PipelineVariant {
+            polygon_mode: self.polygon_mode.into(),
+            cull_mode: self.cull_mode.into(),
+            blend_modes: self.blend.iter().map(|&blend| blend.into()).collect(),
+        }
+    }
+
+    pub fn depth_stencil_state(&self) -> PipelineDepthStencilStateCreateInfo<'static> {
+        PipelineDepthStencilStateCreateInfo::default()
+            .depth_test_enable(self.depth.test)
+            .depth_write_enable(self.depth.write)
+            .depth_compare_op(self.depth.compare.into())
+    }
+
+    /// The layout bindings of descriptor set `set`, ordered by binding.
+    pub fn set_layout_bindings(&self, set: u32) -> Vec<DescriptorSetLayoutBinding<'static>> {
+        let mut bindings: Vec<_> = self
+            .bindings
+            .iter()
+            .filter(|binding| binding.set == set)
+            .collect();
+        bindings.sort_by_key(|binding| binding.binding);
+
+        bindings
+            .into_iter()
+            .map(|binding| {
+                DescriptorSetLayoutBinding::default()
+                    .binding(binding.binding)
+                    .descriptor_type(binding.kind.into())
+                    .descriptor_count(binding.count)
+                    .stage_flags(
+                        binding
+                            .stages
+                            .iter()
+                            .fold(ShaderStageFlags::empty(), |flags, &stage| {
+                                flags | ShaderStageFlags::from(stage)
+                            }),
+                    )
+            })
+            .collect()
+    }
+}
+
+/// The pipeline, textures and factors of a material.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MaterialDesc {
+    pub version: u32,
+    pub name: String,
+    /// The path of the [PipelineDesc] the material is drawn with.
+    pub pipeline: String,
+    /// Texture paths by slot, e.g. `albedo` or `normal` for the PBR pipeline.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub textures: BTreeMap<String, String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub factors: FactorsDesc,
+}
+
+impl MaterialDesc {
+    pub fn new(name: impl Into<String>, pipeline: impl Into<String>) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            name: name.into(),
+            pipeline: pipeline.into(),
+            textures: BTreeMap::new(),
+            factors: FactorsDesc::default(),
+        }
+    }
+
+    /// See [PipelineDesc::check_version].
+    pub fn check_version(&self) -> Result<(), DescError> {
+        check_version(self.version)
+    }
+}
+
+/// The constant factors of [MaterialFactors].
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct FactorsDesc {
+    /// Linear RGBA.
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub occlusion_strength: f32,
+    pub normal_scale: f32,
+}
+
+impl Default for FactorsDesc {
+    fn default() -> Self {
+        MaterialFactors::default().into()
+    }
+}
+
+impl From<MaterialFactors> for FactorsDesc {
+    fn from(value: MaterialFactors) -> Self {
+        Self {
+            base_color: value.base_color,
+            metallic: value.metallic,
+            roughness: value.roughness,
+            occlusion_strength: value.occlusion_strength,
+            normal_scale: value.normal_scale,
+        }
+    }
+}
+
+impl From<FactorsDesc> for MaterialFactors {
+    fn from(value: FactorsDesc) -> Self {
+        Self {
+            base_color: value.base_color,
+            metallic: value.metallic,
+            roughness: value.roughness,
+            occlusion_strength: value.occlusion_strength,
+            normal_scale: value.normal_scale,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct DepthDesc {
+    pub test: bool,
+    pub write: bool,
+    pub compare: Compare,
+}
+
+impl Default for DepthDesc {
+    fn default() -> Self {
+        Self {
+            test: false,
+            write: false,
+            compare: Compare::Less,
+        }
+    }
+}
+
+/// A descriptor binding of the pipeline's layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BindingDesc {
+    pub set: u32,
+    pub binding: u32,
+    pub kind: DescriptorKind,
+    #[cfg_attr(feature = "serde", serde(default = "default_count"))]
+    pub count: u32,
+    pub stages: Vec<Stage>,
+}
+
+/// [BlendMode] with its custom factors spelled out.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum BlendDesc {
+    #[default]
+    Opaque,
+    AlphaBlend,
+    Additive,
+    PremultipliedAlpha,
+    Custom {
+        src_color: Factor,
+        dst_color: Factor,
+        color_op: Op,
+        src_alpha: Factor,
+        dst_alpha: Factor,
+        alpha_op: Op,
+    },
+}
+
+impl From<BlendDesc> for BlendMode {
+    fn from(value: BlendDesc) -> Self {
+        match value {
+            BlendDesc::Opaque => Self::Opaque,
+            BlendDesc::AlphaBlend => Self::AlphaBlend,
+            BlendDesc::Additive => Self::Additive,
+            BlendDesc::PremultipliedAlpha => Self::PremultipliedAlpha,
+            BlendDesc::Custom {
+                src_color,
+                dst_color,
+                color_op,
+                src_alpha,
+                dst_alpha,
+                alpha_op,
+            } => Self::Custom {
+                src_color: src_color.into(),
+                dst_color: dst_color.into(),
+                color_op: color_op.into(),
+                src_alpha: src_alpha.into(),
+                dst_alpha: dst_alpha.into(),
+                alpha_op: alpha_op.into(),
+            },
+        }
+    }
+}
+
+/// Generates a mirror of a Vulkan enum, converting into it.
+macro_rules! vk_mirror {
+    (
+        $(#[$meta:meta])*
+        $name:ident => $target:ty { $($(#[$variant_meta:meta])* $variant:ident = $value:expr),+ $(,)? }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant),+
+        }
+
+        impl From<$name> for $target {
+            fn from(value: $name) -> Self {
+                match value {
+                    $($name::$variant => $value),+
+                }
+            }
+        }
+    };
+}
+
+vk_mirror! {
+    #[derive(Default)]
+    Topology => PrimitiveTopology {
+        PointList = PrimitiveTopology::POINT_LIST,
+        LineList = PrimitiveTopology::LINE_LIST,
+        LineStrip = PrimitiveTopology::LINE_STRIP,
+        #[default]
+        TriangleList = PrimitiveTopology::TRIANGLE_LIST,
+        TriangleStrip = PrimitiveTopology::TRIANGLE_STRIP,
+    }
+}
+
+vk_mirror! {
+    #[derive(Default)]
+    FillMode => PolygonMode {
+        #[default]
+        Fill = PolygonMode::FILL,
+        Line = PolygonMode::LINE,
+        Point = PolygonMode::POINT,
+    }
+}
+
+vk_mirror! {
+    #[derive(Default)]
+    CullMode => CullModeFlags {
+        None = CullModeFlags::NONE,
+        Front = CullModeFlags::FRONT,
+        #[default]
+        Back = CullModeFlags::BACK,
+        FrontAndBack = CullModeFlags::FRONT_AND_BACK,
+    }
+}
+
+vk_mirror! {
+    #[derive(Default)]
+    Winding => FrontFace {
+        #[default]
+        CounterClockwise = FrontFace::COUNTER_CLOCKWISE,
+        Clockwise = FrontFace::CLOCKWISE,
+    }
+}
+
+vk_mirror! {
+    Compare => CompareOp {
+        Never = CompareOp::NEVER,
+        Less = CompareOp::LESS,
+        Equal = CompareOp::EQUAL,
+        LessOrEqual = CompareOp::LESS_OR_EQUAL,
+        Greater = CompareOp::GREATER,
+        NotEqual = CompareOp::NOT_EQUAL,
+        GreaterOrEqual = CompareOp::GREATER_OR_EQUAL,
+        Always = CompareOp::ALWAYS,
+    }
+}
+
+vk_mirror! {
+    Factor => vk::BlendFactor {
+        Zero = vk::BlendFactor::ZERO,
+        One = vk::BlendFactor::ONE,
+        SrcColor = vk::BlendFactor::SRC_COLOR,
+        OneMinusSrcColor = vk::BlendFactor::ONE_MINUS_SRC_COLOR,
+        DstColor = vk::BlendFactor::DST_COLOR,
+        OneMinusDstColor = vk::BlendFactor::ONE_MINUS_DST_COLOR,
+        SrcAlpha = vk::BlendFactor::SRC_ALPHA,
+        OneMinusSrcAlpha = vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+        DstAlpha = vk::BlendFactor::DST_ALPHA,
+        OneMinusDstAlpha = vk::BlendFactor::ONE_MINUS_DST_ALPHA,
+    }
+}
+
+vk_mirror! {
+    Op => vk::BlendOp {
+        Add = vk::BlendOp::ADD,
+        Subtract = vk::BlendOp::SUBTRACT,
+        ReverseSubtract = vk::BlendOp::REVERSE_SUBTRACT,
+        Min = vk::BlendOp::MIN,
+        Max = vk::BlendOp::MAX,
+    }
+}
+
+vk_mirror! {
+    DescriptorKind => DescriptorType {
+        UniformBuffer = DescriptorType::UNIFORM_BUFFER,
+        StorageBuffer = DescriptorType::STORAGE_BUFFER,
+        CombinedImageSampler = DescriptorType::COMBINED_IMAGE_SAMPLER,
+        SampledImage = DescriptorType::SAMPLED_IMAGE,
+        StorageImage = DescriptorType::STORAGE_IMAGE,
+        Sampler = DescriptorType::SAMPLER,
+    }
+}
+
+vk_mirror! {
+    Stage => ShaderStageFlags {
+        Vertex = ShaderStageFlags::VERTEX,
+        Fragment = ShaderStageFlags::FRAGMENT,
+        Compute = ShaderStageFlags::COMPUTE,
+    }
+}
+
+fn default_blend() -> Vec<BlendDesc> {
+    vec![BlendDesc::Opaque]
+}
+
+#[cfg(feature = "serde")]
+fn default_count() -> u32 {
+    1
+}
+
+fn check_version(version: u32) -> Result<(), DescError> {
+    if version == 0 || version > SCHEMA_VERSION {
+        return Err(DescError::UnsupportedVersion(version));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DescError {
+    /// Written by a newer build, or not a version at all.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for DescError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "description has schema version {}, this build reads 1 to {}",
+                version, SCHEMA_VERSION
+            ),
+        }
+    }
+}
+
+impl error::Error for DescError {}
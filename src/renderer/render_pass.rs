@@ -0,0 +1,219 @@
+use std::rc::Rc;
+
+use ash::{
+    prelude::VkResult,
+    vk::{
+        self, AccessFlags, AttachmentDescription, AttachmentLoadOp, AttachmentReference,
+        AttachmentStoreOp, Handle, ImageLayout, PipelineBindPoint, PipelineStageFlags,
+        RenderPassCreateInfo, RenderPassMultiviewCreateInfo, SampleCountFlags, SubpassDependency,
+        SubpassDescription, SUBPASS_EXTERNAL,
+    },
+};
+
+use crate::{
+    api2::host_allocation_callbacks,
+    renderer::{swapchain::Swapchain, teardown_trace},
+};
+
+#[derive(Clone)]
+pub struct RenderPass(Rc<InnerRenderPass>);
+
+impl RenderPass {
+    pub fn new(swapchain: Swapchain) -> VkResult<Self> {
+        Self::with_samples(swapchain, SampleCountFlags::TYPE_1)
+    }
+
+    /// Creates a render pass drawing with `samples` per pixel. When multisampled, attachment 0 is
+    /// the multisampled color target, see [Framebuffers](crate::renderer::framebuffers::Framebuffers), and
+    /// attachment 1 the swapchain image it's resolved into at the end of the subpass.
+    ///
+    /// Fails with `ERROR_FORMAT_NOT_SUPPORTED` when color attachments can't have `samples`, see
+    /// [PhysicalDevice::max_usable_sample_count](crate::renderer::physical_device::PhysicalDevice::max_usable_sample_count).
+    pub fn with_samples(swapchain: Swapchain, samples: SampleCountFlags) -> VkResult<Self> {
+        Self::create(swapchain, samples, 0)
+    }
+
+    /// Creates a render pass like [RenderPass::with_samples] drawing every layer of a layered
+    /// swapchain at once, each draw being broadcast to all of them with `gl_ViewIndex` telling
+    /// shaders which one they render. The framebuffers need views of all the layers, as
+    /// [ImageViews::new](crate::renderer::image_views::ImageViews::new) creates.
+    ///
+    /// Fails with `ERROR_FEATURE_NOT_PRESENT` without
+    /// [multiview](crate::renderer::logical_device::LogicalDevice::supports_multiview) support or when the
+    /// swapchain has more layers than the device can render at once.
+    pub fn multiview(swapchain: Swapchain, samples: SampleCountFlags) -> VkResult<Self> {
+        let layers = swapchain.array_layers();
+
+        if !swapchain.device().supports_multiview() || layers > u32::BITS {
+            return Err(vk::Result::ERROR_FEATURE_NOT_PRESENT);
+        }
+
+        Self::create(swapchain, samples, u32::MAX >> (u32::BITS - layers))
+    }
+
+    fn create(swapchain: Swapchain, samples: SampleCountFlags, view_mask: u32) -> VkResult<Self> {
+        let supported_samples = swapchain
+            .device()
+            .physical_device()
+            .properties()
+            .limits
+            .framebuffer_color_sample_counts;
+
+        if !samples.as_raw().is_power_of_two() || !supported_samples.contains(samples) {
+            return Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED);
+        }
+
+        let format = swapchain.format().format;
+        let multisampled = samples != SampleCountFlags::TYPE_1;
+
+        let color_attachment = AttachmentDescription::default()
+            .format(format)
+            .samples(samples)
+            .load_op(AttachmentLoadOp::CLEAR)
+            .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+            .initial_layout(ImageLayout::UNDEFINED);
+
+        // Only the resolved samples are presented, the multisampled ones are discarded.
+        let attachment_description = if multisampled {
+            vec![
+                color_attachment
+                    .store_op(AttachmentStoreOp::DONT_CARE)
+                    .final_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+                AttachmentDescription::default()
+                    .format(format)
+                    .samples(SampleCountFlags::TYPE_1)
+                    .load_op(AttachmentLoadOp::DONT_CARE)
+                    .store_op(AttachmentStoreOp::STORE)
+                    .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+                    .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+                    .initial_layout(ImageLayout::UNDEFINED)
+                    .final_layout(ImageLayout::PRESENT_SRC_KHR),
+            ]
+        } else {
+            vec![color_attachment
+                .store_op(AttachmentStoreOp::STORE)
+                .final_layout(ImageLayout::PRESENT_SRC_KHR)]
+        };
+
+        let attachment_reference = [AttachmentReference::default()
+            .attachment(0)
+            .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)];
+        let resolve_reference = [AttachmentReference::default()
+            .attachment(1)
+            .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)];
+
+        let mut subpass = SubpassDescription::default()
+            .pipeline_bind_point(PipelineBindPoint::GRAPHICS)
+            .color_attachments(&attachment_reference);
+
+        if multisampled {
+            subpass = subpass.resolve_attachments(&resolve_reference);
+        }
+
+        let subpass = [subpass];
+
+        let dependencies = [SubpassDependency::default()
+            .src_subpass(SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(Default::default())
+            .dst_stage_mask(PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .dst_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE)];
+
+        // The layers are views of the same scene, so they're correlated, which lets
+        // implementations render them together.
+        let view_masks = [view_mask];
+        let mut multiview_info = RenderPassMultiviewCreateInfo::default()
+            .view_masks(&view_masks)
+            .correlation_masks(&view_masks);
+
+        let mut render_pass_info = RenderPassCreateInfo::default()
+            .attachments(&attachment_description)
+            .subpasses(&subpass)
+            .dependencies(&dependencies);
+
+        if view_mask != 0 {
+            render_pass_info = render_pass_info.push_next(&mut multiview_info);
+        }
+
+        let render_pass = unsafe {
+            swapchain
+                .device()
+                .device()
+                .create_render_pass(&render_pass_info, host_allocation_callbacks())
+        }?;
+
+        let attachment_formats = attachment_description
+            .iter()
+            .map(|attachment| attachment.format)
+            .collect();
+
+        Ok(Self(Rc::new(InnerRenderPass {
+            render_pass,
+            attachment_formats,
+            samples,
+            view_mask,
+            swapchain,
+        })))
+    }
+
+    pub fn render_pass(&self) -> &vk::RenderPass {
+        &self.0.render_pass
+    }
+
+    pub fn attachment_formats(&self) -> &[vk::Format] {
+        &self.0.attachment_formats
+    }
+
+    /// The color attachments the subpass writes, the multisampled target or the swapchain image.
+    /// The resolve attachment isn't one of them.
+    pub fn color_attachment_count(&self) -> u32 {
+        1
+    }
+
+    pub fn swapchain(&self) -> &Swapchain {
+        &self.0.swapchain
+    }
+
+    /// The samples per pixel pipelines drawing in this render pass have to use.
+    pub fn samples(&self) -> SampleCountFlags {
+        self.0.samples
+    }
+
+    pub fn is_multisampled(&self) -> bool {
+        self.0.samples != SampleCountFlags::TYPE_1
+    }
+
+    /// The layers the subpass renders to at once, 0 when it isn't multiview.
+    pub fn view_mask(&self) -> u32 {
+        self.0.view_mask
+    }
+
+    /// How many layers every draw renders, 1 when the render pass isn't multiview.
+    pub fn view_count(&self) -> u32 {
+        self.0.view_mask.count_ones().max(1)
+    }
+}
+
+struct InnerRenderPass {
+    render_pass: vk::RenderPass,
+    attachment_formats: Vec<vk::Format>,
+    samples: SampleCountFlags,
+    view_mask: u32,
+
+    swapchain: Swapchain,
+}
+
+impl Drop for InnerRenderPass {
+    fn drop(&mut self) {
+        teardown_trace::record("RenderPass", [self.render_pass.as_raw()]);
+
+        unsafe {
+            self.swapchain
+                .device()
+                .device()
+                .destroy_render_pass(self.render_pass, host_allocation_callbacks());
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Ready-made renderer configurations, so starting out doesn't mean deciding on every subsystem
+//! first. Each preset turns on what the matching stage of learning needs and leaves the rest off:
+//!
+//! - [RendererPreset::Minimal] is the bare triangle chain, no validation, multisampling or
+//!   diagnostics.
+//! - [RendererPreset::Tutorial] adds what the tutorial's later chapters build: validation in debug
+//!   builds, double buffering, the wireframe pipeline and crash dumps. It's the default.
+//! - [RendererPreset::Sandbox] turns on everything useful for experimenting: robustness, 4x MSAA,
+//!   triple buffering and printing the profiling counters at exit.
+//!
+//! A preset is only a starting point, every field of the [RendererConfig] it returns can be
+//! changed before the renderer is created.
+
+use std::{fmt, str::FromStr};
+
+use ash::vk::SampleCountFlags;
+
+use crate::renderer::{
+    logical_device::Robustness, swapchain::SwapchainPreferences, DEFAULT_FRAMES_IN_FLIGHT,
+    ENABLE_VALIDATION_LAYERS, MAX_FRAMES_IN_FLIGHT,
+};
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RendererPreset {
+    Minimal,
+    #[default]
+    Tutorial,
+    Sandbox,
+}
+
+impl RendererPreset {
+    pub const ALL: [Self; 3] = [Self::Minimal, Self::Tutorial, Self::Sandbox];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Minimal => "minimal",
+            Self::Tutorial => "tutorial",
+            Self::Sandbox => "sandbox",
+        }
+    }
+
+    pub fn config(self) -> RendererConfig {
+        match self {
+            Self::Minimal => RendererConfig::minimal(),
+            Self::Tutorial => RendererConfig::tutorial(),
+            Self::Sandbox => RendererConfig::sandbox(),
+        }
+    }
+}
+
+impl fmt::Display for RendererPreset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for RendererPreset {
+    type Err = UnknownPreset;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownPreset(s.to_owned()))
+    }
+}
+
+/// A preset name that isn't one of [RendererPreset::ALL].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPreset(pub String);
+
+impl fmt::Display for UnknownPreset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown preset {:?}, expected minimal, tutorial or sandbox",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownPreset {}
+
+/// What the renderer sets up, see the [module](self) for the presets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RendererConfig {
+    /// Enables the validation layer and its debug messenger.
+    pub validation: bool,
+    pub robustness: Robustness,
+    pub swapchain: SwapchainPreferences,
+    /// The samples per pixel to render with, lowered to what the device supports.
+    pub samples: SampleCountFlags,
+    /// At most [MAX_FRAMES_IN_FLIGHT].
+    pub frames_in_flight: usize,
+    /// Also creates the wireframe pipeline variant when the device supports it.
+    pub wireframe_variant: bool,
+    /// Records checkpoints and writes a crash dump when the device is lost.
+    pub crash_diagnostics: bool,
+    /// Prints the pipeline creation and resource counters at exit.
+    pub profiling: bool,
+}
+
+impl RendererConfig {
+    pub fn minimal() -> Self {
+        Self {
+            validation: false,
+            robustness: Robustness::default(),
+            swapchain: SwapchainPreferences::default(),
+            samples: SampleCountFlags::TYPE_1,
+            frames_in_flight: 1,
+            wireframe_variant: false,
+            crash_diagnostics: false,
+            profiling: false,
+        }
+    }
+
+    pub fn tutorial() -> Self {
+        Self {
+            validation: ENABLE_VALIDATION_LAYERS,
+            frames_in_flight: DEFAULT_FRAMES_IN_FLIGHT,
+            wireframe_variant: true,
+            crash_diagnostics: true,
+            ..Self::minimal()
+        }
+    }
+
+    pub fn sandbox() -> Self {
+        Self {
+            robustness: Robustness {
+                robust_buffer_access: true,
+                null_descriptor: true,
+            },
+            samples: SampleCountFlags::TYPE_4,
+            frames_in_flight: MAX_FRAMES_IN_FLIGHT,
+            profiling: true,
+            ..Self::tutorial()
+        }
+    }
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        RendererPreset::default().config()
+    }
+}
@@ -0,0 +1,84 @@
+use std::{cell::RefCell, rc::Rc};
+
+#[derive(Clone, Default)]
+pub struct ResourceTracker(Rc<RefCell<ResourceStats>>);
+
+impl ResourceTracker {
+    pub fn track(&self, kind: ResourceKind, count: u64, bytes: u64) {
+        let mut stats = self.0.borrow_mut();
+        let entry = stats.entry_mut(kind);
+
+        entry.live += count;
+        entry.bytes += bytes;
+    }
+
+    pub fn untrack(&self, kind: ResourceKind, count: u64, bytes: u64) {
+        let mut stats = self.0.borrow_mut();
+        let entry = stats.entry_mut(kind);
+
+        entry.live = entry.live.saturating_sub(count);
+        entry.bytes = entry.bytes.saturating_sub(bytes);
+    }
+
+    pub fn snapshot(&self) -> ResourceStats {
+        *self.0.borrow()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    Buffer,
+    Image,
+    ImageView,
+    Framebuffer,
+    ShaderModule,
+    Pipeline,
+    DescriptorSet,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResourceCount {
+    pub live: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResourceStats {
+    pub buffers: ResourceCount,
+    pub images: ResourceCount,
+    pub image_views: ResourceCount,
+    pub framebuffers: ResourceCount,
+    pub shader_modules: ResourceCount,
+    pub pipelines: ResourceCount,
+    pub descriptor_sets: ResourceCount,
+}
+
+impl ResourceStats {
+    pub fn get(&self, kind: ResourceKind) -> ResourceCount {
+        match kind {
+            ResourceKind::Buffer => self.buffers,
+            ResourceKind::Image => self.images,
+            ResourceKind::ImageView => self.image_views,
+            ResourceKind::Framebuffer => self.framebuffers,
+            ResourceKind::ShaderModule => self.shader_modules,
+            ResourceKind::Pipeline => self.pipelines,
+            ResourceKind::DescriptorSet => self.descriptor_sets,
+        }
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.buffers.bytes + self.images.bytes
+    }
+
+    fn entry_mut(&mut self, kind: ResourceKind) -> &mut ResourceCount {
+        match kind {
+            ResourceKind::Buffer => &mut self.buffers,
+            ResourceKind::Image => &mut self.images,
+            ResourceKind::ImageView => &mut self.image_views,
+            ResourceKind::Framebuffer => &mut self.framebuffers,
+            ResourceKind::ShaderModule => &mut self.shader_modules,
+            ResourceKind::Pipeline => &mut self.pipelines,
+            ResourceKind::DescriptorSet => &mut self.descriptor_sets,
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use ash::{
+    prelude::VkResult,
+    vk::{
+        self, BorderColor, CompareOp, Filter, Handle, SamplerAddressMode, SamplerCreateInfo,
+        SamplerMipmapMode, LOD_CLAMP_NONE, TRUE,
+    },
+};
+
+use crate::{
+    api2::host_allocation_callbacks,
+    renderer::{logical_device::LogicalDevice, teardown_trace},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SamplerConfig {
+    pub mag_filter: Filter,
+    pub min_filter: Filter,
+    pub mipmap_mode: SamplerMipmapMode,
+    pub address_mode_u: SamplerAddressMode,
+    pub address_mode_v: SamplerAddressMode,
+    pub address_mode_w: SamplerAddressMode,
+    pub anisotropy: Option<u32>,
+    pub border_color: BorderColor,
+    pub compare_op: Option<CompareOp>,
+    pub max_lod: Option<u32>,
+}
+
+impl Default for SamplerConfig {
+    fn default() -> Self {
+        Self {
+            mag_filter: Filter::LINEAR,
+            min_filter: Filter::LINEAR,
+            mipmap_mode: SamplerMipmapMode::LINEAR,
+            address_mode_u: SamplerAddressMode::REPEAT,
+            address_mode_v: SamplerAddressMode::REPEAT,
+            address_mode_w: SamplerAddressMode::REPEAT,
+            anisotropy: None,
+            border_color: BorderColor::INT_OPAQUE_BLACK,
+            compare_op: None,
+            max_lod: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SamplerBuilder {
+    pub config: SamplerConfig,
+}
+
+impl SamplerBuilder {
+    pub fn filter(mut self, mag_filter: Filter, min_filter: Filter) -> Self {
+        self.config.mag_filter = mag_filter;
+        self.config.min_filter = min_filter;
+        self
+    }
+
+    pub fn mipmap_mode(mut self, mipmap_mode: SamplerMipmapMode) -> Self {
+        self.config.mipmap_mode = mipmap_mode;
+        self
+    }
+
+    pub fn address_mode(mut self, address_mode: SamplerAddressMode) -> Self {
+        self.config.address_mode_u = address_mode;
+        self.config.address_mode_v = address_mode;
+        self.config.address_mode_w = address_mode;
+        self
+    }
+
+    pub fn address_modes(
+        mut self,
+        u: SamplerAddressMode,
+        v: SamplerAddressMode,
+        w: SamplerAddressMode,
+    ) -> Self {
+        self.config.address_mode_u = u;
+        self.config.address_mode_v = v;
+        self.config.address_mode_w = w;
+        self
+    }
+
+    pub fn anisotropy(mut self, level: u32) -> Self {
+        self.config.anisotropy = Some(level);
+        self
+    }
+
+    pub fn border_color(mut self, border_color: BorderColor) -> Self {
+        self.config.border_color = border_color;
+        self
+    }
+
+    pub fn compare_op(mut self, compare_op: CompareOp) -> Self {
+        self.config.compare_op = Some(compare_op);
+        self
+    }
+
+    pub fn max_lod(mut self, max_lod: u32) -> Self {
+        self.config.max_lod = Some(max_lod);
+        self
+    }
+
+    pub fn build(self, logical_device: LogicalDevice) -> VkResult<Sampler> {
+        Sampler::new(logical_device, self.config)
+    }
+}
+
+#[derive(Clone)]
+pub struct Sampler(Rc<InnerSampler>);
+
+impl Sampler {
+    pub fn new(logical_device: LogicalDevice, config: SamplerConfig) -> VkResult<Self> {
+        let max_anisotropy = logical_device
+            .physical_device()
+            .properties()
+            .limits
+            .max_sampler_anisotropy;
+
+        // Anisotropy is silently dropped when the device didn't enable the feature and clamped to
+        // the device limit otherwise.
+        let anisotropy = config
+            .anisotropy
+            .filter(|_| logical_device.enabled_features().sampler_anisotropy == TRUE)
+            .map(|level| (level as f32).clamp(1.0, max_anisotropy));
+
+        let create_info = SamplerCreateInfo::default()
+            .mag_filter(config.mag_filter)
+            .min_filter(config.min_filter)
+            .mipmap_mode(config.mipmap_mode)
+            .address_mode_u(config.address_mode_u)
+            .address_mode_v(config.address_mode_v)
+            .address_mode_w(config.address_mode_w)
+            .anisotropy_enable(anisotropy.is_some())
+            .max_anisotropy(anisotropy.unwrap_or(1.0))
+            .border_color(config.border_color)
+            .compare_enable(config.compare_op.is_some())
+            .compare_op(config.compare_op.unwrap_or(CompareOp::ALWAYS))
+            .min_lod(0.0)
+            .max_lod(config.max_lod.map_or(LOD_CLAMP_NONE, |lod| lod as f32));
+
+        let sampler = unsafe {
+            logical_device
+                .device()
+                .create_sampler(&create_info, host_allocation_callbacks())?
+        };
+
+        Ok(Self(Rc::new(InnerSampler {
+            sampler,
+            config,
+            logical_device,
+        })))
+    }
+
+    pub fn sampler(&self) -> &vk::Sampler {
+        &self.0.sampler
+    }
+
+    pub fn config(&self) -> &SamplerConfig {
+        &self.0.config
+    }
+}
+
+struct InnerSampler {
+    sampler: vk::Sampler,
+    config: SamplerConfig,
+    logical_device: LogicalDevice,
+}
+
+impl Drop for InnerSampler {
+    fn drop(&mut self) {
+        teardown_trace::record("Sampler", [self.sampler.as_raw()]);
+
+        unsafe {
+            self.logical_device
+                .device()
+                .destroy_sampler(self.sampler, host_allocation_callbacks());
+        }
+    }
+}
+
+/// Hands out one [Sampler] per [SamplerConfig], so textures sampled the same way share it
+/// instead of each creating their own against `maxSamplerAllocationCount`.
+#[derive(Clone)]
+pub struct SamplerCache(Rc<InnerSamplerCache>);
+
+impl SamplerCache {
+    pub fn new(logical_device: LogicalDevice) -> Self {
+        Self(Rc::new(InnerSamplerCache {
+            logical_device,
+            samplers: RefCell::new(HashMap::new()),
+        }))
+    }
+
+    pub fn get_or_create(&self, config: SamplerConfig) -> VkResult<Sampler> {
+        if let Some(sampler) = self.0.samplers.borrow().get(&config) {
+            return Ok(sampler.clone());
+        }
+
+        let sampler = Sampler::new(self.0.logical_device.clone(), config)?;
+
+        self.0.samplers.borrow_mut().insert(config, sampler.clone());
+
+        Ok(sampler)
+    }
+
+    pub fn clear(&self) {
+        self.0.samplers.borrow_mut().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.samplers.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.samplers.borrow().is_empty()
+    }
+}
+
+struct InnerSamplerCache {
+    logical_device: LogicalDevice,
+    samplers: RefCell<HashMap<SamplerConfig, Sampler>>,
+}
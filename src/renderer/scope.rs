@@ -0,0 +1,217 @@
+//! Scopes owning what was created inside them, for examples that create raw Vulkan objects and
+//! shouldn't have to work out their destruction order. Everything handed to a scope is destroyed
+//! in reverse order of being handed over, once the GPU is done with it: a [DeviceScope] waits
+//! for the whole device, a [FrameScope] only for the fence of its frame.
+//!
+//! `examples/auto_exposure.rs` keeps its HDR image, memory, view and fence in a [DeviceScope].
+//!
+//! ```ignore
+//! let scope = DeviceScope::new(logical_device.clone());
+//! let layout = scope.adopt(unsafe { device.create_pipeline_layout(&info, None) }?);
+//! let pipeline = scope.adopt(create_pipeline(layout)?);
+//! // The pipeline is destroyed before its layout when `scope` drops.
+//! ```
+
+use std::cell::RefCell;
+
+use ash::{
+    prelude::VkResult,
+    vk::{self, Handle},
+    Device,
+};
+
+use crate::{
+    api2::host_allocation_callbacks,
+    renderer::{logical_device::LogicalDevice, teardown_trace},
+};
+
+/// A Vulkan handle a scope can destroy.
+pub trait ScopedHandle: Handle + Copy + 'static {
+    /// The name the destruction is traced as.
+    const NAME: &'static str;
+
+    /// # Safety
+    ///
+    /// The handle must come from `device` and not be in use anymore.
+    unsafe fn destroy(self, device: &Device);
+}
+
+macro_rules! scoped_handles {
+    ($($handle:ident => $destroy:ident,)*) => {
+        $(
+            impl ScopedHandle for vk::$handle {
+                const NAME: &'static str = stringify!($handle);
+
+                unsafe fn destroy(self, device: &Device) {
+                    device.$destroy(self, host_allocation_callbacks());
+                }
+            }
+        )*
+    };
+}
+
+scoped_handles! {
+    Buffer => destroy_buffer,
+    BufferView => destroy_buffer_view,
+    CommandPool => destroy_command_pool,
+    DescriptorPool => destroy_descriptor_pool,
+    DescriptorSetLayout => destroy_descriptor_set_layout,
+    DeviceMemory => free_memory,
+    Event => destroy_event,
+    Fence => destroy_fence,
+    Framebuffer => destroy_framebuffer,
+    Image => destroy_image,
+    ImageView => destroy_image_view,
+    Pipeline => destroy_pipeline,
+    PipelineCache => destroy_pipeline_cache,
+    PipelineLayout => destroy_pipeline_layout,
+    QueryPool => destroy_query_pool,
+    RenderPass => destroy_render_pass,
+    Sampler => destroy_sampler,
+    Semaphore => destroy_semaphore,
+    ShaderModule => destroy_shader_module,
+}
+
+/// Destroys or drops one thing adopted by a scope.
+type Entry = Box<dyn FnOnce(&Device)>;
+
+/// What a scope destroys or drops when it ends, in reverse order.
+#[derive(Default)]
+struct Entries(RefCell<Vec<Entry>>);
+
+impl Entries {
+    fn adopt<H: ScopedHandle>(&self, handle: H) -> H {
+        self.0.borrow_mut().push(Box::new(move |device| {
+            teardown_trace::record(H::NAME, [handle.as_raw()]);
+
+            // SAFETY: scopes only run their entries after the GPU is done with them.
+            unsafe { handle.destroy(device) };
+        }));
+
+        handle
+    }
+
+    fn keep<T: 'static>(&self, value: T) {
+        self.0.borrow_mut().push(Box::new(move |_| drop(value)));
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    fn release(&self, device: &Device) {
+        let entries = self.0.take();
+
+        for entry in entries.into_iter().rev() {
+            entry(device);
+        }
+    }
+}
+
+/// Owns resources until the end of an example, waiting for the device to go idle before
+/// destroying them.
+pub struct DeviceScope {
+    entries: Entries,
+    logical_device: LogicalDevice,
+}
+
+impl DeviceScope {
+    pub fn new(logical_device: LogicalDevice) -> Self {
+        Self {
+            entries: Entries::default(),
+            logical_device,
+        }
+    }
+
+    /// Destroys `handle`, which must come from this scope's device, when the scope ends.
+    pub fn adopt<H: ScopedHandle>(&self, handle: H) -> H {
+        self.entries.adopt(handle)
+    }
+
+    /// Drops `value` when the scope ends, in order with the handles, e.g. a [Buffer] wrapper
+    /// that has to go before the memory it was bound to.
+    ///
+    /// [Buffer]: crate::renderer::buffer::Buffer
+    pub fn keep<T: 'static>(&self, value: T) {
+        self.entries.keep(value)
+    }
+
+    /// Starts a scope for resources of a single frame, released once `fence` signals.
+    pub fn frame(&self, fence: vk::Fence) -> FrameScope {
+        FrameScope::new(self.logical_device.clone(), fence)
+    }
+
+    /// Ends the scope, returning whether waiting for the device failed. Everything is destroyed
+    /// either way.
+    pub fn end(self) -> VkResult<()> {
+        let result = self.logical_device.wait_idle();
+        self.entries.release(self.logical_device.device());
+        result
+    }
+}
+
+impl Drop for DeviceScope {
+    fn drop(&mut self) {
+        if !self.entries.is_empty() {
+            let _ = self.logical_device.wait_idle();
+            self.entries.release(self.logical_device.device());
+        }
+    }
+}
+
+/// Owns resources used by one frame, destroying them once the frame's fence signals instead of
+/// waiting for the whole device.
+pub struct FrameScope {
+    entries: Entries,
+    fence: vk::Fence,
+    logical_device: LogicalDevice,
+}
+
+impl FrameScope {
+    /// `fence` is signalled by the frame's last submission, and must outlive the scope.
+    pub fn new(logical_device: LogicalDevice, fence: vk::Fence) -> Self {
+        Self {
+            entries: Entries::default(),
+            fence,
+            logical_device,
+        }
+    }
+
+    /// Destroys `handle`, which must come from this scope's device, when the scope ends.
+    pub fn adopt<H: ScopedHandle>(&self, handle: H) -> H {
+        self.entries.adopt(handle)
+    }
+
+    /// Drops `value` when the scope ends, in order with the handles.
+    pub fn keep<T: 'static>(&self, value: T) {
+        self.entries.keep(value)
+    }
+
+    /// Ends the scope, returning whether waiting for the fence failed. Everything is destroyed
+    /// either way, after waiting for the whole device if the fence couldn't be waited on.
+    pub fn end(self) -> VkResult<()> {
+        let result = self.wait();
+        self.entries.release(self.logical_device.device());
+        result
+    }
+
+    fn wait(&self) -> VkResult<()> {
+        let device = self.logical_device.device();
+        let result = unsafe { device.wait_for_fences(&[self.fence], true, u64::MAX) };
+
+        if result.is_err() {
+            let _ = self.logical_device.wait_idle();
+        }
+
+        result
+    }
+}
+
+impl Drop for FrameScope {
+    fn drop(&mut self) {
+        if !self.entries.is_empty() {
+            let _ = self.wait();
+            self.entries.release(self.logical_device.device());
+        }
+    }
+}
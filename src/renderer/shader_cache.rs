@@ -0,0 +1,150 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    rc::Rc,
+};
+
+use ash::prelude::VkResult;
+
+use crate::renderer::{logical_device::LogicalDevice, shader_module::ShaderModule};
+use crate::renderer::{
+    shader_compiler::{self, ShaderCompileError, ShaderSource},
+    shader_include::IncludeResolver,
+};
+
+#[derive(Clone)]
+pub struct ShaderCache(Rc<RefCell<InnerShaderCache>>);
+
+impl ShaderCache {
+    pub fn new(logical_device: LogicalDevice) -> Self {
+        Self(Rc::new(RefCell::new(InnerShaderCache {
+            logical_device,
+            modules: HashMap::new(),
+            stats: ShaderCacheStats::default(),
+        })))
+    }
+
+    pub fn get_or_create(&self, shader: &[u32]) -> VkResult<ShaderModule> {
+        let key = hash_spirv(shader);
+        let mut inner = self.0.borrow_mut();
+
+        if let Some(entry) = inner.modules.get(&key) {
+            if entry.code == shader {
+                let shader_module = entry.shader_module.clone();
+                inner.stats.hits += 1;
+                return Ok(shader_module);
+            }
+        }
+
+        let shader_module = ShaderModule::new(inner.logical_device.clone(), shader)?;
+
+        inner.stats.misses += 1;
+        inner.modules.insert(
+            key,
+            CachedShaderModule {
+                code: shader.to_vec(),
+                shader_module: shader_module.clone(),
+            },
+        );
+
+        Ok(shader_module)
+    }
+
+    /// Compiles `source` to SPIR-V with naga and returns the module for it, cached like
+    /// [ShaderCache::get_or_create].
+    pub fn get_or_compile(&self, source: ShaderSource) -> Result<ShaderModule, ShaderCompileError> {
+        let spirv = shader_compiler::compile(source)?;
+
+        self.get_or_create(&spirv).map_err(ShaderCompileError::from)
+    }
+
+    /// Compiles the shader file at `path` with its includes resolved by `resolver`, see
+    /// [shader_compiler::compile_file].
+    pub fn get_or_compile_file(
+        &self,
+        resolver: &mut IncludeResolver,
+        path: &str,
+    ) -> Result<ShaderModule, ShaderCompileError> {
+        let spirv = shader_compiler::compile_file(resolver, path)?;
+
+        self.get_or_create(&spirv).map_err(ShaderCompileError::from)
+    }
+
+    /// Drops the cached module for `shader`. Like [ShaderCache::get_or_create], a module whose
+    /// code only shares the hash of `shader` is left alone.
+    pub fn evict(&self, shader: &[u32]) -> bool {
+        let key = hash_spirv(shader);
+        let mut inner = self.0.borrow_mut();
+
+        if !inner
+            .modules
+            .get(&key)
+            .is_some_and(|entry| entry.code == shader)
+        {
+            return false;
+        }
+
+        inner.modules.remove(&key);
+        inner.stats.evictions += 1;
+
+        true
+    }
+
+    pub fn evict_unused(&self) -> usize {
+        let mut inner = self.0.borrow_mut();
+        let before = inner.modules.len();
+
+        inner
+            .modules
+            .retain(|_, entry| entry.shader_module.is_shared());
+
+        let evicted = before - inner.modules.len();
+        inner.stats.evictions += evicted as u64;
+
+        evicted
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.0.borrow_mut();
+
+        inner.stats.evictions += inner.modules.len() as u64;
+        inner.modules.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.borrow().modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().modules.is_empty()
+    }
+
+    pub fn stats(&self) -> ShaderCacheStats {
+        self.0.borrow().stats
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ShaderCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+struct InnerShaderCache {
+    logical_device: LogicalDevice,
+    modules: HashMap<u64, CachedShaderModule>,
+    stats: ShaderCacheStats,
+}
+
+struct CachedShaderModule {
+    code: Vec<u32>,
+    shader_module: ShaderModule,
+}
+
+fn hash_spirv(shader: &[u32]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    shader.hash(&mut hasher);
+    hasher.finish()
+}
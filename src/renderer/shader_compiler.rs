@@ -0,0 +1,180 @@
+//! Compiles WGSL and GLSL into SPIR-V with naga, so shaders can be built without the external
+//! glslang or shaderc binaries. Without the `shader-compiler` feature naga isn't built and
+//! compiling fails with [ShaderCompileError::Unsupported].
+
+use std::{error, fmt};
+
+use ash::vk;
+#[cfg(feature = "shader-compiler")]
+use naga::{
+    back::spv,
+    front::{glsl, wgsl},
+    valid::{Capabilities, ValidationFlags, Validator},
+};
+
+use crate::renderer::shader_include::{IncludeError, IncludeResolver};
+
+/// The stage a GLSL shader is written for. WGSL declares it on each entry point instead.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Compute,
+}
+
+#[cfg(feature = "shader-compiler")]
+impl From<ShaderStage> for naga::ShaderStage {
+    fn from(value: ShaderStage) -> Self {
+        match value {
+            ShaderStage::Vertex => Self::Vertex,
+            ShaderStage::Fragment => Self::Fragment,
+            ShaderStage::Compute => Self::Compute,
+        }
+    }
+}
+
+/// Shader source code in one of the languages naga reads.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ShaderSource<'a> {
+    Wgsl(&'a str),
+    Glsl { source: &'a str, stage: ShaderStage },
+}
+
+#[cfg(feature = "shader-compiler")]
+impl ShaderSource<'_> {
+    fn text(&self) -> &str {
+        match self {
+            Self::Wgsl(source) | Self::Glsl { source, .. } => source,
+        }
+    }
+}
+
+/// Parses and validates `source` and returns the SPIR-V words for a shader module.
+///
+/// The pipelines look up their entry point as `main`, which GLSL always uses but WGSL shaders
+/// have to name their entry point explicitly.
+#[cfg(feature = "shader-compiler")]
+pub fn compile(source: ShaderSource) -> Result<Vec<u32>, ShaderCompileError> {
+    let module = match source {
+        ShaderSource::Wgsl(text) => {
+            wgsl::parse_str(text).map_err(|e| ShaderCompileError::Parse(e.emit_to_string(text)))?
+        }
+        ShaderSource::Glsl {
+            source: text,
+            stage,
+        } => glsl::Frontend::default()
+            .parse(&glsl::Options::from(naga::ShaderStage::from(stage)), text)
+            .map_err(|e| ShaderCompileError::Parse(e.emit_to_string(text)))?,
+    };
+
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|e| ShaderCompileError::Validation(e.emit_to_string(source.text())))?;
+
+    let mut options = spv::Options::default();
+
+    // WGSL has Y pointing up in clip space and naga flips it for Vulkan. GLSL written for Vulkan
+    // already uses Vulkan's convention.
+    if let ShaderSource::Glsl { .. } = source {
+        options
+            .flags
+            .remove(spv::WriterFlags::ADJUST_COORDINATE_SPACE);
+    }
+
+    spv::write_vec(&module, &info, &options, None).map_err(ShaderCompileError::from)
+}
+
+/// Fails with [ShaderCompileError::Unsupported], naga isn't built without the
+/// `shader-compiler` feature.
+#[cfg(not(feature = "shader-compiler"))]
+pub fn compile(_source: ShaderSource) -> Result<Vec<u32>, ShaderCompileError> {
+    Err(ShaderCompileError::Unsupported)
+}
+
+/// Resolves the includes of the shader at `path` and compiles it.
+///
+/// The language and stage come from the extension: `.wgsl` for WGSL, and `.vert`, `.frag` or
+/// `.comp` for GLSL.
+pub fn compile_file(
+    resolver: &mut IncludeResolver,
+    path: &str,
+) -> Result<Vec<u32>, ShaderCompileError> {
+    let extension = path.rsplit_once('.').map(|(_, extension)| extension);
+    let stage = match extension {
+        Some("wgsl") => None,
+        Some("vert") => Some(ShaderStage::Vertex),
+        Some("frag") => Some(ShaderStage::Fragment),
+        Some("comp") => Some(ShaderStage::Compute),
+        _ => return Err(ShaderCompileError::UnknownExtension(path.to_owned())),
+    };
+
+    let text = resolver.resolve(path)?;
+
+    compile(match stage {
+        None => ShaderSource::Wgsl(&text),
+        Some(stage) => ShaderSource::Glsl {
+            source: &text,
+            stage,
+        },
+    })
+}
+
+#[derive(Debug)]
+pub enum ShaderCompileError {
+    Include(IncludeError),
+    UnknownExtension(String),
+    /// The source failed to parse, with the diagnostics rendered against the source.
+    Parse(String),
+    /// The source parsed but isn't a valid shader, with the diagnostics rendered against the
+    /// source.
+    Validation(String),
+    #[cfg(feature = "shader-compiler")]
+    Spirv(spv::Error),
+    Vulkan(vk::Result),
+    /// Built without the `shader-compiler` feature.
+    Unsupported,
+}
+
+impl From<IncludeError> for ShaderCompileError {
+    fn from(value: IncludeError) -> Self {
+        Self::Include(value)
+    }
+}
+
+#[cfg(feature = "shader-compiler")]
+impl From<spv::Error> for ShaderCompileError {
+    fn from(value: spv::Error) -> Self {
+        Self::Spirv(value)
+    }
+}
+
+impl From<vk::Result> for ShaderCompileError {
+    fn from(value: vk::Result) -> Self {
+        Self::Vulkan(value)
+    }
+}
+
+impl fmt::Display for ShaderCompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Include(e) => e.fmt(f),
+            Self::UnknownExtension(path) => {
+                write!(
+                    f,
+                    "can't tell the shader language of {} from its extension",
+                    path
+                )
+            }
+            Self::Parse(e) => write!(f, "failed to parse shader:\n{}", e),
+            Self::Validation(e) => write!(f, "invalid shader:\n{}", e),
+            #[cfg(feature = "shader-compiler")]
+            Self::Spirv(e) => write!(f, "failed to write SPIR-V: {}", e),
+            Self::Vulkan(e) => e.fmt(f),
+            Self::Unsupported => {
+                write!(f, "shader compilation needs the shader-compiler feature")
+            }
+        }
+    }
+}
+
+impl error::Error for ShaderCompileError {}
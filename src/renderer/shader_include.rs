@@ -0,0 +1,240 @@
+//! `#include` resolution for shaders compiled at runtime, so headers like lighting or tonemapping
+//! can be shared between shaders.
+//!
+//! `#include "file"` is looked up next to the including file and `#include <file>` from the root
+//! of the [ShaderFs]. Every file is pasted at most once per shader, like with `#pragma once`, and
+//! including a file that is still being expanded is an error.
+
+use std::{
+    collections::{HashMap, HashSet},
+    error, fmt, fs, io,
+    path::PathBuf,
+};
+
+/// The shader sources includes are resolved against: files registered in memory, falling back
+/// to a directory on disk.
+#[derive(Debug, Default, Clone)]
+pub struct ShaderFs {
+    root: Option<PathBuf>,
+    files: HashMap<String, String>,
+}
+
+impl ShaderFs {
+    /// Creates a file system with only in-memory files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a file system that reads files not registered in memory from `root`.
+    pub fn with_root<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: Some(root.into()),
+            files: HashMap::new(),
+        }
+    }
+
+    /// Registers `source` under `path`, replacing the file on disk with the same path if any.
+    pub fn insert(&mut self, path: &str, source: impl Into<String>) {
+        self.files.insert(normalize(path), source.into());
+    }
+
+    /// Reads the file at `path`, which has to be normalized.
+    fn read(&self, path: &str) -> Result<Option<String>, io::Error> {
+        if let Some(source) = self.files.get(path) {
+            return Ok(Some(source.clone()));
+        }
+
+        let Some(root) = &self.root else {
+            return Ok(None);
+        };
+
+        match fs::read_to_string(root.join(path)) {
+            Ok(source) => Ok(Some(source)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Expands includes and remembers which files every shader was built from, so a file watcher can
+/// tell which shaders to rebuild when a header changes.
+#[derive(Debug, Default, Clone)]
+pub struct IncludeResolver {
+    /// Where the sources come from.
+    pub fs: ShaderFs,
+    dependencies: HashMap<String, Vec<String>>,
+}
+
+impl IncludeResolver {
+    pub fn new(fs: ShaderFs) -> Self {
+        Self {
+            fs,
+            dependencies: HashMap::new(),
+        }
+    }
+
+    /// Returns the source of the shader at `path` with all includes expanded.
+    pub fn resolve(&mut self, path: &str) -> Result<String, IncludeError> {
+        let path = normalize(path);
+        let mut expansion = Expansion::default();
+
+        expansion.expand(&self.fs, &path, None)?;
+        self.dependencies.insert(path, expansion.files);
+
+        Ok(expansion.output)
+    }
+
+    /// Every file the shader at `path` was built from the last time it was resolved, itself
+    /// included.
+    pub fn dependencies(&self, path: &str) -> &[String] {
+        self.dependencies
+            .get(&normalize(path))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// The shaders that have to be resolved again because the file at `path` changed.
+    pub fn affected_by(&self, path: &str) -> Vec<&str> {
+        let path = normalize(path);
+
+        self.dependencies
+            .iter()
+            .filter(|(_, files)| files.contains(&path))
+            .map(|(shader, _)| shader.as_str())
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct Expansion {
+    output: String,
+    files: Vec<String>,
+    pasted: HashSet<String>,
+    stack: Vec<String>,
+}
+
+impl Expansion {
+    fn expand(
+        &mut self,
+        fs: &ShaderFs,
+        path: &str,
+        included_from: Option<&str>,
+    ) -> Result<(), IncludeError> {
+        if self.stack.iter().any(|open| open == path) {
+            let mut chain = self.stack.clone();
+            chain.push(path.to_owned());
+            return Err(IncludeError::Cycle(chain));
+        }
+
+        if !self.pasted.insert(path.to_owned()) {
+            return Ok(());
+        }
+
+        let source = fs
+            .read(path)
+            .map_err(|e| IncludeError::Io(path.to_owned(), e))?
+            .ok_or_else(|| IncludeError::NotFound {
+                path: path.to_owned(),
+                included_from: included_from.map(str::to_owned),
+            })?;
+
+        self.files.push(path.to_owned());
+        self.stack.push(path.to_owned());
+
+        for (index, line) in source.lines().enumerate() {
+            let Some(directive) = line.trim_start().strip_prefix("#include") else {
+                self.output.push_str(line);
+                self.output.push('\n');
+                continue;
+            };
+
+            let malformed = || IncludeError::Malformed {
+                path: path.to_owned(),
+                line: index + 1,
+            };
+
+            let directive = directive.trim();
+            let target = if let Some(relative) = directive
+                .strip_prefix('"')
+                .and_then(|rest| rest.strip_suffix('"'))
+            {
+                match path.rsplit_once('/') {
+                    Some((dir, _)) => normalize(&format!("{}/{}", dir, relative)),
+                    None => normalize(relative),
+                }
+            } else if let Some(absolute) = directive
+                .strip_prefix('<')
+                .and_then(|rest| rest.strip_suffix('>'))
+            {
+                normalize(absolute)
+            } else {
+                return Err(malformed());
+            };
+
+            if target.is_empty() {
+                return Err(malformed());
+            }
+
+            self.expand(fs, &target, Some(path))?;
+        }
+
+        self.stack.pop();
+
+        Ok(())
+    }
+}
+
+/// Turns `path` into the form files are keyed by: `/`-separated, without `.` and `..` components
+/// and relative to the root.
+fn normalize(path: &str) -> String {
+    let mut components = Vec::new();
+
+    for component in path.split(['/', '\\']) {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+
+    components.join("/")
+}
+
+#[derive(Debug)]
+pub enum IncludeError {
+    NotFound {
+        path: String,
+        included_from: Option<String>,
+    },
+    /// The chain of includes that leads back to a file still being expanded.
+    Cycle(Vec<String>),
+    Malformed {
+        path: String,
+        line: usize,
+    },
+    Io(String, io::Error),
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotFound {
+                path,
+                included_from: Some(from),
+            } => write!(f, "shader {} included from {} not found", path, from),
+            Self::NotFound {
+                path,
+                included_from: None,
+            } => write!(f, "shader {} not found", path),
+            Self::Cycle(chain) => write!(f, "include cycle: {}", chain.join(" -> ")),
+            Self::Malformed { path, line } => {
+                write!(f, "malformed #include at {}:{}", path, line)
+            }
+            Self::Io(path, e) => write!(f, "failed to read shader {}: {}", path, e),
+        }
+    }
+}
+
+impl error::Error for IncludeError {}
@@ -2,11 +2,15 @@ use std::rc::Rc;
 
 use ash::{
     prelude::VkResult,
-    vk::{self, ShaderModuleCreateInfo},
+    vk::{self, Handle, ShaderModuleCreateInfo},
 };
 
-use crate::logical_device::LogicalDevice;
+use crate::{
+    api2::host_allocation_callbacks,
+    renderer::{logical_device::LogicalDevice, resource_stats::ResourceKind, teardown_trace},
+};
 
+#[derive(Clone)]
 pub struct ShaderModule(Rc<InnerShaderModule>);
 
 impl ShaderModule {
@@ -16,9 +20,13 @@ impl ShaderModule {
         let shader_module = unsafe {
             logical_device
                 .device()
-                .create_shader_module(&create_info, None)?
+                .create_shader_module(&create_info, host_allocation_callbacks())?
         };
 
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::ShaderModule, 1, 0);
+
         Ok(Self(Rc::new(InnerShaderModule {
             shader_module,
             logical_device,
@@ -28,6 +36,10 @@ impl ShaderModule {
     pub fn shader_module(&self) -> &vk::ShaderModule {
         &self.0.shader_module
     }
+
+    pub fn is_shared(&self) -> bool {
+        Rc::strong_count(&self.0) > 1
+    }
 }
 
 struct InnerShaderModule {
@@ -39,10 +51,16 @@ struct InnerShaderModule {
 
 impl Drop for InnerShaderModule {
     fn drop(&mut self) {
+        teardown_trace::record("ShaderModule", [self.shader_module.as_raw()]);
+
         unsafe {
             self.logical_device
                 .device()
-                .destroy_shader_module(self.shader_module, None);
+                .destroy_shader_module(self.shader_module, host_allocation_callbacks());
         }
+
+        self.logical_device
+            .resource_tracker()
+            .untrack(ResourceKind::ShaderModule, 1, 0);
     }
 }
@@ -0,0 +1,540 @@
+//! Skeletal animation: joint hierarchies laid out like glTF skins, animation clips sampled into
+//! poses, and the joint matrix palettes `shaders/pbr_skinned.vert` deforms vertices with.
+//!
+//! `examples/skinning.rs` bends a tube with a looping clip on a three-joint chain.
+
+use std::{error, fmt, mem, rc::Rc};
+
+use ash::vk::{
+    self, BufferCreateInfo, BufferUsageFlags, DescriptorBufferInfo, DeviceSize, Format, Handle,
+    MemoryAllocateInfo, MemoryMapFlags, MemoryPropertyFlags, SharingMode,
+    VertexInputAttributeDescription, VertexInputBindingDescription, VertexInputRate, WHOLE_SIZE,
+};
+use nalgebra_glm as glm;
+
+use crate::{
+    api2::{host_allocation_callbacks, ErrorCtx, ResultExt, Transform},
+    renderer::{
+        logical_device::LogicalDevice, resource_stats::ResourceKind, teardown_trace,
+        MAX_FRAMES_IN_FLIGHT,
+    },
+    types::Pod,
+};
+
+/// A column-major joint matrix, as the shaders read it.
+pub type JointMatrix = [[f32; 4]; 4];
+
+/// The vertex layout `shaders/pbr_skinned.vert` reads, a [PbrVertex](crate::renderer::pbr::PbrVertex)
+/// followed by the joints influencing it.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct SkinnedVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    /// xyz is the tangent, w the handedness of the bitangent, like glTF.
+    pub tangent: [f32; 4],
+    pub uv: [f32; 2],
+    /// Indices into the skeleton's joints, like glTF's `JOINTS_0`.
+    pub joints: [u16; 4],
+    /// How much each joint moves the vertex, summing to 1, like glTF's `WEIGHTS_0`.
+    pub weights: [f32; 4],
+}
+
+unsafe impl Pod for SkinnedVertex {}
+
+impl SkinnedVertex {
+    pub fn binding_description() -> VertexInputBindingDescription {
+        VertexInputBindingDescription::default()
+            .binding(0)
+            .stride(mem::size_of::<Self>() as u32)
+            .input_rate(VertexInputRate::VERTEX)
+    }
+
+    pub fn attribute_descriptions() -> [VertexInputAttributeDescription; 6] {
+        let attribute = |location, format, offset| {
+            VertexInputAttributeDescription::default()
+                .binding(0)
+                .location(location)
+                .format(format)
+                .offset(offset as u32)
+        };
+
+        [
+            attribute(0, Format::R32G32B32_SFLOAT, mem::offset_of!(Self, position)),
+            attribute(1, Format::R32G32B32_SFLOAT, mem::offset_of!(Self, normal)),
+            attribute(
+                2,
+                Format::R32G32B32A32_SFLOAT,
+                mem::offset_of!(Self, tangent),
+            ),
+            attribute(3, Format::R32G32_SFLOAT, mem::offset_of!(Self, uv)),
+            attribute(4, Format::R16G16B16A16_UINT, mem::offset_of!(Self, joints)),
+            attribute(
+                5,
+                Format::R32G32B32A32_SFLOAT,
+                mem::offset_of!(Self, weights),
+            ),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Joint {
+    pub name: String,
+    /// The index of the parent joint, which has to come before this one.
+    pub parent: Option<usize>,
+    /// Transforms the mesh from model space into the joint's space at bind time.
+    pub inverse_bind: glm::Mat4,
+    /// The transform relative to the parent when no animation moves the joint.
+    pub rest: Transform,
+}
+
+/// The joints of a skin, parents always before their children so a pose can be resolved in one
+/// pass.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Skeleton {
+    joints: Vec<Joint>,
+}
+
+impl Skeleton {
+    pub fn new(joints: Vec<Joint>) -> Result<Self, SkeletonError> {
+        for (index, joint) in joints.iter().enumerate() {
+            match joint.parent {
+                Some(parent) if parent >= index => {
+                    return Err(SkeletonError::ParentAfterChild {
+                        joint: index,
+                        parent,
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self { joints })
+    }
+
+    /// Creates a skeleton from the arrays of a glTF skin: the parent of each joint in the skin's
+    /// joint order, the `inverseBindMatrices` accessor and the joints' node transforms. A missing
+    /// inverse bind matrix is the identity, like glTF specifies.
+    pub fn from_gltf_skin(
+        parents: &[Option<usize>],
+        inverse_bind_matrices: &[JointMatrix],
+        rest: &[Transform],
+    ) -> Result<Self, SkeletonError> {
+        if rest.len() != parents.len()
+            || !(inverse_bind_matrices.is_empty() || inverse_bind_matrices.len() == parents.len())
+        {
+            return Err(SkeletonError::LengthMismatch {
+                joints: parents.len(),
+                inverse_bind_matrices: inverse_bind_matrices.len(),
+                rest: rest.len(),
+            });
+        }
+
+        let joints = parents
+            .iter()
+            .zip(rest)
+            .enumerate()
+            .map(|(index, (&parent, &rest))| Joint {
+                name: format!("joint{}", index),
+                parent,
+                inverse_bind: inverse_bind_matrices
+                    .get(index)
+                    .map_or_else(glm::Mat4::identity, |&matrix| matrix.into()),
+                rest,
+            })
+            .collect();
+
+        Self::new(joints)
+    }
+
+    pub fn joints(&self) -> &[Joint] {
+        &self.joints
+    }
+
+    pub fn len(&self) -> usize {
+        self.joints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.joints.is_empty()
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.name == name)
+    }
+
+    /// The pose with every joint at rest.
+    pub fn rest_pose(&self) -> Pose {
+        Pose {
+            local: self.joints.iter().map(|joint| joint.rest).collect(),
+        }
+    }
+
+    /// Replaces `palette` with the joint matrices of `pose`, each taking a vertex from bind
+    /// space to where the joint moved it in model space.
+    pub fn compute_palette(&self, pose: &Pose, palette: &mut Vec<JointMatrix>) {
+        assert_eq!(pose.local.len(), self.joints.len(), "the pose has to match");
+
+        let mut model: Vec<glm::Mat4> = Vec::with_capacity(self.joints.len());
+
+        for (joint, local) in self.joints.iter().zip(&pose.local) {
+            let local = local.to_matrix();
+            let joint_model = match joint.parent {
+                Some(parent) => model[parent] * local,
+                None => local,
+            };
+
+            model.push(joint_model);
+        }
+
+        palette.clear();
+        palette.extend(
+            self.joints
+                .iter()
+                .zip(&model)
+                .map(|(joint, model)| JointMatrix::from(model * joint.inverse_bind)),
+        );
+    }
+}
+
+/// The local transform of every joint of a [Skeleton].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Pose {
+    pub local: Vec<Transform>,
+}
+
+/// How a channel gets from one keyframe to the next, like glTF's sampler interpolation.
+/// Cubic splines aren't supported, their tangents have to be dropped when loading.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Interpolation {
+    Step,
+    #[default]
+    Linear,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelValues {
+    Translation(Vec<glm::Vec3>),
+    /// Unit quaternions, spherically interpolated.
+    Rotation(Vec<glm::Quat>),
+    Scale(Vec<glm::Vec3>),
+}
+
+impl ChannelValues {
+    pub fn len(&self) -> usize {
+        match self {
+            ChannelValues::Translation(values) | ChannelValues::Scale(values) => values.len(),
+            ChannelValues::Rotation(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The keyframes of one property of one joint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    pub joint: usize,
+    pub interpolation: Interpolation,
+    /// Increasing keyframe times in seconds, one per value.
+    pub times: Vec<f32>,
+    pub values: ChannelValues,
+}
+
+impl Channel {
+    /// The keyframes around `time` and how far it is between them, clamping outside the range.
+    fn keyframes(&self, time: f32) -> (usize, usize, f32) {
+        let last = self.times.len() - 1;
+        let next = self.times.partition_point(|&keyframe| keyframe <= time);
+
+        if next == 0 {
+            return (0, 0, 0.0);
+        } else if next > last {
+            return (last, last, 0.0);
+        }
+
+        let previous = next - 1;
+        let span = self.times[next] - self.times[previous];
+        let factor = match self.interpolation {
+            Interpolation::Step => 0.0,
+            Interpolation::Linear if span > 0.0 => (time - self.times[previous]) / span,
+            Interpolation::Linear => 0.0,
+        };
+
+        (previous, next, factor)
+    }
+
+    fn apply(&self, time: f32, transform: &mut Transform) {
+        if self.times.is_empty() || self.values.len() != self.times.len() {
+            return;
+        }
+
+        let (a, b, t) = self.keyframes(time);
+
+        match &self.values {
+            ChannelValues::Translation(values) => {
+                transform.translation = glm::lerp(&values[a], &values[b], t);
+            }
+            ChannelValues::Rotation(values) => {
+                transform.rotation = glm::quat_slerp(&values[a], &values[b], t);
+            }
+            ChannelValues::Scale(values) => {
+                transform.scale = glm::lerp(&values[a], &values[b], t);
+            }
+        }
+    }
+}
+
+/// An animation moving the joints of a skeleton.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AnimationClip {
+    pub name: String,
+    channels: Vec<Channel>,
+    duration: f32,
+}
+
+impl AnimationClip {
+    pub fn new(name: impl Into<String>, channels: Vec<Channel>) -> Self {
+        let duration = channels
+            .iter()
+            .filter_map(|channel| channel.times.last().copied())
+            .fold(0.0, f32::max);
+
+        Self {
+            name: name.into(),
+            channels,
+            duration,
+        }
+    }
+
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    /// The time of the last keyframe in seconds.
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    /// Overwrites the joints the clip animates in `pose` with their values at `time`. Joints
+    /// without channels keep whatever `pose` had, usually the rest pose.
+    pub fn sample(&self, time: f32, pose: &mut Pose) {
+        for channel in &self.channels {
+            if let Some(transform) = pose.local.get_mut(channel.joint) {
+                channel.apply(time, transform);
+            }
+        }
+    }
+
+    /// Like [AnimationClip::sample], wrapping `time` around the duration.
+    pub fn sample_looped(&self, time: f32, pose: &mut Pose) {
+        let time = if self.duration > 0.0 {
+            time.rem_euclid(self.duration)
+        } else {
+            0.0
+        };
+
+        self.sample(time, pose);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SkeletonError {
+    /// The joints aren't sorted parents first.
+    ParentAfterChild { joint: usize, parent: usize },
+    LengthMismatch {
+        joints: usize,
+        inverse_bind_matrices: usize,
+        rest: usize,
+    },
+}
+
+impl fmt::Display for SkeletonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkeletonError::ParentAfterChild { joint, parent } => write!(
+                f,
+                "joint {} comes before its parent {}, joints have to be sorted parents first",
+                joint, parent
+            ),
+            SkeletonError::LengthMismatch {
+                joints,
+                inverse_bind_matrices,
+                rest,
+            } => write!(
+                f,
+                "skin has {} joints but {} inverse bind matrices and {} rest transforms",
+                joints, inverse_bind_matrices, rest
+            ),
+        }
+    }
+}
+
+impl error::Error for SkeletonError {}
+
+/// A host-visible storage buffer of joint matrices per frame in flight, bound where
+/// `shaders/skinning.glsl` expects the palette.
+///
+/// Every skinned draw of a frame reads its skeleton's matrices starting at the `first_joint` it
+/// was drawn with, so the palettes of several characters are uploaded one after another.
+#[derive(Clone)]
+pub struct JointPalettes(Rc<InnerJointPalettes>);
+
+impl JointPalettes {
+    /// Creates buffers holding up to `capacity` joint matrices each.
+    pub fn new(logical_device: LogicalDevice, capacity: u32) -> Result<Self, ErrorCtx> {
+        let frame_size =
+            capacity.max(1) as DeviceSize * mem::size_of::<JointMatrix>() as DeviceSize;
+
+        let buffers = (0..MAX_FRAMES_IN_FLIGHT)
+            .map(|_| {
+                let buffer = PaletteBuffer::new(&logical_device, frame_size)?;
+
+                let mapped = unsafe {
+                    logical_device.device().map_memory(
+                        buffer.memory,
+                        0,
+                        WHOLE_SIZE,
+                        MemoryMapFlags::empty(),
+                    )
+                }
+                .context("mapping joint palette memory")?;
+
+                Ok((buffer, mapped.cast::<JointMatrix>()))
+            })
+            .collect::<Result<Vec<_>, ErrorCtx>>()?;
+
+        Ok(Self(Rc::new(InnerJointPalettes {
+            buffers,
+            capacity,
+            logical_device,
+        })))
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.0.capacity
+    }
+
+    /// Copies `palette` into the buffer of frame `frame` from joint `first_joint` on, and
+    /// returns how many matrices fit. The frame's previous submission must have finished.
+    pub fn upload(&self, frame: usize, first_joint: u32, palette: &[JointMatrix]) -> usize {
+        let (_, mapped) = self.0.buffers[frame];
+        let count = palette
+            .len()
+            .min(self.0.capacity.saturating_sub(first_joint) as usize);
+
+        unsafe {
+            mapped
+                .add(first_joint as usize)
+                .copy_from_nonoverlapping(palette.as_ptr(), count)
+        };
+
+        count
+    }
+
+    pub fn descriptor(&self, frame: usize) -> DescriptorBufferInfo {
+        DescriptorBufferInfo::default()
+            .buffer(self.0.buffers[frame].0.buffer)
+            .offset(0)
+            .range(WHOLE_SIZE)
+    }
+}
+
+struct InnerJointPalettes {
+    buffers: Vec<(PaletteBuffer, *mut JointMatrix)>,
+    capacity: u32,
+
+    logical_device: LogicalDevice,
+}
+
+impl Drop for InnerJointPalettes {
+    fn drop(&mut self) {
+        for (buffer, _) in &self.buffers {
+            unsafe { self.logical_device.device().unmap_memory(buffer.memory) };
+        }
+    }
+}
+
+/// A host-visible storage buffer with its own memory allocation.
+struct PaletteBuffer {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    size: DeviceSize,
+
+    logical_device: LogicalDevice,
+}
+
+impl PaletteBuffer {
+    fn new(logical_device: &LogicalDevice, size: DeviceSize) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+
+        let buffer = unsafe {
+            device.create_buffer(
+                &BufferCreateInfo::default()
+                    .size(size)
+                    .usage(BufferUsageFlags::STORAGE_BUFFER)
+                    .sharing_mode(SharingMode::EXCLUSIVE),
+                host_allocation_callbacks(),
+            )
+        }
+        .with_context("creating joint palette buffer", || format!("size={}", size))?;
+
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+
+        let mut palette_buffer = Self {
+            buffer,
+            memory: vk::DeviceMemory::null(),
+            size: requirements.size,
+            logical_device: logical_device.clone(),
+        };
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::Buffer, 1, requirements.size);
+
+        let memory_type_index = logical_device
+            .physical_device()
+            .find_memory_type(
+                requirements.memory_type_bits,
+                MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+            )
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            .with_context("finding joint palette memory", || {
+                format!("memory_type_bits={:#x}", requirements.memory_type_bits)
+            })?;
+
+        palette_buffer.memory = unsafe {
+            device.allocate_memory(
+                &MemoryAllocateInfo::default()
+                    .allocation_size(requirements.size)
+                    .memory_type_index(memory_type_index),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("allocating joint palette memory")?;
+
+        unsafe { device.bind_buffer_memory(buffer, palette_buffer.memory, 0) }
+            .context("binding joint palette memory")?;
+
+        Ok(palette_buffer)
+    }
+}
+
+impl Drop for PaletteBuffer {
+    fn drop(&mut self) {
+        teardown_trace::record("PaletteBuffer", [self.buffer.as_raw()]);
+
+        unsafe {
+            let device = self.logical_device.device();
+            device.destroy_buffer(self.buffer, host_allocation_callbacks());
+            device.free_memory(self.memory, host_allocation_callbacks());
+        }
+
+        self.logical_device
+            .resource_tracker()
+            .untrack(ResourceKind::Buffer, 1, self.size);
+    }
+}
@@ -0,0 +1,431 @@
+use std::{
+    collections::VecDeque,
+    error, fmt, slice,
+    time::{Duration, Instant},
+};
+
+use ash::vk::{
+    self, BufferCreateInfo, BufferUsageFlags, Handle, MemoryAllocateInfo, MemoryMapFlags,
+    MemoryPropertyFlags, SharingMode, WHOLE_SIZE,
+};
+
+use crate::{
+    api2::{align_up, host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{logical_device::LogicalDevice, resource_stats::ResourceKind, teardown_trace},
+};
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct StagingStats {
+    pub allocations: u64,
+    pub bytes_allocated: u64,
+    /// Allocations that didn't fit before the end of the buffer and started over at offset 0.
+    pub wraps: u64,
+    /// Allocations that had to wait for the GPU to finish with older uploads.
+    pub stalls: u64,
+    pub stall_time: Duration,
+    /// The most bytes in use at once, including alignment and wraparound waste.
+    pub high_water_mark: u64,
+}
+
+/// A piece of the staging buffer to write upload data into.
+pub struct StagingAllocation<'a> {
+    pub buffer: vk::Buffer,
+    pub offset: vk::DeviceSize,
+    pub data: &'a mut [u8],
+}
+
+/// Where the used part of a ring of `capacity` bytes starts and ends, kept apart from the buffer
+/// so the arithmetic doesn't need a device.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct RingSpace {
+    capacity: u64,
+    head: u64,
+    tail: u64,
+    used: u64,
+}
+
+/// Space taken by [RingSpace::allocate].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct RingAllocation {
+    offset: u64,
+    /// The allocated bytes together with those skipped before them.
+    bytes: u64,
+    /// Whether the allocation didn't fit before the end and started over at offset 0.
+    wrapped: bool,
+}
+
+impl RingSpace {
+    fn new(capacity: u64) -> Self {
+        Self {
+            capacity,
+            head: 0,
+            tail: 0,
+            used: 0,
+        }
+    }
+
+    fn allocate(&mut self, size: u64, alignment: u64) -> Option<RingAllocation> {
+        if self.used == 0 {
+            self.head = 0;
+            self.tail = 0;
+        }
+
+        let aligned = align_up(self.head, alignment);
+
+        // The free space is [head, capacity) followed by [0, tail) when the used part doesn't
+        // wrap, and [head, tail) when it does.
+        // The skipped bytes, for alignment or the end of the buffer, stay used until the
+        // allocation is released.
+        let (offset, skipped, wrapped) = if self.head > self.tail || self.used == 0 {
+            if aligned + size <= self.capacity {
+                (aligned, aligned - self.head, false)
+            } else if size <= self.tail {
+                (0, self.capacity - self.head, true)
+            } else {
+                return None;
+            }
+        } else if self.head < self.tail && aligned + size <= self.tail {
+            (aligned, aligned - self.head, false)
+        } else {
+            return None;
+        };
+
+        self.used += skipped + size;
+        self.head = offset + size;
+
+        Some(RingAllocation {
+            offset,
+            bytes: skipped + size,
+            wrapped,
+        })
+    }
+
+    /// Frees the oldest `bytes`, which end at `end`.
+    fn release(&mut self, end: u64, bytes: u64) {
+        self.tail = end;
+        self.used -= bytes;
+    }
+}
+
+/// Uploads submitted together with the fence that signals when the GPU is done reading them.
+struct InFlightRegion {
+    fence: vk::Fence,
+    end: u64,
+    bytes: u64,
+}
+
+/// A fixed-size, persistently mapped staging buffer handed out front to back and reused once the
+/// GPU has consumed older uploads, so streaming data never allocates.
+///
+/// Allocations made since the last [StagingRing::submit] belong to the next submission. When the
+/// buffer is full, allocating waits for the oldest submitted fence. Fences are only borrowed, so
+/// call [StagingRing::retire] after waiting for a frame's fence and before resetting it.
+pub struct StagingRing {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    mapped: *mut u8,
+
+    space: RingSpace,
+    open_bytes: u64,
+    in_flight: VecDeque<InFlightRegion>,
+    stats: StagingStats,
+
+    logical_device: LogicalDevice,
+}
+
+impl StagingRing {
+    pub fn new(logical_device: LogicalDevice, capacity: vk::DeviceSize) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+
+        let buffer = unsafe {
+            device.create_buffer(
+                &BufferCreateInfo::default()
+                    .size(capacity)
+                    .usage(BufferUsageFlags::TRANSFER_SRC)
+                    .sharing_mode(SharingMode::EXCLUSIVE),
+                host_allocation_callbacks(),
+            )
+        }
+        .with_context("creating staging ring buffer", || {
+            format!("capacity={}", capacity)
+        })?;
+
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+
+        let memory_type_index = logical_device
+            .physical_device()
+            .find_memory_type(
+                requirements.memory_type_bits,
+                MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+            )
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            .with_context("finding staging ring memory", || {
+                format!("memory_type_bits={:#x}", requirements.memory_type_bits)
+            })?;
+
+        let memory = unsafe {
+            device.allocate_memory(
+                &MemoryAllocateInfo::default()
+                    .allocation_size(requirements.size)
+                    .memory_type_index(memory_type_index),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("allocating staging ring memory")?;
+
+        let mapped = unsafe {
+            device
+                .bind_buffer_memory(buffer, memory, 0)
+                .context("binding staging ring memory")?;
+
+            device
+                .map_memory(memory, 0, WHOLE_SIZE, MemoryMapFlags::empty())
+                .context("mapping staging ring memory")?
+        };
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::Buffer, 1, requirements.size);
+
+        Ok(Self {
+            buffer,
+            memory,
+            mapped: mapped.cast(),
+            space: RingSpace::new(capacity),
+            open_bytes: 0,
+            in_flight: VecDeque::new(),
+            stats: StagingStats::default(),
+            logical_device,
+        })
+    }
+
+    pub fn buffer(&self) -> vk::Buffer {
+        self.buffer
+    }
+
+    pub fn capacity(&self) -> vk::DeviceSize {
+        self.space.capacity
+    }
+
+    pub fn stats(&self) -> StagingStats {
+        self.stats
+    }
+
+    /// Reserves `size` bytes at an offset that is a multiple of `alignment`, a power of two,
+    /// waiting for the GPU if the ring is full.
+    pub fn allocate(
+        &mut self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+    ) -> Result<StagingAllocation<'_>, StagingError> {
+        if size > self.space.capacity {
+            return Err(StagingError::TooLarge {
+                size,
+                capacity: self.space.capacity,
+            });
+        }
+
+        self.retire()?;
+
+        let offset = loop {
+            if let Some(offset) = self.try_allocate(size, alignment) {
+                break offset;
+            }
+
+            // Only what this submission already allocated is left, which can't be waited on.
+            let Some(oldest) = self.in_flight.front() else {
+                return Err(StagingError::OutOfSpace { size });
+            };
+
+            let started = Instant::now();
+
+            unsafe {
+                self.logical_device
+                    .device()
+                    .wait_for_fences(&[oldest.fence], true, u64::MAX)
+            }?;
+
+            self.stats.stalls += 1;
+            self.stats.stall_time += started.elapsed();
+            self.retire()?;
+        };
+
+        self.stats.allocations += 1;
+        self.stats.bytes_allocated += size;
+        self.stats.high_water_mark = self.stats.high_water_mark.max(self.space.used);
+
+        Ok(StagingAllocation {
+            buffer: self.buffer,
+            offset,
+            // SAFETY: the range is inside the mapping and not handed out again until the GPU is
+            // done with it, and the returned borrow keeps the ring from allocating meanwhile.
+            data: unsafe {
+                slice::from_raw_parts_mut(self.mapped.add(offset as usize), size as usize)
+            },
+        })
+    }
+
+    /// Copies `data` into the ring and returns its offset in [StagingRing::buffer].
+    pub fn write(
+        &mut self,
+        data: &[u8],
+        alignment: vk::DeviceSize,
+    ) -> Result<vk::DeviceSize, StagingError> {
+        let allocation = self.allocate(data.len() as u64, alignment)?;
+        allocation.data.copy_from_slice(data);
+
+        Ok(allocation.offset)
+    }
+
+    /// Hands everything allocated since the last call to the submission `fence` will signal for.
+    pub fn submit(&mut self, fence: vk::Fence) {
+        if self.open_bytes == 0 {
+            return;
+        }
+
+        self.in_flight.push_back(InFlightRegion {
+            fence,
+            end: self.space.head,
+            bytes: self.open_bytes,
+        });
+        self.open_bytes = 0;
+    }
+
+    /// Frees the space of every submission the GPU has finished.
+    pub fn retire(&mut self) -> Result<(), StagingError> {
+        while let Some(oldest) = self.in_flight.front() {
+            if !unsafe { self.logical_device.device().get_fence_status(oldest.fence) }? {
+                break;
+            }
+
+            self.space.release(oldest.end, oldest.bytes);
+            self.in_flight.pop_front();
+        }
+
+        Ok(())
+    }
+
+    fn try_allocate(&mut self, size: u64, alignment: u64) -> Option<u64> {
+        let allocation = self.space.allocate(size, alignment)?;
+
+        if allocation.wrapped {
+            self.stats.wraps += 1;
+        }
+
+        self.open_bytes += allocation.bytes;
+
+        Some(allocation.offset)
+    }
+}
+
+impl Drop for StagingRing {
+    fn drop(&mut self) {
+        teardown_trace::record("StagingRing", [self.buffer.as_raw()]);
+
+        let device = self.logical_device.device();
+        let requirements = unsafe { device.get_buffer_memory_requirements(self.buffer) };
+
+        unsafe {
+            device.unmap_memory(self.memory);
+            device.destroy_buffer(self.buffer, host_allocation_callbacks());
+            device.free_memory(self.memory, host_allocation_callbacks());
+        }
+
+        self.logical_device
+            .resource_tracker()
+            .untrack(ResourceKind::Buffer, 1, requirements.size);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum StagingError {
+    Vulkan(vk::Result),
+    /// The allocation is bigger than the whole ring.
+    TooLarge {
+        size: u64,
+        capacity: u64,
+    },
+    /// The current submission already fills the ring, submit it before allocating more.
+    OutOfSpace {
+        size: u64,
+    },
+}
+
+impl From<vk::Result> for StagingError {
+    fn from(value: vk::Result) -> Self {
+        Self::Vulkan(value)
+    }
+}
+
+impl fmt::Display for StagingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Vulkan(e) => e.fmt(f),
+            Self::TooLarge { size, capacity } => write!(
+                f,
+                "staging allocation of {} bytes exceeds the ring's {} bytes",
+                size, capacity
+            ),
+            Self::OutOfSpace { size } => write!(
+                f,
+                "no room for {} more staging bytes until the pending uploads are submitted",
+                size
+            ),
+        }
+    }
+}
+
+impl error::Error for StagingError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocations_skip_to_their_alignment() {
+        let mut space = RingSpace::new(64);
+
+        assert_eq!(space.allocate(3, 1).map(|a| a.offset), Some(0));
+        let allocation = space.allocate(8, 16).unwrap();
+        assert_eq!(allocation.offset, 16);
+        // The 13 bytes skipped stay used until the allocation is released.
+        assert_eq!(allocation.bytes, 21);
+        assert_eq!((space.head, space.used), (24, 24));
+    }
+
+    #[test]
+    fn allocations_wrap_around_behind_the_tail() {
+        let mut space = RingSpace::new(64);
+        space.allocate(24, 1);
+        space.allocate(24, 1);
+        space.release(24, 24);
+
+        // 16 bytes are left at the end, so 20 start over at 0 and skip them.
+        let allocation = space.allocate(20, 1).unwrap();
+        assert!(allocation.wrapped);
+        assert_eq!((allocation.offset, allocation.bytes), (0, 36));
+        assert_eq!((space.head, space.tail, space.used), (20, 24, 60));
+
+        // Once wrapped, only the gap up to the tail is free.
+        assert_eq!(space.allocate(8, 1), None);
+        assert_eq!(space.allocate(4, 1).map(|a| a.offset), Some(20));
+    }
+
+    #[test]
+    fn a_full_ring_has_its_head_on_its_tail() {
+        let mut space = RingSpace::new(64);
+        space.allocate(32, 1);
+        space.allocate(32, 1);
+        space.release(32, 32);
+        space.allocate(32, 1);
+
+        assert_eq!(space.head, space.tail);
+        assert_eq!(space.used, 64);
+        assert_eq!(space.allocate(1, 1), None);
+
+        // Releasing everything starts over at 0.
+        space.release(64, 32);
+        space.release(32, 32);
+        assert_eq!(space.allocate(64, 1).map(|a| a.offset), Some(0));
+    }
+}
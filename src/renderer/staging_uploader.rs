@@ -0,0 +1,393 @@
+//! Blocking uploads to device-local memory through a staging buffer, for data uploaded once like
+//! meshes and textures. Every upload records its copy into a fresh command buffer, submits it and
+//! waits, so nothing needs to outlive the call. Uploads done every frame belong in a
+//! [StagingRing](crate::renderer::staging_ring::StagingRing) instead.
+
+use std::{error, fmt, mem};
+
+use ash::vk::{
+    self, AccessFlags, BufferCopy, BufferUsageFlags, CommandBuffer, CommandBufferAllocateInfo,
+    CommandBufferBeginInfo, CommandBufferLevel, CommandBufferUsageFlags, DependencyFlags,
+    DeviceSize, FenceCreateInfo, Format, Handle, ImageLayout, ImageMemoryBarrier, MemoryBarrier,
+    MemoryPropertyFlags, PipelineStageFlags, SubmitInfo,
+};
+
+use crate::{
+    api2::{host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        buffer::Buffer,
+        command_pool::CommandPool,
+        image_desc::{ImageDesc, TexelBlock},
+        logical_device::LogicalDevice,
+        teardown_trace,
+    },
+    types::Pod,
+};
+
+pub struct StagingUploader {
+    command_pool: CommandPool,
+    fence: vk::Fence,
+
+    logical_device: LogicalDevice,
+}
+
+impl StagingUploader {
+    /// Creates an uploader submitting command buffers of `command_pool` to the graphics queue.
+    pub fn new(command_pool: CommandPool) -> Result<Self, ErrorCtx> {
+        let logical_device = command_pool.logical_device().clone();
+
+        let fence = unsafe {
+            logical_device
+                .device()
+                .create_fence(&FenceCreateInfo::default(), host_allocation_callbacks())
+        }
+        .context("creating staging upload fence")?;
+
+        Ok(Self {
+            command_pool,
+            fence,
+            logical_device,
+        })
+    }
+
+    pub fn logical_device(&self) -> &LogicalDevice {
+        &self.logical_device
+    }
+
+    /// Creates a device-local buffer holding `data`.
+    pub fn create_buffer<T: Pod>(
+        &self,
+        data: &[T],
+        usage: BufferUsageFlags,
+    ) -> Result<Buffer, UploadError> {
+        let buffer = Buffer::new(
+            self.logical_device.clone(),
+            mem::size_of_val(data) as DeviceSize,
+            usage | BufferUsageFlags::TRANSFER_DST,
+            MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+
+        self.upload_buffer(&buffer, 0, data)?;
+
+        Ok(buffer)
+    }
+
+    /// Copies `data` to `offset` bytes into `buffer`, which needs the `TRANSFER_DST` usage.
+    pub fn upload_buffer<T: Pod>(
+        &self,
+        buffer: &Buffer,
+        offset: DeviceSize,
+        data: &[T],
+    ) -> Result<(), UploadError> {
+        let size = mem::size_of_val(data) as DeviceSize;
+
+        if offset
+            .checked_add(size)
+            .map_or(true, |end| end > buffer.size())
+        {
+            return Err(UploadError::OutOfBounds {
+                offset,
+                size,
+                capacity: buffer.size(),
+            });
+        }
+
+        if size == 0 {
+            return Ok(());
+        }
+
+        let staging = self.stage(data)?;
+
+        self.submit("uploading buffer", |command_buffer| unsafe {
+            self.logical_device.device().cmd_copy_buffer(
+                command_buffer,
+                *staging.buffer(),
+                *buffer.buffer(),
+                &[BufferCopy::default().dst_offset(offset).size(size)],
+            );
+        })?;
+
+        Ok(())
+    }
+
+    /// Copies every mip and layer of `image` from `data`, holding them packed as
+    /// [ImageDesc::mip_offset] lays them out in blocks of `block`. The image needs the
+    /// `TRANSFER_DST` usage, its contents are discarded and it's left ready for fragment shaders
+    /// to sample.
+    pub fn upload_image(
+        &self,
+        image: vk::Image,
+        desc: &ImageDesc,
+        block: TexelBlock,
+        data: &[u8],
+    ) -> Result<(), UploadError> {
+        let expected = desc.staging_size(block);
+
+        if data.len() as DeviceSize != expected {
+            return Err(UploadError::ImageSize {
+                expected,
+                found: data.len(),
+            });
+        }
+
+        let staging = self.stage(data)?;
+
+        self.submit("uploading image", |command_buffer| unsafe {
+            let device = self.logical_device.device();
+
+            let barrier = ImageMemoryBarrier::default()
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(desc.full_range());
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::TOP_OF_PIPE,
+                PipelineStageFlags::TRANSFER,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier
+                    .old_layout(ImageLayout::UNDEFINED)
+                    .new_layout(ImageLayout::TRANSFER_DST_OPTIMAL)
+                    .dst_access_mask(AccessFlags::TRANSFER_WRITE)],
+            );
+            device.cmd_copy_buffer_to_image(
+                command_buffer,
+                *staging.buffer(),
+                image,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                &desc.copy_regions(0, block),
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::FRAGMENT_SHADER,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier
+                    .old_layout(ImageLayout::TRANSFER_DST_OPTIMAL)
+                    .new_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    .src_access_mask(AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(AccessFlags::SHADER_READ)],
+            );
+        })?;
+
+        Ok(())
+    }
+
+    /// Reads every mip and layer of `image` back, packed as [ImageDesc::mip_offset] lays them
+    /// out in blocks of `block`. The image needs the `TRANSFER_SRC` usage and has to be in
+    /// `layout`, which it's left in, after the GPU is done writing it.
+    pub fn download_image(
+        &self,
+        image: vk::Image,
+        desc: &ImageDesc,
+        block: TexelBlock,
+        layout: ImageLayout,
+    ) -> Result<Vec<u8>, UploadError> {
+        let size = desc.staging_size(block);
+        let readback = Buffer::new(
+            self.logical_device.clone(),
+            size,
+            BufferUsageFlags::TRANSFER_DST,
+            MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+
+        self.submit("downloading image", |command_buffer| unsafe {
+            let device = self.logical_device.device();
+
+            let barrier = ImageMemoryBarrier::default()
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(desc.full_range());
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::ALL_COMMANDS,
+                PipelineStageFlags::TRANSFER,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier
+                    .old_layout(layout)
+                    .new_layout(ImageLayout::TRANSFER_SRC_OPTIMAL)
+                    .src_access_mask(AccessFlags::MEMORY_WRITE)
+                    .dst_access_mask(AccessFlags::TRANSFER_READ)],
+            );
+            device.cmd_copy_image_to_buffer(
+                command_buffer,
+                image,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                *readback.buffer(),
+                &desc.copy_regions(0, block),
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::ALL_COMMANDS,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier
+                    .old_layout(ImageLayout::TRANSFER_SRC_OPTIMAL)
+                    .new_layout(layout)
+                    .src_access_mask(AccessFlags::TRANSFER_READ)
+                    .dst_access_mask(AccessFlags::MEMORY_READ)],
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::HOST,
+                DependencyFlags::empty(),
+                &[MemoryBarrier::default()
+                    .src_access_mask(AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(AccessFlags::HOST_READ)],
+                &[],
+                &[],
+            );
+        })?;
+
+        Ok(readback.read(0, size)?)
+    }
+
+    fn stage<T: Pod>(&self, data: &[T]) -> Result<Buffer, ErrorCtx> {
+        Buffer::with_data(
+            self.logical_device.clone(),
+            data,
+            BufferUsageFlags::TRANSFER_SRC,
+        )
+    }
+
+    /// Records `record` into a new command buffer, submits it and waits for it to finish.
+    fn submit(
+        &self,
+        operation: &'static str,
+        record: impl FnOnce(CommandBuffer),
+    ) -> Result<(), ErrorCtx> {
+        let device = self.logical_device.device();
+        let command_pool = *self.command_pool.command_pool();
+
+        let command_buffers = unsafe {
+            device.allocate_command_buffers(
+                &CommandBufferAllocateInfo::default()
+                    .command_pool(command_pool)
+                    .level(CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(1),
+            )
+        }
+        .context("allocating staging upload command buffer")?;
+
+        let result = unsafe {
+            device
+                .begin_command_buffer(
+                    command_buffers[0],
+                    &CommandBufferBeginInfo::default()
+                        .flags(CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+                )
+                .context("beginning staging upload command buffer")
+                .and_then(|()| {
+                    record(command_buffers[0]);
+
+                    device
+                        .end_command_buffer(command_buffers[0])
+                        .context("ending staging upload command buffer")
+                })
+                .and_then(|()| {
+                    device
+                        .reset_fences(&[self.fence])
+                        .context("resetting staging upload fence")
+                })
+                .and_then(|()| {
+                    device
+                        .queue_submit(
+                            *self.logical_device.queue(),
+                            &[SubmitInfo::default().command_buffers(&command_buffers)],
+                            self.fence,
+                        )
+                        .context(operation)
+                })
+                .and_then(|()| {
+                    device
+                        .wait_for_fences(&[self.fence], true, u64::MAX)
+                        .context("waiting for staging upload")
+                })
+        };
+
+        // A failed wait leaves the command buffer possibly pending, waiting for the whole device
+        // is the only way to free it then.
+        if result.is_err() {
+            let _ = self.logical_device.wait_idle();
+        }
+
+        unsafe { device.free_command_buffers(command_pool, &command_buffers) };
+
+        result
+    }
+}
+
+impl Drop for StagingUploader {
+    fn drop(&mut self) {
+        teardown_trace::record("StagingUploader", [self.fence.as_raw()]);
+
+        unsafe {
+            self.logical_device
+                .device()
+                .destroy_fence(self.fence, host_allocation_callbacks());
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum UploadError {
+    Context(ErrorCtx),
+    /// The data doesn't fit the buffer past the offset.
+    OutOfBounds {
+        offset: DeviceSize,
+        size: DeviceSize,
+        capacity: DeviceSize,
+    },
+    /// The data isn't exactly every mip and layer of the image.
+    ImageSize {
+        expected: DeviceSize,
+        found: usize,
+    },
+    /// The size of the format's texel blocks isn't known.
+    UnsupportedFormat(Format),
+}
+
+impl From<ErrorCtx> for UploadError {
+    fn from(value: ErrorCtx) -> Self {
+        Self::Context(value)
+    }
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Context(e) => e.fmt(f),
+            Self::OutOfBounds {
+                offset,
+                size,
+                capacity,
+            } => write!(
+                f,
+                "uploading {} bytes at offset {} overruns the {} byte buffer",
+                size, offset, capacity
+            ),
+            Self::ImageSize { expected, found } => write!(
+                f,
+                "the image takes {} bytes of data but {} were given",
+                expected, found
+            ),
+            Self::UnsupportedFormat(format) => {
+                write!(f, "uploading images of format {:?} isn't supported", format)
+            }
+        }
+    }
+}
+
+impl error::Error for UploadError {}
@@ -0,0 +1,365 @@
+//! Tags queue submissions with a label and the time they were submitted, and measures when the
+//! GPU started and finished them, to find stalls between recording and execution.
+//!
+//! The command buffers write a timestamp at their start and end, see [SubmitTracer::write_begin]
+//! and [SubmitTracer::write_end]. Submitting through [SubmitTracer::submit] wraps the submission
+//! in a debug-utils queue label, so it shows up by name in tools like RenderDoc, and notes the CPU
+//! time. Once the submission's fence was waited on, [SubmitTracer::collect] reads its timestamps.
+
+use std::{
+    cell::{Cell, RefCell},
+    ffi::CString,
+    fmt, fs, io,
+    path::Path,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use ash::{
+    ext::debug_utils,
+    prelude::VkResult,
+    vk::{
+        CommandBuffer, DebugUtilsLabelEXT, Fence, Handle, PipelineStageFlags, QueryPool,
+        QueryPoolCreateInfo, QueryResultFlags, QueryType, Queue, SubmitInfo,
+    },
+};
+
+use crate::{
+    api2::{host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{logical_device::LogicalDevice, teardown_trace, ENABLE_VALIDATION_LAYERS},
+};
+
+#[derive(Clone)]
+pub struct SubmitTracer(Rc<InnerSubmitTracer>);
+
+impl SubmitTracer {
+    /// Creates a tracer for `slots` submissions in flight, usually one per command buffer.
+    ///
+    /// Without timestamp support on the graphics queue only the CPU side is traced, and without
+    /// the debug-utils extension submissions aren't labeled.
+    pub fn new(logical_device: LogicalDevice, slots: usize) -> Result<Self, ErrorCtx> {
+        let physical_device = logical_device.physical_device();
+        let instance = physical_device.instance().instance();
+
+        let limits = physical_device.properties().limits;
+        let timestamp_valid_bits = unsafe {
+            instance.get_physical_device_queue_family_properties(*physical_device.device())
+        }[physical_device.graphics_family_u32() as usize]
+            .timestamp_valid_bits;
+
+        let query_pool = if limits.timestamp_compute_and_graphics != 0 && timestamp_valid_bits > 0 {
+            let create_info = QueryPoolCreateInfo::default()
+                .query_type(QueryType::TIMESTAMP)
+                .query_count(2 * slots as u32);
+
+            Some(
+                unsafe {
+                    logical_device
+                        .device()
+                        .create_query_pool(&create_info, host_allocation_callbacks())
+                }
+                .with_context("creating submit trace query pool", || {
+                    format!("slots={}", slots)
+                })?,
+            )
+        } else {
+            println!("timestamps aren't supported on the graphics queue, only tracing submits");
+            None
+        };
+
+        // The instance only enables debug-utils along with the validation layers.
+        let debug_utils = ENABLE_VALIDATION_LAYERS
+            .then(|| debug_utils::Device::new(instance, logical_device.device()));
+
+        Ok(Self(Rc::new(InnerSubmitTracer {
+            query_pool,
+            debug_utils,
+            timestamp_period: limits.timestamp_period as f64,
+            timestamp_mask: 1u64
+                .checked_shl(timestamp_valid_bits)
+                .map_or(u64::MAX, |bit| bit - 1),
+            epoch: Instant::now(),
+            pending: RefCell::new((0..slots).map(|_| None).collect()),
+            records: RefCell::new(Vec::new()),
+            next_sequence: Cell::new(0),
+            logical_device,
+        })))
+    }
+
+    /// Writes the timestamp marking the start of the slot's work, right after beginning the
+    /// command buffer.
+    pub fn write_begin(&self, command_buffer: CommandBuffer, slot: usize) {
+        let Some(query_pool) = self.0.query_pool else {
+            return;
+        };
+
+        let device = self.0.logical_device.device();
+
+        unsafe {
+            device.cmd_reset_query_pool(command_buffer, query_pool, 2 * slot as u32, 2);
+            device.cmd_write_timestamp(
+                command_buffer,
+                PipelineStageFlags::TOP_OF_PIPE,
+                query_pool,
+                2 * slot as u32,
+            );
+        }
+    }
+
+    /// Writes the timestamp marking the end of the slot's work, right before ending the command
+    /// buffer.
+    pub fn write_end(&self, command_buffer: CommandBuffer, slot: usize) {
+        let Some(query_pool) = self.0.query_pool else {
+            return;
+        };
+
+        unsafe {
+            self.0.logical_device.device().cmd_write_timestamp(
+                command_buffer,
+                PipelineStageFlags::BOTTOM_OF_PIPE,
+                query_pool,
+                2 * slot as u32 + 1,
+            );
+        }
+    }
+
+    /// Submits to `queue` under `label`, noting the time. The command buffers have to have
+    /// written the slot's timestamps.
+    pub fn submit(
+        &self,
+        queue: Queue,
+        slot: usize,
+        label: &str,
+        submits: &[SubmitInfo],
+        fence: Fence,
+    ) -> VkResult<()> {
+        let sequence = self.0.next_sequence.get();
+        self.0.next_sequence.set(sequence + 1);
+
+        // Labels are null terminated, so anything after a NUL is dropped.
+        let label_name =
+            CString::new(label.split('\0').next().unwrap_or_default()).unwrap_or_default();
+
+        if let Some(debug_utils) = &self.0.debug_utils {
+            unsafe {
+                debug_utils.queue_begin_debug_utils_label(
+                    queue,
+                    &DebugUtilsLabelEXT::default().label_name(&label_name),
+                );
+            }
+        }
+
+        let cpu_submit = self.0.epoch.elapsed();
+
+        let result = unsafe {
+            self.0
+                .logical_device
+                .device()
+                .queue_submit(queue, submits, fence)
+        };
+
+        if let Some(debug_utils) = &self.0.debug_utils {
+            unsafe { debug_utils.queue_end_debug_utils_label(queue) };
+        }
+
+        result?;
+
+        // A slot still pending was never collected, it's traced without GPU times.
+        self.collect_with(slot, false);
+
+        self.0.pending.borrow_mut()[slot] = Some(PendingSubmit {
+            sequence,
+            label: label.to_owned(),
+            cpu_submit,
+        });
+
+        Ok(())
+    }
+
+    /// Reads the GPU times of the slot's last submission, once its fence was waited on.
+    pub fn collect(&self, slot: usize) {
+        self.collect_with(slot, true);
+    }
+
+    /// Collects every slot, once the device is idle.
+    pub fn collect_all(&self) {
+        let slots = self.0.pending.borrow().len();
+
+        for slot in 0..slots {
+            self.collect(slot);
+        }
+    }
+
+    /// Takes the records of every collected submission, in submission order.
+    pub fn take_trace(&self) -> SubmitTrace {
+        let mut records = self.0.records.take();
+        records.sort_by_key(|record| record.sequence);
+
+        SubmitTrace { records }
+    }
+
+    fn collect_with(&self, slot: usize, read_timestamps: bool) {
+        let Some(pending) = self.0.pending.borrow_mut()[slot].take() else {
+            return;
+        };
+
+        let gpu = match self.0.query_pool {
+            Some(query_pool) if read_timestamps => {
+                let mut ticks = [0u64; 2];
+
+                unsafe {
+                    self.0.logical_device.device().get_query_pool_results(
+                        query_pool,
+                        2 * slot as u32,
+                        &mut ticks,
+                        QueryResultFlags::TYPE_64,
+                    )
+                }
+                .ok()
+                .map(|()| {
+                    let [begin, end] = ticks.map(|tick| self.ticks_to_duration(tick));
+                    (begin, end)
+                })
+            }
+            _ => None,
+        };
+
+        self.0.records.borrow_mut().push(SubmitRecord {
+            sequence: pending.sequence,
+            label: pending.label,
+            cpu_submit: pending.cpu_submit,
+            gpu,
+        });
+    }
+
+    fn ticks_to_duration(&self, ticks: u64) -> Duration {
+        Duration::from_nanos(
+            ((ticks & self.0.timestamp_mask) as f64 * self.0.timestamp_period) as u64,
+        )
+    }
+}
+
+/// A traced submission.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubmitRecord {
+    /// The position of the submission among all traced ones.
+    pub sequence: u64,
+    pub label: String,
+    /// When it was submitted, since the tracer was created.
+    pub cpu_submit: Duration,
+    /// When the GPU started and finished it, on the GPU's own clock. `None` without timestamp
+    /// support or when the results weren't available.
+    pub gpu: Option<(Duration, Duration)>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SubmitTrace {
+    pub records: Vec<SubmitRecord>,
+}
+
+impl SubmitTrace {
+    /// The offset moving GPU times onto the CPU timeline.
+    ///
+    /// The clocks aren't calibrated, so this assumes the submission that waited the least started
+    /// as soon as it was submitted. Queue latencies are then relative to that one, which is
+    /// enough to find the submissions that stalled.
+    pub fn gpu_offset(&self) -> Option<i128> {
+        self.records
+            .iter()
+            .filter_map(|record| {
+                let (begin, _) = record.gpu?;
+                Some(record.cpu_submit.as_nanos() as i128 - begin.as_nanos() as i128)
+            })
+            .max()
+    }
+
+    /// How long each submission waited in the queue before the GPU started it, see
+    /// [SubmitTrace::gpu_offset].
+    pub fn queue_latency(&self, record: &SubmitRecord) -> Option<Duration> {
+        let offset = self.gpu_offset()?;
+        let (begin, _) = record.gpu?;
+        let latency = begin.as_nanos() as i128 + offset - record.cpu_submit.as_nanos() as i128;
+
+        Some(Duration::from_nanos(latency.max(0) as u64))
+    }
+
+    /// Writes one line per submission with its CPU submit time and, moved onto the CPU timeline,
+    /// its GPU start and end, in nanoseconds, followed by its label. Missing GPU times are `-`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for SubmitTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let offset = self.gpu_offset();
+
+        writeln!(
+            f,
+            "# sequence cpu_submit gpu_begin gpu_end queue_latency label"
+        )?;
+
+        for record in &self.records {
+            write!(
+                f,
+                "submit {} {}",
+                record.sequence,
+                record.cpu_submit.as_nanos()
+            )?;
+
+            match (record.gpu, offset) {
+                (Some((begin, end)), Some(offset)) => {
+                    let begin = begin.as_nanos() as i128 + offset;
+
+                    write!(
+                        f,
+                        " {} {} {}",
+                        begin,
+                        end.as_nanos() as i128 + offset,
+                        (begin - record.cpu_submit.as_nanos() as i128).max(0)
+                    )?
+                }
+                _ => write!(f, " - - -")?,
+            }
+
+            writeln!(f, " {}", record.label)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct PendingSubmit {
+    sequence: u64,
+    label: String,
+    cpu_submit: Duration,
+}
+
+struct InnerSubmitTracer {
+    logical_device: LogicalDevice,
+    query_pool: Option<QueryPool>,
+    debug_utils: Option<debug_utils::Device>,
+    timestamp_period: f64,
+    timestamp_mask: u64,
+    epoch: Instant,
+    pending: RefCell<Vec<Option<PendingSubmit>>>,
+    records: RefCell<Vec<SubmitRecord>>,
+    next_sequence: Cell<u64>,
+}
+
+impl Drop for InnerSubmitTracer {
+    fn drop(&mut self) {
+        teardown_trace::record(
+            "SubmitTracer",
+            self.query_pool.iter().map(|handle| handle.as_raw()),
+        );
+
+        if let Some(query_pool) = self.query_pool {
+            unsafe {
+                self.logical_device
+                    .device()
+                    .destroy_query_pool(query_pool, host_allocation_callbacks());
+            }
+        }
+    }
+}
@@ -1,8 +1,15 @@
 use std::rc::Rc;
 
-use ash::{khr::surface, prelude::VkResult, vk::SurfaceKHR};
+use ash::{
+    khr::surface,
+    prelude::VkResult,
+    vk::{Handle, SurfaceKHR},
+};
 
-use crate::{instance::Instance, window::Window};
+use crate::{
+    api2::host_allocation_callbacks,
+    renderer::{instance::Instance, teardown_trace, window::Window},
+};
 
 #[allow(dead_code)]
 #[derive(Clone)]
@@ -17,10 +24,22 @@ impl Surface {
             instance,
             surface_instance,
             surface,
-            window,
+            window: Some(window),
         })))
     }
 
+    /// Takes ownership of a surface created outside of GLFW, e.g. from a native window handle.
+    pub fn from_raw(instance: Instance, surface: SurfaceKHR) -> Self {
+        let surface_instance = surface::Instance::new(instance.entry(), instance.instance());
+
+        Self(Rc::new(InnerSurface {
+            instance,
+            surface_instance,
+            surface,
+            window: None,
+        }))
+    }
+
     pub fn surface(&self) -> SurfaceKHR {
         self.0.surface
     }
@@ -35,7 +54,7 @@ struct InnerSurface {
     instance: Instance,
 
     #[allow(dead_code)]
-    window: Window,
+    window: Option<Window>,
 
     surface_instance: surface::Instance,
     surface: SurfaceKHR,
@@ -43,8 +62,11 @@ struct InnerSurface {
 
 impl Drop for InnerSurface {
     fn drop(&mut self) {
+        teardown_trace::record("Surface", [self.surface.as_raw()]);
+
         unsafe {
-            self.surface_instance.destroy_surface(self.surface, None);
+            self.surface_instance
+                .destroy_surface(self.surface, host_allocation_callbacks());
         }
     }
 }
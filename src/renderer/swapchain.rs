@@ -0,0 +1,510 @@
+use std::{cell::Cell, rc::Rc};
+
+use ash::{
+    khr::swapchain,
+    prelude::VkResult,
+    vk::{
+        self, CompositeAlphaFlagsKHR, Extent2D, Fence, Handle, Image, ImageLayout, ImageUsageFlags,
+        PresentInfoKHR, PresentModeKHR, Semaphore, SharingMode, SurfaceFormatKHR,
+        SwapchainCreateInfoKHR, SwapchainKHR,
+    },
+};
+
+use crate::{
+    api2::{
+        host_allocation_callbacks, is_srgb_format, Color, ErrorCtx, ResultExt, SwapchainExtent,
+    },
+    renderer::{
+        logical_device::LogicalDevice, physical_device::PhysicalDevice, surface::Surface,
+        teardown_trace, window::Window,
+    },
+};
+
+#[derive(Clone)]
+pub struct Swapchain(Rc<InnerSwapchain>);
+
+impl Swapchain {
+    pub fn new(
+        physical_device: PhysicalDevice,
+        logical_device: LogicalDevice,
+        surface: Surface,
+        window: &Window,
+    ) -> Result<Self, ErrorCtx> {
+        Self::with_framebuffer_size(
+            physical_device,
+            logical_device,
+            surface,
+            window.get_framebuffer_size(),
+        )
+    }
+
+    /// Creates a swapchain for a surface that isn't backed by a [Window], sized from the
+    /// framebuffer size reported by whoever owns the native window.
+    pub fn with_framebuffer_size(
+        physical_device: PhysicalDevice,
+        logical_device: LogicalDevice,
+        surface: Surface,
+        framebuffer_size: (i32, i32),
+    ) -> Result<Self, ErrorCtx> {
+        Self::with_preferences(
+            physical_device,
+            logical_device,
+            surface,
+            framebuffer_size,
+            SwapchainPreferences::default(),
+        )
+    }
+
+    /// Creates a swapchain with the surface's current capabilities. Fails with
+    /// `ERROR_OUT_OF_DATE_KHR` while the surface has no area, check
+    /// [PhysicalDevice::swapchain_extent] first to wait for the window to be restored instead.
+    pub fn with_preferences(
+        physical_device: PhysicalDevice,
+        logical_device: LogicalDevice,
+        surface: Surface,
+        framebuffer_size: (i32, i32),
+        preferences: SwapchainPreferences,
+    ) -> Result<Self, ErrorCtx> {
+        // Queried again, the extents cached by the physical device are from before any resize.
+        let swapchain_support = &physical_device
+            .query_swapchain_support(&surface)
+            .context("querying swapchain support")?;
+
+        let format = *swapchain_support.choose_format();
+        let present_mode = preferences
+            .present_mode
+            .filter(|mode| swapchain_support.present_modes.contains(mode))
+            .unwrap_or_else(|| swapchain_support.choose_present_mode());
+
+        if !is_srgb_format(format.format) {
+            println!(
+                "swapchain format {:?} is not sRGB, shader output is written without gamma correction",
+                format.format
+            );
+        }
+        let SwapchainExtent::Extent(extent) =
+            swapchain_support.choose_extent_for_size(framebuffer_size)
+        else {
+            return Err(
+                ErrorCtx::new("creating swapchain", vk::Result::ERROR_OUT_OF_DATE_KHR).details(
+                    format!(
+                        "the surface has no area, framebuffer {}x{}",
+                        framebuffer_size.0, framebuffer_size.1
+                    ),
+                ),
+            );
+        };
+
+        let surface_min_image_count = swapchain_support.capabilities.min_image_count;
+
+        let mut image_count = preferences
+            .min_image_count
+            .unwrap_or(surface_min_image_count + 1)
+            .max(surface_min_image_count);
+
+        if swapchain_support.capabilities.max_image_count > 0
+            && image_count > swapchain_support.capabilities.max_image_count
+        {
+            image_count = swapchain_support.capabilities.max_image_count;
+        }
+
+        let array_layers = preferences.array_layers.unwrap_or(1);
+        let max_array_layers = swapchain_support.capabilities.max_image_array_layers;
+
+        if array_layers == 0 || array_layers > max_array_layers {
+            return Err(
+                ErrorCtx::new("creating swapchain", vk::Result::ERROR_FEATURE_NOT_PRESENT).details(
+                    format!(
+                        "array_layers={}, the surface supports up to {}",
+                        array_layers, max_array_layers
+                    ),
+                ),
+            );
+        }
+
+        // Transfers into the images, like clearing them, need TRANSFER_DST, which surfaces don't
+        // have to support.
+        let mut image_usage = ImageUsageFlags::COLOR_ATTACHMENT;
+
+        if swapchain_support
+            .capabilities
+            .supported_usage_flags
+            .contains(ImageUsageFlags::TRANSFER_DST)
+        {
+            image_usage |= ImageUsageFlags::TRANSFER_DST;
+        }
+
+        let mut swapchain_create_info = SwapchainCreateInfoKHR::default()
+            .surface(surface.surface())
+            .min_image_count(image_count)
+            .image_format(format.format)
+            .image_color_space(format.color_space)
+            .image_extent(extent)
+            .image_array_layers(array_layers)
+            .image_usage(image_usage)
+            .pre_transform(swapchain_support.capabilities.current_transform)
+            .composite_alpha(CompositeAlphaFlagsKHR::OPAQUE)
+            .present_mode(present_mode)
+            .clipped(true);
+
+        let queue_family_indices = [
+            physical_device.graphics_family_u32(),
+            physical_device.present_family_u32(),
+        ];
+
+        let families_differ =
+            physical_device.graphics_family_u32() != physical_device.present_family_u32();
+
+        let ownership_transfer = (families_differ
+            && preferences.sharing == SwapchainSharing::Exclusive)
+            .then_some(QueueFamilyTransfer {
+                graphics_family: physical_device.graphics_family_u32(),
+                present_family: physical_device.present_family_u32(),
+            });
+
+        if families_differ && ownership_transfer.is_none() {
+            swapchain_create_info = swapchain_create_info
+                .image_sharing_mode(SharingMode::CONCURRENT)
+                .queue_family_indices(&queue_family_indices);
+        } else {
+            swapchain_create_info =
+                swapchain_create_info.image_sharing_mode(SharingMode::EXCLUSIVE);
+        }
+
+        let swapchain_instance = swapchain::Device::new(
+            physical_device.instance().instance(),
+            logical_device.device(),
+        );
+
+        let swapchain = unsafe {
+            swapchain_instance.create_swapchain(&swapchain_create_info, host_allocation_callbacks())
+        }
+        .with_context("creating swapchain", || {
+            format!(
+                "image_count={}, format={:?}, extent={}x{}, array_layers={}, present_mode={:?}, sharing={:?}",
+                image_count,
+                format.format,
+                extent.width,
+                extent.height,
+                array_layers,
+                present_mode,
+                preferences.sharing
+            )
+        })?;
+
+        let images: Vec<_> = unsafe { swapchain_instance.get_swapchain_images(swapchain) }
+            .context("getting swapchain images")?
+            .into_iter()
+            .enumerate()
+            .map(|(index, image)| SwapchainImage::new(image, index as u32))
+            .collect();
+
+        if images.len() as u32 != image_count {
+            println!(
+                "requested {} swapchain images, the driver created {}",
+                image_count,
+                images.len()
+            );
+        }
+
+        Ok(Self(Rc::new(InnerSwapchain {
+            physical_device,
+            logical_device,
+            surface,
+            format,
+            present_mode,
+            extent,
+            array_layers,
+            image_usage,
+            swapchain_instance,
+            swapchain,
+            images,
+            requested_image_count: image_count,
+            surface_min_image_count,
+            ownership_transfer,
+        })))
+    }
+
+    pub fn images(&self) -> &[SwapchainImage] {
+        &self.0.images
+    }
+
+    pub fn image(&self, index: u32) -> &SwapchainImage {
+        &self.0.images[index as usize]
+    }
+
+    pub fn present_mode(&self) -> PresentModeKHR {
+        self.0.present_mode
+    }
+
+    /// The number of images the driver created, which may be more than requested.
+    pub fn image_count(&self) -> u32 {
+        self.0.images.len() as u32
+    }
+
+    /// The minimum image count the swapchain was created with.
+    pub fn requested_image_count(&self) -> u32 {
+        self.0.requested_image_count
+    }
+
+    /// How many images can be acquired at once without blocking. The presentation engine keeps
+    /// at least the surface's minimum image count minus one for itself.
+    pub fn max_acquired_images(&self) -> u32 {
+        (self.image_count() + 1).saturating_sub(self.0.surface_min_image_count)
+    }
+
+    pub fn format(&self) -> SurfaceFormatKHR {
+        self.0.format
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        is_srgb_format(self.0.format.format)
+    }
+
+    pub fn encode_color(&self, color: Color) -> [f32; 4] {
+        color.for_surface_format(self.0.format)
+    }
+
+    pub fn extent(&self) -> Extent2D {
+        self.0.extent
+    }
+
+    /// The layers of every image, more than one for stereoscopic or other layered presentation.
+    pub fn array_layers(&self) -> u32 {
+        self.0.array_layers
+    }
+
+    pub fn is_layered(&self) -> bool {
+        self.0.array_layers > 1
+    }
+
+    /// The usage the images were created with, `TRANSFER_DST` only when the surface supports it.
+    pub fn image_usage(&self) -> ImageUsageFlags {
+        self.0.image_usage
+    }
+
+    pub fn device(&self) -> &LogicalDevice {
+        &self.0.logical_device
+    }
+
+    /// The families images have to be handed between before presenting, when the swapchain was
+    /// created exclusive to the graphics family but presents from another one.
+    pub fn ownership_transfer(&self) -> Option<QueueFamilyTransfer> {
+        self.0.ownership_transfer
+    }
+
+    pub fn acquire_next_image(
+        &self,
+        timeout: u64,
+        semaphore: Option<Semaphore>,
+        fence: Option<Fence>,
+    ) -> VkResult<(u32, bool)> {
+        let (index, suboptimal) = unsafe {
+            self.0.swapchain_instance.acquire_next_image(
+                self.0.swapchain,
+                timeout,
+                semaphore.unwrap_or(Semaphore::null()),
+                fence.unwrap_or(Fence::null()),
+            )
+        }?;
+
+        self.image(index).acquired(semaphore, fence);
+
+        Ok((index, suboptimal))
+    }
+
+    pub fn queue_present(
+        &self,
+        wait_semaphore: &[Semaphore],
+        image_index: &[u32],
+    ) -> VkResult<bool> {
+        for &index in image_index {
+            self.image(index).presented();
+        }
+
+        let swapchains = [self.0.swapchain];
+
+        let present_info = PresentInfoKHR::default()
+            .wait_semaphores(wait_semaphore)
+            .swapchains(&swapchains)
+            .image_indices(image_index);
+
+        unsafe {
+            self.0
+                .swapchain_instance
+                .queue_present(*self.0.logical_device.present_queue(), &present_info)
+        }
+    }
+}
+
+/// A swapchain image with the layout it was last left in and what guards it, the
+/// synchronization its next user has to wait on. Acquiring and presenting through the [Swapchain]
+/// update it, submissions rendering to it are noted with [SwapchainImage::submitted]. Misuse, like
+/// presenting an image nothing rendered to, trips debug assertions.
+#[derive(Debug)]
+pub struct SwapchainImage {
+    image: Image,
+    index: u32,
+    layout: Cell<ImageLayout>,
+    state: Cell<SwapchainImageState>,
+}
+
+impl SwapchainImage {
+    fn new(image: Image, index: u32) -> Self {
+        Self {
+            image,
+            index,
+            layout: Cell::new(ImageLayout::UNDEFINED),
+            state: Cell::new(SwapchainImageState::Presentable),
+        }
+    }
+
+    pub fn image(&self) -> Image {
+        self.image
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// The layout the last submission left the image in, `UNDEFINED` before the first one.
+    pub fn layout(&self) -> ImageLayout {
+        self.layout.get()
+    }
+
+    pub fn state(&self) -> SwapchainImageState {
+        self.state.get()
+    }
+
+    /// Whether the image was acquired and nothing was submitted for it yet, so it can be
+    /// recorded for.
+    pub fn is_acquired(&self) -> bool {
+        matches!(self.state.get(), SwapchainImageState::Acquired { .. })
+    }
+
+    /// Notes a submission rendering to the image, signalling `fence` and leaving it in `layout`.
+    pub fn submitted(&self, fence: Fence, layout: ImageLayout) {
+        debug_assert!(
+            self.is_acquired(),
+            "submitted swapchain image {} while {:?}",
+            self.index,
+            self.state.get()
+        );
+
+        self.layout.set(layout);
+        self.state.set(SwapchainImageState::Submitted { fence });
+    }
+
+    fn acquired(&self, semaphore: Option<Semaphore>, fence: Option<Fence>) {
+        debug_assert_eq!(
+            self.state.get(),
+            SwapchainImageState::Presentable,
+            "acquired swapchain image {} twice",
+            self.index
+        );
+
+        self.state
+            .set(SwapchainImageState::Acquired { semaphore, fence });
+    }
+
+    fn presented(&self) {
+        debug_assert!(
+            matches!(self.state.get(), SwapchainImageState::Submitted { .. }),
+            "presented swapchain image {} while {:?}",
+            self.index,
+            self.state.get()
+        );
+        debug_assert_eq!(
+            self.layout.get(),
+            ImageLayout::PRESENT_SRC_KHR,
+            "presented swapchain image {} in the wrong layout",
+            self.index
+        );
+
+        self.state.set(SwapchainImageState::Presentable);
+    }
+}
+
+/// Who a [SwapchainImage] belongs to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SwapchainImageState {
+    /// Owned by the presentation engine until acquired.
+    Presentable,
+    /// Acquired, the presentation engine may still read it until `semaphore` or `fence`
+    /// signals.
+    Acquired {
+        semaphore: Option<Semaphore>,
+        fence: Option<Fence>,
+    },
+    /// Rendered to by a submission signalling `fence`, waiting to be presented.
+    Submitted { fence: Fence },
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SwapchainPreferences {
+    pub sharing: SwapchainSharing,
+    /// The minimum number of images to ask for, one more than the surface's minimum if `None`.
+    /// Clamped to what the surface supports. More images let more frames be acquired ahead at
+    /// the cost of latency.
+    pub min_image_count: Option<u32>,
+    /// The present mode to use when the surface supports it, MAILBOX or else FIFO if `None`.
+    pub present_mode: Option<PresentModeKHR>,
+    /// The layers of every image, 1 if `None`. Creating the swapchain fails when the surface
+    /// supports fewer, most only support one. Layers are rendered one at a time with
+    /// [ImageViews::per_layer](crate::renderer::image_views::ImageViews::per_layer) or all at once with a
+    /// [multiview](crate::renderer::render_pass::RenderPass::multiview) render pass.
+    pub array_layers: Option<u32>,
+}
+
+/// How swapchain images are shared when the graphics and present families differ. With a single
+/// family images are always exclusive.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SwapchainSharing {
+    /// Both families use the images without transfers, which may cost some performance.
+    #[default]
+    Concurrent,
+    /// Images belong to the graphics family and are explicitly handed to the present family
+    /// before presenting, see [crate::renderer::present_transfer::PresentTransfer].
+    Exclusive,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct QueueFamilyTransfer {
+    pub graphics_family: u32,
+    pub present_family: u32,
+}
+
+struct InnerSwapchain {
+    swapchain_instance: swapchain::Device,
+    swapchain: SwapchainKHR,
+    images: Vec<SwapchainImage>,
+    requested_image_count: u32,
+    surface_min_image_count: u32,
+    format: SurfaceFormatKHR,
+    logical_device: LogicalDevice,
+    ownership_transfer: Option<QueueFamilyTransfer>,
+
+    present_mode: PresentModeKHR,
+    array_layers: u32,
+    image_usage: ImageUsageFlags,
+
+    #[allow(dead_code)]
+    extent: Extent2D,
+
+    #[allow(dead_code)]
+    physical_device: PhysicalDevice,
+
+    #[allow(dead_code)]
+    surface: Surface,
+}
+
+impl Drop for InnerSwapchain {
+    fn drop(&mut self) {
+        teardown_trace::record("Swapchain", [self.swapchain.as_raw()]);
+
+        unsafe {
+            self.swapchain_instance
+                .destroy_swapchain(self.swapchain, host_allocation_callbacks());
+        }
+    }
+}
@@ -2,10 +2,13 @@ use std::rc::Rc;
 
 use ash::{
     prelude::VkResult,
-    vk::{Fence, FenceCreateFlags, FenceCreateInfo, Semaphore, SemaphoreCreateInfo},
+    vk::{Fence, FenceCreateFlags, FenceCreateInfo, Handle, Semaphore, SemaphoreCreateInfo},
 };
 
-use crate::logical_device::LogicalDevice;
+use crate::{
+    api2::host_allocation_callbacks,
+    renderer::{logical_device::LogicalDevice, teardown_trace},
+};
 
 pub struct SyncObjects(Rc<InnerSyncObjects>);
 
@@ -19,21 +22,25 @@ impl SyncObjects {
         let mut render_finished_semaphores = Vec::with_capacity(count);
         let mut in_flight_fences = Vec::with_capacity(count);
 
-        for _ in 0..=count {
+        for _ in 0..count {
             unsafe {
                 image_available_semaphores.push(
                     logical_device
                         .device()
-                        .create_semaphore(&semaphore_info, None)?,
+                        .create_semaphore(&semaphore_info, host_allocation_callbacks())?,
                 );
 
                 render_finished_semaphores.push(
                     logical_device
                         .device()
-                        .create_semaphore(&semaphore_info, None)?,
+                        .create_semaphore(&semaphore_info, host_allocation_callbacks())?,
                 );
 
-                in_flight_fences.push(logical_device.device().create_fence(&fence_info, None)?);
+                in_flight_fences.push(
+                    logical_device
+                        .device()
+                        .create_fence(&fence_info, host_allocation_callbacks())?,
+                );
             }
         }
 
@@ -84,21 +91,32 @@ struct InnerSyncObjects {
 
 impl Drop for InnerSyncObjects {
     fn drop(&mut self) {
+        teardown_trace::record(
+            "SyncObjects",
+            self.image_available_semaphores
+                .iter()
+                .chain(self.render_finished_semaphores.iter())
+                .map(|handle| handle.as_raw())
+                .chain(self.in_flight_fences.iter().map(|handle| handle.as_raw())),
+        );
+
         unsafe {
             for semaphore in self.image_available_semaphores.iter() {
                 self.logical_device
                     .device()
-                    .destroy_semaphore(*semaphore, None);
+                    .destroy_semaphore(*semaphore, host_allocation_callbacks());
             }
 
             for semaphore in self.render_finished_semaphores.iter() {
                 self.logical_device
                     .device()
-                    .destroy_semaphore(*semaphore, None);
+                    .destroy_semaphore(*semaphore, host_allocation_callbacks());
             }
 
             for fence in self.in_flight_fences.iter() {
-                self.logical_device.device().destroy_fence(*fence, None);
+                self.logical_device
+                    .device()
+                    .destroy_fence(*fence, host_allocation_callbacks());
             }
         }
     }
@@ -0,0 +1,36 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static ORDER: AtomicUsize = AtomicUsize::new(0);
+
+/// Starts logging every wrapper destruction to stderr.
+///
+/// Destroying a Vulkan object while something created from it is still alive is a common source
+/// of validation errors and crashes at shutdown; the log shows the exact order things went away.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Logs the destruction of the given Vulkan handles, owned by a wrapper of the named type.
+pub fn record(type_name: &str, handles: impl IntoIterator<Item = u64>) {
+    if !is_enabled() {
+        return;
+    }
+
+    let order = ORDER.fetch_add(1, Ordering::Relaxed);
+    let handles: Vec<String> = handles
+        .into_iter()
+        .map(|handle| format!("{:#x}", handle))
+        .collect();
+
+    eprintln!(
+        "[teardown #{}] {} [{}]",
+        order,
+        type_name,
+        handles.join(", ")
+    );
+}
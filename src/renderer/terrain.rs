@@ -0,0 +1,893 @@
+//! Heightmap terrain drawn as a quadtree of chunks: nearby chunks are small and detailed, far
+//! away ones large and coarse, and chunks outside the view frustum aren't drawn at all.
+//!
+//! Every chunk draws the same grid patch, displaced in `shaders/terrain.vert` by the heightmap,
+//! which lives in a storage buffer. Patches of different sizes don't share their edge vertices,
+//! so each one has a skirt hanging down to hide the cracks between them.
+//!
+//! `examples/terrain.rs` circles a camera over generated hills.
+
+use std::{cell::Cell, error, fmt, mem, rc::Rc};
+
+use ash::vk::{
+    self, BufferCreateInfo, BufferUsageFlags, CommandBuffer, CompareOp, CullModeFlags,
+    DescriptorBufferInfo, DescriptorPoolCreateInfo, DescriptorPoolSize, DescriptorSetAllocateInfo,
+    DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo, DescriptorType, DeviceSize,
+    DynamicState, Format, FrontFace, GraphicsPipelineCreateInfo, Handle, IndexType,
+    MemoryAllocateInfo, MemoryMapFlags, MemoryPropertyFlags, PipelineBindPoint, PipelineCache,
+    PipelineColorBlendStateCreateInfo, PipelineDepthStencilStateCreateInfo,
+    PipelineDynamicStateCreateInfo, PipelineInputAssemblyStateCreateInfo, PipelineLayoutCreateInfo,
+    PipelineMultisampleStateCreateInfo, PipelineRasterizationStateCreateInfo,
+    PipelineShaderStageCreateInfo, PipelineVertexInputStateCreateInfo,
+    PipelineViewportStateCreateInfo, PolygonMode, PrimitiveTopology, PushConstantRange,
+    ShaderStageFlags, SharingMode, VertexInputAttributeDescription, VertexInputBindingDescription,
+    VertexInputRate, WriteDescriptorSet, WHOLE_SIZE,
+};
+
+use crate::{
+    api2::{host_allocation_callbacks, ErrorCtx, ResultExt},
+    renderer::{
+        blend_mode::BlendMode,
+        logical_device::LogicalDevice,
+        mesh::{Aabb, Frustum, Matrix},
+        pipeline_stats,
+        render_pass::RenderPass,
+        resource_stats::ResourceKind,
+        shader_cache::ShaderCache,
+        teardown_trace,
+    },
+    types::{slice_bytes, Pod},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TerrainSettings {
+    /// The width and depth of the terrain, which is centered on the origin of the XZ plane.
+    pub size: f32,
+    /// The height of a heightmap sample of 1.
+    pub height_scale: f32,
+    /// How many times the terrain can be split, the number of levels below the root chunk.
+    pub max_depth: u32,
+    /// Quads along each side of a chunk's grid.
+    pub patch_resolution: u32,
+    /// Chunks are split while the camera is closer than this many times their size.
+    pub lod_distance: f32,
+    /// How far the skirts reach below the chunk edges.
+    pub skirt_depth: f32,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            size: 1024.0,
+            height_scale: 128.0,
+            max_depth: 5,
+            patch_resolution: 32,
+            lod_distance: 2.0,
+            skirt_depth: 8.0,
+        }
+    }
+}
+
+/// Heights from 0 to 1, row by row along Z, each row going along X.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Heightmap {
+    width: u32,
+    height: u32,
+    samples: Vec<f32>,
+}
+
+impl Heightmap {
+    pub fn new(width: u32, height: u32, samples: Vec<f32>) -> Result<Self, HeightmapError> {
+        if width < 2 || height < 2 {
+            return Err(HeightmapError::TooSmall { width, height });
+        }
+
+        let expected = width as usize * height as usize;
+
+        if samples.len() != expected {
+            return Err(HeightmapError::SizeMismatch {
+                expected,
+                found: samples.len(),
+            });
+        }
+
+        Ok(Self {
+            width,
+            height,
+            samples,
+        })
+    }
+
+    /// Creates a heightmap from the height at every sample, e.g. procedural noise.
+    pub fn from_fn(width: u32, height: u32, mut f: impl FnMut(u32, u32) -> f32) -> Self {
+        let (width, height) = (width.max(2), height.max(2));
+        let samples = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| f(x, y))
+            .collect();
+
+        Self {
+            width,
+            height,
+            samples,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    fn texel(&self, x: u32, y: u32) -> f32 {
+        let x = x.min(self.width - 1);
+        let y = y.min(self.height - 1);
+        self.samples[(y * self.width + x) as usize]
+    }
+
+    /// The bilinearly filtered height at `(u, v)`, from 0 to 1 across the heightmap, like the
+    /// shaders sample it.
+    pub fn sample(&self, u: f32, v: f32) -> f32 {
+        let x = u.clamp(0.0, 1.0) * (self.width - 1) as f32;
+        let y = v.clamp(0.0, 1.0) * (self.height - 1) as f32;
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (fx, fy) = (x.fract(), y.fract());
+
+        let top = lerp(self.texel(x0, y0), self.texel(x0 + 1, y0), fx);
+        let bottom = lerp(self.texel(x0, y0 + 1), self.texel(x0 + 1, y0 + 1), fx);
+
+        lerp(top, bottom, fy)
+    }
+
+    /// The lowest and highest sample in the inclusive rectangle of texels.
+    fn range(&self, (x0, y0): (u32, u32), (x1, y1): (u32, u32)) -> (f32, f32) {
+        (y0..=y1.min(self.height - 1))
+            .flat_map(|y| (x0..=x1.min(self.width - 1)).map(move |x| (x, y)))
+            .map(|(x, y)| self.texel(x, y))
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), h| {
+                (low.min(h), high.max(h))
+            })
+    }
+}
+
+/// A square of the terrain to draw with one patch.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TerrainChunk {
+    /// The corner with the lowest X and Z.
+    pub origin: [f32; 2],
+    pub size: f32,
+    /// How many times the root chunk was split to get this one.
+    pub depth: u32,
+}
+
+/// The bounds of every possible chunk, and the selection of which ones to draw.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainQuadtree {
+    settings: TerrainSettings,
+    /// The lowest and highest height under each chunk, per depth, row by row.
+    ranges: Vec<Vec<(f32, f32)>>,
+}
+
+impl TerrainQuadtree {
+    pub fn new(heightmap: &Heightmap, settings: TerrainSettings) -> Self {
+        let leaves = 1u32 << settings.max_depth;
+        let texel = |i: u32, extent: u32| ((i as u64 * (extent - 1) as u64) / leaves as u64) as u32;
+        let texel_end =
+            |i: u32, extent: u32| ((i as u64 * (extent - 1) as u64).div_ceil(leaves as u64)) as u32;
+
+        let leaf_ranges: Vec<_> = (0..leaves)
+            .flat_map(|y| (0..leaves).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                heightmap.range(
+                    (texel(x, heightmap.width), texel(y, heightmap.height)),
+                    (
+                        texel_end(x + 1, heightmap.width),
+                        texel_end(y + 1, heightmap.height),
+                    ),
+                )
+            })
+            .collect();
+
+        // Every parent covers the range of its four children.
+        let mut ranges = vec![leaf_ranges];
+
+        for depth in (0..settings.max_depth).rev() {
+            let side = 1usize << depth;
+            let children = &ranges[0];
+
+            let parents = (0..side)
+                .flat_map(|y| (0..side).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    [(0, 0), (1, 0), (0, 1), (1, 1)]
+                        .map(|(dx, dy)| children[(y * 2 + dy) * side * 2 + x * 2 + dx])
+                        .into_iter()
+                        .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), range| {
+                            (low.min(range.0), high.max(range.1))
+                        })
+                })
+                .collect();
+
+            ranges.insert(0, parents);
+        }
+
+        Self { settings, ranges }
+    }
+
+    pub fn settings(&self) -> &TerrainSettings {
+        &self.settings
+    }
+
+    /// The world-space box around chunk `(x, y)` at `depth`.
+    pub fn chunk_bounds(&self, depth: u32, x: u32, y: u32) -> Aabb {
+        let chunk = self.chunk(depth, x, y);
+        let (low, high) = self.ranges[depth as usize][(y << depth) as usize + x as usize];
+
+        Aabb {
+            min: [
+                chunk.origin[0],
+                low * self.settings.height_scale - self.settings.skirt_depth,
+                chunk.origin[1],
+            ],
+            max: [
+                chunk.origin[0] + chunk.size,
+                high * self.settings.height_scale,
+                chunk.origin[1] + chunk.size,
+            ],
+        }
+    }
+
+    fn chunk(&self, depth: u32, x: u32, y: u32) -> TerrainChunk {
+        let size = self.settings.size / (1u32 << depth) as f32;
+        let half = self.settings.size * 0.5;
+
+        TerrainChunk {
+            origin: [x as f32 * size - half, y as f32 * size - half],
+            size,
+            depth,
+        }
+    }
+
+    /// Replaces `chunks` with the chunks to draw for a camera at `camera_position`, skipping
+    /// those outside `frustum`.
+    pub fn select(
+        &self,
+        camera_position: [f32; 3],
+        frustum: &Frustum,
+        chunks: &mut Vec<TerrainChunk>,
+    ) {
+        chunks.clear();
+        self.select_node(0, 0, 0, camera_position, frustum, chunks);
+    }
+
+    fn select_node(
+        &self,
+        depth: u32,
+        x: u32,
+        y: u32,
+        camera_position: [f32; 3],
+        frustum: &Frustum,
+        chunks: &mut Vec<TerrainChunk>,
+    ) {
+        let bounds = self.chunk_bounds(depth, x, y);
+
+        if !frustum.intersects_aabb(&bounds) {
+            return;
+        }
+
+        let chunk = self.chunk(depth, x, y);
+        let closest = [0, 1, 2].map(|i| camera_position[i].clamp(bounds.min[i], bounds.max[i]));
+        let distance = (0..3)
+            .map(|i| (closest[i] - camera_position[i]).powi(2))
+            .sum::<f32>()
+            .sqrt();
+
+        if depth == self.settings.max_depth || distance > chunk.size * self.settings.lod_distance {
+            chunks.push(chunk);
+            return;
+        }
+
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            self.select_node(
+                depth + 1,
+                x * 2 + dx,
+                y * 2 + dy,
+                camera_position,
+                frustum,
+                chunks,
+            );
+        }
+    }
+}
+
+/// The push constants of the terrain shaders, mirrors `TerrainDraw` in `terrain_common.glsl`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct TerrainPushConstants {
+    pub view_projection: [[f32; 4]; 4],
+    /// The chunk's origin, size and skirt depth.
+    pub chunk: [f32; 4],
+    /// The terrain's size and height scale, then the heightmap's width and height.
+    pub terrain: [f32; 4],
+    /// Towards the sun, w is unused.
+    pub sun_direction: [f32; 4],
+}
+
+unsafe impl Pod for TerrainPushConstants {}
+
+/// A vertex of the shared patch, mirrors the input of `shaders/terrain.vert`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+struct PatchVertex {
+    /// The position in the chunk from 0 to 1.
+    uv: [f32; 2],
+    /// 1 on the skirt, 0 on the surface.
+    skirt: f32,
+}
+
+unsafe impl Pod for PatchVertex {}
+
+/// The terrain pipeline with the heightmap and the patch geometry on the GPU.
+#[derive(Clone)]
+pub struct Terrain(Rc<InnerTerrain>);
+
+impl Terrain {
+    /// Creates the terrain for subpass 0 of `render_pass` from the SPIR-V of
+    /// `shaders/terrain.vert` and `shaders/terrain.frag`. Depth testing applies when the subpass
+    /// has a depth attachment.
+    pub fn new(
+        render_pass: RenderPass,
+        shader_cache: &ShaderCache,
+        vertex_shader: &[u32],
+        fragment_shader: &[u32],
+        heightmap: &Heightmap,
+        settings: TerrainSettings,
+    ) -> Result<Self, ErrorCtx> {
+        let logical_device = render_pass.swapchain().device().clone();
+        let device = logical_device.device();
+
+        let quadtree = TerrainQuadtree::new(heightmap, settings);
+        let (vertices, indices) = build_patch(settings.patch_resolution.max(1));
+
+        let heights = TerrainBuffer::with_data(
+            &logical_device,
+            slice_bytes(heightmap.samples()),
+            BufferUsageFlags::STORAGE_BUFFER,
+        )?;
+        let vertex_buffer = TerrainBuffer::with_data(
+            &logical_device,
+            slice_bytes(&vertices),
+            BufferUsageFlags::VERTEX_BUFFER,
+        )?;
+        let index_buffer = TerrainBuffer::with_data(
+            &logical_device,
+            slice_bytes(&indices),
+            BufferUsageFlags::INDEX_BUFFER,
+        )?;
+
+        let bindings = [DescriptorSetLayoutBinding::default()
+            .binding(0)
+            .descriptor_type(DescriptorType::STORAGE_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(ShaderStageFlags::VERTEX)];
+
+        let descriptor_set_layout = unsafe {
+            device.create_descriptor_set_layout(
+                &DescriptorSetLayoutCreateInfo::default().bindings(&bindings),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating terrain descriptor set layout")?;
+
+        let pool_sizes = [DescriptorPoolSize::default()
+            .ty(DescriptorType::STORAGE_BUFFER)
+            .descriptor_count(1)];
+
+        let descriptor_pool = unsafe {
+            device.create_descriptor_pool(
+                &DescriptorPoolCreateInfo::default()
+                    .max_sets(1)
+                    .pool_sizes(&pool_sizes),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating terrain descriptor pool")?;
+
+        let set_layouts = [descriptor_set_layout];
+        let descriptor_set = unsafe {
+            device.allocate_descriptor_sets(
+                &DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(descriptor_pool)
+                    .set_layouts(&set_layouts),
+            )
+        }
+        .context("allocating terrain descriptor set")?[0];
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::DescriptorSet, 1, 0);
+
+        let buffer_info = [DescriptorBufferInfo::default()
+            .buffer(heights.buffer)
+            .offset(0)
+            .range(WHOLE_SIZE)];
+
+        unsafe {
+            device.update_descriptor_sets(
+                &[WriteDescriptorSet::default()
+                    .dst_set(descriptor_set)
+                    .dst_binding(0)
+                    .descriptor_type(DescriptorType::STORAGE_BUFFER)
+                    .buffer_info(&buffer_info)],
+                &[],
+            )
+        };
+
+        let push_constant_ranges = [PushConstantRange::default()
+            .stage_flags(ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT)
+            .size(mem::size_of::<TerrainPushConstants>() as u32)];
+
+        let pipeline_layout = unsafe {
+            device.create_pipeline_layout(
+                &PipelineLayoutCreateInfo::default()
+                    .set_layouts(&set_layouts)
+                    .push_constant_ranges(&push_constant_ranges),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating terrain pipeline layout")?;
+
+        let pipeline = create_pipeline(
+            &render_pass,
+            shader_cache,
+            pipeline_layout,
+            vertex_shader,
+            fragment_shader,
+        )?;
+
+        Ok(Self(Rc::new(InnerTerrain {
+            quadtree,
+            heightmap_size: [heightmap.width() as f32, heightmap.height() as f32],
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_set,
+            pipeline_layout,
+            pipeline,
+            heights,
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as u32,
+            chunks_drawn: Cell::new(0),
+            logical_device,
+            render_pass,
+        })))
+    }
+
+    pub fn quadtree(&self) -> &TerrainQuadtree {
+        &self.0.quadtree
+    }
+
+    /// Replaces `chunks` with the chunks visible through `view_projection` from
+    /// `camera_position`, see [TerrainQuadtree::select].
+    pub fn select(
+        &self,
+        camera_position: [f32; 3],
+        view_projection: &Matrix,
+        chunks: &mut Vec<TerrainChunk>,
+    ) {
+        let frustum = Frustum::from_view_projection(view_projection);
+        self.0.quadtree.select(camera_position, &frustum, chunks);
+    }
+
+    /// How many chunks the last [Terrain::record] drew.
+    pub fn chunks_drawn(&self) -> usize {
+        self.0.chunks_drawn.get()
+    }
+
+    /// Draws `chunks` lit by a sun in `sun_direction`, inside the render pass.
+    pub fn record(
+        &self,
+        command_buffer: CommandBuffer,
+        view_projection: Matrix,
+        sun_direction: [f32; 3],
+        chunks: &[TerrainChunk],
+    ) {
+        let device = self.0.logical_device.device();
+        let settings = self.0.quadtree.settings();
+        let [sun_x, sun_y, sun_z] = sun_direction;
+
+        let mut push_constants = TerrainPushConstants {
+            view_projection,
+            chunk: [0.0; 4],
+            terrain: [
+                settings.size,
+                settings.height_scale,
+                self.0.heightmap_size[0],
+                self.0.heightmap_size[1],
+            ],
+            sun_direction: [sun_x, sun_y, sun_z, 0.0],
+        };
+
+        unsafe {
+            device.cmd_bind_pipeline(command_buffer, PipelineBindPoint::GRAPHICS, self.0.pipeline);
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                PipelineBindPoint::GRAPHICS,
+                self.0.pipeline_layout,
+                0,
+                &[self.0.descriptor_set],
+                &[],
+            );
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[self.0.vertex_buffer.buffer], &[0]);
+            device.cmd_bind_index_buffer(
+                command_buffer,
+                self.0.index_buffer.buffer,
+                0,
+                IndexType::UINT32,
+            );
+
+            for chunk in chunks {
+                push_constants.chunk = [
+                    chunk.origin[0],
+                    chunk.origin[1],
+                    chunk.size,
+                    settings.skirt_depth,
+                ];
+
+                device.cmd_push_constants(
+                    command_buffer,
+                    self.0.pipeline_layout,
+                    ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT,
+                    0,
+                    push_constants.bytes_of(),
+                );
+                device.cmd_draw_indexed(command_buffer, self.0.index_count, 1, 0, 0, 0);
+            }
+        }
+
+        self.0.chunks_drawn.set(chunks.len());
+    }
+}
+
+struct InnerTerrain {
+    quadtree: TerrainQuadtree,
+    heightmap_size: [f32; 2],
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    // Only read by the vertex shader through `descriptor_set`.
+    #[allow(dead_code)]
+    heights: TerrainBuffer,
+    vertex_buffer: TerrainBuffer,
+    index_buffer: TerrainBuffer,
+    index_count: u32,
+    chunks_drawn: Cell<usize>,
+
+    logical_device: LogicalDevice,
+
+    #[allow(dead_code)]
+    render_pass: RenderPass,
+}
+
+impl Drop for InnerTerrain {
+    fn drop(&mut self) {
+        teardown_trace::record("Terrain", [self.pipeline.as_raw()]);
+
+        let device = self.logical_device.device();
+
+        unsafe {
+            device.destroy_pipeline(self.pipeline, host_allocation_callbacks());
+            device.destroy_pipeline_layout(self.pipeline_layout, host_allocation_callbacks());
+            device.destroy_descriptor_pool(self.descriptor_pool, host_allocation_callbacks());
+            device.destroy_descriptor_set_layout(
+                self.descriptor_set_layout,
+                host_allocation_callbacks(),
+            );
+        }
+
+        let tracker = self.logical_device.resource_tracker();
+        tracker.untrack(ResourceKind::Pipeline, 1, 0);
+        tracker.untrack(ResourceKind::DescriptorSet, 1, 0);
+    }
+}
+
+/// Builds a grid of `resolution` quads per side, followed by a skirt hanging from its edges.
+fn build_patch(resolution: u32) -> (Vec<PatchVertex>, Vec<u32>) {
+    let side = resolution + 1;
+    let step = 1.0 / resolution as f32;
+
+    let mut vertices: Vec<_> = (0..side)
+        .flat_map(|y| (0..side).map(move |x| (x, y)))
+        .map(|(x, y)| PatchVertex {
+            uv: [x as f32 * step, y as f32 * step],
+            skirt: 0.0,
+        })
+        .collect();
+
+    let mut indices = Vec::with_capacity((resolution * resolution * 6 + resolution * 24) as usize);
+
+    for y in 0..resolution {
+        for x in 0..resolution {
+            let corner = y * side + x;
+            indices.extend([corner, corner + side, corner + 1]);
+            indices.extend([corner + 1, corner + side, corner + side + 1]);
+        }
+    }
+
+    // The edge vertices once around the grid, each with a copy on the skirt.
+    let edge: Vec<u32> = (0..resolution)
+        .chain((0..resolution).map(|y| y * side + resolution))
+        .chain((1..=resolution).rev().map(|x| resolution * side + x))
+        .chain((1..=resolution).rev().map(|y| y * side))
+        .collect();
+
+    let skirt_start = vertices.len() as u32;
+
+    let skirt: Vec<_> = edge
+        .iter()
+        .map(|&index| PatchVertex {
+            skirt: 1.0,
+            ..vertices[index as usize]
+        })
+        .collect();
+    vertices.extend(skirt);
+
+    for i in 0..edge.len() {
+        let next = (i + 1) % edge.len();
+        let (top, top_next) = (edge[i], edge[next]);
+        let (bottom, bottom_next) = (skirt_start + i as u32, skirt_start + next as u32);
+
+        indices.extend([top, bottom, top_next]);
+        indices.extend([top_next, bottom, bottom_next]);
+    }
+
+    (vertices, indices)
+}
+
+fn create_pipeline(
+    render_pass: &RenderPass,
+    shader_cache: &ShaderCache,
+    pipeline_layout: vk::PipelineLayout,
+    vertex_shader: &[u32],
+    fragment_shader: &[u32],
+) -> Result<vk::Pipeline, ErrorCtx> {
+    let logical_device = render_pass.swapchain().device();
+
+    let shader_modules = [
+        shader_cache
+            .get_or_create(vertex_shader)
+            .context("creating terrain vertex shader module")?,
+        shader_cache
+            .get_or_create(fragment_shader)
+            .context("creating terrain fragment shader module")?,
+    ];
+
+    let stages = [
+        PipelineShaderStageCreateInfo::default()
+            .stage(ShaderStageFlags::VERTEX)
+            .module(*shader_modules[0].shader_module())
+            .name(c"main"),
+        PipelineShaderStageCreateInfo::default()
+            .stage(ShaderStageFlags::FRAGMENT)
+            .module(*shader_modules[1].shader_module())
+            .name(c"main"),
+    ];
+
+    let bindings = [VertexInputBindingDescription::default()
+        .binding(0)
+        .stride(mem::size_of::<PatchVertex>() as u32)
+        .input_rate(VertexInputRate::VERTEX)];
+    let attributes = [VertexInputAttributeDescription::default()
+        .binding(0)
+        .location(0)
+        .format(Format::R32G32B32_SFLOAT)
+        .offset(0)];
+    let vertex_input_info = PipelineVertexInputStateCreateInfo::default()
+        .vertex_binding_descriptions(&bindings)
+        .vertex_attribute_descriptions(&attributes);
+
+    let input_assembly_info =
+        PipelineInputAssemblyStateCreateInfo::default().topology(PrimitiveTopology::TRIANGLE_LIST);
+
+    let dynamic_states = [DynamicState::VIEWPORT, DynamicState::SCISSOR];
+    let dynamic_state_info =
+        PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+    let viewport_info = PipelineViewportStateCreateInfo::default()
+        .viewport_count(1)
+        .scissor_count(1);
+
+    // The skirts are seen from both sides depending on where the camera is.
+    let rasterizer_info = PipelineRasterizationStateCreateInfo::default()
+        .polygon_mode(PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(CullModeFlags::NONE)
+        .front_face(FrontFace::COUNTER_CLOCKWISE);
+
+    let multisample_info =
+        PipelineMultisampleStateCreateInfo::default().rasterization_samples(render_pass.samples());
+
+    // Ignored by subpasses without a depth attachment.
+    let depth_stencil_info = PipelineDepthStencilStateCreateInfo::default()
+        .depth_test_enable(true)
+        .depth_write_enable(true)
+        .depth_compare_op(CompareOp::LESS);
+
+    let color_blend_attachments = [BlendMode::Opaque.attachment_state()];
+    let color_blend_info =
+        PipelineColorBlendStateCreateInfo::default().attachments(&color_blend_attachments);
+
+    let create_info = GraphicsPipelineCreateInfo::default()
+        .stages(&stages)
+        .vertex_input_state(&vertex_input_info)
+        .input_assembly_state(&input_assembly_info)
+        .viewport_state(&viewport_info)
+        .rasterization_state(&rasterizer_info)
+        .multisample_state(&multisample_info)
+        .depth_stencil_state(&depth_stencil_info)
+        .color_blend_state(&color_blend_info)
+        .dynamic_state(&dynamic_state_info)
+        .layout(pipeline_layout)
+        .render_pass(*render_pass.render_pass());
+
+    let pipeline = unsafe {
+        pipeline_stats::create_graphics_pipelines(
+            logical_device,
+            PipelineCache::null(),
+            &[create_info],
+        )
+    }
+    .context("creating terrain pipeline")?[0];
+
+    logical_device
+        .resource_tracker()
+        .track(ResourceKind::Pipeline, 1, 0);
+
+    Ok(pipeline)
+}
+
+/// A host-visible buffer filled once when created.
+struct TerrainBuffer {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    size: DeviceSize,
+
+    logical_device: LogicalDevice,
+}
+
+impl TerrainBuffer {
+    fn with_data(
+        logical_device: &LogicalDevice,
+        data: &[u8],
+        usage: BufferUsageFlags,
+    ) -> Result<Self, ErrorCtx> {
+        let device = logical_device.device();
+
+        let buffer = unsafe {
+            device.create_buffer(
+                &BufferCreateInfo::default()
+                    .size(data.len() as DeviceSize)
+                    .usage(usage)
+                    .sharing_mode(SharingMode::EXCLUSIVE),
+                host_allocation_callbacks(),
+            )
+        }
+        .with_context("creating terrain buffer", || {
+            format!("size={}, usage={:?}", data.len(), usage)
+        })?;
+
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+
+        let mut terrain_buffer = Self {
+            buffer,
+            memory: vk::DeviceMemory::null(),
+            size: requirements.size,
+            logical_device: logical_device.clone(),
+        };
+
+        logical_device
+            .resource_tracker()
+            .track(ResourceKind::Buffer, 1, requirements.size);
+
+        let memory_type_index = logical_device
+            .physical_device()
+            .find_memory_type(
+                requirements.memory_type_bits,
+                MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+            )
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            .with_context("finding terrain buffer memory", || {
+                format!("memory_type_bits={:#x}", requirements.memory_type_bits)
+            })?;
+
+        terrain_buffer.memory = unsafe {
+            device.allocate_memory(
+                &MemoryAllocateInfo::default()
+                    .allocation_size(requirements.size)
+                    .memory_type_index(memory_type_index),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("allocating terrain buffer memory")?;
+
+        unsafe {
+            device
+                .bind_buffer_memory(buffer, terrain_buffer.memory, 0)
+                .context("binding terrain buffer memory")?;
+
+            let mapped = device
+                .map_memory(
+                    terrain_buffer.memory,
+                    0,
+                    WHOLE_SIZE,
+                    MemoryMapFlags::empty(),
+                )
+                .context("mapping terrain buffer memory")?;
+
+            mapped
+                .cast::<u8>()
+                .copy_from_nonoverlapping(data.as_ptr(), data.len());
+            device.unmap_memory(terrain_buffer.memory);
+        }
+
+        Ok(terrain_buffer)
+    }
+}
+
+impl Drop for TerrainBuffer {
+    fn drop(&mut self) {
+        teardown_trace::record("TerrainBuffer", [self.buffer.as_raw()]);
+
+        unsafe {
+            let device = self.logical_device.device();
+            device.destroy_buffer(self.buffer, host_allocation_callbacks());
+            device.free_memory(self.memory, host_allocation_callbacks());
+        }
+
+        self.logical_device
+            .resource_tracker()
+            .untrack(ResourceKind::Buffer, 1, self.size);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HeightmapError {
+    /// Bilinear filtering needs at least two samples along each axis.
+    TooSmall {
+        width: u32,
+        height: u32,
+    },
+    SizeMismatch {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for HeightmapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooSmall { width, height } => write!(
+                f,
+                "heightmap of {}x{} samples is too small, it needs at least 2x2",
+                width, height
+            ),
+            Self::SizeMismatch { expected, found } => write!(
+                f,
+                "heightmap should have {} samples, found {}",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl error::Error for HeightmapError {}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
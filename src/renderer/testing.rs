@@ -0,0 +1,353 @@
+//! Comparing rendered frames, to check that a change to a rendering path keeps its output, or
+//! to measure how far two configurations drift apart.
+
+use std::{
+    error, fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// The byte order of the pixels of a [CapturedFrame].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PixelLayout {
+    Rgba8,
+    /// The usual order of swapchain images.
+    Bgra8,
+}
+
+/// A frame read back from the GPU, 4 bytes per pixel, row by row without padding.
+///
+/// Frames are compared byte for byte, so both should hold the same encoding, e.g. sRGB, even
+/// when one came from a UNORM and the other from an sRGB image.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CapturedFrame {
+    pub width: u32,
+    pub height: u32,
+    pub layout: PixelLayout,
+    pub data: Vec<u8>,
+}
+
+impl CapturedFrame {
+    pub fn new(
+        width: u32,
+        height: u32,
+        layout: PixelLayout,
+        data: Vec<u8>,
+    ) -> Result<Self, CompareError> {
+        let expected = width as usize * height as usize * 4;
+
+        if data.len() != expected {
+            return Err(CompareError::DataSize {
+                expected,
+                found: data.len(),
+            });
+        }
+
+        Ok(Self {
+            width,
+            height,
+            layout,
+            data,
+        })
+    }
+
+    /// The pixel at `(x, y)` as RGBA.
+    pub fn rgba(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let [a, b, c, d] = [0, 1, 2, 3].map(|j| self.data[i + j]);
+
+        match self.layout {
+            PixelLayout::Rgba8 => [a, b, c, d],
+            PixelLayout::Bgra8 => [c, b, a, d],
+        }
+    }
+
+    /// Writes the RGB channels as a binary PPM, which any image viewer opens.
+    pub fn write_ppm(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let [r, g, b, _] = self.rgba(x, y);
+                writer.write_all(&[r, g, b])?;
+            }
+        }
+
+        writer.flush()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CompareOptions {
+    /// The frame is split into this many regions along X and Y for the heatmap.
+    pub regions: (u32, u32),
+    /// Per-channel differences up to this much don't count as a differing pixel, to ignore
+    /// rounding between drivers.
+    pub tolerance: u8,
+    /// Whether alpha takes part in the comparison.
+    pub compare_alpha: bool,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self {
+            regions: (16, 16),
+            tolerance: 1,
+            compare_alpha: false,
+        }
+    }
+}
+
+/// The error of one region of the frame.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RegionError {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Mean squared error over the region's channels, from 0 to 255².
+    pub mse: f64,
+    pub max_error: u8,
+}
+
+/// The differences between the frames of two runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffReport {
+    pub width: u32,
+    pub height: u32,
+    pub mse: f64,
+    /// Peak signal-to-noise ratio in dB, infinite for identical frames.
+    pub psnr: f64,
+    pub max_error: u8,
+    /// Pixels with a channel differing by more than the tolerance.
+    pub differing_pixels: usize,
+    /// Row by row, [CompareOptions::regions] of them.
+    pub regions: Vec<RegionError>,
+    /// The frame's size, each region colored from black for no error through blue, red and
+    /// yellow up to white for the worst region.
+    pub heatmap: CapturedFrame,
+}
+
+impl DiffReport {
+    /// Whether the PSNR reaches `min_psnr`, with [f64::INFINITY] requiring identical frames.
+    pub fn passes(&self, min_psnr: f64) -> bool {
+        self.psnr >= min_psnr
+    }
+
+    /// The region with the largest error.
+    pub fn worst_region(&self) -> Option<&RegionError> {
+        self.regions.iter().max_by(|a, b| a.mse.total_cmp(&b.mse))
+    }
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "size:      {}x{}", self.width, self.height)?;
+        writeln!(f, "psnr:      {:.2} dB", self.psnr)?;
+        writeln!(f, "mse:       {:.4}", self.mse)?;
+        writeln!(f, "max error: {}", self.max_error)?;
+        write!(
+            f,
+            "differing: {} of {} pixels",
+            self.differing_pixels,
+            self.width as usize * self.height as usize
+        )?;
+
+        if let Some(worst) = self.worst_region().filter(|region| region.mse > 0.0) {
+            write!(
+                f,
+                "\nworst region at {},{} ({}x{}): mse {:.4}, max error {}",
+                worst.x, worst.y, worst.width, worst.height, worst.mse, worst.max_error
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compares two frames of the same size.
+pub fn compare_frames(
+    baseline: &CapturedFrame,
+    candidate: &CapturedFrame,
+    options: CompareOptions,
+) -> Result<DiffReport, CompareError> {
+    if (baseline.width, baseline.height) != (candidate.width, candidate.height) {
+        return Err(CompareError::SizeMismatch {
+            baseline: (baseline.width, baseline.height),
+            candidate: (candidate.width, candidate.height),
+        });
+    }
+
+    let (width, height) = (baseline.width, baseline.height);
+    let channels = if options.compare_alpha { 4 } else { 3 };
+    let regions_x = options.regions.0.clamp(1, width.max(1));
+    let regions_y = options.regions.1.clamp(1, height.max(1));
+
+    let mut regions: Vec<_> = (0..regions_y)
+        .flat_map(|ry| (0..regions_x).map(move |rx| (rx, ry)))
+        .map(|(rx, ry)| {
+            let x = rx * width / regions_x;
+            let y = ry * height / regions_y;
+
+            RegionError {
+                x,
+                y,
+                width: (rx + 1) * width / regions_x - x,
+                height: (ry + 1) * height / regions_y - y,
+                mse: 0.0,
+                max_error: 0,
+            }
+        })
+        .collect();
+
+    let mut squared_sum = 0.0;
+    let mut max_error = 0;
+    let mut differing_pixels = 0;
+
+    for region in &mut regions {
+        for y in region.y..region.y + region.height {
+            for x in region.x..region.x + region.width {
+                let a = baseline.rgba(x, y);
+                let b = candidate.rgba(x, y);
+                let mut pixel_max = 0;
+
+                for channel in 0..channels {
+                    let error = a[channel].abs_diff(b[channel]);
+                    let squared = error as f64 * error as f64;
+
+                    squared_sum += squared;
+                    region.mse += squared;
+                    pixel_max = pixel_max.max(error);
+                }
+
+                region.max_error = region.max_error.max(pixel_max);
+                max_error = max_error.max(pixel_max);
+
+                if pixel_max > options.tolerance {
+                    differing_pixels += 1;
+                }
+            }
+        }
+
+        let samples = region.width as f64 * region.height as f64 * channels as f64;
+
+        if samples > 0.0 {
+            region.mse /= samples;
+        }
+    }
+
+    let samples = width as f64 * height as f64 * channels as f64;
+    let mse = if samples > 0.0 {
+        squared_sum / samples
+    } else {
+        0.0
+    };
+    let psnr = if mse > 0.0 {
+        10.0 * (255.0 * 255.0 / mse).log10()
+    } else {
+        f64::INFINITY
+    };
+
+    let heatmap = heatmap(width, height, &regions);
+
+    Ok(DiffReport {
+        width,
+        height,
+        mse,
+        psnr,
+        max_error,
+        differing_pixels,
+        regions,
+        heatmap,
+    })
+}
+
+fn heatmap(width: u32, height: u32, regions: &[RegionError]) -> CapturedFrame {
+    let worst = regions
+        .iter()
+        .map(|region| region.mse.sqrt())
+        .fold(0.0, f64::max);
+
+    let mut data = vec![0; width as usize * height as usize * 4];
+
+    for region in regions {
+        let t = if worst > 0.0 {
+            region.mse.sqrt() / worst
+        } else {
+            0.0
+        };
+        let color = heat_color(t);
+
+        for y in region.y..region.y + region.height {
+            for x in region.x..region.x + region.width {
+                let i = (y as usize * width as usize + x as usize) * 4;
+                data[i..i + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    CapturedFrame {
+        width,
+        height,
+        layout: PixelLayout::Rgba8,
+        data,
+    }
+}
+
+/// Maps `t` from 0 to 1 onto black, blue, red, yellow and white.
+fn heat_color(t: f64) -> [u8; 4] {
+    const STOPS: [[f64; 3]; 5] = [
+        [0.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [1.0, 1.0, 1.0],
+    ];
+
+    let position = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let index = (position as usize).min(STOPS.len() - 2);
+    let f = position - index as f64;
+    let [r, g, b] =
+        [0, 1, 2].map(|i| STOPS[index][i] + (STOPS[index + 1][i] - STOPS[index][i]) * f);
+
+    [
+        (r * 255.0).round() as u8,
+        (g * 255.0).round() as u8,
+        (b * 255.0).round() as u8,
+        255,
+    ]
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CompareError {
+    SizeMismatch {
+        baseline: (u32, u32),
+        candidate: (u32, u32),
+    },
+    /// The pixel data doesn't hold 4 bytes per pixel.
+    DataSize { expected: usize, found: usize },
+}
+
+impl fmt::Display for CompareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SizeMismatch {
+                baseline,
+                candidate,
+            } => write!(
+                f,
+                "baseline frame is {}x{} but candidate is {}x{}",
+                baseline.0, baseline.1, candidate.0, candidate.1
+            ),
+            Self::DataSize { expected, found } => write!(
+                f,
+                "frame should have {} bytes of pixels, found {}",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl error::Error for CompareError {}
@@ -0,0 +1,349 @@
+//! A small pass drawing every object in a flat color from its push constants, with an outline
+//! around the selected one.
+//!
+//! It shows push constants changing per draw, and passes working together: the same objects are
+//! drawn into the [PickingPass] with [TintPass::draw_picking], and what it picks comes back as the
+//! selection of [TintPass::draw]. There's no scene graph, so objects are passed as a flat list.
+
+use std::mem;
+
+use ash::vk::{
+    self, ColorComponentFlags, CommandBuffer, CompareOp, CullModeFlags, DynamicState, Extent2D,
+    Format, FrontFace, GraphicsPipelineCreateInfo, Handle, PipelineBindPoint, PipelineCache,
+    PipelineColorBlendAttachmentState, PipelineColorBlendStateCreateInfo,
+    PipelineDepthStencilStateCreateInfo, PipelineDynamicStateCreateInfo,
+    PipelineInputAssemblyStateCreateInfo, PipelineLayoutCreateInfo,
+    PipelineMultisampleStateCreateInfo, PipelineRasterizationStateCreateInfo,
+    PipelineShaderStageCreateInfo, PipelineVertexInputStateCreateInfo,
+    PipelineViewportStateCreateInfo, PolygonMode, PrimitiveTopology, PushConstantRange, Rect2D,
+    SampleCountFlags, ShaderStageFlags, VertexInputAttributeDescription,
+};
+
+use crate::{
+    api2::{host_allocation_callbacks, Color, ErrorCtx, ResultExt},
+    renderer::{
+        logical_device::LogicalDevice,
+        pbr::{PbrMesh, PbrVertex},
+        picking::{ObjectId, PickingPass},
+        pipeline_stats,
+        resource_stats::ResourceKind,
+        shader_cache::ShaderCache,
+        teardown_trace,
+    },
+    types::Pod,
+};
+
+/// The push constants of the tint shaders, mirrors `tint_draw.glsl`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct TintPushConstants {
+    pub model_view_projection: [[f32; 4]; 4],
+    /// Linear RGBA.
+    pub tint: [f32; 4],
+    pub outline_width: f32,
+}
+
+unsafe impl Pod for TintPushConstants {}
+
+/// An object to draw, with the mesh and ID it's also picked with.
+#[derive(Debug, Copy, Clone)]
+pub struct TintedObject<'a> {
+    pub id: ObjectId,
+    pub mesh: &'a PbrMesh,
+    pub model_view_projection: [[f32; 4]; 4],
+    pub tint: Color,
+}
+
+pub struct TintPass {
+    pipeline_layout: vk::PipelineLayout,
+    fill_pipeline: vk::Pipeline,
+    outline_pipeline: vk::Pipeline,
+    /// The color of the selected object's outline.
+    pub outline_color: Color,
+    /// How far the outline reaches past the surface, in object space.
+    pub outline_width: f32,
+
+    logical_device: LogicalDevice,
+}
+
+impl TintPass {
+    /// Creates the pipelines for subpass 0 of `render_pass`, which needs a depth attachment, from
+    /// the SPIR-V of `shaders/tint.vert` and `shaders/tint.frag`.
+    pub fn new(
+        logical_device: LogicalDevice,
+        shader_cache: &ShaderCache,
+        render_pass: vk::RenderPass,
+        vertex_shader: &[u32],
+        fragment_shader: &[u32],
+    ) -> Result<Self, ErrorCtx> {
+        let push_constant_ranges = [PushConstantRange::default()
+            .stage_flags(ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT)
+            .size(mem::size_of::<TintPushConstants>() as u32)];
+
+        let pipeline_layout = unsafe {
+            logical_device.device().create_pipeline_layout(
+                &PipelineLayoutCreateInfo::default().push_constant_ranges(&push_constant_ranges),
+                host_allocation_callbacks(),
+            )
+        }
+        .context("creating tint pipeline layout")?;
+
+        let mut pass = Self {
+            pipeline_layout,
+            fill_pipeline: vk::Pipeline::null(),
+            outline_pipeline: vk::Pipeline::null(),
+            outline_color: Color::srgb_u8(255, 160, 0, 255),
+            outline_width: 0.02,
+            logical_device: logical_device.clone(),
+        };
+
+        [pass.fill_pipeline, pass.outline_pipeline] = create_pipelines(
+            &logical_device,
+            shader_cache,
+            render_pass,
+            pipeline_layout,
+            [vertex_shader, fragment_shader],
+        )?;
+
+        Ok(pass)
+    }
+
+    /// Draws the objects inside the render pass, outlining `selected`, e.g. what
+    /// [PickingPass::pick] returned.
+    pub fn draw(
+        &self,
+        command_buffer: CommandBuffer,
+        extent: Extent2D,
+        objects: &[TintedObject],
+        selected: Option<ObjectId>,
+    ) {
+        let device = self.logical_device.device();
+
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                PipelineBindPoint::GRAPHICS,
+                self.fill_pipeline,
+            );
+            device.cmd_set_viewport(
+                command_buffer,
+                0,
+                &[self.logical_device.clip_space_y().viewport(extent)],
+            );
+            device.cmd_set_scissor(command_buffer, 0, &[Rect2D::default().extent(extent)]);
+        }
+
+        for object in objects {
+            self.draw_object(
+                command_buffer,
+                object,
+                TintPushConstants {
+                    model_view_projection: object.model_view_projection,
+                    tint: object.tint.to_linear_array(),
+                    outline_width: 0.0,
+                },
+            );
+        }
+
+        let Some(selected) = objects.iter().find(|object| Some(object.id) == selected) else {
+            return;
+        };
+
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                PipelineBindPoint::GRAPHICS,
+                self.outline_pipeline,
+            );
+        }
+
+        self.draw_object(
+            command_buffer,
+            selected,
+            TintPushConstants {
+                model_view_projection: selected.model_view_projection,
+                tint: self.outline_color.to_linear_array(),
+                outline_width: self.outline_width,
+            },
+        );
+    }
+
+    /// Draws the objects into the picking pass, outside of any render pass, so the next picks
+    /// find them. `frame` is the frame in flight, as for [PickingPass::end].
+    pub fn draw_picking(
+        &self,
+        picking: &mut PickingPass,
+        command_buffer: CommandBuffer,
+        frame: usize,
+        objects: &[TintedObject],
+    ) {
+        picking.begin(command_buffer);
+
+        for object in objects {
+            picking.draw(
+                command_buffer,
+                object.id,
+                object.model_view_projection,
+                object.mesh,
+            );
+        }
+
+        picking.end(command_buffer, frame);
+    }
+
+    fn draw_object(
+        &self,
+        command_buffer: CommandBuffer,
+        object: &TintedObject,
+        push_constants: TintPushConstants,
+    ) {
+        let device = self.logical_device.device();
+        let mesh = object.mesh;
+
+        unsafe {
+            device.cmd_push_constants(
+                command_buffer,
+                self.pipeline_layout,
+                ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT,
+                0,
+                push_constants.bytes_of(),
+            );
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[mesh.vertex_buffer], &[0]);
+            device.cmd_bind_index_buffer(command_buffer, mesh.index_buffer, 0, mesh.index_type);
+            device.cmd_draw_indexed(command_buffer, mesh.index_count, 1, 0, 0, 0);
+        }
+    }
+}
+
+impl Drop for TintPass {
+    fn drop(&mut self) {
+        teardown_trace::record(
+            "TintPass",
+            [self.fill_pipeline.as_raw(), self.outline_pipeline.as_raw()],
+        );
+
+        let device = self.logical_device.device();
+        let tracker = self.logical_device.resource_tracker();
+
+        for pipeline in [self.fill_pipeline, self.outline_pipeline] {
+            if pipeline != vk::Pipeline::null() {
+                unsafe { device.destroy_pipeline(pipeline, host_allocation_callbacks()) };
+                tracker.untrack(ResourceKind::Pipeline, 1, 0);
+            }
+        }
+
+        unsafe {
+            device.destroy_pipeline_layout(self.pipeline_layout, host_allocation_callbacks())
+        };
+    }
+}
+
+/// Creates the fill pipeline and the outline pipeline, which only keeps the back faces of the
+/// pushed out surface.
+fn create_pipelines(
+    logical_device: &LogicalDevice,
+    shader_cache: &ShaderCache,
+    render_pass: vk::RenderPass,
+    pipeline_layout: vk::PipelineLayout,
+    [vertex_shader, fragment_shader]: [&[u32]; 2],
+) -> Result<[vk::Pipeline; 2], ErrorCtx> {
+    let shader_modules = [
+        shader_cache
+            .get_or_create(vertex_shader)
+            .context("creating tint vertex shader module")?,
+        shader_cache
+            .get_or_create(fragment_shader)
+            .context("creating tint fragment shader module")?,
+    ];
+
+    let stages = [
+        PipelineShaderStageCreateInfo::default()
+            .stage(ShaderStageFlags::VERTEX)
+            .module(*shader_modules[0].shader_module())
+            .name(c"main"),
+        PipelineShaderStageCreateInfo::default()
+            .stage(ShaderStageFlags::FRAGMENT)
+            .module(*shader_modules[1].shader_module())
+            .name(c"main"),
+    ];
+
+    let bindings = [PbrVertex::binding_description()];
+    let attributes = [
+        VertexInputAttributeDescription::default()
+            .binding(0)
+            .location(0)
+            .format(Format::R32G32B32_SFLOAT)
+            .offset(mem::offset_of!(PbrVertex, position) as u32),
+        VertexInputAttributeDescription::default()
+            .binding(0)
+            .location(1)
+            .format(Format::R32G32B32_SFLOAT)
+            .offset(mem::offset_of!(PbrVertex, normal) as u32),
+    ];
+    let vertex_input_info = PipelineVertexInputStateCreateInfo::default()
+        .vertex_binding_descriptions(&bindings)
+        .vertex_attribute_descriptions(&attributes);
+
+    let input_assembly_info =
+        PipelineInputAssemblyStateCreateInfo::default().topology(PrimitiveTopology::TRIANGLE_LIST);
+
+    let dynamic_states = [DynamicState::VIEWPORT, DynamicState::SCISSOR];
+    let dynamic_state_info =
+        PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+    let viewport_info = PipelineViewportStateCreateInfo::default()
+        .viewport_count(1)
+        .scissor_count(1);
+
+    let fill_rasterizer_info = PipelineRasterizationStateCreateInfo::default()
+        .polygon_mode(PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(CullModeFlags::BACK)
+        .front_face(FrontFace::COUNTER_CLOCKWISE);
+    let outline_rasterizer_info = fill_rasterizer_info.cull_mode(CullModeFlags::FRONT);
+
+    let multisample_info = PipelineMultisampleStateCreateInfo::default()
+        .rasterization_samples(SampleCountFlags::TYPE_1);
+
+    let depth_stencil_info = PipelineDepthStencilStateCreateInfo::default()
+        .depth_test_enable(true)
+        .depth_write_enable(true)
+        .depth_compare_op(CompareOp::LESS);
+
+    let color_blend_attachments = [
+        PipelineColorBlendAttachmentState::default().color_write_mask(ColorComponentFlags::RGBA)
+    ];
+    let color_blend_info =
+        PipelineColorBlendStateCreateInfo::default().attachments(&color_blend_attachments);
+
+    let create_info = GraphicsPipelineCreateInfo::default()
+        .stages(&stages)
+        .vertex_input_state(&vertex_input_info)
+        .input_assembly_state(&input_assembly_info)
+        .viewport_state(&viewport_info)
+        .multisample_state(&multisample_info)
+        .depth_stencil_state(&depth_stencil_info)
+        .color_blend_state(&color_blend_info)
+        .dynamic_state(&dynamic_state_info)
+        .layout(pipeline_layout)
+        .render_pass(render_pass);
+
+    let create_infos = [
+        create_info.rasterization_state(&fill_rasterizer_info),
+        create_info.rasterization_state(&outline_rasterizer_info),
+    ];
+
+    let pipelines = unsafe {
+        pipeline_stats::create_graphics_pipelines(
+            logical_device,
+            PipelineCache::null(),
+            &create_infos,
+        )
+    }
+    .context("creating tint pipelines")?;
+
+    logical_device
+        .resource_tracker()
+        .track(ResourceKind::Pipeline, 2, 0);
+
+    Ok([pipelines[0], pipelines[1]])
+}
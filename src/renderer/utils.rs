@@ -1,8 +1,8 @@
-use std::ffi::CString;
+use std::ffi::{CString, NulError};
 
 use ash::{prelude::VkResult, Entry};
 
-use crate::VALIDATION_LAYERS;
+use crate::renderer::VALIDATION_LAYERS;
 
 pub fn print_available_extensions(entry: &Entry) {
     let extensions = unsafe { entry.enumerate_instance_extension_properties(None) };
@@ -14,9 +14,8 @@ pub fn print_available_extensions(entry: &Entry) {
                 "  {}",
                 extension
                     .extension_name_as_c_str()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
+                    .map(|name| name.to_string_lossy())
+                    .unwrap_or_else(|_| "<invalid name>".into())
             );
         }
     }
@@ -29,7 +28,10 @@ pub fn check_validation_layer_support(entry: &Entry) -> VkResult<bool> {
         let mut layer_found = false;
 
         for layer in layers.iter() {
-            if layer.layer_name_as_c_str().unwrap().to_str().unwrap() == required_layer {
+            if layer
+                .layer_name_as_c_str()
+                .is_ok_and(|name| name.to_bytes() == required_layer.as_bytes())
+            {
                 layer_found = true;
                 break;
             }
@@ -43,10 +45,12 @@ pub fn check_validation_layer_support(entry: &Entry) -> VkResult<bool> {
     Ok(true)
 }
 
-pub fn to_vec_cstring<V: Into<Vec<u8>>, I: IntoIterator<Item = V>>(iter: I) -> Vec<CString> {
-    iter.into_iter().map(|s| CString::new(s).unwrap()).collect()
+pub fn to_vec_cstring<V: Into<Vec<u8>>, I: IntoIterator<Item = V>>(
+    iter: I,
+) -> Result<Vec<CString>, NulError> {
+    iter.into_iter().map(CString::new).collect()
 }
 
-pub fn to_vec_pointer(vector: &Vec<CString>) -> Vec<*const i8> {
+pub fn to_vec_pointer(vector: &[CString]) -> Vec<*const i8> {
     vector.iter().map(|s| s.as_ptr()).collect()
 }
@@ -0,0 +1,60 @@
+use std::mem;
+
+use ash::vk::{
+    Format, VertexInputAttributeDescription, VertexInputBindingDescription, VertexInputRate,
+};
+
+use crate::types::Pod;
+
+/// A vertex of `shaders/shader.vert`, a 2D position and a color.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Vertex {
+    pub position: [f32; 2],
+    pub color: [f32; 3],
+}
+
+unsafe impl Pod for Vertex {}
+
+impl Vertex {
+    /// The triangle the renderer draws.
+    pub const TRIANGLE: [Vertex; 3] = [
+        Vertex {
+            position: [0.0, -0.5],
+            color: [1.0, 0.0, 0.0],
+        },
+        Vertex {
+            position: [0.5, 0.5],
+            color: [0.0, 1.0, 0.0],
+        },
+        Vertex {
+            position: [-0.5, 0.5],
+            color: [0.0, 0.0, 1.0],
+        },
+    ];
+
+    /// The indices of [Vertex::TRIANGLE], in order.
+    pub const TRIANGLE_INDICES: [u16; 3] = [0, 1, 2];
+
+    pub fn binding_description() -> VertexInputBindingDescription {
+        VertexInputBindingDescription::default()
+            .binding(0)
+            .stride(mem::size_of::<Self>() as u32)
+            .input_rate(VertexInputRate::VERTEX)
+    }
+
+    pub fn attribute_descriptions() -> [VertexInputAttributeDescription; 2] {
+        [
+            VertexInputAttributeDescription::default()
+                .binding(0)
+                .location(0)
+                .format(Format::R32G32_SFLOAT)
+                .offset(mem::offset_of!(Self, position) as u32),
+            VertexInputAttributeDescription::default()
+                .binding(0)
+                .location(1)
+                .format(Format::R32G32B32_SFLOAT)
+                .offset(mem::offset_of!(Self, color) as u32),
+        ]
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    cell::RefCell,
+    error,
+    fmt::{self},
+    ptr::null,
+    rc::Rc,
+    sync::mpsc::Sender,
+};
+
+use ash::{
+    prelude::VkResult,
+    vk::{Instance, SurfaceKHR},
+};
+use glfw::{
+    fail_on_errors, ffi::GLFWwindow, ClientApiHint, Context, Cursor, Glfw, GlfwReceiver, InitError,
+    PWindow, WindowEvent, WindowHint, WindowMode,
+};
+
+use crate::api2::{host_allocation_callbacks, CursorIcon, CursorImage, RenderEvent};
+
+#[derive(Debug, Clone)]
+pub struct Window(Rc<RefCell<InnerWindow>>);
+
+impl Window {
+    pub fn new(
+        window_name: &str,
+        window_mode: WindowMode,
+        height: u32,
+        width: u32,
+    ) -> Result<Self, WindowError> {
+        let mut glfw = glfw::init(glfw::fail_on_errors!()).map_err(WindowError::from)?;
+
+        glfw.window_hint(WindowHint::ClientApi(ClientApiHint::NoApi));
+        glfw.window_hint(WindowHint::Resizable(false));
+
+        let (mut window, events) = glfw
+            .create_window(width, height, window_name, window_mode)
+            .ok_or(WindowError::CreateWindow)?;
+
+        window.set_all_polling(true);
+
+        Ok(Self(Rc::new(RefCell::new(InnerWindow {
+            glfw,
+            window,
+            events,
+        }))))
+    }
+
+    pub fn get_required_instance_extensions(&self) -> Option<Vec<String>> {
+        self.0.borrow().glfw.get_required_instance_extensions()
+    }
+
+    pub fn should_close(&self) -> bool {
+        self.0.borrow().window.should_close()
+    }
+
+    pub fn set_should_close(&self, value: bool) {
+        self.0.borrow_mut().window.set_should_close(value);
+    }
+
+    pub fn poll_events(&self) {
+        self.0.borrow_mut().glfw.poll_events();
+    }
+
+    pub fn wait_events(&self) {
+        self.0.borrow_mut().glfw.wait_events();
+    }
+
+    /// Like [Window::wait_events], returning after `seconds` at the latest.
+    pub fn wait_events_timeout(&self, seconds: f64) {
+        self.0.borrow_mut().glfw.wait_events_timeout(seconds);
+    }
+
+    /// Wakes up a thread blocked in [Window::wait_events].
+    pub fn post_empty_event(&self) {
+        self.0.borrow().glfw.post_empty_event();
+    }
+
+    pub fn flush_events(&self) -> Vec<WindowEvent> {
+        glfw::flush_messages(&self.0.borrow().events)
+            .map(|(_, event)| event)
+            .collect()
+    }
+
+    /// Shows a standard cursor shape over the window.
+    pub fn set_cursor(&self, icon: CursorIcon) {
+        self.0
+            .borrow_mut()
+            .window
+            .set_cursor(Some(Cursor::from(icon)));
+    }
+
+    /// Shows a custom cursor over the window.
+    pub fn set_cursor_image(&self, image: &CursorImage) {
+        self.0
+            .borrow_mut()
+            .window
+            .set_cursor(Some(Cursor::from(image)));
+    }
+
+    pub(crate) unsafe fn create_window_surface(&self, instance: Instance) -> VkResult<SurfaceKHR> {
+        let window = &self.0.borrow_mut().window;
+
+        let mut surface = SurfaceKHR::null();
+
+        window
+            .create_window_surface(
+                instance,
+                host_allocation_callbacks().map_or(null(), |callbacks| callbacks),
+                &mut surface,
+            )
+            .result()?;
+
+        Ok(surface)
+    }
+
+    pub fn get_framebuffer_size(&self) -> (i32, i32) {
+        self.0.borrow().window.get_framebuffer_size()
+    }
+
+    /// Makes surfaces for the window on other threads, see [SurfaceSource].
+    pub fn surface_source(&self) -> SurfaceSource {
+        SurfaceSource(self.0.borrow().window.window_ptr())
+    }
+
+    /// Sends resizes and minimizing to a render thread as they happen. Unlike the polled events,
+    /// they still arrive while Windows runs its modal loop for a window being dragged or resized.
+    pub fn forward_to_render_thread(&self, events: Sender<RenderEvent>) {
+        let window = &mut self.0.borrow_mut().window;
+        let resized = events.clone();
+
+        window.set_framebuffer_size_callback(move |_, width, height| {
+            let _ = resized.send(RenderEvent::Resized {
+                width: width.max(0) as u32,
+                height: height.max(0) as u32,
+            });
+        });
+        window.set_iconify_callback(move |_, iconified| {
+            let _ = events.send(RenderEvent::Minimized(iconified));
+        });
+    }
+}
+
+/// Creates surfaces for a [Window] from any thread, which GLFW allows, e.g. for a render thread
+/// owning the [Renderer](crate::renderer::Renderer). The window has to outlive the surfaces.
+#[derive(Debug, Copy, Clone)]
+pub struct SurfaceSource(*mut GLFWwindow);
+
+// Only glfwCreateWindowSurface, which may be called from any thread, is used with the pointer.
+unsafe impl Send for SurfaceSource {}
+
+impl SurfaceSource {
+    /// # Safety
+    ///
+    /// The window has to be alive, and outlive the surface.
+    pub unsafe fn create_surface(&self, instance: Instance) -> VkResult<SurfaceKHR> {
+        let mut surface = SurfaceKHR::null();
+
+        glfw::ffi::glfwCreateWindowSurface(
+            instance,
+            self.0,
+            host_allocation_callbacks().map_or(null(), |callbacks| callbacks),
+            &mut surface,
+        )
+        .result()?;
+
+        Ok(surface)
+    }
+}
+
+#[derive(Debug)]
+struct InnerWindow {
+    glfw: Glfw,
+    window: PWindow,
+    events: GlfwReceiver<(f64, WindowEvent)>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WindowError {
+    Init(InitError),
+    CreateWindow,
+}
+
+impl From<InitError> for WindowError {
+    fn from(value: InitError) -> Self {
+        Self::Init(value)
+    }
+}
+
+impl fmt::Display for WindowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WindowError::Init(e) => e.fmt(f),
+            WindowError::CreateWindow => write!(f, "failed to create GLFW window"),
+        }
+    }
+}
+
+impl error::Error for WindowError {}
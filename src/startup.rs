@@ -0,0 +1,166 @@
+//! Explaining why the renderer couldn't start, for machines without a usable Vulkan driver.
+//!
+//! Instead of panicking, startup returns a [StartupReport] saying what's missing and how to fix
+//! it. [StartupReport::show] prints it and keeps a window open with the summary in its title, so
+//! users launching from a file manager see something too. Nothing is drawn in the window: that
+//! would need a second, non-Vulkan renderer.
+
+use std::{error, fmt};
+
+use ash::{vk, LoadingError};
+
+use learnvulkan::renderer::{
+    instance::InstanceError, physical_device::PhysicalDeviceError, window::Window,
+};
+
+/// What kept the renderer from starting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartupFailure {
+    /// The Vulkan loader library isn't installed.
+    MissingLoader,
+    /// The loader found no driver able to create an instance.
+    MissingDriver,
+    /// The driver reports no GPUs.
+    NoDevices,
+    /// No GPU can present to the window or supports the required extensions.
+    NoSuitableDevice,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartupReport {
+    pub failure: StartupFailure,
+    /// The underlying error, may be empty.
+    pub details: String,
+}
+
+impl StartupReport {
+    pub fn new(failure: StartupFailure, details: impl Into<String>) -> Self {
+        Self {
+            failure,
+            details: details.into(),
+        }
+    }
+
+    /// The report for a failed instance creation, `None` when the failure isn't about a missing
+    /// driver.
+    pub fn from_instance_error(error: &InstanceError) -> Option<Self> {
+        match error {
+            InstanceError::Vulkan(e) if e.source == vk::Result::ERROR_INCOMPATIBLE_DRIVER => {
+                Some(Self::new(StartupFailure::MissingDriver, e.to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The report for a failed [Renderer](learnvulkan::renderer::Renderer), `None` when the
+    /// failure isn't about a missing loader, driver or device.
+    pub fn from_error(error: &(dyn error::Error + 'static)) -> Option<Self> {
+        if let Some(error) = error.downcast_ref::<LoadingError>() {
+            return Some(Self::new(StartupFailure::MissingLoader, error.to_string()));
+        }
+
+        if let Some(error) = error.downcast_ref::<InstanceError>() {
+            return Self::from_instance_error(error);
+        }
+
+        error
+            .downcast_ref::<PhysicalDeviceError>()
+            .map(|error| Self::new(device_failure(error), error.to_string()))
+    }
+
+    /// A one-line description, used as the fallback window's title.
+    pub fn summary(&self) -> &'static str {
+        match self.failure {
+            StartupFailure::MissingLoader => "Vulkan is not installed",
+            StartupFailure::MissingDriver => "No Vulkan driver found",
+            StartupFailure::NoDevices => "No GPU with Vulkan support found",
+            StartupFailure::NoSuitableDevice => "No GPU can render to this window",
+        }
+    }
+
+    /// The steps that usually fix the failure.
+    pub fn remediation(&self) -> &'static [&'static str] {
+        match self.failure {
+            StartupFailure::MissingLoader => &[
+                "install the Vulkan loader, e.g. the vulkan-loader or libvulkan1 package",
+                "on Windows and macOS, install the latest GPU driver or the Vulkan SDK",
+            ],
+            StartupFailure::MissingDriver => &[
+                "install or update the GPU driver, e.g. Mesa's Vulkan drivers on Linux",
+                "on macOS, install MoltenVK through the Vulkan SDK",
+                "check that VK_ICD_FILENAMES or VK_DRIVER_FILES doesn't point at a missing driver",
+            ],
+            StartupFailure::NoDevices => &[
+                "update the GPU driver, the GPU may need a newer one for Vulkan",
+                "in virtual machines and remote sessions, enable GPU acceleration or install a software driver such as lavapipe",
+            ],
+            StartupFailure::NoSuitableDevice => &[
+                "update the GPU driver, the GPU may lack swapchain support",
+                "when running under a compositor or remote session, try running locally",
+            ],
+        }
+    }
+
+    /// Prints the report and shows a window titled with the summary until it's closed.
+    ///
+    /// Only prints when no window can be opened either.
+    pub fn show(&self) {
+        eprintln!("{}", self);
+
+        let window = match Window::new(
+            &format!("Vulkan Tutorial - {}", self.summary()),
+            glfw::WindowMode::Windowed,
+            200,
+            600,
+        ) {
+            Ok(window) => window,
+            Err(e) => {
+                eprintln!("failed to open a window to show the error: {}", e);
+                return;
+            }
+        };
+
+        while !window.should_close() {
+            window.wait_events();
+        }
+    }
+}
+
+impl From<LoadingError> for StartupReport {
+    fn from(error: LoadingError) -> Self {
+        Self::new(StartupFailure::MissingLoader, error.to_string())
+    }
+}
+
+impl From<PhysicalDeviceError> for StartupReport {
+    fn from(error: PhysicalDeviceError) -> Self {
+        Self::new(device_failure(&error), error.to_string())
+    }
+}
+
+fn device_failure(error: &PhysicalDeviceError) -> StartupFailure {
+    match error {
+        PhysicalDeviceError::NoSuitableDevices => StartupFailure::NoSuitableDevice,
+        _ => StartupFailure::NoDevices,
+    }
+}
+
+impl fmt::Display for StartupReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.summary())?;
+
+        if !self.details.is_empty() {
+            write!(f, " ({})", self.details)?;
+        }
+
+        writeln!(f, ". To fix it:")?;
+
+        for step in self.remediation() {
+            writeln!(f, "  - {}", step)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl error::Error for StartupReport {}
@@ -0,0 +1,137 @@
+//! Rendering on a dedicated thread while the main thread pumps the window's messages, for
+//! `LEARNVULKAN_RENDER_THREAD`.
+//!
+//! Windows runs a modal loop inside the message pump while a window is dragged or resized, so a
+//! loop rendering between pumps stalls until the user lets go. Here the [Renderer] lives on an
+//! [api2::RenderThread]: the window forwards resizes and minimizing to it as they happen, and it
+//! reports each presented frame back.
+
+use std::error::Error;
+
+use ash::vk;
+use glfw::{Action, Key, WindowEvent, WindowMode};
+use learnvulkan::{
+    api2::{self, PresentFeedback, RenderContext, RenderEvent},
+    renderer::{
+        renderer_config::RendererConfig,
+        window::{SurfaceSource, Window},
+        Renderer,
+    },
+};
+
+/// How long the main thread waits for window events before checking on the render thread.
+const FEEDBACK_INTERVAL: f64 = 0.1;
+
+pub fn run(config: RendererConfig) -> Result<(), Box<dyn Error>> {
+    let window = Window::new("Vulkan", WindowMode::Windowed, 600, 800)?;
+
+    let extensions = window
+        .get_required_instance_extensions()
+        .ok_or("GLFW found no Vulkan loader for window surfaces")?;
+    let (width, height) = window.get_framebuffer_size();
+    let framebuffer_size = (width.max(0) as u32, height.max(0) as u32);
+    let surface_source = window.surface_source();
+
+    // Joined before the window is dropped, the surface is destroyed with the renderer.
+    let render_thread = api2::RenderThread::spawn(move |context| {
+        if let Err(e) = render(
+            &context,
+            config,
+            extensions,
+            framebuffer_size,
+            surface_source,
+        ) {
+            context.failed(e.to_string());
+        }
+    })?;
+
+    window.forward_to_render_thread(render_thread.events.clone());
+
+    let mut presented = 0;
+
+    while !window.should_close() {
+        window.wait_events_timeout(FEEDBACK_INTERVAL);
+
+        for event in window.flush_events() {
+            if let WindowEvent::Key(Key::Escape, _, Action::Press, _) = event {
+                window.set_should_close(true);
+            }
+        }
+
+        for feedback in render_thread.poll_feedback() {
+            match feedback {
+                PresentFeedback::Presented { frame } => presented = frame,
+                PresentFeedback::Failed(message) => {
+                    eprintln!("the render thread failed: {}", message);
+                    window.set_should_close(true);
+                }
+            }
+        }
+    }
+
+    render_thread
+        .join()
+        .map_err(|_| "the render thread panicked")?;
+
+    println!("presented {} frames from the render thread", presented);
+
+    Ok(())
+}
+
+/// The render thread, drawing until the window thread sends [RenderEvent::Close] or goes away.
+fn render(
+    context: &RenderContext,
+    config: RendererConfig,
+    extensions: Vec<String>,
+    framebuffer_size: (u32, u32),
+    surface_source: SurfaceSource,
+) -> Result<(), Box<dyn Error>> {
+    // The window outlives the render thread, see run.
+    let create_surface =
+        |instance: vk::Instance| unsafe { surface_source.create_surface(instance) };
+
+    let mut renderer = Renderer::builder().config(config).build_with_surface(
+        extensions,
+        framebuffer_size,
+        |instance| Ok(create_surface(instance.instance().handle())?),
+    )?;
+
+    let mut frame = 0;
+
+    loop {
+        let events: Vec<_> = if renderer.needs_wait() {
+            context.wait_event().into_iter().collect()
+        } else {
+            context.poll_events().collect()
+        };
+
+        if renderer.needs_wait() && events.is_empty() || events.contains(&RenderEvent::Close) {
+            break;
+        }
+
+        for event in &events {
+            renderer.handle_render_event(event);
+        }
+
+        match renderer.draw_frame() {
+            Ok(()) => {}
+            Err(e) if e.downcast_ref() == Some(&vk::Result::ERROR_SURFACE_LOST_KHR) => {
+                let instance = renderer.logical_device().physical_device().instance();
+                let surface = create_surface(instance.instance().handle())?;
+                renderer.recreate_surface(surface)?;
+                continue;
+            }
+            Err(e) => return Err(e),
+        }
+
+        if !renderer.needs_wait() {
+            frame += 1;
+
+            if !context.presented(frame) {
+                break;
+            }
+        }
+    }
+
+    renderer.wait_idle()
+}
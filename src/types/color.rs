@@ -0,0 +1,39 @@
+//! Linear colors.
+
+/// A color with linear RGB components and straight alpha.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Color {
+    /// Linear red.
+    pub r: f32,
+    /// Linear green.
+    pub g: f32,
+    /// Linear blue.
+    pub b: f32,
+    /// Alpha.
+    pub a: f32,
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Self::BLACK
+    }
+}
+
+impl Color {
+    /// Opaque black.
+    pub const BLACK: Self = Self::linear(0.0, 0.0, 0.0, 1.0);
+    /// Opaque white.
+    pub const WHITE: Self = Self::linear(1.0, 1.0, 1.0, 1.0);
+    /// Fully transparent black.
+    pub const TRANSPARENT: Self = Self::linear(0.0, 0.0, 0.0, 0.0);
+
+    /// Creates a color from linear components.
+    pub const fn linear(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// The linear components as an array.
+    pub fn to_linear_array(&self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
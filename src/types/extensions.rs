@@ -0,0 +1,118 @@
+//! Lists of Vulkan extension and layer names.
+
+use alloc::{
+    ffi::{CString, NulError},
+    string::String,
+    vec::Vec,
+};
+use core::{
+    borrow::{Borrow, BorrowMut},
+    ffi::{c_char, CStr},
+    ops::{Deref, DerefMut},
+};
+
+/// A collection of Vulkan extensions or layers.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Extensions {
+    /// Internal buffer of extensions or layers in an intermediary type.
+    pub extensions: Vec<CString>,
+}
+
+impl Extensions {
+    /// Create a new empty collection of extensions or layers.
+    pub fn new() -> Self {
+        Self {
+            extensions: Vec::new(),
+        }
+    }
+
+    /// Create a new collection of extensions or layers with a specific capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            extensions: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Create a new collection of extensions or layers of [c_char] pointers that're references to this collection's internal buffer.
+    pub fn as_vec_ptr(&self) -> Vec<*const c_char> {
+        self.extensions.iter().map(|s| s.as_ptr()).collect()
+    }
+
+    /// Create a new collection of extensions or layers of &[str] that're references to this collection's internal buffer.
+    pub fn as_vec_str(&self) -> Vec<&str> {
+        self.extensions.iter().flat_map(|s| s.to_str()).collect()
+    }
+}
+
+impl AsRef<Vec<CString>> for Extensions {
+    fn as_ref(&self) -> &Vec<CString> {
+        &self.extensions
+    }
+}
+
+impl AsMut<Vec<CString>> for Extensions {
+    fn as_mut(&mut self) -> &mut Vec<CString> {
+        &mut self.extensions
+    }
+}
+
+impl Deref for Extensions {
+    type Target = Vec<CString>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.extensions
+    }
+}
+
+impl DerefMut for Extensions {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.extensions
+    }
+}
+
+impl Borrow<Vec<CString>> for Extensions {
+    fn borrow(&self) -> &Vec<CString> {
+        &self.extensions
+    }
+}
+
+impl BorrowMut<Vec<CString>> for Extensions {
+    fn borrow_mut(&mut self) -> &mut Vec<CString> {
+        &mut self.extensions
+    }
+}
+
+impl From<Vec<CString>> for Extensions {
+    fn from(value: Vec<CString>) -> Self {
+        Self { extensions: value }
+    }
+}
+
+impl<const N: usize> From<[CString; N]> for Extensions {
+    fn from(value: [CString; N]) -> Self {
+        Self {
+            extensions: value.to_vec(),
+        }
+    }
+}
+
+impl<const N: usize> From<[&CStr; N]> for Extensions {
+    fn from(value: [&CStr; N]) -> Self {
+        Self {
+            extensions: value.into_iter().map(CString::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<Vec<String>> for Extensions {
+    type Error = NulError;
+
+    fn try_from(value: Vec<String>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            extensions: value
+                .into_iter()
+                .map(CString::new)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
@@ -0,0 +1,107 @@
+//! Two-dimensional sizes and positions in pixels and the integer math around them.
+
+use core::ops;
+
+/// A width and height in pixels.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Extent {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+}
+
+impl Extent {
+    /// Creates an extent.
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    /// Component-wise minimum.
+    pub fn min(self, other: Self) -> Self {
+        Self::new(self.width.min(other.width), self.height.min(other.height))
+    }
+
+    /// Component-wise maximum.
+    pub fn max(self, other: Self) -> Self {
+        Self::new(self.width.max(other.width), self.height.max(other.height))
+    }
+
+    /// Clamps both dimensions into `[min, max]`.
+    pub fn clamp(self, min: Self, max: Self) -> Self {
+        self.max(min).min(max)
+    }
+
+    /// Number of pixels covered by the extent.
+    pub fn area(self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    /// Width divided by height, 0 for zero-height extents.
+    pub fn aspect_ratio(self) -> f32 {
+        if self.height == 0 {
+            0.0
+        } else {
+            self.width as f32 / self.height as f32
+        }
+    }
+
+    /// Whether any of the dimensions is zero.
+    pub fn is_empty(self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+/// A position in pixels.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Offset {
+    /// Horizontal position.
+    pub x: i32,
+    /// Vertical position.
+    pub y: i32,
+}
+
+impl Offset {
+    /// Creates an offset.
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+}
+
+impl ops::Add for Offset {
+    type Output = Self;
+
+    /// Component-wise addition.
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl ops::Sub for Offset {
+    type Output = Self;
+
+    /// Component-wise subtraction.
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+/// Integer division rounding towards positive infinity.
+pub fn div_round_up(value: u32, divisor: u32) -> u32 {
+    value.div_ceil(divisor)
+}
+
+/// Rounds `value` up to the next multiple of `alignment`, which must be a power of two.
+pub fn align_up(value: u64, alignment: u64) -> u64 {
+    debug_assert!(alignment.is_power_of_two());
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+/// Rounds `value` to the nearest multiple of `multiple`.
+pub fn round_to_multiple(value: u32, multiple: u32) -> u32 {
+    if multiple == 0 {
+        return value;
+    }
+
+    ((value + multiple / 2) / multiple) * multiple
+}
@@ -0,0 +1,57 @@
+//! Types laid out the way shaders read them.
+
+use core::{mem, slice};
+
+/// Plain data that can be uploaded as its raw bytes, like push constants.
+///
+/// # Safety
+///
+/// The type must have no padding bytes, and every field must be plain data as well.
+pub unsafe trait Pod: Copy + 'static {
+    /// The raw bytes to upload.
+    fn bytes_of(&self) -> &[u8] {
+        // SAFETY: implementors guarantee there's no padding to read.
+        unsafe { slice::from_raw_parts((self as *const Self).cast::<u8>(), mem::size_of::<Self>()) }
+    }
+}
+
+/// The raw bytes of `values`, like [Pod::bytes_of] for a whole slice.
+pub fn slice_bytes<T: Pod>(values: &[T]) -> &[u8] {
+    // SAFETY: `T: Pod` has no padding, and slices have no gaps between their elements.
+    unsafe { slice::from_raw_parts(values.as_ptr().cast::<u8>(), mem::size_of_val(values)) }
+}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for f32 {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+unsafe impl Pod for GpuTransform {}
+
+/// A model and normal matrix laid out for std140/std430 uniform and storage buffers and push
+/// constants: column-major, with the 3x3 normal matrix padded to three `vec4` columns.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct GpuTransform {
+    /// The model matrix columns.
+    pub model: [[f32; 4]; 4],
+    /// The normal matrix columns, each padded to a `vec4`.
+    pub normal: [[f32; 4]; 3],
+}
+
+impl GpuTransform {
+    /// Creates the GPU representation from column-major model and normal matrices.
+    pub fn from_columns(model: [[f32; 4]; 4], normal: [[f32; 3]; 3]) -> Self {
+        let mut gpu = Self {
+            model,
+            ..Default::default()
+        };
+
+        for (column, padded) in normal.iter().zip(gpu.normal.iter_mut()) {
+            padded[..3].copy_from_slice(column);
+        }
+
+        gpu
+    }
+}
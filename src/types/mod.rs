@@ -0,0 +1,16 @@
+//! Plain data definitions shared by the renderer: colors, extents, GPU buffer layouts and
+//! extension name lists.
+//!
+//! Nothing in here may use `std`, `ash` or `glfw`, only `core` and `alloc`, so the module can be
+//! lifted into a `no_std` crate as is. Operations that need the Vulkan types or `std` float math
+//! are added on top in [crate::api2].
+
+pub use color::*;
+pub use extensions::*;
+pub use extent::*;
+pub use gpu::*;
+
+mod color;
+mod extensions;
+mod extent;
+mod gpu;